@@ -25,8 +25,11 @@ struct Args {
     #[arg(short, long, help = "RDF file to query", value_name = "FILE")]
     file: String,
 
-    #[arg(short, long, help = "SPARQL query string", value_name = "QUERY")]
+    #[arg(short, long, help = "SPARQL query string, optionally prefixed with EXPLAIN or EXPLAIN ANALYZE", value_name = "QUERY")]
     query: String,
+
+    #[arg(long, help = "Print EXPLAIN output as JSON instead of a plan tree")]
+    json: bool,
 }
 
 fn main() {
@@ -35,6 +38,18 @@ fn main() {
     let mut database = SparqlDatabase::new();
     database.parse_rdf_from_file(&args.file);
 
+    if strip_explain_prefix(&args.query).is_some() {
+        match database.explain(&args.query) {
+            Ok(explanation) if args.json => println!("{}", explanation.to_json()),
+            Ok(explanation) => print!("{}", explanation),
+            Err(err) => {
+                eprintln!("Failed to explain the query: {}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     // Execute query
     let results = execute_query(&args.query, &mut database);
     println!("Results: {:?}", results);
@@ -9,191 +9,691 @@
  */
 
 use crate::reasoning::*;
+use shared::dictionary::Dictionary;
+use shared::rule::{check_program_safety, check_rule_safety, pattern_variables, FilterCondition, Rule, RuleBinding, RuleExpression};
 use shared::terms::{Term, TriplePattern, UnresolvedTerm, UnresolvedTriple};
-use shared::rule::Rule;
+use shared::triple::Triple;
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_until},
-    character::complete::{alphanumeric1, multispace0, multispace1},
-    sequence::{delimited, preceded, separated_pair, terminated},
-    multi::{separated_list1, many0},
+    bytes::complete::{tag, tag_no_case, take_till, take_while, take_while1},
+    character::complete::{char, digit1, multispace1, one_of, satisfy},
+    combinator::{map, not, opt, peek, recognize, value},
+    error::{Error, ErrorKind},
+    sequence::{delimited, preceded, terminated},
+    multi::{many0, separated_list1},
     IResult, Parser,
 };
-use nom::combinator::map;
-use std::collections::HashMap;
-
-// Parsing prefix statements
-fn parse_prefix(input: &str) -> IResult<&str, (&str, &str)> {
-    let (input, _) = tag("@prefix").parse(input)?;
-    let (input, _) = multispace1.parse(input)?;
-    let (input, prefix) = terminated(alphanumeric1, tag(":")).parse(input)?;
-    let (input, _) = multispace1.parse(input)?;
-    let (input, uri) = delimited(tag("<"), take_until(">"), tag(">")).parse(input)?;
-    let (input, _) = tag(".").parse(input)?;
-    Ok((input, (prefix, uri)))
-}
-
-/// Parse something like "test:SubClass" or "?variable"
-fn parse_unresolved_term(input: &str) -> IResult<&str, UnresolvedTerm> {
-    alt((
-        map(preceded(tag("?"), alphanumeric1), |var: &str| {
-            UnresolvedTerm::Var(var.to_string())
+use std::collections::{HashMap, HashSet};
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const OWL_SAME_AS: &str = "http://www.w3.org/2002/07/owl#sameAs";
+const LOG: &str = "http://www.w3.org/2000/10/swap/log#";
+const MATH: &str = "http://www.w3.org/2000/10/swap/math#";
+const STRING: &str = "http://www.w3.org/2000/10/swap/string#";
+
+// Whitespace and `#` comments
+fn sp(input: &str) -> IResult<&str, ()> {
+    value((), many0(alt((multispace1, preceded(char('#'), take_till(|c| c == '\n')))))).parse(input)
+}
+
+fn fail<T>(input: &str) -> IResult<&str, T> {
+    Err(nom::Err::Error(Error::new(input, ErrorKind::Verify)))
+}
+
+fn name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+// `<http://...>`, without the brackets
+fn iri_ref(input: &str) -> IResult<&str, &str> {
+    delimited(char('<'), take_while(|c: char| c != '>' && !c.is_whitespace()), char('>')).parse(input)
+}
+
+// The `ex` of `ex:name`, which may be empty
+fn prefix_label(input: &str) -> IResult<&str, &str> {
+    terminated(take_while(|c: char| name_char(c) || c == '.'), char(':')).parse(input)
+}
+
+// Local part of a prefixed name; a trailing dot ends the statement instead
+fn local_name(input: &str) -> IResult<&str, &str> {
+    let end = input
+        .char_indices()
+        .find(|&(_, c)| !(name_char(c) || c == '.' || c == ':' || c == '%'))
+        .map_or(input.len(), |(i, _)| i);
+    let local = input[..end].trim_end_matches('.');
+    Ok((&input[local.len()..], local))
+}
+
+fn prefixed_name(input: &str) -> IResult<&str, UnresolvedTerm> {
+    let (input, prefix) = prefix_label(input)?;
+    let (input, local) = local_name(input)?;
+    Ok((input, UnresolvedTerm::Prefixed(format!("{}:{}", prefix, local))))
+}
+
+fn variable(input: &str) -> IResult<&str, UnresolvedTerm> {
+    map(preceded(char('?'), take_while1(name_char)), |var: &str| UnresolvedTerm::Var(var.to_string())).parse(input)
+}
+
+fn blank_node(input: &str) -> IResult<&str, UnresolvedTerm> {
+    map(preceded(tag("_:"), take_while1(name_char)), |label: &str| UnresolvedTerm::BlankNode(label.to_string()))
+        .parse(input)
+}
+
+/// String between `"`, `'`, `"""` or `'''`, with its escapes resolved
+fn quoted_string(input: &str) -> IResult<&str, String> {
+    let quote = match input.chars().next() {
+        Some(quote @ ('"' | '\'')) => quote,
+        _ => return fail(input),
+    };
+    let long = quote.to_string().repeat(3);
+    let delimiter = if input.starts_with(&long) { long.as_str() } else { &input[..1] };
+    let body = &input[delimiter.len()..];
+
+    let mut lexical = String::new();
+    let mut chars = body.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if body[i..].starts_with(delimiter) {
+            return Ok((&body[i + delimiter.len()..], lexical));
+        }
+        match c {
+            '\\' => {
+                let escaped = match chars.next() {
+                    Some((_, 't')) => '\t',
+                    Some((_, 'n')) => '\n',
+                    Some((_, 'r')) => '\r',
+                    Some((_, 'b')) => '\u{8}',
+                    Some((_, 'f')) => '\u{c}',
+                    Some((_, c @ ('u' | 'U'))) => {
+                        let digits = if c == 'u' { 4 } else { 8 };
+                        let hex: String = chars.by_ref().take(digits).map(|(_, c)| c).collect();
+                        match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                            Some(c) => c,
+                            None => return fail(&body[i..]),
+                        }
+                    }
+                    Some((_, c)) => c,
+                    None => return fail(&body[i..]),
+                };
+                lexical.push(escaped);
+            }
+            '\n' if delimiter.len() == 1 => return fail(&body[i..]),
+            c => lexical.push(c),
+        }
+    }
+    fail(input)
+}
+
+// Literals are stored as their lexical form, with the language tag if any;
+// the datatype is dropped
+fn literal(input: &str) -> IResult<&str, UnresolvedTerm> {
+    let (input, lexical) = quoted_string(input)?;
+    let (input, language) = opt(preceded(char('@'), take_while1(name_char))).parse(input)?;
+    let (input, _) = opt(preceded(tag("^^"), alt((recognize(iri_ref), recognize(prefixed_name))))).parse(input)?;
+    Ok((
+        input,
+        UnresolvedTerm::Literal(match language {
+            Some(language) => format!("{}@{}", lexical, language),
+            None => lexical,
         }),
-        map(
-            separated_pair(alphanumeric1, tag(":"), alphanumeric1),
-            |(prefix, term)| UnresolvedTerm::Prefixed(format!("{}:{}", prefix, term)),
-        ),
-    )).parse(input)
-}
-
-fn parse_unresolved_triple(input: &str) -> IResult<&str, UnresolvedTriple> {
-    let (input, _) = multispace0.parse(input)?;
-    let (input, subject) = parse_unresolved_term(input)?;
-    let (input, _) = multispace1.parse(input)?;
-    let (input, predicate) = parse_unresolved_term(input)?;
-    let (input, _) = multispace1.parse(input)?;
-    let (input, object) = parse_unresolved_term(input)?;
-    let (input, _) = multispace0.parse(input)?;
-    let (input, _) = tag(".").parse(input)?;
-    Ok((input, (subject, predicate, object)))
-}
-
-/// Parsing a single triple block
-fn parse_nested_unresolved_rule(input: &str) -> IResult<&str, UnresolvedTriple> {
-    let (input, _) = multispace0.parse(input)?;
-    let (input, _) = tag("{").parse(input)?;
-
-    let (input, _) = take_until("}").parse(input)?; 
-    let (input, _) = tag("}").parse(input)?;
-    
-    let (input, _) = multispace0.parse(input)?;
-    let (input, _) = tag("=>").parse(input)?;
-    let (input, _) = multispace0.parse(input)?;
-    
-    let (input, _) = tag("{").parse(input)?;
-    let (input, triple) = parse_unresolved_triple(input)?;
-    let (input, _) = multispace0.parse(input)?;
-    let (input, _) = tag("}").parse(input)?;
-    
-    Ok((input, triple))
-}
-
-/// Parse a block that can contain either triple(s) or a nested rule
-fn parse_unresolved_clause_block(input: &str) -> IResult<&str, Vec<UnresolvedTriple>> {
-    separated_list1(
-        multispace0, 
-        alt((
-            parse_nested_unresolved_rule, // if it's a nested rule
-            parse_unresolved_triple,      // if it's just a triple
-        ))
-    ).parse(input)
-}
-
-// Updated to parse multiple conclusion triples
-fn parse_unresolved_rule(input: &str) -> IResult<&str, (Vec<UnresolvedTriple>, Vec<UnresolvedTriple>)> {
-    let (input, _) = multispace0.parse(input)?;
-    
-    // Parse premise
-    let (input, premise_triples) = delimited(
-        tag("{"),
-        parse_unresolved_clause_block,
-        preceded(multispace0, tag("}")),
-    ).parse(input)?;
-    
-    let (input, _) = multispace0.parse(input)?;
-    let (input, _) = tag("=>").parse(input)?;
-    let (input, _) = multispace0.parse(input)?;
-    
-    // Parse multiple conclusions
-    let (input, conclusion_triples) = delimited(
-        tag("{"),
-        parse_unresolved_clause_block,  // Using the same parser for multiple triples
-        preceded(multispace0, tag("}")),
-    ).parse(input)?;
-    
-    Ok((input, (premise_triples, conclusion_triples)))
-}
-
-/// Parsing into unresolved terms
-pub fn parse_n3_rule<'a>(
-    input: &'a str,
-    graph: &mut Reasoner,
-) -> IResult<&'a str, (Vec<(&'a str, &'a str)>, Rule)> {
-    let (input, prefixes) = many0(preceded(multispace0, parse_prefix)).parse(input)?;
+    ))
+}
 
-    // Build a HashMap for prefix expansion
-    let prefix_map: HashMap<String, String> = prefixes
-        .iter()
-        .map(|(prefix, uri)| (prefix.to_string(), uri.to_string()))
-        .collect();
+fn number(input: &str) -> IResult<&str, UnresolvedTerm> {
+    map(
+        recognize((
+            opt(one_of("+-")),
+            alt((recognize((digit1, opt((char('.'), digit1)))), recognize((char('.'), digit1)))),
+            opt((one_of("eE"), opt(one_of("+-")), digit1)),
+        )),
+        |number: &str| UnresolvedTerm::Literal(number.to_string()),
+    )
+    .parse(input)
+}
+
+fn boolean(input: &str) -> IResult<&str, UnresolvedTerm> {
+    map(
+        terminated(alt((tag("true"), tag("false"))), not(satisfy(name_char))),
+        |b: &str| UnresolvedTerm::Literal(b.to_string()),
+    )
+    .parse(input)
+}
+
+fn property_list(input: &str) -> IResult<&str, UnresolvedTerm> {
+    let (rest, pairs) = delimited((char('['), sp), opt(predicate_object_list), (sp, char(']'))).parse(input)?;
+    let pairs = pairs.unwrap_or_default();
+    if pairs.iter().any(|(_, _, reversed)| *reversed) {
+        // `<=` needs a subject to point back to
+        return Err(nom::Err::Failure(Error::new(input, ErrorKind::Verify)));
+    }
+    let pairs = pairs.into_iter().map(|(p, o, _)| (p, o)).collect();
+    Ok((rest, UnresolvedTerm::PropertyList(pairs)))
+}
+
+fn list(input: &str) -> IResult<&str, UnresolvedTerm> {
+    map(
+        delimited((char('('), sp), many0(terminated(term, sp)), char(')')),
+        UnresolvedTerm::List,
+    )
+    .parse(input)
+}
+
+fn formula(input: &str) -> IResult<&str, UnresolvedTerm> {
+    map(delimited((char('{'), sp), formula_body, (sp, char('}'))), UnresolvedTerm::Formula).parse(input)
+}
+
+/// Statements of a formula; the final `.` is optional
+fn formula_body(input: &str) -> IResult<&str, Vec<UnresolvedTriple>> {
+    map(many0(terminated(statement, (sp, opt(char('.')), sp))), |statements| {
+        statements.into_iter().flatten().collect()
+    })
+    .parse(input)
+}
+
+fn term(input: &str) -> IResult<&str, UnresolvedTerm> {
+    alt((
+        variable,
+        map(iri_ref, |iri| UnresolvedTerm::Iri(iri.to_string())),
+        blank_node,
+        prefixed_name,
+        literal,
+        number,
+        boolean,
+        property_list,
+        list,
+        formula,
+    ))
+    .parse(input)
+}
 
-    // Parse to an intermediate unresolved form with multiple conclusions
-    let (input, (premise_triples, conclusion_triples)) = parse_unresolved_rule(input)?;
-    
-    // Convert from unresolved string-based data to final `Term` with dictionary encoding
-    let premise_parsed: Vec<TriplePattern> = premise_triples
+/// Predicate of a statement, and whether subject and object swap (`<=`)
+fn verb(input: &str) -> IResult<&str, (UnresolvedTerm, bool)> {
+    let iri = |iri: &str| UnresolvedTerm::Iri(iri.to_string());
+    alt((
+        value((iri(&format!("{}implies", LOG)), false), tag("=>")),
+        value((iri(&format!("{}implies", LOG)), true), tag("<=")),
+        value((iri(OWL_SAME_AS), false), char('=')),
+        value((iri(RDF_TYPE), false), terminated(char('a'), peek(multispace1))),
+        map(term, |predicate| (predicate, false)),
+    ))
+    .parse(input)
+}
+
+fn object_list(input: &str) -> IResult<&str, Vec<UnresolvedTerm>> {
+    separated_list1((sp, char(','), sp), term).parse(input)
+}
+
+/// `verb objects ; verb objects ...` as (predicate, object, reversed)
+fn predicate_object_list(input: &str) -> IResult<&str, Vec<(UnresolvedTerm, UnresolvedTerm, bool)>> {
+    let (input, groups) = separated_list1((sp, char(';'), sp), (verb, sp, object_list)).parse(input)?;
+    let (input, _) = opt((sp, char(';'))).parse(input)?;
+    let pairs = groups
         .into_iter()
-        .map(|(s, p, o)| {
-            (
-                to_term(s, graph, &prefix_map),
-                to_term(p, graph, &prefix_map),
-                to_term(o, graph, &prefix_map),
-            )
+        .flat_map(|((predicate, reversed), _, objects)| {
+            objects.into_iter().map(move |object| (predicate.clone(), object, reversed))
         })
         .collect();
+    Ok((input, pairs))
+}
 
-    // Convert all conclusions
-    let conclusions_parsed: Vec<TriplePattern> = conclusion_triples
+/// A subject with its predicate-object list, as triples
+fn statement(input: &str) -> IResult<&str, Vec<UnresolvedTriple>> {
+    let (input, subject) = term(input)?;
+    let (input, _) = sp(input)?;
+    let (input, pairs) = predicate_object_list(input)?;
+    let triples = pairs
         .into_iter()
-        .map(|(s, p, o)| {
-            (
-                to_term(s, graph, &prefix_map),
-                to_term(p, graph, &prefix_map),
-                to_term(o, graph, &prefix_map),
-            )
+        .map(|(predicate, object, reversed)| match reversed {
+            true => (object, predicate, subject.clone()),
+            false => (subject.clone(), predicate, object),
         })
         .collect();
+    Ok((input, triples))
+}
 
-    let rule = Rule {
-        premise: premise_parsed,
-        negative_premise: vec![],
-        filters: vec![],
-        conclusion: conclusions_parsed,
-    };
+#[derive(Debug, Clone, Copy)]
+enum Directive<'a> {
+    Prefix(&'a str, &'a str),
+    Base(&'a str),
+}
+
+// `@prefix ex: <...> .`, `PREFIX ex: <...>`, `@base <...> .` or `BASE <...>`
+fn directive(input: &str) -> IResult<&str, Directive<'_>> {
+    alt((
+        map((tag("@prefix"), sp, prefix_label, sp, iri_ref, sp, char('.')), |(_, _, prefix, _, iri, _, _)| {
+            Directive::Prefix(prefix, iri)
+        }),
+        map((tag_no_case("PREFIX"), sp, prefix_label, sp, iri_ref), |(_, _, prefix, _, iri)| {
+            Directive::Prefix(prefix, iri)
+        }),
+        map((tag("@base"), sp, iri_ref, sp, char('.')), |(_, _, iri, _, _)| Directive::Base(iri)),
+        map((tag_no_case("BASE"), sp, iri_ref), |(_, _, iri)| Directive::Base(iri)),
+    ))
+    .parse(input)
+}
+
+/// Where a formula occurs, which decides what its blank nodes stand for
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scope {
+    /// Asserted triples: blank nodes are constants
+    Fact,
+    /// Rule body: blank nodes are variables
+    Body,
+    /// Rule conclusion: blank nodes are variables the body does not bind
+    Head,
+}
 
-    Ok((input, (prefixes, rule)))
+/// Prefixes and base IRI in effect while converting statements
+#[derive(Default)]
+struct N3Context {
+    prefixes: HashMap<String, String>,
+    base: Option<String>,
+    anonymous: usize,
 }
 
-/// Helper to convert UnresolvedTerm to Term - NOW WITH PREFIX EXPANSION
-fn to_term(ut: UnresolvedTerm, graph: &mut Reasoner, prefix_map: &HashMap<String, String>) -> Term {
-    match ut {
-        UnresolvedTerm::Var(v) => Term::Variable(v),
-        UnresolvedTerm::Prefixed(s) => {
-            // Expand the prefix!
-            let expanded = expand_prefix(&s, prefix_map);
-            let mut dict = graph.dictionary.write().unwrap();
-            let id = dict.encode(&expanded);
-            drop(dict);
-            Term::Constant(id)
+impl N3Context {
+    fn apply(&mut self, directive: Directive) {
+        match directive {
+            Directive::Prefix(prefix, iri) => {
+                let iri = self.resolve(iri);
+                self.prefixes.insert(prefix.to_string(), iri);
+            }
+            Directive::Base(iri) => self.base = Some(self.resolve(iri)),
         }
     }
+
+    /// IRI relative to the base IRI made absolute
+    fn resolve(&self, iri: &str) -> String {
+        match &self.base {
+            Some(base) if !iri.contains(':') => format!("{}{}", base, iri),
+            _ => iri.to_string(),
+        }
+    }
+
+    /// Full IRI of a named term, `None` for other terms
+    fn iri(&self, term: &UnresolvedTerm) -> Result<Option<String>, String> {
+        match term {
+            UnresolvedTerm::Iri(iri) => Ok(Some(self.resolve(iri))),
+            UnresolvedTerm::Prefixed(name) => {
+                let (prefix, local) = name.split_once(':').unwrap_or(("", name));
+                match self.prefixes.get(prefix) {
+                    Some(base) => Ok(Some(format!("{}{}", base, local))),
+                    None => Err(format!("unknown prefix '{}:'", prefix)),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn convert_term(
+        &mut self,
+        term: &UnresolvedTerm,
+        scope: Scope,
+        extra: &mut Vec<TriplePattern>,
+        dict: &mut Dictionary,
+    ) -> Result<Term, String> {
+        if let Some(iri) = self.iri(term)? {
+            return Ok(Term::Constant(dict.encode(&iri)));
+        }
+        match term {
+            UnresolvedTerm::Var(var) if scope == Scope::Fact => Err(format!("variable ?{} outside a rule", var)),
+            UnresolvedTerm::Var(var) => Ok(Term::Variable(var.clone())),
+            UnresolvedTerm::Literal(literal) => Ok(Term::Constant(dict.encode(literal))),
+            UnresolvedTerm::BlankNode(label) => Ok(blank_node_term(label, scope, dict)),
+            UnresolvedTerm::PropertyList(pairs) => {
+                let label = format!("n3anon{}", self.anonymous);
+                self.anonymous += 1;
+                let node = blank_node_term(&label, scope, dict);
+                for (predicate, object) in pairs {
+                    let predicate = self.convert_term(predicate, scope, extra, dict)?;
+                    let object = self.convert_term(object, scope, extra, dict)?;
+                    extra.push((node.clone(), predicate, object));
+                }
+                Ok(node)
+            }
+            UnresolvedTerm::List(_) => Err("lists are only supported as arguments of built-ins".to_string()),
+            UnresolvedTerm::Formula(triples) => {
+                let mut patterns = self.convert_triples(triples, scope, dict)?;
+                match (scope, patterns.len()) {
+                    (Scope::Body | Scope::Head, 1) => Ok(Term::QuotedTriple(Box::new(patterns.remove(0)))),
+                    _ => Err("formulas as terms must be a single triple inside a rule".to_string()),
+                }
+            }
+            UnresolvedTerm::Iri(_) | UnresolvedTerm::Prefixed(_) => unreachable!("named terms are handled above"),
+        }
+    }
+
+    fn convert_triples(
+        &mut self,
+        triples: &[UnresolvedTriple],
+        scope: Scope,
+        dict: &mut Dictionary,
+    ) -> Result<Vec<TriplePattern>, String> {
+        let mut patterns = Vec::new();
+        let mut extra = Vec::new();
+        for (s, p, o) in triples {
+            if self.iri(p)?.as_deref() == Some(&format!("{}implies", LOG)) {
+                return Err("nested rules are not supported".to_string());
+            }
+            let s = self.convert_term(s, scope, &mut extra, dict)?;
+            let p = self.convert_term(p, scope, &mut extra, dict)?;
+            let o = self.convert_term(o, scope, &mut extra, dict)?;
+            patterns.push((s, p, o));
+        }
+        patterns.extend(extra);
+        Ok(patterns)
+    }
+
+    fn convert_rule(
+        &mut self,
+        body: &[UnresolvedTriple],
+        head: &[UnresolvedTriple],
+        dict: &mut Dictionary,
+    ) -> Result<Rule, String> {
+        let mut premise = Vec::new();
+        let mut negative_premise = Vec::new();
+        let mut builtins = Vec::new();
+        for triple in body {
+            match self.iri(&triple.1)? {
+                Some(iri) if iri == format!("{}notIncludes", LOG) => match &triple.2 {
+                    UnresolvedTerm::Formula(triples) => {
+                        negative_premise.extend(self.convert_triples(triples, Scope::Body, dict)?)
+                    }
+                    _ => return Err("log:notIncludes needs a formula as object".to_string()),
+                },
+                Some(iri) if [LOG, MATH, STRING].iter().any(|ns| iri.starts_with(ns)) => builtins.push((iri, triple)),
+                _ => premise.extend(self.convert_triples(std::slice::from_ref(triple), Scope::Body, dict)?),
+            }
+        }
+
+        let mut bound: HashSet<String> = premise.iter().flat_map(pattern_variables).map(str::to_string).collect();
+        let mut bindings = Vec::new();
+        let mut filters = Vec::new();
+        for (iri, (subject, _, object)) in builtins {
+            self.convert_builtin(&iri, subject, object, &mut bound, &mut bindings, &mut filters)?;
+        }
+
+        let rule = Rule {
+            premise,
+            negative_premise,
+            bindings,
+            aggregates: vec![],
+            filters,
+            conclusion: self.convert_triples(head, Scope::Head, dict)?,
+        };
+        check_rule_safety(&rule)?;
+        Ok(rule)
+    }
+
+    /// Adds a `math:`, `string:` or `log:` built-in of a rule body as a
+    /// computed variable or a filter
+    fn convert_builtin(
+        &mut self,
+        iri: &str,
+        subject: &UnresolvedTerm,
+        object: &UnresolvedTerm,
+        bound: &mut HashSet<String>,
+        bindings: &mut Vec<RuleBinding>,
+        filters: &mut Vec<FilterCondition>,
+    ) -> Result<(), String> {
+        let comparison = match iri {
+            _ if iri == format!("{}greaterThan", MATH) => Some(">"),
+            _ if iri == format!("{}lessThan", MATH) => Some("<"),
+            _ if iri == format!("{}notGreaterThan", MATH) => Some("<="),
+            _ if iri == format!("{}notLessThan", MATH) => Some(">="),
+            _ if iri == format!("{}equalTo", MATH) || iri == format!("{}equalTo", LOG) => Some("="),
+            _ if iri == format!("{}notEqualTo", MATH) || iri == format!("{}notEqualTo", LOG) => Some("!="),
+            _ => None,
+        };
+        if let Some(operator) = comparison {
+            let filter = match (subject, object) {
+                (UnresolvedTerm::Var(var), other) => FilterCondition {
+                    variable: var.clone(),
+                    operator: operator.to_string(),
+                    value: self.filter_value(other)?,
+                },
+                (other, UnresolvedTerm::Var(var)) => FilterCondition {
+                    variable: var.clone(),
+                    operator: flip_comparison(operator).to_string(),
+                    value: self.filter_value(other)?,
+                },
+                _ => return Err(format!("built-in <{}> compares no variable", iri)),
+            };
+            for var in [&filter.variable, &filter.value] {
+                if matches!((subject, object), (UnresolvedTerm::Var(v), _) | (_, UnresolvedTerm::Var(v)) if v == var)
+                    && !bound.contains(var)
+                {
+                    return Err(format!("built-in <{}> uses unbound variable ?{}", iri, var));
+                }
+            }
+            filters.push(filter);
+            return Ok(());
+        }
+
+        let operands = |arity: Option<usize>| -> Result<Vec<RuleExpression>, String> {
+            let items: Vec<&UnresolvedTerm> = match (subject, arity) {
+                (UnresolvedTerm::List(items), _) => items.iter().collect(),
+                (single, Some(1)) => vec![single],
+                _ => return Err(format!("built-in <{}> needs a list as subject", iri)),
+            };
+            if arity.is_some_and(|arity| arity != items.len()) || items.is_empty() {
+                return Err(format!("built-in <{}> has the wrong number of arguments", iri));
+            }
+            items.into_iter().map(|item| self.operand(item)).collect()
+        };
+        let fold = |args: Vec<RuleExpression>, op: fn(Box<RuleExpression>, Box<RuleExpression>) -> RuleExpression| {
+            args.into_iter().reduce(|l, r| op(Box::new(l), Box::new(r))).expect("at least one argument")
+        };
+        let function = |name: &str, args: Vec<RuleExpression>| RuleExpression::Function(name.to_string(), args);
+        let local = iri.rsplit_once('#').map_or(iri, |(_, local)| local);
+        let expression = match (iri.starts_with(MATH), iri.starts_with(STRING), local) {
+            (true, _, "sum") => fold(operands(None)?, RuleExpression::Add),
+            (true, _, "difference") => fold(operands(Some(2))?, RuleExpression::Subtract),
+            (true, _, "product") => fold(operands(None)?, RuleExpression::Multiply),
+            (true, _, "quotient") => fold(operands(Some(2))?, RuleExpression::Divide),
+            (true, _, "negation") => RuleExpression::Subtract(
+                Box::new(RuleExpression::Literal("0".to_string())),
+                Box::new(operands(Some(1))?.remove(0)),
+            ),
+            (true, _, "absoluteValue") => function("ABS", operands(Some(1))?),
+            (true, _, "rounded") => function("ROUND", operands(Some(1))?),
+            (true, _, "floor") => function("FLOOR", operands(Some(1))?),
+            (true, _, "ceiling") => function("CEIL", operands(Some(1))?),
+            (_, true, "concatenation") => function("CONCAT", operands(None)?),
+            (_, true, "upperCase") => function("UCASE", operands(Some(1))?),
+            (_, true, "lowerCase") => function("LCASE", operands(Some(1))?),
+            (_, true, "length") => function("STRLEN", operands(Some(1))?),
+            _ => return Err(format!("unsupported built-in <{}>", iri)),
+        };
+
+        // The result binds a new variable, or is checked against a value
+        match object {
+            UnresolvedTerm::Var(var) if !bound.contains(var) => {
+                bound.insert(var.clone());
+                bindings.push(RuleBinding { variable: var.clone(), expression });
+            }
+            other => {
+                let variable = format!("_:builtin{}", bindings.len());
+                bound.insert(variable.clone());
+                filters.push(FilterCondition {
+                    variable: variable.clone(),
+                    operator: "=".to_string(),
+                    value: self.filter_value(other)?,
+                });
+                bindings.push(RuleBinding { variable, expression });
+            }
+        }
+        Ok(())
+    }
+
+    fn operand(&self, term: &UnresolvedTerm) -> Result<RuleExpression, String> {
+        if let Some(iri) = self.iri(term)? {
+            return Ok(RuleExpression::Literal(iri));
+        }
+        match term {
+            UnresolvedTerm::Var(var) => Ok(RuleExpression::Variable(var.clone())),
+            UnresolvedTerm::Literal(literal) => Ok(RuleExpression::Literal(literal.clone())),
+            UnresolvedTerm::BlankNode(label) => Ok(RuleExpression::Variable(format!("_:{}", label))),
+            _ => Err("built-in arguments must be variables, names or literals".to_string()),
+        }
+    }
+
+    /// Variable name or constant a filter compares with
+    fn filter_value(&self, term: &UnresolvedTerm) -> Result<String, String> {
+        match self.operand(term)? {
+            RuleExpression::Variable(var) | RuleExpression::Literal(var) => Ok(var),
+            _ => unreachable!("operands are variables or literals"),
+        }
+    }
+
+    /// Converts a top-level statement into the rules and facts it asserts
+    fn convert_statement(
+        &mut self,
+        triples: Vec<UnresolvedTriple>,
+        document: &mut N3Document,
+        dict: &mut Dictionary,
+    ) -> Result<(), String> {
+        for triple in triples {
+            if self.iri(&triple.1)?.as_deref() == Some(&format!("{}implies", LOG)) {
+                let rule = match &triple {
+                    (UnresolvedTerm::Formula(body), _, UnresolvedTerm::Formula(head)) => self.convert_rule(body, head, dict)?,
+                    _ => return Err("log:implies needs formulas on both sides".to_string()),
+                };
+                document.rules.push(rule);
+                continue;
+            }
+            for pattern in self.convert_triples(std::slice::from_ref(&triple), Scope::Fact, dict)? {
+                match pattern {
+                    (Term::Constant(subject), Term::Constant(predicate), Term::Constant(object)) => {
+                        document.facts.push(Triple { subject, predicate, object })
+                    }
+                    _ => unreachable!("facts only contain constants"),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn blank_node_term(label: &str, scope: Scope, dict: &mut Dictionary) -> Term {
+    match scope {
+        Scope::Fact => Term::Constant(dict.encode(&format!("_:{}", label))),
+        Scope::Body => Term::Variable(format!("_:{}", label)),
+        // Conclusion blank nodes are distinct from those of the body
+        Scope::Head => Term::Variable(format!("_:{}@head", label)),
+    }
+}
+
+fn flip_comparison(operator: &str) -> &str {
+    match operator {
+        ">" => "<",
+        "<" => ">",
+        ">=" => "<=",
+        "<=" => ">=",
+        other => other,
+    }
+}
+
+fn syntax_error(rest: &str) -> String {
+    let near: String = rest.lines().next().unwrap_or("").chars().take(40).collect();
+    format!("N3 syntax error near '{}'", near)
+}
+
+/// Rules and facts of an N3 document, with the prefixes it declares
+#[derive(Debug, Clone, Default)]
+pub struct N3Document {
+    pub prefixes: HashMap<String, String>,
+    pub facts: Vec<Triple>,
+    pub rules: Vec<Rule>,
+}
+
+/// Parses an N3 document: `@prefix`/`PREFIX` and `@base`/`BASE` directives,
+/// facts, and rules written `{ body } => { head }`, `{ head } <= { body }` or
+/// with `log:implies`.
+///
+/// Terms are full or relative IRIs, prefixed names, variables, literals
+/// (strings, numbers and booleans), blank nodes, and `a` and `=` for
+/// `rdf:type` and `owl:sameAs`; `;` and `,` abbreviate statements. Blank
+/// nodes are constants in facts and variables in rules; those only in the
+/// conclusion are existential, see `Reasoner::chase`. In a rule body,
+/// `log:notIncludes { ... }` adds negated premises, and the common `math:`
+/// and `string:` built-ins become computed variables or filters. A formula
+/// with one triple can be used as a term, as a quoted triple.
+///
+/// Returns `Err` for unsafe rules and, unless `graph.chase.max_depth` bounds
+/// the chase, for rules that are not weakly acyclic.
+pub fn parse_n3_document(input: &str, graph: &mut Reasoner) -> Result<N3Document, String> {
+    let mut context = N3Context::default();
+    let mut document = N3Document::default();
+    let mut dict = graph.dictionary.write().unwrap();
+    let mut rest = input;
+    loop {
+        rest = sp(rest).map_err(|_| syntax_error(rest))?.0;
+        if rest.is_empty() {
+            break;
+        }
+        if let Ok((next, directive)) = directive(rest) {
+            context.apply(directive);
+            rest = next;
+            continue;
+        }
+        let (next, triples) = terminated(statement, (sp, alt((value((), char('.')), value((), nom::combinator::eof)))))
+            .parse(rest)
+            .map_err(|_| syntax_error(rest))?;
+        context.convert_statement(triples, &mut document, &mut dict)?;
+        rest = next;
+    }
+    drop(dict);
+    check_program_safety(&document.rules, graph.chase.max_depth.is_some())?;
+    document.prefixes = context.prefixes;
+    Ok(document)
+}
+
+/// Parses one N3 rule, after the prefix directives it uses. Returns the
+/// declared prefixes and the rule; the rest of the input may hold more rules.
+pub fn parse_n3_rule<'a>(
+    input: &'a str,
+    graph: &mut Reasoner,
+) -> IResult<&'a str, (Vec<(&'a str, &'a str)>, Rule)> {
+    let (input, directives) = many0(preceded(sp, directive)).parse(input)?;
+    let mut context = N3Context::default();
+    let mut prefixes = Vec::new();
+    for directive in directives {
+        if let Directive::Prefix(prefix, iri) = directive {
+            prefixes.push((prefix, iri));
+        }
+        context.apply(directive);
+    }
+
+    let (rest, triples) = terminated(preceded(sp, statement), opt((sp, char('.')))).parse(input)?;
+    let mut dict = graph.dictionary.write().unwrap();
+    let rule = match triples.as_slice() {
+        [(UnresolvedTerm::Formula(body), predicate, UnresolvedTerm::Formula(head))]
+            if context.iri(predicate).ok().flatten().as_deref() == Some(&format!("{}implies", LOG)) =>
+        {
+            context.convert_rule(body, head, &mut dict)
+        }
+        _ => return Err(nom::Err::Error(Error::new(input, ErrorKind::Verify))),
+    };
+    match rule {
+        Ok(rule) => Ok((rest, (prefixes, rule))),
+        Err(_) => Err(nom::Err::Failure(Error::new(input, ErrorKind::Verify))),
+    }
 }
 
-/// Expand a prefixed name like "ex:hasSensor" to full URI like "http://example.org/hasSensor"
-fn expand_prefix(prefixed: &str, prefix_map: &HashMap<String, String>) -> String {
-    if let Some(colon_pos) = prefixed.find(':') {
-        let prefix = &prefixed[..colon_pos];
-        let local = &prefixed[colon_pos + 1..];
-        
-        if let Some(base_uri) = prefix_map.get(prefix) {
-            // Expand: prefix + local part
-            format!("{}{}", base_uri, local)
-        } else {
-            // No prefix found, return as-is
-            prefixed.to_string()
+impl Reasoner {
+    /// Adds the facts and rules of an N3 document, see `parse_n3_document`.
+    /// Nothing is added when the document is invalid.
+    pub fn load_n3(&mut self, input: &str) -> Result<N3Document, String> {
+        let document = parse_n3_document(input, self)?;
+        let mut program = self.rules.clone();
+        program.extend(document.rules.iter().cloned());
+        crate::reasoning::stratification::rule_strata(&program)?;
+        for fact in &document.facts {
+            self.index_manager.insert(fact);
         }
-    } else {
-        // No colon, return as-is
-        prefixed.to_string()
+        for rule in &document.rules {
+            self.try_add_rule(rule.clone())?;
+        }
+        Ok(document)
     }
-}
\ No newline at end of file
+}
@@ -13,6 +13,12 @@ pub mod backward_chaining;
 pub mod rules;
 pub mod repairs;
 pub mod helpers;
+pub mod stratification;
+pub mod entailment;
+pub mod magic_sets;
+pub mod aggregation;
+pub mod chase;
+pub mod explanation;
 
 use shared::dictionary::Dictionary;
 use shared::triple::Triple;
@@ -23,8 +29,10 @@ use shared::rule::Rule;
 use shared::provenance::Provenance;
 use shared::tag_store::TagStore;
 use std::sync::Arc;
-use std::sync::RwLock;
+use std::sync::{OnceLock, RwLock};
 use crate::reasoning::rules::join_rule;
+use crate::reasoning::chase::ChaseConfig;
+use crate::reasoning::explanation::Derivation;
 
 // Logic part: Knowledge Graph
 
@@ -38,6 +46,17 @@ pub struct Reasoner {
     pub rule_index: RuleIndex,
     pub constraints: Vec<Rule>,
     pub probability_seeds: HashMap<Triple, f64>, // Input probabilities for provenance seeding
+    /// Facts added to `index_manager` by inference; all others were asserted
+    pub derived_facts: HashSet<Triple>,
+    /// How rules with existential variables are evaluated
+    pub chase: ChaseConfig,
+    /// Whether semi-naive materialisation records `derivations`, for `explain`
+    pub record_derivations: bool,
+    /// Rule applications that derived each fact, by the last semi-naive run
+    pub derivations: HashMap<Triple, Vec<Derivation>>,
+    /// Proof depths of the derived facts, computed from `derivations` by the
+    /// first `explain` after a materialisation
+    pub(crate) proof_depths: OnceLock<HashMap<Triple, usize>>,
 }
 
 pub fn convert_string_binding_to_u32(
@@ -62,6 +81,11 @@ impl Reasoner {
             rule_index: RuleIndex::new(),
             constraints: Vec::new(),
             probability_seeds: HashMap::new(),
+            derived_facts: HashSet::new(),
+            chase: ChaseConfig::default(),
+            record_derivations: false,
+            derivations: HashMap::new(),
+            proof_depths: OnceLock::new(),
         }
     }
 
@@ -76,6 +100,7 @@ impl Reasoner {
 
         let triple = Triple { subject: s, predicate: p, object: o };
         self.index_manager.insert(&triple);
+        self.derived_facts.remove(&triple);
         self.probability_seeds.insert(triple, probability);
     }
 
@@ -100,16 +125,19 @@ impl Reasoner {
         let o = dict.encode(object);
         drop(dict);  // Release lock early
 
-        self.index_manager.insert(&Triple {
+        let triple = Triple {
             subject: s,
             predicate: p,
             object: o,
-        });
+        };
+        self.index_manager.insert(&triple);
+        self.derived_facts.remove(&triple);
     }
 
     /// Insert an already-ground triple directly into the fact index.
     pub fn insert_ground_triple(&mut self, triple: Triple) {
         self.index_manager.insert(&triple);
+        self.derived_facts.remove(&triple);
     }
 
     /// Query the ABox for instance-level assertions (using TrieIndex now)
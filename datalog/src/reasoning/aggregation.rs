@@ -0,0 +1,133 @@
+use shared::dictionary::Dictionary;
+use shared::rule::{pattern_variables, Rule};
+use shared::triple::Triple;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use crate::reasoning::convert_string_binding_to_u32;
+use crate::reasoning::materialisation::replace_variables_with_bound_values;
+use crate::reasoning::rules::{compute_bindings, evaluate_filters, join_premise_with_hash_join, negated_premises_absent};
+
+/// Body solutions of a rule with aggregates over `facts`, without duplicates:
+/// the negated premises are absent from `known_facts`, and the variables
+/// computed before aggregation are bound and pass the filters using them.
+pub fn aggregate_body_solutions(
+    rule: &Rule,
+    facts: &[Triple],
+    known_facts: &HashSet<Triple>,
+    dict: &mut Dictionary,
+) -> Vec<HashMap<String, u32>> {
+    let mut bindings = vec![BTreeMap::new()];
+    for premise in &rule.premise {
+        bindings = join_premise_with_hash_join(premise, facts, bindings, dict);
+        if bindings.is_empty() {
+            break;
+        }
+    }
+    let solutions: BTreeSet<BTreeMap<String, u32>> = bindings
+        .iter()
+        .map(|binding| convert_string_binding_to_u32(binding, dict).into_iter().collect())
+        .collect();
+
+    solutions
+        .into_iter()
+        .filter_map(|solution| {
+            let mut solution: HashMap<String, u32> = solution.into_iter().collect();
+            let kept = negated_premises_absent(&solution, &rule.negative_premise, known_facts)
+                && complete_body_solution(rule, &mut solution, dict);
+            kept.then_some(solution)
+        })
+        .collect()
+}
+
+/// Binds the variables `rule` computes before aggregation in a body
+/// solution; returns whether it passes the filters. Filters on aggregate
+/// results are skipped while they are unbound.
+pub(crate) fn complete_body_solution(rule: &Rule, solution: &mut HashMap<String, u32>, dict: &mut Dictionary) -> bool {
+    let post_aggregation = rule.post_aggregation_variables();
+    let pre_aggregation = rule
+        .bindings
+        .iter()
+        .filter(|binding| !post_aggregation.contains(binding.variable.as_str()));
+    compute_bindings(solution, pre_aggregation, dict) && evaluate_filters(solution, &rule.filters, dict)
+}
+
+/// Groups the body solutions of `rule` by the conclusion variables they bind,
+/// and binds the aggregates of each group and the variables computed from
+/// them. Groups failing a filter are dropped.
+pub fn aggregate_groups(
+    rule: &Rule,
+    solutions: &[HashMap<String, u32>],
+    dict: &mut Dictionary,
+) -> Vec<HashMap<String, u32>> {
+    let post_aggregation = rule.post_aggregation_variables();
+    let group_variables: BTreeSet<&str> = rule
+        .conclusion
+        .iter()
+        .flat_map(pattern_variables)
+        .filter(|var| !post_aggregation.contains(var))
+        .collect();
+
+    // Values of the group variables, in order, to the solutions of the group
+    let mut groups: BTreeMap<Vec<Option<u32>>, Vec<&HashMap<String, u32>>> = BTreeMap::new();
+    for solution in solutions {
+        let key = group_variables.iter().map(|&var| solution.get(var).copied()).collect();
+        groups.entry(key).or_default().push(solution);
+    }
+
+    let post_bindings: Vec<_> = rule
+        .bindings
+        .iter()
+        .filter(|binding| post_aggregation.contains(binding.variable.as_str()))
+        .collect();
+    let mut results = Vec::new();
+    'groups: for (key, members) in groups {
+        let mut result: HashMap<String, u32> = group_variables
+            .iter()
+            .zip(key)
+            .filter_map(|(var, id)| Some((var.to_string(), id?)))
+            .collect();
+        for aggregate in &rule.aggregates {
+            let values: Vec<String> = members
+                .iter()
+                .filter_map(|solution| solution.get(&aggregate.argument))
+                .filter_map(|&id| dict.decode(id).map(str::to_string))
+                .collect();
+            match aggregate.compute(values.iter().map(String::as_str)) {
+                Some(value) => {
+                    result.insert(aggregate.result.clone(), dict.encode(&value));
+                }
+                None => continue 'groups,
+            }
+        }
+        if compute_bindings(&mut result, post_bindings.iter().copied(), dict)
+            && evaluate_filters(&result, &rule.filters, dict)
+        {
+            results.push(result);
+        }
+    }
+    results
+}
+
+/// Conclusions of the rules with aggregates among `rules` that are not among
+/// `known_facts` yet. The facts their premises match must be complete, so
+/// they are applied once per stratum, before its other rules.
+pub(crate) fn aggregate_conclusions(
+    rules: &[Rule],
+    facts: &[Triple],
+    known_facts: &HashSet<Triple>,
+    dict: &mut Dictionary,
+) -> Vec<Triple> {
+    let mut new_facts = Vec::new();
+    let mut seen = HashSet::new();
+    for rule in rules.iter().filter(|rule| !rule.aggregates.is_empty()) {
+        let solutions = aggregate_body_solutions(rule, facts, known_facts, dict);
+        for group in aggregate_groups(rule, &solutions, dict) {
+            for conclusion in &rule.conclusion {
+                let fact = replace_variables_with_bound_values(conclusion, &group, dict);
+                if !known_facts.contains(&fact) && seen.insert(fact.clone()) {
+                    new_facts.push(fact);
+                }
+            }
+        }
+    }
+    new_facts
+}
@@ -1,8 +1,11 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use shared::dictionary::Dictionary;
 use shared::rule::Rule;
 use shared::terms::{Term, TriplePattern};
 use shared::triple::Triple;
 use crate::reasoning::Reasoner;
+use crate::reasoning::aggregation::{aggregate_groups, complete_body_solution};
+use crate::reasoning::rules::{complete_solution, matches_rule_pattern};
 
 fn unify_patterns(
     pattern1: &TriplePattern,
@@ -140,6 +143,8 @@ fn rename_rule_variables(rule: &Rule, counter: &mut usize) -> Rule {
     Rule {
         premise: new_premise,
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: new_conclusions,
         filters: rule.filters.clone(),
     }
@@ -203,4 +208,225 @@ impl Reasoner {
 
         results
     }
-}
\ No newline at end of file
+}
+
+/// Answer tables of the subgoals of a tabled query, keyed by the variant of
+/// the subgoal (its pattern with variables renamed by position)
+#[derive(Default)]
+struct Tables {
+    answers: BTreeMap<TriplePattern, BTreeSet<Triple>>,
+    /// Subgoals whose table holds all their answers
+    complete: BTreeSet<TriplePattern>,
+    /// Subgoals evaluated in the current iteration of the leader
+    evaluated: BTreeSet<TriplePattern>,
+    /// Whether an answer was added in the current iteration
+    changed: bool,
+    /// Whether a leader is iterating its subgoals to a fixpoint
+    leader_active: bool,
+}
+
+/// Pattern with its variables renamed in order of appearance, so calls that
+/// only differ in variable names share a table
+fn variant_key(goal: &TriplePattern) -> TriplePattern {
+    let mut names: HashMap<String, String> = HashMap::new();
+    let mut rename = |term: &Term| match term {
+        Term::Variable(v) => {
+            let next = names.len();
+            Term::Variable(names.entry(v.clone()).or_insert_with(|| format!("_{}", next)).clone())
+        }
+        other => other.clone(),
+    };
+    let s = rename(&goal.0);
+    let p = rename(&goal.1);
+    let o = rename(&goal.2);
+    (s, p, o)
+}
+
+/// Pattern with the variables bound in `binding` replaced by their values
+fn ground_pattern(pattern: &TriplePattern, binding: &HashMap<String, u32>) -> TriplePattern {
+    let ground = |term: &Term| match term {
+        Term::Variable(v) => binding.get(v).map_or_else(|| term.clone(), |&id| Term::Constant(id)),
+        other => other.clone(),
+    };
+    (ground(&pattern.0), ground(&pattern.1), ground(&pattern.2))
+}
+
+impl Reasoner {
+    /// Returns the bindings of the variables of `query` for every derivable
+    /// fact matching it, using tabled (SLG-style) backward chaining.
+    ///
+    /// Every subgoal is memoised in a table shared by all calls that are
+    /// variants of it. A call to a subgoal that is still being evaluated
+    /// consumes the answers found so far instead of recursing, and the first
+    /// call re-evaluates its subgoals until no table grows. This terminates on
+    /// recursive rules such as transitive closure, without a depth limit.
+    /// Every tabled subgoal is evaluated again on each iteration of that
+    /// fixpoint loop, but once per iteration however often it is called,
+    /// rather than once per call. Negated premises and the bodies of rules
+    /// with aggregates are solved as separate, completed queries, which is
+    /// sound for stratified rules.
+    pub fn backward_chaining_tabled(&self, query: &TriplePattern) -> Vec<HashMap<String, Term>> {
+        let mut dict = self.dictionary.write().unwrap();
+        let mut tables = Tables::default();
+        let answers = self.solve_tabled(query, &mut tables, &mut dict);
+        answers
+            .iter()
+            .filter_map(|fact| {
+                let mut binding = HashMap::new();
+                matches_rule_pattern(query, fact, &mut binding).then(|| {
+                    binding
+                        .into_iter()
+                        .map(|(var, id)| (var, Term::Constant(id)))
+                        .collect()
+                })
+            })
+            .collect()
+    }
+
+    /// Facts matching `goal`, from its table when it was already called
+    fn solve_tabled(&self, goal: &TriplePattern, tables: &mut Tables, dict: &mut Dictionary) -> BTreeSet<Triple> {
+        let key = variant_key(goal);
+        if tables.complete.contains(&key) || tables.evaluated.contains(&key) {
+            return tables.answers.get(&key).cloned().unwrap_or_default();
+        }
+        tables.answers.entry(key.clone()).or_default();
+
+        if tables.leader_active {
+            // Evaluated once per iteration; the leader repeats until fixpoint
+            tables.evaluated.insert(key.clone());
+            self.evaluate_subgoal(goal, &key, tables, dict);
+            return tables.answers[&key].clone();
+        }
+
+        tables.leader_active = true;
+        loop {
+            tables.changed = false;
+            tables.evaluated.clear();
+            tables.evaluated.insert(key.clone());
+            self.evaluate_subgoal(goal, &key, tables, dict);
+            if !tables.changed {
+                break;
+            }
+        }
+        tables.leader_active = false;
+        let evaluated = std::mem::take(&mut tables.evaluated);
+        tables.complete.extend(evaluated);
+        tables.answers[&key].clone()
+    }
+
+    /// Adds to the table of `key` the facts and rule conclusions matching `goal`
+    fn evaluate_subgoal(&self, goal: &TriplePattern, key: &TriplePattern, tables: &mut Tables, dict: &mut Dictionary) {
+        let constant = |term: &Term| match term {
+            Term::Constant(c) => Some(*c),
+            _ => None,
+        };
+        let mut found: Vec<Triple> = self
+            .index_manager
+            .query(constant(&goal.0), constant(&goal.1), constant(&goal.2))
+            .into_iter()
+            .filter(|fact| matches_rule_pattern(goal, fact, &mut HashMap::new()))
+            .collect();
+
+        for rule in &self.rules {
+            for head in &rule.conclusion {
+                let Some(head_binding) = unify_head(head, goal) else {
+                    continue;
+                };
+                let solutions = if rule.aggregates.is_empty() {
+                    let mut solutions = self.solve_body(&rule.premise, head_binding, tables, dict);
+                    solutions.retain_mut(|binding| {
+                        complete_solution(binding, rule, dict)
+                            && self.negated_subgoals_fail(&rule.negative_premise, binding, dict)
+                    });
+                    solutions
+                } else {
+                    self.aggregate_subgoal(rule, head_binding, dict)
+                };
+                for binding in solutions {
+                    // Heads with a variable the body does not bind derive nothing
+                    if let (Term::Constant(s), Term::Constant(p), Term::Constant(o)) = ground_pattern(head, &binding) {
+                        let fact = Triple { subject: s, predicate: p, object: o };
+                        if matches_rule_pattern(goal, &fact, &mut HashMap::new()) {
+                            found.push(fact);
+                        }
+                    }
+                }
+            }
+        }
+
+        let table = tables.answers.get_mut(key).expect("table created by solve_tabled");
+        for fact in found {
+            if table.insert(fact) {
+                tables.changed = true;
+            }
+        }
+    }
+
+    /// Extensions of `binding` matching every premise, in order
+    fn solve_body(
+        &self,
+        premises: &[TriplePattern],
+        binding: HashMap<String, u32>,
+        tables: &mut Tables,
+        dict: &mut Dictionary,
+    ) -> Vec<HashMap<String, u32>> {
+        let mut bindings = vec![binding];
+        for premise in premises {
+            let mut extended = Vec::new();
+            for binding in &bindings {
+                let subgoal = ground_pattern(premise, binding);
+                for fact in self.solve_tabled(&subgoal, tables, dict) {
+                    let mut candidate = binding.clone();
+                    if matches_rule_pattern(premise, &fact, &mut candidate) {
+                        extended.push(candidate);
+                    }
+                }
+            }
+            bindings = extended;
+            if bindings.is_empty() {
+                break;
+            }
+        }
+        bindings
+    }
+
+    /// Groups of a rule with aggregates whose head unified with the goal as
+    /// `head_binding`. Only the group variables restrict the body, which is
+    /// solved with tables of its own so every group is complete.
+    fn aggregate_subgoal(&self, rule: &Rule, mut head_binding: HashMap<String, u32>, dict: &mut Dictionary) -> Vec<HashMap<String, u32>> {
+        let post_aggregation = rule.post_aggregation_variables();
+        head_binding.retain(|var, _| !post_aggregation.contains(var.as_str()));
+        let mut tables = Tables::default();
+        let mut solutions = self.solve_body(&rule.premise, head_binding, &mut tables, dict);
+        solutions.retain_mut(|binding| {
+            self.negated_subgoals_fail(&rule.negative_premise, binding, dict)
+                && complete_body_solution(rule, binding, dict)
+        });
+        aggregate_groups(rule, &solutions, dict)
+    }
+
+    /// Whether no negated premise has a derivable instance under `binding`.
+    /// Each negated subgoal is a separate query, so it is complete when
+    /// checked.
+    fn negated_subgoals_fail(&self, negative_premise: &[TriplePattern], binding: &HashMap<String, u32>, dict: &mut Dictionary) -> bool {
+        negative_premise.iter().all(|negated| {
+            let mut tables = Tables::default();
+            self.solve_tabled(&ground_pattern(negated, binding), &mut tables, dict).is_empty()
+        })
+    }
+}
+
+/// Binding of the variables of a rule head from the constants of `goal`, or
+/// `None` when they cannot match
+fn unify_head(head: &TriplePattern, goal: &TriplePattern) -> Option<HashMap<String, u32>> {
+    let mut binding = HashMap::new();
+    for (head_term, goal_term) in [(&head.0, &goal.0), (&head.1, &goal.1), (&head.2, &goal.2)] {
+        match (head_term, goal_term) {
+            (Term::Constant(h), Term::Constant(g)) if h != g => return None,
+            (Term::Variable(v), Term::Constant(g)) if *binding.entry(v.clone()).or_insert(*g) != *g => return None,
+            (Term::QuotedTriple(_), _) | (_, Term::QuotedTriple(_)) => return None,
+            _ => {}
+        }
+    }
+    Some(binding)
+}
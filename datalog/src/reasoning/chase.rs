@@ -0,0 +1,123 @@
+use shared::dictionary::Dictionary;
+use shared::index_manager::UnifiedIndex;
+use shared::rule::{pattern_variables, Rule};
+use shared::terms::{Term, TriplePattern};
+use shared::triple::Triple;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+
+pub use shared::rule::check_weak_acyclicity;
+use crate::reasoning::materialisation::replace_variables_with_bound_values;
+use crate::reasoning::rules::matches_rule_pattern;
+
+/// How the values of existential variables are invented
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChaseVariant {
+    /// A rule only fires when no values of its existential variables already
+    /// satisfy its conclusions, and then invents fresh blank nodes
+    #[default]
+    Restricted,
+    /// The invented blank node is a function of the rule, the variable and
+    /// the values of the other conclusion variables, so firing a rule twice
+    /// for the same values derives the same facts
+    Skolem,
+}
+
+/// Chase settings of a `Reasoner`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ChaseConfig {
+    pub variant: ChaseVariant,
+    /// Largest number of invented blank nodes a blank node may descend from,
+    /// including itself. Rules with existential variables then always
+    /// terminate, also when they are not weakly acyclic.
+    pub max_depth: Option<usize>,
+}
+
+/// Number of invented blank nodes a value descends from, including itself;
+/// 0 for all other values
+fn null_depth(id: u32, dict: &Dictionary) -> usize {
+    let label = dict.decode(id).unwrap_or("");
+    label
+        .strip_prefix("_:chase")
+        .or_else(|| label.strip_prefix("_:skolem"))
+        .and_then(|rest| rest.split('_').next())
+        .and_then(|depth| depth.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Whether some values of the unbound variables make every pattern one of
+/// `facts`. Candidates for a pattern are looked up by its constants and the
+/// values the solution already binds.
+fn satisfied(patterns: &[TriplePattern], solution: &HashMap<String, u32>, facts: &[&UnifiedIndex]) -> bool {
+    let Some((first, rest)) = patterns.split_first() else {
+        return true;
+    };
+    let bound = |term: &Term| match term {
+        Term::Constant(id) => Some(*id),
+        Term::Variable(var) => solution.get(var).copied(),
+        Term::QuotedTriple(_) => None,
+    };
+    let (s, p, o) = (bound(&first.0), bound(&first.1), bound(&first.2));
+    facts.iter().flat_map(|index| index.query(s, p, o)).any(|fact| {
+        let mut extended = solution.clone();
+        matches_rule_pattern(first, &fact, &mut extended) && satisfied(rest, &extended, facts)
+    })
+}
+
+/// Conclusions of `rule` for a solution of its body. Existential variables
+/// get invented blank nodes, see [`ChaseVariant`]; `facts` index the facts
+/// derived so far, which the restricted chase checks first. Nothing is
+/// derived when the blank nodes would be deeper than the depth bound.
+pub(crate) fn instantiate_conclusions(
+    rule: &Rule,
+    solution: &HashMap<String, u32>,
+    facts: &[&UnifiedIndex],
+    config: &ChaseConfig,
+    dict: &mut Dictionary,
+) -> Vec<Triple> {
+    let existential = rule.existential_variables();
+    let instantiate = |solution: &HashMap<String, u32>, dict: &mut Dictionary| {
+        rule.conclusion
+            .iter()
+            .map(|conclusion| replace_variables_with_bound_values(conclusion, solution, dict))
+            .collect()
+    };
+    if existential.is_empty() {
+        return instantiate(solution, dict);
+    }
+    if config.variant == ChaseVariant::Restricted && satisfied(&rule.conclusion, solution, facts) {
+        return Vec::new();
+    }
+
+    // Values of the conclusion variables the body binds
+    let frontier: BTreeMap<&str, u32> = rule.conclusion
+        .iter()
+        .flat_map(pattern_variables)
+        .filter_map(|var| solution.get(var).map(|&id| (var, id)))
+        .collect();
+    let depth = 1 + frontier.values().map(|&id| null_depth(id, dict)).max().unwrap_or(0);
+    if config.max_depth.is_some_and(|max| depth > max) {
+        return Vec::new();
+    }
+
+    let mut extended = solution.clone();
+    for var in existential {
+        let label = match config.variant {
+            ChaseVariant::Restricted => {
+                let mut n = dict.string_to_id.len();
+                while dict.string_to_id.contains_key(&format!("_:chase{}_{}", depth, n)) {
+                    n += 1;
+                }
+                format!("_:chase{}_{}", depth, n)
+            }
+            ChaseVariant::Skolem => {
+                let mut hasher = DefaultHasher::new();
+                (format!("{:?}", rule.conclusion), var, &frontier).hash(&mut hasher);
+                format!("_:skolem{}_{:x}", depth, hasher.finish())
+            }
+        };
+        extended.insert(var.to_string(), dict.encode(&label));
+    }
+    instantiate(&extended, dict)
+}
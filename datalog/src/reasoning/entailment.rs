@@ -0,0 +1,386 @@
+use shared::dictionary::Dictionary;
+use shared::index_manager::UnifiedIndex;
+use shared::rule::Rule;
+use shared::terms::{Term, TriplePattern};
+use shared::triple::Triple;
+use std::collections::{HashMap, HashSet};
+use crate::reasoning::Reasoner;
+
+const RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const RDFS: &str = "http://www.w3.org/2000/01/rdf-schema#";
+const OWL: &str = "http://www.w3.org/2002/07/owl#";
+
+pub const OWL_SAME_AS: &str = "http://www.w3.org/2002/07/owl#sameAs";
+
+/// Entailment regime whose standard rules are applied on top of the user rules
+///
+/// Axiomatic triples and the rules only deriving them (e.g. every resource is
+/// an `rdfs:Resource`) are left out, as are the OWL 2 RL rules over RDF lists
+/// (`owl:intersectionOf`, `owl:unionOf`, `owl:propertyChainAxiom`,
+/// `owl:hasKey`), cardinality restrictions and the consistency checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntailmentRegime {
+    /// RDFS: domain, range, sub-property and sub-class entailment
+    Rdfs,
+    /// RDFS plus inverse, symmetric, transitive, functional and
+    /// inverse-functional properties, equivalent classes and properties and
+    /// `owl:sameAs`
+    RdfsPlus,
+    /// RDFS-Plus plus the OWL 2 RL schema rules and `owl:hasValue`,
+    /// `owl:someValuesFrom` and `owl:allValuesFrom` restrictions
+    Owl2Rl,
+}
+
+impl EntailmentRegime {
+    /// The rules of the regime, encoded with `dict`. Equality (`eq-*` rules)
+    /// is not among them: it is handled by equality classes during
+    /// `Reasoner::infer_new_facts_with_entailment`.
+    pub fn rules(&self, dict: &mut Dictionary) -> Vec<Rule> {
+        let mut iri = |namespace: &str, name: &str| Term::Constant(dict.encode(&format!("{}{}", namespace, name)));
+        let ty = iri(RDF, "type");
+        let domain = iri(RDFS, "domain");
+        let range = iri(RDFS, "range");
+        let sub_property = iri(RDFS, "subPropertyOf");
+        let sub_class = iri(RDFS, "subClassOf");
+
+        // rdfs2, rdfs3, rdfs5, rdfs7, rdfs9 and rdfs11
+        let mut rules = vec![
+            rule(vec![(v("p"), domain.clone(), v("c")), (v("x"), v("p"), v("y"))], (v("x"), ty.clone(), v("c"))),
+            rule(vec![(v("p"), range.clone(), v("c")), (v("x"), v("p"), v("y"))], (v("y"), ty.clone(), v("c"))),
+            rule(
+                vec![(v("p1"), sub_property.clone(), v("p2")), (v("p2"), sub_property.clone(), v("p3"))],
+                (v("p1"), sub_property.clone(), v("p3")),
+            ),
+            rule(vec![(v("p1"), sub_property.clone(), v("p2")), (v("x"), v("p1"), v("y"))], (v("x"), v("p2"), v("y"))),
+            rule(vec![(v("c1"), sub_class.clone(), v("c2")), (v("x"), ty.clone(), v("c1"))], (v("x"), ty.clone(), v("c2"))),
+            rule(
+                vec![(v("c1"), sub_class.clone(), v("c2")), (v("c2"), sub_class.clone(), v("c3"))],
+                (v("c1"), sub_class.clone(), v("c3")),
+            ),
+        ];
+        if *self == EntailmentRegime::Rdfs {
+            return rules;
+        }
+
+        let same_as = iri(OWL, "sameAs");
+        let inverse_of = iri(OWL, "inverseOf");
+        let equivalent_class = iri(OWL, "equivalentClass");
+        let equivalent_property = iri(OWL, "equivalentProperty");
+        let symmetric = iri(OWL, "SymmetricProperty");
+        let transitive = iri(OWL, "TransitiveProperty");
+        let functional = iri(OWL, "FunctionalProperty");
+        let inverse_functional = iri(OWL, "InverseFunctionalProperty");
+        rules.extend([
+            // prp-inv1, prp-inv2
+            rule(vec![(v("p1"), inverse_of.clone(), v("p2")), (v("x"), v("p1"), v("y"))], (v("y"), v("p2"), v("x"))),
+            rule(vec![(v("p1"), inverse_of.clone(), v("p2")), (v("x"), v("p2"), v("y"))], (v("y"), v("p1"), v("x"))),
+            // prp-symp, prp-trp
+            rule(vec![(v("p"), ty.clone(), symmetric), (v("x"), v("p"), v("y"))], (v("y"), v("p"), v("x"))),
+            rule(
+                vec![(v("p"), ty.clone(), transitive), (v("x"), v("p"), v("y")), (v("y"), v("p"), v("z"))],
+                (v("x"), v("p"), v("z")),
+            ),
+            // prp-fp, prp-ifp
+            rule(
+                vec![(v("p"), ty.clone(), functional), (v("x"), v("p"), v("y1")), (v("x"), v("p"), v("y2"))],
+                (v("y1"), same_as.clone(), v("y2")),
+            ),
+            rule(
+                vec![(v("p"), ty.clone(), inverse_functional), (v("x1"), v("p"), v("y")), (v("x2"), v("p"), v("y"))],
+                (v("x1"), same_as, v("x2")),
+            ),
+            // scm-eqc1, scm-eqp1: equivalence is sub-classing or sub-properties both ways
+            rule(vec![(v("c1"), equivalent_class.clone(), v("c2"))], (v("c1"), sub_class.clone(), v("c2"))),
+            rule(vec![(v("c1"), equivalent_class.clone(), v("c2"))], (v("c2"), sub_class.clone(), v("c1"))),
+            rule(vec![(v("p1"), equivalent_property.clone(), v("p2"))], (v("p1"), sub_property.clone(), v("p2"))),
+            rule(vec![(v("p1"), equivalent_property.clone(), v("p2"))], (v("p2"), sub_property.clone(), v("p1"))),
+        ]);
+        if *self == EntailmentRegime::RdfsPlus {
+            return rules;
+        }
+
+        let thing = iri(OWL, "Thing");
+        let on_property = iri(OWL, "onProperty");
+        let has_value = iri(OWL, "hasValue");
+        let some_values_from = iri(OWL, "someValuesFrom");
+        let all_values_from = iri(OWL, "allValuesFrom");
+        rules.extend([
+            // scm-cls, scm-eqc2, scm-eqp2
+            rule(vec![(v("c"), ty.clone(), iri(OWL, "Class"))], (v("c"), sub_class.clone(), thing.clone())),
+            rule(
+                vec![(v("c1"), sub_class.clone(), v("c2")), (v("c2"), sub_class.clone(), v("c1"))],
+                (v("c1"), equivalent_class, v("c2")),
+            ),
+            rule(
+                vec![(v("p1"), sub_property.clone(), v("p2")), (v("p2"), sub_property.clone(), v("p1"))],
+                (v("p1"), equivalent_property, v("p2")),
+            ),
+            // scm-dom1, scm-dom2, scm-rng1, scm-rng2
+            rule(vec![(v("p"), domain.clone(), v("c1")), (v("c1"), sub_class.clone(), v("c2"))], (v("p"), domain.clone(), v("c2"))),
+            rule(
+                vec![(v("p2"), domain.clone(), v("c")), (v("p1"), sub_property.clone(), v("p2"))],
+                (v("p1"), domain, v("c")),
+            ),
+            rule(vec![(v("p"), range.clone(), v("c1")), (v("c1"), sub_class.clone(), v("c2"))], (v("p"), range.clone(), v("c2"))),
+            rule(vec![(v("p2"), range.clone(), v("c")), (v("p1"), sub_property, v("p2"))], (v("p1"), range, v("c"))),
+            // cls-hv1, cls-hv2
+            rule(
+                vec![(v("r"), has_value.clone(), v("y")), (v("r"), on_property.clone(), v("p")), (v("x"), ty.clone(), v("r"))],
+                (v("x"), v("p"), v("y")),
+            ),
+            rule(
+                vec![(v("r"), has_value, v("y")), (v("r"), on_property.clone(), v("p")), (v("x"), v("p"), v("y"))],
+                (v("x"), ty.clone(), v("r")),
+            ),
+            // cls-svf1, cls-svf2
+            rule(
+                vec![
+                    (v("r"), some_values_from.clone(), v("c")),
+                    (v("r"), on_property.clone(), v("p")),
+                    (v("x"), v("p"), v("y")),
+                    (v("y"), ty.clone(), v("c")),
+                ],
+                (v("x"), ty.clone(), v("r")),
+            ),
+            rule(
+                vec![(v("r"), some_values_from, thing), (v("r"), on_property.clone(), v("p")), (v("x"), v("p"), v("y"))],
+                (v("x"), ty.clone(), v("r")),
+            ),
+            // cls-avf
+            rule(
+                vec![
+                    (v("r"), all_values_from, v("c")),
+                    (v("r"), on_property, v("p")),
+                    (v("x"), ty.clone(), v("r")),
+                    (v("x"), v("p"), v("y")),
+                ],
+                (v("y"), ty, v("c")),
+            ),
+        ]);
+        rules
+    }
+
+    /// Whether the regime derives `owl:sameAs`, so equality classes are needed
+    pub fn has_equality(&self) -> bool {
+        *self != EntailmentRegime::Rdfs
+    }
+}
+
+impl std::str::FromStr for EntailmentRegime {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace(['-', '_', ' '], "").as_str() {
+            "rdfs" => Ok(EntailmentRegime::Rdfs),
+            "rdfsplus" => Ok(EntailmentRegime::RdfsPlus),
+            "owl2rl" | "owlrl" => Ok(EntailmentRegime::Owl2Rl),
+            _ => Err(format!("unknown entailment regime '{}'", s)),
+        }
+    }
+}
+
+/// Whether `triple` is a schema axiom: its predicate is RDFS or OWL
+/// vocabulary, or it types a term with an RDFS or OWL class (e.g.
+/// `owl:TransitiveProperty`)
+pub fn is_schema_triple(triple: &Triple, dict: &Dictionary) -> bool {
+    let vocabulary = |id: u32| {
+        dict.decode(id)
+            .is_some_and(|term| term.starts_with(RDFS) || term.starts_with(OWL))
+    };
+    let is_type = dict.decode(triple.predicate).and_then(|p| p.strip_prefix(RDF)) == Some("type");
+    vocabulary(triple.predicate) || (is_type && vocabulary(triple.object))
+}
+
+fn v(name: &str) -> Term {
+    Term::Variable(name.to_string())
+}
+
+fn rule(premise: Vec<TriplePattern>, conclusion: TriplePattern) -> Rule {
+    Rule {
+        premise,
+        negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
+        filters: vec![],
+        conclusion: vec![conclusion],
+    }
+}
+
+/// Terms known to be `owl:sameAs` each other, as a union-find over term IDs.
+/// Every class is represented by its smallest ID.
+#[derive(Debug, Clone, Default)]
+pub struct EqualityClasses {
+    parent: HashMap<u32, u32>,
+}
+
+impl EqualityClasses {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn representative(&self, mut term: u32) -> u32 {
+        while let Some(&parent) = self.parent.get(&term) {
+            term = parent;
+        }
+        term
+    }
+
+    /// Merges the classes of `a` and `b`; returns whether they were distinct
+    pub fn union(&mut self, a: u32, b: u32) -> bool {
+        let (a, b) = (self.representative(a), self.representative(b));
+        if a == b {
+            return false;
+        }
+        let (root, child) = if a < b { (a, b) } else { (b, a) };
+        self.parent.insert(child, root);
+        true
+    }
+
+    /// Members of every class with more than one term, by representative
+    pub fn classes(&self) -> HashMap<u32, Vec<u32>> {
+        let mut classes: HashMap<u32, Vec<u32>> = HashMap::new();
+        for &term in self.parent.keys() {
+            classes.entry(self.representative(term)).or_default().push(term);
+        }
+        for (&root, members) in classes.iter_mut() {
+            members.push(root);
+            members.sort_unstable();
+        }
+        classes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    pub fn canonical(&self, triple: &Triple) -> Triple {
+        Triple {
+            subject: self.representative(triple.subject),
+            predicate: self.representative(triple.predicate),
+            object: self.representative(triple.object),
+        }
+    }
+
+    fn canonical_term(&self, term: &Term) -> Term {
+        match term {
+            Term::Constant(c) => Term::Constant(self.representative(*c)),
+            other => other.clone(),
+        }
+    }
+
+    fn canonical_rule(&self, rule: &Rule) -> Rule {
+        let canonical = |patterns: &[TriplePattern]| {
+            patterns
+                .iter()
+                .map(|(s, p, o)| (self.canonical_term(s), self.canonical_term(p), self.canonical_term(o)))
+                .collect()
+        };
+        Rule {
+            premise: canonical(&rule.premise),
+            negative_premise: canonical(&rule.negative_premise),
+            bindings: rule.bindings.clone(),
+            aggregates: rule.aggregates.clone(),
+            filters: rule.filters.clone(),
+            conclusion: canonical(&rule.conclusion),
+        }
+    }
+}
+
+impl Reasoner {
+    /// Materialises the user rules together with the rules of `regime`,
+    /// returning the inferred facts.
+    ///
+    /// `owl:sameAs` is not handled by rewriting every fact for every pair of
+    /// equal terms while reasoning: equal terms are merged into equality
+    /// classes, the rules run over the facts of the class representatives, and
+    /// only the final closure is expanded to all members of each class (along
+    /// with the `owl:sameAs` links between them).
+    pub fn infer_new_facts_with_entailment(&mut self, regime: EntailmentRegime) -> Vec<Triple> {
+        let (program, same_as) = {
+            let mut dict = self.dictionary.write().unwrap();
+            let mut program = self.rules.clone();
+            program.extend(regime.rules(&mut dict));
+            (program, dict.encode(OWL_SAME_AS))
+        };
+        let explicit: HashSet<Triple> = self
+            .index_manager
+            .query(None, None, None)
+            .into_iter()
+            .filter(|fact| !self.derived_facts.contains(fact))
+            .collect();
+        let before: HashSet<Triple> = self.index_manager.query(None, None, None).into_iter().collect();
+        let user_rules = std::mem::replace(&mut self.rules, program.clone());
+
+        let mut equality = EqualityClasses::new();
+        loop {
+            self.infer_new_facts_semi_naive();
+            if !regime.has_equality() {
+                break;
+            }
+            let mut merged = false;
+            for fact in self.index_manager.query(None, Some(same_as), None) {
+                merged |= equality.union(fact.subject, fact.object);
+            }
+            if !merged {
+                break;
+            }
+            // Continue over the representatives; rule constants may have been
+            // merged as well
+            self.rules = program.iter().map(|rule| equality.canonical_rule(rule)).collect();
+            let facts: HashSet<Triple> = self
+                .index_manager
+                .query(None, None, None)
+                .iter()
+                .map(|fact| equality.canonical(fact))
+                .filter(|fact| !(fact.predicate == equality.representative(same_as) && fact.subject == fact.object))
+                .collect();
+            self.index_manager = UnifiedIndex::new();
+            for fact in &facts {
+                self.index_manager.insert(fact);
+            }
+        }
+        self.rules = user_rules;
+
+        if !equality.is_empty() {
+            self.expand_equality_classes(&equality, same_as);
+        }
+        let inferred: Vec<Triple> = self
+            .index_manager
+            .query(None, None, None)
+            .into_iter()
+            .filter(|fact| !before.contains(fact))
+            .collect();
+        self.derived_facts = self
+            .index_manager
+            .query(None, None, None)
+            .into_iter()
+            .filter(|fact| !explicit.contains(fact))
+            .collect();
+        inferred
+    }
+
+    /// Replaces the facts over class representatives by the facts over every
+    /// combination of class members, and links the members by `owl:sameAs`
+    fn expand_equality_classes(&mut self, equality: &EqualityClasses, same_as: u32) {
+        let classes = equality.classes();
+        let members = |term: u32| classes.get(&term).cloned().unwrap_or_else(|| vec![term]);
+        let mut expanded = UnifiedIndex::new();
+        for fact in self.index_manager.query(None, None, None) {
+            for &subject in &members(fact.subject) {
+                for &predicate in &members(fact.predicate) {
+                    for &object in &members(fact.object) {
+                        expanded.insert(&Triple { subject, predicate, object });
+                    }
+                }
+            }
+        }
+        for class in classes.values() {
+            for &subject in class {
+                for &object in class {
+                    expanded.insert(&Triple { subject, predicate: same_as, object });
+                }
+            }
+        }
+        self.index_manager = expanded;
+    }
+}
@@ -0,0 +1,182 @@
+use shared::dictionary::Dictionary;
+use shared::quoted_triple_store::QuotedTripleStore;
+use shared::triple::Triple;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use crate::reasoning::Reasoner;
+
+const PROOF: &str = "http://www.w3.org/ns/proof#";
+
+/// One application of a rule that derived a fact
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Derivation {
+    /// Index of the rule in `Reasoner::rules`
+    pub rule: usize,
+    /// Facts matching the premises of the rule, in the order of the premises
+    pub premises: Vec<Triple>,
+}
+
+/// Why a fact holds: the rule application that derived it, with a proof tree
+/// for each of its premises, or nothing for an asserted fact
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProofTree {
+    pub fact: Triple,
+    /// Index of the rule in `Reasoner::rules`; `None` for asserted facts
+    pub rule: Option<usize>,
+    pub premises: Vec<ProofTree>,
+}
+
+impl ProofTree {
+    fn contains(&self, fact: &Triple) -> bool {
+        self.premises.iter().any(|premise| premise.fact == *fact || premise.contains(fact))
+    }
+
+    /// The proof tree as RDF-star: every derived fact is linked to its rule
+    /// application, a blank node with the rule index and the premises, e.g.
+    /// `<< d >> proof:derivedBy _:derivation_1f`, `_:derivation_1f
+    /// proof:rule "0"^^xsd:integer` and `_:derivation_1f proof:premise << p >>`.
+    /// Asserted facts get `<< p >> proof:asserted true`.
+    pub fn encode_as_rdf_star(&self, dict: &mut Dictionary, qt_store: &mut QuotedTripleStore) -> Vec<Triple> {
+        let mut result = Vec::new();
+        self.encode_into(dict, qt_store, &mut result);
+        result
+    }
+
+    fn encode_into(&self, dict: &mut Dictionary, qt_store: &mut QuotedTripleStore, result: &mut Vec<Triple>) {
+        let fact_qt = qt_store.encode(self.fact.subject, self.fact.predicate, self.fact.object);
+        let Some(rule) = self.rule else {
+            let asserted = Triple {
+                subject: fact_qt,
+                predicate: dict.encode(&format!("{}asserted", PROOF)),
+                object: dict.encode("\"true\"^^<http://www.w3.org/2001/XMLSchema#boolean>"),
+            };
+            if !result.contains(&asserted) {
+                result.push(asserted);
+            }
+            return;
+        };
+
+        // The same rule application gets the same blank node in every tree
+        let mut hasher = DefaultHasher::new();
+        (&self.fact, rule, self.premises.iter().map(|p| &p.fact).collect::<Vec<_>>()).hash(&mut hasher);
+        let derivation = dict.encode(&format!("_:derivation_{:x}", hasher.finish()));
+        let derived_by = Triple {
+            subject: fact_qt,
+            predicate: dict.encode(&format!("{}derivedBy", PROOF)),
+            object: derivation,
+        };
+        if result.contains(&derived_by) {
+            return;
+        }
+        result.push(derived_by);
+        result.push(Triple {
+            subject: derivation,
+            predicate: dict.encode(&format!("{}rule", PROOF)),
+            object: dict.encode(&format!("\"{}\"^^<http://www.w3.org/2001/XMLSchema#integer>", rule)),
+        });
+        let premise_id = dict.encode(&format!("{}premise", PROOF));
+        for premise in &self.premises {
+            let premise_qt = qt_store.encode(premise.fact.subject, premise.fact.predicate, premise.fact.object);
+            result.push(Triple { subject: derivation, predicate: premise_id, object: premise_qt });
+            premise.encode_into(dict, qt_store, result);
+        }
+    }
+}
+
+impl Reasoner {
+    /// Depth of the shallowest proof of every fact with a recorded
+    /// derivation; asserted facts have depth 0. Computed once per
+    /// materialisation.
+    fn proof_depths(&self) -> &HashMap<Triple, usize> {
+        self.proof_depths.get_or_init(|| self.compute_proof_depths())
+    }
+
+    fn compute_proof_depths(&self) -> HashMap<Triple, usize> {
+        let mut depths: HashMap<Triple, usize> = HashMap::new();
+        loop {
+            let mut changed = false;
+            for (fact, derivations) in &self.derivations {
+                for derivation in derivations {
+                    let depth = derivation
+                        .premises
+                        .iter()
+                        .map(|premise| match self.derived_facts.contains(premise) {
+                            true => depths.get(premise).copied(),
+                            false => Some(0),
+                        })
+                        .try_fold(0, |max, depth| depth.map(|d| max.max(d)));
+                    if let Some(depth) = depth.map(|d| d + 1) {
+                        if depths.get(fact).is_none_or(|&known| depth < known) {
+                            depths.insert(fact.clone(), depth);
+                            changed = true;
+                        }
+                    }
+                }
+            }
+            if !changed {
+                return depths;
+            }
+        }
+    }
+
+    /// Shallowest proof of `fact`, each premise being proven by a shallower one
+    fn shallowest_proof(&self, fact: &Triple, depths: &HashMap<Triple, usize>) -> ProofTree {
+        let depth = |fact: &Triple| match self.derived_facts.contains(fact) {
+            true => depths.get(fact).copied().unwrap_or(usize::MAX),
+            false => 0,
+        };
+        let derivation = self.derivations.get(fact).and_then(|derivations| {
+            derivations.iter().find(|d| d.premises.iter().all(|p| depth(p) < depth(fact)))
+        });
+        match derivation {
+            Some(derivation) if self.derived_facts.contains(fact) => ProofTree {
+                fact: fact.clone(),
+                rule: Some(derivation.rule),
+                premises: derivation.premises.iter().map(|p| self.shallowest_proof(p, depths)).collect(),
+            },
+            _ => ProofTree { fact: fact.clone(), rule: None, premises: Vec::new() },
+        }
+    }
+
+    /// Explains why `triple` holds, with one proof tree per recorded
+    /// derivation of it that does not depend on the triple itself. Premises
+    /// are explained by their shallowest proofs. An asserted triple has a
+    /// single proof without a rule; a triple that does not hold has none.
+    ///
+    /// Derivations are only recorded by `infer_new_facts_semi_naive` when
+    /// `record_derivations` is set, and not for rules with aggregates.
+    pub fn explain(&self, triple: &Triple) -> Vec<ProofTree> {
+        let holds = !self
+            .index_manager
+            .query(Some(triple.subject), Some(triple.predicate), Some(triple.object))
+            .is_empty();
+        if !holds {
+            return Vec::new();
+        }
+        if !self.derived_facts.contains(triple) {
+            return vec![ProofTree { fact: triple.clone(), rule: None, premises: Vec::new() }];
+        }
+
+        let depths = self.proof_depths();
+        let mut proofs: Vec<ProofTree> = Vec::new();
+        for derivation in self.derivations.get(triple).into_iter().flatten() {
+            let proven = derivation
+                .premises
+                .iter()
+                .all(|p| !self.derived_facts.contains(p) || depths.contains_key(p));
+            if !proven {
+                continue;
+            }
+            let proof = ProofTree {
+                fact: triple.clone(),
+                rule: Some(derivation.rule),
+                premises: derivation.premises.iter().map(|p| self.shallowest_proof(p, depths)).collect(),
+            };
+            if !proof.contains(triple) && !proofs.contains(&proof) {
+                proofs.push(proof);
+            }
+        }
+        proofs
+    }
+}
@@ -0,0 +1,193 @@
+use shared::dictionary::Dictionary;
+use shared::rule::{pattern_variables, Rule};
+use shared::terms::{Term, TriplePattern};
+use shared::triple::Triple;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+use crate::reasoning::Reasoner;
+use crate::reasoning::rules::matches_rule_pattern;
+
+/// Which of the subject and object of a call are bound
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Adornment {
+    pub subject_bound: bool,
+    pub object_bound: bool,
+}
+
+impl Adornment {
+    /// Adornment of `pattern` when the variables in `bound` have a value
+    fn of(pattern: &TriplePattern, bound: &HashSet<String>) -> Self {
+        let is_bound = |term: &Term| match term {
+            Term::Constant(_) => true,
+            Term::Variable(v) => bound.contains(v),
+            Term::QuotedTriple(_) => false,
+        };
+        Adornment {
+            subject_bound: is_bound(&pattern.0),
+            object_bound: is_bound(&pattern.2),
+        }
+    }
+
+    /// `b`/`f` for the subject, then for the object, e.g. `bf`
+    pub fn name(&self) -> String {
+        let letter = |bound: bool| if bound { 'b' } else { 'f' };
+        format!("{}{}", letter(self.subject_bound), letter(self.object_bound))
+    }
+}
+
+/// Rules rewritten for one goal, and the magic fact seeding their evaluation
+#[derive(Debug, Clone)]
+pub struct MagicProgram {
+    pub rules: Vec<Rule>,
+    pub seed: Triple,
+}
+
+/// Magic-sets rewriting of `rules` for `goal` (Bancilhon et al., 1986), with
+/// left-to-right sideways information passing.
+///
+/// For every call to a derived predicate with a given adornment, a magic
+/// predicate collects the bound arguments the call is made with. Each rule
+/// deriving the predicate gets its magic fact as an extra premise, and magic
+/// rules pass bindings from the head and the premises to the left on to the
+/// derived premises, so semi-naive evaluation of the result only derives
+/// facts relevant to the goal. Magic facts are stored as triples with a
+/// `__magic_<adornment>_<predicate id>` predicate and the bound arguments
+/// (or `__magic_nil`) as subject and object.
+///
+/// Returns `None` when the goal or a rule has a variable predicate, or a rule
+/// has negated premises or aggregates, as those programs are not rewritten.
+pub fn magic_sets_rewrite(rules: &[Rule], goal: &TriplePattern, dict: &mut Dictionary) -> Option<MagicProgram> {
+    let predicate_of = |pattern: &TriplePattern| match pattern.1 {
+        Term::Constant(p) => Some(p),
+        _ => None,
+    };
+    let goal_predicate = predicate_of(goal)?;
+    let mut derived = HashSet::new();
+    for rule in rules {
+        if !rule.negative_premise.is_empty()
+            || !rule.aggregates.is_empty()
+            || rule.premise.iter().any(|p| predicate_of(p).is_none())
+        {
+            return None;
+        }
+        for head in &rule.conclusion {
+            derived.insert(predicate_of(head)?);
+        }
+    }
+
+    let goal_adornment = Adornment::of(goal, &HashSet::new());
+    let seed_pattern = magic_atom(goal_predicate, goal_adornment, &goal.0, &goal.2, dict);
+    let seed = match seed_pattern {
+        (Term::Constant(subject), Term::Constant(predicate), Term::Constant(object)) => Triple { subject, predicate, object },
+        _ => unreachable!("bound arguments of the goal are constants"),
+    };
+
+    let mut rewritten = Vec::new();
+    let mut seen = BTreeSet::from([(goal_predicate, goal_adornment)]);
+    let mut pending = vec![(goal_predicate, goal_adornment)];
+    while let Some((predicate, adornment)) = pending.pop() {
+        for rule in rules {
+            for head in rule.conclusion.iter().filter(|head| head.1 == Term::Constant(predicate)) {
+                let magic_head = magic_atom(predicate, adornment, &head.0, &head.2, dict);
+                let mut bound: HashSet<String> = pattern_variables(&magic_head).map(str::to_string).collect();
+
+                for (idx, premise) in rule.premise.iter().enumerate() {
+                    let premise_predicate = predicate_of(premise)?;
+                    if derived.contains(&premise_predicate) {
+                        let call = Adornment::of(premise, &bound);
+                        let magic_call = magic_atom(premise_predicate, call, &premise.0, &premise.2, dict);
+                        if idx > 0 || magic_call != magic_head {
+                            let mut magic_premise = vec![magic_head.clone()];
+                            magic_premise.extend(rule.premise[..idx].iter().cloned());
+                            rewritten.push(Rule {
+                                premise: magic_premise,
+                                negative_premise: vec![],
+                                bindings: vec![],
+                                aggregates: vec![],
+                                filters: vec![],
+                                conclusion: vec![magic_call],
+                            });
+                        }
+                        if seen.insert((premise_predicate, call)) {
+                            pending.push((premise_predicate, call));
+                        }
+                    }
+                    bound.extend(pattern_variables(premise).map(str::to_string));
+                }
+
+                let mut premise = vec![magic_head];
+                premise.extend(rule.premise.iter().cloned());
+                rewritten.push(Rule {
+                    premise,
+                    negative_premise: vec![],
+                    bindings: rule.bindings.clone(),
+                    aggregates: vec![],
+                    filters: rule.filters.clone(),
+                    conclusion: vec![head.clone()],
+                });
+            }
+        }
+    }
+
+    Some(MagicProgram { rules: rewritten, seed })
+}
+
+/// Magic fact pattern of a call to `predicate` with `adornment`, keeping the
+/// bound arguments among `subject` and `object`
+fn magic_atom(predicate: u32, adornment: Adornment, subject: &Term, object: &Term, dict: &mut Dictionary) -> TriplePattern {
+    let magic = Term::Constant(dict.encode(&format!("__magic_{}_{}", adornment.name(), predicate)));
+    let nil = Term::Constant(dict.encode("__magic_nil"));
+    match (adornment.subject_bound, adornment.object_bound) {
+        (true, true) => (subject.clone(), magic, object.clone()),
+        (true, false) => (subject.clone(), magic, nil),
+        (false, true) => (object.clone(), magic, nil),
+        (false, false) => (nil.clone(), magic, nil),
+    }
+}
+
+impl Reasoner {
+    /// Returns the bindings of the variables of `query` for every derivable
+    /// fact matching it, by semi-naive evaluation of the magic-sets rewriting
+    /// of the rules for `query`. Only facts relevant to the query are
+    /// derived, and the reasoner itself is left unchanged.
+    ///
+    /// Programs `magic_sets_rewrite` does not handle are fully materialised
+    /// instead.
+    pub fn query_with_magic_sets(&self, query: &TriplePattern) -> Vec<HashMap<String, Term>> {
+        let program = {
+            let mut dict = self.dictionary.write().unwrap();
+            magic_sets_rewrite(&self.rules, query, &mut dict)
+        };
+
+        let mut scratch = Reasoner::new();
+        scratch.dictionary = Arc::clone(&self.dictionary);
+        scratch.index_manager = self.index_manager.clone();
+        match program {
+            Some(program) => {
+                scratch.rules = program.rules;
+                scratch.index_manager.insert(&program.seed);
+            }
+            None => scratch.rules = self.rules.clone(),
+        }
+        scratch.infer_new_facts_semi_naive();
+
+        let constant = |term: &Term| match term {
+            Term::Constant(c) => Some(*c),
+            _ => None,
+        };
+        scratch
+            .index_manager
+            .query(constant(&query.0), constant(&query.1), constant(&query.2))
+            .iter()
+            .filter_map(|fact| {
+                let mut binding = HashMap::new();
+                matches_rule_pattern(query, fact, &mut binding).then(|| {
+                    binding
+                        .into_iter()
+                        .map(|(var, id)| (var, Term::Constant(id)))
+                        .collect()
+                })
+            })
+            .collect()
+    }
+}
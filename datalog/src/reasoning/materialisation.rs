@@ -4,6 +4,7 @@ use shared::triple::Triple;
 use std::collections::HashMap;
 
 pub mod infer_generic;
+pub mod incremental;
 pub mod semi_naive;
 pub mod my_naive;
 pub mod semi_naive_parallel;
@@ -0,0 +1,377 @@
+use shared::dictionary::Dictionary;
+use shared::rule::Rule;
+use shared::terms::{Term, TriplePattern};
+use shared::triple::Triple;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use crate::reasoning::Reasoner;
+use crate::reasoning::materialisation::infer_generic::SolutionMapping;
+use crate::reasoning::materialisation::replace_variables_with_bound_values;
+use crate::reasoning::rules::{complete_solution, matches_rule_pattern};
+use crate::reasoning::stratification::rule_strata;
+
+/// Net change of the materialisation after an incremental update
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MaterializationDelta {
+    /// Facts that were not in the materialisation before the update
+    pub added: Vec<Triple>,
+    /// Facts that are no longer in the materialisation
+    pub removed: Vec<Triple>,
+}
+
+/// Facts added and removed so far, netted: a fact removed and derived again
+/// is in neither set. The materialisation before the update is the current
+/// index without `added`, plus `removed`.
+#[derive(Default)]
+struct Changes {
+    added: HashSet<Triple>,
+    removed: HashSet<Triple>,
+}
+
+impl Changes {
+    fn add(&mut self, fact: &Triple) {
+        if !self.removed.remove(fact) {
+            self.added.insert(fact.clone());
+        }
+    }
+
+    fn remove(&mut self, fact: &Triple) {
+        if !self.added.remove(fact) {
+            self.removed.insert(fact.clone());
+        }
+    }
+}
+
+/// State of the facts a rule body is evaluated against
+#[derive(Clone, Copy, PartialEq)]
+enum View {
+    /// The materialisation before the update
+    Old,
+    /// The index as it is now
+    New,
+}
+
+impl Reasoner {
+    /// Whether `fact` was asserted rather than derived by the reasoner
+    pub fn is_explicit(&self, fact: &Triple) -> bool {
+        !self.derived_facts.contains(fact) && self.contains_fact(fact)
+    }
+
+    fn contains_fact(&self, fact: &Triple) -> bool {
+        !self
+            .index_manager
+            .query(Some(fact.subject), Some(fact.predicate), Some(fact.object))
+            .is_empty()
+    }
+
+    /// Updates the materialised facts for a batch of inserted and deleted
+    /// explicit facts with Delete-and-Rederive (Gupta et al., 1993), instead
+    /// of materialising again from scratch.
+    ///
+    /// The index must hold the materialisation of the explicit facts, as left
+    /// by a previous call or by one of the `infer_new_facts_*` strategies.
+    /// Strata are processed bottom-up, and within a stratum facts that may have
+    /// lost their derivation are first over-deleted, then re-derived from what
+    /// remains, and finally new consequences are inserted semi-naively. Negated
+    /// premises are honoured: inserting a fact can retract conclusions and
+    /// deleting one can derive new ones.
+    ///
+    /// Deleting a fact that was never asserted has no effect. When a rule has
+    /// aggregates, the derived facts are materialised again from scratch
+    /// instead, as an aggregate can change with any fact in its group. The
+    /// same goes for existential variables, whose invented blank nodes cannot
+    /// be re-derived. Otherwise the recorded `derivations` no longer describe
+    /// the materialisation and are cleared.
+    pub fn update_materialization(
+        &mut self,
+        inserted: &[Triple],
+        deleted: &[Triple],
+    ) -> Result<MaterializationDelta, String> {
+        let from_scratch = |rule: &Rule| !rule.aggregates.is_empty() || !rule.existential_variables().is_empty();
+        if self.rules.iter().any(from_scratch) {
+            return Ok(self.rematerialize(inserted, deleted));
+        }
+        let strata = rule_strata(&self.rules)?;
+        self.derivations.clear();
+        self.proof_depths.take();
+        let levels = strata.iter().max().map_or(1, |top| top + 1);
+        let dictionary = Arc::clone(&self.dictionary);
+        let mut dict = dictionary.write().unwrap();
+
+        let mut changes = Changes::default();
+        // Present facts whose derivation may be gone, and deleted facts that may
+        // still be derivable, by stratum
+        let mut suspects: Vec<HashSet<Triple>> = vec![HashSet::new(); levels];
+        let mut candidates: Vec<HashSet<Triple>> = vec![HashSet::new(); levels];
+
+        let inserted_set: HashSet<&Triple> = inserted.iter().collect();
+        for fact in deleted {
+            if !inserted_set.contains(fact) && self.is_explicit(fact) {
+                self.index_manager.delete(fact);
+                changes.remove(fact);
+                candidates[self.fact_stratum(fact, &strata)].insert(fact.clone());
+            }
+        }
+        for fact in inserted {
+            if self.derived_facts.remove(fact) {
+                continue;
+            }
+            if self.index_manager.insert(fact) {
+                changes.add(fact);
+            }
+        }
+
+        for stratum in 0..levels {
+            let rule_ids: Vec<usize> = (0..self.rules.len()).filter(|&id| strata[id] == stratum).collect();
+
+            // Over-delete everything derived with a removed fact, or with a
+            // negated fact that is now present
+            let mut overdeleted: HashSet<Triple> = HashSet::new();
+            let mut delta: HashSet<Triple> = changes.removed.clone();
+            let mut negated: HashSet<Triple> = changes.added.clone();
+            for fact in std::mem::take(&mut suspects[stratum]) {
+                if !self.is_explicit(&fact) && self.overdelete(&fact, &mut changes) {
+                    delta.insert(fact.clone());
+                    overdeleted.insert(fact);
+                }
+            }
+            while !delta.is_empty() || !negated.is_empty() {
+                let mut conclusions = Vec::new();
+                for &id in &rule_ids {
+                    let rule = &self.rules[id];
+                    for binding in self.fired_by(rule, &delta, &negated, View::Old, &changes, &mut dict) {
+                        conclusions.extend(
+                            rule.conclusion
+                                .iter()
+                                .map(|head| replace_variables_with_bound_values(head, &binding, &mut dict)),
+                        );
+                    }
+                }
+                delta.clear();
+                negated.clear();
+                for fact in conclusions {
+                    let fact_stratum = self.fact_stratum(&fact, &strata);
+                    if fact_stratum > stratum {
+                        suspects[fact_stratum].insert(fact);
+                    } else if !self.is_explicit(&fact) && self.overdelete(&fact, &mut changes) {
+                        delta.insert(fact.clone());
+                        overdeleted.insert(fact);
+                    }
+                }
+            }
+
+            // Re-derive what still has a derivation in one step; the insertion
+            // phase derives the rest. Re-derived facts are netted out of the
+            // changes, so they are kept apart to seed that phase.
+            overdeleted.extend(std::mem::take(&mut candidates[stratum]));
+            let mut rederived = HashSet::new();
+            for fact in overdeleted {
+                if !self.contains_fact(&fact) && self.derivable(&fact, &mut dict) {
+                    self.index_manager.insert(&fact);
+                    self.derived_facts.insert(fact.clone());
+                    changes.add(&fact);
+                    rederived.insert(fact);
+                }
+            }
+
+            // Insert the consequences of added and re-derived facts, and of
+            // negated facts that are now absent
+            let mut delta: HashSet<Triple> = changes.added.union(&rederived).cloned().collect();
+            let mut negated: HashSet<Triple> = changes.removed.clone();
+            while !delta.is_empty() || !negated.is_empty() {
+                let mut conclusions = Vec::new();
+                for &id in &rule_ids {
+                    let rule = &self.rules[id];
+                    for binding in self.fired_by(rule, &delta, &negated, View::New, &changes, &mut dict) {
+                        conclusions.extend(
+                            rule.conclusion
+                                .iter()
+                                .map(|head| replace_variables_with_bound_values(head, &binding, &mut dict)),
+                        );
+                    }
+                }
+                delta.clear();
+                negated.clear();
+                for fact in conclusions {
+                    if self.index_manager.insert(&fact) {
+                        self.derived_facts.insert(fact.clone());
+                        changes.add(&fact);
+                        delta.insert(fact);
+                    }
+                }
+            }
+        }
+        drop(dict);
+
+        let mut added: Vec<Triple> = changes.added.into_iter().collect();
+        let mut removed: Vec<Triple> = changes.removed.into_iter().collect();
+        added.sort();
+        removed.sort();
+        Ok(MaterializationDelta { added, removed })
+    }
+
+    /// Applies the explicit changes, then derives every fact again
+    fn rematerialize(&mut self, inserted: &[Triple], deleted: &[Triple]) -> MaterializationDelta {
+        let mut changes = Changes::default();
+        for fact in std::mem::take(&mut self.derived_facts) {
+            self.index_manager.delete(&fact);
+            changes.remove(&fact);
+        }
+        let inserted_set: HashSet<&Triple> = inserted.iter().collect();
+        for fact in deleted {
+            if !inserted_set.contains(fact) && self.index_manager.delete(fact) {
+                changes.remove(fact);
+            }
+        }
+        for fact in inserted {
+            if self.index_manager.insert(fact) {
+                changes.add(fact);
+            }
+        }
+        self.infer_new_facts_semi_naive();
+        for fact in &self.derived_facts {
+            changes.add(fact);
+        }
+
+        let mut added: Vec<Triple> = changes.added.into_iter().collect();
+        let mut removed: Vec<Triple> = changes.removed.into_iter().collect();
+        added.sort();
+        removed.sort();
+        MaterializationDelta { added, removed }
+    }
+
+    /// Removes a present derived fact; returns whether it was present
+    fn overdelete(&mut self, fact: &Triple, changes: &mut Changes) -> bool {
+        if !self.index_manager.delete(fact) {
+            return false;
+        }
+        self.derived_facts.remove(fact);
+        changes.remove(fact);
+        true
+    }
+
+    /// Highest stratum of the rules whose conclusions match `fact`, 0 for
+    /// facts no rule derives
+    fn fact_stratum(&self, fact: &Triple, strata: &[usize]) -> usize {
+        self.rules
+            .iter()
+            .zip(strata)
+            .filter(|(rule, _)| {
+                rule.conclusion
+                    .iter()
+                    .any(|head| matches_rule_pattern(head, fact, &mut HashMap::new()))
+            })
+            .map(|(_, &stratum)| stratum)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Facts matching `pattern` under `binding` in `view`
+    fn view_matches(
+        &self,
+        pattern: &TriplePattern,
+        binding: &SolutionMapping,
+        view: View,
+        changes: &Changes,
+    ) -> Vec<Triple> {
+        let ground = |term: &Term| match term {
+            Term::Constant(c) => Some(*c),
+            Term::Variable(v) => binding.get(v).copied(),
+            Term::QuotedTriple(_) => None,
+        };
+        let (s, p, o) = (ground(&pattern.0), ground(&pattern.1), ground(&pattern.2));
+        let mut facts = self.index_manager.query(s, p, o);
+        if view == View::Old {
+            facts.retain(|fact| !changes.added.contains(fact));
+            facts.extend(changes.removed.iter().filter(|fact| {
+                s.is_none_or(|s| s == fact.subject)
+                    && p.is_none_or(|p| p == fact.predicate)
+                    && o.is_none_or(|o| o == fact.object)
+            }).cloned());
+        }
+        facts
+    }
+
+    /// Extensions of `binding` satisfying all premises but `skip`, the
+    /// negated premises and the filters of `rule` in `view`, with the
+    /// variables the rule computes
+    fn complete_binding(
+        &self,
+        rule: &Rule,
+        skip: Option<usize>,
+        binding: SolutionMapping,
+        view: View,
+        changes: &Changes,
+        dict: &mut Dictionary,
+    ) -> Vec<SolutionMapping> {
+        let mut bindings = vec![binding];
+        for (idx, premise) in rule.premise.iter().enumerate() {
+            if Some(idx) == skip {
+                continue;
+            }
+            let mut extended = Vec::new();
+            for partial in &bindings {
+                for fact in self.view_matches(premise, partial, view, changes) {
+                    let mut candidate = partial.clone();
+                    if matches_rule_pattern(premise, &fact, &mut candidate) {
+                        extended.push(candidate);
+                    }
+                }
+            }
+            bindings = extended;
+            if bindings.is_empty() {
+                break;
+            }
+        }
+        bindings.retain_mut(|binding| {
+            rule.negative_premise
+                .iter()
+                .all(|negated| self.view_matches(negated, binding, view, changes).is_empty())
+                && complete_solution(binding, rule, dict)
+        });
+        bindings
+    }
+
+    /// Bindings of `rule` in `view` using a fact of `delta` for some premise,
+    /// or with a negated premise matching a fact of `negated`
+    fn fired_by(
+        &self,
+        rule: &Rule,
+        delta: &HashSet<Triple>,
+        negated: &HashSet<Triple>,
+        view: View,
+        changes: &Changes,
+        dict: &mut Dictionary,
+    ) -> Vec<SolutionMapping> {
+        let mut bindings = Vec::new();
+        for (idx, premise) in rule.premise.iter().enumerate() {
+            for fact in delta {
+                let mut binding = HashMap::new();
+                if matches_rule_pattern(premise, fact, &mut binding) {
+                    bindings.extend(self.complete_binding(rule, Some(idx), binding, view, changes, dict));
+                }
+            }
+        }
+        for negated_premise in &rule.negative_premise {
+            for fact in negated {
+                let mut binding = HashMap::new();
+                if matches_rule_pattern(negated_premise, fact, &mut binding) {
+                    bindings.extend(self.complete_binding(rule, None, binding, view, changes, dict));
+                }
+            }
+        }
+        bindings
+    }
+
+    /// Whether some rule derives `fact` from the facts currently in the index
+    fn derivable(&self, fact: &Triple, dict: &mut Dictionary) -> bool {
+        let changes = Changes::default();
+        self.rules.iter().any(|rule| {
+            rule.conclusion.iter().any(|head| {
+                let mut binding = HashMap::new();
+                matches_rule_pattern(head, fact, &mut binding)
+                    && !self.complete_binding(rule, None, binding, View::New, &changes, dict).is_empty()
+            })
+        })
+    }
+}
@@ -1,8 +1,12 @@
 use shared::dictionary::Dictionary;
+use shared::index_manager::UnifiedIndex;
 use shared::rule::Rule;
 use shared::triple::Triple;
 use std::collections::{HashMap, HashSet};
 use crate::reasoning::Reasoner;
+use crate::reasoning::aggregation::aggregate_conclusions;
+use crate::reasoning::explanation::Derivation;
+use crate::reasoning::stratification::evaluation_layers;
 
 pub type SolutionMapping = HashMap<String, u32>;
 
@@ -11,6 +15,7 @@ pub trait InferenceStrategy {
     /// Applies a single round of inference within the materialisation algorithm.
     /// `param` all_facts: all facts currently in the knowledge base (base facts + those derived in previous rounds)
     /// `known_facts`: same facts but in HashSet for quick membership checks
+    /// `index`: same facts, indexed for lookups by pattern
     /// `returns`: facts that were inferred in this round
     fn infer_round(
         &mut self,
@@ -18,37 +23,111 @@ pub trait InferenceStrategy {
         rules: &Vec<Rule>,
         all_facts: &Vec<Triple>,
         known_facts: &HashSet<Triple>,
+        index: &UnifiedIndex,
     ) -> HashSet<Triple>;
+
+    /// Called before the rules of the next stratum are evaluated; rules of a
+    /// stratum see all facts of the lower strata, including those never used
+    /// as a delta by their own rules.
+    fn start_stratum(&mut self) {}
+
+    /// Derivations found since the last call, with `rule` indexing the rules
+    /// passed to `infer_round`. Strategies that do not record any return none.
+    fn take_derivations(&mut self) -> Vec<(Triple, Derivation)> {
+        Vec::new()
+    }
 }
 
 impl Reasoner {
 
     /// Generic function that infers all derivable facts using a given strategy, e.g. SemiNaive, or Naive
+    ///
+    /// Strata are evaluated bottom-up, each to fixpoint, so negated premises
+    /// are only checked once every fact they could match is derived. Rules
+    /// with aggregates are applied once, at the start of their stratum.
     pub fn infer_with_strategy<S: InferenceStrategy>(&mut self, mut strat: S) -> Vec<Triple> {
+        self.proof_depths.take();
         // In each iteration, facts are added to this list. Use vector to preserve index for initial facts
         let mut all_facts: Vec<Triple> = self.index_manager.query(None, None, None);
         let mut known_facts: HashSet<Triple> = all_facts.iter().cloned().collect();
         let idx_before_inference = all_facts.len(); // Used to keep track of which facts are inferred by the algorithm
 
+        for layer in evaluation_layers(&self.rules) {
+            let (aggregate_ids, rule_ids): (Vec<usize>, Vec<usize>) =
+                layer.into_iter().partition(|&id| !self.rules[id].aggregates.is_empty());
+            let aggregate_rules: Vec<Rule> = aggregate_ids.iter().map(|&id| self.rules[id].clone()).collect();
+            let rules: Vec<Rule> = rule_ids.iter().map(|&id| self.rules[id].clone()).collect();
+            self.apply_aggregate_rules(&aggregate_rules, &mut all_facts, &mut known_facts);
+            strat.start_stratum();
+            self.infer_stratum(&mut strat, &rules, &rule_ids, &mut all_facts, &mut known_facts);
+        }
+
+        all_facts.split_off(idx_before_inference)
+    }
+
+    /// Adds the conclusions of rules with aggregates over all facts so far
+    pub(crate) fn apply_aggregate_rules(
+        &mut self,
+        rules: &[Rule],
+        all_facts: &mut Vec<Triple>,
+        known_facts: &mut HashSet<Triple>,
+    ) {
+        if rules.is_empty() {
+            return;
+        }
+        let mut dict = self.dictionary.write().unwrap();
+        let aggregated = aggregate_conclusions(rules, all_facts, known_facts, &mut dict);
+        drop(dict);
+        for fact in aggregated {
+            known_facts.insert(fact.clone());
+            self.index_manager.insert(&fact);
+            self.derived_facts.insert(fact.clone());
+            all_facts.push(fact);
+        }
+    }
+
+    /// Applies the rules of one stratum, with `rule_ids` their indices in
+    /// `self.rules`, until no new facts are inferred
+    fn infer_stratum<S: InferenceStrategy>(
+        &mut self,
+        strat: &mut S,
+        rules: &Vec<Rule>,
+        rule_ids: &[usize],
+        all_facts: &mut Vec<Triple>,
+        known_facts: &mut HashSet<Triple>,
+    ) {
         loop {
 
             let mut dict = self.dictionary.write().unwrap();
-            let mut inferred_facts_this_round = strat.infer_round(&mut dict, &self.rules, &all_facts, &known_facts);
-
-            if inferred_facts_this_round.is_empty() {
-                break;
-            }
+            let mut inferred_facts_this_round =
+                strat.infer_round(&mut dict, rules, all_facts, known_facts, &self.index_manager);
+            drop(dict);
+            let fixpoint = inferred_facts_this_round.is_empty();
 
             for fact in inferred_facts_this_round.drain() {
                 // Insert into known_facts first; if it was not present, also store it.
                 if !known_facts.contains(&fact) {
                     known_facts.insert(fact.clone()); // Necessary clone apparently
                     self.index_manager.insert(&fact);
+                    self.derived_facts.insert(fact.clone());
                     all_facts.push(fact);
                 }
             }
-        }
 
-        all_facts.split_off(idx_before_inference)
+            // Asserted facts need no derivation
+            for (fact, mut derivation) in strat.take_derivations() {
+                if self.derived_facts.contains(&fact) {
+                    derivation.rule = rule_ids[derivation.rule];
+                    let derivations = self.derivations.entry(fact).or_default();
+                    if !derivations.contains(&derivation) {
+                        derivations.push(derivation);
+                    }
+                }
+            }
+
+            if fixpoint {
+                break;
+            }
+        }
     }
 }
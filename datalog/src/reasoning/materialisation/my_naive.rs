@@ -1,13 +1,16 @@
 use shared::dictionary::Dictionary;
+use shared::index_manager::UnifiedIndex;
 use shared::rule::Rule;
 use shared::triple::Triple;
 use std::collections::{BTreeMap, HashSet};
 use crate::reasoning::{convert_string_binding_to_u32, Reasoner};
 use crate::reasoning::materialisation::infer_generic::{SolutionMapping, InferenceStrategy};
-use crate::reasoning::materialisation::replace_variables_with_bound_values;
-use crate::reasoning::rules::{evaluate_filters, join_premise_with_hash_join};
+use crate::reasoning::chase::{instantiate_conclusions, ChaseConfig};
+use crate::reasoning::rules::{complete_solution, join_premise_with_hash_join, negated_premises_absent};
 
-pub struct NaiveStrategy;
+pub struct NaiveStrategy {
+    pub chase: ChaseConfig,
+}
 
 impl NaiveStrategy {
 
@@ -39,9 +42,11 @@ impl NaiveStrategy {
 
 impl InferenceStrategy for NaiveStrategy {
 
-    fn infer_round(&mut self, dictionary: &mut Dictionary, rules: &Vec<Rule>, all_facts: &Vec<Triple>, known_facts: &HashSet<Triple>) -> HashSet<Triple> {
+    fn infer_round(&mut self, dictionary: &mut Dictionary, rules: &Vec<Rule>, all_facts: &Vec<Triple>, known_facts: &HashSet<Triple>, index: &UnifiedIndex) -> HashSet<Triple> {
         // Also HashSet to prevent duplicate triples from being added this round
         let mut inferred_facts_this_round: HashSet<Triple> = HashSet::new();
+        // The same facts, for the restricted chase to look up
+        let mut inferred_index = UnifiedIndex::new();
 
         // Loop over each rule
         for rule in rules {
@@ -49,16 +54,17 @@ impl InferenceStrategy for NaiveStrategy {
             let binding_sets = self.find_premise_solutions(dictionary, rule, all_facts);
 
             // For each binding that satisfies the premises of the rule, get to the conclusion and apply bindings
-            for binding_set in &binding_sets {
-                if evaluate_filters(&binding_set, &rule.filters, dictionary) {
+            for mut binding_set in binding_sets {
+                if complete_solution(&mut binding_set, rule, dictionary)
+                    && negated_premises_absent(&binding_set, &rule.negative_premise, known_facts)
+                {
                     // Loop over each conclusion of the rule, since for the current binding,
                     // the conclusions of the rule can be inferred (because premises are met)
-                    for conclusion in &rule.conclusion {
-
-                        let inferred_fact =
-                            replace_variables_with_bound_values(conclusion, binding_set, dictionary);
-
+                    let facts = [index, &inferred_index];
+                    let inferred = instantiate_conclusions(rule, &binding_set, &facts, &self.chase, dictionary);
+                    for inferred_fact in inferred {
                         if !known_facts.contains(&inferred_fact) {
+                            inferred_index.insert(&inferred_fact);
                             inferred_facts_this_round.insert(inferred_fact);
                         }
                     }
@@ -72,7 +78,7 @@ impl InferenceStrategy for NaiveStrategy {
 
 impl Reasoner {
     pub fn infer_new_facts_naive(&mut self) -> Vec<Triple> {
-        self.infer_with_strategy(NaiveStrategy)
+        self.infer_with_strategy(NaiveStrategy { chase: self.chase })
     }
 
     /// For backward compatibility
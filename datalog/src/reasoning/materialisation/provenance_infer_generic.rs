@@ -15,6 +15,7 @@ use shared::tag_store::TagStore;
 use shared::triple::Triple;
 use std::collections::HashSet;
 use crate::reasoning::Reasoner;
+use crate::reasoning::stratification::evaluation_strata;
 
 /// Result of a single provenance inference round.
 pub struct ProvenanceInferResult {
@@ -37,10 +38,15 @@ pub trait ProvenanceInferenceStrategy<P: Provenance> {
         known_facts: &HashSet<Triple>,
         tag_store: &mut TagStore<P>,
     ) -> ProvenanceInferResult;
+
+    /// Called before the rules of the next stratum are evaluated.
+    fn start_stratum(&mut self) {}
 }
 
 impl Reasoner {
-    /// Generic driver for provenance-based materialisation. Loops until fixpoint.
+    /// Generic driver for provenance-based materialisation. Evaluates the
+    /// strata bottom-up, looping over each until fixpoint, so the tags of
+    /// negated facts are final when a rule negating them fires.
     pub fn infer_with_provenance_strategy<P, S>(
         &mut self,
         strat: S,
@@ -55,7 +61,6 @@ impl Reasoner {
     }
 
     /// Same as `infer_with_provenance_strategy` but with an explicit rule slice.
-    /// Used for stratified evaluation where stratum-0 uses only positive rules.
     pub fn infer_with_provenance_strategy_and_rules<P, S>(
         &mut self,
         mut strat: S,
@@ -70,13 +75,37 @@ impl Reasoner {
         let mut known_facts: HashSet<Triple> = all_facts.iter().cloned().collect();
         let idx_before_inference = all_facts.len();
 
+        for stratum in evaluation_strata(rules) {
+            // Aggregated facts are not tagged, i.e. they are certain
+            let (aggregate_rules, stratum): (Vec<Rule>, Vec<Rule>) =
+                stratum.into_iter().partition(|rule| !rule.aggregates.is_empty());
+            self.apply_aggregate_rules(&aggregate_rules, &mut all_facts, &mut known_facts);
+            strat.start_stratum();
+            self.infer_provenance_stratum(&mut strat, tag_store, &stratum, &mut all_facts, &mut known_facts);
+        }
+
+        all_facts.split_off(idx_before_inference)
+    }
+
+    /// Runs the rules of one stratum until no facts or tags change
+    fn infer_provenance_stratum<P, S>(
+        &mut self,
+        strat: &mut S,
+        tag_store: &mut TagStore<P>,
+        rules: &[Rule],
+        all_facts: &mut Vec<Triple>,
+        known_facts: &mut HashSet<Triple>,
+    ) where
+        P: Provenance,
+        S: ProvenanceInferenceStrategy<P>,
+    {
         loop {
             let mut dict = self.dictionary.write().unwrap();
             let result = strat.infer_round(
                 &mut dict,
                 rules,
-                &all_facts,
-                &known_facts,
+                all_facts,
+                known_facts,
                 tag_store,
             );
             drop(dict);
@@ -87,6 +116,7 @@ impl Reasoner {
                 if !known_facts.contains(&fact) {
                     known_facts.insert(fact.clone());
                     self.index_manager.insert(&fact);
+                    self.derived_facts.insert(fact.clone());
                     all_facts.push(fact);
                 }
             }
@@ -96,7 +126,5 @@ impl Reasoner {
                 break;
             }
         }
-
-        all_facts.split_off(idx_before_inference)
     }
 }
@@ -13,18 +13,21 @@ use shared::provenance::Provenance;
 use shared::rule::Rule;
 use shared::tag_store::TagStore;
 use shared::triple::Triple;
-use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use crate::reasoning::convert_string_binding_to_u32;
 use crate::reasoning::Reasoner;
 use crate::reasoning::materialisation::provenance_infer_generic::{
     ProvenanceInferResult, ProvenanceInferenceStrategy,
 };
-use crate::reasoning::materialisation::replace_variables_with_bound_values;
-use crate::reasoning::rules::{evaluate_filters, join_premise_with_hash_join};
+use crate::reasoning::chase::{instantiate_conclusions, ChaseConfig, ChaseVariant};
+use crate::reasoning::rules::{complete_solution, join_premise_with_hash_join};
 
 /// Semi-naive materialisation strategy parameterized by a provenance semiring.
 struct ProvenanceSemiNaiveStrategy {
     start_idx_for_delta: usize,
+    /// Always a Skolem chase, so every derivation of a fact with an invented
+    /// blank node contributes to the tag of the same fact
+    chase: ChaseConfig,
 }
 
 impl ProvenanceSemiNaiveStrategy {
@@ -131,19 +134,25 @@ impl<P: Provenance> ProvenanceInferenceStrategy<P> for ProvenanceSemiNaiveStrate
                 self.find_premise_solutions_with_triples(dictionary, rule, all_facts, delta_facts);
 
             for (binding, matched_triples) in &binding_sets {
-                let u32_binding = convert_string_binding_to_u32(binding, dictionary);
+                let mut u32_binding = convert_string_binding_to_u32(binding, dictionary);
 
-                if !evaluate_filters(&u32_binding, &rule.filters, dictionary) {
+                if !complete_solution(&mut u32_binding, rule, dictionary) {
                     continue;
                 }
 
                 // Combine premise tags via conjunction (⊗)
-                let conclusion_tag = matched_triples
+                let pos_tag = matched_triples
                     .iter()
                     .map(|t| tag_store.get_tag(t))
                     .fold(provenance.one(), |acc, tag| {
                         provenance.conjunction(&acc, &tag)
                     });
+                let conclusion_tag = if rule.negative_premise.is_empty() {
+                    pos_tag
+                } else {
+                    let neg_tag = negation_tag(rule, &u32_binding, known_facts, tag_store, &provenance);
+                    provenance.conjunction(&pos_tag, &neg_tag)
+                };
 
                 // Skip if the conclusion tag is zero (impossible derivation)
                 if conclusion_tag == provenance.zero() {
@@ -151,10 +160,7 @@ impl<P: Provenance> ProvenanceInferenceStrategy<P> for ProvenanceSemiNaiveStrate
                 }
 
                 // Generate conclusion triples
-                for conclusion in &rule.conclusion {
-                    let inferred_fact =
-                        replace_variables_with_bound_values(conclusion, &u32_binding, dictionary);
-
+                for inferred_fact in instantiate_conclusions(rule, &u32_binding, &[], &self.chase, dictionary) {
                     let is_new = !known_facts.contains(&inferred_fact);
 
                     if is_new && !new_facts.contains(&inferred_fact) {
@@ -179,12 +185,58 @@ impl<P: Provenance> ProvenanceInferenceStrategy<P> for ProvenanceSemiNaiveStrate
             tag_changed,
         }
     }
+
+    fn start_stratum(&mut self) {
+        // Every fact is new to the rules of a stratum
+        self.start_idx_for_delta = 0;
+    }
+}
+
+/// Conjunction (⊗) of the NAF contributions of the negated atoms of `rule`.
+/// Negated facts belong to lower strata, so `known_facts` and their tags are
+/// final by the time the rule fires.
+fn negation_tag<P: Provenance>(
+    rule: &Rule,
+    binding: &HashMap<String, u32>,
+    known_facts: &HashSet<Triple>,
+    tag_store: &TagStore<P>,
+    provenance: &P,
+) -> P::Tag {
+    let mut neg_tag = provenance.one();
+    for neg_pat in &rule.negative_premise {
+        let s = resolve_term(&neg_pat.0, binding);
+        let p = resolve_term(&neg_pat.1, binding);
+        let o = resolve_term(&neg_pat.2, binding);
+
+        let contrib = match (s, p, o) {
+            (Some(s), Some(p), Some(o)) => {
+                let neg_triple = Triple { subject: s, predicate: p, object: o };
+                if known_facts.contains(&neg_triple) {
+                    // Fact present -> negate its provenance tag.
+                    provenance.negate(&tag_store.get_tag(&neg_triple))
+                } else {
+                    // Fact absent -> NOT absent = certainly true = one().
+                    provenance.one()
+                }
+            }
+            // Unbound variable in negated atom — safety check should have
+            // caught this; treat as zero (cannot fire).
+            _ => provenance.zero(),
+        };
+
+        neg_tag = provenance.conjunction(&neg_tag, &contrib);
+        if neg_tag == provenance.zero() {
+            break;
+        }
+    }
+    neg_tag
 }
 
 impl Reasoner {
-    /// Run provenance-based semi-naive materialisation with single-stratum NAF support.
+    /// Run provenance-based semi-naive materialisation with stratified NAF support.
     ///
-    /// Positive rules run to fixpoint first (stratum 0), then NAF rules run once (stratum 1).
+    /// Strata run to fixpoint bottom-up, so a NAF rule only fires once every
+    /// fact it negates, and its tag, is derived.
     pub fn infer_new_facts_with_provenance<P: Provenance>(
         &mut self,
         provenance: P,
@@ -205,133 +257,21 @@ impl Reasoner {
         // Record sorted seed triples so encode_as_rdf_star_with_explanation can map IDs -> triples.
         tag_store.seed_triples = seeds.iter().map(|(t, _)| (*t).clone()).collect();
 
-        let new_facts = semi_naive_with_initial_tags(self, provenance, tag_store.clone());
+        let new_facts = semi_naive_with_initial_tags(self, tag_store.clone());
         (new_facts.0, new_facts.1)
     }
 }
 
 pub fn semi_naive_with_initial_tags<P: Provenance>(
     reasoner: &mut Reasoner,
-    provenance: P,
     mut initial_tags: TagStore<P>,
 ) -> (Vec<Triple>, TagStore<P>) {
-    // Split rules into positive (stratum 0) and negated (stratum 1).
-    let positive_rules: Vec<Rule> = reasoner.rules.iter()
-        .filter(|r| r.negative_premise.is_empty())
-        .cloned()
-        .collect();
-    let negative_rules: Vec<Rule> = reasoner.rules.iter()
-        .filter(|r| !r.negative_premise.is_empty())
-        .cloned()
-        .collect();
-
-    // Stratum 0: run positive fixpoint.
-    let mut new_facts = reasoner.infer_with_provenance_strategy_and_rules(
-        ProvenanceSemiNaiveStrategy { start_idx_for_delta: 0 },
+    let new_facts = reasoner.infer_with_provenance_strategy(
+        ProvenanceSemiNaiveStrategy {
+            start_idx_for_delta: 0,
+            chase: ChaseConfig { variant: ChaseVariant::Skolem, ..reasoner.chase },
+        },
         &mut initial_tags,
-        &positive_rules,
     );
-
-    // Stratum 1: single negative pass (if any NAF rules exist).
-    if !negative_rules.is_empty() {
-        let neg_new = run_negative_stratum_pass(reasoner, &negative_rules, &mut initial_tags, &provenance);
-        new_facts.extend(neg_new);
-    }
-
     (new_facts, initial_tags)
 }
-
-/// Single forward pass for NAF rules (stratum 1) over the stratum-0 closure.
-fn run_negative_stratum_pass<P: Provenance>(
-    reasoner: &mut Reasoner,
-    rules: &[Rule],
-    tag_store: &mut TagStore<P>,
-    provenance: &P,
-) -> Vec<Triple> {
-    let all_facts: Vec<Triple> = reasoner.index_manager.query(None, None, None);
-    let all_facts_set: HashSet<Triple> = all_facts.iter().cloned().collect();
-    let mut new_derived: Vec<Triple> = Vec::new();
-
-    let mut dict = reasoner.dictionary.write().unwrap();
-
-    for rule in rules {
-        // Join all positive premises to get candidate bindings.
-        let mut bindings: Vec<BTreeMap<String, String>> = vec![BTreeMap::new()];
-        for prem in &rule.premise {
-            bindings = join_premise_with_hash_join(prem, &all_facts, bindings, &dict);
-            if bindings.is_empty() {
-                break;
-            }
-        }
-
-        for binding in &bindings {
-            let u32_binding = convert_string_binding_to_u32(binding, &dict);
-
-            if !evaluate_filters(&u32_binding, &rule.filters, &dict) {
-                continue;
-            }
-
-            // Conjunction of positive premise tags (⊗).
-            let pos_triples = resolve_premise_triples(&rule.premise, &u32_binding);
-            let pos_tag = pos_triples
-                .iter()
-                .map(|t| tag_store.get_tag(t))
-                .fold(provenance.one(), |acc, tag| provenance.conjunction(&acc, &tag));
-
-            if pos_tag == provenance.zero() {
-                continue;
-            }
-
-            // Conjunction of NAF contributions for each negated atom (⊗).
-            let mut neg_tag = provenance.one();
-            for neg_pat in &rule.negative_premise {
-                let s = resolve_term(&neg_pat.0, &u32_binding);
-                let p = resolve_term(&neg_pat.1, &u32_binding);
-                let o = resolve_term(&neg_pat.2, &u32_binding);
-
-                let contrib = match (s, p, o) {
-                    (Some(s), Some(p), Some(o)) => {
-                        let neg_triple = Triple { subject: s, predicate: p, object: o };
-                        if all_facts_set.contains(&neg_triple) {
-                            // Fact present -> negate its provenance tag.
-                            provenance.negate(&tag_store.get_tag(&neg_triple))
-                        } else {
-                            // Fact absent -> NOT absent = certainly true = one().
-                            provenance.one()
-                        }
-                    }
-                    // Unbound variable in negated atom — safety check should have
-                    // caught this; treat as zero (cannot fire).
-                    _ => provenance.zero(),
-                };
-
-                neg_tag = provenance.conjunction(&neg_tag, &contrib);
-                if neg_tag == provenance.zero() {
-                    break;
-                }
-            }
-
-            let conclusion_tag = provenance.conjunction(&pos_tag, &neg_tag);
-            if conclusion_tag == provenance.zero() {
-                continue;
-            }
-
-            // Derive conclusions.
-            for conclusion in &rule.conclusion {
-                let inferred =
-                    replace_variables_with_bound_values(conclusion, &u32_binding, &mut dict);
-
-                if !all_facts_set.contains(&inferred) && !new_derived.contains(&inferred) {
-                    tag_store.set_tag(&inferred, conclusion_tag.clone());
-                    reasoner.index_manager.insert(&inferred);
-                    new_derived.push(inferred);
-                } else {
-                    tag_store.update_disjunction(&inferred, &conclusion_tag);
-                }
-            }
-        }
-    }
-
-    drop(dict);
-    new_derived
-}
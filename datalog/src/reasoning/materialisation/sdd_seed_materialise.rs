@@ -71,5 +71,5 @@ pub fn infer_new_facts_with_sdd_seed_specs(
         }
     }
 
-    semi_naive_with_initial_tags(reasoner, provenance, initial_tags)
+    semi_naive_with_initial_tags(reasoner, initial_tags)
 }
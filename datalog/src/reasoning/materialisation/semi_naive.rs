@@ -1,14 +1,33 @@
 use shared::dictionary::Dictionary;
+use shared::index_manager::UnifiedIndex;
 use shared::rule::Rule;
+use shared::terms::Term;
 use shared::triple::Triple;
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use crate::reasoning::{convert_string_binding_to_u32, Reasoner};
 use crate::reasoning::materialisation::infer_generic::{SolutionMapping, InferenceStrategy};
-use crate::reasoning::materialisation::replace_variables_with_bound_values;
-use crate::reasoning::rules::{evaluate_filters, join_premise_with_hash_join};
+use crate::reasoning::chase::{instantiate_conclusions, ChaseConfig};
+use crate::reasoning::explanation::Derivation;
+use crate::reasoning::rules::{complete_solution, join_premise_with_hash_join, negated_premises_absent};
 
 struct SemiNaiveStrategy {
     start_idx_for_delta: usize,
+    chase: ChaseConfig,
+    /// Rule applications of the current round, if they are recorded
+    derivations: Option<Vec<(Triple, Derivation)>>,
+}
+
+/// Facts matching the premises of a rule for a solution of its body
+fn premise_facts(rule: &Rule, solution: &HashMap<String, u32>) -> Option<Vec<Triple>> {
+    let ground = |term: &Term| match term {
+        Term::Constant(c) => Some(*c),
+        Term::Variable(v) => solution.get(v).copied(),
+        Term::QuotedTriple(_) => None,
+    };
+    rule.premise
+        .iter()
+        .map(|(s, p, o)| Some(Triple { subject: ground(s)?, predicate: ground(p)?, object: ground(o)? }))
+        .collect()
 }
 
 impl SemiNaiveStrategy {
@@ -50,30 +69,41 @@ impl SemiNaiveStrategy {
 
 impl InferenceStrategy for SemiNaiveStrategy {
 
-    fn infer_round(&mut self, dictionary: &mut Dictionary, rules: &Vec<Rule>, all_facts: &Vec<Triple>, known_facts: &HashSet<Triple>) -> HashSet<Triple> {
+    fn infer_round(&mut self, dictionary: &mut Dictionary, rules: &Vec<Rule>, all_facts: &Vec<Triple>, known_facts: &HashSet<Triple>, index: &UnifiedIndex) -> HashSet<Triple> {
             // Also HashSet to prevent duplicate triples from being added this round
             let mut inferred_facts_this_round: HashSet<Triple> = HashSet::new();
+            // The same facts, for the restricted chase to look up
+            let mut inferred_index = UnifiedIndex::new();
 
             let end_idx_for_delta = all_facts.len();
             let delta_facts = &all_facts[self.start_idx_for_delta..end_idx_for_delta]; // Take derived facts from last round and use as delta
             self.start_idx_for_delta = end_idx_for_delta; // Update the pointer for the next round
 
             // Loop over each rule
-            for rule in rules {
+            for (rule_idx, rule) in rules.iter().enumerate() {
                 // These are all bindings such that the premise is satisfied for the given rule
                 let binding_sets = self.find_premise_solutions(dictionary, rule, all_facts, delta_facts);
 
                 // For each binding that satisfies the premises of the rule, get to the conclusion and apply bindings
-                for binding_set in &binding_sets {
-                    if evaluate_filters(&binding_set, &rule.filters, dictionary) {
+                for mut binding_set in binding_sets {
+                    if complete_solution(&mut binding_set, rule, dictionary)
+                        && negated_premises_absent(&binding_set, &rule.negative_premise, known_facts)
+                    {
                         // Loop over each conclusion of the rule, since for the current binding,
                         // the conclusions of the rule can be inferred (because premises are met)
-                        for conclusion in &rule.conclusion {
-                            
-                            let inferred_fact =
-                                replace_variables_with_bound_values(conclusion, binding_set, dictionary);
-
+                        let facts = [index, &inferred_index];
+                        let inferred = instantiate_conclusions(rule, &binding_set, &facts, &self.chase, dictionary);
+                        if let Some(derivations) = &mut self.derivations {
+                            if let Some(premises) = premise_facts(rule, &binding_set) {
+                                for fact in &inferred {
+                                    let derivation = Derivation { rule: rule_idx, premises: premises.clone() };
+                                    derivations.push((fact.clone(), derivation));
+                                }
+                            }
+                        }
+                        for inferred_fact in inferred {
                             if !known_facts.contains(&inferred_fact) {
+                                inferred_index.insert(&inferred_fact);
                                 inferred_facts_this_round.insert(inferred_fact);
                             }
                         }
@@ -83,10 +113,26 @@ impl InferenceStrategy for SemiNaiveStrategy {
 
             inferred_facts_this_round
     }
+
+    fn start_stratum(&mut self) {
+        // Every fact is new to the rules of a stratum
+        self.start_idx_for_delta = 0;
+    }
+
+    fn take_derivations(&mut self) -> Vec<(Triple, Derivation)> {
+        self.derivations.as_mut().map(std::mem::take).unwrap_or_default()
+    }
 }
 
 impl Reasoner {
+    /// Materialises all facts the rules derive. With `record_derivations`
+    /// set, the rule applications deriving each fact replace `derivations`;
+    /// see `explain`.
     pub fn infer_new_facts_semi_naive(&mut self) -> Vec<Triple> {
-        self.infer_with_strategy(SemiNaiveStrategy { start_idx_for_delta: 0 })
+        let derivations = self.record_derivations.then(Vec::new);
+        if self.record_derivations {
+            self.derivations.clear();
+        }
+        self.infer_with_strategy(SemiNaiveStrategy { start_idx_for_delta: 0, chase: self.chase, derivations })
     }
 }
@@ -4,15 +4,29 @@ use shared::triple::Triple;
 use rayon::prelude::*;
 use crate::reasoning::materialisation::replace_variables_with_bound_values;
 use crate::reasoning::Reasoner;
-use crate::reasoning::rules::matches_rule_pattern;
+use crate::reasoning::rules::{matches_rule_pattern, negated_premises_absent};
+use crate::reasoning::stratification::evaluation_layers;
 
 impl Reasoner {
 
     pub fn infer_new_facts_semi_naive_parallel(&mut self) -> Vec<Triple> {
+        // Computed values and invented blank nodes are encoded as they are
+        // derived, which the parallel rounds cannot do
+        let encodes = |rule: &shared::rule::Rule| {
+            !rule.bindings.is_empty() || !rule.aggregates.is_empty() || !rule.existential_variables().is_empty()
+        };
+        if self.rules.iter().any(encodes) {
+            return self.infer_new_facts_semi_naive();
+        }
+
         // Collect all known facts
         let all_initial = self.index_manager.query(None, None, None);
         let mut all_facts: HashSet<Triple> = all_initial.into_iter().collect();
 
+        // Evaluate the strata bottom-up, each to fixpoint
+        let mut layers = evaluation_layers(&self.rules).into_iter();
+        let mut in_stratum: HashSet<usize> = layers.next().unwrap_or_default().into_iter().collect();
+
         // Delta = all the initial facts
         let mut delta = all_facts.clone();
 
@@ -37,6 +51,9 @@ impl Reasoner {
                             None,
                         );
                         for &rule_id in candidate_rule_ids.iter() {
+                            if !in_stratum.contains(&rule_id) {
+                                continue;
+                            }
                             let rule = &self.rules[rule_id];
                             match rule.premise.len() {
                                 1 => {
@@ -46,6 +63,10 @@ impl Reasoner {
                                         &rule.premise[0],
                                         triple1,
                                         &mut variable_bindings,
+                                    ) && negated_premises_absent(
+                                        &variable_bindings,
+                                        &rule.negative_premise,
+                                        &all_facts_arc,
                                     ) {
                                         // Process each conclusion
                                         for conclusion in &rule.conclusion {
@@ -79,6 +100,10 @@ impl Reasoner {
                                                     &rule.premise[1],
                                                     triple2,
                                                     &mut variable_bindings_2,
+                                                ) && negated_premises_absent(
+                                                    &variable_bindings_2,
+                                                    &rule.negative_premise,
+                                                    &all_facts_arc,
                                                 ) {
                                                     // Process each conclusion
                                                     rule.conclusion
@@ -120,6 +145,10 @@ impl Reasoner {
                                                     &rule.premise[0],
                                                     triple2,
                                                     &mut variable_bindings_2b,
+                                                ) && negated_premises_absent(
+                                                    &variable_bindings_2b,
+                                                    &rule.negative_premise,
+                                                    &all_facts_arc,
                                                 ) {
                                                     // Process each conclusion
                                                     rule.conclusion
@@ -160,14 +189,22 @@ impl Reasoner {
                     },
                 );
 
-            // If no new facts were found, we've reached a fixpoint
+            // If no new facts were found, the stratum has reached a fixpoint
             if new_facts.is_empty() {
-                break;
+                // The next stratum starts from all the facts known so far
+                match layers.next() {
+                    Some(layer) => {
+                        in_stratum = layer.into_iter().collect();
+                        delta = all_facts.clone();
+                    }
+                    None => break,
+                }
             } else {
                 for fact in new_facts.iter() {
                     all_facts.insert(fact.clone());
                     inferred_so_far.push(fact.clone());
                     self.index_manager.insert(fact);
+                    self.derived_facts.insert(fact.clone());
                 }
                 delta = new_facts;
             }
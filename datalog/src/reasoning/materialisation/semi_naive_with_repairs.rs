@@ -1,9 +1,12 @@
 use std::collections::HashSet;
 use shared::index_manager::UnifiedIndex;
+use shared::rule::Rule;
 use shared::triple::Triple;
-use crate::reasoning::materialisation::replace_variables_with_bound_values;
+use crate::reasoning::aggregation::aggregate_conclusions;
+use crate::reasoning::chase::instantiate_conclusions;
 use crate::reasoning::Reasoner;
-use crate::reasoning::rules::{evaluate_filters, join_rule};
+use crate::reasoning::rules::{complete_solution, join_rule, negated_premises_absent};
+use crate::reasoning::stratification::evaluation_strata;
 
 impl Reasoner {
 
@@ -25,48 +28,67 @@ impl Reasoner {
             }
         }
 
-        let mut delta = all_facts.clone();
         let mut inferred_so_far = Vec::new();
 
         let mut dict = self.dictionary.write().unwrap();
 
-        loop {
-            let mut new_delta = HashSet::new();
+        // Evaluate the strata bottom-up, each to fixpoint
+        for stratum in evaluation_strata(&self.rules) {
+            let (aggregate_rules, rules): (Vec<Rule>, Vec<Rule>) =
+                stratum.into_iter().partition(|rule| !rule.aggregates.is_empty());
+            let facts: Vec<Triple> = all_facts.iter().cloned().collect();
+            for inferred in aggregate_conclusions(&aggregate_rules, &facts, &all_facts, &mut dict) {
+                let mut temp_facts = all_facts.clone();
+                temp_facts.insert(inferred.clone());
+                if !self.violates_constraints(&temp_facts) && self.index_manager.insert(&inferred) {
+                    self.derived_facts.insert(inferred.clone());
+                    all_facts.insert(inferred.clone());
+                    inferred_so_far.push(inferred);
+                }
+            }
 
-            // Process each rule using the semi-naive approach
-            for rule in &self.rules {
-                let bindings = join_rule(rule, &all_facts, &delta);
-                for binding in bindings {
-                    if evaluate_filters(&binding, &rule.filters, &dict) {
-                        // Process each conclusion
-                        for conclusion in &rule.conclusion {
-                            let inferred =
-                                replace_variables_with_bound_values(conclusion, &binding, &mut dict);
+            let mut delta = all_facts.clone();
 
-                            // Check if adding this fact would cause inconsistency
-                            let mut temp_facts = all_facts.clone();
-                            temp_facts.insert(inferred.clone());
+            loop {
+                let mut new_delta = HashSet::new();
 
-                            if !self.violates_constraints(&temp_facts) {
-                                if self.index_manager.insert(&inferred)
-                                    && !all_facts.contains(&inferred)
-                                {
-                                    new_delta.insert(inferred.clone());
-                                    all_facts.insert(inferred.clone());
-                                    inferred_so_far.push(inferred);
+                // Process each rule using the semi-naive approach
+                for rule in &rules {
+                    let bindings = join_rule(rule, &all_facts, &delta);
+                    for mut binding in bindings {
+                        if complete_solution(&mut binding, rule, &mut dict)
+                            && negated_premises_absent(&binding, &rule.negative_premise, &all_facts)
+                        {
+                            // Process each conclusion
+                            let conclusions =
+                                instantiate_conclusions(rule, &binding, &[&self.index_manager], &self.chase, &mut dict);
+                            for inferred in conclusions {
+                                // Check if adding this fact would cause inconsistency
+                                let mut temp_facts = all_facts.clone();
+                                temp_facts.insert(inferred.clone());
+
+                                if !self.violates_constraints(&temp_facts) {
+                                    if self.index_manager.insert(&inferred)
+                                        && !all_facts.contains(&inferred)
+                                    {
+                                        self.derived_facts.insert(inferred.clone());
+                                        new_delta.insert(inferred.clone());
+                                        all_facts.insert(inferred.clone());
+                                        inferred_so_far.push(inferred);
+                                    }
                                 }
                             }
                         }
                     }
                 }
-            }
 
-            // Terminate when no new facts were inferred
-            if new_delta.is_empty() {
-                break;
-            }
+                // Terminate when no new facts were inferred
+                if new_delta.is_empty() {
+                    break;
+                }
 
-            delta = new_delta;
+                delta = new_delta;
+            }
         }
 
         inferred_so_far
@@ -1,45 +1,226 @@
-use std::collections::{HashMap, HashSet};
-use shared::terms::TriplePattern;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use shared::terms::{Term, TriplePattern};
 use shared::triple::Triple;
 use crate::reasoning::Reasoner;
-use crate::reasoning::rules::matches_rule_pattern;
+use crate::reasoning::rules::{complete_solution, join_rule, matches_rule_pattern, negated_premises_absent};
 
-impl Reasoner {
+/// Which answers count when the facts violate the constraints. A repair is a
+/// maximal subset of the facts that violates no constraint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepairSemantics {
+    /// Answers that hold in every repair
+    AR,
+    /// Answers that hold in the intersection of all repairs, i.e. follow
+    /// from facts that take part in no conflict
+    IAR,
+    /// Answers that hold in at least one repair
+    Brave,
+}
 
-    /// New method: Query with inconsistency-tolerant semantics
-    pub fn query_with_repairs(&self, query: &TriplePattern) -> Vec<HashMap<String, u32>> {
-        let all_facts: HashSet<Triple> = self
-            .index_manager
-            .query(None, None, None)
-            .into_iter()
-            .collect();
+/// A minimal set of facts that violates a constraint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    /// Index of the violated constraint in `Reasoner::constraints`
+    pub constraint: usize,
+    /// The facts, sorted
+    pub facts: Vec<Triple>,
+}
+
+/// Hypergraph over the facts with a hyperedge for every minimal inconsistent
+/// subset. Repairs are exactly its maximal independent sets, so queries can
+/// be answered from the conflicts without enumerating the repairs.
+#[derive(Debug, Clone, Default)]
+pub struct ConflictHypergraph {
+    pub conflicts: Vec<Conflict>,
+    by_fact: HashMap<Triple, Vec<usize>>,
+}
 
-        // Compute all repairs
-        let repairs = self.compute_repairs(&all_facts);
-
-        // IAR semantics: only return answers that are present in all repairs
-        let mut results = Vec::new();
-        if let Some(first_repair) = repairs.first() {
-            // Start with results from first repair
-            for fact in first_repair {
-                let mut vmap = HashMap::new();
-                if matches_rule_pattern(query, fact, &mut vmap) {
-                    results.push(vmap);
+impl ConflictHypergraph {
+    /// Keeps the conflicts that have no other conflict as a subset
+    fn from_violations(mut violations: Vec<Conflict>) -> Self {
+        violations.sort_by(|a, b| a.facts.len().cmp(&b.facts.len()).then_with(|| a.facts.cmp(&b.facts)));
+        let mut graph = ConflictHypergraph::default();
+        for violation in violations {
+            let contained = violation.facts.iter().any(|fact| {
+                graph.conflicts_of(fact).any(|conflict| conflict.facts.iter().all(|f| violation.facts.contains(f)))
+            });
+            if !contained {
+                for fact in &violation.facts {
+                    graph.by_fact.entry(fact.clone()).or_default().push(graph.conflicts.len());
                 }
+                graph.conflicts.push(violation);
             }
+        }
+        graph
+    }
+
+    /// Conflicts `fact` takes part in
+    pub fn conflicts_of<'a>(&'a self, fact: &Triple) -> impl Iterator<Item = &'a Conflict> + 'a {
+        self.by_fact.get(fact).into_iter().flatten().map(|&i| &self.conflicts[i])
+    }
+
+    /// Whether `fact` takes part in no conflict, and so belongs to every repair
+    pub fn is_free(&self, fact: &Triple) -> bool {
+        !self.by_fact.contains_key(fact)
+    }
+
+    /// Whether `facts` contain no conflict, i.e. are part of some repair
+    pub fn is_consistent(&self, facts: &HashSet<Triple>) -> bool {
+        facts.iter().all(|fact| {
+            self.conflicts_of(fact).all(|conflict| !conflict.facts.iter().all(|f| facts.contains(f)))
+        })
+    }
+
+    /// Whether some repair contains none of the supports. Such a repair
+    /// exists iff a consistent set of facts conflicts with a fact of every
+    /// support, which is searched for one support at a time.
+    fn some_repair_avoids(&self, supports: &[Vec<Triple>], chosen: &mut HashSet<Triple>) -> bool {
+        let Some((support, rest)) = supports.split_first() else {
+            return true;
+        };
+        for fact in support {
+            for conflict in self.conflicts_of(fact) {
+                let added: Vec<Triple> = conflict
+                    .facts
+                    .iter()
+                    .filter(|f| *f != fact && !chosen.contains(*f))
+                    .cloned()
+                    .collect();
+                chosen.extend(added.iter().cloned());
+                if self.is_consistent(chosen) && self.some_repair_avoids(rest, chosen) {
+                    return true;
+                }
+                for f in &added {
+                    chosen.remove(f);
+                }
+            }
+        }
+        false
+    }
+}
+
+/// Bound value of a term, if any
+fn ground(term: &Term, binding: &HashMap<String, u32>) -> Option<u32> {
+    match term {
+        Term::Constant(c) => Some(*c),
+        Term::Variable(v) => binding.get(v).copied(),
+        Term::QuotedTriple(_) => None,
+    }
+}
 
-            // Filter out results not present in all repairs
-            results.retain(|binding| {
-                repairs.iter().skip(1).all(|repair| {
-                    repair.iter().any(|fact| {
-                        let mut test_map = HashMap::new();
-                        matches_rule_pattern(query, fact, &mut test_map) && test_map == *binding
+impl Reasoner {
+    /// The minimal inconsistent subsets of the facts, for every constraint.
+    /// A constraint is violated by the facts matching its premises, for
+    /// solutions that pass its filters and whose negated premises are absent.
+    pub fn conflict_hypergraph(&self) -> ConflictHypergraph {
+        let facts: HashSet<Triple> = self.index_manager.query(None, None, None).into_iter().collect();
+        let mut dict = self.dictionary.write().unwrap();
+        let mut seen = HashSet::new();
+        let mut violations = Vec::new();
+        for (index, constraint) in self.constraints.iter().enumerate() {
+            for mut binding in join_rule(constraint, &facts, &facts) {
+                if !complete_solution(&mut binding, constraint, &mut dict)
+                    || !negated_premises_absent(&binding, &constraint.negative_premise, &facts)
+                {
+                    continue;
+                }
+                let grounded: Option<Vec<Triple>> = constraint
+                    .premise
+                    .iter()
+                    .map(|(s, p, o)| {
+                        Some(Triple {
+                            subject: ground(s, &binding)?,
+                            predicate: ground(p, &binding)?,
+                            object: ground(o, &binding)?,
+                        })
                     })
-                })
-            });
+                    .collect();
+                if let Some(mut conflict) = grounded {
+                    conflict.sort();
+                    conflict.dedup();
+                    if seen.insert(conflict.clone()) {
+                        violations.push(Conflict { constraint: index, facts: conflict });
+                    }
+                }
+            }
         }
+        ConflictHypergraph::from_violations(violations)
+    }
 
-        results
+    /// Lists the minimal sets of facts that violate a constraint, to find the
+    /// facts that make the data inconsistent
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        self.conflict_hypergraph().conflicts
     }
 
-}
\ No newline at end of file
+    /// Answers a conjunctive query over facts that may violate the
+    /// constraints, binding the `answer_variables` (with none, the single
+    /// empty answer means the query holds). The conflicts are computed once;
+    /// each answer is then checked against the sets of facts it follows from
+    /// (its supports): IAR needs a support without conflicts, brave a
+    /// consistent support, and AR a set of supports no repair avoids.
+    pub fn query_with_semantics(
+        &self,
+        query: &[TriplePattern],
+        answer_variables: &[&str],
+        semantics: RepairSemantics,
+    ) -> Vec<HashMap<String, u32>> {
+        let graph = self.conflict_hypergraph();
+
+        // Supports of every answer, keyed by a sortable copy of its binding
+        let mut answers: BTreeMap<BTreeMap<String, u32>, Vec<Vec<Triple>>> = BTreeMap::new();
+        let mut partial = vec![(HashMap::new(), Vec::new())];
+        for pattern in query {
+            let mut extended = Vec::new();
+            for (binding, support) in partial {
+                let candidates = self.index_manager.query(
+                    ground(&pattern.0, &binding),
+                    ground(&pattern.1, &binding),
+                    ground(&pattern.2, &binding),
+                );
+                for fact in candidates {
+                    let mut binding = binding.clone();
+                    if matches_rule_pattern(pattern, &fact, &mut binding) {
+                        let mut support = support.clone();
+                        support.push(fact);
+                        extended.push((binding, support));
+                    }
+                }
+            }
+            partial = extended;
+        }
+        for (binding, support) in partial {
+            let answer = binding.into_iter().filter(|(var, _)| answer_variables.contains(&var.as_str())).collect();
+            answers.entry(answer).or_default().push(support);
+        }
+
+        answers
+            .into_iter()
+            .filter(|(_, supports)| {
+                let consistent = supports.iter().filter(|support| graph.is_consistent(&support.iter().cloned().collect()));
+                match semantics {
+                    RepairSemantics::IAR => supports.iter().any(|support| support.iter().all(|f| graph.is_free(f))),
+                    RepairSemantics::Brave => consistent.count() > 0,
+                    RepairSemantics::AR => {
+                        let consistent: Vec<Vec<Triple>> = consistent.cloned().collect();
+                        !consistent.is_empty() && !graph.some_repair_avoids(&consistent, &mut HashSet::new())
+                    }
+                }
+            })
+            .map(|(binding, _)| binding.into_iter().collect())
+            .collect()
+    }
+
+    /// Answers a single triple pattern under IAR semantics: only facts that
+    /// are in every repair are matched
+    pub fn query_with_repairs(&self, query: &TriplePattern) -> Vec<HashMap<String, u32>> {
+        let variables: Vec<&str> = [&query.0, &query.1, &query.2]
+            .into_iter()
+            .filter_map(|term| match term {
+                Term::Variable(v) => Some(v.as_str()),
+                _ => None,
+            })
+            .collect();
+        self.query_with_semantics(std::slice::from_ref(query), &variables, RepairSemantics::IAR)
+    }
+}
@@ -1,9 +1,10 @@
-use crate::reasoning::Reasoner;
+use crate::reasoning::{convert_string_binding_to_u32, Reasoner};
 use shared::dictionary::Dictionary;
 use shared::join_algorithm::perform_hash_join_for_rules;
-use shared::rule::{FilterCondition, Rule};
+use shared::rule::{numeric_value, FilterCondition, Rule, RuleBinding};
 use shared::terms::{Term, TriplePattern};
 use shared::triple::Triple;
+use std::collections::btree_map::Entry;
 use std::collections::{BTreeMap, HashMap, HashSet};
 
 pub fn matches_rule_pattern(
@@ -137,16 +138,41 @@ pub fn evaluate_filters(
 ) -> bool {
     for filter in filters {
         if let Some(&lhs_code) = bindings.get(&filter.variable) {
-            // If the filter value is itself a bound variable, compare by dictionary ID
+            // If the filter value is itself a bound variable, compare by dictionary ID,
+            // or by numeric value for an ordering
             if let Some(&rhs_code) = bindings.get(&filter.value) {
+                let number = |code: u32| dict.decode(code).and_then(numeric_value);
                 match filter.operator.as_str() {
                     "!=" if lhs_code == rhs_code => return false,
                     "=" if lhs_code != rhs_code => return false,
+                    ">" | "<" | ">=" | "<=" => {
+                        let (Some(lhs), Some(rhs)) = (number(lhs_code), number(rhs_code)) else {
+                            return false;
+                        };
+                        let holds = match filter.operator.as_str() {
+                            ">" => lhs > rhs,
+                            "<" => lhs < rhs,
+                            ">=" => lhs >= rhs,
+                            _ => lhs <= rhs,
+                        };
+                        if !holds {
+                            return false;
+                        }
+                    }
                     _ => {}
                 }
             } else {
-                // Compare bound variable against a numeric constant
                 let value_str = dict.decode(lhs_code).unwrap_or("");
+                // Values that are not both numbers are equal when they are the same term
+                if numeric_value(value_str).is_none() || filter.value.parse::<f64>().is_err() {
+                    match filter.operator.as_str() {
+                        "=" if value_str != filter.value => return false,
+                        "!=" if value_str == filter.value => return false,
+                        "=" | "!=" => continue,
+                        _ => {}
+                    }
+                }
+                // Compare bound variable against a numeric constant
                 let bound_num: f64 = value_str.parse().unwrap_or(0.0);
                 let filter_num: f64 = filter.value.parse().unwrap_or(0.0);
                 match filter.operator.as_str() {
@@ -164,12 +190,38 @@ pub fn evaluate_filters(
     true
 }
 
+/// Whether none of the negated premises, grounded by `bindings`, is among
+/// `facts`. Safe rules bind every variable of a negated premise; a premise
+/// that cannot be grounded never matches.
+pub fn negated_premises_absent(
+    bindings: &HashMap<String, u32>,
+    negative_premise: &[TriplePattern],
+    facts: &HashSet<Triple>,
+) -> bool {
+    let ground = |term: &Term| match term {
+        Term::Constant(c) => Some(*c),
+        Term::Variable(v) => bindings.get(v).copied(),
+        Term::QuotedTriple(_) => None,
+    };
+    negative_premise.iter().all(|pattern| {
+        match (ground(&pattern.0), ground(&pattern.1), ground(&pattern.2)) {
+            (Some(subject), Some(predicate), Some(object)) => {
+                !facts.contains(&Triple { subject, predicate, object })
+            }
+            _ => true,
+        }
+    })
+}
+
 pub fn join_premise_with_hash_join(
     premise: &TriplePattern,
     all_facts: &[Triple],
     current_bindings: Vec<BTreeMap<String, String>>,
     dict: &Dictionary
 ) -> Vec<BTreeMap<String, String>> {
+    if let Term::Variable(_) = premise.1 {
+        return join_premise_with_variable_predicate(premise, all_facts, current_bindings, dict);
+    }
     perform_hash_join_for_rules(
         premise,
         all_facts,
@@ -179,22 +231,123 @@ pub fn join_premise_with_hash_join(
     )
 }
 
+/// Nested-loop join for premises with a variable predicate, which the hash
+/// join does not support. Facts are grouped by predicate so bindings that
+/// already bind the predicate only scan the facts using it.
+fn join_premise_with_variable_predicate(
+    premise: &TriplePattern,
+    all_facts: &[Triple],
+    current_bindings: Vec<BTreeMap<String, String>>,
+    dict: &Dictionary,
+) -> Vec<BTreeMap<String, String>> {
+    let Term::Variable(predicate_var) = &premise.1 else {
+        return Vec::new();
+    };
+    let mut by_predicate: HashMap<u32, Vec<&Triple>> = HashMap::new();
+    for fact in all_facts {
+        by_predicate.entry(fact.predicate).or_default().push(fact);
+    }
+    let every_fact: Vec<&Triple> = all_facts.iter().collect();
+
+    let mut results = Vec::new();
+    for binding in current_bindings {
+        let ids = convert_string_binding_to_u32(&binding, dict);
+        // A value missing from the dictionary matches no fact
+        let unmatchable = [&premise.0, &premise.1, &premise.2].into_iter().any(|term| {
+            matches!(term, Term::Variable(v) if binding.contains_key(v) && !ids.contains_key(v))
+        });
+        if unmatchable {
+            continue;
+        }
+        let candidates = match ids.get(predicate_var) {
+            Some(predicate) => by_predicate.get(predicate).map_or(&[][..], Vec::as_slice),
+            None => every_fact.as_slice(),
+        };
+        for fact in candidates {
+            let mut extended_ids = ids.clone();
+            if !matches_rule_pattern(premise, fact, &mut extended_ids) {
+                continue;
+            }
+            let mut extended = binding.clone();
+            for (var, id) in extended_ids {
+                if let (Entry::Vacant(entry), Some(value)) = (extended.entry(var), dict.decode(id)) {
+                    entry.insert(value.to_string());
+                }
+            }
+            results.push(extended);
+        }
+    }
+    results
+}
+
+/// Extends a body solution with the computed variables of `bindings`, in
+/// order, encoding their values; returns `false` when one cannot be computed
+/// or differs from the value the variable already has.
+pub fn compute_bindings<'a>(
+    solution: &mut HashMap<String, u32>,
+    bindings: impl IntoIterator<Item = &'a RuleBinding>,
+    dict: &mut Dictionary,
+) -> bool {
+    for binding in bindings {
+        let value = binding.expression.evaluate(&|var: &str| {
+            solution.get(var).and_then(|&id| dict.decode(id)).map(str::to_string)
+        });
+        match value {
+            Some(value) => {
+                let id = dict.encode(&value);
+                if *solution.entry(binding.variable.clone()).or_insert(id) != id {
+                    return false;
+                }
+            }
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Computes the variables of a body solution of a rule without aggregates
+/// and checks its filters, which may use them
+pub fn complete_solution(solution: &mut HashMap<String, u32>, rule: &Rule, dict: &mut Dictionary) -> bool {
+    compute_bindings(solution, &rule.bindings, dict) && evaluate_filters(solution, &rule.filters, dict)
+}
+
 impl Reasoner {
     /// Add a dynamic rule to the graph.
     ///
     /// Panics if the rule has unsafe negation (a variable in `negative_premise`
-    /// that is not bound by `premise`). For a non-panicking version use [`try_add_rule`].
+    /// that is not bound by `premise`) or makes the rules non-stratifiable.
+    /// For a non-panicking version use [`try_add_rule`].
     ///
     /// [`try_add_rule`]: Self::try_add_rule
     pub fn add_rule(&mut self, rule: Rule) {
         self.try_add_rule(rule).expect("rule safety check failed");
     }
 
-    /// Add a dynamic rule to the graph, returning `Err` if it violates safety.
+    /// Add a dynamic rule to the graph, returning `Err` if it violates safety
+    /// or stratification.
     ///
-    /// Safety requirement: every variable in `negative_premise` must appear in `premise`.
+    /// Safety requirement: every variable in `negative_premise` must appear in `premise`,
+    /// and computed variables and aggregates may only use bound variables.
+    /// Stratification requirement: no fact may depend negatively on itself,
+    /// i.e. a negated premise must not match a conclusion derived (directly or
+    /// recursively) from the rule's own conclusion. The same holds for the
+    /// premises of rules with aggregates.
+    /// Termination requirement: rules with existential variables must be
+    /// weakly acyclic, unless `chase.max_depth` bounds the chase.
     pub fn try_add_rule(&mut self, rule: Rule) -> Result<(), String> {
         shared::rule::check_rule_safety(&rule)?;
+        let non_monotonic = |r: &Rule| !r.negative_premise.is_empty() || !r.aggregates.is_empty();
+        if non_monotonic(&rule) || self.rules.iter().any(non_monotonic) {
+            let mut program = self.rules.clone();
+            program.push(rule.clone());
+            crate::reasoning::stratification::rule_strata(&program)?;
+        }
+        let existential = |r: &Rule| !r.existential_variables().is_empty();
+        if self.chase.max_depth.is_none() && (existential(&rule) || self.rules.iter().any(existential)) {
+            let mut program = self.rules.clone();
+            program.push(rule.clone());
+            shared::rule::check_weak_acyclicity(&program)?;
+        }
         let rule_id = self.rules.len();
         self.rules.push(rule.clone());
         for prem in &rule.premise {
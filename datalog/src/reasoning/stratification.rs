@@ -0,0 +1,88 @@
+use shared::rule::Rule;
+use shared::terms::{Term, TriplePattern};
+
+/// Whether a fact could match both patterns, ignoring repeated variables
+fn patterns_overlap(a: &TriplePattern, b: &TriplePattern) -> bool {
+    let compatible = |x: &Term, y: &Term| match (x, y) {
+        (Term::Constant(x), Term::Constant(y)) => x == y,
+        _ => true,
+    };
+    compatible(&a.0, &b.0) && compatible(&a.1, &b.1) && compatible(&a.2, &b.2)
+}
+
+/// Stratum of every rule, from the dependency graph between rule heads and
+/// rule bodies. This is the predicate dependency graph, refined to whole
+/// patterns so rules with a variable predicate are handled too.
+///
+/// A rule is in a stratum at least as high as the rules deriving facts its
+/// premises match, and strictly higher than the rules deriving facts its
+/// negated premises match. Rules with aggregates are strictly higher than the
+/// rules deriving facts any of their premises match. Returns `Err` when
+/// negation or aggregation occurs through recursion, i.e. the rules cannot
+/// be stratified.
+pub fn rule_strata(rules: &[Rule]) -> Result<Vec<usize>, String> {
+    // (from, to, strict): rule `to` uses facts derived by rule `from`
+    let mut dependencies = Vec::new();
+    for (from, producer) in rules.iter().enumerate() {
+        for (to, consumer) in rules.iter().enumerate() {
+            let derives = |patterns: &[TriplePattern]| {
+                producer
+                    .conclusion
+                    .iter()
+                    .any(|head| patterns.iter().any(|body| patterns_overlap(head, body)))
+            };
+            if derives(&consumer.negative_premise) {
+                dependencies.push((from, to, true));
+            } else if derives(&consumer.premise) {
+                dependencies.push((from, to, !consumer.aggregates.is_empty()));
+            }
+        }
+    }
+
+    let mut strata = vec![0; rules.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &(from, to, strict) in &dependencies {
+            let needed = strata[from] + strict as usize;
+            if strata[to] < needed {
+                if needed >= rules.len() {
+                    return Err(format!(
+                        "rules cannot be stratified: rule {} depends on itself through negation or aggregation",
+                        to
+                    ));
+                }
+                strata[to] = needed;
+                changed = true;
+            }
+        }
+    }
+    Ok(strata)
+}
+
+/// Indices of the rules in each stratum, lowest stratum first
+pub fn stratify(rules: &[Rule]) -> Result<Vec<Vec<usize>>, String> {
+    let strata = rule_strata(rules)?;
+    let levels = strata.iter().max().map_or(0, |top| top + 1);
+    let mut layers = vec![Vec::new(); levels];
+    for (id, stratum) in strata.into_iter().enumerate() {
+        layers[stratum].push(id);
+    }
+    Ok(layers)
+}
+
+/// Indices of the rules in each stratum the materialisation strategies
+/// evaluate bottom-up. `try_add_rule` rejects programs that cannot be
+/// stratified, so only rules pushed to `Reasoner::rules` directly can end up
+/// here as a single stratum.
+pub(crate) fn evaluation_layers(rules: &[Rule]) -> Vec<Vec<usize>> {
+    stratify(rules).unwrap_or_else(|_| vec![(0..rules.len()).collect()])
+}
+
+/// Rules of each stratum, as in `evaluation_layers`
+pub(crate) fn evaluation_strata(rules: &[Rule]) -> Vec<Vec<Rule>> {
+    evaluation_layers(rules)
+        .into_iter()
+        .map(|ids| ids.into_iter().map(|id| rules[id].clone()).collect())
+        .collect()
+}
@@ -1,7 +1,9 @@
 use shared::dictionary::Dictionary;
+use shared::quoted_triple_store::QuotedTripleStore;
 use shared::terms::{Term, TriplePattern};
 use shared::triple::Triple;
 use std::collections::HashSet;
+use crate::reasoning::explanation::ProofTree;
 use crate::reasoning::Reasoner;
 
 /// Contains a method to convert a specific data structure into a representation in the DOT language
@@ -112,3 +114,40 @@ impl ToDot for Reasoner {
         out
     }
 }
+
+fn add_proof_tree(
+    tree: &ProofTree,
+    dictionary: &Dictionary,
+    qt_store: &QuotedTripleStore,
+    out: &mut String,
+    next_id: &mut usize,
+) -> usize {
+    let id = *next_id;
+    *next_id += 1;
+    let label = [tree.fact.subject, tree.fact.predicate, tree.fact.object]
+        .map(|c| dictionary.decode_term(c, qt_store).unwrap_or_else(|| format!("#{}", c)))
+        .join(" ");
+    out.push_str(&format!("Fact{} [label=\"{}\"]\n", id, label.replace('"', "\\\"")));
+    if let Some(rule) = tree.rule {
+        out.push_str(&format!("Apply{} [label=\"Rule{}\", shape=box]\n", id, rule));
+        out.push_str(&format!("Apply{0} -> Fact{0}\n", id));
+        for premise in &tree.premises {
+            let premise_id = add_proof_tree(premise, dictionary, qt_store, out, next_id);
+            out.push_str(&format!("Fact{} -> Apply{}\n", premise_id, id));
+        }
+    }
+    id
+}
+
+/// Outputs a proof tree from `Reasoner::explain`: facts, with edges from the
+/// premises of each rule application (a box named after the rule) to the fact
+/// it derives. Quoted triples in the facts are decoded with `qt_store`; ids
+/// unknown to the dictionary are shown as `#id`.
+pub fn proof_tree_to_dot(tree: &ProofTree, kg: &Reasoner, qt_store: &QuotedTripleStore) -> String {
+    let dict = kg.dictionary.read().unwrap();
+    let mut out = String::new();
+    out.push_str("digraph {\n");
+    add_proof_tree(tree, &dict, qt_store, &mut out, &mut 0);
+    out.push('}');
+    out
+}
@@ -1,9 +1,18 @@
+use datalog::parser_n3_logic::{parse_n3_document, parse_n3_rule};
+use datalog::reasoning::chase::{check_weak_acyclicity, ChaseConfig, ChaseVariant};
+use datalog::reasoning::entailment::EntailmentRegime;
+use datalog::reasoning::explanation::ProofTree;
+use datalog::reasoning::repairs::{Conflict, RepairSemantics};
+use datalog::reasoning::to_dot::proof_tree_to_dot;
 use datalog::reasoning::Reasoner;
-use shared::rule::{FilterCondition, Rule};
+use shared::rule::{AggregateFunction, FilterCondition, Rule, RuleAggregate, RuleBinding, RuleExpression};
 use shared::terms::Term;
 use shared::provenance::{AddMultProbability, MinMaxProbability, BooleanProvenance, Provenance};
 use shared::provenance::{TopKProofs, WmcProvenance};
+use shared::quoted_triple_store::QuotedTripleStore;
+use shared::triple::Triple;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 fn enc(r: &Reasoner, s: &str) -> u32 {
     r.dictionary.write().unwrap().encode(s)
@@ -13,6 +22,8 @@ fn rule(premises: Vec<(Term, Term, Term)>, conclusions: Vec<(Term, Term, Term)>)
     Rule {
         premise: premises,
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: conclusions,
         filters: vec![],
     }
@@ -26,6 +37,8 @@ fn naf_rule(
     Rule {
         premise: premises,
         negative_premise: neg_premises,
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: conclusions,
         filters: vec![],
     }
@@ -134,6 +147,8 @@ fn fc_join_sibling() {
             (Term::Variable("Y".into()), Term::Constant(parent), Term::Variable("P2".into())),
         ],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![
             (Term::Variable("X".into()), Term::Constant(sibling), Term::Variable("Y".into())),
         ],
@@ -237,6 +252,8 @@ fn fc_sibling_three_children() {
             (Term::Variable("Y".into()), Term::Constant(parent), Term::Variable("Z".into())),
         ],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![
             (Term::Variable("X".into()), Term::Constant(sibling), Term::Variable("Y".into())),
         ],
@@ -396,6 +413,8 @@ fn fc_uncle_derived() {
             (Term::Variable("Y".into()), Term::Constant(parent), Term::Variable("Z".into())),
         ],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![
             (Term::Variable("X".into()), Term::Constant(sibling), Term::Variable("Y".into())),
         ],
@@ -422,6 +441,100 @@ fn fc_uncle_derived() {
     assert!(!inferred(&mut r, "A", "uncle", "C"), "A (parent) should not also be uncle of C");
 }
 
+#[test]
+fn fc_filter_compares_non_numeric_values_as_terms() {
+    let mut r = Reasoner::new();
+    r.add_abox_triple("alice", "status", "active");
+    r.add_abox_triple("bob", "status", "inactive");
+
+    let status = enc(&r, "status");
+    let type_ = enc(&r, "type");
+    let (active, inactive) = (enc(&r, "Active"), enc(&r, "Inactive"));
+
+    // Strings are not numbers, so they must not all compare equal as 0
+    for (operator, class) in [("=", active), ("!=", inactive)] {
+        r.add_rule(Rule {
+            premise: vec![(Term::Variable("X".into()), Term::Constant(status), Term::Variable("S".into()))],
+            negative_premise: vec![],
+            bindings: vec![],
+            aggregates: vec![],
+            conclusion: vec![(Term::Variable("X".into()), Term::Constant(type_), Term::Constant(class))],
+            filters: vec![
+                FilterCondition { variable: "S".into(), operator: operator.into(), value: "active".into() },
+            ],
+        });
+    }
+
+    r.infer_new_facts_semi_naive();
+
+    assert!(inferred(&mut r, "alice", "type", "Active"));
+    assert!(!inferred(&mut r, "bob", "type", "Active"));
+    assert!(inferred(&mut r, "bob", "type", "Inactive"));
+    assert!(!inferred(&mut r, "alice", "type", "Inactive"));
+}
+
+#[test]
+fn fc_filter_orders_two_bound_variables_by_value() {
+    let mut r = Reasoner::new();
+    r.add_abox_triple("alice", "age", "30");
+    r.add_abox_triple("bob", "age", "25");
+    r.add_abox_triple("carol", "age", "unknown");
+
+    let age = enc(&r, "age");
+    let older = enc(&r, "olderThan");
+
+    // olderThan(X, Y) :- age(X, A), age(Y, B), A > B
+    r.add_rule(Rule {
+        premise: vec![
+            (Term::Variable("X".into()), Term::Constant(age), Term::Variable("A".into())),
+            (Term::Variable("Y".into()), Term::Constant(age), Term::Variable("B".into())),
+        ],
+        negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
+        conclusion: vec![(Term::Variable("X".into()), Term::Constant(older), Term::Variable("Y".into()))],
+        filters: vec![
+            FilterCondition { variable: "A".into(), operator: ">".into(), value: "B".into() },
+        ],
+    });
+
+    r.infer_new_facts_semi_naive();
+
+    assert!(inferred(&mut r, "alice", "olderThan", "bob"));
+    // Only pairs of numbers are ordered
+    assert_eq!(r.query_abox(None, Some("olderThan"), None).len(), 1);
+}
+
+#[test]
+fn fc_constant_subject_and_object_restrict_premise_matches() {
+    let mut r = Reasoner::new();
+    r.add_abox_triple("alice", "knows", "bob");
+    r.add_abox_triple("carol", "knows", "dave");
+    r.add_abox_triple("erin", "knows", "carol");
+
+    let (alice, carol) = (enc(&r, "alice"), enc(&r, "carol"));
+    let knows = enc(&r, "knows");
+    let (contact, known_by_carol) = (enc(&r, "contactOfAlice"), enc(&r, "knowsCarol"));
+
+    // contactOfAlice(Y) :- knows(alice, Y)
+    r.add_rule(rule(
+        vec![(Term::Constant(alice), Term::Constant(knows), Term::Variable("Y".into()))],
+        vec![(Term::Variable("Y".into()), Term::Constant(contact), Term::Constant(alice))],
+    ));
+    // knowsCarol(X) :- knows(X, carol)
+    r.add_rule(rule(
+        vec![(Term::Variable("X".into()), Term::Constant(knows), Term::Constant(carol))],
+        vec![(Term::Variable("X".into()), Term::Constant(known_by_carol), Term::Constant(carol))],
+    ));
+
+    r.infer_new_facts_semi_naive();
+
+    assert_eq!(r.query_abox(None, Some("contactOfAlice"), None).len(), 1);
+    assert!(inferred(&mut r, "bob", "contactOfAlice", "alice"));
+    assert_eq!(r.query_abox(None, Some("knowsCarol"), None).len(), 1);
+    assert!(inferred(&mut r, "erin", "knowsCarol", "carol"));
+}
+
 // Backward chaining
 
 #[test]
@@ -758,6 +871,8 @@ fn prov_minmax_conjunction() {
             (Term::Variable("Y".into()), Term::Constant(trusts), Term::Variable("Z".into())),
         ],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         filters: vec![],
         conclusion: vec![
             (Term::Variable("X".into()), Term::Constant(recommends), Term::Variable("Z".into())),
@@ -1346,6 +1461,8 @@ fn unsafe_negation_rejected() {
             // ?y is unbound — not in any positive premise
             (Term::Variable("y".into()), Term::Constant(blocked), Term::Constant(yes)),
         ],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![
             (Term::Variable("x".into()), Term::Constant(effective), Term::Constant(yes)),
         ],
@@ -1416,3 +1533,1334 @@ fn wmc_naf_shared_seed_integration() {
     assert!((prob - 0.0).abs() < 1e-9,
         "WMC NAF shared seed: expected 0.0 (p ∧ ¬p = ⊥), got {}", prob);
 }
+
+// ─── Incremental maintenance (DRed) ─────────────────────────────────────────
+
+fn var(name: &str) -> Term {
+    Term::Variable(name.into())
+}
+
+fn fact(r: &Reasoner, s: &str, p: &str, o: &str) -> Triple {
+    Triple { subject: enc(r, s), predicate: enc(r, p), object: enc(r, o) }
+}
+
+fn all_facts(r: &Reasoner) -> Vec<Triple> {
+    let mut facts = r.index_manager.query(None, None, None);
+    facts.sort();
+    facts
+}
+
+/// Materialisation of `explicit` under the rules of `r`, computed from scratch.
+fn from_scratch(r: &Reasoner, explicit: &[Triple]) -> Vec<Triple> {
+    let mut fresh = Reasoner::new();
+    fresh.dictionary = Arc::clone(&r.dictionary);
+    fresh.rules = r.rules.clone();
+    fresh.update_materialization(explicit, &[]).unwrap();
+    all_facts(&fresh)
+}
+
+fn ancestor_rules(r: &mut Reasoner) {
+    let parent = enc(r, "parent");
+    let ancestor = enc(r, "ancestor");
+    r.add_rule(rule(
+        vec![(var("X"), Term::Constant(parent), var("Y"))],
+        vec![(var("X"), Term::Constant(ancestor), var("Y"))],
+    ));
+    r.add_rule(rule(
+        vec![
+            (var("X"), Term::Constant(parent), var("Y")),
+            (var("Y"), Term::Constant(ancestor), var("Z")),
+        ],
+        vec![(var("X"), Term::Constant(ancestor), var("Z"))],
+    ));
+}
+
+#[test]
+fn dred_deletion_retracts_consequences() {
+    let mut r = Reasoner::new();
+    for (s, o) in [("A", "B"), ("B", "C"), ("C", "D")] {
+        r.add_abox_triple(s, "parent", o);
+    }
+    ancestor_rules(&mut r);
+    r.infer_new_facts_semi_naive();
+    assert!(inferred(&mut r, "A", "ancestor", "D"));
+
+    let edge = fact(&r, "B", "parent", "C");
+    let delta = r.update_materialization(&[], &[edge]).unwrap();
+
+    // parent(B,C) and ancestor(A,C), (A,D), (B,C), (B,D)
+    assert_eq!(delta.removed.len(), 5);
+    assert!(delta.added.is_empty());
+    assert!(inferred(&mut r, "A", "ancestor", "B"));
+    assert!(inferred(&mut r, "C", "ancestor", "D"));
+    assert!(!inferred(&mut r, "A", "ancestor", "D"));
+    let explicit = [fact(&r, "A", "parent", "B"), fact(&r, "C", "parent", "D")];
+    assert_eq!(all_facts(&r), from_scratch(&r, &explicit));
+}
+
+#[test]
+fn dred_keeps_facts_with_alternative_derivations() {
+    let mut r = Reasoner::new();
+    for (s, o) in [("A", "B"), ("B", "D"), ("A", "C"), ("C", "D")] {
+        r.add_abox_triple(s, "parent", o);
+    }
+    ancestor_rules(&mut r);
+    r.infer_new_facts_semi_naive();
+
+    let edge = fact(&r, "A", "parent", "B");
+    let delta = r.update_materialization(&[], &[edge]).unwrap();
+    assert!(inferred(&mut r, "A", "ancestor", "D"));
+    assert!(!inferred(&mut r, "A", "ancestor", "B"));
+    assert_eq!(delta.removed, {
+        let mut removed = vec![fact(&r, "A", "parent", "B"), fact(&r, "A", "ancestor", "B")];
+        removed.sort();
+        removed
+    });
+
+    // Deleting a derived fact does nothing, inserting one makes it explicit
+    let derived = fact(&r, "A", "ancestor", "D");
+    assert!(!r.is_explicit(&derived));
+    assert_eq!(r.update_materialization(&[], &[derived.clone()]).unwrap(), Default::default());
+    r.update_materialization(&[derived.clone()], &[]).unwrap();
+    assert!(r.is_explicit(&derived));
+    let edge = fact(&r, "A", "parent", "C");
+    r.update_materialization(&[], &[edge]).unwrap();
+    assert!(inferred(&mut r, "A", "ancestor", "D"));
+}
+
+#[test]
+fn dred_insertions_and_deletions_flip_negated_premises() {
+    let mut r = Reasoner::new();
+    let (ty, person, banned, yes, allowed) =
+        (enc(&r, "type"), enc(&r, "Person"), enc(&r, "banned"), enc(&r, "yes"), enc(&r, "allowed"));
+    r.add_rule(naf_rule(
+        vec![(var("X"), Term::Constant(ty), Term::Constant(person))],
+        vec![(var("X"), Term::Constant(banned), Term::Constant(yes))],
+        vec![(var("X"), Term::Constant(allowed), Term::Constant(yes))],
+    ));
+    let people = [fact(&r, "ann", "type", "Person"), fact(&r, "bob", "type", "Person")];
+    r.update_materialization(&people, &[]).unwrap();
+    assert!(inferred(&mut r, "ann", "allowed", "yes"));
+
+    let ban = fact(&r, "ann", "banned", "yes");
+    let delta = r.update_materialization(&[ban.clone()], &[]).unwrap();
+    assert_eq!(delta.removed, vec![fact(&r, "ann", "allowed", "yes")]);
+    assert!(!inferred(&mut r, "ann", "allowed", "yes"));
+    assert!(inferred(&mut r, "bob", "allowed", "yes"));
+
+    let delta = r.update_materialization(&[], &[ban]).unwrap();
+    assert!(delta.added.contains(&fact(&r, "ann", "allowed", "yes")));
+    assert!(inferred(&mut r, "ann", "allowed", "yes"));
+}
+
+#[test]
+fn dred_matches_recomputation_over_sliding_windows() {
+    let mut r = Reasoner::new();
+    ancestor_rules(&mut r);
+    let (parent, ancestor, above, cyclic) =
+        (enc(&r, "parent"), enc(&r, "ancestor"), enc(&r, "above"), enc(&r, "cyclic"));
+    // Three strata, each negating a predicate derived by the one below
+    r.add_rule(naf_rule(
+        vec![(var("X"), Term::Constant(ancestor), var("Y"))],
+        vec![(var("Y"), Term::Constant(ancestor), var("X"))],
+        vec![(var("X"), Term::Constant(above), var("Y"))],
+    ));
+    r.add_rule(naf_rule(
+        vec![(var("X"), Term::Constant(ancestor), var("Y"))],
+        vec![(var("X"), Term::Constant(above), var("Y"))],
+        vec![(var("X"), Term::Constant(cyclic), var("Y"))],
+    ));
+
+    let nodes: Vec<u32> = (0..8).map(|n| enc(&r, &format!("n{}", n))).collect();
+    for mut seed in [7u64, 11, 23] {
+        let mut next = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) as usize
+        };
+        let edges: Vec<Triple> = (0..40)
+            .map(|_| Triple { subject: nodes[next() % 8], predicate: parent, object: nodes[next() % 8] })
+            .collect();
+
+        // A window of 6 edges sliding by 2
+        let mut window: Vec<Triple> = Vec::new();
+        for start in (0..=edges.len() - 6).step_by(2) {
+            let current: Vec<Triple> = edges[start..start + 6].to_vec();
+            let expired: Vec<Triple> = window.iter().filter(|e| !current.contains(e)).cloned().collect();
+            let arrived: Vec<Triple> = current.iter().filter(|e| !window.contains(e)).cloned().collect();
+            r.update_materialization(&arrived, &expired).unwrap();
+            window = current;
+            assert_eq!(all_facts(&r), from_scratch(&r, &window), "window starting at {}", start);
+        }
+        let expired = std::mem::take(&mut window);
+        r.update_materialization(&[], &expired).unwrap();
+        assert!(all_facts(&r).is_empty());
+    }
+}
+
+#[test]
+fn dred_rejects_negation_through_recursion() {
+    let mut r = Reasoner::new();
+    let (p, q) = (enc(&r, "p"), enc(&r, "q"));
+    // Bypasses the stratification check of try_add_rule
+    r.rules.push(naf_rule(
+        vec![(var("X"), Term::Constant(p), var("Y"))],
+        vec![(var("X"), Term::Constant(q), var("Y"))],
+        vec![(var("X"), Term::Constant(q), var("Y"))],
+    ));
+    assert!(r.update_materialization(&[], &[]).is_err());
+}
+
+// ─── Stratified negation ────────────────────────────────────────────────────
+
+/// Three strata: reachability, then acyclic nodes (NOT reach(X, X)), then
+/// nodes that are not acyclic. The rules are added top stratum first.
+fn cycle_reasoner() -> Reasoner {
+    let mut r = Reasoner::new();
+    for (s, o) in [("a", "b"), ("b", "c"), ("c", "b")] {
+        r.add_abox_triple(s, "edge", o);
+    }
+    for n in ["a", "b", "c", "d"] {
+        r.add_abox_triple(n, "node", "yes");
+    }
+    let (edge, reach, node, yes, acyclic, flagged) = (
+        enc(&r, "edge"),
+        enc(&r, "reach"),
+        enc(&r, "node"),
+        enc(&r, "yes"),
+        enc(&r, "acyclic"),
+        enc(&r, "flagged"),
+    );
+    r.add_rule(naf_rule(
+        vec![(var("X"), Term::Constant(node), Term::Constant(yes))],
+        vec![(var("X"), Term::Constant(acyclic), Term::Constant(yes))],
+        vec![(var("X"), Term::Constant(flagged), Term::Constant(yes))],
+    ));
+    r.add_rule(naf_rule(
+        vec![(var("X"), Term::Constant(node), Term::Constant(yes))],
+        vec![(var("X"), Term::Constant(reach), var("X"))],
+        vec![(var("X"), Term::Constant(acyclic), Term::Constant(yes))],
+    ));
+    r.add_rule(rule(
+        vec![
+            (var("X"), Term::Constant(edge), var("Y")),
+            (var("Y"), Term::Constant(reach), var("Z")),
+        ],
+        vec![(var("X"), Term::Constant(reach), var("Z"))],
+    ));
+    r.add_rule(rule(
+        vec![(var("X"), Term::Constant(edge), var("Y"))],
+        vec![(var("X"), Term::Constant(reach), var("Y"))],
+    ));
+    r
+}
+
+#[test]
+fn stratified_negation_agrees_across_strategies() {
+    let strategies: Vec<(&str, fn(&mut Reasoner))> = vec![
+        ("naive", |r| { r.infer_new_facts_naive(); }),
+        ("semi-naive", |r| { r.infer_new_facts_semi_naive(); }),
+        ("parallel", |r| { r.infer_new_facts_semi_naive_parallel(); }),
+        ("repairs", |r| { r.infer_new_facts_semi_naive_with_repairs(); }),
+        ("provenance", |r| { r.infer_new_facts_with_provenance(BooleanProvenance); }),
+    ];
+    for (name, infer) in strategies {
+        let mut r = cycle_reasoner();
+        infer(&mut r);
+        for n in ["a", "d"] {
+            assert!(inferred(&mut r, n, "acyclic", "yes"), "{}: {} is acyclic", name, n);
+            assert!(!inferred(&mut r, n, "flagged", "yes"), "{}: {} is not flagged", name, n);
+        }
+        for n in ["b", "c"] {
+            assert!(!inferred(&mut r, n, "acyclic", "yes"), "{}: {} is on a cycle", name, n);
+            assert!(inferred(&mut r, n, "flagged", "yes"), "{}: {} is flagged", name, n);
+        }
+
+        let mut incremental = cycle_reasoner();
+        let explicit = all_facts(&incremental);
+        incremental.index_manager = shared::index_manager::UnifiedIndex::new();
+        incremental.update_materialization(&explicit, &[]).unwrap();
+        assert_eq!(all_facts(&r), all_facts(&incremental), "{} matches DRed", name);
+    }
+}
+
+#[test]
+fn provenance_tags_flow_through_strata() {
+    let mut r = Reasoner::new();
+    r.add_tagged_triple("P", "active", "yes", 0.8);
+    r.add_tagged_triple("P", "risky", "yes", 0.5);
+    let (active, risky, blocked, effective, yes) = (
+        enc(&r, "active"),
+        enc(&r, "risky"),
+        enc(&r, "blocked"),
+        enc(&r, "effective"),
+        enc(&r, "yes"),
+    );
+    // The negated predicate is itself derived, by a rule added afterwards
+    r.add_rule(naf_rule(
+        vec![(var("x"), Term::Constant(active), Term::Constant(yes))],
+        vec![(var("x"), Term::Constant(blocked), Term::Constant(yes))],
+        vec![(var("x"), Term::Constant(effective), Term::Constant(yes))],
+    ));
+    r.add_rule(rule(
+        vec![(var("x"), Term::Constant(risky), Term::Constant(yes))],
+        vec![(var("x"), Term::Constant(blocked), Term::Constant(yes))],
+    ));
+
+    let (_, tag_store) = r.infer_new_facts_with_provenance(AddMultProbability);
+    let prob = tag_store.get_tag(&fact(&r, "P", "effective", "yes"));
+    // P(effective) = P(active) * (1 - P(blocked)) = 0.8 * 0.5
+    assert!((prob - 0.4).abs() < 1e-9, "expected 0.4, got {}", prob);
+}
+
+#[test]
+fn try_add_rule_rejects_non_stratifiable_programs() {
+    let mut r = Reasoner::new();
+    let (edge, win, p, q, s) = (enc(&r, "move"), enc(&r, "win"), enc(&r, "p"), enc(&r, "q"), enc(&r, "s"));
+
+    // win(X) :- move(X, Y), NOT win(Y)
+    let err = r
+        .try_add_rule(naf_rule(
+            vec![(var("X"), Term::Constant(edge), var("Y"))],
+            vec![(var("Y"), Term::Constant(win), Term::Constant(win))],
+            vec![(var("X"), Term::Constant(win), Term::Constant(win))],
+        ))
+        .unwrap_err();
+    assert!(err.contains("cannot be stratified"), "{}", err);
+    assert!(r.rules.is_empty());
+
+    // Negation through a cycle of two rules is caught when it closes
+    r.add_rule(rule(
+        vec![(var("X"), Term::Constant(q), var("Y"))],
+        vec![(var("X"), Term::Constant(p), var("Y"))],
+    ));
+    let err = r
+        .try_add_rule(naf_rule(
+            vec![(var("X"), Term::Constant(s), var("Y"))],
+            vec![(var("X"), Term::Constant(p), var("Y"))],
+            vec![(var("X"), Term::Constant(q), var("Y"))],
+        ))
+        .unwrap_err();
+    assert!(err.contains("cannot be stratified"), "{}", err);
+    assert_eq!(r.rules.len(), 1);
+
+    // Negating a lower stratum is fine
+    r.try_add_rule(naf_rule(
+        vec![(var("X"), Term::Constant(s), var("Y"))],
+        vec![(var("X"), Term::Constant(p), var("Y"))],
+        vec![(var("X"), Term::Constant(win), var("Y"))],
+    ))
+    .unwrap();
+}
+
+// ─── Entailment regimes ─────────────────────────────────────────────────────
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const SUB_CLASS_OF: &str = "http://www.w3.org/2000/01/rdf-schema#subClassOf";
+const OWL_NS: &str = "http://www.w3.org/2002/07/owl#";
+
+fn owl(name: &str) -> String {
+    format!("{}{}", OWL_NS, name)
+}
+
+#[test]
+fn rdfs_entails_types_from_classes_properties_domains_and_ranges() {
+    let mut r = Reasoner::new();
+    r.add_abox_triple("Student", SUB_CLASS_OF, "Person");
+    r.add_abox_triple("Person", SUB_CLASS_OF, "Agent");
+    r.add_abox_triple("enrolledIn", "http://www.w3.org/2000/01/rdf-schema#subPropertyOf", "memberOf");
+    r.add_abox_triple("enrolledIn", "http://www.w3.org/2000/01/rdf-schema#domain", "Student");
+    r.add_abox_triple("memberOf", "http://www.w3.org/2000/01/rdf-schema#range", "Organisation");
+    r.add_abox_triple("alice", "enrolledIn", "kuleuven");
+    r.add_abox_triple("alice", owl("sameAs").as_str(), "ali");
+
+    let inferred_facts = r.infer_new_facts_with_entailment(EntailmentRegime::Rdfs);
+    assert!(inferred(&mut r, "Student", SUB_CLASS_OF, "Agent"));
+    assert!(inferred(&mut r, "alice", "memberOf", "kuleuven"));
+    for class in ["Student", "Person", "Agent"] {
+        assert!(inferred(&mut r, "alice", RDF_TYPE, class), "alice is a {}", class);
+    }
+    assert!(inferred(&mut r, "kuleuven", RDF_TYPE, "Organisation"));
+    assert!(inferred_facts.contains(&fact(&r, "alice", RDF_TYPE, "Agent")));
+    // RDFS does not interpret owl:sameAs
+    assert!(!inferred(&mut r, "ali", RDF_TYPE, "Student"));
+    assert!(r.derived_facts.contains(&fact(&r, "alice", RDF_TYPE, "Agent")));
+    assert!(!r.derived_facts.contains(&fact(&r, "alice", "enrolledIn", "kuleuven")));
+}
+
+#[test]
+fn rdfs_plus_interprets_property_characteristics() {
+    let mut r = Reasoner::new();
+    r.add_abox_triple("parentOf", owl("inverseOf").as_str(), "childOf");
+    r.add_abox_triple("knows", RDF_TYPE, owl("SymmetricProperty").as_str());
+    r.add_abox_triple("ancestorOf", RDF_TYPE, owl("TransitiveProperty").as_str());
+    r.add_abox_triple("Human", owl("equivalentClass").as_str(), "Person");
+    r.add_abox_triple("ann", "parentOf", "bob");
+    r.add_abox_triple("ann", "knows", "carl");
+    r.add_abox_triple("ann", "ancestorOf", "bob");
+    r.add_abox_triple("bob", "ancestorOf", "dan");
+    r.add_abox_triple("ann", RDF_TYPE, "Human");
+
+    r.infer_new_facts_with_entailment(EntailmentRegime::RdfsPlus);
+    assert!(inferred(&mut r, "bob", "childOf", "ann"));
+    assert!(inferred(&mut r, "carl", "knows", "ann"));
+    assert!(inferred(&mut r, "ann", "ancestorOf", "dan"));
+    assert!(inferred(&mut r, "ann", RDF_TYPE, "Person"));
+}
+
+#[test]
+fn same_as_merges_equality_classes() {
+    let mut r = Reasoner::new();
+    let same_as = owl("sameAs");
+    r.add_abox_triple("email", RDF_TYPE, owl("InverseFunctionalProperty").as_str());
+    r.add_abox_triple("Student", SUB_CLASS_OF, "Person");
+    r.add_abox_triple("a", "email", "a@example.org");
+    r.add_abox_triple("b", "email", "a@example.org");
+    r.add_abox_triple("b", RDF_TYPE, "Student");
+    r.add_abox_triple("b", &same_as, "c");
+    r.add_abox_triple("c", "livesIn", "leuven");
+    // Equal properties share their facts as well
+    r.add_abox_triple("livesIn", &same_as, "residesIn");
+
+    r.infer_new_facts_with_entailment(EntailmentRegime::RdfsPlus);
+    for x in ["a", "b", "c"] {
+        assert!(inferred(&mut r, x, RDF_TYPE, "Person"), "{} is a Person", x);
+        assert!(inferred(&mut r, x, "residesIn", "leuven"), "{} resides in leuven", x);
+        for y in ["a", "b", "c"] {
+            assert!(inferred(&mut r, x, &same_as, y), "{} sameAs {}", x, y);
+        }
+    }
+    // Explicit facts survive canonicalisation, unrelated terms are not merged
+    assert!(r.is_explicit(&fact(&r, "b", &same_as, "c")));
+    assert!(!inferred(&mut r, "a@example.org", &same_as, "leuven"));
+    assert!(!inferred(&mut r, "leuven", RDF_TYPE, "Person"));
+}
+
+#[test]
+fn owl2rl_interprets_restrictions() {
+    let mut r = Reasoner::new();
+    let (on_property, has_value) = (owl("onProperty"), owl("hasValue"));
+    // Belgian ≡ citizenOf value belgium
+    r.add_abox_triple("Belgian", &on_property, "citizenOf");
+    r.add_abox_triple("Belgian", &has_value, "belgium");
+    // Parent ⊒ hasChild some Person
+    r.add_abox_triple("Parent", &on_property, "hasChild");
+    r.add_abox_triple("Parent", owl("someValuesFrom").as_str(), "Person");
+    // Vegan ⊑ eats only Plant
+    r.add_abox_triple("Vegan", &on_property, "eats");
+    r.add_abox_triple("Vegan", owl("allValuesFrom").as_str(), "Plant");
+
+    r.add_abox_triple("ann", "citizenOf", "belgium");
+    r.add_abox_triple("bob", RDF_TYPE, "Belgian");
+    r.add_abox_triple("ann", "hasChild", "bob");
+    r.add_abox_triple("bob", RDF_TYPE, "Person");
+    r.add_abox_triple("ann", RDF_TYPE, "Vegan");
+    r.add_abox_triple("ann", "eats", "kale");
+
+    r.infer_new_facts_with_entailment(EntailmentRegime::Owl2Rl);
+    assert!(inferred(&mut r, "ann", RDF_TYPE, "Belgian"));
+    assert!(inferred(&mut r, "bob", "citizenOf", "belgium"));
+    assert!(inferred(&mut r, "ann", RDF_TYPE, "Parent"));
+    assert!(inferred(&mut r, "kale", RDF_TYPE, "Plant"));
+
+    // None of them is a restriction in RDFS-Plus
+    let mut plus = Reasoner::new();
+    plus.dictionary = Arc::clone(&r.dictionary);
+    for t in r.index_manager.query(None, None, None).into_iter().filter(|t| r.is_explicit(t)) {
+        plus.index_manager.insert(&t);
+    }
+    plus.infer_new_facts_with_entailment(EntailmentRegime::RdfsPlus);
+    assert!(!inferred(&mut plus, "ann", RDF_TYPE, "Belgian"));
+    assert!(!inferred(&mut plus, "kale", RDF_TYPE, "Plant"));
+}
+
+#[test]
+fn entailment_regimes_parse_from_names() {
+    assert_eq!("rdfs".parse::<EntailmentRegime>(), Ok(EntailmentRegime::Rdfs));
+    assert_eq!("RDFS-Plus".parse::<EntailmentRegime>(), Ok(EntailmentRegime::RdfsPlus));
+    assert_eq!("owl2_rl".parse::<EntailmentRegime>(), Ok(EntailmentRegime::Owl2Rl));
+    assert!("owl-full".parse::<EntailmentRegime>().is_err());
+}
+
+// ─── Tabling and magic sets ─────────────────────────────────────────────────
+
+/// Left-recursive ancestor rules over a chain longer than the depth limit of
+/// `backward_chaining`, closed into a cycle, plus a disconnected chain
+fn left_recursive_reasoner() -> Reasoner {
+    let mut r = Reasoner::new();
+    for i in 0..15 {
+        r.add_abox_triple(&format!("n{}", i), "parent", &format!("n{}", i + 1));
+    }
+    r.add_abox_triple("n15", "parent", "n10");
+    for i in 0..5 {
+        r.add_abox_triple(&format!("m{}", i), "parent", &format!("m{}", i + 1));
+    }
+    let parent = enc(&r, "parent");
+    let ancestor = enc(&r, "ancestor");
+    r.add_rule(rule(
+        vec![(var("X"), Term::Constant(parent), var("Y"))],
+        vec![(var("X"), Term::Constant(ancestor), var("Y"))],
+    ));
+    r.add_rule(rule(
+        vec![
+            (var("X"), Term::Constant(ancestor), var("Y")),
+            (var("Y"), Term::Constant(parent), var("Z")),
+        ],
+        vec![(var("X"), Term::Constant(ancestor), var("Z"))],
+    ));
+    r
+}
+
+fn sorted_answers(results: Vec<HashMap<String, Term>>) -> Vec<Vec<(String, Term)>> {
+    let mut answers: Vec<Vec<(String, Term)>> = results
+        .into_iter()
+        .map(|binding| {
+            let mut pairs: Vec<_> = binding.into_iter().collect();
+            pairs.sort();
+            pairs
+        })
+        .collect();
+    answers.sort();
+    answers.dedup();
+    answers
+}
+
+#[test]
+fn tabled_backward_chaining_terminates_on_left_recursion() {
+    let r = left_recursive_reasoner();
+    let ancestor = enc(&r, "ancestor");
+    let query = (Term::Constant(enc(&r, "n0")), Term::Constant(ancestor), var("Y"));
+    let results = r.backward_chaining_tabled(&query);
+
+    // n1..n15, each once
+    assert_eq!(results.len(), 15);
+    assert!(bc_has(&results, "Y", enc(&r, "n15")));
+    assert!(!bc_has(&results, "Y", enc(&r, "n0")));
+    assert!(!bc_has(&results, "Y", enc(&r, "m1")));
+
+    // Nodes on the cycle are their own ancestors
+    let query = (Term::Constant(enc(&r, "n12")), Term::Constant(ancestor), Term::Constant(enc(&r, "n12")));
+    assert_eq!(r.backward_chaining_tabled(&query).len(), 1);
+}
+
+#[test]
+fn tabling_and_magic_sets_agree_with_materialisation() {
+    let r = left_recursive_reasoner();
+    let ancestor = enc(&r, "ancestor");
+    let mut full = left_recursive_reasoner();
+    full.infer_new_facts_semi_naive();
+
+    let goals = vec![
+        (Term::Constant(enc(&r, "n3")), Term::Constant(ancestor), var("Y")),
+        (var("X"), Term::Constant(ancestor), Term::Constant(enc(&r, "m4"))),
+        (Term::Constant(enc(&r, "n0")), Term::Constant(ancestor), Term::Constant(enc(&r, "n11"))),
+        (var("X"), Term::Constant(ancestor), var("Y")),
+        (var("X"), Term::Constant(ancestor), var("X")),
+    ];
+    for goal in goals {
+        let expected = sorted_answers(full.backward_chaining_tabled(&goal));
+        assert!(!expected.is_empty(), "{:?} has answers", goal);
+        assert_eq!(sorted_answers(r.backward_chaining_tabled(&goal)), expected, "tabling: {:?}", goal);
+        assert_eq!(sorted_answers(r.query_with_magic_sets(&goal)), expected, "magic sets: {:?}", goal);
+    }
+    // Answering a query leaves the reasoner unchanged
+    assert!(r.derived_facts.is_empty());
+}
+
+#[test]
+fn magic_sets_only_derive_relevant_facts() {
+    let r = left_recursive_reasoner();
+    let ancestor = enc(&r, "ancestor");
+    let goal = (Term::Constant(enc(&r, "m2")), Term::Constant(ancestor), var("Y"));
+    let program = {
+        let mut dict = r.dictionary.write().unwrap();
+        datalog::reasoning::magic_sets::magic_sets_rewrite(&r.rules, &goal, &mut dict).unwrap()
+    };
+
+    let mut magic = Reasoner::new();
+    magic.dictionary = Arc::clone(&r.dictionary);
+    magic.index_manager = r.index_manager.clone();
+    magic.rules = program.rules;
+    magic.index_manager.insert(&program.seed);
+    magic.infer_new_facts_semi_naive();
+
+    let derived: Vec<Triple> = magic.index_manager.query(None, Some(ancestor), None);
+    // Only m2 is called: m2 ancestor m3, m4, m5
+    assert_eq!(derived.len(), 3);
+    assert!(derived.iter().all(|t| t.subject == enc(&r, "m2")));
+}
+
+#[test]
+fn tabled_negation_sees_complete_subgoals() {
+    let r = cycle_reasoner();
+    let (acyclic, flagged, yes) = (enc(&r, "acyclic"), enc(&r, "flagged"), enc(&r, "yes"));
+    for (node, on_cycle) in [("a", false), ("b", true), ("c", true), ("d", false)] {
+        let n = enc(&r, node);
+        let is_acyclic = (Term::Constant(n), Term::Constant(acyclic), Term::Constant(yes));
+        let is_flagged = (Term::Constant(n), Term::Constant(flagged), Term::Constant(yes));
+        assert_eq!(r.backward_chaining_tabled(&is_acyclic).is_empty(), on_cycle, "{} acyclic", node);
+        assert_eq!(r.backward_chaining_tabled(&is_flagged).is_empty(), !on_cycle, "{} flagged", node);
+    }
+    // Negation is not rewritten, so the whole program is materialised
+    let query = (var("X"), Term::Constant(flagged), Term::Constant(yes));
+    assert_eq!(r.query_with_magic_sets(&query).len(), 2);
+}
+
+// ─── Aggregates and computed variables ──────────────────────────────────────
+
+fn aggregate(function: AggregateFunction, argument: &str, result: &str) -> RuleAggregate {
+    RuleAggregate { function, argument: argument.into(), result: result.into() }
+}
+
+fn filter(variable: &str, operator: &str, value: &str) -> FilterCondition {
+    FilterCondition { variable: variable.into(), operator: operator.into(), value: value.into() }
+}
+
+/// s1 raises 4 alarms and s2 raises 2; a sensor with more than 3 alarms is
+/// faulty, and every sensor gets its alarm count.
+fn alarm_reasoner() -> Reasoner {
+    let mut r = Reasoner::new();
+    for (alarm, sensor) in [("a1", "s1"), ("a2", "s1"), ("a3", "s1"), ("a4", "s1"), ("a5", "s2"), ("a6", "s2")] {
+        r.add_abox_triple(alarm, "alarmOf", sensor);
+    }
+    let (alarm_of, status, faulty, alarm_count) =
+        (enc(&r, "alarmOf"), enc(&r, "status"), enc(&r, "faulty"), enc(&r, "alarmCount"));
+    r.add_rule(Rule {
+        premise: vec![(var("A"), Term::Constant(alarm_of), var("S"))],
+        negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![aggregate(AggregateFunction::Count, "A", "N")],
+        filters: vec![filter("N", ">", "3")],
+        conclusion: vec![(var("S"), Term::Constant(status), Term::Constant(faulty))],
+    });
+    r.add_rule(Rule {
+        premise: vec![(var("A"), Term::Constant(alarm_of), var("S"))],
+        negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![aggregate(AggregateFunction::Count, "A", "N")],
+        filters: vec![],
+        conclusion: vec![(var("S"), Term::Constant(alarm_count), var("N"))],
+    });
+    r
+}
+
+#[test]
+fn count_aggregates_with_having_filter_agree_across_strategies() {
+    let strategies: Vec<(&str, fn(&mut Reasoner))> = vec![
+        ("naive", |r| { r.infer_new_facts_naive(); }),
+        ("semi-naive", |r| { r.infer_new_facts_semi_naive(); }),
+        ("parallel", |r| { r.infer_new_facts_semi_naive_parallel(); }),
+        ("repairs", |r| { r.infer_new_facts_semi_naive_with_repairs(); }),
+        ("provenance", |r| { r.infer_new_facts_with_provenance(BooleanProvenance); }),
+    ];
+    for (name, infer) in strategies {
+        let mut r = alarm_reasoner();
+        infer(&mut r);
+        assert!(inferred(&mut r, "s1", "status", "faulty"), "{}: s1 is faulty", name);
+        assert!(!inferred(&mut r, "s2", "status", "faulty"), "{}: s2 is not faulty", name);
+        assert!(inferred(&mut r, "s1", "alarmCount", "4"), "{}: s1 count", name);
+        assert!(inferred(&mut r, "s2", "alarmCount", "2"), "{}: s2 count", name);
+        assert_eq!(all_facts(&r).len(), 6 + 3, "{}: nothing else is derived", name);
+    }
+
+    let r = alarm_reasoner();
+    let status = enc(&r, "status");
+    let faulty_query = (var("S"), Term::Constant(status), Term::Constant(enc(&r, "faulty")));
+    let answers = r.backward_chaining_tabled(&faulty_query);
+    assert_eq!(answers.len(), 1);
+    assert!(bc_has(&answers, "S", enc(&r, "s1")));
+
+    let count_query = (Term::Constant(enc(&r, "s2")), Term::Constant(enc(&r, "alarmCount")), var("N"));
+    let answers = r.backward_chaining_tabled(&count_query);
+    assert_eq!(answers.len(), 1);
+    assert!(bc_has(&answers, "N", enc(&r, "2")));
+    let wrong_count = (Term::Constant(enc(&r, "s2")), Term::Constant(enc(&r, "alarmCount")), Term::Constant(enc(&r, "4")));
+    assert!(r.backward_chaining_tabled(&wrong_count).is_empty());
+}
+
+#[test]
+fn numeric_aggregates_group_by_conclusion_variables() {
+    let mut r = Reasoner::new();
+    for (reading, sensor, value) in [("r1", "s1", "3"), ("r2", "s1", "5"), ("r3", "s1", "10"), ("r4", "s2", "7")] {
+        r.add_abox_triple(reading, "readingOf", sensor);
+        r.add_abox_triple(reading, "value", value);
+    }
+    let (reading_of, value) = (enc(&r, "readingOf"), enc(&r, "value"));
+    let (total, lowest, highest, mean) = (enc(&r, "total"), enc(&r, "lowest"), enc(&r, "highest"), enc(&r, "mean"));
+    r.add_rule(Rule {
+        premise: vec![
+            (var("R"), Term::Constant(reading_of), var("S")),
+            (var("R"), Term::Constant(value), var("V")),
+        ],
+        negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![
+            aggregate(AggregateFunction::Sum, "V", "Total"),
+            aggregate(AggregateFunction::Min, "V", "Lowest"),
+            aggregate(AggregateFunction::Max, "V", "Highest"),
+            aggregate(AggregateFunction::Avg, "V", "Mean"),
+        ],
+        filters: vec![],
+        conclusion: vec![
+            (var("S"), Term::Constant(total), var("Total")),
+            (var("S"), Term::Constant(lowest), var("Lowest")),
+            (var("S"), Term::Constant(highest), var("Highest")),
+            (var("S"), Term::Constant(mean), var("Mean")),
+        ],
+    });
+    r.infer_new_facts_semi_naive();
+
+    for (p, o) in [("total", "18"), ("lowest", "3"), ("highest", "10"), ("mean", "6")] {
+        assert!(inferred(&mut r, "s1", p, o), "s1 {} {}", p, o);
+    }
+    for p in ["total", "lowest", "highest", "mean"] {
+        assert!(inferred(&mut r, "s2", p, "7"), "s2 {} 7", p);
+    }
+}
+
+#[test]
+fn computed_variables_derive_new_terms() {
+    let mut r = Reasoner::new();
+    r.add_abox_triple("o1", "net", "40");
+    r.add_abox_triple("o1", "tax", "2");
+    r.add_abox_triple("o1", "customer", "alice");
+    r.add_abox_triple("o2", "net", "unknown");
+    r.add_abox_triple("o2", "tax", "1");
+    let (net, tax, gross, customer, label) =
+        (enc(&r, "net"), enc(&r, "tax"), enc(&r, "gross"), enc(&r, "customer"), enc(&r, "label"));
+
+    // ?total = ?a + ?b; orders whose net price is not a number are skipped
+    r.add_rule(Rule {
+        premise: vec![(var("O"), Term::Constant(net), var("A")), (var("O"), Term::Constant(tax), var("B"))],
+        negative_premise: vec![],
+        bindings: vec![RuleBinding {
+            variable: "Total".into(),
+            expression: RuleExpression::Add(
+                Box::new(RuleExpression::Variable("A".into())),
+                Box::new(RuleExpression::Variable("B".into())),
+            ),
+        }],
+        aggregates: vec![],
+        filters: vec![],
+        conclusion: vec![(var("O"), Term::Constant(gross), var("Total"))],
+    });
+    r.add_rule(Rule {
+        premise: vec![(var("O"), Term::Constant(customer), var("C"))],
+        negative_premise: vec![],
+        bindings: vec![RuleBinding {
+            variable: "L".into(),
+            expression: RuleExpression::Function(
+                "CONCAT".into(),
+                vec![
+                    RuleExpression::Literal("order for ".into()),
+                    RuleExpression::Function("UCASE".into(), vec![RuleExpression::Variable("C".into())]),
+                ],
+            ),
+        }],
+        aggregates: vec![],
+        filters: vec![],
+        conclusion: vec![(var("O"), Term::Constant(label), var("L"))],
+    });
+
+    let query = (var("O"), Term::Constant(gross), var("Total"));
+    let tabled = r.backward_chaining_tabled(&query);
+    assert_eq!(tabled.len(), 1);
+    assert!(bc_has(&tabled, "Total", enc(&r, "42")));
+
+    r.infer_new_facts_semi_naive();
+    assert!(inferred(&mut r, "o1", "gross", "42"));
+    assert!(r.query_abox(Some("o2"), Some("gross"), None).is_empty());
+    assert!(inferred(&mut r, "o1", "label", "order for ALICE"));
+}
+
+#[test]
+fn aggregates_wait_for_the_facts_they_count() {
+    let mut r = Reasoner::new();
+    for (reading, value) in [("r1", "70"), ("r2", "80"), ("r3", "20")] {
+        r.add_abox_triple(reading, "readingOf", "s1");
+        r.add_abox_triple(reading, "value", value);
+    }
+    let (reading_of, value, alarm_of, alarm_count) =
+        (enc(&r, "readingOf"), enc(&r, "value"), enc(&r, "alarmOf"), enc(&r, "alarmCount"));
+    // Counting is added before the rule deriving what it counts
+    r.add_rule(Rule {
+        premise: vec![(var("A"), Term::Constant(alarm_of), var("S"))],
+        negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![aggregate(AggregateFunction::Count, "A", "N")],
+        filters: vec![],
+        conclusion: vec![(var("S"), Term::Constant(alarm_count), var("N"))],
+    });
+    r.add_rule(Rule {
+        premise: vec![
+            (var("R"), Term::Constant(reading_of), var("S")),
+            (var("R"), Term::Constant(value), var("V")),
+        ],
+        negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
+        filters: vec![filter("V", ">", "50")],
+        conclusion: vec![(var("R"), Term::Constant(alarm_of), var("S"))],
+    });
+
+    let explicit = all_facts(&r);
+    r.infer_new_facts_semi_naive();
+    assert!(inferred(&mut r, "s1", "alarmCount", "2"));
+    assert!(r.query_abox(Some("s1"), Some("alarmCount"), Some("1")).is_empty());
+    assert_eq!(all_facts(&r), from_scratch(&r, &explicit));
+
+    // Updates recompute the counts
+    let mut incremental = Reasoner::new();
+    incremental.dictionary = Arc::clone(&r.dictionary);
+    incremental.rules = r.rules.clone();
+    incremental.update_materialization(&explicit, &[]).unwrap();
+    let r4 = [fact(&r, "r4", "readingOf", "s1"), fact(&r, "r4", "value", "95")];
+    let delta = incremental.update_materialization(&r4, &[]).unwrap();
+    assert!(delta.added.contains(&fact(&r, "s1", "alarmCount", "3")));
+    assert!(delta.removed.contains(&fact(&r, "s1", "alarmCount", "2")));
+    let delta = incremental.update_materialization(&[], &r4).unwrap();
+    assert!(delta.added.contains(&fact(&r, "s1", "alarmCount", "2")));
+    assert!(delta.removed.contains(&fact(&r, "s1", "alarmCount", "3")));
+}
+
+#[test]
+fn unsafe_and_recursive_aggregates_are_rejected() {
+    let mut r = Reasoner::new();
+    let (edge, degree) = (enc(&r, "edge"), enc(&r, "degree"));
+    let counting = |argument: &str, conclusion_predicate: u32| Rule {
+        premise: vec![(var("X"), Term::Constant(edge), var("Y"))],
+        negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![aggregate(AggregateFunction::Count, argument, "N")],
+        filters: vec![],
+        conclusion: vec![(var("X"), Term::Constant(conclusion_predicate), var("N"))],
+    };
+
+    let err = r.try_add_rule(counting("Z", degree)).unwrap_err();
+    assert!(err.contains("unsafe aggregate"), "{}", err);
+    // The count of edges would be an edge itself
+    let err = r.try_add_rule(counting("Y", edge)).unwrap_err();
+    assert!(err.contains("cannot be stratified"), "{}", err);
+    assert!(r.rules.is_empty());
+
+    let err = r
+        .try_add_rule(Rule {
+            bindings: vec![RuleBinding { variable: "Y".into(), expression: RuleExpression::Literal("1".into()) }],
+            ..counting("Y", degree)
+        })
+        .unwrap_err();
+    assert!(err.contains("already bound"), "{}", err);
+    r.try_add_rule(counting("Y", degree)).unwrap();
+}
+
+// ─── N3 rules ───────────────────────────────────────────────────────────────
+
+fn ex(name: &str) -> String {
+    format!("http://example.org/{}", name)
+}
+
+#[test]
+fn n3_documents_load_facts_and_rules() {
+    let mut r = Reasoner::new();
+    let document = r
+        .load_n3(
+            r#"
+            @prefix ex: <http://example.org/> .
+            PREFIX rdfs: <http://www.w3.org/2000/01/rdf-schema#>
+            # Facts use `a`, `;` and `,`, full IRIs and literals
+            ex:alice a ex:Person ;
+                ex:knows ex:bob, <http://example.org/carol> ;
+                rdfs:label "Alice"@en, "A. \"Al\" Smith" ;
+                ex:born-in ex:gent.
+            ex:bob ex:age "42"^^<http://www.w3.org/2001/XMLSchema#integer> .
+
+            { ?x a ex:Person . ?x ex:knows ?y } => { ?y a ex:Person } .
+            { ?y ex:knownBy ?x } <= { ?x ex:knows ?y } .
+            { ?x ex:age ?a } <http://www.w3.org/2000/10/swap/log#implies> { ?x ex:hasAge ?a } .
+            "#,
+        )
+        .unwrap();
+    assert_eq!(document.prefixes["ex"], "http://example.org/");
+    assert_eq!(document.facts.len(), 7);
+    assert_eq!(document.rules.len(), 3);
+    let label = "http://www.w3.org/2000/01/rdf-schema#label";
+    assert!(inferred(&mut r, &ex("alice"), label, "Alice@en"));
+    assert!(inferred(&mut r, &ex("alice"), label, "A. \"Al\" Smith"));
+    assert!(inferred(&mut r, &ex("alice"), &ex("born-in"), &ex("gent")));
+
+    r.infer_new_facts_semi_naive();
+    let rdf_type = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+    assert!(inferred(&mut r, &ex("carol"), rdf_type, &ex("Person")));
+    assert!(inferred(&mut r, &ex("bob"), &ex("knownBy"), &ex("alice")));
+    assert!(inferred(&mut r, &ex("bob"), &ex("hasAge"), "42"));
+}
+
+#[test]
+fn n3_blank_nodes_are_constants_in_facts_and_variables_in_rules() {
+    let mut r = Reasoner::new();
+    r.load_n3(
+        r#"
+        @base <http://example.org/> .
+        @prefix : <http://example.org/> .
+        _:s1 :locatedIn [ :name "kitchen" ] .
+        <s2> :locatedIn [ :name "garage" ] .
+        { ?s :locatedIn [ :name "kitchen" ] } => { ?s :indoors true } .
+        { _:s :locatedIn _:room . _:room :name ?n } => { :rooms :include ?n } .
+        "#,
+    )
+    .unwrap();
+    r.infer_new_facts_semi_naive();
+
+    assert!(inferred(&mut r, "_:s1", &ex("indoors"), "true"));
+    assert!(r.query_abox(Some(&ex("s2")), Some(&ex("indoors")), None).is_empty());
+    for room in ["kitchen", "garage"] {
+        assert!(inferred(&mut r, &ex("rooms"), &ex("include"), room), "{}", room);
+    }
+}
+
+#[test]
+fn n3_builtins_compute_and_compare_values() {
+    let mut r = Reasoner::new();
+    r.load_n3(
+        r#"
+        @prefix : <http://example.org/> .
+        @prefix math: <http://www.w3.org/2000/10/swap/math#> .
+        @prefix string: <http://www.w3.org/2000/10/swap/string#> .
+        @prefix log: <http://www.w3.org/2000/10/swap/log#> .
+
+        :o1 :net 40 ; :tax 2 ; :customer "alice" .
+        :o2 :net 10 ; :tax 1 ; :customer "bob" ; :refunded true .
+        :o3 :net 7 ; :limit 9 .
+
+        {
+            ?o :net ?n ; :tax ?t .
+            (?n ?t) math:sum ?total .
+            ?total math:greaterThan 20 .
+            ?o log:notIncludes { ?o :refunded true } .
+        } => { ?o :gross ?total } .
+        { ?o :customer ?c . ("order for " ?c) string:concatenation ?label } => { ?o :label ?label } .
+        { ?o :net ?n ; :limit ?l . ?n math:lessThan ?l . (?n 2) math:product 14 } => { ?o :withinLimit true } .
+        "#,
+    )
+    .unwrap();
+    r.infer_new_facts_semi_naive();
+
+    assert!(inferred(&mut r, &ex("o1"), &ex("gross"), "42"));
+    assert!(r.query_abox(Some(&ex("o2")), Some(&ex("gross")), None).is_empty());
+    assert!(inferred(&mut r, &ex("o2"), &ex("label"), "order for bob"));
+    assert!(inferred(&mut r, &ex("o3"), &ex("withinLimit"), "true"));
+}
+
+#[test]
+fn n3_rules_parse_one_at_a_time() {
+    let mut r = Reasoner::new();
+    let input = "@prefix ex: <http://example.org/>.
+        { ?x ex:parent ?y } => { ?y ex:child ?x }.
+        { ?x ex:child ?y } => { ?y ex:parent ?x }.";
+    let (rest, (prefixes, first)) = parse_n3_rule(input, &mut r).unwrap();
+    assert_eq!(prefixes, vec![("ex", "http://example.org/")]);
+    assert_eq!(first.premise.len(), 1);
+    assert!(rest.trim_start().starts_with("{ ?x ex:child"));
+    // Each rule needs the prefixes it uses
+    assert!(parse_n3_rule(rest, &mut r).is_err());
+}
+
+#[test]
+fn invalid_n3_documents_are_rejected() {
+    let mut r = Reasoner::new();
+    let cases = [
+        ("{ ?x ex:p ?y } => { ?y ex:q ?x } .", "unknown prefix"),
+        ("@prefix : <http://e/> . :a :p ?x .", "outside a rule"),
+        (
+            "@prefix : <http://e/> . @prefix math: <http://www.w3.org/2000/10/swap/math#> .
+            { ?x :p ?y . ?y math:cosine ?z } => { ?x :q ?z } .",
+            "unsupported built-in",
+        ),
+        (
+            "@prefix : <http://e/> . @prefix log: <http://www.w3.org/2000/10/swap/log#> .
+            { ?x :p ?y . ?x log:notIncludes { ?z :q ?x } } => { ?x :r ?y } .",
+            "unsafe negation",
+        ),
+        ("@prefix : <http://e/> . :a :p \"open .", "syntax error"),
+    ];
+    for (input, message) in cases {
+        let err = parse_n3_document(input, &mut r).unwrap_err();
+        assert!(err.contains(message), "{}: {}", input, err);
+    }
+    assert!(r.rules.is_empty());
+}
+
+// ─── Existential rules ──────────────────────────────────────────────────────
+
+/// Every sensor has some calibration; s1 already has one
+fn calibration_reasoner(chase: ChaseConfig) -> Reasoner {
+    let mut r = Reasoner::new();
+    r.chase = chase;
+    for sensor in ["s1", "s2", "s3"] {
+        r.add_abox_triple(sensor, "type", "Sensor");
+    }
+    r.add_abox_triple("s1", "calibratedBy", "c1");
+    r.add_abox_triple("c1", "type", "Calibration");
+    let (type_, sensor, calibrated_by, calibration) =
+        (enc(&r, "type"), enc(&r, "Sensor"), enc(&r, "calibratedBy"), enc(&r, "Calibration"));
+    r.try_add_rule(rule(
+        vec![(var("S"), Term::Constant(type_), Term::Constant(sensor))],
+        vec![
+            (var("S"), Term::Constant(calibrated_by), var("C")),
+            (var("C"), Term::Constant(type_), Term::Constant(calibration)),
+        ],
+    ))
+    .unwrap();
+    r
+}
+
+fn calibrations(r: &mut Reasoner, sensor: &str) -> Vec<String> {
+    let facts = r.query_abox(Some(sensor), Some("calibratedBy"), None);
+    let dict = r.dictionary.read().unwrap();
+    let mut objects: Vec<String> = facts.iter().map(|t| dict.decode(t.object).unwrap().to_string()).collect();
+    objects.sort();
+    objects
+}
+
+#[test]
+fn restricted_chase_invents_values_only_when_needed() {
+    let mut r = calibration_reasoner(ChaseConfig::default());
+    let derived = r.infer_new_facts_semi_naive();
+
+    assert_eq!(calibrations(&mut r, "s1"), vec!["c1"]);
+    let (s2, s3) = (calibrations(&mut r, "s2"), calibrations(&mut r, "s3"));
+    assert_eq!((s2.len(), s3.len()), (1, 1));
+    assert!(s2[0].starts_with("_:") && s2[0] != s3[0]);
+    assert!(inferred(&mut r, &s2[0], "type", "Calibration"));
+    assert_eq!(derived.len(), 4);
+
+    // The conclusions now hold, so inferring again adds nothing
+    assert!(r.infer_new_facts_semi_naive().is_empty());
+    let mut naive = calibration_reasoner(ChaseConfig::default());
+    assert_eq!(naive.infer_new_facts_naive().len(), 4);
+}
+
+#[test]
+fn skolem_chase_invents_the_same_values_every_time() {
+    let skolem = ChaseConfig { variant: ChaseVariant::Skolem, max_depth: None };
+    let mut r = calibration_reasoner(skolem);
+    r.infer_new_facts_semi_naive();
+    let mut again = calibration_reasoner(skolem);
+    again.infer_new_facts_semi_naive_parallel();
+
+    // Unlike the restricted chase, s1 gets a calibration too
+    assert_eq!(calibrations(&mut r, "s1").len(), 2);
+    for sensor in ["s1", "s2", "s3"] {
+        assert_eq!(calibrations(&mut r, sensor), calibrations(&mut again, sensor), "{}", sensor);
+    }
+    assert!(r.infer_new_facts_semi_naive().is_empty());
+}
+
+#[test]
+fn chase_terminates_by_weak_acyclicity_or_depth_bound() {
+    let mut r = Reasoner::new();
+    r.add_abox_triple("alice", "type", "Person");
+    let (type_, person, parent) = (enc(&r, "type"), enc(&r, "Person"), enc(&r, "hasParent"));
+    // Every person has a parent who is a person
+    let ancestry = rule(
+        vec![(var("X"), Term::Constant(type_), Term::Constant(person))],
+        vec![
+            (var("X"), Term::Constant(parent), var("Y")),
+            (var("Y"), Term::Constant(type_), Term::Constant(person)),
+        ],
+    );
+
+    let err = check_weak_acyclicity(std::slice::from_ref(&ancestry)).unwrap_err();
+    assert!(err.contains("not weakly acyclic"), "{}", err);
+    assert_eq!(r.try_add_rule(ancestry.clone()).unwrap_err(), err);
+    assert!(r.rules.is_empty());
+
+    r.chase.max_depth = Some(3);
+    r.try_add_rule(ancestry).unwrap();
+    r.infer_new_facts_semi_naive();
+    assert_eq!(r.query_abox(None, Some("hasParent"), None).len(), 3);
+    assert_eq!(r.query_abox(None, Some("type"), Some("Person")).len(), 4);
+
+    // Without the recursion the rule is weakly acyclic
+    let calibration = calibration_reasoner(ChaseConfig::default());
+    assert!(check_weak_acyclicity(&calibration.rules).is_ok());
+}
+
+#[test]
+fn existential_variables_must_be_used_safely() {
+    let mut r = Reasoner::new();
+    let (type_, sensor, calibrated_by) = (enc(&r, "type"), enc(&r, "Sensor"), enc(&r, "calibratedBy"));
+    let existential = rule(
+        vec![(var("S"), Term::Constant(type_), Term::Constant(sensor))],
+        vec![(var("S"), Term::Constant(calibrated_by), var("C"))],
+    );
+    assert_eq!(existential.existential_variables().into_iter().collect::<Vec<_>>(), vec!["C"]);
+
+    let err = r
+        .try_add_rule(Rule { filters: vec![filter("C", ">", "1")], ..existential.clone() })
+        .unwrap_err();
+    assert!(err.contains("unsafe filter"), "{}", err);
+    let err = r
+        .try_add_rule(Rule {
+            aggregates: vec![aggregate(AggregateFunction::Count, "S", "N")],
+            conclusion: vec![(var("N"), Term::Constant(calibrated_by), var("C"))],
+            ..existential
+        })
+        .unwrap_err();
+    assert!(err.contains("cannot be combined with aggregates"), "{}", err);
+}
+
+#[test]
+fn n3_conclusion_blank_nodes_are_existential() {
+    let mut r = Reasoner::new();
+    r.load_n3(
+        r#"
+        @prefix : <http://example.org/> .
+        :s1 a :Sensor .
+        { ?s a :Sensor } => { ?s :calibratedBy [ a :Calibration ] } .
+        "#,
+    )
+    .unwrap();
+    r.infer_new_facts_semi_naive();
+    let calibrations = r.query_abox(Some(&ex("s1")), Some(&ex("calibratedBy")), None);
+    assert_eq!(calibrations.len(), 1);
+    let rdf_type = enc(&r, "http://www.w3.org/1999/02/22-rdf-syntax-ns#type");
+    let calibration = enc(&r, &ex("Calibration"));
+    let typed = Triple { subject: calibrations[0].object, predicate: rdf_type, object: calibration };
+    assert!(r.index_manager.query(None, None, None).contains(&typed));
+}
+
+#[test]
+fn n3_rules_must_be_weakly_acyclic_without_a_depth_bound() {
+    let ancestry = r#"
+        @prefix : <http://example.org/> .
+        { ?x a :Person } => { ?x :hasParent _:p . _:p a :Person } .
+        "#;
+    let mut r = Reasoner::new();
+    let err = parse_n3_document(ancestry, &mut r).unwrap_err();
+    assert!(err.contains("not weakly acyclic"), "{}", err);
+    assert!(r.load_n3(ancestry).is_err());
+    assert!(r.rules.is_empty());
+
+    r.chase.max_depth = Some(2);
+    assert_eq!(parse_n3_document(ancestry, &mut r).unwrap().rules.len(), 1);
+}
+
+// ─── Inconsistency-tolerant queries ─────────────────────────────────────────
+
+/// Nothing is both a cat and a dog, and no age is negative; tom is both, and
+/// bob has a negative age
+fn pets_reasoner() -> Reasoner {
+    let mut r = Reasoner::new();
+    r.add_abox_triple("tom", "type", "Cat");
+    r.add_abox_triple("tom", "type", "Dog");
+    r.add_abox_triple("tom", "name", "Tom");
+    r.add_abox_triple("felix", "type", "Cat");
+    r.add_abox_triple("Cat", "subClassOf", "Animal");
+    r.add_abox_triple("Dog", "subClassOf", "Animal");
+    r.add_abox_triple("bob", "age", "-5");
+    r.add_abox_triple("ann", "age", "30");
+    let var = |v: &str| Term::Variable(v.into());
+    let [ty, cat, dog, name, age] = ["type", "Cat", "Dog", "name", "age"].map(|s| Term::Constant(enc(&r, s)));
+    let cat_and_dog = vec![(var("x"), ty.clone(), cat), (var("x"), ty, dog)];
+    r.add_constraint(rule(cat_and_dog.clone(), vec![]));
+    // Implied by the first constraint, so never a minimal conflict
+    let mut named_cat_and_dog = cat_and_dog;
+    named_cat_and_dog.push((var("x"), name, var("n")));
+    r.add_constraint(rule(named_cat_and_dog, vec![]));
+    r.add_constraint(Rule {
+        filters: vec![FilterCondition { variable: "a".into(), operator: "<".into(), value: "0".into() }],
+        ..rule(vec![(var("x"), age, var("a"))], vec![])
+    });
+    r
+}
+
+fn answers(r: &Reasoner, query: &[(Term, Term, Term)], var: &str, semantics: RepairSemantics) -> Vec<String> {
+    let results = r.query_with_semantics(query, &[var], semantics);
+    let dict = r.dictionary.read().unwrap();
+    let mut answers: Vec<String> = results
+        .iter()
+        .map(|answer| dict.decode(answer[var]).unwrap().to_string())
+        .collect();
+    answers.sort();
+    answers
+}
+
+#[test]
+fn conflicts_are_the_minimal_inconsistent_subsets() {
+    let r = pets_reasoner();
+    let triple = |s: &str, p: &str, o: &str| Triple { subject: enc(&r, s), predicate: enc(&r, p), object: enc(&r, o) };
+    let conflicts = r.conflicts();
+    assert_eq!(conflicts.len(), 2);
+    let mut cat_and_dog = vec![triple("tom", "type", "Cat"), triple("tom", "type", "Dog")];
+    cat_and_dog.sort();
+    assert!(conflicts.contains(&Conflict { constraint: 0, facts: cat_and_dog }));
+    assert!(conflicts.contains(&Conflict { constraint: 2, facts: vec![triple("bob", "age", "-5")] }));
+
+    let graph = r.conflict_hypergraph();
+    assert!(graph.is_free(&triple("tom", "name", "Tom")));
+    assert!(!graph.is_free(&triple("tom", "type", "Dog")));
+    assert_eq!(graph.conflicts_of(&triple("tom", "type", "Cat")).count(), 1);
+}
+
+#[test]
+fn ar_iar_and_brave_answers_differ_on_conflicting_facts() {
+    let r = pets_reasoner();
+    let var = |v: &str| Term::Variable(v.into());
+    let [ty, sub, animal, dog, age] = ["type", "subClassOf", "Animal", "Dog", "age"].map(|s| Term::Constant(enc(&r, s)));
+
+    // tom is an animal in every repair, either as a cat or as a dog
+    let animals = [(var("x"), ty.clone(), var("c")), (var("c"), sub, animal)];
+    assert_eq!(answers(&r, &animals, "x", RepairSemantics::AR), ["felix", "tom"]);
+    assert_eq!(answers(&r, &animals, "x", RepairSemantics::IAR), ["felix"]);
+    assert_eq!(answers(&r, &animals, "x", RepairSemantics::Brave), ["felix", "tom"]);
+
+    // Only some repairs make tom a dog, and none keep bob's age
+    let dogs = [(var("x"), ty, dog)];
+    assert!(answers(&r, &dogs, "x", RepairSemantics::AR).is_empty());
+    assert_eq!(answers(&r, &dogs, "x", RepairSemantics::Brave), ["tom"]);
+    let aged = [(var("x"), age, var("a"))];
+    assert_eq!(answers(&r, &aged, "x", RepairSemantics::Brave), ["ann"]);
+
+    // Boolean queries have one empty answer when they hold
+    assert_eq!(r.query_with_semantics(&dogs, &[], RepairSemantics::Brave), vec![HashMap::new()]);
+    assert!(r.query_with_semantics(&dogs, &[], RepairSemantics::AR).is_empty());
+
+    // query_with_repairs keeps its IAR behaviour for single patterns
+    let results = r.query_with_repairs(&(var("x"), Term::Constant(enc(&r, "type")), var("c")));
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["x"], enc(&r, "felix"));
+}
+
+// ─── Proof trees ────────────────────────────────────────────────────────────
+
+/// ancestor from parent (rule 0), extended by a parent (rule 1) or by
+/// transitivity (rule 2); a parent of b, b parent of c
+fn family_reasoner() -> Reasoner {
+    let mut r = Reasoner::new();
+    r.add_abox_triple("a", "parent", "b");
+    r.add_abox_triple("b", "parent", "c");
+    let parent = Term::Constant(enc(&r, "parent"));
+    let ancestor = Term::Constant(enc(&r, "ancestor"));
+    r.add_rule(rule(vec![(var("x"), parent.clone(), var("y"))], vec![(var("x"), ancestor.clone(), var("y"))]));
+    r.add_rule(rule(
+        vec![(var("x"), ancestor.clone(), var("y")), (var("y"), parent, var("z"))],
+        vec![(var("x"), ancestor.clone(), var("z"))],
+    ));
+    r.add_rule(rule(
+        vec![(var("x"), ancestor.clone(), var("y")), (var("y"), ancestor.clone(), var("z"))],
+        vec![(var("x"), ancestor, var("z"))],
+    ));
+    r
+}
+
+#[test]
+fn derivations_are_only_recorded_behind_the_flag() {
+    let mut r = family_reasoner();
+    r.infer_new_facts_semi_naive();
+    assert!(r.derivations.is_empty());
+    assert!(r.explain(&fact(&r, "a", "ancestor", "c")).is_empty());
+    // Asserted facts need no recorded derivation
+    let asserted = fact(&r, "a", "parent", "b");
+    assert_eq!(r.explain(&asserted), vec![ProofTree { fact: asserted, rule: None, premises: vec![] }]);
+    assert!(r.explain(&fact(&r, "c", "parent", "a")).is_empty());
+}
+
+#[test]
+fn explain_returns_a_proof_tree_per_derivation() {
+    let mut r = family_reasoner();
+    r.record_derivations = true;
+    r.infer_new_facts_semi_naive();
+
+    let leaf = |t: Triple| ProofTree { fact: t, rule: None, premises: vec![] };
+    let a_b = ProofTree { fact: fact(&r, "a", "ancestor", "b"), rule: Some(0), premises: vec![leaf(fact(&r, "a", "parent", "b"))] };
+    let b_c = ProofTree { fact: fact(&r, "b", "ancestor", "c"), rule: Some(0), premises: vec![leaf(fact(&r, "b", "parent", "c"))] };
+    let mut proofs = r.explain(&fact(&r, "a", "ancestor", "c"));
+    proofs.sort_by_key(|proof| proof.rule);
+    assert_eq!(
+        proofs,
+        vec![
+            ProofTree { fact: fact(&r, "a", "ancestor", "c"), rule: Some(1), premises: vec![a_b.clone(), leaf(fact(&r, "b", "parent", "c"))] },
+            ProofTree { fact: fact(&r, "a", "ancestor", "c"), rule: Some(2), premises: vec![a_b, b_c] },
+        ]
+    );
+
+    // Running again re-records the same derivations
+    let recorded = r.derivations.clone();
+    r.infer_new_facts_semi_naive();
+    assert_eq!(r.derivations, recorded);
+}
+
+#[test]
+fn explanations_follow_the_latest_materialisation() {
+    let mut r = family_reasoner();
+    r.record_derivations = true;
+    r.infer_new_facts_semi_naive();
+    assert_eq!(r.explain(&fact(&r, "a", "ancestor", "c")).len(), 2);
+
+    // Incremental maintenance records no derivations, so none are kept
+    let c_d = fact(&r, "c", "parent", "d");
+    let delta = r.update_materialization(&[c_d], &[]).unwrap();
+    assert!(delta.added.contains(&fact(&r, "a", "ancestor", "d")));
+    assert!(r.derivations.is_empty());
+    assert!(r.explain(&fact(&r, "a", "ancestor", "c")).is_empty());
+
+    // Materialising again explains the new facts too
+    r.infer_new_facts_semi_naive();
+    assert!(!r.explain(&fact(&r, "a", "ancestor", "d")).is_empty());
+}
+
+#[test]
+fn proof_trees_never_depend_on_the_explained_fact() {
+    let mut r = family_reasoner();
+    r.add_abox_triple("c", "parent", "a");
+    r.record_derivations = true;
+    r.infer_new_facts_semi_naive();
+    fn contains(tree: &ProofTree, t: &Triple) -> bool {
+        tree.premises.iter().any(|p| p.fact == *t || contains(p, t))
+    }
+    for (s, o) in [("a", "a"), ("a", "c"), ("c", "b")] {
+        let target = fact(&r, s, "ancestor", o);
+        let proofs = r.explain(&target);
+        assert!(!proofs.is_empty(), "{} ancestor {}", s, o);
+        assert!(proofs.iter().all(|proof| !contains(proof, &target)));
+    }
+}
+
+#[test]
+fn proof_trees_render_as_dot_and_rdf_star() {
+    let mut r = family_reasoner();
+    r.record_derivations = true;
+    r.infer_new_facts_semi_naive();
+    let proof = r
+        .explain(&fact(&r, "a", "ancestor", "c"))
+        .into_iter()
+        .find(|proof| proof.rule == Some(1))
+        .unwrap();
+
+    let mut qt_store = QuotedTripleStore::new();
+    let dot = proof_tree_to_dot(&proof, &r, &qt_store);
+    assert!(dot.starts_with("digraph {"));
+    assert!(dot.contains("Fact0 [label=\"a ancestor c\"]"));
+    assert!(dot.contains("Apply0 [label=\"Rule1\", shape=box]"));
+    assert!(dot.contains("Fact1 -> Apply0"));
+    assert!(dot.contains("Apply1 [label=\"Rule0\", shape=box]"));
+
+    // Quoted triples render through the store instead of the dictionary
+    let quoted = qt_store.encode(fact(&r, "a", "parent", "b").subject, enc(&r, "parent"), enc(&r, "b"));
+    let about = ProofTree { fact: Triple { subject: quoted, predicate: enc(&r, "since"), object: enc(&r, "2020") }, rule: None, premises: vec![] };
+    assert!(proof_tree_to_dot(&about, &r, &qt_store).contains("label=\"<< a parent b >> since 2020\""));
+
+    let mut dict = r.dictionary.write().unwrap();
+    let triples = proof.encode_as_rdf_star(&mut dict, &mut qt_store);
+    let mut count = |predicate: &str| {
+        let id = dict.encode(&format!("http://www.w3.org/ns/proof#{}", predicate));
+        triples.iter().filter(|t| t.predicate == id).count()
+    };
+    assert_eq!(count("derivedBy"), 2);
+    assert_eq!(count("rule"), 2);
+    assert_eq!(count("premise"), 3);
+    assert_eq!(count("asserted"), 2);
+    let [a, ancestor, c] = ["a", "ancestor", "c"].map(|s| dict.encode(s));
+    let a_c = qt_store.encode(a, ancestor, c);
+    assert!(triples.iter().any(|t| t.subject == a_c));
+}
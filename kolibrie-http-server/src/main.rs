@@ -8,14 +8,18 @@
  * you can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use datalog::parser_n3_logic::parse_n3_rule;
 use datalog::reasoning::Reasoner;
-use kolibrie::execute_query::{execute_query, execute_query_rayon_parallel2_volcano};
+use kolibrie::execute_query::{
+    execute_query_iter, execute_query_volcano_with_context, execute_query_with_context,
+    strip_explain_prefix,
+};
 use kolibrie::parser::process_rule_definition;
+use kolibrie::query_context::{CancellationToken, QueryContext};
 use kolibrie::rsp_engine::{
     OperationMode, QueryExecutionMode, RSPBuilder, ResultConsumer, SimpleR2R,
 };
 use kolibrie::sparql_database::SparqlDatabase;
+use kolibrie::streamertail_optimizer::QueryExplanation;
 use serde::{Deserialize, Serialize};
 use shared::triple::Triple;
 use std::collections::HashMap;
@@ -37,11 +41,16 @@ struct EngineSession {
 
 type Sessions = Arc<Mutex<HashMap<String, EngineSession>>>;
 
+// Cancellation tokens of the `/query` requests that were given a `query_id`
+type RunningQueries = Arc<Mutex<HashMap<String, CancellationToken>>>;
+
 static SESSION_COUNTER: AtomicU64 = AtomicU64::new(1);
 
 const READ_CHUNK_SIZE: usize = 8 * 1024;
 const MAX_REQUEST_SIZE: usize = 64 * 1024 * 1024;
 const INCOMPLETE_JSON_GRACE_PERIOD: Duration = Duration::from_millis(750);
+/// Rows written per chunk by the streaming query endpoint.
+const STREAM_CHUNK_ROWS: usize = 1000;
 
 struct HttpRequest {
     method: String,
@@ -60,7 +69,7 @@ struct QueryRequest {
     #[serde(default)]
     rdf: Option<String>,
     // N3 logic rules in { pattern } => { conclusion } syntax with @prefix declarations.
-    // Parsed by parse_n3_rule (parser_n3_logic.rs) via the Reasoner — completely separate
+    // Parsed by parse_n3_document (parser_n3_logic.rs) via the Reasoner — completely separate
     // from the SPARQL RULE syntax handled by process_rule_definition (parser.rs).
     #[serde(default)]
     n3logic: Option<String>,
@@ -70,6 +79,19 @@ struct QueryRequest {
     rules: Option<Vec<String>>,
     #[serde(default = "default_format")]
     format: String,
+    // Per-query limits; a query exceeding them fails instead of running on.
+    #[serde(default)]
+    timeout_ms: Option<u64>,
+    #[serde(default)]
+    memory_limit_mb: Option<usize>,
+    // Client-chosen id under which the request can be stopped via /query/cancel.
+    #[serde(default)]
+    query_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CancelRequest {
+    query_id: String,
 }
 
 // Default format is RDF/XML for backwards compatibility
@@ -88,6 +110,29 @@ struct QueryResult {
     query: String,
     data: Vec<Vec<String>>,
     execution_time_ms: f64,
+    /// Plan of an `EXPLAIN` query; `data` then holds its text rendering, one line per row.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    explain: Option<QueryExplanation>,
+}
+
+/// One line of the NDJSON body written by `/query/stream`.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum StreamedQueryChunk<'a> {
+    Header {
+        query_index: usize,
+        query: &'a str,
+        variables: &'a [String],
+    },
+    Rows {
+        query_index: usize,
+        data: Vec<Vec<String>>,
+    },
+    Done {
+        query_index: usize,
+        total_rows: usize,
+        execution_time_ms: f64,
+    },
 }
 
 #[derive(Debug, Serialize)]
@@ -216,7 +261,7 @@ fn results_to_table(results: &[Vec<(String, String)>]) -> Vec<Vec<String>> {
 fn has_n3_rule_text(text: &str) -> bool {
     text.lines()
         .map(str::trim_start)
-        .any(|line| !line.starts_with('#') && line.contains("=>"))
+        .any(|line| !line.starts_with('#') && ["=>", "<=", "implies"].iter().any(|arrow| line.contains(arrow)))
 }
 
 fn strip_hash_comments(text: &str) -> String {
@@ -280,6 +325,7 @@ fn main() {
     println!("Starting Kolibrie HTTP Server on 0.0.0.0:8080");
 
     let sessions: Sessions = Arc::new(Mutex::new(HashMap::new()));
+    let running: RunningQueries = Arc::new(Mutex::new(HashMap::new()));
 
     let listener = TcpListener::bind("0.0.0.0:8080").expect("Failed to bind to port 8080");
 
@@ -287,8 +333,9 @@ fn main() {
         match stream {
             Ok(stream) => {
                 let sessions = Arc::clone(&sessions);
+                let running = Arc::clone(&running);
                 thread::spawn(move || {
-                    handle_client(stream, sessions);
+                    handle_client(stream, sessions, running);
                 });
             }
             Err(e) => {
@@ -298,7 +345,7 @@ fn main() {
     }
 }
 
-fn handle_client(mut stream: TcpStream, sessions: Sessions) {
+fn handle_client(mut stream: TcpStream, sessions: Sessions, running: RunningQueries) {
     match read_http_request(&mut stream) {
         Ok(request) => {
             // SSE handler must keep the connection open, so it is handled here
@@ -308,8 +355,20 @@ fn handle_client(mut stream: TcpStream, sessions: Sessions) {
                 rsp_events_sse(&session_id, stream, &sessions);
                 return;
             }
+            // Streamed query results are written chunk by chunk as well.
+            if request.method == "POST" && request.path == "/query/stream" {
+                match request_body(&request.body) {
+                    Some(body) => execute_sparql_streaming(body, stream),
+                    None => {
+                        let response = json_error_response("Request body is not valid UTF-8");
+                        let _ = stream.write_all(response.as_bytes());
+                        let _ = stream.flush();
+                    }
+                }
+                return;
+            }
 
-            let response = handle_request(&request, &sessions);
+            let response = handle_request(&request, &sessions, &running);
             let _ = stream.write_all(response.as_bytes());
             let _ = stream.flush();
         }
@@ -590,7 +649,7 @@ fn find_crlf(bytes: &[u8]) -> Option<usize> {
     bytes.windows(2).position(|window| window == b"\r\n")
 }
 
-fn handle_request(request: &HttpRequest, sessions: &Sessions) -> String {
+fn handle_request(request: &HttpRequest, sessions: &Sessions, running: &RunningQueries) -> String {
     let method = request.method.as_str();
     let path = request.path.as_str();
 
@@ -600,7 +659,14 @@ fn handle_request(request: &HttpRequest, sessions: &Sessions) -> String {
 
     if method == "POST" && path == "/query" {
         return match request_body(&request.body) {
-            Some(body) => execute_sparql_with_context(body),
+            Some(body) => execute_sparql_with_context(body, running),
+            None => json_error_response("Request body is not valid UTF-8"),
+        };
+    }
+
+    if method == "POST" && path == "/query/cancel" {
+        return match request_body(&request.body) {
+            Some(body) => cancel_query(body, running),
             None => json_error_response("Request body is not valid UTF-8"),
         };
     }
@@ -892,34 +958,35 @@ fn serve_playground() -> String {
     )
 }
 
-fn execute_sparql_with_context(body: &str) -> String {
-    let request: QueryRequest = match serde_json::from_str(body) {
+/// Parses a `/query` or `/query/stream` body into its queries and rules.
+fn parse_query_request(body: &str) -> Result<(QueryRequest, Vec<String>, Vec<String>), String> {
+    let mut request: QueryRequest = match serde_json::from_str(body) {
         Ok(req) => req,
         Err(e) => {
             eprintln!("JSON parse error after {} byte(s): {}", body.len(), e);
-            return json_error_response(&format!("Invalid JSON: {}", e));
+            return Err(format!("Invalid JSON: {}", e));
         }
     };
 
     // Collect all queries
     let mut queries = Vec::new();
-    if let Some(single_query) = request.sparql {
+    if let Some(single_query) = request.sparql.take() {
         queries.push(single_query);
     }
-    if let Some(multi_queries) = request.queries {
+    if let Some(multi_queries) = request.queries.take() {
         queries.extend(multi_queries);
     }
 
     if queries.is_empty() {
-        return json_error_response("No queries provided");
+        return Err("No queries provided".to_string());
     }
 
     // Collect all rules
     let mut rules = Vec::new();
-    if let Some(single_rule) = request.rule {
+    if let Some(single_rule) = request.rule.take() {
         rules.push(single_rule);
     }
-    if let Some(multi_rules) = request.rules {
+    if let Some(multi_rules) = request.rules.take() {
         rules.extend(multi_rules);
     }
 
@@ -929,11 +996,228 @@ fn execute_sparql_with_context(body: &str) -> String {
         rules.len()
     );
 
-    let mut database = SparqlDatabase::new();
+    Ok((request, queries, rules))
+}
+
+fn execute_sparql_with_context(body: &str, running: &RunningQueries) -> String {
+    let (request, queries, rules) = match parse_query_request(body) {
+        Ok(parsed) => parsed,
+        Err(message) => return json_error_response(&message),
+    };
+
+    // Loading the data is not interruptible; a cancellation arriving meanwhile
+    // stops the query as soon as it starts
+    let token = CancellationToken::new();
+    if let Some(query_id) = &request.query_id {
+        running.lock().unwrap().insert(query_id.clone(), token.clone());
+    }
+    let response = run_query_request(&request, &queries, &rules, token);
+    if let Some(query_id) = &request.query_id {
+        running.lock().unwrap().remove(query_id);
+    }
+    response
+}
+
+/// Execution context of a request: its timeout (counted from now), memory
+/// limit and cancellation token
+fn request_context(request: &QueryRequest, token: CancellationToken) -> QueryContext {
+    let mut context = QueryContext::new().with_cancellation(token);
+    if let Some(timeout_ms) = request.timeout_ms {
+        context = context.with_timeout(Duration::from_millis(timeout_ms));
+    }
+    if let Some(limit_mb) = request.memory_limit_mb {
+        context = context.with_memory_limit(limit_mb.saturating_mul(1024 * 1024));
+    }
+    context
+}
+
+fn run_query_request(
+    request: &QueryRequest,
+    queries: &[String],
+    rules: &[String],
+    token: CancellationToken,
+) -> String {
+    let context = request_context(request, token);
+    let mut database = load_request_database(request, rules);
     let use_optimizer = request.format == "ntriples";
 
+    // Execute all queries
+    let mut all_results = Vec::new();
+
+    for (idx, query) in queries.iter().enumerate() {
+        println!("Executing query {}/{}...", idx + 1, queries.len());
+        let start_time = std::time::Instant::now();
+        let executable_query = strip_hash_comments(query);
+
+        let mut explain = None;
+        let results = if strip_explain_prefix(&executable_query).is_some() {
+            match database.explain(&executable_query) {
+                Ok(explanation) => {
+                    let lines = explanation.to_text().lines().map(|line| vec![line.to_string()]).collect();
+                    explain = Some(explanation);
+                    lines
+                }
+                Err(e) => return json_error_response(&format!("EXPLAIN failed: {}", e)),
+            }
+        } else {
+            let outcome = if use_optimizer {
+                execute_query_volcano_with_context(&executable_query, &mut database, &context)
+            } else {
+                execute_query_with_context(&executable_query, &mut database, &context)
+            };
+            match outcome {
+                Ok(rows) => rows,
+                Err(e) => {
+                    eprintln!("Query {} stopped: {}", idx + 1, e);
+                    return json_error_response(&format!("Query {} failed: {}", idx + 1, e));
+                }
+            }
+        };
+
+        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        all_results.push(QueryResult {
+            query_index: idx,
+            query: query.clone(),
+            data: results,
+            execution_time_ms: execution_time,
+            explain,
+        });
+    }
+
+    let response = QueryResponse {
+        results: all_results,
+    };
+    let json = match serde_json::to_string(&response) {
+        Ok(j) => j,
+        Err(e) => {
+            eprintln!("Failed to serialize response: {}", e);
+            return json_error_response("Failed to serialize results");
+        }
+    };
+
+    format!(
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Methods: POST, OPTIONS\r\n\
+         Access-Control-Allow-Headers: Content-Type\r\n\
+         \r\n\
+         {}",
+        json.len(),
+        json
+    )
+}
+
+/// Cancels the running `/query` request registered under the given `query_id`.
+fn cancel_query(body: &str, running: &RunningQueries) -> String {
+    let request: CancelRequest = match serde_json::from_str(body) {
+        Ok(r) => r,
+        Err(e) => return json_error_response(&format!("Invalid JSON: {}", e)),
+    };
+    match running.lock().unwrap().get(&request.query_id) {
+        Some(token) => {
+            println!("Cancelling query {}", request.query_id);
+            token.cancel();
+            json_ok()
+        }
+        None => json_error_response("Query not found"),
+    }
+}
+
+/// Streaming variant of `/query`: results are pulled from the query engine
+/// `STREAM_CHUNK_ROWS` at a time and written as newline-delimited JSON over a
+/// chunked response, so large results never have to be held in memory and a
+/// client that disconnects stops the evaluation.
+fn execute_sparql_streaming(body: &str, mut stream: TcpStream) {
+    let (request, queries, rules) = match parse_query_request(body) {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            let _ = stream.write_all(json_error_response(&message).as_bytes());
+            let _ = stream.flush();
+            return;
+        }
+    };
+
+    let mut database = load_request_database(&request, &rules);
+
+    if stream
+        .write_all(
+            b"HTTP/1.1 200 OK\r\n\
+              Content-Type: application/x-ndjson\r\n\
+              Transfer-Encoding: chunked\r\n\
+              Access-Control-Allow-Origin: *\r\n\
+              Access-Control-Allow-Methods: POST, OPTIONS\r\n\
+              Access-Control-Allow-Headers: Content-Type\r\n\
+              \r\n",
+        )
+        .is_err()
+    {
+        return;
+    }
+
+    for (idx, query) in queries.iter().enumerate() {
+        println!("Streaming query {}/{}...", idx + 1, queries.len());
+        let start_time = std::time::Instant::now();
+        let executable_query = strip_hash_comments(query);
+        let mut rows = execute_query_iter(&executable_query, &mut database);
+
+        let header = StreamedQueryChunk::Header {
+            query_index: idx,
+            query,
+            variables: rows.variables(),
+        };
+        if write_ndjson_chunk(&mut stream, &header).is_err() {
+            return;
+        }
+
+        let mut total_rows = 0;
+        loop {
+            let data = rows.next_batch(STREAM_CHUNK_ROWS);
+            if data.is_empty() {
+                break;
+            }
+            total_rows += data.len();
+            let chunk = StreamedQueryChunk::Rows { query_index: idx, data };
+            if write_ndjson_chunk(&mut stream, &chunk).is_err() {
+                println!("Streaming client disconnected after {} row(s)", total_rows);
+                return;
+            }
+        }
+
+        let done = StreamedQueryChunk::Done {
+            query_index: idx,
+            total_rows,
+            execution_time_ms: start_time.elapsed().as_secs_f64() * 1000.0,
+        };
+        if write_ndjson_chunk(&mut stream, &done).is_err() {
+            return;
+        }
+    }
+
+    // Zero-length chunk terminates the response
+    let _ = stream.write_all(b"0\r\n\r\n");
+    let _ = stream.flush();
+}
+
+/// Writes one JSON value followed by a newline as a single HTTP chunk.
+fn write_ndjson_chunk(stream: &mut TcpStream, value: &StreamedQueryChunk) -> io::Result<()> {
+    let mut line = serde_json::to_vec(value).map_err(io::Error::other)?;
+    line.push(b'\n');
+    write!(stream, "{:x}\r\n", line.len())?;
+    stream.write_all(&line)?;
+    stream.write_all(b"\r\n")?;
+    stream.flush()
+}
+
+/// Builds the database a query request runs against: the RDF payload, N3
+/// logic rules and SPARQL-syntax rules, in that order.
+fn load_request_database(request: &QueryRequest, rules: &[String]) -> SparqlDatabase {
+    let mut database = SparqlDatabase::new();
+
     // Load RDF data once
-    if let Some(rdf_data) = request.rdf {
+    if let Some(rdf_data) = &request.rdf {
         if !rdf_data.trim().is_empty() {
             let cleaned_rdf_data;
             let rdf_data_for_parse = match request.format.as_str() {
@@ -941,7 +1225,7 @@ fn execute_sparql_with_context(body: &str) -> String {
                     cleaned_rdf_data = strip_hash_comments(&rdf_data);
                     cleaned_rdf_data.as_str()
                 }
-                _ => rdf_data.as_str(),
+                _ => rdf_data,
             };
             match request.format.as_str() {
                 "ntriples" => {
@@ -966,7 +1250,7 @@ fn execute_sparql_with_context(body: &str) -> String {
         }
     }
 
-    // Process N3 logic rules (n3logic field) using parse_n3_rule + Reasoner.
+    // Process N3 logic rules (n3logic field) using Reasoner::load_n3.
     // Syntax: @prefix declarations followed by { premise } => { conclusion } .
     // This is completely separate from the SPARQL RULE syntax — it uses the
     // Reasoner class (datalog/reasoning.rs) exactly as shown in the test example.
@@ -1007,29 +1291,24 @@ fn execute_sparql_with_context(body: &str) -> String {
             }
 
             let n3_text = n3_rules_text.trim();
-            match parse_n3_rule(n3_text, &mut kg) {
-                Ok((_, (prefixes, rule))) => {
+            match kg.load_n3(n3_text) {
+                Ok(document) => {
                     println!(
-                        "N3 rule parsed ({} prefix(es), {} premise(s), {} conclusion(s))",
-                        prefixes.len(),
-                        rule.premise.len(),
-                        rule.conclusion.len(),
+                        "N3 document parsed ({} prefix(es), {} fact(s), {} rule(s))",
+                        document.prefixes.len(),
+                        document.facts.len(),
+                        document.rules.len(),
                     );
 
                     // **IMPORTANT**: Register N3 prefixes with the database so SPARQL rules can use them
-                    for (prefix, uri) in prefixes {
-                        database
-                            .prefixes
-                            .insert(prefix.to_string(), uri.to_string());
-                    }
+                    database.prefixes.extend(document.prefixes);
 
-                    // Register the rule and infer new facts
-                    kg.add_rule(rule);
+                    // Infer new facts from the loaded rules
                     let inferred = kg.infer_new_facts_semi_naive();
                     println!("N3 rule inferred {} fact(s)", inferred.len());
 
-                    // Push inferred triples into the database triple store
-                    for triple in inferred {
+                    // Push the document's facts and the inferred triples into the database triple store
+                    for triple in document.facts.into_iter().chain(inferred) {
                         database.triples.insert(triple);
                     }
 
@@ -1074,53 +1353,7 @@ fn execute_sparql_with_context(body: &str) -> String {
         }
     }
 
-    // Execute all queries
-    let mut all_results = Vec::new();
-
-    for (idx, query) in queries.iter().enumerate() {
-        println!("Executing query {}/{}...", idx + 1, queries.len());
-        let start_time = std::time::Instant::now();
-        let executable_query = strip_hash_comments(query);
-
-        let results = if use_optimizer {
-            execute_query_rayon_parallel2_volcano(&executable_query, &mut database)
-        } else {
-            execute_query(&executable_query, &mut database)
-        };
-
-        let execution_time = start_time.elapsed().as_secs_f64() * 1000.0;
-
-        all_results.push(QueryResult {
-            query_index: idx,
-            query: query.clone(),
-            data: results,
-            execution_time_ms: execution_time,
-        });
-    }
-
-    let response = QueryResponse {
-        results: all_results,
-    };
-    let json = match serde_json::to_string(&response) {
-        Ok(j) => j,
-        Err(e) => {
-            eprintln!("Failed to serialize response: {}", e);
-            return json_error_response("Failed to serialize results");
-        }
-    };
-
-    format!(
-        "HTTP/1.1 200 OK\r\n\
-         Content-Type: application/json\r\n\
-         Content-Length: {}\r\n\
-         Access-Control-Allow-Origin: *\r\n\
-         Access-Control-Allow-Methods: POST, OPTIONS\r\n\
-         Access-Control-Allow-Headers: Content-Type\r\n\
-         \r\n\
-         {}",
-        json.len(),
-        json
-    )
+    database
 }
 
 fn execute_rsp_query(body: &str) -> String {
@@ -1266,7 +1499,9 @@ fn execute_rsp_query(body: &str) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::strip_hash_comments;
+    use super::{cancel_query, strip_hash_comments, CancellationToken, RunningQueries};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
 
     #[test]
     fn strips_hash_comments_without_touching_iris_or_literals() {
@@ -1283,6 +1518,18 @@ FILTER(?s<3) \n\
 
         assert_eq!(strip_hash_comments(input), expected);
     }
+
+    #[test]
+    fn cancelling_triggers_the_token_of_a_running_query() {
+        let running: RunningQueries = Arc::new(Mutex::new(HashMap::new()));
+        let token = CancellationToken::new();
+        running.lock().unwrap().insert("q1".to_string(), token.clone());
+
+        assert!(cancel_query(r#"{"query_id":"q2"}"#, &running).starts_with("HTTP/1.1 400"));
+        assert!(!token.is_cancelled());
+        assert!(cancel_query(r#"{"query_id":"q1"}"#, &running).starts_with("HTTP/1.1 200"));
+        assert!(token.is_cancelled());
+    }
 }
 
 fn json_ok() -> String {
@@ -1,8 +1,7 @@
 extern crate kolibrie;
-use kolibrie::{container_stats::ContainerStats, experiment_logging, rsp::s2r::ContentContainer, rsp_engine::{OperationMode, QueryExecutionMode, RSPBuilder, RSPEngine, ResultConsumer, SimpleR2R}, streamertail_optimizer::{LogicalOperator, PhysicalOperator}};
+use kolibrie::{adaptive_planner::{AdaptivePlanner, AdaptivePlannerConfig, PlanStrategy, ReplanTrigger, StatsBaseline}, experiment_logging, rsp_engine::{OperationMode, QueryExecutionMode, RSPBuilder, RSPEngine, ResultConsumer, SimpleR2R}, streamertail_optimizer::PhysicalOperator};
 use shared::triple::Triple;
-use std::{fmt, fs::read_to_string, path::Path, sync::{Arc, Mutex}, time::Instant};
-use kolibrie::join_reordering;
+use std::{fs::read_to_string, path::Path, sync::{Arc, Mutex}, time::Instant};
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize)]
@@ -31,7 +30,7 @@ WHERE {
     }
 }"#;
 
-    set_up_engine(path, query, replan_trigger, naief, None);
+    set_up_engine(path, query, replan_trigger, naief);
 }
 
 #[allow(dead_code)]
@@ -50,7 +49,7 @@ fn example_window2(path: String, replan_trigger: ReplanTrigger, naief: bool) {
     }
     "#;
 
-    set_up_engine(path, query, replan_trigger, naief, None);
+    set_up_engine(path, query, replan_trigger, naief);
 }
 
 pub fn physical_plan_to_string(plan: &PhysicalOperator) -> String {
@@ -61,7 +60,7 @@ pub fn are_physical_plans_identical(left: &PhysicalOperator, right: &PhysicalOpe
     physical_plan_to_string(left) == physical_plan_to_string(right)
 }
 
-fn set_up_engine(path: String, query: &str, replan_trigger: ReplanTrigger, naief: bool, threshold: Option<f64>) {
+fn set_up_engine(path: String, query: &str, replan_trigger: ReplanTrigger, naief: bool) {
     // Set up a file to write results
     let dataset_name = Path::new(&path)
         .file_stem()
@@ -101,147 +100,16 @@ fn set_up_engine(path: String, query: &str, replan_trigger: ReplanTrigger, naief
             .build()
             .expect("Failed to build RSP engine");
 
-    // View logical plan per window
-    let window_info = engine.get_window_info();
-    let window_plans: Vec<LogicalOperator> = window_info.iter().map(|w| {
-        w.query.clone()
-    }).collect();
-
-    // Select logical plan of the first (and only) window
-    let logical_plan = window_plans.first().unwrap().clone();
-    let first_window_iri = window_info.first().unwrap().window_iri.clone();
-    let replan_metric = make_replan_fn(replan_trigger);
-
-    // Variable to keep track of the stats of the previous window
-    let previous_stats = Arc::new(Mutex::new(ContainerStats::default()));
-    let first_window_plan_done = Arc::new(Mutex::new(false));
-
     // Runtime adaptor: inspect each fired window and optionally swap plan
-    engine.set_window_plan_adaptor(
-        Arc::new(
-        move |window_iri, content: &ContentContainer<Triple>, ts, _current_plan| {
-
-            // START: Gather stats
-            let window_start = Instant::now();
-
-            // Get container stats
-            let current_stats = ContainerStats::gather_stats(content);
-
-            // Load previous window stats to compare
-            let mut previous_stats_guard = previous_stats.lock().unwrap();
-            let previous_stats = previous_stats_guard.clone();
-            
-            // END gather stats
-            let stats_time = window_start.elapsed().as_secs_f64() * 1000.0;
-
-            let window_size = content.len();
-            // // Take a quick look at the previous stats  
-            // println!("Previous stats: Total: {}, Cardinalities: {}, {}, {}",
-            //     previous_stats.get_total_triples(),
-            //     previous_stats.get_total_subjects(),
-            //     previous_stats.get_total_predicates(),
-            //     previous_stats.get_total_objects()
-            // );
-
-            // Take a quick look at the stats  
-            // println!("Current stats: Total: {}, Cardinalities: {}, {}, {}",
-            //     current_stats.get_total_triples(),
-            //     current_stats.get_total_subjects(),
-            //     current_stats.get_total_predicates(),
-            //     current_stats.get_total_objects()
-            // );
-             
-            // println!(
-            //     "[Adaptor] window={} ts={} tuples_in_window={}",
-            //     window_iri, ts, window_size
-            // );
-
-            // dbg!(&_current_plan);
-
-            // Check if it's the first time we see this window
-            let force_initial_plan = if window_iri == first_window_iri {
-                let mut first_window_plan_done_guard = first_window_plan_done.lock().unwrap();
-                if !*first_window_plan_done_guard {
-                    *first_window_plan_done_guard = true;
-                    true
-                } else {
-                    false
-                }
-            } else {
-                false
-            };
-
-            // START new plan calculation
-            let start_calculation = Instant::now();
-
-            // Decide whether to replan based on the selected trigger.
-            let replan = force_initial_plan || replan_metric(&current_stats, &previous_stats);
-            
-            // Calculate a potential new plan 
-            if replan {
-                let new_plan: PhysicalOperator = if (naief || force_initial_plan) {
-                    // println!("[Adaptor] Manually calculated initial plan for {}", window_iri);
-                    join_reordering::calculate_initial_window_plan(logical_plan.clone(), current_stats.clone())
-                } else {
-                    //println!("[Adaptor] Recalculate plan for {}", window_iri);
-                    join_reordering::recalculate_window_plan(_current_plan.clone(), current_stats.clone())
-                };
-
-                // for debugging: check if the new plan is actually different from the current plan
-                // if are_physical_plans_identical(_current_plan, &new_plan) {
-                //     println!("[Adaptor] The same plan was chosen");
-                // } else {
-                //     // dbg!(&new_plan);
-                //     println!("[Adaptor] New plan was chosen");
-                // }
-
-                let optimize_plan_time = start_calculation.elapsed().as_secs_f64() * 1000.0;
-                // println!("[Timing] window={} latency={:.3}ms stats_time={:.3}ms tuples={}", 
-                //    window_iri, optimize_plan_time, stats_time, window_size);
-                if let Err(error) = experiment_logging::append_experiment_row(
-                    "optimize",
-                    window_iri,
-                    ts,
-                    optimize_plan_time,
-                    Some(stats_time),
-                    window_size,
-                    None,
-                    if force_initial_plan { "initial" } else { "replan" },
-                    threshold,
-                ) {
-                    eprintln!("Failed to write optimization timing for {}: {:?}", window_iri, error);
-                }
-                *previous_stats_guard = current_stats; // Update previous stats for next window
-                return Some(new_plan);
-            }
-            
-            let optimize_plan_time = start_calculation.elapsed().as_secs_f64() * 1000.0;
-            // println!("[Timing] window={} latency={:.3}ms stats_time={:.3}ms tuples={}", 
-            //     window_iri, optimize_plan_time, stats_time, window_size);
-            if let Err(error) = experiment_logging::append_experiment_row(
-                "optimize",
-                window_iri,
-                ts,
-                optimize_plan_time,
-                Some(stats_time),
-                window_size,
-                None,
-                "no_change",
-                threshold,
-            ) {
-                eprintln!("Failed to write optimization timing for {}: {:?}", window_iri, error);
-            }
-
-            // Plan remains the same
-            // println!("[Adaptor] Plan remains the same {}", window_iri);
-            
-            // CHOOSE TO UPDATE STATS (TODO ADD AS PARAMETER)
-            // Only update the stats of the previous if replanning was necessary
-            // *previous_stats_guard = current_stats; // Update previous stats for next window
-
-            return None;
-        },
-    ));
+    let strategy = if naief { PlanStrategy::Exhaustive } else { PlanStrategy::Incremental };
+    engine.set_adaptive_planner(Arc::new(AdaptivePlanner::new(AdaptivePlannerConfig {
+        trigger: replan_trigger,
+        strategy,
+        // Only update the stats of the previous window if replanning was necessary
+        baseline: StatsBaseline::LastReplan,
+        ..AdaptivePlannerConfig::default()
+    })))
+    .expect("Failed to install the adaptive planner");
 
     let amount_of_triples = stream_dataset(&mut engine, &path);
     // println!("Amount of triples: {}", amount_of_triples);
@@ -357,60 +225,7 @@ fn load_stream_events(content: &str) -> Vec<StreamEvent> {
     }
 }
 
-// Use an Arc-wrapped function to choose our replanning trigger
-type ReplanFn = Arc<dyn Fn(&ContainerStats, &ContainerStats) -> bool + Send + Sync>;
-
-fn make_replan_fn(trigger: ReplanTrigger) -> ReplanFn {
-    match trigger {
-        ReplanTrigger::Static => Arc::new(|_, _| false),
-        ReplanTrigger::Always => Arc::new(|_, _| true),
-        ReplanTrigger::OnSizeChange { threshold } => {
-            Arc::new(move |current, previous| current.size_change_ratio(previous) > threshold)
-        }
-        ReplanTrigger::OnDistributionChange { threshold } => Arc::new(move |current, previous| {
-            current.object_distribution_distance(previous) > threshold
-        }),
-        ReplanTrigger::OnRankingChange { threshold } => {
-            Arc::new(move |current, previous| current.object_rank_change_ratio(previous) > threshold)
-        }
-        ReplanTrigger::Hybrid {
-            size_threshold,
-            distribution_threshold,
-            ranking_threshold,
-        } => Arc::new(move |current, previous| {
-            current.size_change_ratio(previous) > size_threshold
-                || current.object_distribution_distance(previous) > distribution_threshold
-                || current.object_rank_change_ratio(previous) > ranking_threshold
-        }),
-    }
-}
-
-#[allow(dead_code)]
-enum ReplanTrigger {
-    Static,
-    Always,
-    OnSizeChange { threshold: f64 },
-    OnDistributionChange { threshold: f64, },
-    OnRankingChange { threshold: f64 },
-    Hybrid { size_threshold: f64, distribution_threshold: f64, ranking_threshold: f64 },
-}
-
-impl fmt::Display for ReplanTrigger {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            ReplanTrigger::Static => write!(f, "Static"),
-            ReplanTrigger::Always => write!(f, "Always"),
-            ReplanTrigger::OnSizeChange { threshold } => write!(f, "OnSizeChange({})", threshold),
-            ReplanTrigger::OnDistributionChange { threshold } => write!(f, "OnDistributionChange"),
-            ReplanTrigger::OnRankingChange { threshold } => write!(f, "OnRankingChange" ),
-            ReplanTrigger::Hybrid { size_threshold, distribution_threshold, ranking_threshold } => {
-                write!(f, "Hybrid({},{},{})", size_threshold, distribution_threshold, ranking_threshold)
-            }
-        }
-    }
-}
-
-fn example_window3(path: String, replan_trigger: ReplanTrigger, naief: bool, range_size: usize, step_size: usize,threshold: Option<f64>) {
+fn example_window3(path: String, replan_trigger: ReplanTrigger, naief: bool, range_size: usize, step_size: usize) {
     let query = format!(r#"
     PREFIX ex: <http://example.org/stream/>
 
@@ -427,7 +242,7 @@ fn example_window3(path: String, replan_trigger: ReplanTrigger, naief: bool, ran
     }}
     }}"#);
 
-    set_up_engine(path, &query.to_string(), replan_trigger, naief, threshold);
+    set_up_engine(path, &query.to_string(), replan_trigger, naief);
 }
 
 fn experiment(window_size: usize, threshold_dist: f64, threshold_rank: f64, naief: bool) {
@@ -437,20 +252,20 @@ fn experiment(window_size: usize, threshold_dist: f64, threshold_rank: f64, naie
     let range_size = window_size;
     let step_size = window_size;
     // Static data
-    example_window3(static_path.clone(), ReplanTrigger::Static, naief, range_size, step_size, None);
-    example_window3(static_path.clone(), ReplanTrigger::Always, naief, range_size, step_size, None);
-    example_window3(static_path.clone(), ReplanTrigger::OnDistributionChange { threshold: threshold_dist }, naief, range_size, step_size, Some(threshold_dist));
-    example_window3(static_path.clone(), ReplanTrigger::OnRankingChange { threshold: threshold_rank }, naief, range_size, step_size, Some(threshold_rank));
+    example_window3(static_path.clone(), ReplanTrigger::Static, naief, range_size, step_size);
+    example_window3(static_path.clone(), ReplanTrigger::Always, naief, range_size, step_size);
+    example_window3(static_path.clone(), ReplanTrigger::OnDistributionChange { threshold: threshold_dist }, naief, range_size, step_size);
+    example_window3(static_path.clone(), ReplanTrigger::OnRankingChange { threshold: threshold_rank }, naief, range_size, step_size);
     // Dynamic data
-    example_window3(volatile_path.clone(), ReplanTrigger::Static, naief, range_size, step_size, None);
-    example_window3(volatile_path.clone(), ReplanTrigger::Always, naief, range_size, step_size, None);
-    example_window3(volatile_path.clone(), ReplanTrigger::OnDistributionChange { threshold: threshold_dist }, naief, range_size, step_size, Some(threshold_dist));
-    example_window3(volatile_path.clone(), ReplanTrigger::OnRankingChange { threshold: threshold_rank }, naief, range_size, step_size, Some(threshold_rank));
+    example_window3(volatile_path.clone(), ReplanTrigger::Static, naief, range_size, step_size);
+    example_window3(volatile_path.clone(), ReplanTrigger::Always, naief, range_size, step_size);
+    example_window3(volatile_path.clone(), ReplanTrigger::OnDistributionChange { threshold: threshold_dist }, naief, range_size, step_size);
+    example_window3(volatile_path.clone(), ReplanTrigger::OnRankingChange { threshold: threshold_rank }, naief, range_size, step_size);
     // Gradual data change
-    example_window3(gradual_path.clone(), ReplanTrigger::Static, naief, range_size, step_size, None);
-    example_window3(gradual_path.clone(), ReplanTrigger::Always, naief, range_size, step_size, None);
-    example_window3(gradual_path.clone(), ReplanTrigger::OnDistributionChange { threshold: threshold_dist }, naief, range_size, step_size, Some(threshold_dist));
-    example_window3(gradual_path.clone(), ReplanTrigger::OnRankingChange { threshold: threshold_rank }, naief, range_size, step_size, Some(threshold_rank));
+    example_window3(gradual_path.clone(), ReplanTrigger::Static, naief, range_size, step_size);
+    example_window3(gradual_path.clone(), ReplanTrigger::Always, naief, range_size, step_size);
+    example_window3(gradual_path.clone(), ReplanTrigger::OnDistributionChange { threshold: threshold_dist }, naief, range_size, step_size);
+    example_window3(gradual_path.clone(), ReplanTrigger::OnRankingChange { threshold: threshold_rank }, naief, range_size, step_size);
 }
 
 fn experiment_over_window_size() {
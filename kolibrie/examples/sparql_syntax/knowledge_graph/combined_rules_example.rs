@@ -59,6 +59,8 @@ fn main() {
             (Term::Variable("Y".to_string()), Term::Constant(knows_id),     Term::Variable("Z".to_string())),
         ],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![(
             Term::Variable("X".to_string()), Term::Constant(connected_id), Term::Variable("Z".to_string()),
         )],
@@ -72,6 +74,8 @@ fn main() {
             (Term::Variable("Y".to_string()), Term::Constant(connected_id), Term::Variable("Z".to_string())),
         ],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![(
             Term::Variable("X".to_string()), Term::Constant(connected_id), Term::Variable("Z".to_string()),
         )],
@@ -85,6 +89,8 @@ fn main() {
             (Term::Variable("Y".to_string()), Term::Constant(strong_bond_id), Term::Variable("Z".to_string())),
         ],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![(
             Term::Variable("X".to_string()), Term::Constant(trust_comm_id), Term::Variable("Z".to_string()),
         )],
@@ -103,6 +109,8 @@ fn main() {
             (Term::Variable("Y".to_string()), Term::Constant(trusts_id),   Term::Variable("Z".to_string())),
         ],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![(
             Term::Variable("X".to_string()), Term::Constant(indirect_id), Term::Variable("Z".to_string()),
         )],
@@ -117,6 +125,8 @@ fn main() {
             (Term::Variable("X".to_string()), Term::Constant(trusts_id),      Term::Variable("Z".to_string())),
         ],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![(
             Term::Variable("X".to_string()), Term::Constant(strong_bond_id), Term::Variable("Z".to_string()),
         )],
@@ -42,6 +42,8 @@ fn example_with_contradictions() -> Reasoner {
             )
         ],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![
             (
                 Term::Constant(0),
@@ -66,6 +68,8 @@ fn example_with_contradictions() -> Reasoner {
             Term::Variable("Y".to_string())
         )],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![
             (
                 Term::Variable("X".to_string()),
@@ -84,6 +88,8 @@ fn example_with_contradictions() -> Reasoner {
             Term::Variable("Y".to_string())
         )],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![
             (
                 Term::Variable("X".to_string()),
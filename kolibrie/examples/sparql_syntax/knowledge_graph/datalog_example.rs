@@ -30,6 +30,8 @@ fn main() {
                           Term::Variable("Y".to_string())),
         ],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![(Term::Variable("X".to_string()), 
                                   Term::Constant(ancestor_id), 
                                   Term::Variable("Y".to_string())),],
@@ -46,6 +48,8 @@ fn main() {
                           Term::Variable("Z".to_string())),
         ],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![(Term::Variable("X".to_string()), 
                                   Term::Constant(ancestor_id), 
                                   Term::Variable("Z".to_string())),],
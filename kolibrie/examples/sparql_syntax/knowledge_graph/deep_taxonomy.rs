@@ -81,6 +81,8 @@ fn main() {
             ),
         ],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![
             (
                 Term::Variable("X".to_string()),
@@ -29,6 +29,8 @@ pub fn setup_example_hierarchy() -> ReasoningHierarchy {
             Term::Constant(1), // "man"
         )],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![(
             Term::Variable("x".to_string()),
             Term::Constant(2), // "is"
@@ -47,6 +49,8 @@ pub fn setup_example_hierarchy() -> ReasoningHierarchy {
                 Term::Constant(3), // "mortal"
             )],
             negative_premise: vec![],
+            bindings: vec![],
+            aggregates: vec![],
             conclusion: vec![(
                 Term::Variable("x".to_string()),
                 Term::Constant(4), // "might_have"
@@ -130,6 +130,8 @@ fn create_mortality_rule(hierarchy: &mut ReasoningHierarchy) -> Rule {
             Term::Constant(human_id),
         )],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![(
             Term::Variable("x".to_string()),
             Term::Constant(is_a_id),
@@ -161,6 +163,8 @@ fn create_teaching_transitivity_rule(hierarchy: &mut ReasoningHierarchy) -> Rule
             ),
         ],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![(
             Term::Variable("x".to_string()),
             Term::Constant(influences_id),
@@ -186,6 +190,8 @@ fn create_wisdom_rule(hierarchy: &mut ReasoningHierarchy) -> Rule {
             Term::Variable("y".to_string()),
         )],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![(
             Term::Variable("x".to_string()),
             Term::Constant(is_a_id),
@@ -221,6 +227,8 @@ fn create_soul_hypothesis_rule(hierarchy: &mut ReasoningHierarchy) -> Hierarchic
                 ),
             ],
             negative_premise: vec![],
+            bindings: vec![],
+            aggregates: vec![],
             conclusion: vec![(
                 Term::Variable("x".to_string()),
                 Term::Constant(might_have_id),
@@ -251,6 +259,8 @@ fn create_memory_hypothesis_rule(hierarchy: &mut ReasoningHierarchy) -> Hierarch
                 Term::Variable("y".to_string()),
             )],
             negative_premise: vec![],
+            bindings: vec![],
+            aggregates: vec![],
             conclusion: vec![(
                 Term::Variable("x".to_string()),
                 Term::Constant(might_be_id),
@@ -291,6 +301,8 @@ fn create_significance_meta_rule(hierarchy: &mut ReasoningHierarchy) -> Hierarch
                 ),
             ],
             negative_premise: vec![],
+            bindings: vec![],
+            aggregates: vec![],
             conclusion: vec![(
                 Term::Variable("x".to_string()),
                 Term::Constant(is_a_id),
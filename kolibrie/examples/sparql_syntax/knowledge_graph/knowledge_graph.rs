@@ -41,6 +41,8 @@ fn knowledge_graph() {
             ),
         ],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![(
             Term::Variable("X".to_string()),
             Term::Constant(has_grandparent_id),
@@ -90,6 +92,8 @@ fn backward_chaining() {
             Term::Variable("Y".to_string()),
         )],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![(
             Term::Variable("X".to_string()),
             Term::Constant(ancestor),
@@ -113,6 +117,8 @@ fn backward_chaining() {
             ),
         ],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![(
             Term::Variable("X".to_string()),
             Term::Constant(ancestor),
@@ -257,6 +263,8 @@ fn inconsistency() {
             )
         ],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![(
             Term::Constant(0), // Dummy values for constraint
             Term::Constant(0),
@@ -45,6 +45,8 @@ fn test1() {
             ),
         ],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![(
             Term::Variable("x".to_string()),
             Term::Constant(likes_id),
@@ -61,6 +63,8 @@ fn test1() {
             Term::Variable("y".to_string()),
         )],
         negative_premise: vec![],
+        bindings: vec![],
+        aggregates: vec![],
         conclusion: vec![(
             Term::Variable("y".to_string()),
             Term::Constant(likes_id),
@@ -132,6 +136,8 @@ fn test2() {
         let rule = Rule {
             premise: vec![premise_pattern],
             negative_premise: vec![],
+            bindings: vec![],
+            aggregates: vec![],
             conclusion: conclusion_pattern,
             filters: vec![],
         };
@@ -201,6 +207,8 @@ fn transitivity_benchmark() {
                 ),
             ],
             negative_premise: vec![],
+            bindings: vec![],
+            aggregates: vec![],
             conclusion: vec![(
                 Term::Variable("x".to_string()),
                 Term::Constant(likes_id),
@@ -0,0 +1,484 @@
+/*
+ * Copyright © 2025 Volodymyr Kadzhaia
+ * Copyright © 2025 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Adaptive join-order planning for RSP windows.
+//!
+//! On every window firing the planner gathers `ContainerStats` for the window content,
+//! compares them with a baseline from an earlier firing and, when the window's
+//! `ReplanTrigger` fires, recomputes the join order with `join_reordering`. The first
+//! firing of a window always gets a stats-based initial plan. After each execution the
+//! engine reports the actual operator output sizes, from which the q-error of the
+//! running plan's estimates is derived for `ReplanTrigger::OnEstimateError`.
+
+use crate::container_stats::ContainerStats;
+use crate::experiment_logging;
+use crate::join_reordering;
+use crate::rsp::s2r::ContentContainer;
+use crate::stream_estimator::StreamEstimator;
+use crate::streamertail_optimizer::{ExecutionProfile, LogicalOperator, PhysicalOperator};
+#[cfg(not(test))]
+use log::{debug, warn};
+use shared::triple::Triple;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+#[cfg(test)]
+use std::{println as debug, println as warn};
+
+pub use shared::query::ReplanTrigger;
+
+/// Why a window was replanned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReplanReason {
+    /// First firing of the window.
+    Initial,
+    /// `ReplanTrigger::Always`.
+    Always,
+    SizeChange,
+    DistributionChange,
+    RankingChange,
+    /// The running plan's cardinality estimates drifted past the q-error threshold.
+    EstimateError,
+}
+
+/// How a new plan is computed once a trigger fires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PlanStrategy {
+    /// Cheapest of the current plan and its restructured neighbours.
+    #[default]
+    Incremental,
+    /// Cheapest join order of the window's logical plan, enumerated with DPccp.
+    Exhaustive,
+}
+
+/// Which earlier firing the current statistics are compared against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatsBaseline {
+    /// The firing that produced the current plan.
+    #[default]
+    LastReplan,
+    /// The immediately preceding firing.
+    PreviousFiring,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdaptivePlannerConfig {
+    /// Trigger for windows without a `WITH REPLAN` clause.
+    pub trigger: ReplanTrigger,
+    pub strategy: PlanStrategy,
+    pub baseline: StatsBaseline,
+    /// Number of past firings whose statistics are kept per window.
+    pub history: usize,
+}
+
+impl Default for AdaptivePlannerConfig {
+    fn default() -> Self {
+        AdaptivePlannerConfig {
+            trigger: ReplanTrigger::Static,
+            strategy: PlanStrategy::Incremental,
+            baseline: StatsBaseline::LastReplan,
+            history: 16,
+        }
+    }
+}
+
+/// How often and why the planner replanned.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlannerMetrics {
+    /// Firings the planner was consulted for.
+    pub firings: u64,
+    pub replans: u64,
+    /// Replans that produced a plan different from the one it replaced.
+    pub plan_changes: u64,
+    pub reasons: HashMap<ReplanReason, u64>,
+    /// Time spent gathering and comparing statistics.
+    pub stats_time: Duration,
+    /// Time spent computing new plans.
+    pub planning_time: Duration,
+    /// Q-error of the most recent execution, if it could be measured. Summed metrics
+    /// report the largest value over all windows.
+    pub last_q_error: Option<f64>,
+}
+
+impl PlannerMetrics {
+    fn merge(&mut self, other: &PlannerMetrics) {
+        self.firings += other.firings;
+        self.replans += other.replans;
+        self.plan_changes += other.plan_changes;
+        for (reason, count) in &other.reasons {
+            *self.reasons.entry(*reason).or_insert(0) += count;
+        }
+        self.stats_time += other.stats_time;
+        self.planning_time += other.planning_time;
+        self.last_q_error = match (self.last_q_error, other.last_q_error) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+    }
+}
+
+/// Evaluate `trigger` for the current statistics against `baseline`.
+/// `OnEstimateError` depends on execution feedback rather than statistics and never
+/// fires here.
+pub fn trigger_reason(
+    trigger: &ReplanTrigger,
+    current: &ContainerStats,
+    baseline: &ContainerStats,
+) -> Option<ReplanReason> {
+    match trigger {
+        ReplanTrigger::Static => None,
+        ReplanTrigger::Always => Some(ReplanReason::Always),
+        ReplanTrigger::OnSizeChange { threshold } => {
+            (current.size_change_ratio(baseline) > *threshold).then_some(ReplanReason::SizeChange)
+        }
+        ReplanTrigger::OnDistributionChange { threshold } => (current.object_distribution_distance(baseline)
+            > *threshold)
+            .then_some(ReplanReason::DistributionChange),
+        ReplanTrigger::OnRankingChange { threshold } => {
+            (current.object_rank_change_ratio(baseline) > *threshold).then_some(ReplanReason::RankingChange)
+        }
+        ReplanTrigger::Hybrid {
+            size_threshold,
+            distribution_threshold,
+            ranking_threshold,
+        } => {
+            if current.size_change_ratio(baseline) > *size_threshold {
+                Some(ReplanReason::SizeChange)
+            } else if current.object_distribution_distance(baseline) > *distribution_threshold {
+                Some(ReplanReason::DistributionChange)
+            } else if current.object_rank_change_ratio(baseline) > *ranking_threshold {
+                Some(ReplanReason::RankingChange)
+            } else {
+                None
+            }
+        }
+        ReplanTrigger::OnEstimateError { .. } => None,
+    }
+}
+
+fn trigger_threshold(trigger: &ReplanTrigger) -> Option<f64> {
+    match trigger {
+        ReplanTrigger::OnSizeChange { threshold }
+        | ReplanTrigger::OnDistributionChange { threshold }
+        | ReplanTrigger::OnRankingChange { threshold }
+        | ReplanTrigger::OnEstimateError { threshold, .. } => Some(*threshold),
+        _ => None,
+    }
+}
+
+struct WindowState {
+    logical_plan: LogicalOperator,
+    trigger: ReplanTrigger,
+    baseline: Option<ContainerStats>,
+    history: VecDeque<ContainerStats>,
+    metrics: PlannerMetrics,
+    /// Q-error of the last execution, not yet consumed by a firing.
+    q_error: Option<f64>,
+    /// Consecutive executions whose q-error exceeded the `OnEstimateError` threshold.
+    drift_streak: usize,
+}
+
+impl WindowState {
+    /// Replan once the q-error has exceeded `threshold` in `patience` consecutive
+    /// executions, so a single outlier window does not make the plan flap.
+    fn estimate_error_reason(&mut self, threshold: f64, patience: usize) -> Option<ReplanReason> {
+        match self.q_error.take() {
+            Some(q_error) if q_error > threshold => {
+                self.drift_streak += 1;
+                (self.drift_streak >= patience).then_some(ReplanReason::EstimateError)
+            }
+            Some(_) => {
+                self.drift_streak = 0;
+                None
+            }
+            // No feedback for the last execution; keep the streak as it is.
+            None => None,
+        }
+    }
+}
+
+/// Per-window statistics history and replanning decisions, shared by the window
+/// workers of an `RSPEngine` (see `RSPEngine::set_adaptive_planner`).
+pub struct AdaptivePlanner {
+    config: AdaptivePlannerConfig,
+    windows: Mutex<HashMap<String, WindowState>>,
+}
+
+impl AdaptivePlanner {
+    pub fn new(config: AdaptivePlannerConfig) -> Self {
+        AdaptivePlanner {
+            config,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &AdaptivePlannerConfig {
+        &self.config
+    }
+
+    /// Register a window and the logical plan its join orders are derived from.
+    /// `trigger` overrides the configured default.
+    pub fn register_window(&self, window_iri: &str, logical_plan: LogicalOperator, trigger: Option<ReplanTrigger>) {
+        self.windows.lock().unwrap().insert(
+            window_iri.to_string(),
+            WindowState {
+                logical_plan,
+                trigger: trigger.unwrap_or_else(|| self.config.trigger.clone()),
+                baseline: None,
+                history: VecDeque::new(),
+                metrics: PlannerMetrics::default(),
+                q_error: None,
+                drift_streak: 0,
+            },
+        );
+    }
+
+    /// Consult the planner for one firing. Returns the plan to execute instead of
+    /// `current_plan`, or `None` to keep it.
+    pub fn on_firing(
+        &self,
+        window_iri: &str,
+        content: &ContentContainer<Triple>,
+        ts: usize,
+        current_plan: &PhysicalOperator,
+    ) -> Option<PhysicalOperator> {
+        let mut windows = self.windows.lock().unwrap();
+        let state = windows.get_mut(window_iri)?;
+        state.metrics.firings += 1;
+
+        let stats_start = Instant::now();
+        let current = ContainerStats::gather_stats(content);
+        let reason = match (&state.baseline, state.trigger.clone()) {
+            (None, _) => Some(ReplanReason::Initial),
+            (Some(_), ReplanTrigger::OnEstimateError { threshold, patience }) => {
+                state.estimate_error_reason(threshold, patience)
+            }
+            (Some(baseline), trigger) => trigger_reason(&trigger, &current, baseline),
+        };
+        let stats_time = stats_start.elapsed();
+        state.metrics.stats_time += stats_time;
+
+        state.history.push_back(current.clone());
+        while state.history.len() > self.config.history {
+            state.history.pop_front();
+        }
+
+        let planning_start = Instant::now();
+        let new_plan = reason.map(|reason| match (reason, self.config.strategy) {
+            (ReplanReason::Initial, _) | (_, PlanStrategy::Exhaustive) => {
+                join_reordering::calculate_initial_window_plan(state.logical_plan.clone(), current.clone())
+            }
+            (_, PlanStrategy::Incremental) => {
+                join_reordering::recalculate_window_plan(current_plan.clone(), current.clone())
+            }
+        });
+        let planning_time = planning_start.elapsed();
+
+        // Timings are only recorded for experiment runs.
+        if experiment_logging::is_initialized() {
+            let note = match reason {
+                Some(ReplanReason::Initial) => "initial",
+                Some(_) => "replan",
+                None => "no_change",
+            };
+            if let Err(error) = experiment_logging::append_experiment_row(
+                "optimize",
+                window_iri,
+                ts,
+                planning_time.as_secs_f64() * 1000.0,
+                Some(stats_time.as_secs_f64() * 1000.0),
+                content.len(),
+                None,
+                note,
+                trigger_threshold(&state.trigger),
+            ) {
+                warn!("Failed to write optimization timing for {}: {:?}", window_iri, error);
+            }
+        }
+
+        if reason.is_some() || self.config.baseline == StatsBaseline::PreviousFiring {
+            state.baseline = Some(current);
+        }
+        let (reason, new_plan) = (reason?, new_plan?);
+        state.drift_streak = 0;
+        state.metrics.replans += 1;
+        state.metrics.planning_time += planning_time;
+        *state.metrics.reasons.entry(reason).or_insert(0) += 1;
+        if new_plan != *current_plan {
+            state.metrics.plan_changes += 1;
+        }
+        debug!("Replanned window {} at {} ({:?})", window_iri, ts, reason);
+        Some(new_plan)
+    }
+
+    /// Record the actual operator output sizes of the execution that followed the last
+    /// firing of a window, and the resulting q-error of the plan's estimates.
+    pub fn on_execution(&self, window_iri: &str, plan: &PhysicalOperator, profile: &ExecutionProfile) {
+        let mut windows = self.windows.lock().unwrap();
+        let Some(state) = windows.get_mut(window_iri) else {
+            return;
+        };
+        let Some(stats) = state.history.back() else {
+            return;
+        };
+        let q_error = StreamEstimator::new(stats.clone()).max_q_error(plan, profile);
+        debug!("Window {} executed with q-error {:?}", window_iri, q_error);
+        state.q_error = q_error;
+        state.metrics.last_q_error = q_error;
+    }
+
+    /// Metrics per registered window.
+    pub fn metrics(&self) -> HashMap<String, PlannerMetrics> {
+        self.windows
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(window_iri, state)| (window_iri.clone(), state.metrics.clone()))
+            .collect()
+    }
+
+    /// Metrics summed over all windows.
+    pub fn total_metrics(&self) -> PlannerMetrics {
+        let mut total = PlannerMetrics::default();
+        for state in self.windows.lock().unwrap().values() {
+            total.merge(&state.metrics);
+        }
+        total
+    }
+
+    /// Statistics of the most recent firings of a window, oldest first.
+    pub fn stats_history(&self, window_iri: &str) -> Vec<ContainerStats> {
+        self.windows
+            .lock()
+            .unwrap()
+            .get(window_iri)
+            .map(|state| state.history.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sparql_database::SparqlDatabase;
+    use shared::terms::Term;
+
+    fn stats(objects: &[(u32, i64)]) -> ContainerStats {
+        let mut stats = ContainerStats::new();
+        for (object, count) in objects {
+            stats.object_cardinalities.insert(*object, *count);
+            stats.total_triples += count;
+        }
+        stats
+    }
+
+    #[test]
+    fn triggers_report_the_first_exceeded_threshold() {
+        let baseline = stats(&[(1, 10), (2, 10)]);
+        let grown = stats(&[(1, 20), (2, 20)]);
+        let shifted = stats(&[(1, 2), (2, 18)]);
+
+        assert_eq!(trigger_reason(&ReplanTrigger::Static, &grown, &baseline), None);
+        assert_eq!(trigger_reason(&ReplanTrigger::Always, &baseline, &baseline), Some(ReplanReason::Always));
+        assert_eq!(
+            trigger_reason(&ReplanTrigger::OnSizeChange { threshold: 0.5 }, &grown, &baseline),
+            Some(ReplanReason::SizeChange)
+        );
+        assert_eq!(trigger_reason(&ReplanTrigger::OnSizeChange { threshold: 0.5 }, &shifted, &baseline), None);
+        let hybrid = ReplanTrigger::Hybrid {
+            size_threshold: 0.5,
+            distribution_threshold: 0.1,
+            ranking_threshold: 1.0,
+        };
+        assert_eq!(trigger_reason(&hybrid, &shifted, &baseline), Some(ReplanReason::DistributionChange));
+    }
+
+    #[test]
+    fn estimate_error_needs_consecutive_drifting_executions() {
+        let planner = AdaptivePlanner::new(AdaptivePlannerConfig::default());
+        let scan = LogicalOperator::scan((Term::Variable("s".into()), Term::Constant(1), Term::Variable("o".into())));
+        planner.register_window(":w", scan, Some(ReplanTrigger::OnEstimateError { threshold: 2.0, patience: 2 }));
+        let mut windows = planner.windows.lock().unwrap();
+        let state = windows.get_mut(":w").unwrap();
+
+        let mut fire = |q_error: Option<f64>| {
+            state.q_error = q_error;
+            state.estimate_error_reason(2.0, 2)
+        };
+        assert_eq!(fire(Some(5.0)), None);
+        // A single accurate execution resets the streak.
+        assert_eq!(fire(Some(1.5)), None);
+        assert_eq!(fire(Some(5.0)), None);
+        // Missing feedback neither extends nor resets it.
+        assert_eq!(fire(None), None);
+        assert_eq!(fire(Some(3.0)), Some(ReplanReason::EstimateError));
+    }
+
+    #[test]
+    fn plans_with_values_and_bind_match_the_unoptimized_plan() {
+        let mut database = SparqlDatabase::new();
+        let mut encode = |term: &str| database.dictionary.write().unwrap().encode(term);
+        let [a, b, p, q, x, y, z] = ["a", "b", "p", "q", "x", "y", "z"].map(&mut encode);
+        let triples = vec![
+            Triple { subject: a, predicate: p, object: x },
+            Triple { subject: b, predicate: p, object: y },
+            Triple { subject: a, predicate: q, object: z },
+            Triple { subject: b, predicate: q, object: z },
+        ];
+        for triple in &triples {
+            database.add_triple(triple.clone());
+        }
+
+        let var = |name: &str| Term::Variable(name.into());
+        let by_p = (var("s"), Term::Constant(p), var("o"));
+        let by_q = (var("s"), Term::Constant(q), var("z"));
+        let values = || vec![vec![Some("a".to_string())], vec![Some("c".to_string())]];
+        let logical = LogicalOperator::join(
+            LogicalOperator::join(LogicalOperator::scan(by_p.clone()), LogicalOperator::scan(by_q.clone())),
+            LogicalOperator::bind(
+                LogicalOperator::values(vec!["s".into()], values()),
+                "CONCAT".into(),
+                vec!["?s".into()],
+                "label".into(),
+            ),
+        );
+        let unoptimized = PhysicalOperator::hash_join(
+            PhysicalOperator::hash_join(PhysicalOperator::table_scan(by_p), PhysicalOperator::table_scan(by_q)),
+            PhysicalOperator::bind(
+                PhysicalOperator::values(vec!["s".into()], values()),
+                "CONCAT".into(),
+                vec!["?s".into()],
+                "label".into(),
+            ),
+        );
+
+        let planner = AdaptivePlanner::new(AdaptivePlannerConfig::default());
+        planner.register_window(":w", logical, None);
+        let content = ContentContainer::from_items(triples, 1);
+        let planned = planner.on_firing(":w", &content, 1, &unoptimized).expect("the first firing plans the window");
+
+        let mut rows = |plan: &PhysicalOperator| {
+            let mut rows: Vec<Vec<(String, u32)>> = plan
+                .execute_with_ids(&mut database)
+                .into_iter()
+                .map(|row| {
+                    let mut row: Vec<(String, u32)> = row.into_iter().collect();
+                    row.sort();
+                    row
+                })
+                .collect();
+            rows.sort();
+            rows
+        };
+        let expected = rows(&unoptimized);
+        assert_eq!(expected.len(), 1);
+        assert_eq!(rows(&planned), expected);
+    }
+}
@@ -469,6 +469,8 @@ mod tests {
                 (sample.clone(), right_pred, right_value),
             ],
             negative_premise: vec![],
+            bindings: vec![],
+            aggregates: vec![],
             filters: Vec::<FilterCondition>::new(),
             conclusion: vec![(sample, ok_pred, yes_value)],
         });
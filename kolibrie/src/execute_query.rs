@@ -15,7 +15,11 @@ use crate::neural_relations::{
     execute_train_decl, materialize_neural_relations_for_patterns, register_neural_declarations,
 };
 use crate::parser::*;
+use crate::plan_cache::{PlanCache, LITERAL_VARIABLE_PREFIX};
+use crate::prepared_query::{bind_parameters, BoundValue};
+use crate::query_context::{run_with_context, OperatorBudget, QueryContext, QueryError};
 use shared::query::*;
+use shared::rule::{format_number, numeric_value};
 use shared::triple::Triple;
 use shared::GPU_MODE_ENABLED;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
@@ -221,8 +225,27 @@ pub fn execute_query(sparql: &str, database: &mut SparqlDatabase) -> Vec<Vec<Str
         }
 
         // Process the INSERT clause if present
+        let is_update = insert_clause.is_some();
         process_insert_clause(insert_clause, database);
 
+        let rule_predicates = database
+            .rule_map
+            .values()
+            .cloned()
+            .collect::<std::collections::HashSet<String>>();
+        let references_rules = patterns.iter().any(|(_, predicate, object_var)| {
+            *object_var == "true" && rule_predicates.contains(&expand_prefixed_predicate(predicate, &prefixes))
+        });
+
+        // Plain SELECT queries run the plan the optimizer picks, taken from
+        // the plan cache when the query shape was planned before
+        if !is_update && !references_rules && subqueries.is_empty() && values_clause.is_none() {
+            if let Ok(planned) = plan_query(sparql, database, false) {
+                let rows = ExecutionEngine::execute(&planned.plan, database);
+                return finish_planned_rows(&planned, rows);
+            }
+        }
+
         // If SELECT * is used, gather all variables from patterns
         if variables == vec![("*", "*", None)] {
             let mut all_vars = BTreeSet::new();
@@ -245,12 +268,6 @@ pub fn execute_query(sparql: &str, database: &mut SparqlDatabase) -> Vec<Vec<Str
         // Initialize final_results based on the VALUES clause
         final_results = initialize_results(&values_clause);
 
-        let rule_predicates = database
-            .rule_map
-            .values()
-            .cloned()
-            .collect::<std::collections::HashSet<String>>();
-
         // Process each pattern in the WHERE clause
         for (subject_var, predicate, object_var) in patterns {
             if predicate == "RULECALL" {
@@ -259,16 +276,7 @@ pub fn execute_query(sparql: &str, database: &mut SparqlDatabase) -> Vec<Vec<Str
             }
 
             // Process direct rule conclusion reference: ?room ex:overheatingAlert true
-            let resolved_predicate = if predicate.contains(':') {
-                let parts: Vec<&str> = predicate.split(':').collect();
-                if parts.len() == 2 && prefixes.contains_key(parts[0]) {
-                    format!("{}{}", prefixes[parts[0]], parts[1])
-                } else {
-                    predicate.to_string()
-                }
-            } else {
-                predicate.to_string()
-            };
+            let resolved_predicate = expand_prefixed_predicate(predicate, &prefixes);
 
             // Check if this is a direct reference to a rule conclusion
             if rule_predicates.contains(&resolved_predicate) && object_var == "true" {
@@ -755,43 +763,47 @@ pub fn execute_query_rayon_parallel2_volcano(
                 .build()
                 . unwrap();*/
 
-            // Use Volcano optimizer for CPU execution
-            let mut logical_plan = build_logical_plan(
-                selected_variables
-                    .iter()
-                    .map(|(t, v)| (t.as_str(), v.as_str()))
-                    .collect(),
-                resolved_patterns,
-                filters.clone(),
-                &prefixes,
-                database,
-                &binds,
-                values_clause.as_ref(),
-            ); 
-
-            // Integrate subqueries into the logical plan
-            for subquery in &subqueries {
-                let subquery_plan = build_logical_plan_from_subquery(
-                    subquery,
-                    &prefixes,
-                    database,
-                );
-            
-                // Join the subquery with the main query
-                logical_plan = LogicalOperator::join(logical_plan, subquery_plan);
-            }
+            // Use Volcano optimizer for CPU execution, through the plan cache
+            let optimized_plan = match plan_query(sparql, database, false) {
+                Ok(planned) => planned.plan,
+                Err(_) => {
+                    let mut logical_plan = build_logical_plan(
+                        selected_variables
+                            .iter()
+                            .map(|(t, v)| (t.as_str(), v.as_str()))
+                            .collect(),
+                        resolved_patterns,
+                        filters.clone(),
+                        &prefixes,
+                        database,
+                        &binds,
+                        values_clause.as_ref(),
+                    );
 
-            if database.cached_stats.is_none() {
-                database.get_or_build_stats();
-            }
-            
-            let stats = database
-                .cached_stats
-                .as_ref()
-                .expect("database stats should be available");
-            let mut optimizer = Streamertail::with_cached_stats(stats.clone());
+                    // Integrate subqueries into the logical plan
+                    for subquery in &subqueries {
+                        let subquery_plan = build_logical_plan_from_subquery(
+                            subquery,
+                            &prefixes,
+                            database,
+                        );
 
-            let optimized_plan = optimizer.find_best_plan(&logical_plan);
+                        // Join the subquery with the main query
+                        logical_plan = LogicalOperator::join(logical_plan, subquery_plan);
+                    }
+
+                    if database.cached_stats.is_none() {
+                        database.get_or_build_stats();
+                    }
+
+                    let stats = database
+                        .cached_stats
+                        .as_ref()
+                        .expect("database stats should be available");
+                    let mut optimizer = Streamertail::with_cached_stats(stats.clone());
+                    optimizer.find_best_plan(&logical_plan)
+                }
+            };
             let results = optimized_plan.execute(database);
             /*if let Ok(report) = guard.report().build() {
                 let file = std::fs::File::create("streamertail_optimizer_flamegraph.svg").unwrap();
@@ -864,6 +876,547 @@ pub fn execute_query_rayon_parallel2_volcano(
     Vec::new()
 }
 
+/// Runs `execute_query` within `context`, so a query running past its
+/// deadline, cancelled or exceeding its memory limit stops with an error
+pub fn execute_query_with_context(
+    sparql: &str,
+    database: &mut SparqlDatabase,
+    context: &QueryContext,
+) -> Result<Vec<Vec<String>>, QueryError> {
+    run_with_context(context, || execute_query(sparql, database))
+}
+
+/// Runs `execute_query_rayon_parallel2_volcano` within `context`
+pub fn execute_query_volcano_with_context(
+    sparql: &str,
+    database: &mut SparqlDatabase,
+    context: &QueryContext,
+) -> Result<Vec<Vec<String>>, QueryError> {
+    run_with_context(context, || execute_query_rayon_parallel2_volcano(sparql, database))
+}
+
+/// Rows of a SPARQL SELECT query, produced as they are pulled.
+///
+/// Plain basic graph patterns with filters, binds and projections are
+/// evaluated through a pipelined [`ResultCursor`], so `LIMIT` and callers that
+/// stop early never compute the rest of the result. Queries that need their
+/// whole result first (ORDER BY, GROUP BY, subqueries, VALUES, updates, ...)
+/// are evaluated eagerly and their rows handed out one at a time.
+pub struct QueryResultIter<'a> {
+    variables: Vec<String>,
+    rows: Box<dyn Iterator<Item = Vec<String>> + 'a>,
+}
+
+impl QueryResultIter<'_> {
+    /// The selected variables, in the order of the values in every row
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    /// Pulls up to `size` rows; an empty batch means the query is exhausted
+    pub fn next_batch(&mut self, size: usize) -> Vec<Vec<String>> {
+        self.rows.by_ref().take(size).collect()
+    }
+}
+
+impl Iterator for QueryResultIter<'_> {
+    type Item = Vec<String>;
+
+    fn next(&mut self) -> Option<Vec<String>> {
+        self.rows.next()
+    }
+}
+
+/// Optimized physical plan of a SELECT query, with the solution modifiers
+/// applied to the rows it produces
+#[derive(Debug, Clone)]
+pub(crate) struct PlannedQuery {
+    pub(crate) plan: PhysicalOperator,
+    /// Selected variables, in output order
+    pub(crate) variables: Vec<String>,
+    pub(crate) limit: Option<usize>,
+    /// Whether rows can be produced without the whole result (no GROUP BY,
+    /// ORDER BY, subqueries or VALUES)
+    pub(crate) pipelined: bool,
+    pub(crate) group_by: Vec<String>,
+    /// Aggregates as `(function, variable, output variable)`
+    pub(crate) aggregates: Vec<(String, String, String)>,
+    pub(crate) order_by: Vec<(String, SortDirection)>,
+}
+
+/// Plan `Streamertail` picks for `sparql`, taken from the database's plan
+/// cache when the same query shape was planned before. Updates, rule calls
+/// and neural declarations cannot be planned on their own; with
+/// `pipelined_only`, neither can queries whose solution modifiers need the
+/// whole result first (GROUP BY, ORDER BY, subqueries, VALUES).
+pub(crate) fn plan_query(
+    sparql: &str,
+    database: &mut SparqlDatabase,
+    pipelined_only: bool,
+) -> Result<PlannedQuery, String> {
+    // Neural relations are materialized while planning, so their plans are not reused
+    let cacheable = database.neural_relation_decls.is_empty();
+    if !cacheable {
+        let planned = build_plan(sparql, database)?;
+        if pipelined_only && !planned.pipelined {
+            return Err("query needs its whole result before producing rows".to_string());
+        }
+        return Ok(planned);
+    }
+
+    // Queries differing only in literals of similar selectivity share the
+    // plan of their shape, in which the literals are variables bound for
+    // this execution
+    database.register_prefixes_from_query(sparql);
+    let shape = PlanCache::abstract_literals(sparql);
+    let key = PlanCache::selectivity_key(&shape, database);
+    let mut planned = match database.plan_cache.get(&key, database.triples.len()) {
+        Some(planned) => planned,
+        None => {
+            let mut planned = build_plan(&shape.sparql, database)?;
+            planned.variables.retain(|var| !var.starts_with(LITERAL_VARIABLE_PREFIX));
+            database.plan_cache.insert(key, planned.clone(), database.triples.len());
+            planned
+        }
+    };
+    if !shape.literals.is_empty() {
+        let values: HashMap<String, BoundValue> = shape
+            .literals
+            .iter()
+            .enumerate()
+            .map(|(index, literal)| {
+                let name = format!("{}{}", LITERAL_VARIABLE_PREFIX, index);
+                (name[1..].to_string(), BoundValue::new(literal, database))
+            })
+            .collect();
+        planned.plan = bind_parameters(&planned.plan, &values);
+    }
+    if pipelined_only && !planned.pipelined {
+        return Err("query needs its whole result before producing rows".to_string());
+    }
+    Ok(planned)
+}
+
+/// Parses `sparql` and builds the plan `Streamertail` picks for it
+fn build_plan(sparql: &str, database: &mut SparqlDatabase) -> Result<PlannedQuery, String> {
+    let sparql = normalize_query(sparql);
+    database.register_prefixes_from_query(sparql);
+
+    let combined = match parse_combined_query(sparql) {
+        Ok((_, combined)) => combined,
+        Err(err) => return Err(format_parse_error(sparql, err)),
+    };
+    let (
+        insert_clause,
+        mut variables,
+        patterns,
+        filters,
+        group_vars,
+        parsed_prefixes,
+        values_clause,
+        binds,
+        subqueries,
+        limit,
+        _,
+        order_conditions,
+    ) = combined.sparql;
+
+    if insert_clause.is_some() || combined.delete_clause.is_some() {
+        return Err("updates have no query plan".to_string());
+    }
+    if !combined.model_decls.is_empty()
+        || !combined.neural_relation_decls.is_empty()
+        || !combined.train_neural_relation_decls.is_empty()
+    {
+        return Err("queries declaring models or neural relations cannot be planned".to_string());
+    }
+    if patterns.iter().any(|(_, predicate, _)| *predicate == "RULECALL") {
+        return Err("rule calls cannot be planned".to_string());
+    }
+    if GPU_MODE_ENABLED.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err("queries in GPU mode are not planned by the optimizer".to_string());
+    }
+
+    if variables == vec![("*", "*", None)] {
+        let mut all_vars = BTreeSet::new();
+        for (subject_var, predicate_var, object_var) in &patterns {
+            all_vars.insert(*subject_var);
+            all_vars.insert(*predicate_var);
+            all_vars.insert(*object_var);
+        }
+        variables = all_vars.into_iter().map(|var| ("VAR", var, None)).collect();
+    }
+    let mut selected_variables: Vec<(String, String)> = Vec::new();
+    let mut aggregation_vars: Vec<(&str, &str, &str)> = Vec::new();
+    process_variables(&mut selected_variables, &mut aggregation_vars, variables);
+
+    let pipelined = group_vars.is_empty()
+        && aggregation_vars.is_empty()
+        && values_clause.is_none()
+        && subqueries.is_empty()
+        && order_conditions.is_empty();
+
+    let mut prefixes = combined.prefixes.clone();
+    prefixes.extend(parsed_prefixes);
+    database.share_prefixes_with(&mut prefixes);
+    materialize_neural_relations_for_patterns(database, &patterns, &prefixes)
+        .map_err(|err| format!("Failed to materialize neural relations: {}", err))?;
+
+    let resolved_patterns: Vec<(&str, &str, &str)> = patterns
+        .iter()
+        .map(|(subject_var, predicate, object_var)| {
+            let (resolved_subject, resolved_predicate, resolved_object) =
+                resolve_triple_pattern(subject_var, predicate, object_var, database, &prefixes);
+            let subject_static: &'static str = Box::leak(resolved_subject.into_boxed_str());
+            let predicate_static: &'static str = Box::leak(resolved_predicate.into_boxed_str());
+            let object_static: &'static str = Box::leak(resolved_object.into_boxed_str());
+            (subject_static, predicate_static, object_static)
+        })
+        .collect();
+
+    let mut logical_plan = build_logical_plan(
+        selected_variables
+            .iter()
+            .map(|(t, v)| (t.as_str(), v.as_str()))
+            .collect(),
+        resolved_patterns,
+        filters,
+        &prefixes,
+        database,
+        &binds,
+        values_clause.as_ref(),
+    );
+    for subquery in &subqueries {
+        let subquery_plan = build_logical_plan_from_subquery(subquery, &prefixes, database);
+        logical_plan = LogicalOperator::join(logical_plan, subquery_plan);
+    }
+
+    if database.cached_stats.is_none() {
+        database.get_or_build_stats();
+    }
+    let stats = database
+        .cached_stats
+        .as_ref()
+        .expect("database stats should be available");
+    let mut optimizer = Streamertail::with_cached_stats(stats.clone());
+
+    Ok(PlannedQuery {
+        plan: optimizer.find_best_plan(&logical_plan),
+        variables: selected_variables.into_iter().map(|(_, var)| var).collect(),
+        limit,
+        pipelined,
+        group_by: group_vars.iter().map(|var| var.to_string()).collect(),
+        aggregates: aggregation_vars
+            .iter()
+            .map(|(function, var, output)| (function.to_string(), var.to_string(), output.to_string()))
+            .collect(),
+        order_by: order_conditions
+            .iter()
+            .map(|condition| (condition.variable.to_string(), condition.direction.clone()))
+            .collect(),
+    })
+}
+
+/// Solution modifier of a planned query, applied to the rows of its plan
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SolutionModifier {
+    Group,
+    Order,
+    Limit,
+}
+
+impl PlannedQuery {
+    /// Solution modifiers the query applies, in order
+    pub(crate) fn modifiers(&self) -> Vec<SolutionModifier> {
+        let mut modifiers = Vec::new();
+        if !self.group_by.is_empty() {
+            modifiers.push(SolutionModifier::Group);
+        }
+        if !self.order_by.is_empty() {
+            modifiers.push(SolutionModifier::Order);
+        }
+        if self.limit.is_some_and(|limit| limit > 0) {
+            modifiers.push(SolutionModifier::Limit);
+        }
+        modifiers
+    }
+}
+
+/// Applies the GROUP BY, ORDER BY and LIMIT of a planned query to the rows
+/// its plan produced, the way `execute_query_rayon_parallel2_volcano` does
+pub(crate) fn finish_planned_rows(planned: &PlannedQuery, rows: Vec<HashMap<String, String>>) -> Vec<Vec<String>> {
+    finish_planned_rows_observed(planned, rows, |_, _, _| {})
+}
+
+/// Like [`finish_planned_rows`], reporting the rows each solution modifier
+/// produced and the time it took
+fn finish_planned_rows_observed(
+    planned: &PlannedQuery,
+    rows: Vec<HashMap<String, String>>,
+    mut observe: impl FnMut(SolutionModifier, usize, std::time::Duration),
+) -> Vec<Vec<String>> {
+    let keys: BTreeSet<String> = rows
+        .iter()
+        .flat_map(|row| row.keys())
+        .map(|key| if key.starts_with('?') { key.clone() } else { format!("?{}", key) })
+        .collect();
+    let mut final_results: Vec<BTreeMap<&str, String>> = rows
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .filter_map(|(key, value)| {
+                    let prefixed = if key.starts_with('?') { key } else { format!("?{}", key) };
+                    keys.get(&prefixed).map(|key| (key.as_str(), value))
+                })
+                .collect()
+        })
+        .collect();
+
+    let group_vars: Vec<&str> = planned.group_by.iter().map(String::as_str).collect();
+    let aggregation_vars: Vec<(&str, &str, &str)> = planned
+        .aggregates
+        .iter()
+        .map(|(function, var, output)| (function.as_str(), var.as_str(), output.as_str()))
+        .collect();
+    if !group_vars.is_empty() {
+        let started = std::time::Instant::now();
+        final_results = group_and_aggregate_results(final_results, &group_vars, &aggregation_vars);
+        observe(SolutionModifier::Group, final_results.len(), started.elapsed());
+    }
+
+    let order_conditions: Vec<OrderCondition> = planned
+        .order_by
+        .iter()
+        .map(|(variable, direction)| OrderCondition { variable, direction: direction.clone() })
+        .collect();
+    if !order_conditions.is_empty() {
+        let started = std::time::Instant::now();
+        final_results = apply_order_by(final_results, order_conditions);
+        observe(SolutionModifier::Order, final_results.len(), started.elapsed());
+    }
+
+    if let Some(limit_value) = planned.limit {
+        if limit_value > 0 {
+            let started = std::time::Instant::now();
+            final_results.truncate(limit_value);
+            observe(SolutionModifier::Limit, final_results.len(), started.elapsed());
+        }
+    }
+
+    let selected_variables: Vec<(String, String)> = planned
+        .variables
+        .iter()
+        .map(|var| ("VAR".to_string(), var.clone()))
+        .collect();
+    format_results(final_results, &selected_variables)
+}
+
+/// Executes a SPARQL query lazily; see [`QueryResultIter`]
+pub fn execute_query_iter<'a>(sparql: &str, database: &'a mut SparqlDatabase) -> QueryResultIter<'a> {
+    let planned = match plan_query(sparql, database, true) {
+        Ok(planned) => planned,
+        Err(_) => {
+            // Evaluate eagerly, which also applies updates and reports parse errors
+            let variables = selected_column_names(sparql);
+            return QueryResultIter {
+                variables,
+                rows: Box::new(execute_query_rayon_parallel2_volcano(sparql, database).into_iter()),
+            };
+        }
+    };
+
+    let cursor = ExecutionEngine::execute_iter(&planned.plan, database);
+    let database = cursor.database();
+    let lookup_names = planned.variables.clone();
+    let rows = cursor.map(move |row| {
+        let dict = database.dictionary.read().unwrap();
+        let qt_store = database.quoted_triple_store.read().unwrap();
+        lookup_names
+            .iter()
+            .map(|var| {
+                row.get(var.strip_prefix('?').unwrap_or(var))
+                    .map(|&id| {
+                        dict.decode_term(id, &qt_store)
+                            .unwrap_or_else(|| "unknown".to_string())
+                    })
+                    .unwrap_or_default()
+            })
+            .collect()
+    });
+
+    QueryResultIter {
+        variables: planned.variables,
+        rows: match planned.limit {
+            Some(limit_value) if limit_value > 0 => Box::new(rows.take(limit_value)),
+            _ => Box::new(rows),
+        },
+    }
+}
+
+/// Names of the columns `execute_query_rayon_parallel2_volcano` returns for `sparql`
+fn selected_column_names(sparql: &str) -> Vec<String> {
+    let Ok((_, combined)) = parse_combined_query(normalize_query(sparql)) else {
+        return Vec::new();
+    };
+    let (_, mut variables, patterns, ..) = combined.sparql;
+    if variables == vec![("*", "*", None)] {
+        let all_vars: BTreeSet<&str> = patterns.iter().flat_map(|(s, p, o)| [*s, *p, *o]).collect();
+        variables = all_vars.into_iter().map(|var| ("VAR", var, None)).collect();
+    }
+    let mut selected_variables: Vec<(String, String)> = Vec::new();
+    process_variables(&mut selected_variables, &mut Vec::new(), variables);
+    selected_variables.into_iter().map(|(_, var)| var).collect()
+}
+
+/// How much `EXPLAIN` reports about a query
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExplainMode {
+    /// The chosen plan with estimated cardinality and cost per operator
+    Plan,
+    /// The plan executed, with actual row counts and timings per operator
+    Analyze,
+}
+
+/// Splits a leading `EXPLAIN` or `EXPLAIN ANALYZE` off a query
+pub fn strip_explain_prefix(sparql: &str) -> Option<(ExplainMode, &str)> {
+    let keyword = regex::Regex::new(r"(?i)^\s*EXPLAIN\b(\s+ANALYZE\b)?").unwrap();
+    let captures = keyword.captures(sparql)?;
+    let mode = if captures.get(1).is_some() {
+        ExplainMode::Analyze
+    } else {
+        ExplainMode::Plan
+    };
+    Some((mode, &sparql[captures.get(0)?.end()..]))
+}
+
+/// Explains the plan `Streamertail` picks for a query. The query may start
+/// with `EXPLAIN` or `EXPLAIN ANALYZE`; without a prefix only the plan is shown.
+pub fn explain_query(sparql: &str, database: &mut SparqlDatabase) -> Result<QueryExplanation, String> {
+    let (mode, query) = strip_explain_prefix(sparql).unwrap_or((ExplainMode::Plan, sparql));
+
+    // Planned afresh, so the planning time is that of the optimizer
+    let planning_started = std::time::Instant::now();
+    let planned = build_plan(query, database)?;
+    let planning_time_ms = planning_started.elapsed().as_secs_f64() * 1000.0;
+
+    let mut modifier_runs = Vec::new();
+    let (profile, execution_time_ms, total_rows) = match mode {
+        ExplainMode::Plan => (None, None, None),
+        ExplainMode::Analyze => {
+            let execution_started = std::time::Instant::now();
+            let (results, profile) = ExecutionEngine::execute_profiled(&planned.plan, database);
+            let total_rows = results.len();
+            finish_planned_rows_observed(&planned, results, |modifier, rows, elapsed| {
+                modifier_runs.push((modifier, rows, elapsed))
+            });
+            let execution_time_ms = execution_started.elapsed().as_secs_f64() * 1000.0;
+            (Some(profile), Some(execution_time_ms), Some(total_rows))
+        }
+    };
+
+    let stats = database
+        .cached_stats
+        .as_ref()
+        .expect("database stats should be available");
+    let dictionary = database.dictionary.read().unwrap();
+    Ok(QueryExplanation {
+        analyzed: mode == ExplainMode::Analyze,
+        planning_time_ms,
+        execution_time_ms,
+        total_rows,
+        plan: explain_modifiers(
+            &planned,
+            ExplainNode::new(&planned.plan, stats, &dictionary, profile.as_ref()),
+            profile.as_ref().and_then(|profile| profile.elapsed(&planned.plan)),
+            &modifier_runs,
+        ),
+    })
+}
+
+/// Puts a node for each solution modifier of the query above the plan's
+/// node. `runs` are the rows and times of the modifiers after EXPLAIN
+/// ANALYZE, and `plan_elapsed` the time the plan took.
+fn explain_modifiers(
+    planned: &PlannedQuery,
+    plan: ExplainNode,
+    plan_elapsed: Option<std::time::Duration>,
+    runs: &[(SolutionModifier, usize, std::time::Duration)],
+) -> ExplainNode {
+    let mut elapsed = plan_elapsed.unwrap_or_default();
+    planned.modifiers().into_iter().fold(plan, |input, modifier| {
+        let actual = runs
+            .iter()
+            .find(|(run, ..)| *run == modifier)
+            .map(|&(_, rows, time)| {
+                elapsed += time;
+                (rows, elapsed)
+            });
+        let (rows, cost) = (input.estimated_rows, input.estimated_cost);
+        match modifier {
+            SolutionModifier::Group => {
+                let aggregates = planned.aggregates.iter().map(|(function, var, output)| {
+                    format!("{}({}) AS {}", function, explain::format_variable(var), explain::format_variable(output))
+                });
+                let detail = format!(
+                    "by {}",
+                    planned
+                        .group_by
+                        .iter()
+                        .map(|var| explain::format_variable(var))
+                        .chain(aggregates)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                // At most one group per input row
+                ExplainNode::modifier("Aggregate", detail, rows, cost.saturating_add(rows), actual, input)
+            }
+            SolutionModifier::Order => {
+                let detail = planned
+                    .order_by
+                    .iter()
+                    .map(|(var, direction)| {
+                        let direction = match direction {
+                            SortDirection::Asc => "ASC",
+                            SortDirection::Desc => "DESC",
+                        };
+                        format!("{} {}", explain::format_variable(var), direction)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let sort_cost = (rows as f64 * (rows.max(2) as f64).log2()) as u64;
+                ExplainNode::modifier("Sort", detail, rows, cost.saturating_add(sort_cost), actual, input)
+            }
+            SolutionModifier::Limit => {
+                let limit = planned.limit.unwrap_or_default();
+                ExplainNode::modifier("Limit", limit.to_string(), rows.min(limit as u64), cost, actual, input)
+            }
+        }
+    })
+}
+
+/// Answers a SPARQL ASK query, stopping at the first solution. A SELECT query
+/// is answered by whether it has any result.
+pub fn execute_ask(sparql: &str, database: &mut SparqlDatabase) -> bool {
+    let mut rows = match ask_as_select(sparql) {
+        Some(select) => execute_query_iter(&select, database),
+        None => execute_query_iter(sparql, database),
+    };
+    rows.next().is_some()
+}
+
+/// Rewrites `ASK [WHERE] { ... }` into the equivalent `SELECT * WHERE { ... }`
+fn ask_as_select(sparql: &str) -> Option<String> {
+    let keyword = regex::Regex::new(r"(?i)(^|\s)ASK\b\s*(WHERE\b)?").unwrap();
+    let captures = keyword.captures(sparql)?;
+    let whole = captures.get(0)?;
+    let start = captures.get(1).map_or(whole.start(), |prefix| prefix.end());
+    Some(format!(
+        "{}SELECT * WHERE {}",
+        &sparql[..start],
+        sparql[whole.end()..].trim_start()
+    ))
+}
+
 // Convert the final BTreeMap results into Vec<Vec<String>>
 fn format_results(
     final_results: Vec<BTreeMap<&str, String>>,
@@ -894,6 +1447,16 @@ fn format_results(
         .collect()
 }
 
+// Helper function to expand a prefixed predicate with the query's prefixes
+fn expand_prefixed_predicate(predicate: &str, prefixes: &HashMap<String, String>) -> String {
+    let parts: Vec<&str> = predicate.split(':').collect();
+    if parts.len() == 2 && prefixes.contains_key(parts[0]) {
+        format!("{}{}", prefixes[parts[0]], parts[1])
+    } else {
+        predicate.to_string()
+    }
+}
+
 // Helper function to normalize the query by removing any RULE prefix
 fn normalize_query(sparql: &str) -> &str {
     if sparql.contains("RULE") {
@@ -1216,7 +1779,17 @@ fn process_bind_clauses<'a>(
     database: &'a SparqlDatabase,
 ) {
     for (func_name, args, new_var) in binds {
-        if func_name == "CONCAT" {
+        if func_name == ARITHMETIC_BIND {
+            let Some(Ok((_, expression))) = args.first().map(|text| parse_arithmetic_expression(text)) else {
+                continue;
+            };
+            for row in final_results.iter_mut() {
+                let resolve = |var: &str| row.get(var).and_then(|value| numeric_value(value));
+                if let Ok(value) = expression.evaluate(&resolve) {
+                    row.insert(new_var, format_number(value));
+                }
+            }
+        } else if func_name == "CONCAT" {
             // Process CONCAT function
             for row in final_results.iter_mut() {
                 let concatenated = args
@@ -1309,8 +1882,13 @@ pub fn group_and_aggregate_results<'a>(
         Vec<String>,
         (BTreeMap<&'a str, String>, HashMap<&'a str, (f64, usize)>),
     > = HashMap::new();
+    let budget = OperatorBudget::current(group_by_vars.len() + aggregation_vars.len());
 
     for result in results {
+        if !budget.scan(1) {
+            return Vec::new();
+        }
+
         // Create the key based on the group by variables
         let key: Vec<String> = group_by_vars
             .iter()
@@ -1347,6 +1925,7 @@ pub fn group_and_aggregate_results<'a>(
                 }
             })
             .or_insert_with(|| {
+                budget.produce(1);
                 let mut agg_map = HashMap::new();
                 for (_, _, output_var_name) in aggregation_vars {
                     let value = agg_values.get(*output_var_name).cloned().unwrap_or(0.0);
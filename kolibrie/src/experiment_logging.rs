@@ -38,6 +38,11 @@ pub fn init_experiment_log(file_path: impl Into<PathBuf>) -> io::Result<()> {
     }
 }
 
+/// Whether `init_experiment_log` has been called in this process.
+pub fn is_initialized() -> bool {
+    LOG_FILE_PATH.get().is_some()
+}
+
 fn current_log_path() -> PathBuf {
     LOG_FILE_PATH
         .get()
@@ -2,7 +2,11 @@ use crate::container_stats::ContainerStats;
 use crate::stream_estimator::StreamEstimator;
 use crate::streamertail_optimizer::*;
 use crate::sparql_database::SparqlDatabase;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+
+/// Connected join graphs with more relations than this are ordered greedily instead
+/// of with DPccp, whose work grows exponentially for star- and clique-shaped queries.
+pub const DP_RELATION_LIMIT: usize = 10;
 
 /**
  * Reordering based on a ContentContainer with a StreamEstimator
@@ -26,33 +30,22 @@ pub fn recalculate_window_plan(logical_plan: PhysicalOperator, container_stats:
 }
 
 pub fn calculate_initial_window_plan(logical_plan: LogicalOperator, container_stats: ContainerStats) -> PhysicalOperator {
-    let plans = generate_all_reorderings(&logical_plan);
-    let mut result = logical_to_physical(plans.get(0).unwrap().clone()); // initialize result variable
     let estimator = StreamEstimator::new(container_stats);
-    let mut minimum_estimated_cost = i64::MAX;
-    // println!("[Initial] {} query plans considered", plans.len());
-    for plan in plans {
-        let plan = logical_to_physical(plan);
-        let cost = estimator.estimate_cost(&plan).unwrap(); // Estimate cost
-        // println!("{}", cost);
-        if cost < minimum_estimated_cost {
-            result = plan;
-            minimum_estimated_cost = cost; // Minimalize cost
-        }
-    }
-    result.clone()
+    optimize_join_order(&logical_plan, &|plan| estimator.estimate_cost(plan).unwrap_or(i64::MAX))
 }
 
 /*
- Naive static join reordering
- Generate all possible join reoderings and pick the "best" one
+ Static join reordering
+ Cost every join order DPccp considers with the Volcano cost estimator
  */
 
 pub fn naive_reordering(logical_plan: LogicalOperator, db: &mut SparqlDatabase) -> PhysicalOperator {
-    let plans = generate_all_reorderings(&logical_plan);
-    pick_best_one(plans, db)
+    let binding = &DatabaseStats::gather_stats_fast(db);
+    let estimator = CostEstimator::new(binding);
+    optimize_join_order(&logical_plan, &|plan| estimator.estimate_cost(plan).min(i64::MAX as u64) as i64)
 }
 
+// Cost every plan and return the cheapest; plans come from generate_all_reorderings
 pub fn pick_best_one(plans: Vec<LogicalOperator>, db: &mut SparqlDatabase) -> PhysicalOperator {
     let mut result = logical_to_physical(plans.get(0).unwrap().clone());
     let binding = &DatabaseStats::gather_stats_fast(db);
@@ -70,6 +63,276 @@ pub fn pick_best_one(plans: Vec<LogicalOperator>, db: &mut SparqlDatabase) -> Ph
     result
 }
 
+/*
+DYNAMIC PROGRAMMING JOIN ENUMERATION
+The joins of a plan are flattened into a join graph: its relations are the maximal
+non-join subtrees (scans, filtered scans, VALUES, BIND, subqueries, ...) and two
+relations are connected when they share a variable. Each connected component is
+ordered with DPccp, which only combines connected subgraphs, so no cross products
+are considered. Components above DP_RELATION_LIMIT relations are ordered greedily, and
+the components themselves are combined greedily (these are the only cross products).
+ */
+
+/// Cheapest join order of `logical_plan` under `cost`. Non-join operators are kept
+/// in place, with the joins below them ordered independently.
+pub fn optimize_join_order(logical_plan: &LogicalOperator, cost: &dyn Fn(&PhysicalOperator) -> i64) -> PhysicalOperator {
+    let plan = pushdown_filters(logical_plan.clone());
+    let (core_plan, top_ops) = strip_top_level_ops(plan);
+    let top_ops: Vec<TopLevelPhysicalOp> = top_ops
+        .into_iter()
+        .map(|op| match op {
+            TopLevelOp::Selection(condition) => TopLevelPhysicalOp::Filter(condition),
+            TopLevelOp::Projection(variables) => TopLevelPhysicalOp::Projection(variables),
+        })
+        .collect();
+    apply_top_level_physical_ops(order_joins(core_plan, cost), &top_ops)
+}
+
+// Order the join tree rooted at plan; any other operator is converted as is
+fn order_joins(plan: LogicalOperator, cost: &dyn Fn(&PhysicalOperator) -> i64) -> PhysicalOperator {
+    let mut relations = Vec::new();
+    collect_join_relations(plan, &mut relations);
+    if relations.len() == 1 {
+        return relation_to_physical(relations.pop().unwrap(), cost);
+    }
+
+    let variables: Vec<HashSet<String>> = relations
+        .iter()
+        .map(|relation| {
+            collect_plan_vars(relation)
+                .into_iter()
+                .map(|var| var.trim_start_matches('?').to_string())
+                .collect()
+        })
+        .collect();
+    let mut relations: Vec<Option<PhysicalOperator>> = relations
+        .into_iter()
+        .map(|relation| Some(relation_to_physical(relation, cost)))
+        .collect();
+
+    let components: Vec<(HashSet<String>, PhysicalOperator)> = join_graph_components(&variables)
+        .into_iter()
+        .map(|component| {
+            let component_vars: HashSet<String> =
+                component.iter().flat_map(|&idx| variables[idx].iter().cloned()).collect();
+            let members: Vec<(HashSet<String>, PhysicalOperator)> = component
+                .iter()
+                .map(|&idx| (variables[idx].clone(), relations[idx].take().unwrap()))
+                .collect();
+            let plan = if members.len() > DP_RELATION_LIMIT {
+                greedy_join_order(members, cost)
+            } else {
+                dpccp_join_order(members, cost)
+            };
+            (component_vars, plan)
+        })
+        .collect();
+    greedy_join_order(components, cost)
+}
+
+// Flatten a join tree into its relations (maximal non-join subtrees)
+fn collect_join_relations(plan: LogicalOperator, relations: &mut Vec<LogicalOperator>) {
+    match plan {
+        LogicalOperator::Join { left, right } => {
+            collect_join_relations(*left, relations);
+            collect_join_relations(*right, relations);
+        }
+        other => relations.push(other),
+    }
+}
+
+// Convert a join graph relation, ordering the joins nested inside it
+fn relation_to_physical(relation: LogicalOperator, cost: &dyn Fn(&PhysicalOperator) -> i64) -> PhysicalOperator {
+    match relation {
+        LogicalOperator::Selection { predicate, condition } => PhysicalOperator::Filter {
+            input: Box::new(order_joins(*predicate, cost)),
+            condition,
+        },
+        LogicalOperator::Projection { predicate, variables } => PhysicalOperator::Projection {
+            input: Box::new(order_joins(*predicate, cost)),
+            variables,
+        },
+        LogicalOperator::Subquery { inner, projected_vars } => PhysicalOperator::Subquery {
+            inner: Box::new(optimize_join_order(&inner, cost)),
+            projected_vars,
+        },
+        LogicalOperator::Bind { input, function_name, arguments, output_variable } => PhysicalOperator::Bind {
+            input: Box::new(order_joins(*input, cost)),
+            function_name,
+            arguments,
+            output_variable,
+        },
+        LogicalOperator::MLPredict { input, model_name, input_variables, output_variable } => {
+            PhysicalOperator::MLPredict {
+                input: Box::new(order_joins(*input, cost)),
+                model_name,
+                model_path: Streamertail::discover_model_path(),
+                input_variables,
+                output_variable,
+            }
+        }
+        join @ LogicalOperator::Join { .. } => order_joins(join, cost),
+        leaf => logical_to_physical(leaf),
+    }
+}
+
+// Group relations into connected components of the join graph
+fn join_graph_components(variables: &[HashSet<String>]) -> Vec<Vec<usize>> {
+    let mut assigned = vec![false; variables.len()];
+    let mut components: Vec<Vec<usize>> = Vec::new();
+    for start in 0..variables.len() {
+        if assigned[start] {
+            continue;
+        }
+        assigned[start] = true;
+        let mut component = vec![start];
+        let mut next = 0;
+        while next < component.len() {
+            let current = component[next];
+            next += 1;
+            for other in 0..variables.len() {
+                if !assigned[other] && !variables[current].is_disjoint(&variables[other]) {
+                    assigned[other] = true;
+                    component.push(other);
+                }
+            }
+        }
+        components.push(component);
+    }
+    components
+}
+
+// DPccp over one connected component: the cheapest plan for every connected subset,
+// built from the cheapest plans of its csg-cmp-pairs
+fn dpccp_join_order(
+    members: Vec<(HashSet<String>, PhysicalOperator)>,
+    cost: &dyn Fn(&PhysicalOperator) -> i64,
+) -> PhysicalOperator {
+    let count = members.len();
+    let adjacency: Vec<u64> = (0..count)
+        .map(|i| {
+            (0..count)
+                .filter(|&j| j != i && !members[i].0.is_disjoint(&members[j].0))
+                .fold(0u64, |mask, j| mask | (1 << j))
+        })
+        .collect();
+
+    let mut best: HashMap<u64, (i64, PhysicalOperator)> = HashMap::new();
+    for (idx, (_, plan)) in members.into_iter().enumerate() {
+        best.insert(1 << idx, (cost(&plan), plan));
+    }
+
+    let mut pairs = csg_cmp_pairs(&adjacency);
+    // Smaller subsets first, so both halves of a pair are final when it is combined
+    pairs.sort_by_key(|(s1, s2)| (s1 | s2).count_ones());
+    for (s1, s2) in pairs {
+        let left = best[&s1].1.clone();
+        let right = best[&s2].1.clone();
+        for (left, right) in [(left.clone(), right.clone()), (right, left)] {
+            let candidate = PhysicalOperator::HashJoin { left: Box::new(left), right: Box::new(right) };
+            let candidate_cost = cost(&candidate);
+            if best.get(&(s1 | s2)).is_none_or(|(current, _)| candidate_cost < *current) {
+                best.insert(s1 | s2, (candidate_cost, candidate));
+            }
+        }
+    }
+
+    best.remove(&below(count - 1)).map(|(_, plan)| plan).expect("join graph component is connected")
+}
+
+// All csg-cmp-pairs (S1, S2) of the join graph: S1 and S2 are disjoint, each induces a
+// connected subgraph and an edge connects them. Each unordered pair is listed once.
+fn csg_cmp_pairs(adjacency: &[u64]) -> Vec<(u64, u64)> {
+    let mut csgs = Vec::new();
+    for i in (0..adjacency.len()).rev() {
+        csgs.push(1u64 << i);
+        enumerate_csg_rec(1 << i, below(i), adjacency, &mut csgs);
+    }
+
+    let mut pairs = Vec::new();
+    for s1 in csgs {
+        let exclusion = below(s1.trailing_zeros() as usize) | s1;
+        let neighbours = neighbourhood(s1, adjacency) & !exclusion;
+        for i in (0..adjacency.len()).rev().filter(|i| neighbours & (1 << i) != 0) {
+            let mut cmps = vec![1u64 << i];
+            enumerate_csg_rec(1 << i, exclusion | (below(i) & neighbours), adjacency, &mut cmps);
+            pairs.extend(cmps.into_iter().map(|s2| (s1, s2)));
+        }
+    }
+    pairs
+}
+
+// Extend the connected set s with neighbours outside x, recording every extension
+fn enumerate_csg_rec(s: u64, x: u64, adjacency: &[u64], out: &mut Vec<u64>) {
+    let neighbours = neighbourhood(s, adjacency) & !x;
+    if neighbours == 0 {
+        return;
+    }
+    for subset in non_empty_subsets(neighbours) {
+        out.push(s | subset);
+    }
+    for subset in non_empty_subsets(neighbours) {
+        enumerate_csg_rec(s | subset, x | neighbours, adjacency, out);
+    }
+}
+
+fn neighbourhood(set: u64, adjacency: &[u64]) -> u64 {
+    let mut neighbours = 0;
+    for (idx, edges) in adjacency.iter().enumerate() {
+        if set & (1 << idx) != 0 {
+            neighbours |= edges;
+        }
+    }
+    neighbours & !set
+}
+
+// Relations 0..=i
+fn below(i: usize) -> u64 {
+    if i >= 63 { u64::MAX } else { (1u64 << (i + 1)) - 1 }
+}
+
+fn non_empty_subsets(set: u64) -> impl Iterator<Item = u64> {
+    let mut subset = 0u64;
+    std::iter::from_fn(move || {
+        subset = subset.wrapping_sub(set) & set;
+        (subset != 0).then_some(subset)
+    })
+}
+
+// Greedy operator ordering: repeatedly join the cheapest pair of connected plans,
+// falling back to a cross product when no two plans share a variable
+fn greedy_join_order(
+    mut plans: Vec<(HashSet<String>, PhysicalOperator)>,
+    cost: &dyn Fn(&PhysicalOperator) -> i64,
+) -> PhysicalOperator {
+    while plans.len() > 1 {
+        let connected = (0..plans.len())
+            .any(|i| (i + 1..plans.len()).any(|j| !plans[i].0.is_disjoint(&plans[j].0)));
+        let mut best: Option<(i64, usize, usize, PhysicalOperator)> = None;
+        for i in 0..plans.len() {
+            for j in 0..plans.len() {
+                if i == j || (connected && plans[i].0.is_disjoint(&plans[j].0)) {
+                    continue;
+                }
+                let candidate = PhysicalOperator::HashJoin {
+                    left: Box::new(plans[i].1.clone()),
+                    right: Box::new(plans[j].1.clone()),
+                };
+                let candidate_cost = cost(&candidate);
+                if best.as_ref().is_none_or(|(current, ..)| candidate_cost < *current) {
+                    best = Some((candidate_cost, i, j, candidate));
+                }
+            }
+        }
+        let (_, i, j, joined) = best.unwrap();
+        let (right_vars, _) = plans.remove(i.max(j));
+        let (mut left_vars, _) = plans.remove(i.min(j));
+        left_vars.extend(right_vars);
+        plans.push((left_vars, joined));
+    }
+    plans.pop().unwrap().1
+}
+
 fn logical_to_physical(logical_plan: LogicalOperator) -> PhysicalOperator {
     match logical_plan {
         LogicalOperator::Scan { pattern } => 
@@ -82,9 +345,20 @@ fn logical_to_physical(logical_plan: LogicalOperator) -> PhysicalOperator {
             PhysicalOperator::HashJoin { left: Box::new(logical_to_physical(*left)), right: Box::new(logical_to_physical(*right))},
         LogicalOperator::Subquery { inner, projected_vars } =>
             PhysicalOperator::Subquery { inner: Box::new(logical_to_physical(*inner)), projected_vars },
-        _ => {
-            panic!("I don't know about the other operators!");
-        }
+        LogicalOperator::Buffer { content, origin } =>
+            PhysicalOperator::InMemoryBuffer { content, origin },
+        LogicalOperator::Values { variables, values } =>
+            PhysicalOperator::Values { variables, values },
+        LogicalOperator::Bind { input, function_name, arguments, output_variable } =>
+            PhysicalOperator::Bind { input: Box::new(logical_to_physical(*input)), function_name, arguments, output_variable },
+        LogicalOperator::MLPredict { input, model_name, input_variables, output_variable } =>
+            PhysicalOperator::MLPredict {
+                input: Box::new(logical_to_physical(*input)),
+                model_name,
+                model_path: Streamertail::discover_model_path(),
+                input_variables,
+                output_variable,
+            },
     }
 }
 
@@ -260,17 +534,9 @@ fn extract_leaf_scans_physical_helper(plan: &PhysicalOperator, scans: &mut Vec<P
             extract_leaf_scans_physical_helper(left, scans);
             extract_leaf_scans_physical_helper(right, scans);
         }
-        PhysicalOperator::Filter { input, .. } => {
-            extract_leaf_scans_physical_helper(input, scans);
-        }
-        PhysicalOperator::Projection { input, .. } => {
-            extract_leaf_scans_physical_helper(input, scans);
-        }
-        PhysicalOperator::TableScan { pattern } => {
-            scans.push(PhysicalOperator::TableScan { pattern: pattern.clone() });
-        }
         _ => {
-            // For other leaf types, just include them as-is
+            // Filters and projections below a join (pushed down onto a scan) stay
+            // attached to their input; other leaf types are included as-is too
             scans.push(plan.clone());
         }
     }
@@ -757,7 +1023,7 @@ fn collect_plan_vars(plan: &LogicalOperator) -> HashSet<String> {
             vars
         }
         LogicalOperator::Values { variables, .. } => variables.iter().cloned().collect(),
-        LogicalOperator::Buffer { .. } => HashSet::new(),
+        LogicalOperator::Buffer { content, .. } => content.iter().flat_map(|row| row.keys().cloned()).collect(),
         LogicalOperator::MLPredict { input, output_variable, .. } => {
             let mut vars = collect_plan_vars(input);
             vars.insert(output_variable.clone());
@@ -836,4 +1102,88 @@ mod tests {
         let plans = generate_all_reorderings(&plan);
         assert_eq!(plans.len(), 3); // number of possible combinations
     }
-}
\ No newline at end of file
+
+    fn pattern(subject: &str, predicate: u32, object: &str) -> LogicalOperator {
+        LogicalOperator::scan((
+            Term::Variable(subject.to_string()),
+            Term::Constant(predicate),
+            Term::Variable(object.to_string()),
+        ))
+    }
+
+    fn window_stats(predicates: &[(u32, i64)]) -> ContainerStats {
+        let mut stats = ContainerStats::new();
+        for (predicate, count) in predicates {
+            stats.predicate_cardinalities.insert(*predicate, *count);
+            stats.total_triples += count;
+        }
+        stats
+    }
+
+    fn count_joins(plan: &PhysicalOperator) -> usize {
+        match plan {
+            PhysicalOperator::HashJoin { left, right } => 1 + count_joins(left) + count_joins(right),
+            PhysicalOperator::Filter { input, .. } | PhysicalOperator::Projection { input, .. } => count_joins(input),
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn dp_finds_the_cheapest_exhaustive_order() {
+        // Star on ?s with very different predicate cardinalities, so no order needs a cross product
+        let plan = LogicalOperator::join(
+            LogicalOperator::join(LogicalOperator::join(pattern("s", 1, "a"), pattern("s", 2, "b")), pattern("s", 3, "c")),
+            pattern("s", 4, "d"),
+        );
+        let stats = window_stats(&[(1, 5000), (2, 10), (3, 2000), (4, 3)]);
+        let estimator = StreamEstimator::new(stats.clone());
+
+        let exhaustive = generate_all_reorderings(&plan)
+            .into_iter()
+            .map(|plan| estimator.estimate_cost(&logical_to_physical(plan)).unwrap())
+            .min()
+            .unwrap();
+        let dp_plan = calculate_initial_window_plan(plan, stats);
+        assert!(estimator.estimate_cost(&dp_plan).unwrap() <= exhaustive);
+    }
+
+    #[test]
+    fn large_stars_fall_back_to_greedy_ordering() {
+        let relations = DP_RELATION_LIMIT + 6;
+        let mut plan = pattern("s", 0, "o0");
+        for idx in 1..relations as u32 {
+            plan = LogicalOperator::join(plan, pattern("s", idx, &format!("o{}", idx)));
+        }
+        let stats = window_stats(&(0..relations as u32).map(|idx| (idx, 10 + idx as i64)).collect::<Vec<_>>());
+
+        let physical = calculate_initial_window_plan(plan, stats);
+        assert_eq!(count_joins(&physical), relations - 1);
+    }
+
+    #[test]
+    fn values_and_bind_relations_are_ordered() {
+        let values = LogicalOperator::values(vec!["a".to_string()], vec![vec![Some("x".to_string())]]);
+        let bind = LogicalOperator::bind(values, "CONCAT".to_string(), vec!["?a".to_string()], "label".to_string());
+        let plan = LogicalOperator::join(LogicalOperator::join(pattern("a", 1, "b"), bind), pattern("b", 2, "c"));
+
+        let physical = calculate_initial_window_plan(plan, window_stats(&[(1, 10), (2, 10)]));
+        assert_eq!(count_joins(&physical), 2);
+        assert!(matches!(
+            logical_to_physical(LogicalOperator::values(vec![], vec![])),
+            PhysicalOperator::Values { .. }
+        ));
+    }
+
+    #[test]
+    fn disconnected_components_are_joined_last() {
+        let plan = LogicalOperator::join(
+            LogicalOperator::join(pattern("a", 1, "b"), pattern("x", 2, "y")),
+            pattern("b", 3, "c"),
+        );
+        let physical = calculate_initial_window_plan(plan, window_stats(&[(1, 10), (2, 10), (3, 10)]));
+
+        // The cross product sits at the root, above the join of the connected patterns
+        let PhysicalOperator::HashJoin { left, right } = physical else { panic!("expected a join") };
+        assert_eq!(count_joins(&left) + count_joins(&right), 1);
+    }
+}
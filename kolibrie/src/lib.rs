@@ -10,6 +10,7 @@
 
 mod storage_trait;
 mod storage_manager;
+mod turtle;
 pub mod cuda;
 pub mod error_handler;
 pub mod execute_ml;
@@ -21,8 +22,12 @@ pub mod ml_predict_candle;
 pub mod ml_predict_runtime;
 pub mod neural_relations;
 pub mod parser;
+pub mod plan_cache;
+pub mod prepared_query;
+pub mod query_context;
 pub mod query_builder;
 pub mod rsp_engine;
+pub mod shacl;
 pub mod sparql_database;
 pub mod utils;
 pub mod streamertail_optimizer;
@@ -30,6 +35,7 @@ pub mod rsp;
 pub mod query_engine;
 
 // Query plan optimization
+pub mod adaptive_planner;
 pub mod join_reordering;
 pub mod container_stats;
 pub mod stream_estimator;
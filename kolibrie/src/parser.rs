@@ -29,10 +29,11 @@ use shared::triple::Triple;
 use shared::dictionary::Dictionary;
 use shared::rule::FilterCondition;
 use shared::rule::Rule;
+use shared::rule::{AggregateFunction, RuleAggregate, RuleBinding, RuleExpression};
 use shared::terms::*;
 use shared::query::*;
 // Add RSP imports
-use crate::rsp::s2r::{CSPARQLWindow, Report, ReportStrategy, Tick, WindowTriple, ContentContainer};
+use crate::rsp::s2r::{CSPARQLWindow, ContentContainer, ObjectKind, Report, ReportStrategy, Tick, WindowTriple};
 use crate::rsp::r2s::{Relation2StreamOperator, StreamOperator};
 use std::collections::HashMap;
 
@@ -607,11 +608,27 @@ pub fn parse_filter(input: &str) -> IResult<&str, FilterExpression<'_>> {
     Ok((input, expr))
 }
 
-// Parser for BIND clauses: BIND(funcName(?var, "literal") AS ?newVar)
+/// Function name recorded for `BIND(<arithmetic expression> AS ?var)`; the
+/// expression text is its only argument
+pub const ARITHMETIC_BIND: &str = "ARITHMETIC";
+
+// Parser for BIND clauses: BIND(funcName(?var, "literal") AS ?newVar) or
+// BIND(?a + ?b AS ?newVar)
 pub fn parse_bind(input: &str) -> IResult<&str, (&str, Vec<&str>, &str)> {
     let (input, _) = tag("BIND").parse(input)?;
     let (input, _) = multispace0.parse(input)?;
     let (input, _) = char('(').parse(input)?;
+    let (input, (func_name, args)) = alt((parse_bind_function, parse_bind_arithmetic)).parse(input)?;
+    let (input, _) = multispace0.parse(input)?;
+    let (input, _) = tag("AS").parse(input)?;
+    let (input, _) = multispace1.parse(input)?;
+    let (input, new_var) = variable(input)?;
+    let (input, _) = char(')').parse(input)?;
+
+    Ok((input, (func_name, args, new_var)))
+}
+
+fn parse_bind_function(input: &str) -> IResult<&str, (&str, Vec<&str>)> {
     let (input, func_name) = identifier(input)?;
     let (input, _) = char('(').parse(input)?;
 
@@ -623,12 +640,45 @@ pub fn parse_bind(input: &str) -> IResult<&str, (&str, Vec<&str>, &str)> {
 
     let (input, _) = char(')').parse(input)?;
     let (input, _) = multispace1.parse(input)?;
-    let (input, _) = tag("AS").parse(input)?;
-    let (input, _) = multispace1.parse(input)?;
-    let (input, new_var) = variable(input)?;
-    let (input, _) = char(')').parse(input)?;
+    Ok((input, (func_name, args)))
+}
 
-    Ok((input, (func_name, args, new_var)))
+fn parse_bind_arithmetic(input: &str) -> IResult<&str, (&str, Vec<&str>)> {
+    let (input, expression) = recognize(parse_arithmetic_expression).parse(input)?;
+    Ok((input, (ARITHMETIC_BIND, vec![expression.trim()])))
+}
+
+// Convert an arithmetic expression into the expression of a rule binding
+pub fn convert_arithmetic_expression(expression: &ArithmeticExpression) -> RuleExpression {
+    let convert = |l: &ArithmeticExpression, r: &ArithmeticExpression| {
+        (Box::new(convert_arithmetic_expression(l)), Box::new(convert_arithmetic_expression(r)))
+    };
+    match expression {
+        ArithmeticExpression::Operand(operand) => convert_bind_argument(operand),
+        ArithmeticExpression::Add(l, r) => {
+            let (l, r) = convert(l, r);
+            RuleExpression::Add(l, r)
+        }
+        ArithmeticExpression::Subtract(l, r) => {
+            let (l, r) = convert(l, r);
+            RuleExpression::Subtract(l, r)
+        }
+        ArithmeticExpression::Multiply(l, r) => {
+            let (l, r) = convert(l, r);
+            RuleExpression::Multiply(l, r)
+        }
+        ArithmeticExpression::Divide(l, r) => {
+            let (l, r) = convert(l, r);
+            RuleExpression::Divide(l, r)
+        }
+    }
+}
+
+fn convert_bind_argument(argument: &str) -> RuleExpression {
+    match argument.strip_prefix('?') {
+        Some(var) => RuleExpression::Variable(var.to_string()),
+        None => RuleExpression::Literal(argument.to_string()),
+    }
 }
 
 pub fn parse_subquery<'a>(input: &'a str) -> IResult<&'a str, SubQuery<'a>> {
@@ -1838,6 +1888,73 @@ fn parse_from_named_window_policy(input: &str) -> IResult<&str, shared::query::S
     parse_sync_policy(input)
 }
 
+fn parse_from_named_window_partition(input: &str) -> IResult<&str, &str> {
+    let (input, _) = multispace0.parse(input)?;
+    let (input, _) = tag("PARTITION").parse(input)?;
+    let (input, _) = multispace1.parse(input)?;
+    let (input, _) = tag("BY").parse(input)?;
+    let (input, _) = multispace1.parse(input)?;
+    variable(input)
+}
+
+fn parse_from_named_window_replan(input: &str) -> IResult<&str, ReplanTrigger> {
+    let (input, _) = multispace0.parse(input)?;
+    let (input, _) = tag("WITH").parse(input)?;
+    let (input, _) = multispace1.parse(input)?;
+    let (input, _) = tag("REPLAN").parse(input)?;
+    let (input, _) = multispace1.parse(input)?;
+    parse_replan_trigger(input)
+}
+
+/// Parse the trigger after `WITH REPLAN`.
+/// - `static` / `always`
+/// - `size(<t>)`, `distribution(<t>)`, `ranking(<t>)`
+/// - `hybrid(<size>, <distribution>, <ranking>)`
+/// - `qerror(<t>)` / `qerror(<t>, <patience>)`, patience defaulting to 2 firings
+fn parse_replan_trigger(input: &str) -> IResult<&str, ReplanTrigger> {
+    alt((
+        tag("static").map(|_| ReplanTrigger::Static),
+        tag("always").map(|_| ReplanTrigger::Always),
+        preceded(tag("size"), parse_replan_thresholds)
+            .map(|t| ReplanTrigger::OnSizeChange { threshold: t[0] }),
+        preceded(tag("distribution"), parse_replan_thresholds)
+            .map(|t| ReplanTrigger::OnDistributionChange { threshold: t[0] }),
+        preceded(tag("ranking"), parse_replan_thresholds)
+            .map(|t| ReplanTrigger::OnRankingChange { threshold: t[0] }),
+        preceded(tag("hybrid"), parse_replan_thresholds)
+            .map(|t| ReplanTrigger::Hybrid {
+                size_threshold: t[0],
+                distribution_threshold: t.get(1).copied().unwrap_or(t[0]),
+                ranking_threshold: t.get(2).copied().unwrap_or(t[0]),
+            }),
+        preceded(tag("qerror"), parse_replan_thresholds)
+            .map(|t| ReplanTrigger::OnEstimateError {
+                threshold: t[0],
+                patience: t.get(1).map_or(2, |p| (*p as usize).max(1)),
+            }),
+    ))
+    .parse(input)
+}
+
+fn parse_replan_thresholds(input: &str) -> IResult<&str, Vec<f64>> {
+    let (input, _) = multispace0.parse(input)?;
+    let (input, _) = char('(').parse(input)?;
+    let (input, values) = separated_list1(
+        char(','),
+        delimited(
+            multispace0,
+            recognize((
+                take_while1(|c: char| c.is_ascii_digit()),
+                opt((char('.'), take_while1(|c: char| c.is_ascii_digit()))),
+            )),
+            multispace0,
+        ),
+    )
+    .parse(input)?;
+    let (input, _) = char(')').parse(input)?;
+    Ok((input, values.iter().map(|v| v.parse::<f64>().unwrap_or(0.0)).collect()))
+}
+
 fn parse_sync_policy_steal(input: &str) -> IResult<&str, shared::query::SyncPolicy> {
     let (input, _) = tag("steal").parse(input)?;
     Ok((input, shared::query::SyncPolicy::Steal))
@@ -1937,14 +2054,22 @@ pub fn parse_from_named_window(input: &str) -> IResult<&str, WindowClause<'_>> {
     // Parse window specification with ISO 8601 duration support
     let (input, window_spec) = parse_window_spec(input)?;
 
+    // Optional: PARTITION BY ?var
+    let (input, partition_by) = opt(parse_from_named_window_partition).parse(input)?;
+
     // Optional: WITH POLICY <policy>
     let (input, policy) = opt(parse_from_named_window_policy).parse(input)?;
 
+    // Optional: WITH REPLAN <trigger>
+    let (input, replan) = opt(parse_from_named_window_replan).parse(input)?;
+
     Ok((input, WindowClause {
         window_iri,
         stream_iri,
         window_spec,
+        partition_by,
         policy,
+        replan,
     }))
 }
 
@@ -2350,6 +2475,36 @@ pub fn convert_combined_rule<'a>(
         })
         .collect();
 
+    // Convert BIND clauses: aggregates of one variable are grouped by the
+    // conclusion variables, everything else is computed per solution
+    let mut bindings = Vec::new();
+    let mut aggregates = Vec::new();
+    for (func_name, args, new_var) in cr.body.3 {
+        let variable = new_var.trim_start_matches('?').to_string();
+        if func_name == ARITHMETIC_BIND {
+            if let Some(Ok((_, expression))) = args.first().map(|text| parse_arithmetic_expression(text)) {
+                bindings.push(RuleBinding {
+                    variable,
+                    expression: convert_arithmetic_expression(&expression),
+                });
+            }
+        } else if let (Ok(function), [argument]) = (func_name.parse::<AggregateFunction>(), args.as_slice()) {
+            aggregates.push(RuleAggregate {
+                function,
+                argument: argument.trim_start_matches('?').to_string(),
+                result: variable,
+            });
+        } else {
+            bindings.push(RuleBinding {
+                variable,
+                expression: RuleExpression::Function(
+                    func_name.to_string(),
+                    args.iter().map(|arg| convert_bind_argument(arg)).collect(),
+                ),
+            });
+        }
+    }
+
     // Convert all conclusion triples, preserving their structure
     let mut conclusion_triples: Vec<TriplePattern> = cr.conclusion
         .into_iter()
@@ -2425,6 +2580,8 @@ pub fn convert_combined_rule<'a>(
     Rule {
         premise: premise_patterns,
         negative_premise: negative_premise_patterns,
+        bindings,
+        aggregates,
         filters: filter_conditions,
         conclusion: conclusion_triples,
     }
@@ -2501,6 +2658,8 @@ pub fn process_rule_definition(
         let mut dict = kg.dictionary.write().unwrap();
         let dynamic_rule = convert_combined_rule(rule.clone(), &mut dict, &rule_prefixes);
         drop(dict);
+        // Unknown functions would otherwise make the rule derive nothing
+        shared::rule::check_rule_safety(&dynamic_rule)?;
         database.dictionary = kg.dictionary.clone();
 
         // Check if this rule has windowing - if so, set up RSP processing
@@ -2530,6 +2689,7 @@ pub fn process_rule_definition(
                         s: dict.decode(triple.subject).unwrap_or("").to_string(),
                         p: dict.decode(triple.predicate).unwrap_or("").to_string(),
                         o: dict.decode(triple.object).unwrap_or("").to_string(),
+                        object_kind: ObjectKind::Unknown,
                     };
                     drop(dict);
 
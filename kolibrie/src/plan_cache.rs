@@ -0,0 +1,423 @@
+/*
+ * Copyright © 2025 Volodymyr Kadzhaia
+ * Copyright © 2025 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use crate::execute_query::PlannedQuery;
+use crate::sparql_database::SparqlDatabase;
+use shared::rule::numeric_value;
+use std::collections::HashMap;
+
+/// Prefix of the variables standing for the literals of a query shape
+pub const LITERAL_VARIABLE_PREFIX: &str = "?__literal";
+
+/// Plans kept by a database unless configured otherwise
+pub const DEFAULT_PLAN_CACHE_CAPACITY: usize = 256;
+
+/// Relative change in the number of triples after which cached plans are
+/// considered stale: their join orders were chosen for different data
+pub const PLAN_CACHE_MAX_DRIFT: f64 = 0.2;
+
+/// A query with its literals replaced by variables, see
+/// `PlanCache::abstract_literals`
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryShape {
+    /// Cache key: the tokens of the query separated by single spaces
+    pub key: String,
+    /// The query with its n-th literal replaced by `?__literal<n>`
+    pub sparql: String,
+    /// The literals, as written
+    pub literals: Vec<String>,
+    /// Where each literal occurs
+    pub roles: Vec<LiteralRole>,
+}
+
+/// Where a literal of a query shape occurs, which decides how the optimizer
+/// estimates its selectivity
+#[derive(Debug, Clone, PartialEq)]
+pub enum LiteralRole {
+    /// Object of a triple pattern with this predicate, as written
+    Object { predicate: String },
+    /// Right side of a comparison in a FILTER, with the predicate of the
+    /// triple pattern the compared variable is the object of, if any
+    Comparison { operator: String, predicate: Option<String> },
+}
+
+#[derive(Debug, Clone)]
+struct CachedPlan {
+    planned: PlannedQuery,
+    last_used: u64,
+}
+
+/// Optimized plans by query shape, so repeated queries skip parsing and
+/// `Streamertail::find_best_plan`. Least recently used plans are evicted
+/// first, and all plans are dropped once the data changed significantly
+/// since they were made.
+#[derive(Debug, Clone)]
+pub struct PlanCache {
+    entries: HashMap<String, CachedPlan>,
+    capacity: usize,
+    /// Triples in the database when the cached plans were made
+    planned_triples: usize,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl PlanCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            capacity,
+            planned_triples: 0,
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Cache key of a query: its tokens separated by single spaces, with the
+    /// literals `abstract_literals` takes out replaced by variables
+    pub fn shape_of(sparql: &str) -> String {
+        Self::abstract_literals(sparql).key
+    }
+
+    /// Takes the literals out of the triple pattern objects and the
+    /// `?var <op> literal` comparisons of a query, so queries differing only
+    /// in those literals share a plan
+    pub fn abstract_literals(sparql: &str) -> QueryShape {
+        let tokens = tokenize(sparql);
+        let mut shape = QueryShape {
+            key: String::new(),
+            sparql: String::new(),
+            literals: Vec::new(),
+            roles: Vec::new(),
+        };
+        let mut copied = 0;
+        let mut key = Vec::with_capacity(tokens.len());
+        for (index, token) in tokens.iter().enumerate() {
+            if let Some(role) = abstractable_literal_role(&tokens, index) {
+                let variable = format!("{}{}", LITERAL_VARIABLE_PREFIX, shape.literals.len());
+                shape.sparql.push_str(&sparql[copied..token.start]);
+                shape.sparql.push_str(&variable);
+                copied = token.start + token.text.len();
+                shape.literals.push(token.text.to_string());
+                shape.roles.push(role);
+                key.push(variable);
+            } else {
+                key.push(token.text.to_string());
+            }
+        }
+        shape.sparql.push_str(&sparql[copied..]);
+        shape.key = key.join(" ");
+        shape
+    }
+
+    /// Cache key of `shape` on `database`: its key followed by the
+    /// selectivity class of each literal, the bit length of the number of
+    /// triples the optimizer estimates it to match. Literals within a power
+    /// of two of each other share a plan; a much more or less selective one
+    /// gets a plan of its own, as its join order may differ.
+    pub(crate) fn selectivity_key(shape: &QueryShape, database: &mut SparqlDatabase) -> String {
+        if shape.literals.is_empty() {
+            return shape.key.clone();
+        }
+        let stats = database.get_or_build_stats();
+        let dict = database.dictionary.read().unwrap();
+        let id_of = |term: &str| {
+            let resolved = database.resolve_query_term(term, &HashMap::new());
+            dict.string_to_id.get(&resolved).copied()
+        };
+        let classes: Vec<String> = shape
+            .literals
+            .iter()
+            .zip(&shape.roles)
+            .map(|(literal, role)| {
+                let matches = match role {
+                    LiteralRole::Object { predicate } => id_of(predicate).map(|predicate| {
+                        id_of(literal).map_or(0, |object| stats.get_predicate_object_count(predicate, object))
+                    }),
+                    LiteralRole::Comparison { operator, predicate } => {
+                        predicate.as_deref().and_then(id_of).and_then(|predicate| {
+                            let selectivity = stats.numeric_selectivity(predicate, operator, numeric_value(literal)?)?;
+                            Some((selectivity * stats.get_predicate_cardinality(predicate) as f64).round() as u64)
+                        })
+                    }
+                };
+                matches.map_or_else(|| "-".to_string(), |count| (u64::BITS - count.leading_zeros()).to_string())
+            })
+            .collect();
+        format!("{} | {}", shape.key, classes.join(" "))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Changes the number of plans kept, evicting plans beyond it
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.entries.len() > capacity {
+            self.evict_least_recently_used();
+        }
+    }
+
+    /// Lookups answered from the cache
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Lookups that had to plan the query
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Drops every plan when `triple_count` differs from the number of
+    /// triples the plans were made for by more than `PLAN_CACHE_MAX_DRIFT`;
+    /// returns whether it did
+    pub fn invalidate_if_changed(&mut self, triple_count: usize) -> bool {
+        if self.entries.is_empty() {
+            return false;
+        }
+        let base = self.planned_triples.max(1) as f64;
+        let drift = (triple_count as f64 - self.planned_triples as f64).abs() / base;
+        if drift > PLAN_CACHE_MAX_DRIFT {
+            self.entries.clear();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(crate) fn get(&mut self, shape: &str, triple_count: usize) -> Option<PlannedQuery> {
+        self.invalidate_if_changed(triple_count);
+        self.clock += 1;
+        match self.entries.get_mut(shape) {
+            Some(entry) => {
+                entry.last_used = self.clock;
+                self.hits += 1;
+                Some(entry.planned.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub(crate) fn insert(&mut self, shape: String, planned: PlannedQuery, triple_count: usize) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.is_empty() {
+            self.planned_triples = triple_count;
+        }
+        if !self.entries.contains_key(&shape) && self.entries.len() >= self.capacity {
+            self.evict_least_recently_used();
+        }
+        self.clock += 1;
+        self.entries.insert(shape, CachedPlan { planned, last_used: self.clock });
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(shape, _)| shape.clone());
+        if let Some(shape) = oldest {
+            self.entries.remove(&shape);
+        }
+    }
+}
+
+impl Default for PlanCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_PLAN_CACHE_CAPACITY)
+    }
+}
+
+struct Token<'a> {
+    text: &'a str,
+    /// Byte offset in the query
+    start: usize,
+}
+
+/// Splits a query into IRIs, literals, words, numbers and punctuation,
+/// dropping whitespace and comments
+fn tokenize(sparql: &str) -> Vec<Token<'_>> {
+    let bytes = sparql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let rest = &sparql[i..];
+        match bytes[i] {
+            c if c.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'#' => {
+                i = rest.find('\n').map_or(bytes.len(), |end| i + end);
+                continue;
+            }
+            quote @ (b'"' | b'\'') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i = (i + 1).min(bytes.len());
+                // Language tags and datatypes belong to the literal
+                if sparql[i..].starts_with('@') || sparql[i..].starts_with("^^") {
+                    while i < bytes.len() && !bytes[i].is_ascii_whitespace() && !b".;,)}".contains(&bytes[i]) {
+                        i += 1;
+                    }
+                }
+            }
+            b'<' if rest.starts_with("<<") => i += 2,
+            b'<' => match rest.find(|c: char| c == '>' || c.is_whitespace()) {
+                Some(end) if end > 1 && rest[end..].starts_with('>') && !rest[1..].starts_with('=') => i += end + 1,
+                _ if rest.starts_with("<=") => i += 2,
+                _ => i += 1,
+            },
+            b'>' if rest.starts_with(">>") || rest.starts_with(">=") => i += 2,
+            b'!' if rest.starts_with("!=") => i += 2,
+            b'&' if rest.starts_with("&&") => i += 2,
+            b'|' if rest.starts_with("||") => i += 2,
+            b'^' if rest.starts_with("^^") => i += 2,
+            c if c.is_ascii_digit() => {
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_digit()
+                        || (bytes[i] == b'.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit)))
+                {
+                    i += 1;
+                }
+            }
+            c if b"{}()[].;,=!<>+-*/@&|^".contains(&c) => i += 1,
+            _ => {
+                // Keywords, variables and prefixed names; a dot only inside one
+                let end = rest
+                    .char_indices()
+                    .skip(1)
+                    .find(|&(offset, c)| {
+                        c.is_whitespace()
+                            || "{}(),;\"'<>=!&|".contains(c)
+                            || (c == '.' && !rest[offset + 1..].starts_with(|c: char| c.is_alphanumeric() || c == '_'))
+                    })
+                    .map_or(rest.len(), |(offset, _)| offset);
+                i += end;
+            }
+        }
+        tokens.push(Token { text: &sparql[start..i], start });
+    }
+    tokens
+}
+
+/// Whether a token can be a variable, IRI or prefixed name
+fn is_term(token: &str) -> bool {
+    token.starts_with(['?', '$'])
+        || (token.starts_with('<') && token.len() > 2 && !token.starts_with("<<"))
+        || (token.contains(':') && !token.starts_with(['"', '\'']))
+        || token == "a"
+}
+
+/// Role of the literal at `index` if it is the object of a triple pattern,
+/// or the right side of a comparison between a variable and a literal in a
+/// FILTER, outside any VALUES block
+fn abstractable_literal_role(tokens: &[Token], index: usize) -> Option<LiteralRole> {
+    let text = tokens[index].text;
+    let plain_string = text.len() >= 2 && text.starts_with('"') && text.ends_with('"') && !text.starts_with("\"\"\"");
+    let number = text.starts_with(|c: char| c.is_ascii_digit());
+    if !(plain_string || number) || index < 2 {
+        return None;
+    }
+
+    let mut braces = 0usize;
+    let mut parens = 0usize;
+    let mut filter_parens = None;
+    let mut values_braces = None;
+    let mut after_values = false;
+    for (i, token) in tokens[..index].iter().enumerate() {
+        match token.text {
+            "{" => {
+                braces += 1;
+                if after_values {
+                    values_braces = values_braces.or(Some(braces));
+                    after_values = false;
+                }
+            }
+            "}" => {
+                if values_braces == Some(braces) {
+                    values_braces = None;
+                }
+                braces = braces.saturating_sub(1);
+            }
+            "(" => {
+                parens += 1;
+                if filter_parens.is_none() && i > 0 && tokens[i - 1].text.eq_ignore_ascii_case("FILTER") {
+                    filter_parens = Some(parens);
+                }
+            }
+            ")" => {
+                if filter_parens == Some(parens) {
+                    filter_parens = None;
+                }
+                parens = parens.saturating_sub(1);
+            }
+            word if word.eq_ignore_ascii_case("VALUES") => after_values = true,
+            _ => {}
+        }
+    }
+    if braces == 0 || values_braces.is_some() || after_values {
+        return None;
+    }
+
+    let previous = tokens[index - 1].text;
+    let before = tokens[index - 2].text;
+    let next = tokens.get(index + 1).map_or("", |token| token.text);
+    if filter_parens.is_some() {
+        let comparison = ["=", "!=", "<", ">", "<=", ">="].contains(&previous)
+            && before.starts_with('?')
+            && [")", "&&", "||"].contains(&next);
+        comparison.then(|| LiteralRole::Comparison {
+            operator: previous.to_string(),
+            predicate: object_predicate(tokens, before),
+        })
+    } else {
+        let object = parens == 0
+            && is_term(previous)
+            && (is_term(before) || before == ";")
+            && [".", ";", "}", ","].contains(&next);
+        object.then(|| LiteralRole::Object { predicate: previous.to_string() })
+    }
+}
+
+/// Constant predicate of the first triple pattern with `variable` as object
+fn object_predicate(tokens: &[Token], variable: &str) -> Option<String> {
+    tokens.windows(4).find_map(|window| {
+        let [before, predicate, object, next] = window else { return None };
+        let pattern = object.text == variable
+            && is_term(predicate.text)
+            && !predicate.text.starts_with(['?', '$'])
+            && (is_term(before.text) || before.text == ";")
+            && [".", ";", "}", ","].contains(&next.text);
+        pattern.then(|| predicate.text.to_string())
+    })
+}
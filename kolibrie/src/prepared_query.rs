@@ -0,0 +1,309 @@
+/*
+ * Copyright © 2025 Volodymyr Kadzhaia
+ * Copyright © 2025 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use crate::execute_query::{finish_planned_rows, plan_query, PlannedQuery};
+use crate::sparql_database::SparqlDatabase;
+use crate::streamertail_optimizer::execution::columnar::collect_filter_variables;
+use crate::streamertail_optimizer::{ExecutionEngine, PhysicalOperator};
+use shared::query::FilterExpression;
+use shared::terms::{Term, TriplePattern};
+use std::collections::{HashMap, HashSet};
+
+/// A SELECT query planned once and executed with different constants.
+///
+/// `$name` placeholders mark the parameters of the query. They are planned as
+/// the variable `?name` when the query is prepared, and bound to the given
+/// values in the kept plan on every execution, so the query is neither parsed
+/// nor optimized again. Any other variable of the query may be bound the same
+/// way.
+///
+/// ```ignore
+/// let query = database.prepare("SELECT ?name WHERE { ?p ex:name ?name . ?p ex:age ?age . FILTER(?age > $min) }")?;
+/// let adults = query.execute(&mut database, &[("min", "17")])?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct PreparedQuery {
+    /// The query with `$name` rewritten to `?name`
+    sparql: String,
+    parameters: Vec<String>,
+    /// Every variable of the query, placeholders included
+    variables: Vec<String>,
+    planned: PlannedQuery,
+}
+
+/// A value bound to a variable of a plan, encoded once per execution
+pub(crate) struct BoundValue {
+    id: u32,
+    /// The value as filters compare it: an IRI or literal without delimiters
+    text: String,
+    /// The value as it appears in result rows
+    display: String,
+}
+
+impl BoundValue {
+    /// Encodes `value`, written as in SPARQL
+    pub(crate) fn new(value: &str, database: &mut SparqlDatabase) -> Self {
+        let text = database.resolve_query_term(value, &HashMap::new());
+        let id = database.encode_term_star(&text);
+        let display = {
+            let dict = database.dictionary.read().unwrap();
+            let qt_store = database.quoted_triple_store.read().unwrap();
+            dict.decode_term(id, &qt_store).unwrap_or_else(|| text.clone())
+        };
+        BoundValue { id, text, display }
+    }
+}
+
+impl PreparedQuery {
+    /// Plans `sparql` on `database`, keeping the plan in its plan cache
+    pub fn new(sparql: &str, database: &mut SparqlDatabase) -> Result<Self, String> {
+        let placeholder = regex::Regex::new(r"\$([A-Za-z_][A-Za-z0-9_]*)").unwrap();
+        let mut parameters: Vec<String> = Vec::new();
+        for captures in placeholder.captures_iter(sparql) {
+            let name = captures[1].to_string();
+            if !parameters.contains(&name) {
+                parameters.push(name);
+            }
+        }
+        let sparql = placeholder.replace_all(sparql, "?$1").into_owned();
+        let variable = regex::Regex::new(r"\?([A-Za-z_][A-Za-z0-9_]*)").unwrap();
+        let mut variables: Vec<String> = variable.captures_iter(&sparql).map(|captures| captures[1].to_string()).collect();
+        variables.sort();
+        variables.dedup();
+        let planned = plan_query(&sparql, database, false)?;
+        check_bindable(&planned.plan, &parameters)?;
+        Ok(Self { sparql, parameters, variables, planned })
+    }
+
+    /// Names of the `$` placeholders, in order of first appearance
+    pub fn parameters(&self) -> &[String] {
+        &self.parameters
+    }
+
+    /// The query as planned, with placeholders turned into variables
+    pub fn sparql(&self) -> &str {
+        &self.sparql
+    }
+
+    /// Runs the query with `bindings` of parameter or variable names (with
+    /// or without `$`/`?`) to values written as in SPARQL: `<iri>`, `prefix:name`,
+    /// `"literal"` or a plain number. Every `$` placeholder must be bound.
+    pub fn execute(
+        &self,
+        database: &mut SparqlDatabase,
+        bindings: &[(&str, &str)],
+    ) -> Result<Vec<Vec<String>>, String> {
+        let mut values: HashMap<String, BoundValue> = HashMap::new();
+        for (name, value) in bindings {
+            let name = name.trim_start_matches(['$', '?']);
+            if self.variables.binary_search_by(|variable| variable.as_str().cmp(name)).is_err() {
+                return Err(format!("the query has no parameter or variable named {}", name));
+            }
+            values.insert(name.to_string(), BoundValue::new(value, database));
+        }
+        if let Some(missing) = self.parameters.iter().find(|name| !values.contains_key(*name)) {
+            return Err(format!("no value bound for parameter ${}", missing));
+        }
+
+        let names: Vec<String> = values.keys().cloned().collect();
+        check_bindable(&self.planned.plan, &names)?;
+        let plan = bind_parameters(&self.planned.plan, &values);
+        let mut rows = ExecutionEngine::execute(&plan, database);
+        for row in &mut rows {
+            for (name, value) in &values {
+                row.insert(name.clone(), value.display.clone());
+            }
+        }
+        Ok(finish_planned_rows(&self.planned, rows))
+    }
+}
+
+fn variable_name(var: &str) -> &str {
+    var.strip_prefix('?').unwrap_or(var)
+}
+
+fn bind_term(term: &Term, values: &HashMap<String, BoundValue>) -> Term {
+    match term {
+        Term::Variable(var) => match values.get(variable_name(var)) {
+            Some(value) => Term::Constant(value.id),
+            None => term.clone(),
+        },
+        Term::Constant(_) => term.clone(),
+        Term::QuotedTriple(inner) => Term::QuotedTriple(Box::new((
+            bind_term(&inner.0, values),
+            bind_term(&inner.1, values),
+            bind_term(&inner.2, values),
+        ))),
+    }
+}
+
+fn bind_pattern(pattern: &TriplePattern, values: &HashMap<String, BoundValue>) -> TriplePattern {
+    (
+        bind_term(&pattern.0, values),
+        bind_term(&pattern.1, values),
+        bind_term(&pattern.2, values),
+    )
+}
+
+fn mentions_bound(pattern: &TriplePattern, values: &HashMap<String, BoundValue>) -> bool {
+    bind_pattern(pattern, values) != *pattern
+}
+
+/// Joins index scans of the patterns, for multi-way joins whose shape no
+/// longer holds once a variable became a constant
+fn scan_chain(patterns: &[TriplePattern], values: &HashMap<String, BoundValue>) -> PhysicalOperator {
+    patterns
+        .iter()
+        .map(|pattern| PhysicalOperator::index_scan(bind_pattern(pattern, values)))
+        .reduce(PhysicalOperator::hash_join)
+        .unwrap_or(PhysicalOperator::Values { variables: vec![], values: vec![] })
+}
+
+/// Whether `operand` mentions the variable `name` (without `?`)
+fn mentions_variable(operand: &str, name: &str) -> bool {
+    operand.match_indices('?').any(|(start, _)| {
+        let rest = &operand[start + 1..];
+        rest.starts_with(name)
+            && !rest[name.len()..].starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// Comparison operands that are expressions rather than a single term
+fn compound_operands<'a>(expression: &FilterExpression<'a>, operands: &mut Vec<&'a str>) {
+    match expression {
+        FilterExpression::Comparison(left, _, right) => {
+            for operand in [*left, *right] {
+                if operand.contains(char::is_whitespace) || operand.starts_with('(') {
+                    operands.push(operand);
+                }
+            }
+        }
+        FilterExpression::And(left, right) | FilterExpression::Or(left, right) => {
+            compound_operands(left, operands);
+            compound_operands(right, operands);
+        }
+        FilterExpression::Not(inner) => compound_operands(inner, operands),
+        FilterExpression::ArithmeticExpr(_) | FilterExpression::FunctionCall(..) => {}
+    }
+}
+
+/// Rejects plans comparing against an expression over one of `names`, which
+/// filters do not evaluate
+fn check_bindable(plan: &PhysicalOperator, names: &[String]) -> Result<(), String> {
+    match plan {
+        PhysicalOperator::Filter { input, condition } => {
+            let mut operands = Vec::new();
+            compound_operands(&condition.expression, &mut operands);
+            for operand in operands {
+                if let Some(name) = names.iter().find(|name| mentions_variable(operand, name)) {
+                    return Err(format!("?{} cannot be bound inside the expression {}", name, operand));
+                }
+            }
+            check_bindable(input, names)
+        }
+        PhysicalOperator::HashJoin { left, right }
+        | PhysicalOperator::NestedLoopJoin { left, right }
+        | PhysicalOperator::ParallelJoin { left, right }
+        | PhysicalOperator::OptimizedHashJoin { left, right } => {
+            check_bindable(left, names)?;
+            check_bindable(right, names)
+        }
+        PhysicalOperator::Projection { input, .. }
+        | PhysicalOperator::Bind { input, .. }
+        | PhysicalOperator::MLPredict { input, .. } => check_bindable(input, names),
+        PhysicalOperator::Subquery { inner, .. } => check_bindable(inner, names),
+        _ => Ok(()),
+    }
+}
+
+/// Replaces the bound variables of a plan by their values. Filters read the
+/// values from a `VALUES` row joined to their input, so arithmetic and
+/// function calls see them too.
+pub(crate) fn bind_parameters(plan: &PhysicalOperator, values: &HashMap<String, BoundValue>) -> PhysicalOperator {
+    let bind = |input: &PhysicalOperator| Box::new(bind_parameters(input, values));
+    match plan {
+        PhysicalOperator::TableScan { pattern } => PhysicalOperator::TableScan { pattern: bind_pattern(pattern, values) },
+        PhysicalOperator::IndexScan { pattern } => PhysicalOperator::IndexScan { pattern: bind_pattern(pattern, values) },
+        PhysicalOperator::Filter { input, condition } => {
+            let mut referenced = HashSet::new();
+            collect_filter_variables(&condition.expression, &mut referenced);
+            let mut bound: Vec<(&String, &BoundValue)> = values
+                .iter()
+                .filter(|(name, _)| referenced.contains(*name))
+                .collect();
+            bound.sort_by_key(|(name, _)| *name);
+            let input = if bound.is_empty() {
+                bind(input)
+            } else {
+                let row = PhysicalOperator::Values {
+                    variables: bound.iter().map(|(name, _)| format!("?{}", name)).collect(),
+                    values: vec![bound.iter().map(|(_, value)| Some(value.text.clone())).collect()],
+                };
+                Box::new(PhysicalOperator::hash_join(bind_parameters(input, values), row))
+            };
+            PhysicalOperator::Filter { input, condition: condition.clone() }
+        }
+        PhysicalOperator::HashJoin { left, right } => PhysicalOperator::HashJoin { left: bind(left), right: bind(right) },
+        PhysicalOperator::NestedLoopJoin { left, right } => {
+            PhysicalOperator::NestedLoopJoin { left: bind(left), right: bind(right) }
+        }
+        PhysicalOperator::ParallelJoin { left, right } => {
+            PhysicalOperator::ParallelJoin { left: bind(left), right: bind(right) }
+        }
+        PhysicalOperator::OptimizedHashJoin { left, right } => {
+            PhysicalOperator::OptimizedHashJoin { left: bind(left), right: bind(right) }
+        }
+        PhysicalOperator::StarJoin { join_var, patterns } => {
+            if values.contains_key(variable_name(join_var)) {
+                scan_chain(patterns, values)
+            } else {
+                PhysicalOperator::StarJoin {
+                    join_var: join_var.clone(),
+                    patterns: patterns.iter().map(|pattern| bind_pattern(pattern, values)).collect(),
+                }
+            }
+        }
+        PhysicalOperator::LeapfrogJoin { patterns, .. } if patterns.iter().any(|p| mentions_bound(p, values)) => {
+            scan_chain(patterns, values)
+        }
+        PhysicalOperator::Projection { input, variables } => PhysicalOperator::Projection {
+            input: bind(input),
+            variables: variables.clone(),
+        },
+        PhysicalOperator::Subquery { inner, projected_vars } => PhysicalOperator::Subquery {
+            inner: bind(inner),
+            projected_vars: projected_vars.clone(),
+        },
+        PhysicalOperator::Bind { input, function_name, arguments, output_variable } => PhysicalOperator::Bind {
+            input: bind(input),
+            function_name: function_name.clone(),
+            arguments: arguments
+                .iter()
+                .map(|argument| match values.get(variable_name(argument)) {
+                    Some(value) if argument.starts_with('?') => value.text.clone(),
+                    _ => argument.clone(),
+                })
+                .collect(),
+            output_variable: output_variable.clone(),
+        },
+        PhysicalOperator::MLPredict { input, model_name, model_path, input_variables, output_variable } => {
+            PhysicalOperator::MLPredict {
+                input: bind(input),
+                model_name: model_name.clone(),
+                model_path: model_path.clone(),
+                input_variables: input_variables.clone(),
+                output_variable: output_variable.clone(),
+            }
+        }
+        PhysicalOperator::LeapfrogJoin { .. }
+        | PhysicalOperator::InMemoryBuffer { .. }
+        | PhysicalOperator::Values { .. } => plan.clone(),
+    }
+}
@@ -10,7 +10,7 @@
 
 use crate::sparql_database::SparqlDatabase;
 use crate::rsp::r2s::{Relation2StreamOperator, StreamOperator};
-use crate::rsp::s2r::{ContentContainer, ReportStrategy, Tick, WindowTriple};
+use crate::rsp::s2r::{ContentContainer, ObjectKind, ReportStrategy, Tick, WindowTriple};
 use crate::rsp::window_runner::{WindowRunner, WindowSpec};
 use shared::triple::Triple;
 use std::collections::{BTreeMap, BTreeSet, HashSet};
@@ -687,6 +687,7 @@ impl<'a> QueryBuilder<'a> {
                 s: subject.to_string(),
                 p: predicate.to_string(),
                 o: object.to_string(),
+                object_kind: ObjectKind::Unknown,
             };
             runner.push(triple, timestamp);
             self.current_timestamp = timestamp;
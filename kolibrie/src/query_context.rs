@@ -0,0 +1,377 @@
+/*
+ * Copyright © 2025 Volodymyr Kadzhaia
+ * Copyright © 2025 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Input rows an operator scans between two checks of its query context,
+/// and rows it produces between two updates of the memory it accounts
+pub const CHECK_INTERVAL_ROWS: usize = 1024;
+
+thread_local! {
+    /// Context of the query running on this thread, installed by `run_with_context`.
+    static CURRENT: RefCell<Option<QueryContext>> = const { RefCell::new(None) };
+}
+
+/// Why a query stopped before producing its result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    /// The deadline of the query passed
+    Timeout,
+    /// The cancellation token of the query was triggered
+    Cancelled,
+    /// Intermediate results would have exceeded the memory budget (in bytes)
+    MemoryLimit { limit: usize },
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::Timeout => write!(f, "query timed out"),
+            QueryError::Cancelled => write!(f, "query was cancelled"),
+            QueryError::MemoryLimit { limit } => {
+                write!(f, "query exceeded its memory limit of {} bytes", limit)
+            }
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// Shared flag stopping a running query; clones refer to the same flag, so a
+/// token can be kept by another thread (e.g. an HTTP handler) to cancel it.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Default)]
+struct ContextState {
+    deadline: Option<Instant>,
+    token: CancellationToken,
+    memory_limit: Option<usize>,
+    memory_used: AtomicUsize,
+    aborted: AtomicBool,
+    /// First reason the query stopped; later ones are ignored
+    error: Mutex<Option<QueryError>>,
+}
+
+/// Execution context of one query: a deadline, a cancellation token and a
+/// budget for the bytes held by intermediate results. Join and aggregation
+/// operators check it while producing rows and stop early once it is
+/// exceeded, after which the query returns the recorded `QueryError`.
+#[derive(Debug, Clone, Default)]
+pub struct QueryContext {
+    inner: Arc<ContextState>,
+}
+
+impl QueryContext {
+    /// A context without any limit
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    pub fn with_deadline(self, deadline: Instant) -> Self {
+        self.rebuild(|state| state.deadline = Some(deadline))
+    }
+
+    /// Limits the bytes held by intermediate results at any time
+    pub fn with_memory_limit(self, bytes: usize) -> Self {
+        self.rebuild(|state| state.memory_limit = Some(bytes))
+    }
+
+    pub fn with_cancellation(self, token: CancellationToken) -> Self {
+        self.rebuild(|state| state.token = token)
+    }
+
+    fn rebuild(self, configure: impl FnOnce(&mut ContextState)) -> Self {
+        let mut state = ContextState {
+            deadline: self.inner.deadline,
+            token: self.inner.token.clone(),
+            memory_limit: self.inner.memory_limit,
+            ..ContextState::default()
+        };
+        configure(&mut state);
+        Self { inner: Arc::new(state) }
+    }
+
+    /// Token cancelling the queries running with this context
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.inner.token.clone()
+    }
+
+    /// Bytes currently accounted to intermediate results
+    pub fn memory_used(&self) -> usize {
+        self.inner.memory_used.load(Ordering::Relaxed)
+    }
+
+    /// Reason the query stopped, if it did
+    pub fn error(&self) -> Option<QueryError> {
+        self.inner.error.lock().unwrap().clone()
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.inner.aborted.load(Ordering::Relaxed)
+    }
+
+    /// Checks the cancellation token and the deadline
+    pub fn check(&self) -> Result<(), QueryError> {
+        if let Some(error) = self.error() {
+            return Err(error);
+        }
+        if self.inner.token.is_cancelled() {
+            return Err(self.abort(QueryError::Cancelled));
+        }
+        if self.inner.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(self.abort(QueryError::Timeout));
+        }
+        Ok(())
+    }
+
+    /// Accounts `bytes` more to intermediate results, failing once the
+    /// memory limit is exceeded
+    pub fn allocate(&self, bytes: usize) -> Result<(), QueryError> {
+        let used = self.inner.memory_used.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        self.check_memory(used)
+    }
+
+    pub fn release(&self, bytes: usize) {
+        let _ = self
+            .inner
+            .memory_used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| Some(used.saturating_sub(bytes)));
+    }
+
+    /// Resets the accounted memory to `before` plus the `bytes` of the output
+    /// an operator kept, once everything it allocated meanwhile is freed
+    pub(crate) fn settle(&self, before: usize, bytes: usize) -> Result<(), QueryError> {
+        let used = before.saturating_add(bytes);
+        self.inner.memory_used.store(used, Ordering::Relaxed);
+        self.check_memory(used)
+    }
+
+    fn check_memory(&self, used: usize) -> Result<(), QueryError> {
+        match self.inner.memory_limit {
+            Some(limit) if used > limit => Err(self.abort(QueryError::MemoryLimit { limit })),
+            _ => Ok(()),
+        }
+    }
+
+    /// Records `error` unless the query already stopped, returning the
+    /// error the query stopped with
+    fn abort(&self, error: QueryError) -> QueryError {
+        let mut recorded = self.inner.error.lock().unwrap();
+        self.inner.aborted.store(true, Ordering::Relaxed);
+        recorded.get_or_insert(error).clone()
+    }
+}
+
+/// Context of the query running on this thread
+pub fn current_context() -> Option<QueryContext> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Reinstalls the context that was current before `run_with_context`, also
+/// when the query panics
+struct RestoreContext(Option<QueryContext>);
+
+impl Drop for RestoreContext {
+    fn drop(&mut self) {
+        let outer = self.0.take();
+        CURRENT.with(|current| *current.borrow_mut() = outer);
+    }
+}
+
+/// Runs `f` with `context` as the context of the queries it executes on this
+/// thread. Results produced after the context stopped the query are
+/// incomplete, so they are replaced by the error it recorded.
+pub fn run_with_context<R>(context: &QueryContext, f: impl FnOnce() -> R) -> Result<R, QueryError> {
+    context.check()?;
+    let restore = RestoreContext(CURRENT.with(|current| current.borrow_mut().replace(context.clone())));
+    let result = f();
+    drop(restore);
+    match context.error() {
+        Some(error) => Err(error),
+        None => Ok(result),
+    }
+}
+
+/// Rough size in bytes of a row binding `width` variables, counting the map
+/// entries and variable names
+pub(crate) fn estimated_row_bytes(width: usize) -> usize {
+    48 + 64 * width
+}
+
+/// Checks an operator's rows against the context of the running query. It
+/// is taken on the thread that runs the query and can then be shared with
+/// the worker threads producing the rows.
+pub(crate) struct OperatorBudget {
+    context: Option<QueryContext>,
+    row_bytes: usize,
+    scanned: AtomicUsize,
+    pending: AtomicUsize,
+}
+
+impl OperatorBudget {
+    /// Budget of an operator producing rows of `row_width` variables
+    pub(crate) fn current(row_width: usize) -> Self {
+        Self::with_row_bytes(estimated_row_bytes(row_width))
+    }
+
+    /// Budget of an operator producing rows of `row_bytes` bytes each
+    pub(crate) fn with_row_bytes(row_bytes: usize) -> Self {
+        Self {
+            context: current_context(),
+            row_bytes,
+            scanned: AtomicUsize::new(0),
+            pending: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn is_aborted(&self) -> bool {
+        self.context.as_ref().is_some_and(QueryContext::is_aborted)
+    }
+
+    /// Accounts `rows` more input rows the operator goes through, checking
+    /// the deadline and the cancellation token every `CHECK_INTERVAL_ROWS`
+    /// rows, so operators producing few rows still stop; returns whether to
+    /// go on scanning
+    pub(crate) fn scan(&self, rows: usize) -> bool {
+        let Some(context) = &self.context else {
+            return true;
+        };
+        let scanned = self.scanned.fetch_add(rows, Ordering::Relaxed) + rows;
+        if scanned >= CHECK_INTERVAL_ROWS {
+            self.scanned.store(0, Ordering::Relaxed);
+            if context.check().is_err() {
+                return false;
+            }
+        }
+        !context.is_aborted()
+    }
+
+    /// Accounts `rows` more produced rows to the memory budget every
+    /// `CHECK_INTERVAL_ROWS` rows; returns whether to go on producing
+    pub(crate) fn produce(&self, rows: usize) -> bool {
+        let Some(context) = &self.context else {
+            return true;
+        };
+        let pending = self.pending.fetch_add(rows, Ordering::Relaxed) + rows;
+        if pending >= CHECK_INTERVAL_ROWS {
+            let flushed = self.pending.swap(0, Ordering::Relaxed);
+            if context.allocate(flushed * self.row_bytes).is_err() {
+                return false;
+            }
+        }
+        !context.is_aborted()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_error_wins() {
+        let context = QueryContext::new().with_memory_limit(100);
+        assert!(context.allocate(60).is_ok());
+        assert_eq!(context.allocate(60), Err(QueryError::MemoryLimit { limit: 100 }));
+        context.cancellation_token().cancel();
+        assert_eq!(context.check(), Err(QueryError::MemoryLimit { limit: 100 }));
+        assert!(context.is_aborted());
+    }
+
+    #[test]
+    fn deadlines_and_tokens_stop_queries() {
+        let expired = QueryContext::new().with_deadline(Instant::now());
+        assert_eq!(expired.check(), Err(QueryError::Timeout));
+
+        let token = CancellationToken::new();
+        let context = QueryContext::new().with_cancellation(token.clone());
+        assert!(context.check().is_ok());
+        token.cancel();
+        assert_eq!(context.check(), Err(QueryError::Cancelled));
+    }
+
+    #[test]
+    fn budgets_see_the_context_installed_on_their_thread() {
+        let context = QueryContext::new().with_memory_limit(estimated_row_bytes(2) * 2000);
+        let produced = run_with_context(&context, || {
+            let budget = OperatorBudget::current(2);
+            (0..10_000).take_while(|_| budget.produce(1)).count()
+        });
+        assert_eq!(produced, Err(QueryError::MemoryLimit { limit: estimated_row_bytes(2) * 2000 }));
+        assert!(current_context().is_none());
+
+        // Without a context nothing is limited
+        let budget = OperatorBudget::current(2);
+        assert_eq!((0..10_000).take_while(|_| budget.produce(1)).count(), 10_000);
+    }
+
+    #[test]
+    fn scanning_checks_the_context_without_producing() {
+        let token = CancellationToken::new();
+        let context = QueryContext::new().with_cancellation(token.clone());
+        let scanned = run_with_context(&context, || {
+            let budget = OperatorBudget::current(2);
+            (0..10_000)
+                .take_while(|&row| {
+                    if row == 100 {
+                        token.cancel();
+                    }
+                    budget.scan(1)
+                })
+                .count()
+        });
+        assert_eq!(scanned, Err(QueryError::Cancelled));
+    }
+
+    #[test]
+    fn panicking_queries_restore_the_outer_context() {
+        let outer = QueryContext::new();
+        run_with_context(&outer, || {
+            let inner = QueryContext::new();
+            let panicked = std::panic::catch_unwind(|| run_with_context(&inner, || panic!("query failed")));
+            assert!(panicked.is_err());
+            assert!(current_context().is_some_and(|current| Arc::ptr_eq(&current.inner, &outer.inner)));
+        })
+        .unwrap();
+        assert!(current_context().is_none());
+    }
+
+    #[test]
+    fn settling_keeps_only_the_output() {
+        let context = QueryContext::new();
+        context.allocate(1000).unwrap();
+        context.settle(200, 50).unwrap();
+        assert_eq!(context.memory_used(), 250);
+        context.release(300);
+        assert_eq!(context.memory_used(), 0);
+    }
+}
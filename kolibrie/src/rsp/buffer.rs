@@ -0,0 +1,211 @@
+/*
+ * Copyright © 2025 Volodymyr Kadzhaia
+ * Copyright © 2025 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Bounded buffers between a window and its R2R worker thread (MultiThread mode).
+
+use crossbeam::channel::{bounded, Receiver, SendError, Sender, TrySendError};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// What a window does when its worker's buffer is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Wait until the worker takes an item (backpressure on the producer).
+    #[default]
+    Block,
+    /// Discard the oldest buffered window content to make room.
+    DropOldest,
+    /// Discard everything buffered; the worker only evaluates the newest content.
+    Coalesce,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferConfig {
+    /// Maximum number of window contents waiting for a worker (at least 1).
+    pub capacity: usize,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for BufferConfig {
+    fn default() -> Self {
+        BufferConfig {
+            capacity: 64,
+            overflow: OverflowPolicy::Block,
+        }
+    }
+}
+
+/// Snapshot of a buffer's counters.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueueMetrics {
+    /// Items currently waiting.
+    pub depth: usize,
+    pub capacity: usize,
+    /// Highest depth observed.
+    pub max_depth: usize,
+    /// Items accepted into the buffer.
+    pub enqueued: u64,
+    /// Items discarded by `DropOldest`.
+    pub dropped: u64,
+    /// Items discarded by `Coalesce`.
+    pub coalesced: u64,
+    /// Total time producers spent blocked by `Block`.
+    pub blocked: Duration,
+}
+
+#[derive(Default)]
+struct Counters {
+    max_depth: AtomicUsize,
+    enqueued: AtomicU64,
+    dropped: AtomicU64,
+    coalesced: AtomicU64,
+    blocked_nanos: AtomicU64,
+}
+
+/// Producer half of a bounded buffer applying an `OverflowPolicy`.
+pub struct BoundedSender<T> {
+    sender: Sender<T>,
+    // Lets the producer evict buffered items; does not keep the channel alive for the worker.
+    evict: Receiver<T>,
+    overflow: OverflowPolicy,
+    counters: Arc<Counters>,
+}
+
+/// Read-only view on a buffer's metrics.
+pub struct QueueMonitor<T> {
+    receiver: Receiver<T>,
+    capacity: usize,
+    counters: Arc<Counters>,
+}
+
+/// Create a buffer; the receiver is handed to the worker thread.
+pub fn bounded_buffer<T>(config: BufferConfig) -> (BoundedSender<T>, Receiver<T>, QueueMonitor<T>) {
+    let capacity = config.capacity.max(1);
+    let (sender, receiver) = bounded(capacity);
+    let counters = Arc::new(Counters::default());
+    (
+        BoundedSender {
+            sender,
+            evict: receiver.clone(),
+            overflow: config.overflow,
+            counters: Arc::clone(&counters),
+        },
+        receiver.clone(),
+        QueueMonitor {
+            receiver,
+            capacity,
+            counters,
+        },
+    )
+}
+
+impl<T> BoundedSender<T> {
+    /// Enqueue `item` according to the overflow policy.
+    /// Fails only when the worker has gone away.
+    pub fn send(&self, item: T) -> Result<(), SendError<T>> {
+        let mut item = item;
+        loop {
+            match self.sender.try_send(item) {
+                Ok(()) => break,
+                Err(TrySendError::Disconnected(back)) => return Err(SendError(back)),
+                Err(TrySendError::Full(back)) => match self.overflow {
+                    OverflowPolicy::Block => {
+                        let start = Instant::now();
+                        self.sender.send(back)?;
+                        self.counters
+                            .blocked_nanos
+                            .fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+                        break;
+                    }
+                    OverflowPolicy::DropOldest => {
+                        if self.evict.try_recv().is_ok() {
+                            self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                        }
+                        item = back;
+                    }
+                    OverflowPolicy::Coalesce => {
+                        let discarded = self.evict.try_iter().count() as u64;
+                        self.counters.coalesced.fetch_add(discarded, Ordering::Relaxed);
+                        item = back;
+                    }
+                },
+            }
+        }
+        self.counters.enqueued.fetch_add(1, Ordering::Relaxed);
+        self.counters
+            .max_depth
+            .fetch_max(self.sender.len(), Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl<T> QueueMonitor<T> {
+    pub fn metrics(&self) -> QueueMetrics {
+        QueueMetrics {
+            depth: self.receiver.len(),
+            capacity: self.capacity,
+            max_depth: self.counters.max_depth.load(Ordering::Relaxed),
+            enqueued: self.counters.enqueued.load(Ordering::Relaxed),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+            coalesced: self.counters.coalesced.load(Ordering::Relaxed),
+            blocked: Duration::from_nanos(self.counters.blocked_nanos.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn config(capacity: usize, overflow: OverflowPolicy) -> BufferConfig {
+        BufferConfig { capacity, overflow }
+    }
+
+    #[test]
+    fn test_drop_oldest_keeps_newest() {
+        let (sender, receiver, monitor) = bounded_buffer(config(2, OverflowPolicy::DropOldest));
+        for i in 0..5 {
+            sender.send(i).unwrap();
+        }
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![3, 4]);
+        let metrics = monitor.metrics();
+        assert_eq!(metrics.enqueued, 5);
+        assert_eq!(metrics.dropped, 3);
+        assert_eq!(metrics.max_depth, 2);
+        assert_eq!(metrics.depth, 0);
+    }
+
+    #[test]
+    fn test_coalesce_keeps_only_latest() {
+        let (sender, receiver, monitor) = bounded_buffer(config(3, OverflowPolicy::Coalesce));
+        for i in 0..4 {
+            sender.send(i).unwrap();
+        }
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![3]);
+        assert_eq!(monitor.metrics().coalesced, 3);
+    }
+
+    #[test]
+    fn test_block_waits_for_consumer() {
+        let (sender, receiver, monitor) = bounded_buffer(config(1, OverflowPolicy::Block));
+        sender.send(0).unwrap();
+        let worker = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            receiver.iter().take(2).collect::<Vec<_>>()
+        });
+        sender.send(1).unwrap();
+        assert_eq!(worker.join().unwrap(), vec![0, 1]);
+        let metrics = monitor.metrics();
+        assert_eq!(metrics.dropped + metrics.coalesced, 0);
+        assert!(metrics.blocked >= Duration::from_millis(20));
+    }
+}
@@ -7,10 +7,15 @@
 * you can obtain one at https://mozilla.org/MPL/2.0/.
 */
 
+use crate::adaptive_planner::{AdaptivePlanner, AdaptivePlannerConfig};
 use crate::parser::parse_combined_query;
+use crate::rsp::buffer::{BufferConfig, OverflowPolicy};
+use crate::rsp::checkpoint::{CheckpointConfig, EngineCheckpoint};
 use crate::rsp::r2r::R2ROperator;
 use crate::rsp::r2s::StreamOperator;
 use crate::rsp::s2r::{ReportStrategy, Tick};
+use crate::rsp::sink::{OutputBindings, StreamSink};
+use crate::rsp::source::StreamSource;
 use crate::rsp_engine::{
     OperationMode, QueryExecutionMode, RSPEngine, RSPQueryPlan, RSPWindow, ResultConsumer,
 };
@@ -18,12 +23,15 @@ use crate::sparql_database::SparqlDatabase;
 use crate::streamertail_optimizer::{
     build_logical_plan, LogicalOperator, PhysicalOperator, Streamertail,
 };
+use datalog::reasoning::entailment::EntailmentRegime;
 use shared::query::{StreamType, SyncPolicy, WindowBlock, WindowClause};
 use shared::rule::Rule;
 use shared::terms::Term;
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 /// RSP Query configuration extracted from parsed RSP-QL
 #[derive(Debug)]
@@ -50,11 +58,21 @@ pub struct RSPBuilder<'a, I, O> {
     sync_policy: SyncPolicy,
     reasoning_rules: Vec<Rule>,
     sparql_rules: Vec<String>,
+    entailment: Option<EntailmentRegime>,
+    /// Stream sources to attach once the engine is built, keyed by stream IRI.
+    sources: Vec<(String, Box<dyn StreamSource>)>,
+    /// Sinks to attach once the engine is built, keyed by output stream IRI.
+    sinks: Vec<(String, Box<dyn StreamSink>)>,
+    checkpointing: Option<CheckpointConfig>,
+    restore_from: Option<PathBuf>,
+    buffer_config: BufferConfig,
+    partition_workers: usize,
+    adaptive_planner: Option<AdaptivePlannerConfig>,
 }
 
 impl<'a, I, O> RSPBuilder<'a, I, O>
 where
-    O: Clone + Eq + Send + Debug + Hash + 'static + From<Vec<(String, String)>>,
+    O: Clone + Eq + Send + Debug + Hash + 'static + From<Vec<(String, String)>> + OutputBindings,
     I: Eq + PartialEq + Clone + Debug + Hash + Send + 'static,
 {
     pub fn new() -> RSPBuilder<'a, I, O> {
@@ -70,6 +88,16 @@ where
             sync_policy: SyncPolicy::default(),
             reasoning_rules: Vec::new(),
             sparql_rules: Vec::new(),
+            entailment: None,
+            sources: Vec::new(),
+            sinks: Vec::new(),
+            checkpointing: None,
+            restore_from: None,
+            buffer_config: BufferConfig::default(),
+            partition_workers: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            adaptive_planner: None,
         }
     }
 
@@ -93,6 +121,66 @@ where
         self
     }
 
+    /// Apply the standard rules of an entailment regime (RDFS, RDFS-Plus, OWL 2 RL)
+    /// per window firing. The schema axioms of the static data added with
+    /// `RSPEngine::add_static_ntriples` are taken into account.
+    pub fn set_entailment_regime(mut self, regime: EntailmentRegime) -> RSPBuilder<'a, I, O> {
+        self.entailment = Some(regime);
+        self
+    }
+
+    /// Attach a stream source to a stream IRI declared in the RSP-QL query.
+    /// `build()` fails if the IRI does not match any `FROM NAMED WINDOW ... ON <stream>`.
+    pub fn add_source(mut self, stream_iri: &str, source: Box<dyn StreamSource>) -> RSPBuilder<'a, I, O> {
+        self.sources.push((stream_iri.to_string(), source));
+        self
+    }
+
+    /// Attach a sink to the output stream named in the query's `REGISTER` clause.
+    /// `build()` fails if `output_iri` is not that stream.
+    pub fn add_sink(mut self, output_iri: &str, sink: Box<dyn StreamSink>) -> RSPBuilder<'a, I, O> {
+        self.sinks.push((output_iri.to_string(), sink));
+        self
+    }
+
+    /// Periodically write engine checkpoints to `path`, at most once per `interval`.
+    pub fn set_checkpointing<P: AsRef<Path>>(mut self, path: P, interval: Duration) -> RSPBuilder<'a, I, O> {
+        self.checkpointing = Some(CheckpointConfig {
+            path: path.as_ref().to_path_buf(),
+            interval,
+        });
+        self
+    }
+
+    /// Restore windows, operator state and source positions from a checkpoint file
+    /// written by an engine running the same query. `build()` fails if it cannot be read.
+    pub fn restore_from_checkpoint<P: AsRef<Path>>(mut self, path: P) -> RSPBuilder<'a, I, O> {
+        self.restore_from = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Bound the queue between each window and its worker thread (MultiThread mode)
+    /// to `capacity` window contents, applying `overflow` when a query falls behind.
+    pub fn set_window_buffer(mut self, capacity: usize, overflow: OverflowPolicy) -> RSPBuilder<'a, I, O> {
+        self.buffer_config = BufferConfig { capacity, overflow };
+        self
+    }
+
+    /// Number of worker threads evaluating the keys of each `PARTITION BY` window
+    /// (MultiThread mode). Defaults to the available parallelism.
+    pub fn set_partition_workers(mut self, workers: usize) -> RSPBuilder<'a, I, O> {
+        self.partition_workers = workers.max(1);
+        self
+    }
+
+    /// Replan window join orders from per-firing statistics. `config.trigger` applies to
+    /// windows without a `WITH REPLAN` clause; a planner with the default configuration
+    /// is installed automatically when any window has one.
+    pub fn set_adaptive_planner(mut self, config: AdaptivePlannerConfig) -> RSPBuilder<'a, I, O> {
+        self.adaptive_planner = Some(config);
+        self
+    }
+
     /// Add RSP-QL query instead of separate window parameters and SPARQL query
     pub fn add_rsp_ql_query(mut self, query: &'a str) -> RSPBuilder<'a, I, O> {
         self.rsp_ql_query = Some(query);
@@ -193,14 +281,30 @@ where
         database: &mut SparqlDatabase,
     ) -> Result<RSPWindow, String> {
         // Find the corresponding window block for this window
+        let window_block = window_blocks
+            .iter()
+            .find(|block| block.window_name == window_clause.window_iri);
+
+        // Partitioning by subject keeps every solution inside one partition only if
+        // all of the window's patterns share that subject.
+        if let Some(var) = window_clause.partition_by {
+            let star_shaped = window_block.is_some_and(|block| {
+                !block.patterns.is_empty() && block.patterns.iter().all(|(s, _, _)| *s == var)
+            });
+            if !star_shaped {
+                return Err(format!(
+                    "PARTITION BY {} requires {} to be the subject of every pattern in window {}",
+                    var, var, window_clause.window_iri
+                ));
+            }
+        }
+
         let spo_query = LogicalOperator::scan((
             Term::Variable("s".to_string()),
             Term::Variable("p".to_string()),
             Term::Variable("o".to_string()),
         ));
-        let window_query = window_blocks
-            .iter()
-            .find(|block| block.window_name == window_clause.window_iri)
+        let window_query = window_block
             .map(|block| {
                 // Convert window block patterns to query plan
                 for (j, (s, p, o)) in block.patterns.iter().enumerate() {
@@ -249,6 +353,8 @@ where
             tick,
             report_strategy,
             query: window_query,
+            partition_by: window_clause.partition_by.map(|var| var.to_string()),
+            replan: window_clause.replan.clone(),
         })
     }
 
@@ -320,11 +426,18 @@ where
         let query_config = self.parse_rsp_ql_query(rsp_ql_query)?;
 
         let sync_policy = query_config.sync_policy.clone();
+        let planner_config = match self.adaptive_planner.take() {
+            Some(config) => Some(config),
+            None if query_config.windows.iter().any(|w| w.replan.is_some()) => {
+                Some(AdaptivePlannerConfig::default())
+            }
+            None => None,
+        };
 
         // Create RSP-QL query plan using Volcano optimizer
         let rsp_query_plan = Self::create_rsp_query_plan(&query_config)?;
 
-        Ok(RSPEngine::new(
+        let mut engine = RSPEngine::new(
             query_config,
             triples,
             syntax,
@@ -337,6 +450,27 @@ where
             sync_policy,
             self.reasoning_rules,
             self.sparql_rules,
-        ))
+            self.entailment,
+            self.buffer_config,
+            self.partition_workers,
+        );
+
+        if let Some(config) = planner_config {
+            engine.set_adaptive_planner(Arc::new(AdaptivePlanner::new(config)))?;
+        }
+        if let Some(path) = &self.restore_from {
+            engine.restore_checkpoint(&EngineCheckpoint::load(path)?)?;
+        }
+        if let Some(config) = self.checkpointing {
+            engine.set_checkpointing(config);
+        }
+        for (stream_iri, source) in self.sources {
+            engine.attach_source(&stream_iri, source)?;
+        }
+        for (output_iri, sink) in self.sinks {
+            engine.attach_sink(&output_iri, sink)?;
+        }
+
+        Ok(engine)
     }
 }
@@ -0,0 +1,100 @@
+/*
+ * Copyright © 2025 Volodymyr Kadzhaia
+ * Copyright © 2025 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! On-disk snapshots of `RSPEngine` state.
+//!
+//! Window items are stored as decoded N-Triples statements rather than dictionary
+//! IDs, so a checkpoint stays valid for a freshly built engine whose dictionary
+//! assigns different IDs.
+
+use crate::rsp::source::SourcePosition;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Bumped whenever the layout below changes incompatibly.
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EngineCheckpoint {
+    pub version: u32,
+    /// Output stream of the query the checkpoint was taken from.
+    pub output_stream: String,
+    pub windows: Vec<WindowCheckpoint>,
+    /// `Relation2StreamOperator` result set used for ISTREAM/DSTREAM differencing.
+    pub last_result: Vec<Vec<(String, String)>>,
+    /// Latest per-window bindings awaiting a cross-window join (SingleThread mode).
+    pub pending_window_results: HashMap<String, Vec<HashMap<String, String>>>,
+    /// Read positions of attached sources, keyed by `StreamSource::describe()`.
+    pub source_positions: HashMap<String, SourcePosition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowCheckpoint {
+    pub window_iri: String,
+    pub app_time: usize,
+    pub active: Vec<WindowContentCheckpoint>,
+    /// Items of the last firing, currently loaded (and reasoned over) in the R2R store.
+    pub materialized: Vec<String>,
+    /// Open windows of each key of a `PARTITION BY` window, which keeps
+    /// nothing in `active` or `materialized`.
+    #[serde(default)]
+    pub partitions: Vec<Vec<WindowContentCheckpoint>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowContentCheckpoint {
+    pub open: usize,
+    pub close: usize,
+    pub last_timestamp_changed: usize,
+    pub items: Vec<String>,
+}
+
+/// Where and how often the engine writes checkpoints.
+#[derive(Debug, Clone)]
+pub struct CheckpointConfig {
+    pub path: PathBuf,
+    pub interval: Duration,
+}
+
+impl EngineCheckpoint {
+    /// Write the checkpoint as JSON. The file is replaced atomically, so a crash
+    /// mid-write leaves the previous checkpoint intact.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        let json = serde_json::to_vec(self).map_err(|e| format!("Failed to serialize checkpoint: {}", e))?;
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        fs::write(&tmp, json)
+            .map_err(|e| format!("Failed to write checkpoint {}: {}", tmp.display(), e))?;
+        fs::rename(&tmp, path)
+            .map_err(|e| format!("Failed to move checkpoint into {}: {}", path.display(), e))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<EngineCheckpoint, String> {
+        let path = path.as_ref();
+        let data = fs::read(path)
+            .map_err(|e| format!("Failed to read checkpoint {}: {}", path.display(), e))?;
+        let checkpoint: EngineCheckpoint = serde_json::from_slice(&data)
+            .map_err(|e| format!("Invalid checkpoint {}: {}", path.display(), e))?;
+        if checkpoint.version != CHECKPOINT_VERSION {
+            return Err(format!(
+                "Unsupported checkpoint version {} in {} (expected {})",
+                checkpoint.version,
+                path.display(),
+                CHECKPOINT_VERSION
+            ));
+        }
+        Ok(checkpoint)
+    }
+}
@@ -8,9 +8,14 @@
  * you can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+pub mod buffer;
 pub mod builder;
+pub mod checkpoint;
+pub mod partition;
 pub mod r2r;
 pub mod r2s;
 pub mod s2r;
 pub mod simple_r2r;
+pub mod sink;
+pub mod source;
 pub mod window_runner;
\ No newline at end of file
@@ -0,0 +1,491 @@
+/*
+ * Copyright © 2025 Volodymyr Kadzhaia
+ * Copyright © 2025 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Keyed windows (`PARTITION BY ?var`).
+//!
+//! The stream is hash-partitioned by subject; every key gets its own window, all
+//! keys share one clock, and the contents fired at the same moment are evaluated
+//! on a pool of workers. Their results are merged back into a single result set
+//! per firing before the R2S operator (or the cross-window join) sees them.
+
+use crate::rsp::buffer::{bounded_buffer, BoundedSender, BufferConfig, OverflowPolicy, QueueMetrics, QueueMonitor};
+use crate::rsp::s2r::ContentContainer;
+use crate::rsp::window_runner::{WindowRunner, WindowSpec};
+use crossbeam::channel::{unbounded, Sender};
+#[cfg(not(test))]
+use log::debug;
+use shared::triple::Triple;
+use std::any::Any;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use std::mem;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+#[cfg(test)]
+use std::println as debug;
+
+/// Evaluates the content of one key's window firing and returns the R2R results.
+pub type PartitionProcessor<I, O> = Box<dyn FnMut(ContentContainer<I>) -> Vec<O> + Send>;
+
+/// Receives the merged results of one firing together with its timestamp.
+pub type MergedResultsFn<O> = Arc<dyn Fn(Vec<O>, usize) + Send + Sync>;
+
+/// Partition key of a stream item: the subject for triples, the whole item otherwise.
+pub fn partition_key<I: Hash + 'static>(item: &I) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    match (item as &dyn Any).downcast_ref::<Triple>() {
+        Some(triple) => triple.subject.hash(&mut hasher),
+        None => item.hash(&mut hasher),
+    }
+    hasher.finish()
+}
+
+type Fired<I> = Arc<Mutex<Vec<(u64, ContentContainer<I>)>>>;
+
+/// Open windows of one key, as `(open, close, items, last_timestamp_changed)`.
+pub type KeyWindows<I> = Vec<(usize, usize, Vec<I>, usize)>;
+
+/// One window per partition key, all built from the same specification.
+pub struct KeyedWindows<I>
+where
+    I: Eq + PartialEq + Clone + Debug + Hash + Send,
+{
+    spec: WindowSpec,
+    uri: String,
+    windows: HashMap<u64, WindowRunner<I>>,
+    fired: Fired<I>,
+    app_time: Option<usize>,
+}
+
+impl<I> KeyedWindows<I>
+where
+    I: Eq + PartialEq + Clone + Debug + Hash + Send + 'static,
+{
+    pub fn new(spec: WindowSpec, uri: String) -> Self {
+        KeyedWindows {
+            spec,
+            uri,
+            windows: HashMap::new(),
+            fired: Arc::new(Mutex::new(Vec::new())),
+            app_time: None,
+        }
+    }
+
+    /// Add an item to its key's window. When `ts` moves time forward, every key's
+    /// window is advanced first, so keys without new items still fire and evict.
+    /// Returns the non-empty contents fired by this item, tagged with their key.
+    pub fn add(&mut self, item: I, ts: usize) -> Vec<(u64, ContentContainer<I>)> {
+        if self.app_time.is_none_or(|t| ts > t) {
+            self.app_time = Some(ts);
+            for window in self.windows.values_mut() {
+                window.advance(ts);
+            }
+            // Keys whose windows hold nothing are recreated on their next item.
+            self.windows.retain(|_, window| {
+                window.active_windows().iter().any(|(_, _, content)| content.len() > 0)
+            });
+        }
+        let key = partition_key(&item);
+        self.window(key).add_to_window(item, ts);
+        self.take_fired()
+    }
+
+    /// The window of `key`, created when the key has none.
+    fn window(&mut self, key: u64) -> &mut WindowRunner<I> {
+        let (spec, uri, fired) = (&self.spec, &self.uri, &self.fired);
+        self.windows.entry(key).or_insert_with(|| {
+            let mut window = WindowRunner::new(spec.clone(), uri.clone());
+            let fired = Arc::clone(fired);
+            window.register_callback(Box::new(move |content| {
+                fired.lock().unwrap().push((key, content));
+            }));
+            window
+        })
+    }
+
+    /// Latest timestamp seen, shared by the windows of all keys.
+    pub fn app_time(&self) -> Option<usize> {
+        self.app_time
+    }
+
+    /// Open windows of every key, as `(open, close, content)`.
+    pub fn active_windows(&self) -> Vec<Vec<(usize, usize, &ContentContainer<I>)>> {
+        self.windows.values().map(|window| window.active_windows()).collect()
+    }
+
+    /// Replace every key's windows with the given open windows. The key is
+    /// taken from the items, so partitions without items are skipped.
+    pub fn restore(&mut self, app_time: Option<usize>, partitions: Vec<KeyWindows<I>>) {
+        self.windows.clear();
+        self.app_time = app_time;
+        let Some(app_time) = app_time else {
+            return;
+        };
+        for windows in partitions {
+            let Some(item) = windows.iter().flat_map(|(_, _, items, _)| items).next() else {
+                continue;
+            };
+            let key = partition_key(item);
+            self.window(key).restore(app_time, windows);
+        }
+    }
+
+    /// Fire the open windows of every key.
+    pub fn flush(&mut self) -> Vec<(u64, ContentContainer<I>)> {
+        for window in self.windows.values_mut() {
+            window.flush();
+        }
+        self.take_fired()
+    }
+
+    /// Number of keys with a live window.
+    pub fn len(&self) -> usize {
+        self.windows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    fn take_fired(&mut self) -> Vec<(u64, ContentContainer<I>)> {
+        let mut fired = mem::take(&mut *self.fired.lock().unwrap());
+        fired.retain(|(_, content)| content.len() > 0);
+        fired
+    }
+}
+
+enum MergeMessage<O> {
+    /// Announced before the firing's contents are handed to the workers.
+    Firing { tick: u64, ts: usize, contents: usize },
+    Results { tick: u64, results: Vec<O> },
+}
+
+struct PartitionTask<I>
+where
+    I: Eq + PartialEq + Clone + Debug + Hash + Send,
+{
+    tick: u64,
+    content: ContentContainer<I>,
+}
+
+struct Workers<I, O>
+where
+    I: Eq + PartialEq + Clone + Debug + Hash + Send,
+{
+    senders: Vec<BoundedSender<PartitionTask<I>>>,
+    monitors: Vec<QueueMonitor<PartitionTask<I>>>,
+    merge: Sender<MergeMessage<O>>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+/// Evaluates fired key windows and merges each firing's results.
+///
+/// With a single processor, contents are evaluated inline on the caller's thread.
+/// Otherwise every processor runs on its own worker thread behind a bounded queue,
+/// and a merger thread emits firings in the order they were dispatched.
+pub struct PartitionPool<I, O>
+where
+    I: Eq + PartialEq + Clone + Debug + Hash + Send,
+{
+    inline: Option<PartitionProcessor<I, O>>,
+    workers: Option<Workers<I, O>>,
+    merged: MergedResultsFn<O>,
+    next_tick: u64,
+}
+
+impl<I, O> PartitionPool<I, O>
+where
+    I: Eq + PartialEq + Clone + Debug + Hash + Send + 'static,
+    O: Send + 'static,
+{
+    pub fn inline(processor: PartitionProcessor<I, O>, merged: MergedResultsFn<O>) -> Self {
+        PartitionPool {
+            inline: Some(processor),
+            workers: None,
+            merged,
+            next_tick: 0,
+        }
+    }
+
+    /// Spawn one worker per processor. Worker queues always block when full: dropping
+    /// a key's content would leave its firing waiting forever in the merger.
+    pub fn threaded(
+        processors: Vec<PartitionProcessor<I, O>>,
+        buffer_config: BufferConfig,
+        merged: MergedResultsFn<O>,
+    ) -> Self {
+        let (merge, merge_receiver) = unbounded::<MergeMessage<O>>();
+        let config = BufferConfig {
+            capacity: buffer_config.capacity,
+            overflow: OverflowPolicy::Block,
+        };
+        let mut senders = Vec::with_capacity(processors.len());
+        let mut monitors = Vec::with_capacity(processors.len());
+        let mut handles = Vec::with_capacity(processors.len() + 1);
+        for (worker, mut processor) in processors.into_iter().enumerate() {
+            let (sender, receiver, monitor) = bounded_buffer::<PartitionTask<I>>(config);
+            let results = merge.clone();
+            handles.push(thread::spawn(move || {
+                for task in receiver.iter() {
+                    let output = processor(task.content);
+                    if results.send(MergeMessage::Results { tick: task.tick, results: output }).is_err() {
+                        break;
+                    }
+                }
+                debug!("Partition worker {} shut down", worker);
+            }));
+            senders.push(sender);
+            monitors.push(monitor);
+        }
+
+        let emit = Arc::clone(&merged);
+        handles.push(thread::spawn(move || {
+            // tick -> (ts, contents still outstanding, results so far)
+            let mut pending: BTreeMap<u64, (usize, usize, Vec<O>)> = BTreeMap::new();
+            for message in merge_receiver.iter() {
+                match message {
+                    MergeMessage::Firing { tick, ts, contents } => {
+                        pending.insert(tick, (ts, contents, Vec::new()));
+                    }
+                    MergeMessage::Results { tick, results } => {
+                        if let Some((_, outstanding, merged)) = pending.get_mut(&tick) {
+                            *outstanding -= 1;
+                            merged.extend(results);
+                        }
+                    }
+                }
+                while let Some(entry) = pending.first_entry() {
+                    if entry.get().1 > 0 {
+                        break;
+                    }
+                    let (ts, _, results) = entry.remove();
+                    (emit)(results, ts);
+                }
+            }
+            debug!("Partition merger shut down");
+        }));
+
+        PartitionPool {
+            inline: None,
+            workers: Some(Workers {
+                senders,
+                monitors,
+                merge,
+                handles,
+            }),
+            merged,
+            next_tick: 0,
+        }
+    }
+
+    /// Evaluate the contents fired at one moment and emit their merged results,
+    /// stamped with the latest timestamp among them.
+    pub fn dispatch(&mut self, fired: Vec<(u64, ContentContainer<I>)>) {
+        if fired.is_empty() {
+            return;
+        }
+        let ts = fired
+            .iter()
+            .map(|(_, content)| content.get_last_timestamp_changed())
+            .max()
+            .unwrap_or(0);
+        if let Some(processor) = &mut self.inline {
+            let mut results = Vec::new();
+            for (_, content) in fired {
+                results.extend(processor(content));
+            }
+            (self.merged)(results, ts);
+            return;
+        }
+        let Some(workers) = &self.workers else {
+            return;
+        };
+        let tick = self.next_tick;
+        self.next_tick += 1;
+        if workers
+            .merge
+            .send(MergeMessage::Firing { tick, ts, contents: fired.len() })
+            .is_err()
+        {
+            return;
+        }
+        for (key, content) in fired {
+            let worker = (key % workers.senders.len() as u64) as usize;
+            if workers.senders[worker].send(PartitionTask { tick, content }).is_err() {
+                debug!("Partition worker {} is gone", worker);
+            }
+        }
+    }
+
+    /// Queue metrics per worker; empty when evaluating inline.
+    pub fn queue_metrics(&self) -> Vec<QueueMetrics> {
+        self.workers
+            .as_ref()
+            .map(|w| w.monitors.iter().map(|m| m.metrics()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Wait until everything dispatched so far has been evaluated and emitted, then
+    /// shut the workers down.
+    pub fn stop(&mut self) {
+        if let Some(workers) = self.workers.take() {
+            drop(workers.senders);
+            drop(workers.merge);
+            for handle in workers.handles {
+                let _ = handle.join();
+            }
+        }
+    }
+}
+
+impl<I, O> Drop for PartitionPool<I, O>
+where
+    I: Eq + PartialEq + Clone + Debug + Hash + Send,
+{
+    fn drop(&mut self) {
+        // Closing the queues lets the threads finish; they are not joined here.
+        self.workers.take();
+    }
+}
+
+/// A `PARTITION BY` window: keyed windows feeding a partition pool.
+pub struct PartitionedWindow<I, O>
+where
+    I: Eq + PartialEq + Clone + Debug + Hash + Send,
+{
+    windows: KeyedWindows<I>,
+    pool: PartitionPool<I, O>,
+}
+
+impl<I, O> PartitionedWindow<I, O>
+where
+    I: Eq + PartialEq + Clone + Debug + Hash + Send + 'static,
+    O: Send + 'static,
+{
+    pub fn new(windows: KeyedWindows<I>, pool: PartitionPool<I, O>) -> Self {
+        PartitionedWindow { windows, pool }
+    }
+
+    pub fn add(&mut self, item: I, ts: usize) {
+        let fired = self.windows.add(item, ts);
+        self.pool.dispatch(fired);
+    }
+
+    /// Fire all open windows and wait for their results.
+    pub fn flush_and_stop(&mut self) {
+        let fired = self.windows.flush();
+        self.pool.dispatch(fired);
+        self.pool.stop();
+    }
+
+    pub fn keys(&self) -> usize {
+        self.windows.len()
+    }
+
+    pub fn windows(&self) -> &KeyedWindows<I> {
+        &self.windows
+    }
+
+    pub fn windows_mut(&mut self) -> &mut KeyedWindows<I> {
+        &mut self.windows
+    }
+
+    pub fn queue_metrics(&self) -> Vec<QueueMetrics> {
+        self.pool.queue_metrics()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsp::s2r::{ReportStrategy, Tick};
+
+    fn triple(subject: u32, object: u32) -> Triple {
+        Triple {
+            subject,
+            predicate: 1,
+            object,
+        }
+    }
+
+    fn spec(width: usize, slide: usize) -> WindowSpec {
+        WindowSpec {
+            width,
+            slide,
+            report_strategies: vec![ReportStrategy::OnWindowClose],
+            tick: Tick::TimeDriven,
+        }
+    }
+
+    #[test]
+    fn test_keys_fire_together_when_time_advances() {
+        let mut windows = KeyedWindows::new(spec(2, 2), "w".to_string());
+        assert!(windows.add(triple(1, 10), 0).is_empty());
+        assert!(windows.add(triple(2, 20), 1).is_empty());
+        assert_eq!(windows.len(), 2);
+
+        // Only key 1 receives an item at ts=2, yet key 2's window closes too.
+        let fired = windows.add(triple(1, 11), 2);
+        assert_eq!(fired.len(), 2);
+        let mut sizes: Vec<(u64, usize)> = fired.iter().map(|(k, c)| (*k, c.len())).collect();
+        sizes.sort();
+        let mut expected = vec![(partition_key(&triple(1, 0)), 1), (partition_key(&triple(2, 0)), 1)];
+        expected.sort();
+        assert_eq!(sizes, expected);
+    }
+
+    #[test]
+    fn test_idle_keys_are_dropped() {
+        let mut windows = KeyedWindows::new(spec(2, 2), "w".to_string());
+        windows.add(triple(1, 10), 0);
+        windows.add(triple(2, 20), 0);
+        windows.add(triple(1, 11), 2);
+        windows.add(triple(1, 12), 4);
+        assert_eq!(windows.len(), 1);
+    }
+
+    #[test]
+    fn test_threaded_pool_merges_in_dispatch_order() {
+        let emitted: Arc<Mutex<Vec<(usize, Vec<usize>)>>> = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&emitted);
+        let processors: Vec<PartitionProcessor<Triple, usize>> = (0..3)
+            .map(|_| {
+                Box::new(|content: ContentContainer<Triple>| {
+                    content.iter().map(|t| t.object as usize).collect()
+                }) as PartitionProcessor<Triple, usize>
+            })
+            .collect();
+        let mut pool = PartitionPool::threaded(
+            processors,
+            BufferConfig::default(),
+            Arc::new(move |mut results: Vec<usize>, ts| {
+                results.sort();
+                sink.lock().unwrap().push((ts, results));
+            }),
+        );
+        let mut windows = KeyedWindows::new(spec(1, 1), "w".to_string());
+        for ts in 0..4usize {
+            for subject in 0..5u32 {
+                let fired = windows.add(triple(subject, ts as u32 * 10 + subject), ts);
+                pool.dispatch(fired);
+            }
+        }
+        pool.stop();
+
+        let emitted = emitted.lock().unwrap();
+        assert_eq!(emitted.len(), 3);
+        for (i, (ts, results)) in emitted.iter().enumerate() {
+            assert_eq!(*ts, i);
+            assert_eq!(*results, (0..5).map(|s| i * 10 + s).collect::<Vec<_>>());
+        }
+    }
+}
@@ -8,7 +8,7 @@
  * you can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use crate::streamertail_optimizer::PhysicalOperator;
+use crate::streamertail_optimizer::{ExecutionProfile, PhysicalOperator};
 
 use std::any::Any;
 
@@ -26,5 +26,24 @@ pub trait R2ROperator<I, R, O>: Send + AsAnyMut {
     fn materialize(&mut self) -> Vec<I>;
     fn execute_query(&mut self, op: &PhysicalOperator) -> Vec<O>;
 
+    /// Like `execute_query`, also returning the output size of each operator of `op`.
+    /// Operators that cannot profile their execution return `None`.
+    fn execute_query_profiled(&mut self, op: &PhysicalOperator) -> (Vec<O>, Option<ExecutionProfile>) {
+        (self.execute_query(op), None)
+    }
+
     fn parse_data(&mut self, data: &str) -> Vec<I>;
+
+    /// Serialize an item back to one N-Triples statement, the inverse of `parse_data`.
+    /// Needed for checkpointing; operators that return `None` cannot be checkpointed.
+    fn to_ntriples(&self, _data: &I) -> Option<String> {
+        None
+    }
+
+    /// Independent copy of this operator (same data, rules and dictionary), used to give
+    /// each partition worker its own store. Operators that return `None` evaluate all
+    /// partitions on the engine's shared store, one at a time.
+    fn fork(&self) -> Option<Box<dyn R2ROperator<I, R, O>>> {
+        None
+    }
 }
\ No newline at end of file
@@ -34,6 +34,15 @@ impl <O> Relation2StreamOperator <O> where O: Clone + Hash + Eq {
         }
     }
 
+    /// Result set remembered for ISTREAM/DSTREAM differencing.
+    pub fn last_result(&self) -> &HashSet<O> {
+        &self.last_result
+    }
+
+    pub fn set_last_result(&mut self, last_result: HashSet<O>) {
+        self.last_result = last_result;
+    }
+
     pub fn eval(&mut self, new_response: Vec<O>, _ts: usize) -> Vec<O> {
         match self.stream_operator {
             StreamOperator::RSTREAM => new_response,
@@ -8,6 +8,7 @@
  * you can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+use crate::rsp::buffer::{bounded_buffer, BoundedSender, BufferConfig, QueueMonitor};
 #[cfg(not(test))]
 use log::{debug, warn}; // Use log crate when building application
 use std::collections::hash_set::{IntoIter, Iter};
@@ -157,6 +158,8 @@ where
     tick: Tick,
     app_time: usize,
     consumer: Option<Sender<ContentContainer<I>>>,
+    // bounded multithreaded consumer
+    buffer: Option<BoundedSender<ContentContainer<I>>>,
     // Make callbacks Send so they can be safely transferred to worker threads
     call_back: Option<Box<dyn FnMut(ContentContainer<I>) -> () + Send + 'static>>,
     uri: String
@@ -174,6 +177,7 @@ where
             app_time: 0,
             report,
             consumer: None,
+            buffer: None,
             active_windows: HashMap::new(),
             tick,
             call_back: None,
@@ -181,6 +185,14 @@ where
         }
     }
     pub fn add_to_window(&mut self, event_item: I, ts: usize) {
+        self.process(Some(event_item), ts);
+    }
+    /// Move the window to `ts` without adding an element, firing and evicting
+    /// windows exactly as an element arriving at `ts` would.
+    pub fn advance(&mut self, ts: usize) {
+        self.process(None, ts);
+    }
+    fn process(&mut self, event_item: Option<I>, ts: usize) {
         let event_time = ts;
         self.scope(&event_time);
 
@@ -194,11 +206,13 @@ where
                     window.open, window.close, event_item, ts
                 );
                 if window.open <= event_time && event_time < window.close {
-                    debug!(
-                        "Adding element [{:?}] to Window [{:?},{:?})",
-                        event_item, window.open, window.close
-                    );
-                    content.add(event_item.clone(), ts);
+                    if let Some(event_item) = &event_item {
+                        debug!(
+                            "Adding element [{:?}] to Window [{:?},{:?})",
+                            event_item, window.open, window.close
+                        );
+                        content.add(event_item.clone(), ts);
+                    }
                     Some((window, content))
                 } else {
                     debug!(
@@ -228,6 +242,11 @@ where
                                 warn!("Failed to send window content to consumer: {:?}", e);
                             }
                         }
+                        if let Some(buffer) = &self.buffer {
+                            if let Err(e) = buffer.send(max_window.1.clone()) {
+                                warn!("Failed to send window content to consumer: {:?}", e);
+                            }
+                        }
                         // single threaded consumer using callback
                         if let Some(call_back) = &mut self.call_back {
                             (call_back)(max_window.1.clone());
@@ -278,6 +297,16 @@ where
         self.consumer.replace(send);
         recv
     }
+    /// Like `register`, but through a bounded buffer that applies `config.overflow`
+    /// when the consumer falls behind.
+    pub fn register_bounded(
+        &mut self,
+        config: BufferConfig,
+    ) -> (crossbeam::channel::Receiver<ContentContainer<I>>, QueueMonitor<ContentContainer<I>>) {
+        let (sender, receiver, monitor) = bounded_buffer(config);
+        self.buffer.replace(sender);
+        (receiver, monitor)
+    }
     pub fn register_callback(
         &mut self,
         function: Box<dyn FnMut(ContentContainer<I>) -> () + Send + 'static>,
@@ -292,10 +321,40 @@ where
             if let Some(sender) = &self.consumer {
                 let _ = sender.send(content.clone());
             }
+            if let Some(buffer) = &self.buffer {
+                let _ = buffer.send(content.clone());
+            }
         }
     }
     pub fn stop(&mut self) {
         self.consumer.take();
+        self.buffer.take();
+    }
+
+    /// Application time of the last firing.
+    pub fn app_time(&self) -> usize {
+        self.app_time
+    }
+
+    /// Currently open windows as `(open, close, content)`.
+    pub fn active_windows(&self) -> Vec<(usize, usize, &ContentContainer<I>)> {
+        self.active_windows
+            .iter()
+            .map(|(window, content)| (window.open, window.close, content))
+            .collect()
+    }
+
+    /// Replace the open windows and application time, e.g. from a checkpoint.
+    /// Each window is given as `(open, close, items, last_timestamp_changed)`.
+    pub fn restore(&mut self, app_time: usize, windows: Vec<(usize, usize, Vec<I>, usize)>) {
+        self.app_time = app_time;
+        self.active_windows.clear();
+        for (open, close, items, last_ts) in windows {
+            let mut content = ContentContainer::new_with_origin(&self.uri);
+            content.elements.extend(items);
+            content.last_timestamp_changed = last_ts;
+            self.active_windows.insert(Window { open, close }, content);
+        }
     }
 }
 
@@ -351,6 +410,97 @@ pub struct WindowTriple {
     pub s: String,
     pub p: String,
     pub o: String,
+    /// How the object was written in its source; subjects and predicates are never literals.
+    pub object_kind: ObjectKind,
+}
+
+/// Kind of RDF term held in `WindowTriple::o`.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Default)]
+pub enum ObjectKind {
+    /// Not recorded, e.g. for values decoded from a dictionary. Serialized as an IRI
+    /// when it starts with a URI scheme, and as a plain literal otherwise.
+    #[default]
+    Unknown,
+    Iri,
+    /// `o` is the lexical form, followed by `@language` when the literal has a language tag.
+    Literal {
+        datatype: Option<String>,
+        language: Option<String>,
+    },
+}
+
+impl ObjectKind {
+    /// Kind of a term as written in N-Triples, e.g. `<iri>` or `"lex"^^<datatype>`.
+    /// Blank nodes, quoted triples and bare values are `Unknown`.
+    pub fn of_ntriples_term(term: &str) -> ObjectKind {
+        let term = term.trim();
+        if term.starts_with('<') && !term.starts_with("<<") {
+            return ObjectKind::Iri;
+        }
+        if !term.starts_with('"') {
+            return ObjectKind::Unknown;
+        }
+        let annotation = term.rfind('"').map_or("", |end| &term[end + 1..]);
+        ObjectKind::Literal {
+            datatype: annotation
+                .strip_prefix("^^<")
+                .and_then(|dt| dt.strip_suffix('>'))
+                .map(str::to_string),
+            language: annotation.strip_prefix('@').map(str::to_string),
+        }
+    }
+}
+
+impl WindowTriple {
+    /// Serialize the (decoded) terms back into a single N-Triples statement so the
+    /// triple can be re-encoded through an R2R operator's dictionary.
+    pub fn to_ntriples(&self) -> String {
+        format!(
+            "{} <{}> {} .",
+            subject_term(&self.s),
+            self.p,
+            object_term(&self.o, &self.object_kind)
+        )
+    }
+}
+
+fn subject_term(term: &str) -> String {
+    if term.starts_with("<<") || term.starts_with("_:") {
+        term.to_string()
+    } else {
+        format!("<{}>", term)
+    }
+}
+
+fn object_term(term: &str, kind: &ObjectKind) -> String {
+    match kind {
+        ObjectKind::Iri => format!("<{}>", term),
+        ObjectKind::Literal { datatype, language } => {
+            let lexical = language
+                .as_ref()
+                .and_then(|lang| term.strip_suffix(&format!("@{}", lang)))
+                .unwrap_or(term);
+            let literal = format!("\"{}\"", lexical.replace('"', "\\\""));
+            match (language, datatype) {
+                (Some(lang), _) => format!("{}@{}", literal, lang),
+                (None, Some(datatype)) => format!("{}^^<{}>", literal, datatype),
+                (None, None) => literal,
+            }
+        }
+        ObjectKind::Unknown if term.starts_with("<<") || term.starts_with("_:") => term.to_string(),
+        ObjectKind::Unknown if has_uri_scheme(term) => format!("<{}>", term),
+        ObjectKind::Unknown => format!("\"{}\"", term.replace('"', "\\\"")),
+    }
+}
+
+/// Whether `term` looks like an absolute IRI: `scheme:rest` without spaces or delimiters.
+fn has_uri_scheme(term: &str) -> bool {
+    let Some((scheme, _)) = term.split_once(':') else {
+        return false;
+    };
+    scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        && !term.contains(|c: char| c.is_whitespace() || c == '"' || c == '<' || c == '>')
 }
 #[cfg(test)]
 mod tests {
@@ -371,6 +521,7 @@ mod tests {
             report,
             tick: Tick::TimeDriven,
             consumer: None,
+            buffer: None,
             call_back: None,
             uri: "test_window".to_string()
         };
@@ -383,6 +534,7 @@ mod tests {
                 s: format!("s{}", i),
                 p: "p".to_string(),
                 o: "o".to_string(),
+                object_kind: ObjectKind::Unknown,
             };
             window.add_to_window(triple, i);
         }
@@ -404,6 +556,7 @@ mod tests {
             report,
             tick: Tick::TimeDriven,
             consumer: None,
+            buffer: None,
             call_back: None,
             uri: "test_window".to_string()
         };
@@ -420,6 +573,7 @@ mod tests {
                 s: format!("s{}", i),
                 p: "p".to_string(),
                 o: "o".to_string(),
+                object_kind: ObjectKind::Unknown,
             };
             window.add_to_window(triple, i);
         }
@@ -8,13 +8,16 @@
 */
 
 use crate::rsp::r2r::{AsAnyMut, R2ROperator};
+use crate::rsp::s2r::{ObjectKind, WindowTriple};
 use crate::rsp_engine::QueryExecutionMode;
 use crate::sparql_database::SparqlDatabase;
-use crate::streamertail_optimizer::{ExecutionEngine, PhysicalOperator};
-use datalog::parser_n3_logic::parse_n3_rule;
+use crate::streamertail_optimizer::{ExecutionEngine, ExecutionProfile, PhysicalOperator};
+use datalog::parser_n3_logic::parse_n3_document;
+use datalog::reasoning::entailment::EntailmentRegime;
 use datalog::reasoning::Reasoner;
-use shared::rule::Rule;
+use shared::rule::{check_program_safety, Rule};
 use shared::triple::Triple;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 #[cfg(not(test))]
@@ -26,7 +29,16 @@ pub struct SimpleR2R {
     pub item: SparqlDatabase,
     pub execution_mode: QueryExecutionMode,
     pub rules: Vec<Rule>,
-    derived_triples: Vec<Triple>,
+    /// Materialisation kept up to date incrementally across window firings
+    reasoner: Option<Reasoner>,
+    /// Triples added (`true`) or removed (`false`) since the last materialisation
+    pending: HashMap<Triple, bool>,
+    /// Standard rules applied to the window contents besides `rules`
+    entailment: Option<EntailmentRegime>,
+    /// Schema triples (e.g. from the static data) the entailment regime reasons with
+    ontology: Vec<Triple>,
+    /// Entailments of the ontology alone, kept out of the window contents
+    ontology_closure: Option<HashSet<Triple>>,
 }
 
 impl SimpleR2R {
@@ -35,7 +47,11 @@ impl SimpleR2R {
             item: SparqlDatabase::new(),
             execution_mode: QueryExecutionMode::Standard,
             rules: Vec::new(),
-            derived_triples: Vec::new(),
+            reasoner: None,
+            pending: HashMap::new(),
+            entailment: None,
+            ontology: Vec::new(),
+            ontology_closure: None,
         }
     }
 
@@ -44,12 +60,92 @@ impl SimpleR2R {
             item: SparqlDatabase::new(),
             execution_mode,
             rules: Vec::new(),
-            derived_triples: Vec::new(),
+            reasoner: None,
+            pending: HashMap::new(),
+            entailment: None,
+            ontology: Vec::new(),
+            ontology_closure: None,
         }
     }
 
-    pub fn add_reasoning_rules(&mut self, rules: Vec<Rule>) {
-        self.rules.extend(rules);
+    /// Adds rules to the ones applied on every materialisation, returning
+    /// `Err` if the resulting program is unsafe or not weakly acyclic
+    pub fn add_reasoning_rules(&mut self, rules: Vec<Rule>) -> Result<(), String> {
+        let mut program = self.rules.clone();
+        program.extend(rules);
+        check_program_safety(&program, false)?;
+        self.rules = program;
+        Ok(())
+    }
+
+    /// Applies the rules of `regime` to the window contents on every
+    /// materialisation, together with the ontology triples
+    pub fn set_entailment_regime(&mut self, regime: EntailmentRegime) {
+        self.reset_materialization();
+        self.entailment = Some(regime);
+        self.ontology_closure = None;
+    }
+
+    pub fn entailment_regime(&self) -> Option<EntailmentRegime> {
+        self.entailment
+    }
+
+    /// Adds schema triples (class and property axioms) the entailment regime
+    /// reasons with. They are not part of the window contents.
+    pub fn add_ontology_triples(&mut self, triples: impl IntoIterator<Item = Triple>) {
+        self.ontology.extend(triples);
+        self.ontology_closure = None;
+    }
+
+    /// Materialises the window contents under the entailment regime.
+    /// Equality classes may split when triples expire, so the closure is
+    /// recomputed on every firing rather than maintained incrementally.
+    fn materialize_entailment(&mut self, regime: EntailmentRegime) -> Vec<Triple> {
+        self.reset_materialization();
+        if self.ontology_closure.is_none() {
+            let mut reasoner = Reasoner::new();
+            reasoner.dictionary = Arc::clone(&self.item.dictionary);
+            reasoner.rules = self.rules.clone();
+            for t in &self.ontology {
+                reasoner.index_manager.insert(t);
+            }
+            reasoner.infer_new_facts_with_entailment(regime);
+            self.ontology_closure = Some(reasoner.index_manager.query(None, None, None).into_iter().collect());
+        }
+        let ontology_closure = self.ontology_closure.as_ref().unwrap();
+
+        let mut reasoner = Reasoner::new();
+        reasoner.dictionary = Arc::clone(&self.item.dictionary);
+        reasoner.rules = self.rules.clone();
+        for t in self.item.triples.iter().chain(&self.ontology) {
+            reasoner.index_manager.insert(t);
+        }
+        let derived: Vec<Triple> = reasoner
+            .infer_new_facts_with_entailment(regime)
+            .into_iter()
+            .filter(|t| !ontology_closure.contains(t))
+            .collect();
+        debug!("materialize: {} facts entailed under {:?}", derived.len(), regime);
+        for t in &derived {
+            self.item.add_triple(t.clone());
+        }
+        // Only the triples added to the window contents count as derived
+        reasoner.derived_facts = derived.iter().cloned().collect();
+        self.reasoner = Some(reasoner);
+        derived
+    }
+
+    /// Removes the derived triples of the current materialisation, so the
+    /// next one starts from the explicit triples
+    fn reset_materialization(&mut self) {
+        if let Some(reasoner) = self.reasoner.take() {
+            for t in &reasoner.derived_facts {
+                if self.pending.get(t) != Some(&true) {
+                    self.item.delete_triple(t);
+                }
+            }
+        }
+        self.pending.clear();
     }
 }
 
@@ -76,55 +172,91 @@ impl R2ROperator<Triple, Vec<PhysicalOperator>, Vec<(String, String)>> for Simpl
         }
         let mut temp_reasoner = Reasoner::new();
         temp_reasoner.dictionary = Arc::clone(&self.item.dictionary);
-        let mut remaining = data;
-        loop {
-            match parse_n3_rule(remaining, &mut temp_reasoner) {
-                Ok((rest, (_, rule))) => {
-                    self.rules.push(rule);
-                    remaining = rest;
-                    if remaining.trim().is_empty() {
-                        break;
-                    }
-                }
-                Err(_) => return Err("Failed to parse N3 rules"),
-            }
+        let document = parse_n3_document(data, &mut temp_reasoner).map_err(|e| {
+            error!("{}", e);
+            "Failed to parse N3 rules"
+        })?;
+        self.add_reasoning_rules(document.rules).map_err(|e| {
+            error!("{}", e);
+            "Failed to add N3 rules"
+        })?;
+        // The facts of the document hold in every window
+        for fact in document.facts {
+            self.add(fact);
         }
         Ok(())
     }
 
     fn add(&mut self, data: Triple) {
+        self.pending.insert(data.clone(), true);
         self.item.add_triple(data);
     }
 
     fn remove(&mut self, data: &Triple) {
+        self.pending.insert(data.clone(), false);
         self.item.delete_triple(data);
     }
 
+    /// Brings the derived triples up to date with the triples added and
+    /// removed since the last call, using Delete-and-Rederive so a window
+    /// slide only touches the consequences of the triples that changed.
     fn materialize(&mut self) -> Vec<Triple> {
-        // Evict derived triples from the previous cycle
-        for t in &self.derived_triples {
-            self.item.delete_triple(t);
+        if let Some(regime) = self.entailment {
+            return self.materialize_entailment(regime);
+        }
+        let rules_changed = self
+            .reasoner
+            .as_ref()
+            .is_some_and(|reasoner| reasoner.rules.len() != self.rules.len());
+        if rules_changed || self.rules.is_empty() {
+            self.reset_materialization();
         }
-        self.derived_triples.clear();
-
         if self.rules.is_empty() {
             return Vec::new();
         }
 
-        let mut reasoner = Reasoner::new();
-        reasoner.dictionary = Arc::clone(&self.item.dictionary);
-        for triple in self.item.triples.iter() {
-            reasoner.index_manager.insert(triple);
-        }
-        reasoner.rules = self.rules.clone();
+        let pending = std::mem::take(&mut self.pending);
+        let (inserted, deleted): (Vec<Triple>, Vec<Triple>) = match &self.reasoner {
+            Some(_) => {
+                let inserted = pending.iter().filter(|(_, &added)| added).map(|(t, _)| t.clone()).collect();
+                let deleted = pending.iter().filter(|(_, &added)| !added).map(|(t, _)| t.clone()).collect();
+                (inserted, deleted)
+            }
+            None => (self.item.triples.iter().cloned().collect(), Vec::new()),
+        };
+        let reasoner = self.reasoner.get_or_insert_with(|| {
+            let mut reasoner = Reasoner::new();
+            reasoner.dictionary = Arc::clone(&self.item.dictionary);
+            reasoner.rules = self.rules.clone();
+            reasoner
+        });
 
-        let derived = reasoner.infer_new_facts_semi_naive();
-        debug!("materialize: {} facts derived by reasoning", derived.len());
-        for t in &derived {
-            self.item.add_triple(t.clone());
-            self.derived_triples.push(t.clone());
+        let delta = match reasoner.update_materialization(&inserted, &deleted) {
+            Ok(delta) => delta,
+            Err(e) => {
+                error!("materialize: {}", e);
+                self.reasoner = None;
+                return Vec::new();
+            }
+        };
+        debug!(
+            "materialize: {} facts derived, {} retracted by reasoning",
+            delta.added.len(),
+            delta.removed.len()
+        );
+        for t in &delta.removed {
+            self.item.delete_triple(t);
         }
-        derived
+        for t in delta.added {
+            self.item.add_triple(t);
+        }
+        // Removed triples that are still derived stay visible to queries
+        for t in &deleted {
+            if reasoner.derived_facts.contains(t) {
+                self.item.add_triple(t.clone());
+            }
+        }
+        reasoner.derived_facts.iter().cloned().collect()
     }
 
     fn execute_query(&mut self, op: &PhysicalOperator) -> Vec<Vec<(String, String)>> {
@@ -132,17 +264,56 @@ impl R2ROperator<Triple, Vec<PhysicalOperator>, Vec<(String, String)>> for Simpl
 
         // Execute the physical operator using the Volcano execution engine.
         // The engine returns Vec<HashMap<String,String>> (bindings per row).
-        ExecutionEngine::execute(op, &mut self.item)
-            .into_iter()
-            .map(|hashmap| {
-                let mut v: Vec<(String, String)> = hashmap.into_iter().collect();
-                v.sort_unstable_by(|a, b| a.0.cmp(&b.0));
-                v
-            })
-            .collect()
+        to_binding_rows(ExecutionEngine::execute(op, &mut self.item))
+    }
+
+    fn execute_query_profiled(
+        &mut self,
+        op: &PhysicalOperator,
+    ) -> (Vec<Vec<(String, String)>>, Option<ExecutionProfile>) {
+        let (results, profile) = ExecutionEngine::execute_profiled(op, &mut self.item);
+        (to_binding_rows(results), Some(profile))
     }
 
     fn parse_data(&mut self, data: &str) -> Vec<Triple> {
         self.item.parse_and_encode_ntriples(data)
     }
+
+    fn to_ntriples(&self, data: &Triple) -> Option<String> {
+        let dict = self.item.dictionary.read().unwrap();
+        let qt_store = self.item.quoted_triple_store.read().unwrap();
+        let triple = WindowTriple {
+            s: dict.decode_term(data.subject, &qt_store)?,
+            p: dict.decode_term(data.predicate, &qt_store)?,
+            o: dict.decode_term(data.object, &qt_store)?,
+            object_kind: ObjectKind::Unknown,
+        };
+        Some(triple.to_ntriples())
+    }
+
+    fn fork(&self) -> Option<Box<dyn R2ROperator<Triple, Vec<PhysicalOperator>, Vec<(String, String)>>>> {
+        // Cloning the database shares the dictionary, so IDs stay consistent across forks.
+        Some(Box::new(SimpleR2R {
+            item: self.item.clone(),
+            execution_mode: self.execution_mode,
+            rules: self.rules.clone(),
+            reasoner: self.reasoner.clone(),
+            pending: self.pending.clone(),
+            entailment: self.entailment,
+            ontology: self.ontology.clone(),
+            ontology_closure: self.ontology_closure.clone(),
+        }))
+    }
+}
+
+/// Bindings per row, sorted by variable name.
+fn to_binding_rows(results: Vec<HashMap<String, String>>) -> Vec<Vec<(String, String)>> {
+    results
+        .into_iter()
+        .map(|hashmap| {
+            let mut v: Vec<(String, String)> = hashmap.into_iter().collect();
+            v.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+            v
+        })
+        .collect()
 }
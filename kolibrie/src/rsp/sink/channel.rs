@@ -0,0 +1,84 @@
+/*
+ * Copyright © 2025 Volodymyr Kadzhaia
+ * Copyright © 2025 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use super::{OutputEvent, StreamSink};
+use crate::rsp::source::{StreamEvent, StreamSource};
+use crossbeam::channel::{unbounded, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+const RECV_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Create a connected sink/source pair: everything emitted into the sink is
+/// produced, as stream triples, by the source.
+pub fn output_channel(stream_iri: &str) -> (ChannelSink, OutputStreamSource) {
+    let (sender, receiver) = unbounded();
+    (
+        ChannelSink {
+            stream_iri: stream_iri.to_string(),
+            sender,
+        },
+        OutputStreamSource {
+            stream_iri: stream_iri.to_string(),
+            receiver,
+        },
+    )
+}
+
+/// Sink half of `output_channel`.
+pub struct ChannelSink {
+    stream_iri: String,
+    sender: Sender<StreamEvent>,
+}
+
+impl StreamSink for ChannelSink {
+    fn describe(&self) -> String {
+        format!("channel:{}", self.stream_iri)
+    }
+
+    fn emit(&mut self, event: &OutputEvent) -> Result<(), String> {
+        for stream_event in event.to_stream_events() {
+            self.sender
+                .send(stream_event)
+                .map_err(|_| "Output stream subscriber is gone".to_string())?;
+        }
+        Ok(())
+    }
+}
+
+/// Source half of `output_channel`; finishes once the producing engine stops.
+pub struct OutputStreamSource {
+    stream_iri: String,
+    receiver: Receiver<StreamEvent>,
+}
+
+impl OutputStreamSource {
+    /// Output stream IRI this source replays.
+    pub fn stream_iri(&self) -> &str {
+        &self.stream_iri
+    }
+}
+
+impl StreamSource for OutputStreamSource {
+    fn describe(&self) -> String {
+        format!("output:{}", self.stream_iri)
+    }
+
+    fn poll_events(&mut self) -> Result<Option<Vec<StreamEvent>>, String> {
+        match self.receiver.recv_timeout(RECV_TIMEOUT) {
+            Ok(first) => {
+                let mut events = vec![first];
+                events.extend(self.receiver.try_iter());
+                Ok(Some(events))
+            }
+            Err(RecvTimeoutError::Timeout) => Ok(Some(Vec::new())),
+            Err(RecvTimeoutError::Disconnected) => Ok(None),
+        }
+    }
+}
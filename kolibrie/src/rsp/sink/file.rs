@@ -0,0 +1,69 @@
+/*
+ * Copyright © 2025 Volodymyr Kadzhaia
+ * Copyright © 2025 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use super::{OutputEvent, StreamSink};
+use crate::rsp::source::EventFormat;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Writes output events as N-Triples or JSON lines to a file.
+pub struct FileSink {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    format: EventFormat,
+}
+
+impl FileSink {
+    /// Create (or truncate) `path`.
+    pub fn create<P: AsRef<Path>>(path: P, format: EventFormat) -> Result<FileSink, String> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::create(&path)
+            .map_err(|e| format!("Unable to create sink file {}: {}", path.display(), e))?;
+        Ok(FileSink {
+            path,
+            writer: BufWriter::new(file),
+            format,
+        })
+    }
+
+    /// Append to `path`, creating it if needed.
+    pub fn append<P: AsRef<Path>>(path: P, format: EventFormat) -> Result<FileSink, String> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("Unable to open sink file {}: {}", path.display(), e))?;
+        Ok(FileSink {
+            path,
+            writer: BufWriter::new(file),
+            format,
+        })
+    }
+}
+
+impl StreamSink for FileSink {
+    fn describe(&self) -> String {
+        format!("file:{}", self.path.display())
+    }
+
+    fn emit(&mut self, event: &OutputEvent) -> Result<(), String> {
+        self.writer
+            .write_all(event.encode(self.format).as_bytes())
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        self.writer
+            .flush()
+            .map_err(|e| format!("Failed to flush {}: {}", self.path.display(), e))
+    }
+}
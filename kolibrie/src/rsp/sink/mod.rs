@@ -0,0 +1,264 @@
+/*
+ * Copyright © 2025 Volodymyr Kadzhaia
+ * Copyright © 2025 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Output sinks for the results of a registered RSP-QL query.
+//!
+//! Every binding the engine emits for `REGISTER ... <output> AS` becomes an
+//! `OutputEvent` on the output stream. Sinks are attached to that output IRI with
+//! `RSPEngine::attach_sink` (or `RSPBuilder::add_sink`); each one runs on its own
+//! thread so slow network sinks never stall window evaluation. An `OutputStreamSource`
+//! obtained from `RSPEngine::subscribe_output` turns the output stream back into a
+//! `StreamSource`, so a query of another engine can consume it as an input stream.
+//!
+//! Queries of the same engine are chained with `RSPEngine::chain`: the output stream of
+//! one reaches the windows of the others that read it with `FROM NAMED WINDOW ... ON
+//! <output>`, through the same channel. Chains that would feed a query's output back
+//! into its own windows are rejected.
+
+pub mod channel;
+pub mod file;
+pub mod mqtt;
+pub mod webhook;
+
+pub use channel::{output_channel, ChannelSink, OutputStreamSource};
+pub use file::FileSink;
+pub use mqtt::MqttSink;
+pub use webhook::WebhookSink;
+
+use crate::rsp::s2r::{ObjectKind, WindowTriple};
+use crate::rsp::source::{EventFormat, StreamEvent};
+use crossbeam::channel::{unbounded, Sender};
+#[cfg(not(test))]
+use log::{debug, warn};
+use std::thread::{self, JoinHandle};
+#[cfg(test)]
+use std::{println as debug, println as warn};
+
+/// One result binding emitted on a query's output stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputEvent {
+    /// Output stream IRI from the `REGISTER` clause.
+    pub stream_iri: String,
+    pub ts: usize,
+    /// Position of this result on the output stream, starting at 0.
+    pub index: usize,
+    pub bindings: Vec<(String, String)>,
+}
+
+impl OutputEvent {
+    /// Represent the binding as stream triples.
+    ///
+    /// A binding of exactly `?s ?p ?o` is the triple itself. Any other binding becomes
+    /// a blank node (one per result) with one `<output#var>` property per variable.
+    pub fn to_stream_events(&self) -> Vec<StreamEvent> {
+        let value = |name: &str| {
+            self.bindings
+                .iter()
+                .find(|(var, _)| var == name)
+                .map(|(_, v)| v.clone())
+        };
+        if self.bindings.len() == 3 {
+            if let (Some(s), Some(p), Some(o)) = (value("s"), value("p"), value("o")) {
+                return vec![StreamEvent {
+                    triple: WindowTriple {
+                        s,
+                        p,
+                        o,
+                        object_kind: ObjectKind::Unknown,
+                    },
+                    ts: self.ts,
+                }];
+            }
+        }
+        let subject = format!("_:out{}", self.index);
+        self.bindings
+            .iter()
+            .map(|(var, val)| StreamEvent {
+                triple: WindowTriple {
+                    s: subject.clone(),
+                    p: binding_predicate(&self.stream_iri, var),
+                    o: val.clone(),
+                    object_kind: ObjectKind::Unknown,
+                },
+                ts: self.ts,
+            })
+            .collect()
+    }
+
+    /// Serialize the event as newline-terminated lines in `format`; the output can be
+    /// read back by any source using the same format.
+    pub fn encode(&self, format: EventFormat) -> String {
+        let mut out = String::new();
+        for event in self.to_stream_events() {
+            out.push_str(&encode_line(format, &event));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+fn binding_predicate(stream_iri: &str, var: &str) -> String {
+    let base = if stream_iri.contains(':') {
+        stream_iri.to_string()
+    } else {
+        // Unresolved prefixed name; keep the predicate an absolute IRI.
+        format!("urn:kolibrie:{}", stream_iri)
+    };
+    if base.ends_with('#') || base.ends_with('/') {
+        format!("{}{}", base, var)
+    } else {
+        format!("{}#{}", base, var)
+    }
+}
+
+/// Inverse of `LineDecoder::decode_line` for a single event (without the newline).
+pub fn encode_line(format: EventFormat, event: &StreamEvent) -> String {
+    match format {
+        EventFormat::NTriples => event.triple.to_ntriples(),
+        EventFormat::JsonLines => serde_json::json!({
+            "s": event.triple.s,
+            "p": event.triple.p,
+            "o": event.triple.o,
+            "ts": event.ts,
+        })
+        .to_string(),
+    }
+}
+
+/// Engine results that can be emitted on an output stream as variable bindings.
+pub trait OutputBindings {
+    /// The `(variable, value)` pairs of this result.
+    fn bindings(&self) -> Vec<(String, String)>;
+}
+
+impl OutputBindings for Vec<(String, String)> {
+    fn bindings(&self) -> Vec<(String, String)> {
+        self.clone()
+    }
+}
+
+/// Destination for the results of a query's output stream.
+pub trait StreamSink: Send {
+    /// Short human-readable description used in logs.
+    fn describe(&self) -> String;
+
+    /// Accept one output event. Sinks may buffer until `flush`.
+    fn emit(&mut self, event: &OutputEvent) -> Result<(), String>;
+
+    /// Deliver anything buffered. Called after every burst of events and on shutdown.
+    fn flush(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Running sink thread attached to an engine.
+pub struct SinkHandle {
+    name: String,
+    sender: Option<Sender<OutputEvent>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SinkHandle {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Queue an event for the sink; ignored once the sink has been stopped.
+    pub fn send(&self, event: OutputEvent) {
+        if let Some(sender) = &self.sender {
+            if sender.send(event).is_err() {
+                warn!("Stream sink {} is no longer running", self.name);
+            }
+        }
+    }
+
+    /// Deliver the queued events, flush the sink and wait for its thread to finish.
+    pub fn stop(&mut self) {
+        self.sender = None;
+        if let Some(handle) = self.thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Run `sink` on a dedicated thread, fed through the returned handle.
+pub fn spawn_sink(mut sink: Box<dyn StreamSink>) -> SinkHandle {
+    let name = sink.describe();
+    let thread_name = name.clone();
+    let (sender, receiver) = unbounded::<OutputEvent>();
+    let thread = thread::spawn(move || {
+        while let Ok(event) = receiver.recv() {
+            if let Err(e) = sink.emit(&event) {
+                warn!("Stream sink {}: {}", thread_name, e);
+            }
+            // Hand everything already queued to the sink before flushing once.
+            for event in receiver.try_iter() {
+                if let Err(e) = sink.emit(&event) {
+                    warn!("Stream sink {}: {}", thread_name, e);
+                }
+            }
+            if let Err(e) = sink.flush() {
+                warn!("Stream sink {}: {}", thread_name, e);
+            }
+        }
+        debug!("Stream sink {} finished", thread_name);
+    });
+    SinkHandle {
+        name,
+        sender: Some(sender),
+        thread: Some(thread),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsp::source::{LineDecoder, Timestamping};
+
+    #[test]
+    fn test_output_event_to_triples() {
+        let triple_event = OutputEvent {
+            stream_iri: "http://out/stream".to_string(),
+            ts: 4,
+            index: 0,
+            bindings: vec![
+                ("o".to_string(), "http://ex/T".to_string()),
+                ("p".to_string(), "http://ex/p".to_string()),
+                ("s".to_string(), "http://ex/s".to_string()),
+            ],
+        };
+        let events = triple_event.to_stream_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].triple.s, "http://ex/s");
+        assert_eq!(events[0].ts, 4);
+
+        let row_event = OutputEvent {
+            stream_iri: "http://out/stream".to_string(),
+            ts: 5,
+            index: 7,
+            bindings: vec![
+                ("sensor".to_string(), "http://ex/s1".to_string()),
+                ("temp".to_string(), "21.5".to_string()),
+            ],
+        };
+        let events = row_event.to_stream_events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].triple.s, "_:out7");
+        assert_eq!(events[1].triple.p, "http://out/stream#temp");
+
+        // Both formats read back through the source decoder.
+        for format in [EventFormat::NTriples, EventFormat::JsonLines] {
+            let mut decoder = LineDecoder::new(format, Timestamping::Sequence);
+            let decoded = decoder.decode_payload(&row_event.encode(format));
+            assert_eq!(decoded.len(), 2);
+            assert_eq!(decoded[1].triple.o, "21.5");
+        }
+    }
+}
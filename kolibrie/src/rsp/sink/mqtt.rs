@@ -0,0 +1,115 @@
+/*
+ * Copyright © 2025 Volodymyr Kadzhaia
+ * Copyright © 2025 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use super::{OutputEvent, StreamSink};
+use crate::rsp::source::EventFormat;
+#[cfg(not(test))]
+use log::warn;
+use rumqttc::{Client, Event, MqttOptions, Outgoing, QoS};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+#[cfg(test)]
+use std::println as warn;
+
+const RECONNECT_DELAY: Duration = Duration::from_millis(500);
+
+/// Publishes output events to an MQTT topic, one message per flushed burst.
+pub struct MqttSink {
+    description: String,
+    client: Client,
+    topic: String,
+    qos: QoS,
+    format: EventFormat,
+    pending: String,
+    closing: Arc<AtomicBool>,
+    event_loop: Option<JoinHandle<()>>,
+}
+
+impl MqttSink {
+    pub fn new(options: MqttOptions, topic: &str, format: EventFormat) -> MqttSink {
+        let (host, port) = options.broker_address();
+        let description = format!("mqtt://{}:{}/{}", host, port, topic);
+        let (client, mut connection) = Client::new(options, 64);
+        let closing = Arc::new(AtomicBool::new(false));
+        let closing_flag = Arc::clone(&closing);
+        let loop_description = description.clone();
+        // The sync client only makes progress while its connection is iterated.
+        let event_loop = thread::spawn(move || {
+            for notification in connection.iter() {
+                match notification {
+                    Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+                    Ok(_) => {}
+                    Err(_) if closing_flag.load(Ordering::SeqCst) => break,
+                    Err(e) => {
+                        warn!("{}: connection error: {}", loop_description, e);
+                        thread::sleep(RECONNECT_DELAY);
+                    }
+                }
+            }
+        });
+        MqttSink {
+            description,
+            client,
+            topic: topic.to_string(),
+            qos: QoS::AtLeastOnce,
+            format,
+            pending: String::new(),
+            closing,
+            event_loop: Some(event_loop),
+        }
+    }
+
+    /// Convenience constructor with a keep-alive of 5 seconds and a clean session.
+    pub fn connect(client_id: &str, host: &str, port: u16, topic: &str, format: EventFormat) -> MqttSink {
+        let mut options = MqttOptions::new(client_id, host, port);
+        options.set_keep_alive(Duration::from_secs(5));
+        options.set_clean_session(true);
+        Self::new(options, topic, format)
+    }
+
+    pub fn qos(mut self, qos: QoS) -> Self {
+        self.qos = qos;
+        self
+    }
+}
+
+impl StreamSink for MqttSink {
+    fn describe(&self) -> String {
+        self.description.clone()
+    }
+
+    fn emit(&mut self, event: &OutputEvent) -> Result<(), String> {
+        self.pending.push_str(&event.encode(self.format));
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let payload = std::mem::take(&mut self.pending);
+        self.client
+            .publish(self.topic.as_str(), self.qos, false, payload.into_bytes())
+            .map_err(|e| format!("Failed to publish to {}: {}", self.topic, e))
+    }
+}
+
+impl Drop for MqttSink {
+    fn drop(&mut self) {
+        self.closing.store(true, Ordering::SeqCst);
+        // Queued behind any outstanding publishes, so those still go out first.
+        let _ = self.client.disconnect();
+        if let Some(handle) = self.event_loop.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,138 @@
+/*
+ * Copyright © 2025 Volodymyr Kadzhaia
+ * Copyright © 2025 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use super::{OutputEvent, StreamSink};
+use crate::rsp::source::EventFormat;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// POSTs output events to an HTTP endpoint, one request per flushed burst.
+/// Only plain `http://` URLs are supported.
+pub struct WebhookSink {
+    url: String,
+    authority: String,
+    path: String,
+    format: EventFormat,
+    headers: Vec<(String, String)>,
+    pending: String,
+}
+
+impl WebhookSink {
+    pub fn new(url: &str, format: EventFormat) -> Result<WebhookSink, String> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("Unsupported webhook URL (expected http://): {}", url))?;
+        let (authority, path) = match rest.find('/') {
+            Some(idx) => (&rest[..idx], &rest[idx..]),
+            None => (rest, "/"),
+        };
+        if authority.is_empty() {
+            return Err(format!("Webhook URL has no host: {}", url));
+        }
+        Ok(WebhookSink {
+            url: url.to_string(),
+            authority: authority.to_string(),
+            path: path.to_string(),
+            format,
+            headers: Vec::new(),
+            pending: String::new(),
+        })
+    }
+
+    /// Add an extra request header, e.g. for authentication.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    fn content_type(&self) -> &'static str {
+        match self.format {
+            EventFormat::NTriples => "application/n-triples",
+            EventFormat::JsonLines => "application/x-ndjson",
+        }
+    }
+
+    fn post(&self, body: &str) -> Result<(), String> {
+        let addr = if self.authority.contains(':') {
+            self.authority.clone()
+        } else {
+            format!("{}:80", self.authority)
+        };
+        let mut stream =
+            TcpStream::connect(&addr).map_err(|e| format!("Webhook connect to {} failed: {}", self.url, e))?;
+        stream
+            .set_read_timeout(Some(IO_TIMEOUT))
+            .and_then(|_| stream.set_write_timeout(Some(IO_TIMEOUT)))
+            .map_err(|e| format!("Unable to configure webhook socket: {}", e))?;
+
+        let mut request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.path,
+            self.authority,
+            self.content_type(),
+            body.len()
+        );
+        for (name, value) in &self.headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str("\r\n");
+        request.push_str(body);
+        stream
+            .write_all(request.as_bytes())
+            .map_err(|e| format!("Webhook POST to {} failed: {}", self.url, e))?;
+
+        // Only the status line matters; read until the headers are complete.
+        let mut response = Vec::new();
+        let mut buf = [0u8; 1024];
+        loop {
+            let n = stream
+                .read(&mut buf)
+                .map_err(|e| format!("Webhook response from {} failed: {}", self.url, e))?;
+            if n == 0 {
+                break;
+            }
+            response.extend_from_slice(&buf[..n]);
+            if response.windows(4).any(|w| w == b"\r\n\r\n") {
+                break;
+            }
+        }
+        let mut headers = [httparse::EMPTY_HEADER; 32];
+        let mut parsed = httparse::Response::new(&mut headers);
+        parsed
+            .parse(&response)
+            .map_err(|e| format!("Invalid webhook response from {}: {}", self.url, e))?;
+        match parsed.code {
+            Some(code) if (200..300).contains(&code) => Ok(()),
+            code => Err(format!("Webhook {} answered with status {:?}", self.url, code)),
+        }
+    }
+}
+
+impl StreamSink for WebhookSink {
+    fn describe(&self) -> String {
+        self.url.clone()
+    }
+
+    fn emit(&mut self, event: &OutputEvent) -> Result<(), String> {
+        self.pending.push_str(&event.encode(self.format));
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let body = std::mem::take(&mut self.pending);
+        self.post(&body)
+    }
+}
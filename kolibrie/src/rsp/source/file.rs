@@ -0,0 +1,219 @@
+/*
+ * Copyright © 2025 Volodymyr Kadzhaia
+ * Copyright © 2025 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use super::{EventFormat, LineDecoder, SourcePosition, StreamEvent, StreamSource, Timestamping};
+#[cfg(not(test))]
+use log::warn;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+#[cfg(test)]
+use std::println as warn;
+
+/// Maximum number of lines handed to the engine per poll.
+const BATCH_SIZE: usize = 256;
+
+/// Reads N-Triples or JSON-lines events from a file, optionally following it
+/// like `tail -f` and replaying the recorded timestamps at a chosen speed.
+pub struct FileSource {
+    path: PathBuf,
+    reader: BufReader<File>,
+    decoder: LineDecoder,
+    follow: bool,
+    poll_interval: Duration,
+    replay_speed: Option<f64>,
+    time_unit: Duration,
+    last_ts: Option<usize>,
+    partial: Vec<u8>,
+    /// Event held back so the batch before it is delivered without waiting for its replay delay,
+    /// with the position just after it.
+    held: Option<(StreamEvent, SourcePosition)>,
+    /// Bytes of complete lines taken from the reader.
+    consumed: u64,
+    /// Position after the last line handed out (or skipped).
+    delivered: SourcePosition,
+}
+
+impl FileSource {
+    pub fn open<P: AsRef<Path>>(path: P, format: EventFormat) -> Result<FileSource, String> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path)
+            .map_err(|e| format!("Unable to open stream file {}: {}", path.display(), e))?;
+        Ok(FileSource {
+            path,
+            reader: BufReader::new(file),
+            decoder: LineDecoder::new(format, Timestamping::Sequence),
+            follow: false,
+            poll_interval: Duration::from_millis(100),
+            replay_speed: None,
+            time_unit: Duration::from_secs(1),
+            last_ts: None,
+            partial: Vec::new(),
+            held: None,
+            consumed: 0,
+            delivered: SourcePosition {
+                offset: 0,
+                next_sequence: 1,
+            },
+        })
+    }
+
+    /// Keep waiting for appended lines at end of file instead of finishing.
+    pub fn follow(mut self, follow: bool) -> Self {
+        self.follow = follow;
+        self
+    }
+
+    /// How long to wait before re-checking the file for new data when following.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Pace events by their timestamps: `1.0` replays in real time, `2.0` twice as fast.
+    /// Without a replay speed the file is read as fast as possible.
+    pub fn replay_speed(mut self, speed: f64) -> Self {
+        self.replay_speed = if speed > 0.0 { Some(speed) } else { None };
+        self
+    }
+
+    /// Wall-clock duration of one timestamp unit when replaying (defaults to one second).
+    pub fn time_unit(mut self, unit: Duration) -> Self {
+        self.time_unit = unit;
+        self
+    }
+
+    pub fn timestamping(mut self, timestamping: Timestamping) -> Self {
+        self.decoder.set_timestamping(timestamping);
+        self
+    }
+
+    fn needs_pause(&self, ts: usize) -> bool {
+        self.replay_speed.is_some() && self.last_ts.is_some_and(|last| ts > last)
+    }
+
+    fn pace(&mut self, ts: usize) {
+        if let (Some(speed), Some(last)) = (self.replay_speed, self.last_ts) {
+            if ts > last {
+                let delay = self.time_unit.mul_f64((ts - last) as f64 / speed);
+                thread::sleep(delay);
+            }
+        }
+        self.last_ts = Some(ts);
+    }
+
+    fn current_position(&self) -> SourcePosition {
+        SourcePosition {
+            offset: self.consumed,
+            next_sequence: self.decoder.next_sequence(),
+        }
+    }
+
+    /// Append `event` to the batch, or hold it back if replaying requires a pause first.
+    /// Returns `false` when the batch should be delivered now.
+    fn push_paced(&mut self, event: StreamEvent, events: &mut Vec<StreamEvent>) -> bool {
+        let position = self.current_position();
+        if !events.is_empty() && self.needs_pause(event.ts) {
+            self.held = Some((event, position));
+            return false;
+        }
+        self.pace(event.ts);
+        events.push(event);
+        self.delivered = position;
+        true
+    }
+}
+
+impl StreamSource for FileSource {
+    fn describe(&self) -> String {
+        format!("file:{}", self.path.display())
+    }
+
+    fn poll_events(&mut self) -> Result<Option<Vec<StreamEvent>>, String> {
+        let mut events = Vec::new();
+        if let Some((event, position)) = self.held.take() {
+            self.pace(event.ts);
+            events.push(event);
+            self.delivered = position;
+        }
+        loop {
+            if events.len() >= BATCH_SIZE {
+                return Ok(Some(events));
+            }
+            let read = match self.reader.read_until(b'\n', &mut self.partial) {
+                Ok(read) => read,
+                Err(e) => return Err(format!("Failed to read {}: {}", self.path.display(), e)),
+            };
+
+            if read == 0 || !self.partial.ends_with(b"\n") {
+                // End of file; a trailing line without newline may still be growing.
+                if self.follow {
+                    if events.is_empty() {
+                        thread::sleep(self.poll_interval);
+                    }
+                    return Ok(Some(events));
+                }
+                let rest = std::mem::take(&mut self.partial);
+                self.consumed += rest.len() as u64;
+                match decode_raw(&mut self.decoder, &self.path, &rest) {
+                    Some(event) => {
+                        self.push_paced(event, &mut events);
+                    }
+                    None => self.delivered = self.current_position(),
+                }
+                if events.is_empty() && self.held.is_none() {
+                    return Ok(None);
+                }
+                return Ok(Some(events));
+            }
+
+            let line = std::mem::take(&mut self.partial);
+            self.consumed += line.len() as u64;
+            match decode_raw(&mut self.decoder, &self.path, &line) {
+                Some(event) => {
+                    if !self.push_paced(event, &mut events) {
+                        return Ok(Some(events));
+                    }
+                }
+                None => self.delivered = self.current_position(),
+            }
+        }
+    }
+
+    fn position(&self) -> Option<SourcePosition> {
+        Some(self.delivered)
+    }
+
+    fn resume_from(&mut self, position: &SourcePosition) -> Result<(), String> {
+        self.reader
+            .seek(SeekFrom::Start(position.offset))
+            .map_err(|e| format!("Unable to seek in {}: {}", self.path.display(), e))?;
+        self.decoder.set_next_sequence(position.next_sequence);
+        self.partial.clear();
+        self.held = None;
+        self.last_ts = None;
+        self.consumed = position.offset;
+        self.delivered = *position;
+        Ok(())
+    }
+}
+
+fn decode_raw(decoder: &mut LineDecoder, path: &Path, raw: &[u8]) -> Option<StreamEvent> {
+    let line = String::from_utf8_lossy(raw);
+    match decoder.decode_line(&line) {
+        Ok(event) => event,
+        Err(e) => {
+            warn!("{}: {}", path.display(), e);
+            None
+        }
+    }
+}
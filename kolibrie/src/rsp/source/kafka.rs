@@ -0,0 +1,524 @@
+/*
+ * Copyright © 2025 Volodymyr Kadzhaia
+ * Copyright © 2025 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Minimal consumer for the Kafka wire protocol (Kafka, Redpanda, ...).
+//!
+//! It talks to a single broker that must lead the requested partition, tracks the
+//! offset in-process (no consumer groups or commits) and understands uncompressed
+//! v2 record batches, which is what producers emit by default.
+
+use super::{EventFormat, LineDecoder, SourcePosition, StreamEvent, StreamSource, Timestamping};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+const API_FETCH: i16 = 1;
+const API_LIST_OFFSETS: i16 = 2;
+const FETCH_VERSION: i16 = 4;
+const LIST_OFFSETS_VERSION: i16 = 1;
+
+/// Where consumption starts when the source is created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartOffset {
+    Earliest,
+    Latest,
+    At(i64),
+}
+
+pub struct KafkaSource {
+    broker: String,
+    topic: String,
+    partition: i32,
+    client_id: String,
+    stream: TcpStream,
+    correlation_id: i32,
+    offset: i64,
+    max_wait: Duration,
+    max_bytes: i32,
+    decoder: LineDecoder,
+}
+
+impl KafkaSource {
+    pub fn connect(
+        broker: &str,
+        topic: &str,
+        partition: i32,
+        format: EventFormat,
+        start: StartOffset,
+    ) -> Result<KafkaSource, String> {
+        let stream = TcpStream::connect(broker)
+            .map_err(|e| format!("Kafka connect to {} failed: {}", broker, e))?;
+        let mut source = KafkaSource {
+            broker: broker.to_string(),
+            topic: topic.to_string(),
+            partition,
+            client_id: "kolibrie".to_string(),
+            stream,
+            correlation_id: 0,
+            offset: 0,
+            max_wait: Duration::from_millis(100),
+            max_bytes: 1 << 20,
+            decoder: LineDecoder::new(format, Timestamping::WallClock),
+        };
+        source.offset = match start {
+            StartOffset::At(offset) => offset,
+            StartOffset::Earliest => source.list_offset(-2)?,
+            StartOffset::Latest => source.list_offset(-1)?,
+        };
+        Ok(source)
+    }
+
+    /// How long the broker may hold a fetch open waiting for data.
+    pub fn max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = max_wait;
+        self
+    }
+
+    /// Upper bound on the record bytes returned per fetch.
+    pub fn max_bytes(mut self, max_bytes: i32) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn timestamping(mut self, timestamping: Timestamping) -> Self {
+        self.decoder.set_timestamping(timestamping);
+        self
+    }
+
+    /// Offset of the next record to be consumed.
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+
+    fn request(&mut self, api_key: i16, api_version: i16, body: &[u8]) -> Result<Vec<u8>, String> {
+        self.correlation_id = self.correlation_id.wrapping_add(1);
+        let mut header = Vec::new();
+        put_i16(&mut header, api_key);
+        put_i16(&mut header, api_version);
+        put_i32(&mut header, self.correlation_id);
+        put_str(&mut header, &self.client_id);
+
+        let mut frame = Vec::with_capacity(4 + header.len() + body.len());
+        put_i32(&mut frame, (header.len() + body.len()) as i32);
+        frame.extend_from_slice(&header);
+        frame.extend_from_slice(body);
+        self.stream
+            .write_all(&frame)
+            .map_err(|e| format!("Kafka request to {} failed: {}", self.broker, e))?;
+
+        let mut size = [0u8; 4];
+        self.stream
+            .read_exact(&mut size)
+            .map_err(|e| format!("Kafka response from {} failed: {}", self.broker, e))?;
+        let mut response = vec![0u8; i32::from_be_bytes(size).max(0) as usize];
+        self.stream
+            .read_exact(&mut response)
+            .map_err(|e| format!("Kafka response from {} failed: {}", self.broker, e))?;
+
+        let mut reader = ByteReader::new(&response);
+        let correlation_id = reader.i32()?;
+        if correlation_id != self.correlation_id {
+            return Err(format!(
+                "Kafka correlation id mismatch (expected {}, got {})",
+                self.correlation_id, correlation_id
+            ));
+        }
+        Ok(response[4..].to_vec())
+    }
+
+    /// Resolve a ListOffsets timestamp (`-2` earliest, `-1` latest) to an offset.
+    fn list_offset(&mut self, timestamp: i64) -> Result<i64, String> {
+        let mut body = Vec::new();
+        put_i32(&mut body, -1); // replica id
+        put_i32(&mut body, 1);
+        put_str(&mut body, &self.topic);
+        put_i32(&mut body, 1);
+        put_i32(&mut body, self.partition);
+        put_i64(&mut body, timestamp);
+
+        let response = self.request(API_LIST_OFFSETS, LIST_OFFSETS_VERSION, &body)?;
+        let mut reader = ByteReader::new(&response);
+        for _ in 0..reader.i32()? {
+            let topic = reader.string()?;
+            for _ in 0..reader.i32()? {
+                let partition = reader.i32()?;
+                let error_code = reader.i16()?;
+                let _timestamp = reader.i64()?;
+                let offset = reader.i64()?;
+                if topic == self.topic && partition == self.partition {
+                    if error_code != 0 {
+                        return Err(format!("Kafka ListOffsets error code {}", error_code));
+                    }
+                    return Ok(offset);
+                }
+            }
+        }
+        Err(format!("Kafka ListOffsets returned no offset for {}-{}", self.topic, self.partition))
+    }
+
+    /// Fetch the next records as `(offset, value)` pairs.
+    fn fetch(&mut self) -> Result<Vec<(i64, Vec<u8>)>, String> {
+        let mut body = Vec::new();
+        put_i32(&mut body, -1); // replica id
+        put_i32(&mut body, self.max_wait.as_millis() as i32);
+        put_i32(&mut body, 1); // min bytes
+        put_i32(&mut body, self.max_bytes);
+        body.push(0); // isolation level: read uncommitted
+        put_i32(&mut body, 1);
+        put_str(&mut body, &self.topic);
+        put_i32(&mut body, 1);
+        put_i32(&mut body, self.partition);
+        put_i64(&mut body, self.offset);
+        put_i32(&mut body, self.max_bytes);
+
+        let response = self.request(API_FETCH, FETCH_VERSION, &body)?;
+        let mut reader = ByteReader::new(&response);
+        let _throttle_ms = reader.i32()?;
+        let mut records = Vec::new();
+        for _ in 0..reader.i32()? {
+            let topic = reader.string()?;
+            for _ in 0..reader.i32()? {
+                let partition = reader.i32()?;
+                let error_code = reader.i16()?;
+                let _high_watermark = reader.i64()?;
+                let _last_stable_offset = reader.i64()?;
+                let aborted = reader.i32()?;
+                for _ in 0..aborted.max(0) {
+                    reader.skip(16)?;
+                }
+                let record_set = reader.nullable_bytes()?;
+                if topic != self.topic || partition != self.partition {
+                    continue;
+                }
+                if error_code != 0 {
+                    return Err(format!(
+                        "Kafka fetch error code {} for {}-{} at offset {}",
+                        error_code, self.topic, self.partition, self.offset
+                    ));
+                }
+                if let Some(data) = record_set {
+                    records.extend(decode_record_batches(data, self.offset)?);
+                }
+            }
+        }
+        Ok(records)
+    }
+}
+
+impl StreamSource for KafkaSource {
+    fn describe(&self) -> String {
+        format!("kafka://{}/{}/{}", self.broker, self.topic, self.partition)
+    }
+
+    fn poll_events(&mut self) -> Result<Option<Vec<StreamEvent>>, String> {
+        let records = self.fetch()?;
+        let mut events = Vec::new();
+        for (offset, value) in records {
+            events.extend(self.decoder.decode_payload(&String::from_utf8_lossy(&value)));
+            self.offset = offset + 1;
+        }
+        Ok(Some(events))
+    }
+
+    fn position(&self) -> Option<SourcePosition> {
+        Some(SourcePosition {
+            offset: self.offset.max(0) as u64,
+            next_sequence: self.decoder.next_sequence(),
+        })
+    }
+
+    fn resume_from(&mut self, position: &SourcePosition) -> Result<(), String> {
+        self.offset = position.offset as i64;
+        self.decoder.set_next_sequence(position.next_sequence);
+        Ok(())
+    }
+}
+
+/// Decode a (possibly truncated) sequence of v2 record batches, skipping records
+/// before `min_offset` and control batches.
+fn decode_record_batches(data: &[u8], min_offset: i64) -> Result<Vec<(i64, Vec<u8>)>, String> {
+    let mut reader = ByteReader::new(data);
+    let mut records = Vec::new();
+    while reader.remaining() >= 12 {
+        let base_offset = reader.i64()?;
+        let batch_length = reader.i32()?.max(0) as usize;
+        if reader.remaining() < batch_length {
+            // The broker cut the last batch at max_bytes; it is fetched again next time.
+            break;
+        }
+        let mut batch = ByteReader::new(reader.take(batch_length)?);
+        let _leader_epoch = batch.i32()?;
+        let magic = batch.i8()?;
+        if magic != 2 {
+            return Err(format!("Unsupported Kafka record batch version {}", magic));
+        }
+        let _crc = batch.i32()?;
+        let attributes = batch.i16()?;
+        if attributes & 0x07 != 0 {
+            return Err("Compressed Kafka record batches are not supported".to_string());
+        }
+        let is_control = attributes & 0x20 != 0;
+        batch.skip(4 + 8 + 8 + 8 + 2 + 4)?; // last offset delta .. base sequence
+        let count = batch.i32()?;
+        for _ in 0..count.max(0) {
+            let length = batch.varint()?.max(0) as usize;
+            let mut record = ByteReader::new(batch.take(length)?);
+            let _attributes = record.i8()?;
+            let _timestamp_delta = record.varint()?;
+            let offset = base_offset + record.varint()?;
+            let key_len = record.varint()?;
+            if key_len > 0 {
+                record.skip(key_len as usize)?;
+            }
+            let value_len = record.varint()?;
+            let value = if value_len > 0 {
+                record.take(value_len as usize)?.to_vec()
+            } else {
+                Vec::new()
+            };
+            if !is_control && offset >= min_offset {
+                records.push((offset, value));
+            }
+        }
+    }
+    Ok(records)
+}
+
+fn put_i16(buf: &mut Vec<u8>, v: i16) {
+    buf.extend_from_slice(&v.to_be_bytes());
+}
+
+fn put_i32(buf: &mut Vec<u8>, v: i32) {
+    buf.extend_from_slice(&v.to_be_bytes());
+}
+
+fn put_i64(buf: &mut Vec<u8>, v: i64) {
+    buf.extend_from_slice(&v.to_be_bytes());
+}
+
+fn put_str(buf: &mut Vec<u8>, s: &str) {
+    put_i16(buf, s.len() as i16);
+    buf.extend_from_slice(s.as_bytes());
+}
+
+struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        ByteReader { data, pos: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.remaining() < n {
+            return Err("Truncated Kafka response".to_string());
+        }
+        let slice = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn skip(&mut self, n: usize) -> Result<(), String> {
+        self.take(n).map(|_| ())
+    }
+
+    fn i8(&mut self) -> Result<i8, String> {
+        Ok(self.take(1)?[0] as i8)
+    }
+
+    fn i16(&mut self) -> Result<i16, String> {
+        let b = self.take(2)?;
+        Ok(i16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        let b = self.take(4)?;
+        Ok(i32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn i64(&mut self) -> Result<i64, String> {
+        let mut b = [0u8; 8];
+        b.copy_from_slice(self.take(8)?);
+        Ok(i64::from_be_bytes(b))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.i16()?;
+        if len < 0 {
+            return Ok(String::new());
+        }
+        Ok(String::from_utf8_lossy(self.take(len as usize)?).into_owned())
+    }
+
+    fn nullable_bytes(&mut self) -> Result<Option<&'a [u8]>, String> {
+        let len = self.i32()?;
+        if len < 0 {
+            return Ok(None);
+        }
+        self.take(len as usize).map(Some)
+    }
+
+    /// Zig-zag encoded variable-length integer (covers both varint and varlong).
+    fn varint(&mut self) -> Result<i64, String> {
+        let mut value: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.take(1)?[0];
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+            if shift > 63 {
+                return Err("Malformed Kafka varint".to_string());
+            }
+        }
+        Ok(((value >> 1) as i64) ^ -((value & 1) as i64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn put_varint(buf: &mut Vec<u8>, v: i64) {
+        let mut z = ((v << 1) ^ (v >> 63)) as u64;
+        loop {
+            if z < 0x80 {
+                buf.push(z as u8);
+                break;
+            }
+            buf.push((z as u8 & 0x7F) | 0x80);
+            z >>= 7;
+        }
+    }
+
+    fn record_batch(base_offset: i64, values: &[&str]) -> Vec<u8> {
+        let mut records = Vec::new();
+        for (i, value) in values.iter().enumerate() {
+            let mut record = vec![0u8]; // attributes
+            put_varint(&mut record, 0); // timestamp delta
+            put_varint(&mut record, i as i64); // offset delta
+            put_varint(&mut record, -1); // null key
+            put_varint(&mut record, value.len() as i64);
+            record.extend_from_slice(value.as_bytes());
+            put_varint(&mut record, 0); // headers
+            put_varint(&mut records, record.len() as i64);
+            records.extend(record);
+        }
+        let mut batch = Vec::new();
+        put_i32(&mut batch, 0); // leader epoch
+        batch.push(2); // magic
+        put_i32(&mut batch, 0); // crc (not validated)
+        put_i16(&mut batch, 0); // attributes
+        put_i32(&mut batch, values.len() as i32 - 1);
+        put_i64(&mut batch, 0);
+        put_i64(&mut batch, 0);
+        put_i64(&mut batch, -1);
+        put_i16(&mut batch, -1);
+        put_i32(&mut batch, -1);
+        put_i32(&mut batch, values.len() as i32);
+        batch.extend(records);
+
+        let mut out = Vec::new();
+        put_i64(&mut out, base_offset);
+        put_i32(&mut out, batch.len() as i32);
+        out.extend(batch);
+        out
+    }
+
+    /// In-process broker answering ListOffsets with 0 and serving `batch` to the first fetch.
+    fn fake_broker(topic: &'static str, batch: Vec<u8>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let (mut conn, _) = listener.accept().unwrap();
+            let mut served = false;
+            loop {
+                let mut size = [0u8; 4];
+                if conn.read_exact(&mut size).is_err() {
+                    break;
+                }
+                let mut req = vec![0u8; i32::from_be_bytes(size) as usize];
+                conn.read_exact(&mut req).unwrap();
+                let api_key = i16::from_be_bytes([req[0], req[1]]);
+                let correlation = &req[4..8];
+
+                let mut body = correlation.to_vec();
+                if api_key == API_LIST_OFFSETS {
+                    put_i32(&mut body, 1);
+                    put_str(&mut body, topic);
+                    put_i32(&mut body, 1);
+                    put_i32(&mut body, 0);
+                    put_i16(&mut body, 0);
+                    put_i64(&mut body, -1);
+                    put_i64(&mut body, 0);
+                } else {
+                    let records = if served { Vec::new() } else { batch.clone() };
+                    served = true;
+                    put_i32(&mut body, 0); // throttle
+                    put_i32(&mut body, 1);
+                    put_str(&mut body, topic);
+                    put_i32(&mut body, 1);
+                    put_i32(&mut body, 0);
+                    put_i16(&mut body, 0);
+                    put_i64(&mut body, 2);
+                    put_i64(&mut body, 2);
+                    put_i32(&mut body, 0); // no aborted transactions
+                    put_i32(&mut body, records.len() as i32);
+                    body.extend(records);
+                }
+                let mut frame = Vec::new();
+                put_i32(&mut frame, body.len() as i32);
+                frame.extend(body);
+                conn.write_all(&frame).unwrap();
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_decode_record_batches_skips_consumed_offsets() {
+        let mut data = record_batch(10, &["a", "b", "c"]);
+        // A truncated trailing batch is ignored.
+        data.extend_from_slice(&record_batch(13, &["d"])[..20]);
+        let records = decode_record_batches(&data, 11).unwrap();
+        assert_eq!(records, vec![(11, b"b".to_vec()), (12, b"c".to_vec())]);
+    }
+
+    #[test]
+    fn test_kafka_source_consumes_from_broker() {
+        let batch = record_batch(
+            0,
+            &[
+                "<http://ex/s1> <http://ex/p> <http://ex/o1> .",
+                "<http://ex/s2> <http://ex/p> <http://ex/o2> .",
+            ],
+        );
+        let addr = fake_broker("events", batch);
+        let mut source = KafkaSource::connect(&addr, "events", 0, EventFormat::NTriples, StartOffset::Earliest)
+            .unwrap()
+            .timestamping(Timestamping::Sequence);
+
+        let events = source.poll_events().unwrap().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].triple.o, "http://ex/o2");
+        assert_eq!(source.offset(), 2);
+        assert!(source.poll_events().unwrap().unwrap().is_empty());
+    }
+}
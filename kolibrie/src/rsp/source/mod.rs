@@ -0,0 +1,360 @@
+/*
+ * Copyright © 2025 Volodymyr Kadzhaia
+ * Copyright © 2025 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Stream source connectors feeding an `RSPEngine`.
+//!
+//! A `StreamSource` produces batches of `StreamEvent`s (a decoded `WindowTriple`
+//! plus its application timestamp). Sources are attached to a stream IRI declared
+//! in the RSP-QL query with `RSPEngine::attach_source` (or `RSPBuilder::add_source`);
+//! each one runs on its own thread and the engine pushes the events into the
+//! matching windows when `poll_sources`/`run_sources` is called.
+
+pub mod file;
+pub mod kafka;
+pub mod mqtt;
+pub mod socket;
+
+pub use file::FileSource;
+pub use kafka::KafkaSource;
+pub use mqtt::MqttSource;
+pub use socket::{TcpLineSource, WebSocketSource};
+
+use crate::rsp::s2r::{ObjectKind, WindowTriple};
+use crate::sparql_database::SparqlDatabase;
+use crossbeam::channel::Sender;
+#[cfg(not(test))]
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{SystemTime, UNIX_EPOCH};
+#[cfg(test)]
+use std::{println as debug, println as warn};
+
+/// A single decoded stream element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamEvent {
+    pub triple: WindowTriple,
+    pub ts: usize,
+}
+
+/// Serialization of the lines/payloads a source reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventFormat {
+    /// One N-Triples statement per line.
+    NTriples,
+    /// One JSON object per line: `{"s": ..., "p": ..., "o": ..., "ts": 12}`.
+    /// `ts` is optional; terms may be bare IRIs/values or N-Triples terms.
+    JsonLines,
+}
+
+/// How events that do not carry their own timestamp are stamped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timestamping {
+    /// Consecutive numbers starting at 1, one per event.
+    Sequence,
+    /// Seconds since the UNIX epoch at the moment the event is decoded.
+    WallClock,
+}
+
+/// Resumable read position of a source, recorded in engine checkpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourcePosition {
+    /// Source-specific offset (byte position in a file, record offset in a Kafka partition).
+    pub offset: u64,
+    /// Next sequence timestamp, so `Timestamping::Sequence` continues where it stopped.
+    pub next_sequence: usize,
+}
+
+/// Connector producing events for a single stream.
+pub trait StreamSource: Send {
+    /// Short human-readable description used in logs.
+    /// Also identifies the source's position in engine checkpoints.
+    fn describe(&self) -> String;
+
+    /// Wait (boundedly) for the next batch of events.
+    ///
+    /// Returns `Ok(Some(vec![]))` when nothing arrived in time but the source is
+    /// still alive, and `Ok(None)` once it is exhausted and will produce no more events.
+    fn poll_events(&mut self) -> Result<Option<Vec<StreamEvent>>, String>;
+
+    /// Position just after the last batch returned by `poll_events`, or `None` if the
+    /// source cannot be resumed (e.g. live sockets).
+    fn position(&self) -> Option<SourcePosition> {
+        None
+    }
+
+    /// Continue reading from a position taken from a checkpoint.
+    fn resume_from(&mut self, _position: &SourcePosition) -> Result<(), String> {
+        Err(format!("Source {} cannot resume from a checkpoint", self.describe()))
+    }
+}
+
+/// Turns text lines or payloads into `StreamEvent`s according to an `EventFormat`.
+#[derive(Debug, Clone)]
+pub struct LineDecoder {
+    format: EventFormat,
+    timestamping: Timestamping,
+    next_seq: usize,
+}
+
+impl LineDecoder {
+    pub fn new(format: EventFormat, timestamping: Timestamping) -> Self {
+        LineDecoder {
+            format,
+            timestamping,
+            next_seq: 1,
+        }
+    }
+
+    pub fn set_timestamping(&mut self, timestamping: Timestamping) {
+        self.timestamping = timestamping;
+    }
+
+    /// Sequence timestamp the next event without its own `ts` receives.
+    pub fn next_sequence(&self) -> usize {
+        self.next_seq
+    }
+
+    pub fn set_next_sequence(&mut self, next_seq: usize) {
+        self.next_seq = next_seq;
+    }
+
+    /// Decode one line. Blank lines and `#` comments yield `Ok(None)`.
+    pub fn decode_line(&mut self, line: &str) -> Result<Option<StreamEvent>, String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+        let (triple, ts) = match self.format {
+            EventFormat::NTriples => (decode_ntriples(line)?, None),
+            EventFormat::JsonLines => decode_json(line)?,
+        };
+        let ts = match ts {
+            Some(ts) => ts,
+            None => self.next_timestamp(),
+        };
+        Ok(Some(StreamEvent { triple, ts }))
+    }
+
+    /// Decode every line of a payload, skipping (and logging) malformed ones.
+    pub fn decode_payload(&mut self, payload: &str) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+        for line in payload.lines() {
+            match self.decode_line(line) {
+                Ok(Some(event)) => events.push(event),
+                Ok(None) => {}
+                Err(e) => warn!("Skipping malformed stream line: {}", e),
+            }
+        }
+        events
+    }
+
+    fn next_timestamp(&mut self) -> usize {
+        match self.timestamping {
+            Timestamping::Sequence => {
+                let ts = self.next_seq;
+                self.next_seq += 1;
+                ts
+            }
+            Timestamping::WallClock => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs() as usize)
+                .unwrap_or(0),
+        }
+    }
+}
+
+fn decode_ntriples(line: &str) -> Result<WindowTriple, String> {
+    let body = line
+        .strip_suffix('.')
+        .ok_or_else(|| format!("N-Triples line is missing the final dot: {}", line))?;
+    let terms = SparqlDatabase::split_ntriples_line(body.trim());
+    let (s, p, o) = SparqlDatabase::clean_ntriples_parts(&terms)
+        .ok_or_else(|| format!("Invalid N-Triples line: {}", line))?;
+    Ok(WindowTriple {
+        s,
+        p,
+        o,
+        object_kind: ObjectKind::of_ntriples_term(&terms[2]),
+    })
+}
+
+fn decode_json(line: &str) -> Result<(WindowTriple, Option<usize>), String> {
+    let value: serde_json::Value =
+        serde_json::from_str(line).map_err(|e| format!("Invalid JSON line: {}", e))?;
+    let term = |key: &str| -> Result<&str, String> {
+        value
+            .get(key)
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| format!("JSON event is missing string field '{}': {}", key, line))
+    };
+    let object = term("o")?;
+    let triple = WindowTriple {
+        s: SparqlDatabase::clean_ntriples_term(term("s")?),
+        p: SparqlDatabase::clean_ntriples_term(term("p")?),
+        o: SparqlDatabase::clean_ntriples_term(object),
+        object_kind: ObjectKind::of_ntriples_term(object),
+    };
+    let ts = value.get("ts").and_then(|v| v.as_u64()).map(|v| v as usize);
+    Ok((triple, ts))
+}
+
+/// Messages sent from source threads to the engine.
+pub enum SourceMessage {
+    Event {
+        stream_iri: String,
+        event: StreamEvent,
+    },
+    /// Sent after each batch so the engine knows how far the applied events reach.
+    Position {
+        source: String,
+        position: SourcePosition,
+    },
+    Finished {
+        source: String,
+        error: Option<String>,
+    },
+}
+
+/// Running source thread attached to an engine.
+pub struct SourceHandle {
+    name: String,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SourceHandle {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Ask the source thread to stop after its current poll and wait for it.
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Run `source` on a dedicated thread, forwarding its events to `sender` tagged with `stream_iri`.
+pub fn spawn_source(
+    stream_iri: String,
+    mut source: Box<dyn StreamSource>,
+    sender: Sender<SourceMessage>,
+) -> SourceHandle {
+    let name = source.describe();
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_flag = Arc::clone(&stop);
+    let thread_name = name.clone();
+    let thread = thread::spawn(move || {
+        let error = loop {
+            if stop_flag.load(Ordering::SeqCst) {
+                break None;
+            }
+            match source.poll_events() {
+                Ok(Some(events)) => {
+                    let batch_len = events.len();
+                    for event in events {
+                        let msg = SourceMessage::Event {
+                            stream_iri: stream_iri.clone(),
+                            event,
+                        };
+                        if sender.send(msg).is_err() {
+                            // Engine is gone; nothing left to feed.
+                            return;
+                        }
+                    }
+                    if batch_len > 0 {
+                        if let Some(position) = source.position() {
+                            let _ = sender.send(SourceMessage::Position {
+                                source: thread_name.clone(),
+                                position,
+                            });
+                        }
+                    }
+                }
+                Ok(None) => break None,
+                Err(e) => break Some(e),
+            }
+        };
+        debug!("Stream source {} finished", thread_name);
+        let _ = sender.send(SourceMessage::Finished {
+            source: thread_name,
+            error,
+        });
+    });
+    SourceHandle {
+        name,
+        stop,
+        thread: Some(thread),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_ntriples_and_json_lines() {
+        let mut decoder = LineDecoder::new(EventFormat::NTriples, Timestamping::Sequence);
+        let events = decoder.decode_payload(
+            "# comment\n<http://ex/s1> <http://ex/p> \"21.5\" .\n\n<http://ex/s2> a <http://ex/T> .\n",
+        );
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].triple.s, "http://ex/s1");
+        assert_eq!(events[0].triple.o, "21.5");
+        assert_eq!(events[0].ts, 1);
+        assert_eq!(events[1].triple.p, "http://www.w3.org/1999/02/22-rdf-syntax-ns#type");
+        assert_eq!(events[1].ts, 2);
+
+        let mut decoder = LineDecoder::new(EventFormat::JsonLines, Timestamping::Sequence);
+        let event = decoder
+            .decode_line(r#"{"s": "<http://ex/s1>", "p": "http://ex/p", "o": "7", "ts": 42}"#)
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.triple.s, "http://ex/s1");
+        assert_eq!(event.triple.p, "http://ex/p");
+        assert_eq!(event.ts, 42);
+        assert!(decoder.decode_line(r#"{"s": "x"}"#).is_err());
+    }
+
+    #[test]
+    fn test_window_triple_round_trip() {
+        let triple = WindowTriple {
+            s: "http://ex/s1".to_string(),
+            p: "http://ex/p".to_string(),
+            o: "hello world".to_string(),
+            object_kind: ObjectKind::Literal {
+                datatype: None,
+                language: None,
+            },
+        };
+        let mut decoder = LineDecoder::new(EventFormat::NTriples, Timestamping::Sequence);
+        let event = decoder.decode_line(&triple.to_ntriples()).unwrap().unwrap();
+        assert_eq!(event.triple, triple);
+
+        // Objects keep the kind of term they were written as.
+        for line in [
+            r#"<http://ex/s1> <http://ex/at> "12:30" ."#,
+            r#"<http://ex/s1> <http://ex/temp> "21.5"^^<http://www.w3.org/2001/XMLSchema#decimal> ."#,
+            r#"<http://ex/s1> <http://ex/label> "warm"@en ."#,
+            r#"<http://ex/s1> <http://ex/next> <urn:ex:s2> ."#,
+            r#"_:b1 <http://ex/near> _:b2 ."#,
+        ] {
+            let event = decoder.decode_line(line).unwrap().unwrap();
+            assert_eq!(event.triple.to_ntriples(), line);
+        }
+        let event = decoder.decode_line(r#"<http://ex/s1> <http://ex/label> "warm"@en ."#).unwrap().unwrap();
+        assert_eq!(event.triple.o, "warm@en");
+    }
+}
@@ -0,0 +1,93 @@
+/*
+ * Copyright © 2025 Volodymyr Kadzhaia
+ * Copyright © 2025 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use super::{EventFormat, LineDecoder, StreamEvent, StreamSource, Timestamping};
+#[cfg(not(test))]
+use log::warn;
+use rumqttc::{Client, Connection, Event, MqttOptions, Packet, QoS, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+#[cfg(test)]
+use std::println as warn;
+
+const RECV_TIMEOUT: Duration = Duration::from_millis(100);
+const RECONNECT_DELAY: Duration = Duration::from_millis(500);
+
+/// Subscribes to one or more MQTT topics; every publish payload is decoded as
+/// one or more event lines.
+pub struct MqttSource {
+    description: String,
+    // Kept alive so the event loop keeps running; dropping it ends the connection.
+    _client: Client,
+    connection: Connection,
+    decoder: LineDecoder,
+}
+
+impl MqttSource {
+    pub fn new(options: MqttOptions, topics: &[&str], format: EventFormat) -> Result<MqttSource, String> {
+        let (host, port) = options.broker_address();
+        let description = format!("mqtt://{}:{}/{}", host, port, topics.join(","));
+        let (mut client, connection) = Client::new(options, 64);
+        for topic in topics {
+            client
+                .subscribe(*topic, QoS::AtLeastOnce)
+                .map_err(|e| format!("Failed to subscribe to {}: {}", topic, e))?;
+        }
+        Ok(MqttSource {
+            description,
+            _client: client,
+            connection,
+            decoder: LineDecoder::new(format, Timestamping::WallClock),
+        })
+    }
+
+    /// Convenience constructor with a keep-alive of 5 seconds and a clean session.
+    pub fn connect(
+        client_id: &str,
+        host: &str,
+        port: u16,
+        topic: &str,
+        format: EventFormat,
+    ) -> Result<MqttSource, String> {
+        let mut options = MqttOptions::new(client_id, host, port);
+        options.set_keep_alive(Duration::from_secs(5));
+        options.set_clean_session(true);
+        Self::new(options, &[topic], format)
+    }
+
+    pub fn timestamping(mut self, timestamping: Timestamping) -> Self {
+        self.decoder.set_timestamping(timestamping);
+        self
+    }
+}
+
+impl StreamSource for MqttSource {
+    fn describe(&self) -> String {
+        self.description.clone()
+    }
+
+    fn poll_events(&mut self) -> Result<Option<Vec<StreamEvent>>, String> {
+        match self.connection.recv_timeout(RECV_TIMEOUT) {
+            Ok(Ok(Event::Incoming(Packet::Publish(msg)))) => {
+                let payload = String::from_utf8_lossy(&msg.payload);
+                Ok(Some(self.decoder.decode_payload(&payload)))
+            }
+            Ok(Ok(_)) => Ok(Some(Vec::new())),
+            Ok(Err(e)) => {
+                // The event loop reconnects on the next poll.
+                warn!("{}: connection error: {}", self.description, e);
+                thread::sleep(RECONNECT_DELAY);
+                Ok(Some(Vec::new()))
+            }
+            Err(RecvTimeoutError::Timeout) => Ok(Some(Vec::new())),
+            Err(RecvTimeoutError::Disconnected) => Ok(None),
+        }
+    }
+}
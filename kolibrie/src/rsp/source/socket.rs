@@ -0,0 +1,302 @@
+/*
+ * Copyright © 2025 Volodymyr Kadzhaia
+ * Copyright © 2025 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use super::{EventFormat, LineDecoder, StreamEvent, StreamSource, Timestamping};
+use rand::Rng;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+const MAX_BATCH: usize = 256;
+
+fn is_timeout(e: &io::Error) -> bool {
+    matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)
+}
+
+/// Reads newline-delimited events from a raw TCP connection.
+pub struct TcpLineSource {
+    peer: String,
+    reader: BufReader<TcpStream>,
+    decoder: LineDecoder,
+    partial: Vec<u8>,
+}
+
+impl TcpLineSource {
+    pub fn connect<A: ToSocketAddrs>(addr: A, format: EventFormat) -> Result<TcpLineSource, String> {
+        let stream = TcpStream::connect(addr).map_err(|e| format!("TCP connect failed: {}", e))?;
+        let peer = stream
+            .peer_addr()
+            .map(|a| a.to_string())
+            .unwrap_or_else(|_| "unknown".to_string());
+        stream
+            .set_read_timeout(Some(READ_TIMEOUT))
+            .map_err(|e| format!("Unable to configure TCP socket: {}", e))?;
+        Ok(TcpLineSource {
+            peer,
+            reader: BufReader::new(stream),
+            decoder: LineDecoder::new(format, Timestamping::WallClock),
+            partial: Vec::new(),
+        })
+    }
+
+    pub fn timestamping(mut self, timestamping: Timestamping) -> Self {
+        self.decoder.set_timestamping(timestamping);
+        self
+    }
+}
+
+impl StreamSource for TcpLineSource {
+    fn describe(&self) -> String {
+        format!("tcp:{}", self.peer)
+    }
+
+    fn poll_events(&mut self) -> Result<Option<Vec<StreamEvent>>, String> {
+        let mut events = Vec::new();
+        loop {
+            match self.reader.read_until(b'\n', &mut self.partial) {
+                Ok(0) => {
+                    // Peer closed the connection; flush an unterminated last line.
+                    let rest = std::mem::take(&mut self.partial);
+                    events.extend(self.decoder.decode_payload(&String::from_utf8_lossy(&rest)));
+                    return if events.is_empty() { Ok(None) } else { Ok(Some(events)) };
+                }
+                Ok(_) if self.partial.ends_with(b"\n") => {
+                    let line = std::mem::take(&mut self.partial);
+                    events.extend(self.decoder.decode_payload(&String::from_utf8_lossy(&line)));
+                    // Deliver what is buffered before blocking on the socket again.
+                    if self.reader.buffer().is_empty() || events.len() >= MAX_BATCH {
+                        return Ok(Some(events));
+                    }
+                }
+                Ok(_) => {}
+                Err(e) if is_timeout(&e) => return Ok(Some(events)),
+                Err(e) => return Err(format!("TCP read from {} failed: {}", self.peer, e)),
+            }
+        }
+    }
+}
+
+/// Reads events from the text (or UTF-8 binary) messages of a WebSocket connection.
+/// Each message may hold several lines. Only plain `ws://` URLs are supported.
+pub struct WebSocketSource {
+    url: String,
+    stream: TcpStream,
+    decoder: LineDecoder,
+    /// Payload of a fragmented message still being assembled.
+    fragments: Vec<u8>,
+    closed: bool,
+}
+
+impl WebSocketSource {
+    pub fn connect(url: &str, format: EventFormat) -> Result<WebSocketSource, String> {
+        let rest = url
+            .strip_prefix("ws://")
+            .ok_or_else(|| format!("Unsupported WebSocket URL (expected ws://): {}", url))?;
+        let (authority, path) = match rest.find('/') {
+            Some(idx) => (&rest[..idx], &rest[idx..]),
+            None => (rest, "/"),
+        };
+        let addr = if authority.contains(':') {
+            authority.to_string()
+        } else {
+            format!("{}:80", authority)
+        };
+
+        let mut stream =
+            TcpStream::connect(&addr).map_err(|e| format!("WebSocket connect failed: {}", e))?;
+        let key: [u8; 16] = rand::rng().random();
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            path,
+            authority,
+            base64_encode(&key)
+        );
+        stream
+            .write_all(request.as_bytes())
+            .map_err(|e| format!("WebSocket handshake failed: {}", e))?;
+
+        // Read the handshake response byte by byte so no frame data is consumed.
+        let mut response = Vec::new();
+        let mut byte = [0u8; 1];
+        while !response.ends_with(b"\r\n\r\n") {
+            let n = stream
+                .read(&mut byte)
+                .map_err(|e| format!("WebSocket handshake failed: {}", e))?;
+            if n == 0 {
+                return Err("WebSocket handshake failed: connection closed".to_string());
+            }
+            response.push(byte[0]);
+        }
+        let mut headers = [httparse::EMPTY_HEADER; 32];
+        let mut parsed = httparse::Response::new(&mut headers);
+        parsed
+            .parse(&response)
+            .map_err(|e| format!("Invalid WebSocket handshake response: {}", e))?;
+        if parsed.code != Some(101) {
+            return Err(format!(
+                "WebSocket upgrade rejected with status {:?}",
+                parsed.code
+            ));
+        }
+
+        stream
+            .set_read_timeout(Some(READ_TIMEOUT))
+            .map_err(|e| format!("Unable to configure WebSocket: {}", e))?;
+        Ok(WebSocketSource {
+            url: url.to_string(),
+            stream,
+            decoder: LineDecoder::new(format, Timestamping::WallClock),
+            fragments: Vec::new(),
+            closed: false,
+        })
+    }
+
+    pub fn timestamping(mut self, timestamping: Timestamping) -> Self {
+        self.decoder.set_timestamping(timestamping);
+        self
+    }
+
+    /// Read one frame; `Ok(None)` if no frame started within the read timeout.
+    fn read_frame(&mut self) -> io::Result<Option<(u8, bool, Vec<u8>)>> {
+        let mut header = [0u8; 2];
+        match self.stream.read(&mut header[..1]) {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(_) => {}
+            Err(e) if is_timeout(&e) => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        // Once a frame has started, wait for the rest of it.
+        self.stream.set_read_timeout(None)?;
+        let frame = self.read_frame_rest(&mut header);
+        self.stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        frame.map(Some)
+    }
+
+    fn read_frame_rest(&mut self, header: &mut [u8; 2]) -> io::Result<(u8, bool, Vec<u8>)> {
+        self.stream.read_exact(&mut header[1..])?;
+        let fin = header[0] & 0x80 != 0;
+        let opcode = header[0] & 0x0F;
+        let masked = header[1] & 0x80 != 0;
+        let len = match header[1] & 0x7F {
+            126 => {
+                let mut buf = [0u8; 2];
+                self.stream.read_exact(&mut buf)?;
+                u16::from_be_bytes(buf) as usize
+            }
+            127 => {
+                let mut buf = [0u8; 8];
+                self.stream.read_exact(&mut buf)?;
+                u64::from_be_bytes(buf) as usize
+            }
+            n => n as usize,
+        };
+        let mut mask = [0u8; 4];
+        if masked {
+            self.stream.read_exact(&mut mask)?;
+        }
+        let mut payload = vec![0u8; len];
+        self.stream.read_exact(&mut payload)?;
+        if masked {
+            for (i, b) in payload.iter_mut().enumerate() {
+                *b ^= mask[i % 4];
+            }
+        }
+        Ok((opcode, fin, payload))
+    }
+
+    /// Send a (masked, as required for clients) control frame.
+    fn send_control(&mut self, opcode: u8, payload: &[u8]) -> io::Result<()> {
+        let mask: [u8; 4] = rand::rng().random();
+        let mut frame = vec![0x80 | opcode, 0x80 | payload.len().min(125) as u8];
+        frame.extend_from_slice(&mask);
+        frame.extend(
+            payload
+                .iter()
+                .take(125)
+                .enumerate()
+                .map(|(i, b)| b ^ mask[i % 4]),
+        );
+        self.stream.write_all(&frame)
+    }
+}
+
+impl StreamSource for WebSocketSource {
+    fn describe(&self) -> String {
+        self.url.clone()
+    }
+
+    fn poll_events(&mut self) -> Result<Option<Vec<StreamEvent>>, String> {
+        if self.closed {
+            return Ok(None);
+        }
+        let (opcode, fin, payload) = match self.read_frame() {
+            Ok(Some(frame)) => frame,
+            Ok(None) => return Ok(Some(Vec::new())),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(format!("WebSocket read from {} failed: {}", self.url, e)),
+        };
+        let events = match opcode {
+            // Continuation, text and binary frames carry data.
+            0x0..=0x2 => {
+                self.fragments.extend_from_slice(&payload);
+                if fin {
+                    let message = std::mem::take(&mut self.fragments);
+                    self.decoder.decode_payload(&String::from_utf8_lossy(&message))
+                } else {
+                    Vec::new()
+                }
+            }
+            0x8 => {
+                let _ = self.send_control(0x8, &[]);
+                self.closed = true;
+                return Ok(None);
+            }
+            0x9 => {
+                self.send_control(0xA, &payload)
+                    .map_err(|e| format!("WebSocket pong failed: {}", e))?;
+                Vec::new()
+            }
+            _ => Vec::new(),
+        };
+        Ok(Some(events))
+    }
+}
+
+fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::base64_encode;
+
+    #[test]
+    fn test_base64_encode() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+    }
+}
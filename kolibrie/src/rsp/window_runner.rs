@@ -8,12 +8,13 @@
  * you can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+use crate::rsp::buffer::{BufferConfig, QueueMonitor};
 use crate::rsp::s2r::{CSPARQLWindow, ContentContainer, Report, ReportStrategy, Tick};
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::sync::mpsc::Receiver;
 
-pub use crate::rsp::s2r::WindowTriple;
+pub use crate::rsp::s2r::{ObjectKind, WindowTriple};
 
 #[derive(Debug, Clone)]
 pub struct WindowSpec {
@@ -81,10 +82,21 @@ where
         self.inner.add_to_window(item, ts);
     }
 
+    pub fn advance(&mut self, ts: usize) {
+        self.inner.advance(ts);
+    }
+
     pub fn register(&mut self) -> Receiver<ContentContainer<I>> {
         self.inner.register()
     }
 
+    pub fn register_bounded(
+        &mut self,
+        config: BufferConfig,
+    ) -> (crossbeam::channel::Receiver<ContentContainer<I>>, QueueMonitor<ContentContainer<I>>) {
+        self.inner.register_bounded(config)
+    }
+
     pub fn register_callback(
         &mut self,
         f: Box<dyn FnMut(ContentContainer<I>) -> () + Send + 'static>,
@@ -99,4 +111,16 @@ where
     pub fn stop(&mut self) {
         self.inner.stop();
     }
+
+    pub fn app_time(&self) -> usize {
+        self.inner.app_time()
+    }
+
+    pub fn active_windows(&self) -> Vec<(usize, usize, &ContentContainer<I>)> {
+        self.inner.active_windows()
+    }
+
+    pub fn restore(&mut self, app_time: usize, windows: Vec<(usize, usize, Vec<I>, usize)>) {
+        self.inner.restore(app_time, windows);
+    }
 }
@@ -7,30 +7,44 @@
 * you can obtain one at [https://mozilla.org/MPL/2.0/](https://mozilla.org/MPL/2.0/).
 */
 
+use crate::adaptive_planner::{AdaptivePlanner, ReplanTrigger};
 use crate::experiment_logging;
+use crate::rsp::buffer::{BufferConfig, QueueMetrics, QueueMonitor};
+use crate::rsp::checkpoint::{
+    CheckpointConfig, EngineCheckpoint, WindowCheckpoint, WindowContentCheckpoint, CHECKPOINT_VERSION,
+};
+use crate::rsp::partition::{KeyedWindows, PartitionPool, PartitionProcessor, PartitionedWindow};
 use crate::rsp::r2r::R2ROperator;
 use crate::rsp::r2s::Relation2StreamOperator;
-use crate::rsp::s2r::{ContentContainer, ReportStrategy, Tick};
+use crate::rsp::s2r::{ContentContainer, ReportStrategy, Tick, WindowTriple};
+use crate::rsp::sink::{
+    output_channel, spawn_sink, OutputBindings, OutputEvent, OutputStreamSource, SinkHandle, StreamSink,
+};
+use crate::rsp::source::{spawn_source, SourceHandle, SourceMessage, SourcePosition, StreamSource};
 use crate::rsp::window_runner::{WindowRunner, WindowSpec};
 
 #[cfg(not(test))]
-use log::{debug, error}; // Use log crate when building application
+use log::{debug, error, warn}; // Use log crate when building application
 use shared::query::{Fallback, SyncPolicy};
 use shared::rule::Rule;
+use shared::triple::Triple;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::hash::Hash;
 use crossbeam::channel::{unbounded, RecvTimeoutError, Receiver, Sender};
 use std::collections::HashSet;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 use std::time::Instant;
 #[cfg(test)]
-use std::{println as debug, println as error};
+use std::{println as debug, println as error, println as warn};
 
 use crate::parser::process_rule_definition;
 use crate::sparql_database::SparqlDatabase;
-use crate::streamertail_optimizer::{ExecutionEngine, LogicalOperator, PhysicalOperator};
+use datalog::reasoning::entailment::{is_schema_triple, EntailmentRegime};
+use crate::streamertail_optimizer::{ExecutionEngine, ExecutionProfile, LogicalOperator, PhysicalOperator};
 
 // Re-exports to preserve the public API used by kolibrie-http-server and examples.
 pub use crate::rsp::builder::{RSPBuilder, RSPQueryConfig};
@@ -58,6 +72,10 @@ pub struct RSPWindow {
     pub tick: Tick,
     pub report_strategy: ReportStrategy,
     pub query: LogicalOperator, // The SPARQL query to execute on this window
+    /// `PARTITION BY` variable: the window is kept per subject bound to it.
+    pub partition_by: Option<String>,
+    /// `WITH REPLAN` trigger; `None` falls back to the adaptive planner's default.
+    pub replan: Option<ReplanTrigger>,
 }
 
 /// RSP-QL Query Plan using Volcano optimizer
@@ -79,18 +97,24 @@ pub struct ResultConsumer<I> {
     pub function: Arc<dyn Fn(I) -> () + Send + Sync>,
 }
 
+/// Delivers one R2S output (with its timestamp) to the result consumer and the attached sinks.
+type OutputFn<O> = Arc<dyn Fn(O, usize) + Send + Sync>;
+
 pub type WindowPlanAdaptor<I> = Arc<
     dyn Fn(&str, &ContentContainer<I>, usize, &PhysicalOperator) -> Option<PhysicalOperator>
         + Send
         + Sync,
 >;
 
+/// Runtime hook receiving the actual per-operator output sizes of each window execution.
+pub type WindowPlanFeedback = Arc<dyn Fn(&str, &PhysicalOperator, &ExecutionProfile) + Send + Sync>;
+
 /// Macro to generate the window processing logic
 macro_rules! create_window_processor {
     ($window_iri:expr, $query_execution_mode:expr,
      $r2r_store:expr, $has_joins:expr, $window_result_sender:expr, $r2s_consumer_func:expr,
-     $window_idx:expr, $window_plans:expr, $window_plan_adaptor:expr) => {{
-        let mut prev_window_triples: Vec<I> = Vec::new();
+     $window_idx:expr, $window_plans:expr, $window_plan_adaptor:expr, $window_plan_feedback:expr,
+     $materialized:expr) => {{
         move |content: ContentContainer<I>| {
             let mut query = {
                 let plans = $window_plans.read().unwrap();
@@ -122,9 +146,10 @@ macro_rules! create_window_processor {
             let ts = content.get_last_timestamp_changed();
             let window_size = content.len();
             let mut store = $r2r_store.lock().unwrap();
+            let mut prev_window_triples = $materialized.lock().unwrap();
 
             // Evict triples from the previous firing of this window
-            for t in &prev_window_triples {
+            for t in prev_window_triples.iter() {
                 store.remove(t);
             }
             prev_window_triples.clear();
@@ -141,12 +166,20 @@ macro_rules! create_window_processor {
             // Measure query execution time
             let query_start = Instant::now();
 
-            let results = store.execute_query(&query);
+            let feedback = $window_plan_feedback.read().unwrap().clone();
+            let (results, profile) = match feedback {
+                Some(_) => store.execute_query_profiled(&query),
+                None => (store.execute_query(&query), None),
+            };
             debug!("Got # results {} for window {}", results.len(), $window_iri);
 
             // Query execution ends here, print elapsed time
             let query_execution_time = query_start.elapsed().as_secs_f64() * 1000.0;
 
+            if let (Some(feedback), Some(profile)) = (feedback, profile) {
+                feedback(&$window_iri, &query, &profile);
+            }
+
             // println!(
             //     "[WindowQueryTime] window={} ts={} query_execution_time={:.3} ms results={}",
             //     $window_iri, ts, query_execution_time, results.len()
@@ -169,7 +202,8 @@ macro_rules! create_window_processor {
                 );
             }
 
-            // Release lock early to reduce contention
+            // Release locks early to reduce contention
+            drop(prev_window_triples);
             drop(store);
 
             if $has_joins {
@@ -206,8 +240,8 @@ macro_rules! register_window {
     (SingleThread, $window:expr, $processor:expr) => {
         $window.register_callback(Box::new($processor));
     };
-    (MultiThread, $window:expr, $processor:expr, $window_iri:expr) => {{
-        let receiver = $window.register();
+    (MultiThread, $window:expr, $processor:expr, $window_iri:expr, $buffer_config:expr) => {{
+        let (receiver, monitor) = $window.register_bounded($buffer_config);
         thread::spawn(move || {
             loop {
                 match receiver.recv() {
@@ -222,6 +256,7 @@ macro_rules! register_window {
             }
             debug!("Shutdown complete for window {}!", $window_iri);
         });
+        monitor
     }};
 }
 
@@ -232,7 +267,11 @@ where
 {
     windows: Vec<WindowRunner<I>>,
     r2r: Arc<Mutex<Box<dyn R2ROperator<I, Vec<PhysicalOperator>, O>>>>,
-    r2s_consumer: ResultConsumer<O>,
+    output: OutputFn<O>,
+    /// Output stream IRI from the REGISTER clause (normalized).
+    output_stream: String,
+    /// Sinks bound to the output stream; shared with the emission path.
+    sinks: Arc<RwLock<Vec<SinkHandle>>>,
     window_configs: Vec<RSPWindow>,
     query_execution_mode: QueryExecutionMode,
     operation_mode: OperationMode,
@@ -242,19 +281,42 @@ where
     // RSP-QL Query Plan using Volcano optimizer
     rsp_query_plan: RSPQueryPlan,
     window_plan_adaptor: Arc<RwLock<Option<WindowPlanAdaptor<I>>>>,
+    window_plan_feedback: Arc<RwLock<Option<WindowPlanFeedback>>>,
+    adaptive_planner: Option<Arc<AdaptivePlanner>>,
     /// Latest materialized results per window (replace semantics); SingleThread only.
     single_thread_last_materialized: Arc<Mutex<HashMap<String, Vec<HashMap<String, String>>>>>,
     /// Synchronization policy governing multi-window coordination.
     sync_policy: SyncPolicy,
+    /// Capacity and overflow policy of the window-to-worker buffers (MultiThread only).
+    buffer_config: BufferConfig,
+    queue_monitors: Vec<(String, QueueMonitor<ContentContainer<I>>)>,
+    /// Keyed (`PARTITION BY`) windows by window index; their `windows` entry stays unused.
+    partitioned: HashMap<usize, PartitionedWindow<I, O>>,
+    /// Number of workers evaluating the partitions of each keyed window (MultiThread only).
+    partition_workers: usize,
     /// Separate store for static background triples (never touched by window processors).
     static_db: Arc<Mutex<SparqlDatabase>>,
     /// R2S operator for stream-type filtering (RSTREAM/ISTREAM/DSTREAM).
     r2s_operator: Arc<Mutex<Relation2StreamOperator<O>>>,
+    /// Events produced by attached stream sources, pushed into windows by `poll_sources`.
+    source_sender: Sender<SourceMessage>,
+    source_receiver: Receiver<SourceMessage>,
+    sources: Vec<SourceHandle>,
+    /// Number of attached sources that have not reported completion yet.
+    active_sources: usize,
+    /// Read positions of sources whose events reached the windows, keyed by source description.
+    source_positions: HashMap<String, SourcePosition>,
+    /// Items of each window's last firing, currently loaded in the R2R store.
+    window_materialized: Vec<Arc<Mutex<Vec<I>>>>,
+    checkpoint_config: Option<CheckpointConfig>,
+    last_checkpoint: Instant,
+    /// Queries registered with `chain`, each after the queries whose output it reads.
+    chained: Vec<RSPEngine<I, O>>,
 }
 
 impl<I, O> RSPEngine<I, O>
 where
-    O: Clone + Hash + Eq + Send + 'static + From<Vec<(String, String)>>,
+    O: Clone + Hash + Eq + Send + 'static + From<Vec<(String, String)>> + OutputBindings,
     I: Eq + PartialEq + Clone + Debug + Hash + Send + 'static,
 {
     pub fn new(
@@ -270,6 +332,9 @@ where
         sync_policy: SyncPolicy,
         reasoning_rules: Vec<Rule>,
         sparql_rules: Vec<String>,
+        entailment: Option<EntailmentRegime>,
+        buffer_config: BufferConfig,
+        partition_workers: usize,
     ) -> RSPEngine<I, O> {
         let mut store = r2r;
 
@@ -316,7 +381,16 @@ where
 
         if !reasoning_rules.is_empty() {
             if let Some(simple_r2r) = store.as_any_mut().downcast_mut::<SimpleR2R>() {
-                simple_r2r.add_reasoning_rules(reasoning_rules);
+                if let Err(e) = simple_r2r.add_reasoning_rules(reasoning_rules) {
+                    error!("Failed to add reasoning rules: {}", e);
+                }
+            }
+        }
+
+        if let Some(regime) = entailment {
+            match store.as_any_mut().downcast_mut::<SimpleR2R>() {
+                Some(simple_r2r) => simple_r2r.set_entailment_regime(regime),
+                None => warn!("Entailment regime {:?} needs a SimpleR2R store; ignored", regime),
             }
         }
 
@@ -332,7 +406,9 @@ where
                     match process_rule_definition(rule_str, &mut temp_db) {
                         Ok((rule, _)) => {
                             if let Some(simple_r2r) = store.as_any_mut().downcast_mut::<SimpleR2R>() {
-                                simple_r2r.rules.push(rule);
+                                if let Err(e) = simple_r2r.add_reasoning_rules(vec![rule]) {
+                                    error!("Failed to add SPARQL rule: {}", e);
+                                }
                             }
                         }
                         Err(e) => error!("Failed to parse SPARQL rule: {:?}", e),
@@ -358,11 +434,17 @@ where
 
         let stream_type = query_config.stream_type.clone();
         let r2s_operator = Arc::new(Mutex::new(Relation2StreamOperator::new(stream_type, 0)));
+        let (source_sender, source_receiver) = unbounded::<SourceMessage>();
+        let output_stream = normalize_stream_iri(&query_config.output_stream);
+        let sinks = Arc::new(RwLock::new(Vec::new()));
+        let output = output_fn(result_consumer, Arc::clone(&sinks), output_stream.clone());
 
         let mut engine = RSPEngine {
             windows,
             r2r: Arc::new(Mutex::new(store)),
-            r2s_consumer: result_consumer,
+            output,
+            output_stream,
+            sinks,
             window_configs: query_config.windows.clone(),
             query_execution_mode,
             operation_mode,
@@ -370,10 +452,27 @@ where
             window_result_receiver: result_receiver,
             rsp_query_plan,
             window_plan_adaptor: Arc::new(RwLock::new(None)),
+            window_plan_feedback: Arc::new(RwLock::new(None)),
+            adaptive_planner: None,
             single_thread_last_materialized: Arc::new(Mutex::new(HashMap::new())),
             sync_policy,
+            buffer_config,
+            queue_monitors: Vec::new(),
+            partitioned: HashMap::new(),
+            partition_workers,
             static_db,
             r2s_operator,
+            source_sender,
+            source_receiver,
+            sources: Vec::new(),
+            active_sources: 0,
+            source_positions: HashMap::new(),
+            window_materialized: (0..query_config.windows.len())
+                .map(|_| Arc::new(Mutex::new(Vec::new())))
+                .collect(),
+            checkpoint_config: None,
+            last_checkpoint: Instant::now(),
+            chained: Vec::new(),
         };
 
         match operation_mode {
@@ -396,31 +495,38 @@ where
     fn register_windows(&mut self, operation_mode: OperationMode) {
         let has_joins = self.windows.len() > 1
             || self.rsp_query_plan.static_data_plan.is_some();
+        let mut queue_monitors = Vec::new();
 
         for (window_idx, window) in self.windows.iter_mut().enumerate() {
+            if self.window_configs[window_idx].partition_by.is_some() {
+                continue;
+            }
             let window_iri = self.window_configs[window_idx].window_iri.clone();
             let window_iri_for_thread = window_iri.clone(); // Clone for MultiThread usage
+            let window_iri_for_monitor = window_iri.clone();
             let query_execution_mode = self.query_execution_mode;
             let window_result_sender = self.window_result_sender.clone();
             let r2r_store = self.r2r.clone();
             let window_plans = Arc::clone(&self.rsp_query_plan.window_plans);
             let window_plan_adaptor = Arc::clone(&self.window_plan_adaptor);
+            let window_plan_feedback = Arc::clone(&self.window_plan_feedback);
+            let materialized = Arc::clone(&self.window_materialized[window_idx]);
 
             let r2s_consumer_func: Arc<dyn Fn(Vec<O>, usize) + Send + Sync> = if has_joins {
                 Arc::new(|_, _| {})
             } else {
                 let r2s_op = Arc::clone(&self.r2s_operator);
-                let consumer_fn = self.r2s_consumer.function.clone();
+                let output = Arc::clone(&self.output);
                 Arc::new(move |results: Vec<O>, ts: usize| {
                     let filtered = r2s_op.lock().unwrap().eval(results, ts);
                     for r in filtered {
-                        (consumer_fn)(r);
+                        (output)(r, ts);
                     }
                 })
             };
 
             // Create processor using macro
-            let mut processor = create_window_processor!(
+            let processor = create_window_processor!(
                 window_iri,
                 query_execution_mode,
                 r2r_store,
@@ -429,7 +535,9 @@ where
                 r2s_consumer_func,
                 window_idx,
                 window_plans,
-                window_plan_adaptor
+                window_plan_adaptor,
+                window_plan_feedback,
+                materialized
             );
 
             // Register based on mode
@@ -438,10 +546,139 @@ where
                     register_window!(SingleThread, window, processor);
                 }
                 OperationMode::MultiThread => {
-                    register_window!(MultiThread, window, processor, window_iri_for_thread);
+                    let monitor = register_window!(
+                        MultiThread,
+                        window,
+                        processor,
+                        window_iri_for_thread,
+                        self.buffer_config
+                    );
+                    queue_monitors.push((window_iri_for_monitor, monitor));
                 }
             }
         }
+        self.queue_monitors = queue_monitors;
+
+        for window_idx in 0..self.window_configs.len() {
+            if self.window_configs[window_idx].partition_by.is_some() {
+                let partitioned = self.create_partitioned_window(window_idx, operation_mode, has_joins);
+                self.partitioned.insert(window_idx, partitioned);
+            }
+        }
+    }
+
+    /// Build a keyed window whose partitions are evaluated by `partition_workers`
+    /// processors, each on its own fork of the R2R store.
+    fn create_partitioned_window(
+        &self,
+        window_idx: usize,
+        operation_mode: OperationMode,
+        has_joins: bool,
+    ) -> PartitionedWindow<I, O> {
+        let config = &self.window_configs[window_idx];
+        let window_iri = config.window_iri.clone();
+        let spec = WindowSpec {
+            width: config.width,
+            slide: config.slide,
+            report_strategies: vec![config.report_strategy.clone()],
+            tick: config.tick.clone(),
+        };
+
+        let workers = match operation_mode {
+            OperationMode::SingleThread => 1,
+            OperationMode::MultiThread => self.partition_workers.max(1),
+        };
+        let mut stores = Vec::with_capacity(workers);
+        for _ in 0..workers {
+            match self.r2r.lock().unwrap().fork() {
+                Some(store) => stores.push(Arc::new(Mutex::new(store))),
+                None => break,
+            }
+        }
+        if stores.is_empty() {
+            warn!(
+                "R2R operator cannot be forked; partitions of window {} share the engine store",
+                window_iri
+            );
+            stores.push(self.r2r.clone());
+        }
+
+        let processors: Vec<PartitionProcessor<I, O>> = stores
+            .into_iter()
+            .map(|r2r_store| {
+                let collected: Arc<Mutex<Vec<O>>> = Arc::new(Mutex::new(Vec::new()));
+                let collector = Arc::clone(&collected);
+                let r2s_consumer_func: Arc<dyn Fn(Vec<O>, usize) + Send + Sync> =
+                    Arc::new(move |results: Vec<O>, _| collector.lock().unwrap().extend(results));
+                let window_iri = window_iri.clone();
+                let query_execution_mode = self.query_execution_mode;
+                let window_result_sender = self.window_result_sender.clone();
+                let window_plans = Arc::clone(&self.rsp_query_plan.window_plans);
+                let window_plan_adaptor = Arc::clone(&self.window_plan_adaptor);
+                let window_plan_feedback = Arc::clone(&self.window_plan_feedback);
+                let materialized: Arc<Mutex<Vec<I>>> = Arc::new(Mutex::new(Vec::new()));
+                let processor = create_window_processor!(
+                    window_iri,
+                    query_execution_mode,
+                    r2r_store,
+                    false,
+                    window_result_sender,
+                    r2s_consumer_func,
+                    window_idx,
+                    window_plans,
+                    window_plan_adaptor,
+                    window_plan_feedback,
+                    materialized
+                );
+                Box::new(move |content: ContentContainer<I>| {
+                    processor(content);
+                    std::mem::take(&mut *collected.lock().unwrap())
+                }) as PartitionProcessor<I, O>
+            })
+            .collect();
+
+        // Merged partition results take the place of a single window's results.
+        let merged: Arc<dyn Fn(Vec<O>, usize) + Send + Sync> = if has_joins {
+            let window_result_sender = self.window_result_sender.clone();
+            let window_iri = window_iri.clone();
+            Arc::new(move |results: Vec<O>, ts: usize| {
+                let mapped_results = results
+                    .iter()
+                    .filter_map(|res| {
+                        (res as &dyn std::any::Any)
+                            .downcast_ref::<Vec<(String, String)>>()
+                            .map(|bindings| bindings.iter().cloned().collect())
+                    })
+                    .collect();
+                let window_res = WindowResult {
+                    window_iri: window_iri.clone(),
+                    results: mapped_results,
+                    timestamp: ts,
+                };
+                if let Err(e) = window_result_sender.send(window_res) {
+                    error!("Failed to send window result to buffer: {:?}", e);
+                }
+            })
+        } else {
+            let r2s_op = Arc::clone(&self.r2s_operator);
+            let output = Arc::clone(&self.output);
+            Arc::new(move |results: Vec<O>, ts: usize| {
+                let filtered = r2s_op.lock().unwrap().eval(results, ts);
+                for r in filtered {
+                    (output)(r, ts);
+                }
+            })
+        };
+
+        let pool = match operation_mode {
+            OperationMode::SingleThread => {
+                PartitionPool::inline(processors.into_iter().next().unwrap(), merged)
+            }
+            OperationMode::MultiThread => {
+                PartitionPool::threaded(processors, self.buffer_config, merged)
+            }
+        };
+        PartitionedWindow::new(KeyedWindows::new(spec, window_iri), pool)
     }
 
     /// Start a coordinator thread that collects and joins results from multiple windows
@@ -451,7 +688,7 @@ where
         O: From<Vec<(String, String)>>,
     {
         let receiver = self.window_result_receiver.clone();
-        let consumer = self.r2s_consumer.function.clone();
+        let consumer = Arc::clone(&self.output);
         let num_windows = self.windows.len();
         let static_data_plan = self.rsp_query_plan.static_data_plan.clone();
         let static_db = self.static_db.clone();
@@ -578,41 +815,32 @@ where
             self.process_single_thread_window_results();
         }
 
-        fn normalize_stream_iri(s: &str) -> String {
-            let s = s.trim();
-            // Some callers might pass a full IRI in `<...>` form.
-            let s = s.trim_start_matches('<').trim_end_matches('>');
-            // Accept prefixed notation with an optional leading colon, e.g. `:stream1`.
-            let s = s.strip_prefix(':').unwrap_or(s);
-            s.to_string()
-        }
-
         let input_norm = normalize_stream_iri(stream_iri);
 
         // Find windows that match this stream IRI
-        for (window_idx, window_config) in self.window_configs.iter().enumerate() {
-            // Variable stream (e.g. `?s`) matches any stream.
-            if window_config.stream_iri.starts_with('?') {
-                if let Some(window) = self.windows.get_mut(window_idx) {
-                    window.add_to_window(event_item.clone(), ts);
-                }
-                continue;
-            }
-
-            let cfg_norm = normalize_stream_iri(&window_config.stream_iri);
-            if cfg_norm == input_norm {
-                if let Some(window) = self.windows.get_mut(window_idx) {
-                    window.add_to_window(event_item.clone(), ts);
-                }
-            }
+        // Variable stream (e.g. `?s`) matches any stream.
+        let matching: Vec<usize> = self
+            .window_configs
+            .iter()
+            .enumerate()
+            .filter(|(_, window_config)| {
+                window_config.stream_iri.starts_with('?')
+                    || normalize_stream_iri(&window_config.stream_iri) == input_norm
+            })
+            .map(|(window_idx, _)| window_idx)
+            .collect();
+        for window_idx in matching {
+            self.add_to_window_at(window_idx, event_item.clone(), ts);
         }
+
+        self.maybe_checkpoint();
     }
 
     pub fn process_single_thread_window_results(&mut self)
     where
         O: From<Vec<(String, String)>>,
     {
-        let consumer = self.r2s_consumer.function.clone();
+        let consumer = Arc::clone(&self.output);
         let num_windows = self.windows.len();
         let sync_policy = self.sync_policy.clone();
 
@@ -657,19 +885,421 @@ where
     /// Legacy method for backward compatibility
     pub fn add(&mut self, event_item: I, ts: usize) {
         // Add to all windows (for backward compatibility)
-        for window in &mut self.windows {
-            window.add_to_window(event_item.clone(), ts);
+        for window_idx in 0..self.windows.len() {
+            self.add_to_window_at(window_idx, event_item.clone(), ts);
+        }
+    }
+
+    fn add_to_window_at(&mut self, window_idx: usize, event_item: I, ts: usize) {
+        if let Some(partitioned) = self.partitioned.get_mut(&window_idx) {
+            partitioned.add(event_item, ts);
+        } else if let Some(window) = self.windows.get_mut(window_idx) {
+            window.add_to_window(event_item, ts);
+        }
+    }
+
+    /// Add a decoded triple to a stream, encoding it through the R2R operator's dictionary.
+    pub fn add_window_triple(&mut self, stream_iri: &str, triple: &WindowTriple, ts: usize) {
+        for item in self.parse_data(&triple.to_ntriples()) {
+            self.add_to_stream(stream_iri, item, ts);
+        }
+    }
+
+    /// Attach a source to a stream declared in the RSP-QL query. The source runs on its
+    /// own thread; its events reach the windows through `poll_sources` or `run_sources`.
+    /// If a restored checkpoint recorded a position for this source, it resumes from there.
+    pub fn attach_source(
+        &mut self,
+        stream_iri: &str,
+        mut source: Box<dyn StreamSource>,
+    ) -> Result<(), String> {
+        if !self.reads_stream(&normalize_stream_iri(stream_iri)) {
+            return Err(format!(
+                "Stream {} is not declared in the RSP-QL query (declared: {:?})",
+                stream_iri,
+                self.stream_iris()
+            ));
+        }
+        if let Some(position) = self.source_positions.get(&source.describe()) {
+            debug!("Resuming source {} from {:?}", source.describe(), position);
+            source.resume_from(position)?;
+        }
+        debug!("Attaching source {} to stream {}", source.describe(), stream_iri);
+        self.sources.push(spawn_source(
+            stream_iri.to_string(),
+            source,
+            self.source_sender.clone(),
+        ));
+        self.active_sources += 1;
+        Ok(())
+    }
+
+    /// Whether a window reads the (normalized) stream; variable streams read every stream.
+    fn reads_stream(&self, stream_iri: &str) -> bool {
+        self.window_configs.iter().any(|w| {
+            w.stream_iri.starts_with('?') || normalize_stream_iri(&w.stream_iri) == stream_iri
+        })
+    }
+
+    /// Push every event received from attached sources so far into the windows,
+    /// those of chained queries included.
+    /// Returns the number of events processed; never blocks.
+    pub fn poll_sources(&mut self) -> usize {
+        let mut processed = 0;
+        while let Ok(msg) = self.source_receiver.try_recv() {
+            processed += self.handle_source_message(msg);
+        }
+        for chained in &mut self.chained {
+            processed += chained.poll_sources();
+        }
+        processed
+    }
+
+    /// Register another query in this engine, fed by the output streams of its queries.
+    ///
+    /// Every result a query of the engine emits on its `REGISTER` output stream reaches,
+    /// as stream triples (see `OutputEvent::to_stream_events`), the windows of the chained
+    /// queries reading that stream with `FROM NAMED WINDOW ... ON <output>`. `downstream`
+    /// must read the output stream of this query or of a query chained before. Chaining
+    /// fails if it would close a cycle, e.g. a query reading its own output stream.
+    /// `poll_sources` and `stop` drive the chained queries along with this one.
+    pub fn chain(&mut self, mut downstream: RSPEngine<I, O>) -> Result<(), String> {
+        let order = {
+            let mut queries: Vec<&RSPEngine<I, O>> = vec![&*self];
+            queries.extend(self.chained.iter());
+            if !queries.iter().any(|query| downstream.reads_stream(&query.output_stream)) {
+                return Err(format!(
+                    "Query registered on {} reads none of the output streams {:?}",
+                    downstream.output_stream,
+                    queries.iter().map(|query| query.output_stream.as_str()).collect::<Vec<_>>()
+                ));
+            }
+            queries.push(&downstream);
+            chain_order(&queries)?
+        };
+
+        // Route the outputs the new query reads, and its own output to the queries reading it
+        if downstream.reads_stream(&self.output_stream) {
+            let source = self.subscribe_output();
+            downstream.attach_source(&self.output_stream, Box::new(source))?;
+        }
+        for chained in &mut self.chained {
+            if downstream.reads_stream(&chained.output_stream) {
+                let source = chained.subscribe_output();
+                downstream.attach_source(&chained.output_stream, Box::new(source))?;
+            }
+            if chained.reads_stream(&downstream.output_stream) {
+                let source = downstream.subscribe_output();
+                chained.attach_source(&downstream.output_stream, Box::new(source))?;
+            }
+        }
+        debug!("Chaining query registered on {}", downstream.output_stream);
+
+        // Keep the chained queries after the queries they read; this query comes first
+        let mut chained: Vec<Option<RSPEngine<I, O>>> = std::mem::take(&mut self.chained)
+            .into_iter()
+            .chain(std::iter::once(downstream))
+            .map(Some)
+            .collect();
+        self.chained = order
+            .into_iter()
+            .filter(|&query| query > 0)
+            .filter_map(|query| chained[query - 1].take())
+            .collect();
+        Ok(())
+    }
+
+    /// Block, pushing source events into the windows, until every attached source is exhausted.
+    /// Returns the number of events processed.
+    pub fn run_sources(&mut self) -> usize {
+        let mut processed = 0;
+        while self.active_sources > 0 {
+            match self.source_receiver.recv() {
+                Ok(msg) => processed += self.handle_source_message(msg),
+                Err(_) => break,
+            }
+        }
+        processed
+    }
+
+    fn handle_source_message(&mut self, msg: SourceMessage) -> usize {
+        match msg {
+            SourceMessage::Event { stream_iri, event } => {
+                self.add_window_triple(&stream_iri, &event.triple, event.ts);
+                1
+            }
+            SourceMessage::Position { source, position } => {
+                self.source_positions.insert(source, position);
+                0
+            }
+            SourceMessage::Finished { source, error } => {
+                self.active_sources = self.active_sources.saturating_sub(1);
+                match error {
+                    Some(e) => warn!("Stream source {} stopped with error: {}", source, e),
+                    None => debug!("Stream source {} exhausted", source),
+                }
+                0
+            }
+        }
+    }
+
+    /// Write a checkpoint to `config.path` every `config.interval`; checked whenever
+    /// an event is added and once more on `stop()`.
+    pub fn set_checkpointing(&mut self, config: CheckpointConfig) {
+        self.checkpoint_config = Some(config);
+        self.last_checkpoint = Instant::now();
+    }
+
+    fn maybe_checkpoint(&mut self) {
+        let Some(config) = &self.checkpoint_config else {
+            return;
+        };
+        if self.last_checkpoint.elapsed() < config.interval {
+            return;
+        }
+        if let Err(e) = self.save_checkpoint(&config.path) {
+            error!("Periodic checkpoint failed: {}", e);
+        }
+        self.last_checkpoint = Instant::now();
+    }
+
+    /// Snapshot open windows (those of every key for PARTITION BY windows), the R2S
+    /// operator state, the items materialized in the R2R store and the positions of
+    /// attached sources.
+    ///
+    /// In MultiThread mode, window contents already handed to worker threads but not
+    /// yet evaluated are not part of the snapshot.
+    pub fn checkpoint(&self) -> Result<EngineCheckpoint, String> {
+        // Copy out the materialized items first: window processors lock the store before them.
+        let materialized: Vec<Vec<I>> = self
+            .window_materialized
+            .iter()
+            .map(|m| m.lock().unwrap().clone())
+            .collect();
+
+        let r2r = self.r2r.lock().unwrap();
+        let encode = |item: &I| {
+            r2r.to_ntriples(item)
+                .ok_or_else(|| "The R2R operator cannot serialize window items".to_string())
+        };
+        let encode_active = |active: Vec<(usize, usize, &ContentContainer<I>)>| {
+            active
+                .into_iter()
+                .map(|(open, close, content)| {
+                    Ok(WindowContentCheckpoint {
+                        open,
+                        close,
+                        last_timestamp_changed: content.get_last_timestamp_changed(),
+                        items: content.iter().map(&encode).collect::<Result<_, String>>()?,
+                    })
+                })
+                .collect::<Result<Vec<_>, String>>()
+        };
+        let mut windows = Vec::with_capacity(self.windows.len());
+        for (idx, window) in self.windows.iter().enumerate() {
+            let mut app_time = window.app_time();
+            let mut partitions = Vec::new();
+            if let Some(partitioned) = self.partitioned.get(&idx) {
+                let keyed = partitioned.windows();
+                app_time = keyed.app_time().unwrap_or(app_time);
+                for active in keyed.active_windows() {
+                    partitions.push(encode_active(active)?);
+                }
+            }
+            windows.push(WindowCheckpoint {
+                window_iri: self.window_configs[idx].window_iri.clone(),
+                app_time,
+                active: encode_active(window.active_windows())?,
+                materialized: materialized[idx].iter().map(&encode).collect::<Result<_, _>>()?,
+                partitions,
+            });
+        }
+        drop(r2r);
+
+        let last_result = self
+            .r2s_operator
+            .lock()
+            .unwrap()
+            .last_result()
+            .iter()
+            .map(OutputBindings::bindings)
+            .collect();
+
+        Ok(EngineCheckpoint {
+            version: CHECKPOINT_VERSION,
+            output_stream: self.output_stream.clone(),
+            windows,
+            last_result,
+            pending_window_results: self.single_thread_last_materialized.lock().unwrap().clone(),
+            source_positions: self.source_positions.clone(),
+        })
+    }
+
+    pub fn save_checkpoint<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let checkpoint = self.checkpoint()?;
+        checkpoint.save(path)?;
+        debug!("Checkpoint written with {} windows", checkpoint.windows.len());
+        Ok(())
+    }
+
+    /// Restore state captured by `checkpoint()`. Must be called before sources are
+    /// attached so they can resume from their recorded positions.
+    pub fn restore_checkpoint(&mut self, checkpoint: &EngineCheckpoint) -> Result<(), String> {
+        if checkpoint.output_stream != self.output_stream {
+            return Err(format!(
+                "Checkpoint belongs to output stream {}, not {}",
+                checkpoint.output_stream, self.output_stream
+            ));
+        }
+        for window_cp in &checkpoint.windows {
+            let idx = self
+                .window_configs
+                .iter()
+                .position(|w| w.window_iri == window_cp.window_iri)
+                .ok_or_else(|| format!("Checkpoint window {} is not in the query", window_cp.window_iri))?;
+
+            let mut active = Vec::with_capacity(window_cp.active.len());
+            for content in &window_cp.active {
+                let items = self.parse_items(&content.items);
+                active.push((content.open, content.close, items, content.last_timestamp_changed));
+            }
+            self.windows[idx].restore(window_cp.app_time, active);
+
+            if self.partitioned.contains_key(&idx) {
+                let mut partitions = Vec::with_capacity(window_cp.partitions.len());
+                for windows in &window_cp.partitions {
+                    let mut active = Vec::with_capacity(windows.len());
+                    for content in windows {
+                        let items = self.parse_items(&content.items);
+                        active.push((content.open, content.close, items, content.last_timestamp_changed));
+                    }
+                    partitions.push(active);
+                }
+                let app_time = (!partitions.is_empty()).then_some(window_cp.app_time);
+                if let Some(partitioned) = self.partitioned.get_mut(&idx) {
+                    partitioned.windows_mut().restore(app_time, partitions);
+                }
+            }
+
+            let items = self.parse_items(&window_cp.materialized);
+            let mut store = self.r2r.lock().unwrap();
+            let mut materialized = self.window_materialized[idx].lock().unwrap();
+            for item in materialized.iter() {
+                store.remove(item);
+            }
+            for item in &items {
+                store.add(item.clone());
+            }
+            *materialized = items;
         }
+        // Re-derive the reasoning closure over the restored window items.
+        self.r2r.lock().unwrap().materialize();
+
+        let last_result = checkpoint
+            .last_result
+            .iter()
+            .map(|row| O::from(row.clone()))
+            .collect();
+        self.r2s_operator.lock().unwrap().set_last_result(last_result);
+        *self.single_thread_last_materialized.lock().unwrap() = checkpoint.pending_window_results.clone();
+        self.source_positions = checkpoint.source_positions.clone();
+        Ok(())
+    }
+
+    fn parse_items(&mut self, lines: &[String]) -> Vec<I> {
+        lines.iter().flat_map(|line| self.parse_data(line)).collect()
+    }
+
+    /// Depth and overflow counters of each window's worker buffer, keyed by window IRI.
+    /// Partitioned windows report one entry per worker, keyed `<window IRI>#<worker>`.
+    /// Empty in SingleThread mode, where windows are evaluated inline.
+    pub fn queue_metrics(&self) -> Vec<(String, QueueMetrics)> {
+        let mut metrics: Vec<(String, QueueMetrics)> = self
+            .queue_monitors
+            .iter()
+            .map(|(window_iri, monitor)| (window_iri.clone(), monitor.metrics()))
+            .collect();
+        for (window_idx, partitioned) in &self.partitioned {
+            let window_iri = &self.window_configs[*window_idx].window_iri;
+            for (worker, worker_metrics) in partitioned.queue_metrics().into_iter().enumerate() {
+                metrics.push((format!("{}#{}", window_iri, worker), worker_metrics));
+            }
+        }
+        metrics
+    }
+
+    /// Number of keys with an open window in each `PARTITION BY` window, keyed by window IRI.
+    pub fn partition_keys(&self) -> Vec<(String, usize)> {
+        self.partitioned
+            .iter()
+            .map(|(window_idx, partitioned)| {
+                (self.window_configs[*window_idx].window_iri.clone(), partitioned.keys())
+            })
+            .collect()
+    }
+
+    /// Output stream IRI of the registered query.
+    pub fn output_stream(&self) -> &str {
+        &self.output_stream
+    }
+
+    /// Attach a sink to the query's output stream, or to that of a chained query. Fails
+    /// if `output_iri` is not the IRI named in one of their `REGISTER` clauses. The sink
+    /// runs on its own thread.
+    pub fn attach_sink(&mut self, output_iri: &str, sink: Box<dyn StreamSink>) -> Result<(), String> {
+        let norm = normalize_stream_iri(output_iri);
+        if let Some(chained) = self.chained.iter_mut().find(|chained| chained.output_stream == norm) {
+            return chained.attach_sink(output_iri, sink);
+        }
+        if norm != self.output_stream {
+            return Err(format!(
+                "Stream {} is not the registered output stream {}",
+                output_iri, self.output_stream
+            ));
+        }
+        debug!("Attaching sink {} to output stream {}", sink.describe(), output_iri);
+        self.sinks.write().unwrap().push(spawn_sink(sink));
+        Ok(())
+    }
+
+    /// Subscribe to the output stream as a `StreamSource`, so another engine can consume
+    /// it as an input stream via `attach_source(upstream.output_stream(), ...)`.
+    /// The source finishes once this engine is stopped. Queries of the same engine are
+    /// chained with `chain` instead.
+    pub fn subscribe_output(&mut self) -> OutputStreamSource {
+        let (sink, source) = output_channel(&self.output_stream);
+        self.sinks.write().unwrap().push(spawn_sink(Box::new(sink)));
+        source
     }
 
     pub fn stop(&mut self) {
+        for source in &mut self.sources {
+            source.stop();
+        }
+        self.poll_sources();
         for window in &mut self.windows {
             window.flush();
             window.stop();
         }
+        for partitioned in self.partitioned.values_mut() {
+            partitioned.flush_and_stop();
+        }
         if matches!(self.operation_mode, OperationMode::SingleThread) {
             self.process_single_thread_window_results();
         }
+        if let Some(config) = &self.checkpoint_config {
+            if let Err(e) = self.save_checkpoint(&config.path) {
+                error!("Final checkpoint failed: {}", e);
+            }
+        }
+        for sink in self.sinks.write().unwrap().iter_mut() {
+            sink.stop();
+        }
+        // The sinks above delivered the last results to the chained queries
+        for chained in &mut self.chained {
+            chained.run_sources();
+            chained.stop();
+        }
     }
 
     pub fn parse_data(&mut self, data: &str) -> Vec<I> {
@@ -680,11 +1310,28 @@ where
     /// These triples are never placed in the window R2R store, so they cannot
     /// leak into window query results.  They are only visible when `emit_results`
     /// joins the window output with the static-data plan.
+    ///
+    /// With an entailment regime, the schema axioms among them (subclasses,
+    /// domains, property characteristics, ...) are also handed to the R2R
+    /// store, which reasons over the window contents with them.
     pub fn add_static_ntriples(&mut self, data: &str) {
         let mut db = self.static_db.lock().unwrap();
-        db.parse_ntriples_and_add(data);
+        let triples = db.parse_and_encode_ntriples(data);
+        for triple in &triples {
+            db.add_triple(triple.clone());
+        }
         db.get_or_build_stats();
         db.build_all_indexes();
+
+        let mut r2r = self.r2r.lock().unwrap();
+        if let Some(simple_r2r) = r2r.as_any_mut().downcast_mut::<SimpleR2R>() {
+            if simple_r2r.entailment_regime().is_some() {
+                let dict = db.dictionary.read().unwrap();
+                let schema: Vec<Triple> = triples.into_iter().filter(|t| is_schema_triple(t, &dict)).collect();
+                drop(dict);
+                simple_r2r.add_ontology_triples(schema);
+            }
+        }
     }
 
     /// Get information about configured windows
@@ -711,6 +1358,14 @@ where
         *guard = Some(adaptor);
     }
 
+    /// Register a runtime hook that receives, after each window execution, the plan
+    /// that ran and the actual output size of each of its operators. Only called when
+    /// the R2R operator supports `execute_query_profiled`.
+    pub fn set_window_plan_feedback(&mut self, feedback: WindowPlanFeedback) {
+        let mut guard = self.window_plan_feedback.write().unwrap();
+        *guard = Some(feedback);
+    }
+
     /// Replace a specific window's physical plan at runtime.
     pub fn update_window_plan(&self, window_idx: usize, new_plan: PhysicalOperator) {
         let mut plans = self.rsp_query_plan.window_plans.write().unwrap();
@@ -718,10 +1373,118 @@ where
             plans[window_idx] = new_plan;
         }
     }
+
+    /// Let `planner` pick the join order of every (non-partitioned) window at each firing.
+    /// Installs the planner as the window plan adaptor and feedback hook, replacing any
+    /// previous ones; the plans it returns are swapped in as by `update_window_plan`.
+    /// Fails unless the windows hold `Triple`s, whose statistics the planner reads.
+    pub fn set_adaptive_planner(&mut self, planner: Arc<AdaptivePlanner>) -> Result<(), String> {
+        if std::any::TypeId::of::<I>() != std::any::TypeId::of::<Triple>() {
+            return Err(format!(
+                "The adaptive planner needs Triple window items, not {}",
+                std::any::type_name::<I>()
+            ));
+        }
+        for window in &self.window_configs {
+            if window.partition_by.is_none() {
+                planner.register_window(&window.window_iri, window.query.clone(), window.replan.clone());
+            }
+        }
+        let adaptor_planner = Arc::clone(&planner);
+        self.set_window_plan_adaptor(Arc::new(move |window_iri, content, ts, current_plan| {
+            let content = (content as &dyn std::any::Any).downcast_ref::<ContentContainer<Triple>>()?;
+            adaptor_planner.on_firing(window_iri, content, ts, current_plan)
+        }));
+        let feedback_planner = Arc::clone(&planner);
+        self.set_window_plan_feedback(Arc::new(move |window_iri, plan, profile| {
+            feedback_planner.on_execution(window_iri, plan, profile);
+        }));
+        self.adaptive_planner = Some(planner);
+        Ok(())
+    }
+
+    /// The planner installed with `set_adaptive_planner`, e.g. to read its metrics.
+    pub fn adaptive_planner(&self) -> Option<&Arc<AdaptivePlanner>> {
+        self.adaptive_planner.as_ref()
+    }
+}
+
+/// Build the emission path: attached sinks receive each result's bindings as an
+/// `OutputEvent`, then the consumer gets the result itself.
+fn output_fn<O>(
+    consumer: ResultConsumer<O>,
+    sinks: Arc<RwLock<Vec<SinkHandle>>>,
+    stream_iri: String,
+) -> OutputFn<O>
+where
+    O: OutputBindings + 'static,
+{
+    let next_index = AtomicUsize::new(0);
+    Arc::new(move |result: O, ts: usize| {
+        {
+            let sinks = sinks.read().unwrap();
+            if !sinks.is_empty() {
+                let event = OutputEvent {
+                    stream_iri: stream_iri.clone(),
+                    ts,
+                    index: next_index.fetch_add(1, Ordering::SeqCst),
+                    bindings: result.bindings(),
+                };
+                for sink in sinks.iter() {
+                    sink.send(event.clone());
+                }
+            }
+        }
+        (consumer.function)(result);
+    })
+}
+
+/// Order of chained queries in which each comes after the queries whose output stream it
+/// reads, as indices into `queries`. Fails if the queries read each other's output in a cycle.
+fn chain_order<I, O>(queries: &[&RSPEngine<I, O>]) -> Result<Vec<usize>, String>
+where
+    O: Clone + Hash + Eq + Send + 'static + From<Vec<(String, String)>> + OutputBindings,
+    I: Eq + PartialEq + Clone + Debug + Hash + Send + 'static,
+{
+    let feeds = |from: usize, to: usize| queries[to].reads_stream(&queries[from].output_stream);
+    let mut inputs: Vec<usize> = (0..queries.len())
+        .map(|to| (0..queries.len()).filter(|&from| feeds(from, to)).count())
+        .collect();
+    let mut ready: Vec<usize> = (0..queries.len()).filter(|&query| inputs[query] == 0).collect();
+    let mut order = Vec::new();
+    while let Some(query) = ready.pop() {
+        order.push(query);
+        for (to, count) in inputs.iter_mut().enumerate() {
+            if to != query && feeds(query, to) {
+                *count -= 1;
+                if *count == 0 {
+                    ready.push(to);
+                }
+            }
+        }
+    }
+    if order.len() < queries.len() {
+        let cycle: Vec<&str> = (0..queries.len())
+            .filter(|query| !order.contains(query))
+            .map(|query| queries[query].output_stream.as_str())
+            .collect();
+        return Err(format!("Chained queries would read their own output in a cycle through {:?}", cycle));
+    }
+    Ok(order)
+}
+
+/// Normalize a stream IRI for matching against the window configuration.
+fn normalize_stream_iri(s: &str) -> String {
+    let s = s.trim();
+    // Some callers might pass a full IRI in `<...>` form.
+    let s = s.trim_start_matches('<').trim_end_matches('>');
+    // Accept prefixed notation with an optional leading colon, e.g. `:stream1`.
+    let s = s.strip_prefix(':').unwrap_or(s);
+    s.to_string()
 }
 
 /// Join all window results, optionally apply the static-data join, apply the R2S operator,
-/// and call `consumer` for each output binding.
+/// and hand each output binding to `consumer`.
 /// Called from both the coordinator thread and the SingleThread processor.
 fn emit_results<O>(
     last_materialized: &HashMap<String, Vec<HashMap<String, String>>>,
@@ -729,7 +1492,7 @@ fn emit_results<O>(
     static_db: &Arc<Mutex<SparqlDatabase>>,
     r2s: &Arc<Mutex<Relation2StreamOperator<O>>>,
     ts: usize,
-    consumer: &Arc<dyn Fn(O) -> () + Send + Sync>,
+    consumer: &OutputFn<O>,
 ) where
     O: 'static + Clone + Hash + Eq + From<Vec<(String, String)>>,
 {
@@ -754,7 +1517,7 @@ fn emit_results<O>(
         .collect();
     let filtered = r2s.lock().unwrap().eval(outputs, ts);
     for result in filtered {
-        (consumer)(result);
+        (consumer)(result, ts);
     }
 }
 
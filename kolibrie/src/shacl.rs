@@ -0,0 +1,867 @@
+/*
+ * Copyright © 2025 Volodymyr Kadzhaia
+ * Copyright © 2025 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use crate::sparql_database::SparqlDatabase;
+use crate::turtle::{self, TurtleTerm, TurtleTriple};
+use regex::{Regex, RegexBuilder};
+use shared::dictionary::Dictionary;
+use shared::index_manager::UnifiedIndex;
+use shared::rule::numeric_value;
+use shared::triple::Triple;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{LazyLock, RwLockReadGuard};
+
+const SH: &str = "http://www.w3.org/ns/shacl#";
+const RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const RDFS_CLASS: &str = "http://www.w3.org/2000/01/rdf-schema#Class";
+const RDFS_SUBCLASS_OF: &str = "http://www.w3.org/2000/01/rdf-schema#subClassOf";
+const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+
+/// Recursive shapes are not defined by SHACL; checking stops at this depth
+const MAX_SHAPE_DEPTH: usize = 32;
+
+static XSD_DATE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^-?\d{4,}-\d{2}-\d{2}(Z|[+-]\d{2}:\d{2})?$").unwrap());
+static XSD_DATE_TIME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^-?\d{4,}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(\.\d+)?(Z|[+-]\d{2}:\d{2})?$").unwrap()
+});
+
+fn sh(name: &str) -> String {
+    format!("{}{}", SH, name)
+}
+
+fn rdf(name: &str) -> String {
+    format!("{}{}", RDF, name)
+}
+
+/// Triples of a shapes graph indexed both ways, with the terms as stored in
+/// the dictionary
+#[derive(Debug, Clone, Default)]
+struct Graph {
+    outgoing: HashMap<String, HashMap<String, Vec<String>>>,
+    incoming: HashMap<String, HashMap<String, Vec<String>>>,
+}
+
+impl Graph {
+    fn insert(&mut self, subject: String, predicate: String, object: String) {
+        let objects = self.outgoing.entry(subject.clone()).or_default().entry(predicate.clone()).or_default();
+        if !objects.contains(&object) {
+            objects.push(object.clone());
+            self.incoming.entry(object).or_default().entry(predicate).or_default().push(subject);
+        }
+    }
+
+    fn objects(&self, subject: &str, predicate: &str) -> &[String] {
+        self.outgoing.get(subject).and_then(|p| p.get(predicate)).map_or(&[], Vec::as_slice)
+    }
+
+    fn subjects(&self, predicate: &str, object: &str) -> &[String] {
+        self.incoming.get(object).and_then(|p| p.get(predicate)).map_or(&[], Vec::as_slice)
+    }
+
+    fn object(&self, subject: &str, predicate: &str) -> Option<&str> {
+        self.objects(subject, predicate).first().map(String::as_str)
+    }
+
+    /// All (subject, object) pairs of a predicate
+    fn pairs<'a>(&'a self, predicate: &'a str) -> impl Iterator<Item = (&'a str, &'a str)> + 'a {
+        self.outgoing.iter().flat_map(move |(subject, predicates)| {
+            predicates.get(predicate).into_iter().flatten().map(move |object| (subject.as_str(), object.as_str()))
+        })
+    }
+
+    /// Members of the RDF collection starting at `head`
+    fn list(&self, head: &str) -> Vec<String> {
+        let mut items = Vec::new();
+        let mut node = head.to_string();
+        let mut seen = HashSet::new();
+        while node != rdf("nil") && seen.insert(node.clone()) {
+            match self.object(&node, &rdf("first")) {
+                Some(item) => items.push(item.to_string()),
+                None => break,
+            }
+            match self.object(&node, &rdf("rest")) {
+                Some(rest) => node = rest.to_string(),
+                None => break,
+            }
+        }
+        items
+    }
+}
+
+/// The triples of a `SparqlDatabase`, looked up through its dictionary and
+/// index, so only the terms the validation reaches are decoded
+struct DataGraph<'a> {
+    dictionary: RwLockReadGuard<'a, Dictionary>,
+    index: &'a UnifiedIndex,
+}
+
+impl<'a> DataGraph<'a> {
+    fn new(database: &'a SparqlDatabase) -> Self {
+        Self { dictionary: database.dictionary.read().unwrap(), index: &database.index_manager }
+    }
+
+    fn id(&self, term: &str) -> Option<u32> {
+        self.dictionary.string_to_id.get(term).copied()
+    }
+
+    /// Decoded terms, sorted so reports come out in a stable order
+    fn terms<'i>(&self, ids: impl IntoIterator<Item = &'i u32>) -> Vec<String> {
+        let mut terms: Vec<String> =
+            ids.into_iter().filter_map(|&id| self.dictionary.decode(id)).map(str::to_string).collect();
+        terms.sort();
+        terms
+    }
+
+    fn objects(&self, subject: &str, predicate: &str) -> Vec<String> {
+        match (self.id(subject), self.id(predicate)) {
+            (Some(s), Some(p)) => self.terms(self.index.scan_sp(s, p).into_iter().flatten()),
+            _ => Vec::new(),
+        }
+    }
+
+    fn subjects(&self, predicate: &str, object: &str) -> Vec<String> {
+        match (self.id(predicate), self.id(object)) {
+            (Some(p), Some(o)) => self.terms(self.index.scan_po(p, o).into_iter().flatten()),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Subjects, or objects, of a predicate
+    fn ends(&self, predicate: &str, subjects: bool) -> Vec<String> {
+        let Some(pairs) = self.id(predicate).and_then(|p| self.index.pso.get(&p)) else {
+            return Vec::new();
+        };
+        if subjects {
+            self.terms(pairs.keys())
+        } else {
+            self.terms(pairs.values().flatten())
+        }
+    }
+
+    /// (predicate, object) pairs of the triples about `subject`
+    fn outgoing(&self, subject: &str) -> Vec<(String, String)> {
+        let Some(predicates) = self.id(subject).and_then(|s| self.index.spo.get(&s)) else {
+            return Vec::new();
+        };
+        let mut pairs: Vec<(String, String)> = predicates
+            .iter()
+            .flat_map(|(&p, objects)| objects.iter().map(move |&o| (p, o)))
+            .filter_map(|(p, o)| Some((self.dictionary.decode(p)?.to_string(), self.dictionary.decode(o)?.to_string())))
+            .collect();
+        pairs.sort();
+        pairs
+    }
+
+    /// `class` and its subclasses, by `rdfs:subClassOf`
+    fn subclasses(&self, class: &str) -> HashSet<String> {
+        let mut classes = HashSet::from([class.to_string()]);
+        let mut queue = VecDeque::from([class.to_string()]);
+        while let Some(class) = queue.pop_front() {
+            for sub in self.subjects(RDFS_SUBCLASS_OF, &class) {
+                if classes.insert(sub.clone()) {
+                    queue.push_back(sub);
+                }
+            }
+        }
+        classes
+    }
+
+    fn instances(&self, class: &str) -> Vec<String> {
+        let mut instances: Vec<String> =
+            self.subclasses(class).iter().flat_map(|class| self.subjects(&rdf("type"), class)).collect();
+        instances.sort();
+        instances.dedup();
+        instances
+    }
+
+    fn is_instance(&self, node: &str, class: &str) -> bool {
+        let classes = self.subclasses(class);
+        self.objects(node, &rdf("type")).iter().any(|t| classes.contains(t))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NodeKind {
+    Iri,
+    BlankNode,
+    Literal,
+}
+
+/// Kind of a stored term. Like the serializers of `SparqlDatabase`, IRIs are
+/// told apart from literals by their scheme.
+fn node_kind(term: &str) -> NodeKind {
+    if term.starts_with("_:") {
+        NodeKind::BlankNode
+    } else if !term.starts_with('"')
+        && !term.contains(char::is_whitespace)
+        && (term.contains("://") || term.starts_with("urn:") || term.starts_with("mailto:"))
+    {
+        NodeKind::Iri
+    } else {
+        NodeKind::Literal
+    }
+}
+
+/// Lexical form, datatype and language of a stored literal: `"42"^^<...#int>`,
+/// `"hi"@en`, or `42` and `hi@en` when stored without quotes
+fn literal_parts(term: &str) -> (&str, Option<&str>, Option<&str>) {
+    if let Some(end) = term.strip_prefix('"').and_then(|rest| rest.rfind('"')) {
+        let (lexical, rest) = (&term[1..end + 1], &term[end + 2..]);
+        if let Some(datatype) = rest.strip_prefix("^^") {
+            return (lexical, Some(datatype.trim_start_matches('<').trim_end_matches('>')), None);
+        }
+        return (lexical, None, rest.strip_prefix('@'));
+    }
+    match term.rsplit_once('@') {
+        Some((lexical, language)) if is_language_tag(language) => (lexical, None, Some(language)),
+        _ => (term, None, None),
+    }
+}
+
+fn is_language_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+    let primary = subtags.next().unwrap_or("");
+    (2..=3).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|s| (1..=8).contains(&s.len()) && s.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+/// String value of a term, as used by `sh:pattern` and the length constraints
+fn lexical_form(term: &str) -> &str {
+    match node_kind(term) {
+        NodeKind::Literal => literal_parts(term).0,
+        _ => term,
+    }
+}
+
+fn valid_lexical_form(lexical: &str, datatype: &str) -> Option<bool> {
+    let integer = || {
+        let digits = lexical.strip_prefix(['+', '-']).unwrap_or(lexical);
+        !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+    };
+    let local = datatype.strip_prefix(XSD)?;
+    Some(match local {
+        "integer" | "int" | "long" | "short" | "byte" => integer(),
+        "nonNegativeInteger" | "unsignedInt" | "unsignedLong" | "unsignedShort" | "unsignedByte" => {
+            integer() && !lexical.starts_with('-')
+        }
+        "positiveInteger" => integer() && lexical.parse::<f64>().is_ok_and(|v| v > 0.0),
+        "negativeInteger" => integer() && lexical.parse::<f64>().is_ok_and(|v| v < 0.0),
+        "nonPositiveInteger" => integer() && lexical.parse::<f64>().is_ok_and(|v| v <= 0.0),
+        "decimal" => {
+            let digits = lexical.strip_prefix(['+', '-']).unwrap_or(lexical);
+            digits.chars().any(|c| c.is_ascii_digit()) && digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+                && digits.matches('.').count() <= 1
+        }
+        "double" | "float" => lexical.parse::<f64>().is_ok() || ["INF", "-INF", "NaN"].contains(&lexical),
+        "boolean" => ["true", "false", "1", "0"].contains(&lexical),
+        "date" => XSD_DATE.is_match(lexical),
+        "dateTime" => XSD_DATE_TIME.is_match(lexical),
+        "string" | "anyURI" | "normalizedString" | "token" => true,
+        _ => return None,
+    })
+}
+
+/// Whether a term is a literal of `datatype`. Datatypes are dropped from
+/// most stored literals, so a literal without one matches every datatype its
+/// lexical form is valid for.
+fn has_datatype(term: &str, datatype: &str) -> bool {
+    if node_kind(term) != NodeKind::Literal {
+        return false;
+    }
+    match literal_parts(term) {
+        (_, _, Some(_)) => datatype == rdf("langString"),
+        (lexical, Some(explicit), None) => {
+            explicit == datatype && valid_lexical_form(lexical, datatype).unwrap_or(true)
+        }
+        (lexical, None, None) => valid_lexical_form(lexical, datatype).unwrap_or(false),
+    }
+}
+
+/// Whether two terms are the same RDF term, comparing literals by value
+fn same_term(a: &str, b: &str) -> bool {
+    match (node_kind(a), node_kind(b)) {
+        (NodeKind::Literal, NodeKind::Literal) => {
+            let ((la, _, lang_a), (lb, _, lang_b)) = (literal_parts(a), literal_parts(b));
+            la == lb && lang_a == lang_b
+        }
+        (kind_a, kind_b) => kind_a == kind_b && a == b,
+    }
+}
+
+/// Order of two literals for the range constraints: numbers by value, other
+/// values (such as dates) by their lexical form
+fn compare(a: &str, b: &str) -> Option<Ordering> {
+    if node_kind(a) != NodeKind::Literal || node_kind(b) != NodeKind::Literal {
+        return None;
+    }
+    let ((la, _, _), (lb, _, _)) = (literal_parts(a), literal_parts(b));
+    match (numeric_value(la), numeric_value(lb)) {
+        (Some(x), Some(y)) => x.partial_cmp(&y),
+        (None, None) => Some(la.cmp(lb)),
+        _ => None,
+    }
+}
+
+/// SHACL property path
+#[derive(Debug, Clone, PartialEq)]
+pub enum Path {
+    Predicate(String),
+    Inverse(Box<Path>),
+    Sequence(Vec<Path>),
+    Alternative(Vec<Path>),
+    ZeroOrMore(Box<Path>),
+    OneOrMore(Box<Path>),
+    ZeroOrOne(Box<Path>),
+}
+
+impl Path {
+    fn parse(shapes: &Graph, node: &str) -> Result<Path, String> {
+        if node_kind(node) == NodeKind::Iri {
+            return Ok(Path::Predicate(node.to_string()));
+        }
+        let nested = |name: &str| shapes.object(node, &sh(name)).map(|inner| Path::parse(shapes, inner));
+        let members = || shapes.list(node).iter().map(|item| Path::parse(shapes, item)).collect::<Result<Vec<_>, _>>();
+        if let Some(inner) = nested("inversePath") {
+            Ok(Path::Inverse(Box::new(inner?)))
+        } else if let Some(alternatives) = shapes.object(node, &sh("alternativePath")) {
+            let items = shapes.list(alternatives);
+            Ok(Path::Alternative(items.iter().map(|item| Path::parse(shapes, item)).collect::<Result<_, _>>()?))
+        } else if let Some(inner) = nested("zeroOrMorePath") {
+            Ok(Path::ZeroOrMore(Box::new(inner?)))
+        } else if let Some(inner) = nested("oneOrMorePath") {
+            Ok(Path::OneOrMore(Box::new(inner?)))
+        } else if let Some(inner) = nested("zeroOrOnePath") {
+            Ok(Path::ZeroOrOne(Box::new(inner?)))
+        } else if shapes.object(node, &rdf("first")).is_some() {
+            Ok(Path::Sequence(members()?))
+        } else {
+            Err(format!("unsupported property path {}", node))
+        }
+    }
+
+    /// Nodes reached from `focus` over the path, without duplicates
+    fn values(&self, data: &DataGraph, focus: &str) -> Vec<String> {
+        let mut values = Vec::new();
+        for value in self.reach(data, focus) {
+            if !values.contains(&value) {
+                values.push(value);
+            }
+        }
+        values
+    }
+
+    fn reach(&self, data: &DataGraph, focus: &str) -> Vec<String> {
+        let step = |path: &Path, nodes: &[String]| -> Vec<String> {
+            nodes.iter().flat_map(|node| path.reach(data, node)).collect()
+        };
+        let closure = |path: &Path, start: Vec<String>| {
+            let mut seen: Vec<String> = Vec::new();
+            let mut queue = VecDeque::from(start);
+            while let Some(node) = queue.pop_front() {
+                if !seen.contains(&node) {
+                    queue.extend(path.reach(data, &node));
+                    seen.push(node);
+                }
+            }
+            seen
+        };
+        match self {
+            Path::Predicate(p) => data.objects(focus, p),
+            Path::Inverse(inner) => match inner.as_ref() {
+                Path::Predicate(p) => data.subjects(p, focus),
+                // Only inverses of predicates are evaluated backwards
+                _ => Vec::new(),
+            },
+            Path::Sequence(steps) => steps.iter().fold(vec![focus.to_string()], |nodes, path| step(path, &nodes)),
+            Path::Alternative(paths) => paths.iter().flat_map(|path| path.reach(data, focus)).collect(),
+            Path::ZeroOrMore(inner) => closure(inner, vec![focus.to_string()]),
+            Path::OneOrMore(inner) => closure(inner, inner.reach(data, focus)),
+            Path::ZeroOrOne(inner) => std::iter::once(focus.to_string()).chain(inner.reach(data, focus)).collect(),
+        }
+    }
+
+    /// Triples describing the path, with `node` standing for it
+    fn describe(&self, node: &str, triples: &mut Vec<(String, String, String)>) -> String {
+        let nested = |name: &str, inner: &Path, triples: &mut Vec<(String, String, String)>| {
+            let inner = inner.describe(&format!("{}_0", node), triples);
+            triples.push((node.to_string(), sh(name), inner));
+            node.to_string()
+        };
+        match self {
+            Path::Predicate(p) => p.clone(),
+            Path::Inverse(inner) => nested("inversePath", inner, triples),
+            Path::ZeroOrMore(inner) => nested("zeroOrMorePath", inner, triples),
+            Path::OneOrMore(inner) => nested("oneOrMorePath", inner, triples),
+            Path::ZeroOrOne(inner) => nested("zeroOrOnePath", inner, triples),
+            Path::Sequence(paths) => describe_list(node, paths, triples),
+            Path::Alternative(paths) => {
+                let list = describe_list(&format!("{}_list", node), paths, triples);
+                triples.push((node.to_string(), sh("alternativePath"), list));
+                node.to_string()
+            }
+        }
+    }
+}
+
+fn describe_list(node: &str, paths: &[Path], triples: &mut Vec<(String, String, String)>) -> String {
+    let mut list = rdf("nil");
+    for (i, path) in paths.iter().enumerate().rev() {
+        let cell = format!("{}_{}", node, i);
+        let item = path.describe(&format!("{}_item", cell), triples);
+        triples.push((cell.clone(), rdf("first"), item));
+        triples.push((cell.clone(), rdf("rest"), list));
+        list = cell;
+    }
+    list
+}
+
+/// One `sh:ValidationResult` of a report
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationResult {
+    pub focus_node: String,
+    pub result_path: Option<Path>,
+    pub value: Option<String>,
+    pub source_shape: String,
+    /// Local name of the constraint component, e.g. `MinCountConstraintComponent`
+    pub source_constraint_component: String,
+    /// Severity IRI, `sh:Violation` unless the shape sets `sh:severity`
+    pub severity: String,
+    pub message: String,
+}
+
+/// Outcome of validating a data graph against a shapes graph
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationReport {
+    pub conforms: bool,
+    pub results: Vec<ValidationResult>,
+}
+
+impl ValidationReport {
+    /// The report as an RDF graph of one `sh:ValidationReport`, with terms as
+    /// stored in the dictionary (IRIs without brackets, literals without
+    /// quotes). Blank nodes are labelled `_:report`, `_:result0`, ...
+    pub fn to_triples(&self) -> Vec<(String, String, String)> {
+        let report = "_:report".to_string();
+        let mut triples = vec![
+            (report.clone(), rdf("type"), sh("ValidationReport")),
+            (report.clone(), sh("conforms"), self.conforms.to_string()),
+        ];
+        for (i, result) in self.results.iter().enumerate() {
+            let node = format!("_:result{}", i);
+            triples.push((report.clone(), sh("result"), node.clone()));
+            triples.push((node.clone(), rdf("type"), sh("ValidationResult")));
+            triples.push((node.clone(), sh("focusNode"), result.focus_node.clone()));
+            if let Some(path) = &result.result_path {
+                let path = path.describe(&format!("_:result{}_path", i), &mut triples);
+                triples.push((node.clone(), sh("resultPath"), path));
+            }
+            if let Some(value) = &result.value {
+                triples.push((node.clone(), sh("value"), value.clone()));
+            }
+            triples.push((node.clone(), sh("sourceShape"), result.source_shape.clone()));
+            triples.push((node.clone(), sh("sourceConstraintComponent"), sh(&result.source_constraint_component)));
+            triples.push((node.clone(), sh("resultSeverity"), result.severity.clone()));
+            triples.push((node.clone(), sh("resultMessage"), result.message.clone()));
+        }
+        triples
+    }
+
+    /// The report in Turtle
+    pub fn to_turtle(&self) -> String {
+        let term = |term: &str| match node_kind(term) {
+            NodeKind::Iri => match term.strip_prefix(SH) {
+                Some(local) => format!("sh:{}", local),
+                None => format!("<{}>", term),
+            },
+            NodeKind::BlankNode => term.to_string(),
+            NodeKind::Literal => {
+                let (lexical, datatype, language) = literal_parts(term);
+                let quoted = format!("\"{}\"", lexical.replace('\\', "\\\\").replace('"', "\\\""));
+                match (datatype, language) {
+                    (Some(datatype), _) => format!("{}^^<{}>", quoted, datatype),
+                    (None, Some(language)) => format!("{}@{}", quoted, language),
+                    _ => quoted,
+                }
+            }
+        };
+        let mut output = format!("@prefix sh: <{}> .\n\n", SH);
+        for (s, p, o) in self.to_triples() {
+            let p = if p == rdf("type") { "a".to_string() } else { term(&p) };
+            // sh:conforms is a boolean, not a string
+            let o = if p == "sh:conforms" { o } else { term(&o) };
+            output.push_str(&format!("{} {} {} .\n", term(&s), p, o));
+        }
+        output
+    }
+
+    /// Adds the report to `database`, so it can be queried with SPARQL
+    pub fn add_to(&self, database: &mut SparqlDatabase) {
+        for (s, p, o) in self.to_triples() {
+            database.add_triple_parts(&s, &p, &o);
+        }
+    }
+}
+
+/// A SHACL shapes graph, validated against data with [`ShapesGraph::validate`].
+///
+/// Supports node and property shapes with `sh:targetClass`, `sh:targetNode`,
+/// `sh:targetSubjectsOf`, `sh:targetObjectsOf` and implicit class targets;
+/// all SHACL property paths; and the core constraints `sh:class`,
+/// `sh:datatype`, `sh:nodeKind`, `sh:minCount`, `sh:maxCount`,
+/// `sh:minInclusive`, `sh:maxInclusive`, `sh:minExclusive`,
+/// `sh:maxExclusive`, `sh:minLength`, `sh:maxLength`, `sh:pattern` (with
+/// `sh:flags`), `sh:in`, `sh:hasValue`, `sh:node`, `sh:not`, `sh:and`,
+/// `sh:or`, `sh:xone` and `sh:closed`. Shapes with `sh:deactivated true` are
+/// skipped, and `sh:severity` and `sh:message` carry over to the results.
+#[derive(Debug, Clone, Default)]
+pub struct ShapesGraph {
+    graph: Graph,
+}
+
+impl ShapesGraph {
+    /// Parses a shapes graph written in Turtle, keeping the datatypes of its
+    /// literals
+    pub fn parse(turtle: &str) -> Result<Self, String> {
+        let mut graph = Graph::default();
+        for [subject, predicate, object] in parse_turtle_strictly(turtle, &mut HashMap::new())? {
+            graph.insert(turtle_term(&subject), turtle_term(&predicate), turtle_term(&object));
+        }
+        Ok(Self { graph })
+    }
+
+    /// Shapes graph made of the triples of `database`, for shapes loaded with
+    /// its parsers
+    pub fn from_database(database: &SparqlDatabase) -> Self {
+        let mut graph = Graph::default();
+        for triple in &database.triples {
+            if let Some((s, p, o)) = database.decode_triple(triple) {
+                graph.insert(s, p, o);
+            }
+        }
+        Self { graph }
+    }
+
+    /// Shapes with at least one target, in a stable order
+    fn targeted_shapes(&self) -> Vec<String> {
+        let mut shapes: Vec<String> = ["targetClass", "targetNode", "targetSubjectsOf", "targetObjectsOf"]
+            .iter()
+            .flat_map(|target| self.graph.pairs(&sh(target)).map(|(shape, _)| shape.to_string()).collect::<Vec<_>>())
+            .chain(
+                [sh("NodeShape"), sh("PropertyShape")]
+                    .iter()
+                    .flat_map(|kind| self.graph.subjects(&rdf("type"), kind).to_vec())
+                    .filter(|shape| self.graph.objects(shape, &rdf("type")).iter().any(|t| t == RDFS_CLASS)),
+            )
+            .collect();
+        shapes.sort();
+        shapes.dedup();
+        shapes
+    }
+
+    fn focus_nodes(&self, shape: &str, data: &DataGraph) -> Vec<String> {
+        let mut nodes: Vec<String> = self.graph.objects(shape, &sh("targetNode")).to_vec();
+        for class in self.graph.objects(shape, &sh("targetClass")) {
+            nodes.extend(data.instances(class));
+        }
+        if self.graph.objects(shape, &rdf("type")).iter().any(|t| t == RDFS_CLASS) {
+            nodes.extend(data.instances(shape));
+        }
+        for predicate in self.graph.objects(shape, &sh("targetSubjectsOf")) {
+            nodes.extend(data.ends(predicate, true));
+        }
+        for predicate in self.graph.objects(shape, &sh("targetObjectsOf")) {
+            nodes.extend(data.ends(predicate, false));
+        }
+        nodes.sort();
+        nodes.dedup();
+        nodes
+    }
+
+    /// Validates the triples of `database`, read through its dictionary and
+    /// `index_manager`. Returns `Err` when a shape is malformed, e.g. has an
+    /// unsupported path or an invalid `sh:pattern`.
+    ///
+    /// The parsers of `SparqlDatabase` drop the datatype of most literals,
+    /// so `sh:datatype` accepts a literal stored without one for every
+    /// datatype its lexical form is valid for: `"42"` conforms to both
+    /// `xsd:integer` and `xsd:string`, `"2024-01-31"` to `xsd:date`.
+    /// Literals keeping a language tag only conform to `rdf:langString`.
+    /// Data validated with [`ShapesGraph::validate_turtle`] keeps its
+    /// datatypes, which are then checked as written.
+    pub fn validate(&self, database: &SparqlDatabase) -> Result<ValidationReport, String> {
+        let data = DataGraph::new(database);
+        let validator = Validator { shapes: &self.graph, data: &data };
+        let mut results = Vec::new();
+        for shape in self.targeted_shapes() {
+            for focus in self.focus_nodes(&shape, &data) {
+                validator.validate_shape(&shape, &focus, 0, &mut results)?;
+            }
+        }
+        Ok(ValidationReport { conforms: results.is_empty(), results })
+    }
+
+    /// Validates a data graph written in Turtle. Its literals keep their
+    /// datatypes, numbers and booleans included, so `"42"^^xsd:string` does
+    /// not conform to `sh:datatype xsd:integer`.
+    pub fn validate_turtle(&self, turtle: &str) -> Result<ValidationReport, String> {
+        let mut database = SparqlDatabase::new();
+        for [subject, predicate, object] in parse_turtle_strictly(turtle, &mut database.prefixes)? {
+            let triple = Triple {
+                subject: encode_turtle_term(&database, &subject),
+                predicate: encode_turtle_term(&database, &predicate),
+                object: encode_turtle_term(&database, &object),
+            };
+            database.add_triple(triple);
+        }
+        self.validate(&database)
+    }
+}
+
+/// Triples of a Turtle document, or the error of its first invalid statement
+fn parse_turtle_strictly(turtle: &str, prefixes: &mut HashMap<String, String>) -> Result<Vec<TurtleTriple>, String> {
+    let (triples, errors) = turtle::parse_turtle(turtle, prefixes);
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(triples),
+    }
+}
+
+/// A parsed term as the shapes graph holds it, quoted triples as `<< s p o >>`
+fn turtle_term(term: &TurtleTerm) -> String {
+    match term {
+        TurtleTerm::Term(term) => term.clone(),
+        TurtleTerm::Quoted(triple) => {
+            let [subject, predicate, object] = triple.as_ref();
+            format!("<< {} {} {} >>", turtle_term(subject), turtle_term(predicate), turtle_term(object))
+        }
+    }
+}
+
+fn encode_turtle_term(database: &SparqlDatabase, term: &TurtleTerm) -> u32 {
+    match term {
+        TurtleTerm::Term(term) => database.dictionary.write().unwrap().encode(term),
+        TurtleTerm::Quoted(triple) => {
+            let [subject, predicate, object] = triple.as_ref();
+            let (s_id, p_id, o_id) = (
+                encode_turtle_term(database, subject),
+                encode_turtle_term(database, predicate),
+                encode_turtle_term(database, object),
+            );
+            database.quoted_triple_store.write().unwrap().encode(s_id, p_id, o_id)
+        }
+    }
+}
+
+/// Parameter name, constraint component and the order a value must have
+/// relative to the bound
+type RangeConstraint = (&'static str, &'static str, fn(Ordering) -> bool);
+
+struct Validator<'a> {
+    shapes: &'a Graph,
+    data: &'a DataGraph<'a>,
+}
+
+impl Validator<'_> {
+    fn conforms(&self, shape: &str, focus: &str, depth: usize) -> Result<bool, String> {
+        let mut results = Vec::new();
+        self.validate_shape(shape, focus, depth + 1, &mut results)?;
+        Ok(results.is_empty())
+    }
+
+    fn validate_shape(
+        &self,
+        shape: &str,
+        focus: &str,
+        depth: usize,
+        results: &mut Vec<ValidationResult>,
+    ) -> Result<(), String> {
+        let param = |name: &str| self.shapes.objects(shape, &sh(name));
+        if depth > MAX_SHAPE_DEPTH || param("deactivated").iter().any(|v| lexical_form(v) == "true") {
+            return Ok(());
+        }
+        let path = match param("path").first() {
+            Some(path) => Some(Path::parse(self.shapes, path)?),
+            None => None,
+        };
+        let values = match &path {
+            Some(path) => path.values(self.data, focus),
+            None => vec![focus.to_string()],
+        };
+        let mut report = |component: &str, value: Option<&str>, message: String| {
+            results.push(ValidationResult {
+                focus_node: focus.to_string(),
+                result_path: path.clone(),
+                value: value.map(str::to_string),
+                source_shape: shape.to_string(),
+                source_constraint_component: format!("{}ConstraintComponent", component),
+                severity: param("severity").first().cloned().unwrap_or_else(|| sh("Violation")),
+                message: param("message").first().map_or(message, |m| lexical_form(m).to_string()),
+            })
+        };
+
+        // Constraints on the number of value nodes
+        if let Some(min) = param("minCount").first().and_then(|v| numeric_value(v)) {
+            if (values.len() as f64) < min {
+                report("MinCount", None, format!("expected at least {} value(s), found {}", min, values.len()));
+            }
+        }
+        if let Some(max) = param("maxCount").first().and_then(|v| numeric_value(v)) {
+            if values.len() as f64 > max {
+                report("MaxCount", None, format!("expected at most {} value(s), found {}", max, values.len()));
+            }
+        }
+        for expected in param("hasValue") {
+            if !values.iter().any(|v| same_term(v, expected)) {
+                report("HasValue", None, format!("missing the value {}", expected));
+            }
+        }
+
+        let flags = param("flags").first().map_or("", |f| lexical_form(f));
+        let patterns = param("pattern")
+            .iter()
+            .map(|pattern| {
+                RegexBuilder::new(lexical_form(pattern))
+                    .case_insensitive(flags.contains('i'))
+                    .multi_line(flags.contains('m'))
+                    .dot_matches_new_line(flags.contains('s'))
+                    .ignore_whitespace(flags.contains('x'))
+                    .build()
+                    .map(|regex| (lexical_form(pattern), regex))
+                    .map_err(|e| format!("invalid sh:pattern {}: {}", pattern, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Constraints on each value node
+        for value in &values {
+            for class in param("class") {
+                if !self.data.is_instance(value, class) {
+                    report("Class", Some(value), format!("{} is not an instance of {}", value, class));
+                }
+            }
+            for datatype in param("datatype") {
+                if !has_datatype(value, datatype) {
+                    report("Datatype", Some(value), format!("{} is not a literal of datatype {}", value, datatype));
+                }
+            }
+            for kind in param("nodeKind") {
+                let allowed: &[NodeKind] = match kind.strip_prefix(SH).unwrap_or("") {
+                    "IRI" => &[NodeKind::Iri],
+                    "BlankNode" => &[NodeKind::BlankNode],
+                    "Literal" => &[NodeKind::Literal],
+                    "BlankNodeOrIRI" => &[NodeKind::BlankNode, NodeKind::Iri],
+                    "BlankNodeOrLiteral" => &[NodeKind::BlankNode, NodeKind::Literal],
+                    "IRIOrLiteral" => &[NodeKind::Iri, NodeKind::Literal],
+                    _ => return Err(format!("unknown sh:nodeKind {}", kind)),
+                };
+                if !allowed.contains(&node_kind(value)) {
+                    report("NodeKind", Some(value), format!("{} is not of node kind {}", value, kind));
+                }
+            }
+            let ranges: [RangeConstraint; 4] = [
+                ("minInclusive", "MinInclusive", Ordering::is_ge),
+                ("maxInclusive", "MaxInclusive", Ordering::is_le),
+                ("minExclusive", "MinExclusive", Ordering::is_gt),
+                ("maxExclusive", "MaxExclusive", Ordering::is_lt),
+            ];
+            for (name, component, holds) in ranges {
+                for bound in param(name) {
+                    if !compare(value, bound).is_some_and(holds) {
+                        report(component, Some(value), format!("{} violates {} {}", value, name, lexical_form(bound)));
+                    }
+                }
+            }
+            let length = (node_kind(value) != NodeKind::BlankNode).then(|| lexical_form(value).chars().count());
+            for min in param("minLength").iter().filter_map(|v| numeric_value(v)) {
+                if !length.is_some_and(|len| len as f64 >= min) {
+                    report("MinLength", Some(value), format!("{} is shorter than {} characters", value, min));
+                }
+            }
+            for max in param("maxLength").iter().filter_map(|v| numeric_value(v)) {
+                if !length.is_some_and(|len| len as f64 <= max) {
+                    report("MaxLength", Some(value), format!("{} is longer than {} characters", value, max));
+                }
+            }
+            for (pattern, regex) in &patterns {
+                if node_kind(value) == NodeKind::BlankNode || !regex.is_match(lexical_form(value)) {
+                    report("Pattern", Some(value), format!("{} does not match {}", value, pattern));
+                }
+            }
+            for list in param("in") {
+                if !self.shapes.list(list).iter().any(|member| same_term(value, member)) {
+                    report("In", Some(value), format!("{} is not one of the allowed values", value));
+                }
+            }
+
+            // Logical constraints and shape references
+            for other in param("node") {
+                if !self.conforms(other, value, depth)? {
+                    report("Node", Some(value), format!("{} does not conform to shape {}", value, other));
+                }
+            }
+            for other in param("not") {
+                if self.conforms(other, value, depth)? {
+                    report("Not", Some(value), format!("{} conforms to shape {}", value, other));
+                }
+            }
+            for (name, component) in [("and", "And"), ("or", "Or"), ("xone", "Xone")] {
+                for list in param(name) {
+                    let mut conforming = 0;
+                    let members = self.shapes.list(list);
+                    for member in &members {
+                        conforming += self.conforms(member, value, depth)? as usize;
+                    }
+                    let holds = match name {
+                        "and" => conforming == members.len(),
+                        "or" => conforming > 0,
+                        _ => conforming == 1,
+                    };
+                    if !holds {
+                        let message = format!("{} conforms to {} of {} shapes in sh:{}", value, conforming, members.len(), name);
+                        report(component, Some(value), message);
+                    }
+                }
+            }
+        }
+
+        if path.is_none() && param("closed").iter().any(|v| lexical_form(v) == "true") {
+            let mut allowed: HashSet<String> = param("ignoredProperties")
+                .iter()
+                .flat_map(|list| self.shapes.list(list))
+                .collect();
+            for property in param("property") {
+                if let Some(Path::Predicate(p)) =
+                    self.shapes.object(property, &sh("path")).map(|p| Path::parse(self.shapes, p)).transpose()?
+                {
+                    allowed.insert(p);
+                }
+            }
+            let unexpected = self.data.outgoing(focus).into_iter().filter(|(predicate, _)| !allowed.contains(predicate));
+            for (predicate, object) in unexpected {
+                results.push(ValidationResult {
+                    focus_node: focus.to_string(),
+                    result_path: Some(Path::Predicate(predicate.clone())),
+                    value: Some(object),
+                    source_shape: shape.to_string(),
+                    source_constraint_component: "ClosedConstraintComponent".to_string(),
+                    severity: param("severity").first().cloned().unwrap_or_else(|| sh("Violation")),
+                    message: format!("{} is not allowed by the closed shape", predicate),
+                });
+            }
+        }
+
+        for property in param("property") {
+            self.validate_shape(property, focus, depth + 1, results)?;
+        }
+        Ok(())
+    }
+}
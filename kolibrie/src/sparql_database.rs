@@ -19,6 +19,10 @@ use crate::utils::ClonableFn;
 use crate::cuda::cuda_join::*;
 use shared::index_manager::UnifiedIndex;
 use crate::query_builder::QueryBuilder;
+use crate::plan_cache::PlanCache;
+use crate::prepared_query::PreparedQuery;
+use crate::shacl::{ShapesGraph, ValidationReport};
+use crate::query_context::OperatorBudget;
 use crossbeam::channel::unbounded;
 use crossbeam::scope;
 use percent_encoding::percent_decode;
@@ -34,7 +38,9 @@ use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::sync::Arc;
 use std::sync::{Mutex, RwLock};
 use url::Url;
-use crate::streamertail_optimizer::DatabaseStats;
+use crate::streamertail_optimizer::{DatabaseStats, QueryExplanation};
+use datalog::reasoning::entailment::EntailmentRegime;
+use datalog::reasoning::Reasoner;
 
 const MIN_CHUNK_SIZE: usize = 1024;
 const HASHMAP_INITIAL_CAPACITY: usize = 4096;
@@ -58,6 +64,7 @@ pub struct SparqlDatabase {
     pub ml_predict_materialized_triples: HashMap<String, Vec<Triple>>,
     pub probability_seeds: HashMap<Triple, f64>,
     pub cached_stats: Option<Arc<DatabaseStats>>,
+    pub plan_cache: PlanCache,
     pub quoted_triple_store: Arc<RwLock<QuotedTripleStore>>,
 }
 
@@ -79,6 +86,7 @@ impl SparqlDatabase {
             ml_predict_materialized_triples: HashMap::new(),
             probability_seeds: HashMap::new(),
             cached_stats: None,
+            plan_cache: PlanCache::default(),
             quoted_triple_store: Arc::new(RwLock::new(QuotedTripleStore::new())),
         }
     }
@@ -211,16 +219,55 @@ impl SparqlDatabase {
         stats
     }
     
+    /// Drops the cached statistics, and the cached query plans too when the
+    /// data changed significantly since they were made
     pub fn invalidate_stats_cache(&mut self) {
         self.cached_stats = None;
+        self.plan_cache.invalidate_if_changed(self.triples.len());
+    }
+
+    /// Plans a SELECT query with `$name` placeholders once, to execute it with
+    /// different values; see `PreparedQuery`
+    pub fn prepare(&mut self, sparql: &str) -> Result<PreparedQuery, String> {
+        PreparedQuery::new(sparql, self)
     }
 
     pub fn query(&self) -> QueryBuilder<'_> {
         QueryBuilder::new(self)
     }
 
+    /// Explains the plan chosen for a SPARQL query, optionally prefixed with
+    /// `EXPLAIN` or `EXPLAIN ANALYZE`; see `execute_query::explain_query`.
+    pub fn explain(&mut self, sparql: &str) -> Result<QueryExplanation, String> {
+        crate::execute_query::explain_query(sparql, self)
+    }
+
+    /// Adds the triples entailed by the stored triples under `regime` (e.g.
+    /// the types implied by `rdfs:subClassOf`), returning them. Triples loaded
+    /// later are not covered until this is called again.
+    pub fn materialize_entailment(&mut self, regime: EntailmentRegime) -> Vec<Triple> {
+        let mut reasoner = Reasoner::new();
+        reasoner.dictionary = Arc::clone(&self.dictionary);
+        for triple in self.triples.iter() {
+            reasoner.index_manager.insert(triple);
+        }
+        let entailed = reasoner.infer_new_facts_with_entailment(regime);
+        for triple in &entailed {
+            self.add_triple(triple.clone());
+        }
+        entailed
+    }
+
+    /// Validates the stored triples against a SHACL shapes graph; see
+    /// `shacl::ShapesGraph::validate`.
+    pub fn validate(&self, shapes: &ShapesGraph) -> Result<ValidationReport, String> {
+        shapes.validate(self)
+    }
+
     pub fn add_triple(&mut self, triple: Triple) {
-        self.triples.insert(triple.clone());
+        if self.triples.insert(triple.clone()) {
+            self.update_cached_stats(&triple, true);
+        }
         self.index_manager.insert(&triple);
     }
     
@@ -228,10 +275,27 @@ impl SparqlDatabase {
         let removed = self.triples.remove(triple);
         if removed {
             self.index_manager.delete(triple);
+            self.update_cached_stats(triple, false);
         }
         removed
     }
 
+    /// Keeps cached statistics in step with an added or removed triple. Stats
+    /// still shared with a running optimizer are dropped and rebuilt on demand.
+    fn update_cached_stats(&mut self, triple: &Triple, added: bool) {
+        let Some(cached) = self.cached_stats.as_mut() else {
+            return;
+        };
+        match Arc::get_mut(cached) {
+            Some(stats) if added => {
+                let dict = self.dictionary.read().unwrap();
+                stats.update_stats_with_dictionary(triple.subject, triple.predicate, triple.object, &dict);
+            }
+            Some(stats) => stats.remove_stats(triple.subject, triple.predicate, triple.object),
+            None => self.cached_stats = None,
+        }
+    }
+
     /// Helper function that accepts parts of a triple, constructs a Triple, and adds it
     pub fn add_triple_parts(&mut self, subject: &str, predicate: &str, object: &str) {
         let mut dict = self.dictionary.write().unwrap();
@@ -1004,7 +1068,7 @@ impl SparqlDatabase {
                     let line_without_dot = &line[..line.len() - 1].trim();
 
                     // Parse the triple
-                    if let Some((subject, predicate, object)) = self.parse_ntriples_line(line_without_dot) {
+                    if let Some((subject, predicate, object)) = Self::parse_ntriples_line(line_without_dot) {
                         local_triples.push((subject, predicate, object));
                     }
                 }
@@ -1037,8 +1101,19 @@ impl SparqlDatabase {
         self.encode_triples(partial_results)
     }
 
-    // Helper method to parse a single N-Triples line
-    fn parse_ntriples_line(&self, line: &str) -> Option<(String, String, String)> {
+    /// Parse a single N-Triples line (without the trailing dot) into decoded
+    /// `(subject, predicate, object)` strings, as stored in the dictionary.
+    pub(crate) fn parse_ntriples_line(line: &str) -> Option<(String, String, String)> {
+        let terms = Self::clean_ntriples_parts(&Self::split_ntriples_line(line));
+        if terms.is_none() {
+            eprintln!("Invalid N-Triples line (expected 3 terms): {}", line);
+        }
+        terms
+    }
+
+    /// Split a single N-Triples line (without the trailing dot) into its terms as
+    /// written, e.g. `<iri>` or `"lex"^^<datatype>`.
+    pub(crate) fn split_ntriples_line(line: &str) -> Vec<String> {
         let mut parts = Vec::new();
         let mut current_part = String::new();
         let mut in_uri = false;
@@ -1175,25 +1250,28 @@ impl SparqlDatabase {
         if !current_part.is_empty() {
             parts.push(current_part.trim().to_string());
         }
+        parts
+    }
 
+    /// Decode the terms returned by `split_ntriples_line`; `None` unless there are three.
+    pub(crate) fn clean_ntriples_parts(parts: &[String]) -> Option<(String, String, String)> {
         if parts.len() == 3 {
-            let subject = self.clean_ntriples_term(&parts[0]);
+            let subject = Self::clean_ntriples_term(&parts[0]);
             // Expand the Turtle `a` shorthand for rdf:type in predicate position.
             let predicate = if parts[1] == "a" {
                 "http://www.w3.org/1999/02/22-rdf-syntax-ns#type".to_string()
             } else {
-                self.clean_ntriples_term(&parts[1])
+                Self::clean_ntriples_term(&parts[1])
             };
-            let object = self.clean_ntriples_term(&parts[2]);
+            let object = Self::clean_ntriples_term(&parts[2]);
             Some((subject, predicate, object))
         } else {
-            eprintln!("Invalid N-Triples line (expected 3 parts, got {}): {}", parts.len(), line);
             None
         }
     }
 
     // Helper method to clean N-Triples terms
-    fn clean_ntriples_term(&self, term: &str) -> String {
+    pub(crate) fn clean_ntriples_term(term: &str) -> String {
         let term = term.trim();
 
         // Keep quoted triples as-is
@@ -1928,6 +2006,7 @@ impl SparqlDatabase {
             ml_predict_materialized_triples: self.ml_predict_materialized_triples.clone(),
             probability_seeds: merged_seeds,
             cached_stats: None,
+            plan_cache: PlanCache::default(),
             quoted_triple_store: Arc::clone(&self.quoted_triple_store),
         }
     }
@@ -2005,6 +2084,7 @@ impl SparqlDatabase {
             ml_predict_materialized_triples: self.ml_predict_materialized_triples.clone(),
             probability_seeds: HashMap::new(),
             cached_stats: None,
+            plan_cache: PlanCache::default(),
             quoted_triple_store: Arc::clone(&self.quoted_triple_store),
         }
     }
@@ -2073,6 +2153,7 @@ impl SparqlDatabase {
         }
 
         let dictionary = dictionary.read().unwrap();
+        let row_width = final_results[0].len() + 2;
 
         let predicate_bytes = predicate.as_bytes();
         let literal_filter_bytes = literal_filter.as_ref().map(|s| s.as_bytes());
@@ -2115,10 +2196,14 @@ impl SparqlDatabase {
 
         // Pre-allocate output vector
         let results = Mutex::new(Vec::new());
+        let budget = OperatorBudget::current(row_width);
 
         // Using Rayon for parallel processing
         triples.par_chunks(256).for_each(|chunk| {
             let mut local_results = Vec::new();
+            if !budget.scan(chunk.len()) {
+                return;
+            }
 
             for triple in chunk {
                 if let (Some(subject), Some(pred), Some(object)) = (
@@ -2210,10 +2295,14 @@ impl SparqlDatabase {
             }
 
             // Push local results to the shared results vector
+            budget.produce(local_results.len());
             let mut global_results = results.lock().unwrap();
             global_results.extend(local_results);
         });
 
+        if budget.is_aborted() {
+            return Vec::new();
+        }
         results.into_inner().unwrap()
     }
 
@@ -1,5 +1,5 @@
 use shared::{query::FilterExpression, terms::{Term, TriplePattern}};
-use crate::{container_stats::ContainerStats, streamertail_optimizer::{Condition, CostConstants, PhysicalOperator}};
+use crate::{container_stats::ContainerStats, streamertail_optimizer::{Condition, CostConstants, ExecutionProfile, PhysicalOperator}};
 use std::collections::{HashMap, HashSet};
 
 #[derive(Default, Clone)]
@@ -30,34 +30,72 @@ impl StreamEstimator {
     pub fn estimate_cost(&self, plan: &PhysicalOperator) -> Option<i64> {
         match plan {
             // Scan
-            PhysicalOperator::TableScan { pattern } => {
+            PhysicalOperator::TableScan { pattern } | PhysicalOperator::IndexScan { pattern } => {
                 Some(self.estimate_cardinality(pattern) * CostConstants::COST_PER_ROW_SCAN as i64)
             }
             // Selection
             PhysicalOperator::Filter { input, condition } => {
-                let input_cost = self.estimate_cost(input).unwrap();
+                let input_cost = self.estimate_cost(input)?;
                 let selectivity = self.estimate_selectivity(condition);
                 Some((input_cost as f64 * selectivity) as i64 + CostConstants::COST_PER_FILTER as i64)
             }
             // Projection
             PhysicalOperator::Projection { input, .. } => {
-                Some(self.estimate_cost(input).unwrap() + CostConstants::COST_PER_PROJECTION as i64)
+                Some(self.estimate_cost(input)? + CostConstants::COST_PER_PROJECTION as i64)
             }
             // Join
-            PhysicalOperator::HashJoin { left, right } => {
-                let left_cost = self.estimate_cost(left).unwrap();
-                let right_cost = self.estimate_cost(right).unwrap();
+            PhysicalOperator::HashJoin { left, right }
+            | PhysicalOperator::OptimizedHashJoin { left, right }
+            | PhysicalOperator::NestedLoopJoin { left, right }
+            | PhysicalOperator::ParallelJoin { left, right } => {
+                let left_cost = self.estimate_cost(left)?;
+                let right_cost = self.estimate_cost(right)?;
                 let join_output = self.estimate_join_output_cardinality_with_distinct(left, right);
 
+                // Saturate: cross products of large windows overflow i64
                 Some(
                 left_cost
-                    + right_cost
-                    + join_output
-                        * CostConstants::COST_PER_ROW_NESTED_LOOP as i64
+                    .saturating_add(right_cost)
+                    .saturating_add(join_output.saturating_mul(CostConstants::COST_PER_ROW_NESTED_LOOP as i64))
                 )
             }
-            // Other operators not implemented
-            _ => None 
+            // Star join: scan every pattern once, then join on the shared variable
+            PhysicalOperator::StarJoin { patterns, .. } => {
+                let scan_cost: i64 = patterns
+                    .iter()
+                    .map(|pattern| self.estimate_cardinality(pattern) * CostConstants::COST_PER_ROW_SCAN as i64)
+                    .sum();
+                Some(scan_cost + self.estimate_output_cardinality(plan) * CostConstants::COST_PER_ROW_JOIN as i64)
+            }
+            // Leapfrog join: build one sorted trie per pattern, then seek once per output row
+            PhysicalOperator::LeapfrogJoin { patterns, .. } => {
+                let trie_cost: i64 = patterns
+                    .iter()
+                    .map(|pattern| self.estimate_cardinality(pattern) * CostConstants::COST_PER_ROW_INDEX_SCAN as i64)
+                    .sum();
+                Some(trie_cost + self.estimate_output_cardinality(plan) * CostConstants::COST_PER_ROW_JOIN as i64)
+            }
+            // Materialized rows
+            PhysicalOperator::InMemoryBuffer { content, .. } => {
+                Some(content.len() as i64 * CostConstants::TUPLE_COST as i64)
+            }
+            PhysicalOperator::Values { values, .. } => {
+                Some(values.len() as i64 * CostConstants::TUPLE_COST as i64)
+            }
+            PhysicalOperator::Subquery { inner, .. } => {
+                let inner_cardinality = self.estimate_output_cardinality(inner);
+                Some(self.estimate_cost(inner)? + inner_cardinality * CostConstants::TUPLE_COST as i64)
+            }
+            // Per-row functions
+            PhysicalOperator::Bind { input, .. } => {
+                let input_cardinality = self.estimate_output_cardinality(input);
+                Some(self.estimate_cost(input)? + input_cardinality * CostConstants::COST_PER_PROJECTION as i64)
+            }
+            PhysicalOperator::MLPredict { input, input_variables, .. } => {
+                // Same model as estimator.rs: interop overhead plus a per-row, per-feature cost
+                let input_cardinality = self.estimate_output_cardinality(input);
+                Some(self.estimate_cost(input)? + 1000 + input_cardinality * 100 * input_variables.len() as i64)
+            }
         }
     }
 
@@ -164,7 +202,7 @@ impl StreamEstimator {
     /// from estimator.rs but now logical
     fn extract_predicate_from_logical(&self, plan: &PhysicalOperator) -> Option<u32> {
         match plan {
-            PhysicalOperator::TableScan { pattern } => {
+            PhysicalOperator::TableScan { pattern } | PhysicalOperator::IndexScan { pattern } => {
                 if let Term::Constant(pred_id) = pattern.1 {
                     Some(pred_id)
                 } else {
@@ -196,28 +234,55 @@ impl StreamEstimator {
         roles: &mut HashMap<String, VariableRoleStats>,
     ) {
         match plan {
-            PhysicalOperator::TableScan { pattern } => {
-                if let Term::Variable(name) = &pattern.0 {
-                    let entry = roles.entry(name.clone()).or_default();
-                    entry.subject_occurrences += 1;
-                }
-                if let Term::Variable(name) = &pattern.1 {
-                    let entry = roles.entry(name.clone()).or_default();
-                    entry.predicate_occurrences += 1;
-                }
-                if let Term::Variable(name) = &pattern.2 {
-                    let entry = roles.entry(name.clone()).or_default();
-                    entry.object_occurrences += 1;
+            PhysicalOperator::TableScan { pattern } | PhysicalOperator::IndexScan { pattern } => {
+                Self::collect_pattern_roles(pattern, roles);
+            }
+            PhysicalOperator::StarJoin { patterns, .. } | PhysicalOperator::LeapfrogJoin { patterns, .. } => {
+                for pattern in patterns {
+                    Self::collect_pattern_roles(pattern, roles);
                 }
             }
             PhysicalOperator::Filter { input, .. } => self.collect_variable_roles(input, roles),
             PhysicalOperator::Projection { input, .. } => self.collect_variable_roles(input, roles),
-            PhysicalOperator::HashJoin { left, right } => {
+            PhysicalOperator::HashJoin { left, right }
+            | PhysicalOperator::OptimizedHashJoin { left, right }
+            | PhysicalOperator::NestedLoopJoin { left, right }
+            | PhysicalOperator::ParallelJoin { left, right } => {
                 self.collect_variable_roles(left, roles);
                 self.collect_variable_roles(right, roles);
             }
             PhysicalOperator::Subquery { inner, .. } => self.collect_variable_roles(inner, roles),
-            _ => {}
+            // Variables without a triple position: distinct counts fall back to cardinality
+            PhysicalOperator::Bind { input, output_variable, .. }
+            | PhysicalOperator::MLPredict { input, output_variable, .. } => {
+                self.collect_variable_roles(input, roles);
+                roles.entry(output_variable.trim_start_matches('?').to_string()).or_default();
+            }
+            PhysicalOperator::Values { variables, .. } => {
+                for variable in variables {
+                    roles.entry(variable.trim_start_matches('?').to_string()).or_default();
+                }
+            }
+            PhysicalOperator::InMemoryBuffer { content, .. } => {
+                for variable in content.iter().flat_map(|row| row.keys()) {
+                    roles.entry(variable.clone()).or_default();
+                }
+            }
+        }
+    }
+
+    fn collect_pattern_roles(pattern: &TriplePattern, roles: &mut HashMap<String, VariableRoleStats>) {
+        if let Term::Variable(name) = &pattern.0 {
+            let entry = roles.entry(name.clone()).or_default();
+            entry.subject_occurrences += 1;
+        }
+        if let Term::Variable(name) = &pattern.1 {
+            let entry = roles.entry(name.clone()).or_default();
+            entry.predicate_occurrences += 1;
+        }
+        if let Term::Variable(name) = &pattern.2 {
+            let entry = roles.entry(name.clone()).or_default();
+            entry.object_occurrences += 1;
         }
     }
 
@@ -290,17 +355,99 @@ impl StreamEstimator {
     /// based on same method in estimator.rs
     pub fn estimate_output_cardinality(&self, plan: &PhysicalOperator) -> i64 {
         match plan {
-            PhysicalOperator::TableScan { pattern } => self.estimate_cardinality(pattern),
+            PhysicalOperator::TableScan { pattern } | PhysicalOperator::IndexScan { pattern } => {
+                self.estimate_cardinality(pattern)
+            }
             PhysicalOperator::Filter { input, condition } => {
                 let input_cardinality = self.estimate_output_cardinality(input);
                 let selectivity = self.estimate_selectivity(condition);
                 ((input_cardinality as f64 * selectivity) as i64).max(1)
             }
-            PhysicalOperator::HashJoin { left, right } => {
+            PhysicalOperator::HashJoin { left, right }
+            | PhysicalOperator::OptimizedHashJoin { left, right }
+            | PhysicalOperator::NestedLoopJoin { left, right }
+            | PhysicalOperator::ParallelJoin { left, right } => {
                 self.estimate_join_output_cardinality_with_distinct(left, right)
             }
-            PhysicalOperator::Projection { input, .. } => self.estimate_output_cardinality(input),
-            _ => 0
+            // Every pattern shares the join variable; the most selective one bounds the output.
+            // The same bound is used for the cyclic patterns of a leapfrog join.
+            PhysicalOperator::StarJoin { patterns, .. } | PhysicalOperator::LeapfrogJoin { patterns, .. } => patterns
+                .iter()
+                .map(|pattern| self.estimate_cardinality(pattern))
+                .min()
+                .unwrap_or(0),
+            PhysicalOperator::Projection { input, .. }
+            | PhysicalOperator::Bind { input, .. }
+            | PhysicalOperator::MLPredict { input, .. } => self.estimate_output_cardinality(input),
+            PhysicalOperator::Subquery { inner, .. } => self.estimate_output_cardinality(inner),
+            PhysicalOperator::InMemoryBuffer { content, .. } => content.len() as i64,
+            PhysicalOperator::Values { values, .. } => values.len() as i64,
         }
     }
-}
\ No newline at end of file
+
+    /// Largest q-error (max of estimate/actual and actual/estimate) over the operators
+    /// of an executed plan that `profile` has a size for. Returns None when there are none.
+    pub fn max_q_error(&self, plan: &PhysicalOperator, profile: &ExecutionProfile) -> Option<f64> {
+        let children: Vec<&PhysicalOperator> = match plan {
+            PhysicalOperator::Filter { input, .. }
+            | PhysicalOperator::Projection { input, .. }
+            | PhysicalOperator::Bind { input, .. }
+            | PhysicalOperator::MLPredict { input, .. } => vec![input],
+            PhysicalOperator::Subquery { inner, .. } => vec![inner],
+            PhysicalOperator::HashJoin { left, right }
+            | PhysicalOperator::OptimizedHashJoin { left, right }
+            | PhysicalOperator::NestedLoopJoin { left, right }
+            | PhysicalOperator::ParallelJoin { left, right } => vec![left, right],
+            _ => Vec::new(),
+        };
+
+        let own = profile.rows(plan).map(|actual| {
+            let estimated = self.estimate_output_cardinality(plan).max(1) as f64;
+            let actual = actual.max(1) as f64;
+            (estimated / actual).max(actual / estimated)
+        });
+
+        children
+            .into_iter()
+            .filter_map(|child| self.max_q_error(child, profile))
+            .chain(own)
+            .reduce(f64::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sparql_database::SparqlDatabase;
+    use crate::streamertail_optimizer::ExecutionEngine;
+    use shared::triple::Triple;
+
+    #[test]
+    fn q_error_compares_estimates_with_executed_sizes() {
+        let mut database = SparqlDatabase::new();
+        let predicate = database.dictionary.write().unwrap().encode("http://example.org/value");
+        for subject in 0..4 {
+            let subject = database.dictionary.write().unwrap().encode(&format!("http://example.org/s{}", subject));
+            database.add_triple(Triple { subject, predicate, object: subject });
+        }
+        let plan = PhysicalOperator::Projection {
+            input: Box::new(PhysicalOperator::TableScan {
+                pattern: (Term::Variable("s".into()), Term::Constant(predicate), Term::Variable("o".into())),
+            }),
+            variables: vec!["s".into()],
+        };
+        let (results, profile) = ExecutionEngine::execute_profiled(&plan, &mut database);
+        assert_eq!(results.len(), 4);
+        assert_eq!(profile.rows(&plan), Some(4));
+
+        // Statistics from a window twice the size of what the plan ran on.
+        let mut stats = ContainerStats::new();
+        stats.total_triples = 8;
+        stats.predicate_cardinalities.insert(predicate, 8);
+        assert_eq!(StreamEstimator::new(stats).max_q_error(&plan, &profile), Some(2.0));
+
+        let mut accurate = ContainerStats::new();
+        accurate.predicate_cardinalities.insert(predicate, 4);
+        assert_eq!(StreamEstimator::new(accurate).max_q_error(&plan, &profile), Some(1.0));
+    }
+}
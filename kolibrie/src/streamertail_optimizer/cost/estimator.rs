@@ -9,6 +9,7 @@
  */
 
 use super::super::operators::PhysicalOperator;
+use super::super::stats::histogram::parse_numeric_literal;
 use super::super::stats::DatabaseStats;
 use super::super::types::Condition;
 use shared::terms::{Term, TriplePattern};
@@ -61,7 +62,7 @@ impl<'a> CostEstimator<'a> {
             }
             PhysicalOperator::Filter { input, condition } => {
                 let input_cost = self.estimate_cost(input);
-                let selectivity = self.estimate_selectivity(condition);
+                let selectivity = self.estimate_filter_selectivity(&condition.expression, Some(input));
                 (input_cost as f64 * selectivity) as u64 + CostConstants::COST_PER_FILTER
             }
             PhysicalOperator::OptimizedHashJoin { left, right } => {
@@ -133,6 +134,16 @@ impl<'a> CostEstimator<'a> {
 
                 base_cost + filter_cost
             }
+            PhysicalOperator::LeapfrogJoin { patterns, .. } => {
+                // Cost = materialize every pattern's trie once + one seek per output row
+                let trie_cost = patterns
+                    .iter()
+                    .map(|p| self.estimate_cardinality(p))
+                    .sum::<u64>()
+                    * CostConstants::COST_PER_ROW_INDEX_SCAN;
+
+                trie_cost + self.estimate_output_cardinality(plan) * CostConstants::COST_PER_ROW_JOIN
+            }
             PhysicalOperator::InMemoryBuffer { .. } => {0}
             PhysicalOperator::Subquery { inner, projected_vars } => {
                 let inner_cost = self.estimate_cost(inner);
@@ -257,13 +268,19 @@ impl<'a> CostEstimator<'a> {
 
     /// Estimates the selectivity of a condition
     pub fn estimate_selectivity(&self, condition: &Condition) -> f64 {
-        self.estimate_filter_selectivity(&condition.expression)
+        self.estimate_filter_selectivity(&condition.expression, None)
     }
 
-    /// Recursively estimates the selectivity of a filter expression
-    fn estimate_filter_selectivity(&self, expr: &FilterExpression) -> f64 {
+    /// Recursively estimates the selectivity of a filter expression. With the
+    /// filter's input known, comparisons on a variable bound by a predicate's
+    /// objects use that predicate's value statistics.
+    fn estimate_filter_selectivity(&self, expr: &FilterExpression, input: Option<&PhysicalOperator>) -> f64 {
         match expr {
-            FilterExpression::Comparison(_, op, _) => {
+            FilterExpression::Comparison(var, op, value) => {
+                let predicate = input.and_then(|input| self.object_predicate_of(input, var));
+                if let Some(selectivity) = predicate.and_then(|p| self.comparison_selectivity(p, op, value)) {
+                    return selectivity;
+                }
                 match *op {
                     "=" => 0.05,  // Equality is very selective
                     "!=" => 0.95, // Not equal is not very selective
@@ -274,19 +291,19 @@ impl<'a> CostEstimator<'a> {
             }
             FilterExpression::And(left, right) => {
                 // AND is more selective - multiply selectivities
-                let left_sel = self.estimate_filter_selectivity(left);
-                let right_sel = self.estimate_filter_selectivity(right);
+                let left_sel = self.estimate_filter_selectivity(left, input);
+                let right_sel = self.estimate_filter_selectivity(right, input);
                 left_sel * right_sel
             }
             FilterExpression::Or(left, right) => {
                 // OR is less selective - use formula: sel(A OR B) = sel(A) + sel(B) - sel(A)*sel(B)
-                let left_sel = self.estimate_filter_selectivity(left);
-                let right_sel = self.estimate_filter_selectivity(right);
+                let left_sel = self.estimate_filter_selectivity(left, input);
+                let right_sel = self.estimate_filter_selectivity(right, input);
                 left_sel + right_sel - (left_sel * right_sel)
             }
             FilterExpression::Not(inner) => {
                 // NOT inverts selectivity
-                let inner_sel = self.estimate_filter_selectivity(inner);
+                let inner_sel = self.estimate_filter_selectivity(inner, input);
                 1.0 - inner_sel
             }
             FilterExpression::ArithmeticExpr(_) => {
@@ -302,6 +319,142 @@ impl<'a> CostEstimator<'a> {
         }
     }
 
+    /// Selectivity of `?var op value` when `?var` ranges over the objects of `predicate`
+    fn comparison_selectivity(&self, predicate: u32, op: &str, value: &str) -> Option<f64> {
+        match parse_numeric_literal(value) {
+            Some(number) => self.stats.numeric_selectivity(predicate, op, number),
+            None => {
+                // Non-numeric equality: assume uniformly used objects
+                let distinct = self.stats.get_predicate_distinct_objects(predicate);
+                match op {
+                    "=" if distinct > 0 => Some(1.0 / distinct as f64),
+                    "!=" if distinct > 0 => Some(1.0 - 1.0 / distinct as f64),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Constant predicate of a scan in `plan` binding `var` in object position
+    fn object_predicate_of(&self, plan: &PhysicalOperator, var: &str) -> Option<u32> {
+        let var = var.strip_prefix('?').unwrap_or(var);
+        let mut scans = Vec::new();
+        collect_scans(plan, &mut scans);
+        scans.into_iter().find_map(|pattern| match pattern {
+            (_, Term::Constant(predicate), Term::Variable(object)) if same_variable(object, var) => {
+                Some(*predicate)
+            }
+            _ => None,
+        })
+    }
+
+    /// Characteristic-set estimate for joins whose inputs are scans sharing
+    /// one subject variable with distinct object variables or constants
+    fn characteristic_set_estimate(&self, plan: &PhysicalOperator) -> Option<u64> {
+        if self.stats.characteristic_sets.is_empty() {
+            return None;
+        }
+        let mut patterns = Vec::new();
+        if !collect_scans(plan, &mut patterns) || patterns.len() < 2 {
+            return None;
+        }
+        let Term::Variable(subject) = &patterns[0].0 else {
+            return None;
+        };
+
+        let mut predicates = Vec::with_capacity(patterns.len());
+        let mut object_vars: Vec<&str> = Vec::new();
+        let mut object_factor = 1.0;
+        for pattern in &patterns {
+            let (Term::Variable(s), Term::Constant(p)) = (&pattern.0, &pattern.1) else {
+                return None;
+            };
+            if !same_variable(s, subject) {
+                return None;
+            }
+            match &pattern.2 {
+                Term::Variable(o) => {
+                    if same_variable(o, subject) || object_vars.iter().any(|seen| same_variable(seen, o)) {
+                        return None;
+                    }
+                    object_vars.push(o);
+                }
+                Term::Constant(o) => {
+                    let with_object = self.stats.get_predicate_object_count(*p, *o) as f64;
+                    let total = self.stats.get_predicate_cardinality(*p).max(1) as f64;
+                    object_factor *= (with_object / total).min(1.0);
+                }
+                Term::QuotedTriple(_) => return None,
+            }
+            predicates.push(*p);
+        }
+
+        let estimate = self.stats.estimate_star(&predicates) * object_factor;
+        Some((estimate.round() as u64).max(1))
+    }
+
+    /// Estimates a join of `outer` with a scan `inner` from the statistics of
+    /// the scan in `outer` the two are linked through: a chain via predicate
+    /// co-occurrence, or a shared subject via the characteristic sets
+    fn linked_join_estimate(&self, outer: &PhysicalOperator, outer_cardinality: u64, inner: &PhysicalOperator) -> Option<u64> {
+        if self.stats.characteristic_sets.is_empty() {
+            return None;
+        }
+        let (PhysicalOperator::TableScan { pattern: inner } | PhysicalOperator::IndexScan { pattern: inner }) = inner else {
+            return None;
+        };
+        let Term::Constant(inner_predicate) = inner.1 else {
+            return None;
+        };
+        let inner_object_factor = match &inner.2 {
+            Term::Variable(_) => 1.0,
+            Term::Constant(o) => {
+                self.stats.get_predicate_object_count(inner_predicate, *o) as f64
+                    / self.stats.get_predicate_cardinality(inner_predicate).max(1) as f64
+            }
+            Term::QuotedTriple(_) => return None,
+        };
+
+        let mut scans = Vec::new();
+        collect_scans(outer, &mut scans);
+        let rows_per_outer_row = scans.into_iter().find_map(|pattern| {
+            let Term::Constant(outer_predicate) = pattern.1 else {
+                return None;
+            };
+            let outer_triples = self.stats.get_predicate_cardinality(outer_predicate).max(1) as f64;
+            let matches = if shares_variable(&pattern.2, &inner.0) {
+                self.stats.get_chain_count(outer_predicate, inner_predicate) as f64
+            } else if shares_variable(&inner.2, &pattern.0) {
+                self.stats.get_chain_count(inner_predicate, outer_predicate) as f64
+            } else if shares_variable(&pattern.0, &inner.0) {
+                self.stats.estimate_star(&[outer_predicate, inner_predicate])
+            } else {
+                return None;
+            };
+            Some(matches / outer_triples)
+        })?;
+
+        let estimate = outer_cardinality as f64 * rows_per_outer_row * inner_object_factor;
+        Some((estimate.round() as u64).max(1))
+    }
+
+    /// Output cardinality of a hash-based join of `left` and `right`
+    fn estimate_join_cardinality(&self, plan: &PhysicalOperator, left: &PhysicalOperator, right: &PhysicalOperator) -> u64 {
+        if let Some(estimate) = self.characteristic_set_estimate(plan) {
+            return estimate;
+        }
+        let left_cardinality = self.estimate_output_cardinality(left);
+        let right_cardinality = self.estimate_output_cardinality(right);
+        if let Some(estimate) = self
+            .linked_join_estimate(left, left_cardinality, right)
+            .or_else(|| self.linked_join_estimate(right, right_cardinality, left))
+        {
+            return estimate;
+        }
+        let join_selectivity = self.compute_join_selectivity(left, right);
+        ((left_cardinality.min(right_cardinality) as f64 * join_selectivity) as u64).max(1)
+    }
+
     /// Extracts the predicate ID from a physical operator if it's a scan
     fn extract_predicate_from_physical(&self, plan: &PhysicalOperator) -> Option<u32> {
         match plan {
@@ -337,20 +490,14 @@ impl<'a> CostEstimator<'a> {
             PhysicalOperator::IndexScan { pattern } => self.estimate_cardinality(pattern),
             PhysicalOperator::Filter { input, condition } => {
                 let input_cardinality = self.estimate_output_cardinality(input);
-                let selectivity = self.estimate_selectivity(condition);
+                let selectivity = self.estimate_filter_selectivity(&condition.expression, Some(input));
                 ((input_cardinality as f64 * selectivity) as u64).max(1)
             }
             PhysicalOperator::OptimizedHashJoin { left, right } => {
-                let left_cardinality = self.estimate_output_cardinality(left);
-                let right_cardinality = self.estimate_output_cardinality(right);
-                let join_selectivity = self.compute_join_selectivity(left, right);
-                ((left_cardinality.min(right_cardinality) as f64 * join_selectivity) as u64).max(1)
+                self.estimate_join_cardinality(plan, left, right)
             }
             PhysicalOperator::HashJoin { left, right } => {
-                let left_cardinality = self.estimate_output_cardinality(left);
-                let right_cardinality = self.estimate_output_cardinality(right);
-                let join_selectivity = self.compute_join_selectivity(left, right);
-                ((left_cardinality.min(right_cardinality) as f64 * join_selectivity) as u64).max(1)
+                self.estimate_join_cardinality(plan, left, right)
             }
             PhysicalOperator::NestedLoopJoin { left, right } => {
                 let left_cardinality = self.estimate_output_cardinality(left);
@@ -358,14 +505,15 @@ impl<'a> CostEstimator<'a> {
                 (left_cardinality * right_cardinality / 1000).max(1)
             }
             PhysicalOperator::ParallelJoin { left, right } => {
-                let left_cardinality = self.estimate_output_cardinality(left);
-                let right_cardinality = self.estimate_output_cardinality(right);
-                let join_selectivity = self.compute_join_selectivity(left, right);
-                ((left_cardinality.min(right_cardinality) as f64 * join_selectivity) as u64).max(1)
+                self.estimate_join_cardinality(plan, left, right)
             }
             PhysicalOperator::Projection { input, .. } => self.estimate_output_cardinality(input),
             PhysicalOperator::StarJoin { patterns, .. } => {
-                // Estimate cardinality of star join:
+                if let Some(estimate) = self.characteristic_set_estimate(plan) {
+                    return estimate;
+                }
+
+                // Without characteristic sets:
                 // Start with most selective pattern, then apply filtering
                 let mut cardinalities: Vec<u64> = patterns
                     .iter()
@@ -387,6 +535,15 @@ impl<'a> CostEstimator<'a> {
 
                 ((base as f64 * filter_factor) as u64).max(1)
             }
+            PhysicalOperator::LeapfrogJoin { patterns, .. } => {
+                // A worst-case optimal join never produces more rows than its smallest pattern
+                // for the cyclic patterns it is used for, so bound the output by that pattern
+                patterns
+                    .iter()
+                    .map(|p| self.estimate_cardinality(p))
+                    .min()
+                    .unwrap_or(0)
+            }
             PhysicalOperator::InMemoryBuffer { .. } => {0}
             PhysicalOperator::Subquery { inner, .. } => {
                 // Subquery cardinality is the same as inner query
@@ -438,6 +595,41 @@ impl<'a> CostEstimator<'a> {
     }
 }
 
+/// Collects the scan patterns of a plan, looking through joins, filters and
+/// projections; returns whether the plan consists of joined scans only
+fn collect_scans<'p>(plan: &'p PhysicalOperator, scans: &mut Vec<&'p TriplePattern>) -> bool {
+    match plan {
+        PhysicalOperator::TableScan { pattern } | PhysicalOperator::IndexScan { pattern } => {
+            scans.push(pattern);
+            true
+        }
+        PhysicalOperator::StarJoin { patterns, .. } => {
+            scans.extend(patterns.iter());
+            true
+        }
+        PhysicalOperator::HashJoin { left, right }
+        | PhysicalOperator::OptimizedHashJoin { left, right }
+        | PhysicalOperator::ParallelJoin { left, right }
+        | PhysicalOperator::NestedLoopJoin { left, right } => {
+            let left_scans_only = collect_scans(left, scans);
+            collect_scans(right, scans) && left_scans_only
+        }
+        PhysicalOperator::Filter { input, .. } | PhysicalOperator::Projection { input, .. } => {
+            collect_scans(input, scans);
+            false
+        }
+        _ => false,
+    }
+}
+
+fn same_variable(a: &str, b: &str) -> bool {
+    a.strip_prefix('?').unwrap_or(a) == b.strip_prefix('?').unwrap_or(b)
+}
+
+fn shares_variable(a: &Term, b: &Term) -> bool {
+    matches!((a, b), (Term::Variable(a), Term::Variable(b)) if same_variable(a, b))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -480,4 +672,58 @@ mod tests {
         let pattern = (Term::Constant(1), Term::Constant(2), Term::Constant(3));
         assert_eq!(estimator.count_bound_variables(&pattern), 3);
     }
+
+    fn scan(subject: &str, predicate: u32, object: &str) -> PhysicalOperator {
+        PhysicalOperator::index_scan((
+            Term::Variable(subject.to_string()),
+            Term::Constant(predicate),
+            Term::Variable(object.to_string()),
+        ))
+    }
+
+    /// 100 subjects with predicate 1, the first 10 also with two triples of predicate 2;
+    /// predicate 2 points at subjects with predicate 1
+    fn structured_stats() -> DatabaseStats {
+        let mut stats = DatabaseStats::new();
+        for subject in 0..100 {
+            stats.update_stats(subject, 1, 1000 + subject);
+            if subject < 10 {
+                stats.update_stats(subject, 2, 50 + subject);
+                stats.update_stats(subject, 2, 60 + subject);
+            }
+        }
+        stats
+    }
+
+    #[test]
+    fn test_star_join_uses_characteristic_sets() {
+        let stats = structured_stats();
+        let estimator = CostEstimator::new(&stats);
+        let star = PhysicalOperator::hash_join(scan("?s", 1, "?a"), scan("?s", 2, "?b"));
+        assert_eq!(estimator.estimate_output_cardinality(&star), 20);
+    }
+
+    #[test]
+    fn test_chain_join_uses_predicate_co_occurrence() {
+        let stats = structured_stats();
+        let estimator = CostEstimator::new(&stats);
+        // Every predicate 2 object in 50..70 has exactly one predicate 1 triple
+        let chain = PhysicalOperator::hash_join(scan("?s", 2, "?x"), scan("?x", 1, "?y"));
+        assert_eq!(estimator.estimate_output_cardinality(&chain), 20);
+    }
+
+    #[test]
+    fn test_filter_selectivity_uses_histograms() {
+        let mut stats = DatabaseStats::new();
+        for subject in 0..100 {
+            stats.numeric_values.insert(1000 + subject, subject as f64);
+            stats.update_stats(subject, 1, 1000 + subject);
+        }
+        let estimator = CostEstimator::new(&stats);
+        let filter = PhysicalOperator::filter(
+            scan("?s", 1, "?age"),
+            Condition::new("?age".to_string(), ">".to_string(), "89".to_string()),
+        );
+        assert_eq!(estimator.estimate_output_cardinality(&filter), 10);
+    }
 }
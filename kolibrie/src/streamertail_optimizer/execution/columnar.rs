@@ -0,0 +1,517 @@
+/*
+ * Copyright © 2024 Volodymyr Kadzhaia
+ * Copyright © 2024 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use super::super::operators::PhysicalOperator;
+use super::super::types::Condition;
+use super::engine::{record_operator, ExecutionEngine};
+use crate::query_context::{current_context, OperatorBudget};
+use crate::sparql_database::SparqlDatabase;
+use rayon::prelude::*;
+use shared::query::{ArithmeticExpression, FilterExpression};
+use shared::terms::{Term, TriplePattern};
+use shared::triple::Triple;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+/// Rows processed per batch by the vectorized join probe and filter.
+pub const BATCH_SIZE: usize = 4096;
+
+/// Marks a variable without a value in a row (UNDEF in VALUES).
+pub const UNBOUND: u32 = u32::MAX;
+
+/// A set of solutions stored column-wise: one `Vec<u32>` of dictionary IDs per
+/// variable, with a fixed variable-to-column schema. Variable names carry no '?'.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColumnBatch {
+    variables: Vec<String>,
+    columns: Vec<Vec<u32>>,
+    /// Tracked separately so batches without variables can still hold rows
+    rows: usize,
+}
+
+impl ColumnBatch {
+    /// Creates an empty batch with the given schema
+    pub fn new(variables: Vec<String>) -> Self {
+        let columns = vec![Vec::new(); variables.len()];
+        Self { variables, columns, rows: 0 }
+    }
+
+    /// Creates a batch from columns of equal length
+    pub fn from_columns(variables: Vec<String>, columns: Vec<Vec<u32>>) -> Self {
+        assert_eq!(variables.len(), columns.len(), "one column per variable");
+        let rows = columns.first().map_or(0, Vec::len);
+        assert!(columns.iter().all(|column| column.len() == rows), "columns of equal length");
+        Self { variables, columns, rows }
+    }
+
+    /// Converts row-based results; variables missing from a row become UNBOUND
+    pub fn from_rows(results: &[HashMap<String, u32>]) -> Self {
+        let mut variables: Vec<String> = results
+            .iter()
+            .flat_map(|row| row.keys())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .cloned()
+            .collect();
+        variables.sort();
+
+        let columns = variables
+            .iter()
+            .map(|var| results.iter().map(|row| row.get(var).copied().unwrap_or(UNBOUND)).collect())
+            .collect();
+        Self { variables, columns, rows: results.len() }
+    }
+
+    /// Converts to row-based results, leaving UNBOUND variables out of each row
+    pub fn into_rows(self) -> Vec<HashMap<String, u32>> {
+        (0..self.rows)
+            .into_par_iter()
+            .map(|row| {
+                self.variables
+                    .iter()
+                    .zip(&self.columns)
+                    .filter(|(_, column)| column[row] != UNBOUND)
+                    .map(|(var, column)| (var.clone(), column[row]))
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    /// The column of `variable` ('?' prefix optional)
+    pub fn column(&self, variable: &str) -> Option<&[u32]> {
+        let variable = variable.strip_prefix('?').unwrap_or(variable);
+        self.position(variable).map(|idx| self.columns[idx].as_slice())
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows
+    }
+
+    /// Bytes held by the values of the batch
+    fn byte_size(&self) -> usize {
+        self.rows * self.columns.len() * std::mem::size_of::<u32>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows == 0
+    }
+
+    fn position(&self, variable: &str) -> Option<usize> {
+        self.variables.iter().position(|var| var == variable)
+    }
+
+    fn push_row(&mut self, values: &[u32]) {
+        for (column, &value) in self.columns.iter_mut().zip(values) {
+            column.push(value);
+        }
+        self.rows += 1;
+    }
+
+    /// Keeps the rows at `indices`, in that order
+    fn gather(&self, indices: &[usize]) -> ColumnBatch {
+        let columns = self
+            .columns
+            .par_iter()
+            .map(|column| indices.iter().map(|&idx| column[idx]).collect())
+            .collect();
+        ColumnBatch { variables: self.variables.clone(), columns, rows: indices.len() }
+    }
+
+    /// Keeps the columns of `variables` that are in the schema
+    fn project(mut self, variables: &[String]) -> ColumnBatch {
+        let keep: Vec<bool> = self
+            .variables
+            .iter()
+            .map(|var| variables.iter().any(|v| v.strip_prefix('?').unwrap_or(v) == var))
+            .collect();
+        let mut keep_iter = keep.iter();
+        self.variables.retain(|_| *keep_iter.next().unwrap());
+        let mut keep_iter = keep.iter();
+        self.columns.retain(|_| *keep_iter.next().unwrap());
+        self
+    }
+}
+
+/// Executes `operator` batch-at-a-time over columns. Scans, joins, filters and
+/// projections run natively; the other operators run row-based and are converted.
+pub(super) fn execute_columnar(operator: &PhysicalOperator, database: &mut SparqlDatabase) -> ColumnBatch {
+    let context = current_context();
+    let before = match &context {
+        Some(context) if context.check().is_err() => return ColumnBatch::default(),
+        Some(context) => context.memory_used(),
+        None => 0,
+    };
+    let started = Instant::now();
+    let batch = match operator {
+        PhysicalOperator::TableScan { pattern } if !has_quoted_triple_term(pattern) => {
+            scan_batch(database.triples.iter(), pattern)
+        }
+        PhysicalOperator::IndexScan { pattern } if !has_quoted_triple_term(pattern) => {
+            let fully_unbound = [&pattern.0, &pattern.1, &pattern.2]
+                .iter()
+                .all(|term| matches!(term, Term::Variable(_)));
+            if fully_unbound {
+                scan_batch(database.triples.iter(), pattern)
+            } else {
+                let triples = database.index_manager.get_matching_triples(pattern);
+                scan_batch(triples.iter(), pattern)
+            }
+        }
+        PhysicalOperator::Filter { input, condition } => {
+            let input = execute_columnar(input, database);
+            filter_batch(input, condition, database)
+        }
+        PhysicalOperator::Projection { input, variables } => {
+            execute_columnar(input, database).project(variables)
+        }
+        PhysicalOperator::HashJoin { left, right }
+        | PhysicalOperator::OptimizedHashJoin { left, right }
+        | PhysicalOperator::NestedLoopJoin { left, right }
+        | PhysicalOperator::ParallelJoin { left, right } => {
+            let left = execute_columnar(left, database);
+            if left.is_empty() {
+                left
+            } else {
+                let right = execute_columnar(right, database);
+                hash_join_batches(left, right)
+            }
+        }
+        other => {
+            // Row-based execution records its own output size
+            return ColumnBatch::from_rows(&ExecutionEngine::execute_with_ids(other, database));
+        }
+    };
+    record_operator(operator, batch.len(), started.elapsed());
+    if let Some(context) = context {
+        // Inputs were consumed, only the output is still held
+        if context.settle(before, batch.byte_size()).is_err() {
+            return ColumnBatch::default();
+        }
+    }
+    batch
+}
+
+fn has_quoted_triple_term(pattern: &TriplePattern) -> bool {
+    pattern.0.is_quoted_triple() || pattern.1.is_quoted_triple() || pattern.2.is_quoted_triple()
+}
+
+/// Matches of `pattern` among `triples`, one column per distinct variable
+fn scan_batch<'a>(triples: impl Iterator<Item = &'a Triple>, pattern: &TriplePattern) -> ColumnBatch {
+    let terms = [&pattern.0, &pattern.1, &pattern.2];
+    let mut variables: Vec<String> = Vec::new();
+    // Column of the term at each position, None for constants
+    let slots: Vec<Option<usize>> = terms
+        .iter()
+        .map(|term| match term {
+            Term::Variable(var) => {
+                let var = var.strip_prefix('?').unwrap_or(var);
+                Some(variables.iter().position(|v| v == var).unwrap_or_else(|| {
+                    variables.push(var.to_string());
+                    variables.len() - 1
+                }))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let mut batch = ColumnBatch::new(variables);
+    let mut row = vec![UNBOUND; batch.variables.len()];
+    'triples: for triple in triples {
+        row.fill(UNBOUND);
+        let values = [triple.subject, triple.predicate, triple.object];
+        for ((term, slot), value) in terms.iter().zip(&slots).zip(values) {
+            match (term, slot) {
+                (Term::Constant(constant), _) if *constant != value => continue 'triples,
+                // A variable repeated within the pattern must bind one value
+                (_, Some(column)) if row[*column] != UNBOUND && row[*column] != value => continue 'triples,
+                (_, Some(column)) => row[*column] = value,
+                _ => {}
+            }
+        }
+        batch.push_row(&row);
+    }
+    batch
+}
+
+/// Evaluates `condition` batch by batch into a selection vector, then gathers
+/// the selected rows. Conditions over a single variable are evaluated once per
+/// distinct ID instead of once per row.
+fn filter_batch(input: ColumnBatch, condition: &Condition, database: &SparqlDatabase) -> ColumnBatch {
+    let mut referenced = HashSet::new();
+    collect_filter_variables(&condition.expression, &mut referenced);
+    let columns: Vec<(String, &[u32])> = referenced
+        .iter()
+        .filter_map(|var| input.column(var).map(|column| (var.clone(), column)))
+        .collect();
+
+    let dictionary = database.dictionary.read().unwrap();
+    let selected: Vec<usize> = if let [(var, column)] = columns.as_slice() {
+        let mut outcomes: HashMap<u32, bool> = HashMap::new();
+        let mut row = HashMap::with_capacity(1);
+        (0..input.len())
+            .filter(|&idx| {
+                let id = column[idx];
+                *outcomes.entry(id).or_insert_with(|| {
+                    row.clear();
+                    if id != UNBOUND {
+                        row.insert(var.clone(), id);
+                    }
+                    condition.evaluate_with_ids(&row, &dictionary)
+                })
+            })
+            .collect()
+    } else {
+        (0..input.len())
+            .collect::<Vec<_>>()
+            .par_chunks(BATCH_SIZE)
+            .flat_map_iter(|chunk| {
+                let mut row = HashMap::with_capacity(columns.len());
+                chunk
+                    .iter()
+                    .copied()
+                    .filter(|&idx| {
+                        row.clear();
+                        for (var, column) in &columns {
+                            if column[idx] != UNBOUND {
+                                row.insert(var.clone(), column[idx]);
+                            }
+                        }
+                        condition.evaluate_with_ids(&row, &dictionary)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    };
+    drop(dictionary);
+
+    if selected.len() == input.len() {
+        input
+    } else {
+        input.gather(&selected)
+    }
+}
+
+/// Variables `expression` reads, without `?`
+pub(crate) fn collect_filter_variables(expression: &FilterExpression, variables: &mut HashSet<String>) {
+    let mut add = |name: &str| {
+        variables.insert(name.strip_prefix('?').unwrap_or(name).to_string());
+    };
+    match expression {
+        FilterExpression::Comparison(var, _, value) => {
+            add(var);
+            if value.starts_with('?') {
+                add(value);
+            }
+        }
+        FilterExpression::And(left, right) | FilterExpression::Or(left, right) => {
+            collect_filter_variables(left, variables);
+            collect_filter_variables(right, variables);
+        }
+        FilterExpression::Not(inner) => collect_filter_variables(inner, variables),
+        FilterExpression::ArithmeticExpr(expr) => collect_arithmetic_variables(expr, variables),
+        FilterExpression::FunctionCall(_, args) => {
+            for arg in args.iter().filter(|arg| arg.starts_with('?')) {
+                add(arg);
+            }
+        }
+    }
+}
+
+fn collect_arithmetic_variables(expression: &ArithmeticExpression, variables: &mut HashSet<String>) {
+    match expression {
+        ArithmeticExpression::Operand(operand) => {
+            if let Some(var) = operand.strip_prefix('?') {
+                variables.insert(var.to_string());
+            }
+        }
+        ArithmeticExpression::Add(left, right)
+        | ArithmeticExpression::Subtract(left, right)
+        | ArithmeticExpression::Multiply(left, right)
+        | ArithmeticExpression::Divide(left, right) => {
+            collect_arithmetic_variables(left, variables);
+            collect_arithmetic_variables(right, variables);
+        }
+    }
+}
+
+/// Hash join on all shared variables: the smaller batch is built into a hash
+/// table, the larger one is probed in parallel batches of `BATCH_SIZE` rows.
+/// The output schema is the left schema followed by the right-only variables.
+fn hash_join_batches(left: ColumnBatch, right: ColumnBatch) -> ColumnBatch {
+    let shared: Vec<(usize, usize)> = left
+        .variables
+        .iter()
+        .enumerate()
+        .filter_map(|(l, var)| right.position(var).map(|r| (l, r)))
+        .collect();
+    let right_only: Vec<usize> = (0..right.variables.len())
+        .filter(|r| !shared.iter().any(|(_, shared_r)| shared_r == r))
+        .collect();
+
+    let has_unbound_key = shared.iter().any(|&(l, r)| {
+        left.columns[l].contains(&UNBOUND) || right.columns[r].contains(&UNBOUND)
+    });
+    if has_unbound_key {
+        // UNDEF is compatible with any value; leave those joins to the row-based path
+        return ColumnBatch::from_rows(&ExecutionEngine::join_rows(left.into_rows(), right.into_rows()));
+    }
+
+    let build_left = left.len() <= right.len();
+    let (build, probe) = if build_left { (&left, &right) } else { (&right, &left) };
+    let (left_keys, right_keys): (Vec<usize>, Vec<usize>) = shared.iter().copied().unzip();
+    let (build_keys, probe_keys) = if build_left { (left_keys, right_keys) } else { (right_keys, left_keys) };
+    let key = |batch: &ColumnBatch, keys: &[usize], row: usize| -> Vec<u32> {
+        keys.iter().map(|&column| batch.columns[column][row]).collect()
+    };
+
+    let mut table: HashMap<Vec<u32>, Vec<usize>> = HashMap::new();
+    for row in 0..build.len() {
+        table.entry(key(build, &build_keys, row)).or_default().push(row);
+    }
+
+    // Matching (build row, probe row) pairs, found batch by batch
+    let width = left.variables.len() + right_only.len();
+    let budget = OperatorBudget::with_row_bytes(std::mem::size_of::<(usize, usize)>() + width * std::mem::size_of::<u32>());
+    let probe_rows: Vec<usize> = (0..probe.len()).collect();
+    let pairs: Vec<(usize, usize)> = probe_rows
+        .par_chunks(BATCH_SIZE)
+        .flat_map_iter(|chunk| {
+            let mut matches = Vec::new();
+            if !budget.scan(chunk.len()) {
+                return matches;
+            }
+            for &probe_row in chunk {
+                if let Some(build_rows) = table.get(&key(probe, &probe_keys, probe_row)) {
+                    matches.extend(build_rows.iter().map(|&build_row| (build_row, probe_row)));
+                }
+            }
+            budget.produce(matches.len());
+            matches
+        })
+        .collect();
+    if budget.is_aborted() {
+        return ColumnBatch::default();
+    }
+
+    let (left_rows, right_rows): (Vec<usize>, Vec<usize>) = if build_left {
+        pairs.into_iter().unzip()
+    } else {
+        pairs.into_iter().map(|(build_row, probe_row)| (probe_row, build_row)).unzip()
+    };
+
+    let mut variables = left.variables.clone();
+    variables.extend(right_only.iter().map(|&r| right.variables[r].clone()));
+    let mut columns: Vec<Vec<u32>> = left
+        .columns
+        .par_iter()
+        .map(|column| left_rows.iter().map(|&row| column[row]).collect())
+        .collect();
+    columns.extend(
+        right_only
+            .par_iter()
+            .map(|&r| right_rows.iter().map(|&row| right.columns[r][row]).collect::<Vec<u32>>())
+            .collect::<Vec<_>>(),
+    );
+    ColumnBatch { variables, columns, rows: left_rows.len() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch(variables: &[&str], rows: &[&[u32]]) -> ColumnBatch {
+        let mut batch = ColumnBatch::new(variables.iter().map(|var| var.to_string()).collect());
+        for row in rows {
+            batch.push_row(row);
+        }
+        batch
+    }
+
+    #[test]
+    fn hash_join_keeps_the_left_schema_first() {
+        let left = batch(&["s", "name"], &[&[1, 10], &[2, 20], &[3, 30]]);
+        let right = batch(&["age", "s"], &[&[40, 2], &[50, 3], &[60, 3], &[70, 4]]);
+
+        let joined = hash_join_batches(left, right);
+        assert_eq!(joined.variables(), ["s", "name", "age"]);
+        let mut rows: Vec<Vec<u32>> = (0..joined.len())
+            .map(|row| joined.columns.iter().map(|column| column[row]).collect())
+            .collect();
+        rows.sort();
+        assert_eq!(rows, vec![vec![2, 20, 40], vec![3, 30, 50], vec![3, 30, 60]]);
+    }
+
+    #[test]
+    fn joins_without_shared_variables_are_cross_products() {
+        let joined = hash_join_batches(batch(&["a"], &[&[1], &[2]]), batch(&["b"], &[&[3], &[4], &[5]]));
+        assert_eq!(joined.len(), 6);
+    }
+
+    #[test]
+    fn scans_enforce_repeated_variables() {
+        let triples = [
+            Triple { subject: 1, predicate: 7, object: 1 },
+            Triple { subject: 1, predicate: 7, object: 2 },
+            Triple { subject: 2, predicate: 8, object: 2 },
+        ];
+        let pattern = (Term::Variable("x".into()), Term::Constant(7), Term::Variable("?x".into()));
+        let scanned = scan_batch(triples.iter(), &pattern);
+        assert_eq!(scanned.variables(), ["x"]);
+        assert_eq!(scanned.column("?x"), Some(&[1][..]));
+    }
+
+    #[test]
+    fn columnar_execution_matches_row_execution() {
+        let mut database = SparqlDatabase::new();
+        let (age, knows) = {
+            let mut dict = database.dictionary.write().unwrap();
+            (dict.encode("http://example.org/age"), dict.encode("http://example.org/knows"))
+        };
+        for person in 0..20u32 {
+            let value = database.dictionary.write().unwrap().encode(&(20 + person % 7).to_string());
+            database.add_triple(Triple { subject: person, predicate: age, object: value });
+            database.add_triple(Triple { subject: person, predicate: knows, object: (person + 1) % 20 });
+        }
+
+        let scan = |s: &str, p: u32, o: &str| {
+            PhysicalOperator::index_scan((Term::Variable(s.into()), Term::Constant(p), Term::Variable(o.into())))
+        };
+        let plan = PhysicalOperator::projection(
+            PhysicalOperator::filter(
+                PhysicalOperator::hash_join(scan("a", knows, "b"), scan("b", age, "age")),
+                Condition::new("?age".into(), ">".into(), "23".into()),
+            ),
+            vec!["?a".into(), "?age".into()],
+        );
+
+        let sorted = |mut rows: Vec<HashMap<String, u32>>| {
+            rows.sort_by_key(|row| (row["a"], row["age"]));
+            rows
+        };
+        let columnar = execute_columnar(&plan, &mut database);
+        assert_eq!(columnar.variables(), ["a", "age"]);
+        assert_eq!(sorted(columnar.into_rows()), sorted(ExecutionEngine::execute_with_ids(&plan, &mut database)));
+    }
+
+    #[test]
+    fn rows_round_trip_with_unbound_values() {
+        let rows = vec![
+            HashMap::from([("a".to_string(), 1), ("b".to_string(), 2)]),
+            HashMap::from([("a".to_string(), 3)]),
+        ];
+        let batch = ColumnBatch::from_rows(&rows);
+        assert_eq!(batch.column("b"), Some(&[2, UNBOUND][..]));
+        assert_eq!(batch.into_rows(), rows);
+    }
+}
@@ -0,0 +1,375 @@
+/*
+ * Copyright © 2024 Volodymyr Kadzhaia
+ * Copyright © 2024 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use super::super::operators::PhysicalOperator;
+use super::engine::ExecutionEngine;
+use crate::sparql_database::SparqlDatabase;
+use shared::index_manager::UnifiedIndex;
+use shared::terms::{Term, TriplePattern};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+type Row = HashMap<String, u32>;
+type Rows<'a> = Box<dyn Iterator<Item = Row> + 'a>;
+type Triples<'a> = Box<dyn Iterator<Item = [u32; 3]> + 'a>;
+/// Second and third level of an index permutation
+type Postings = HashMap<u32, HashSet<u32>>;
+
+/// Pull-based cursor over the ID results of a physical plan.
+///
+/// Scans, filters, projections and joins are pipelined: a row is only produced
+/// when it is pulled, so a consumer that stops early (LIMIT, ASK) never pays for
+/// the rest of the result. Joins whose right side is an index scan probe the
+/// index once per left row; other joins build a hash table over their right
+/// side on the first pull. Operators that need their whole input (aggregation,
+/// buffers, ML predictions, ...) are evaluated up front.
+pub struct ResultCursor<'a> {
+    database: &'a SparqlDatabase,
+    rows: Rows<'a>,
+}
+
+impl<'a> ResultCursor<'a> {
+    pub fn new(operator: &PhysicalOperator, database: &'a mut SparqlDatabase) -> Self {
+        let mut materialized = VecDeque::new();
+        materialize_blocking(operator, database, &mut materialized);
+        let database: &'a SparqlDatabase = database;
+        let rows = stream(operator, database, &mut materialized);
+        ResultCursor { database, rows }
+    }
+
+    /// The database the cursor reads from, e.g. to decode the IDs it yields
+    pub fn database(&self) -> &'a SparqlDatabase {
+        self.database
+    }
+
+    /// Pulls up to `size` rows; an empty batch means the cursor is exhausted
+    pub fn next_batch(&mut self, size: usize) -> Vec<Row> {
+        self.rows.by_ref().take(size).collect()
+    }
+
+    /// Whether the plan has at least one result, pulling a single row
+    pub fn exists(mut self) -> bool {
+        self.rows.next().is_some()
+    }
+}
+
+impl Iterator for ResultCursor<'_> {
+    type Item = Row;
+
+    fn next(&mut self) -> Option<Row> {
+        self.rows.next()
+    }
+}
+
+fn is_pipelined_scan(pattern: &TriplePattern) -> bool {
+    !(pattern.0.is_quoted_triple() || pattern.1.is_quoted_triple() || pattern.2.is_quoted_triple())
+}
+
+/// Evaluates the operators that cannot be pipelined, in the order `stream`
+/// visits them
+fn materialize_blocking(
+    operator: &PhysicalOperator,
+    database: &mut SparqlDatabase,
+    materialized: &mut VecDeque<Vec<Row>>,
+) {
+    match operator {
+        PhysicalOperator::TableScan { pattern } | PhysicalOperator::IndexScan { pattern }
+            if is_pipelined_scan(pattern) => {}
+        PhysicalOperator::Filter { input, .. } | PhysicalOperator::Projection { input, .. } => {
+            materialize_blocking(input, database, materialized)
+        }
+        PhysicalOperator::HashJoin { left, right }
+        | PhysicalOperator::OptimizedHashJoin { left, right }
+        | PhysicalOperator::NestedLoopJoin { left, right }
+        | PhysicalOperator::ParallelJoin { left, right } => {
+            materialize_blocking(left, database, materialized);
+            materialize_blocking(right, database, materialized);
+        }
+        other => materialized.push_back(ExecutionEngine::execute_with_ids(other, database)),
+    }
+}
+
+fn stream<'a>(
+    operator: &PhysicalOperator,
+    database: &'a SparqlDatabase,
+    materialized: &mut VecDeque<Vec<Row>>,
+) -> Rows<'a> {
+    match operator {
+        PhysicalOperator::TableScan { pattern } if is_pipelined_scan(pattern) => {
+            let triples = database
+                .triples
+                .iter()
+                .map(|triple| [triple.subject, triple.predicate, triple.object]);
+            scan_rows(Box::new(triples), pattern)
+        }
+        PhysicalOperator::IndexScan { pattern } if is_pipelined_scan(pattern) => {
+            scan_rows(index_triples(database, pattern), pattern)
+        }
+        PhysicalOperator::Filter { input, condition } => {
+            let condition = condition.clone();
+            let input = stream(input, database, materialized);
+            Box::new(input.filter(move |row| {
+                let dictionary = database.dictionary.read().unwrap();
+                condition.evaluate_with_ids(row, &dictionary)
+            }))
+        }
+        PhysicalOperator::Projection { input, variables } => {
+            let variables: HashSet<String> = variables
+                .iter()
+                .map(|var| var.strip_prefix('?').unwrap_or(var).to_string())
+                .collect();
+            let input = stream(input, database, materialized);
+            Box::new(input.map(move |mut row| {
+                row.retain(|var, _| variables.contains(var.strip_prefix('?').unwrap_or(var)));
+                row
+            }))
+        }
+        PhysicalOperator::HashJoin { left, right }
+        | PhysicalOperator::OptimizedHashJoin { left, right }
+        | PhysicalOperator::NestedLoopJoin { left, right }
+        | PhysicalOperator::ParallelJoin { left, right } => {
+            let left = stream(left, database, materialized);
+            match right.as_ref() {
+                PhysicalOperator::IndexScan { pattern } if is_pipelined_scan(pattern) => {
+                    bind_join(left, pattern.clone(), database)
+                }
+                _ => {
+                    let right = stream(right, database, materialized);
+                    hash_join(left, right)
+                }
+            }
+        }
+        _ => Box::new(materialized.pop_front().unwrap_or_default().into_iter()),
+    }
+}
+
+/// Rows of `pattern` over `triples`, checking constants and repeated variables
+fn scan_rows<'a>(triples: Triples<'a>, pattern: &TriplePattern) -> Rows<'a> {
+    let terms: Vec<Term> = vec![pattern.0.clone(), pattern.1.clone(), pattern.2.clone()];
+    Box::new(triples.filter_map(move |values| {
+        let mut row = HashMap::new();
+        for (term, value) in terms.iter().zip(values) {
+            match term {
+                Term::Constant(constant) if *constant != value => return None,
+                Term::Variable(var) => {
+                    let var = var.strip_prefix('?').unwrap_or(var);
+                    // A variable repeated within the pattern must bind one value
+                    if *row.entry(var.to_string()).or_insert(value) != value {
+                        return None;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Some(row)
+    }))
+}
+
+/// Triples matching the constants of `pattern`, walked lazily from the index
+/// permutation that binds them as a prefix
+fn index_triples<'a>(database: &'a SparqlDatabase, pattern: &TriplePattern) -> Triples<'a> {
+    let constant = |term: &Term| match term {
+        Term::Constant(value) => Some(*value),
+        _ => None,
+    };
+    let index: &'a UnifiedIndex = &database.index_manager;
+    match (constant(&pattern.0), constant(&pattern.1), constant(&pattern.2)) {
+        (None, None, None) => Box::new(
+            database
+                .triples
+                .iter()
+                .map(|triple| [triple.subject, triple.predicate, triple.object]),
+        ),
+        (Some(s), Some(p), o) => Box::new(walk(&index.spo, Some(s), Some(p)).filter(move |t| o.is_none_or(|o| t[2] == o))),
+        (Some(s), None, o) => match o {
+            Some(o) => Box::new(walk(&index.sop, Some(s), Some(o)).map(|[s, o, p]| [s, p, o])),
+            None => Box::new(walk(&index.spo, Some(s), None)),
+        },
+        (None, Some(p), Some(o)) => Box::new(walk(&index.pos, Some(p), Some(o)).map(|[p, o, s]| [s, p, o])),
+        (None, Some(p), None) => Box::new(walk(&index.pso, Some(p), None).map(|[p, s, o]| [s, p, o])),
+        (None, None, Some(o)) => Box::new(walk(&index.ops, Some(o), None).map(|[o, p, s]| [s, p, o])),
+    }
+}
+
+/// Entries of one index permutation, optionally restricted to a key prefix
+fn walk<'a>(
+    index: &'a HashMap<u32, Postings>,
+    first: Option<u32>,
+    second: Option<u32>,
+) -> Triples<'a> {
+    let outer: Box<dyn Iterator<Item = (&'a u32, &'a Postings)> + 'a> = match first {
+        Some(key) => Box::new(index.get_key_value(&key).into_iter()),
+        None => Box::new(index.iter()),
+    };
+    Box::new(outer.flat_map(move |(&a, inner)| {
+        let middle: Box<dyn Iterator<Item = (&'a u32, &'a HashSet<u32>)> + 'a> = match second {
+            Some(key) => Box::new(inner.get_key_value(&key).into_iter()),
+            None => Box::new(inner.iter()),
+        };
+        middle.flat_map(move |(&b, leaves)| leaves.iter().map(move |&c| [a, b, c]))
+    }))
+}
+
+/// Index nested loop join: each left row binds the right pattern, whose
+/// matches are then read straight from the index
+fn bind_join<'a>(left: Rows<'a>, pattern: TriplePattern, database: &'a SparqlDatabase) -> Rows<'a> {
+    Box::new(left.flat_map(move |row| {
+        let bind = |term: &Term| match term {
+            Term::Variable(var) => match row.get(var.strip_prefix('?').unwrap_or(var)) {
+                Some(&value) => Term::Constant(value),
+                None => term.clone(),
+            },
+            _ => term.clone(),
+        };
+        let bound = (bind(&pattern.0), bind(&pattern.1), bind(&pattern.2));
+        scan_rows(index_triples(database, &bound), &bound).map(move |matched| {
+            let mut joined = row.clone();
+            joined.extend(matched);
+            joined
+        })
+    }))
+}
+
+/// Hash join streaming the left side; the right side is consumed and hashed
+/// on the first left row, keyed on the variables the two sides share
+fn hash_join<'a>(left: Rows<'a>, right: Rows<'a>) -> Rows<'a> {
+    let mut right = Some(right);
+    let mut table: Option<JoinTable> = None;
+    Box::new(left.flat_map(move |row| {
+        let table = table.get_or_insert_with(|| JoinTable::build(right.take().unwrap().collect(), &row));
+        table.probe(&row)
+    }))
+}
+
+struct JoinTable {
+    rows: Vec<Row>,
+    keys: Vec<String>,
+    buckets: HashMap<Vec<u32>, Vec<usize>>,
+    /// Rows missing a key variable, compatible with any value of it
+    unkeyed: Vec<usize>,
+}
+
+impl JoinTable {
+    fn build(rows: Vec<Row>, first_left: &Row) -> Self {
+        let mut keys: Vec<String> = rows
+            .first()
+            .map(|row| row.keys().filter(|var| first_left.contains_key(*var)).cloned().collect())
+            .unwrap_or_default();
+        keys.sort();
+
+        let mut buckets: HashMap<Vec<u32>, Vec<usize>> = HashMap::new();
+        let mut unkeyed = Vec::new();
+        for (idx, row) in rows.iter().enumerate() {
+            match keys.iter().map(|var| row.get(var).copied()).collect::<Option<Vec<u32>>>() {
+                Some(key) => buckets.entry(key).or_default().push(idx),
+                None => unkeyed.push(idx),
+            }
+        }
+        JoinTable { rows, keys, buckets, unkeyed }
+    }
+
+    fn probe(&self, left: &Row) -> Vec<Row> {
+        let candidates: Vec<usize> = match self.keys.iter().map(|var| left.get(var).copied()).collect::<Option<Vec<u32>>>() {
+            Some(key) => self
+                .buckets
+                .get(&key)
+                .into_iter()
+                .flatten()
+                .chain(&self.unkeyed)
+                .copied()
+                .collect(),
+            None => (0..self.rows.len()).collect(),
+        };
+        candidates
+            .into_iter()
+            .map(|idx| &self.rows[idx])
+            .filter(|right| {
+                right
+                    .iter()
+                    .all(|(var, value)| left.get(var).is_none_or(|bound| bound == value))
+            })
+            .map(|right| {
+                let mut joined = left.clone();
+                joined.extend(right.iter().map(|(var, &value)| (var.clone(), value)));
+                joined
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::types::Condition;
+    use super::*;
+    use shared::triple::Triple;
+
+    fn knows_ring(people: u32) -> (SparqlDatabase, u32, u32) {
+        let mut database = SparqlDatabase::new();
+        let (age, knows) = {
+            let mut dict = database.dictionary.write().unwrap();
+            (dict.encode("http://example.org/age"), dict.encode("http://example.org/knows"))
+        };
+        for person in 0..people {
+            let value = database.dictionary.write().unwrap().encode(&(20 + person % 7).to_string());
+            database.add_triple(Triple { subject: person, predicate: age, object: value });
+            database.add_triple(Triple { subject: person, predicate: knows, object: (person + 1) % people });
+        }
+        (database, age, knows)
+    }
+
+    fn scan(s: &str, p: u32, o: &str) -> PhysicalOperator {
+        PhysicalOperator::index_scan((Term::Variable(s.into()), Term::Constant(p), Term::Variable(o.into())))
+    }
+
+    #[test]
+    fn cursor_matches_materialized_execution() {
+        let (mut database, age, knows) = knows_ring(20);
+        let plan = PhysicalOperator::projection(
+            PhysicalOperator::filter(
+                PhysicalOperator::hash_join(
+                    PhysicalOperator::hash_join(scan("a", knows, "b"), scan("b", age, "age")),
+                    PhysicalOperator::table_scan((
+                        Term::Variable("b".into()),
+                        Term::Constant(knows),
+                        Term::Variable("c".into()),
+                    )),
+                ),
+                Condition::new("?age".into(), ">".into(), "23".into()),
+            ),
+            vec!["?a".into(), "?c".into()],
+        );
+
+        let sorted = |mut rows: Vec<Row>| {
+            rows.sort_by_key(|row| (row["a"], row["c"]));
+            rows
+        };
+        let streamed: Vec<Row> = ResultCursor::new(&plan, &mut database).collect();
+        let materialized = ExecutionEngine::execute_with_ids(&plan, &mut database);
+        assert!(!materialized.is_empty());
+        assert_eq!(sorted(streamed), sorted(materialized));
+    }
+
+    #[test]
+    fn batches_are_pulled_on_demand() {
+        let (mut database, _, knows) = knows_ring(5);
+        let plan = scan("s", knows, "o");
+
+        {
+            let mut cursor = ResultCursor::new(&plan, &mut database);
+            assert_eq!(cursor.next_batch(2).len(), 2);
+            assert_eq!(cursor.next_batch(2).len(), 2);
+            assert_eq!(cursor.next_batch(2).len(), 1);
+            assert!(cursor.next_batch(2).is_empty());
+        }
+
+        assert!(ResultCursor::new(&plan, &mut database).exists());
+        let unknown = scan("s", knows + 100, "o");
+        assert!(!ResultCursor::new(&unknown, &mut database).exists());
+    }
+}
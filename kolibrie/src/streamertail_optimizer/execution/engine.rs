@@ -9,15 +9,69 @@
  */
 
 use super::super::operators::PhysicalOperator;
+use super::columnar::{self, ColumnBatch};
+use super::cursor::ResultCursor;
+use super::leapfrog;
 
+use crate::query_context::{current_context, estimated_row_bytes, run_with_context, OperatorBudget, QueryContext, QueryError};
+use crate::parser::{parse_arithmetic_expression, ARITHMETIC_BIND};
 use crate::sparql_database::SparqlDatabase;
 use ml::MLPredictionResult;
 use rayon::prelude::*;
 
+use shared::rule::{format_number, numeric_value};
 use shared::terms::{Term, TriplePattern};
 use shared::quoted_triple_store::is_quoted_triple_id;
 
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+thread_local! {
+    /// Operator statistics recorded while `execute_profiled` runs.
+    static PROFILE: RefCell<Option<ExecutionProfile>> = const { RefCell::new(None) };
+}
+
+/// Records the output size and evaluation time of `operator` when a profiled
+/// execution is running.
+pub(super) fn record_operator(operator: &PhysicalOperator, rows: usize, elapsed: Duration) {
+    PROFILE.with(|profile| {
+        if let Some(profile) = profile.borrow_mut().as_mut() {
+            let key = operator as *const PhysicalOperator as usize;
+            profile.rows.insert(key, rows);
+            profile.elapsed.insert(key, elapsed);
+        }
+    });
+}
+
+/// Actual output sizes and timings of the operators of one executed plan.
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionProfile {
+    rows: HashMap<usize, usize>,
+    elapsed: HashMap<usize, Duration>,
+}
+
+impl ExecutionProfile {
+    /// Rows produced by `operator`, a node of the plan that was executed. `None` when
+    /// the operator was not evaluated on its own (e.g. the probe side of a bind join).
+    pub fn rows(&self, operator: &PhysicalOperator) -> Option<usize> {
+        self.rows.get(&(operator as *const PhysicalOperator as usize)).copied()
+    }
+
+    /// Time spent evaluating `operator`, including its inputs.
+    pub fn elapsed(&self, operator: &PhysicalOperator) -> Option<Duration> {
+        self.elapsed.get(&(operator as *const PhysicalOperator as usize)).copied()
+    }
+
+    /// Number of operators with a recorded output size.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+}
 
 /// Execution engine for physical operators
 pub struct ExecutionEngine;
@@ -28,7 +82,7 @@ impl ExecutionEngine {
         operator: &PhysicalOperator,
         database: &mut SparqlDatabase,
     ) -> Vec<HashMap<String, String>> {
-        let id_results = Self::execute_with_ids(operator, database);
+        let id_results = Self::execute_columnar(operator, database).into_rows();
 
         // Convert ID results to string results only at the final step
         id_results
@@ -50,10 +104,79 @@ impl ExecutionEngine {
         .collect()
     }
 
+    /// Executes a physical operator like `execute`, additionally recording the
+    /// output size of every operator evaluated along the way.
+    pub fn execute_profiled(
+        operator: &PhysicalOperator,
+        database: &mut SparqlDatabase,
+    ) -> (Vec<HashMap<String, String>>, ExecutionProfile) {
+        let outer = PROFILE.with(|profile| profile.borrow_mut().replace(ExecutionProfile::default()));
+        let results = Self::execute(operator, database);
+        let profile = PROFILE.with(|profile| std::mem::replace(&mut *profile.borrow_mut(), outer));
+        (results, profile.unwrap_or_default())
+    }
+
+    /// Executes a physical operator like `execute`, stopping with an error once
+    /// `context` times out, is cancelled or runs out of memory.
+    pub fn execute_with_context(
+        operator: &PhysicalOperator,
+        database: &mut SparqlDatabase,
+        context: &QueryContext,
+    ) -> Result<Vec<HashMap<String, String>>, QueryError> {
+        run_with_context(context, || Self::execute(operator, database))
+    }
+
     /// Executes a physical operator and returns ID-based results for performance
     pub fn execute_with_ids(
         operator: &PhysicalOperator,
         database: &mut SparqlDatabase,
+    ) -> Vec<HashMap<String, u32>> {
+        let context = current_context();
+        let before = match &context {
+            Some(context) if context.check().is_err() => return Vec::new(),
+            Some(context) => context.memory_used(),
+            None => 0,
+        };
+        let started = Instant::now();
+        let results = Self::execute_operator_with_ids(operator, database);
+        record_operator(operator, results.len(), started.elapsed());
+        if let Some(context) = context {
+            // Inputs were consumed, only the output is still held
+            let width = results.first().map_or(0, HashMap::len);
+            if context.settle(before, results.len() * estimated_row_bytes(width)).is_err() {
+                return Vec::new();
+            }
+        }
+        results
+    }
+
+    /// Executes a physical operator batch-at-a-time over columns of IDs
+    pub fn execute_columnar(
+        operator: &PhysicalOperator,
+        database: &mut SparqlDatabase,
+    ) -> ColumnBatch {
+        columnar::execute_columnar(operator, database)
+    }
+
+    /// Executes a physical operator lazily, producing ID results as they are pulled
+    pub fn execute_iter<'a>(
+        operator: &PhysicalOperator,
+        database: &'a mut SparqlDatabase,
+    ) -> ResultCursor<'a> {
+        ResultCursor::new(operator, database)
+    }
+
+    /// Joins row-based results whose rows may bind different variables
+    pub(super) fn join_rows(
+        left_results: Vec<HashMap<String, u32>>,
+        right_results: Vec<HashMap<String, u32>>,
+    ) -> Vec<HashMap<String, u32>> {
+        Self::execute_nested_loop_join_with_ids(left_results, right_results)
+    }
+
+    fn execute_operator_with_ids(
+        operator: &PhysicalOperator,
+        database: &mut SparqlDatabase,
     ) -> Vec<HashMap<String, u32>> {
         match operator {
             PhysicalOperator::TableScan { pattern } => {
@@ -125,6 +248,9 @@ impl ExecutionEngine {
             PhysicalOperator::StarJoin { join_var, patterns } => {
                 Self::execute_star_join_with_ids(database, join_var, patterns)
             }
+            PhysicalOperator::LeapfrogJoin { patterns, variable_order } => {
+                leapfrog::execute_leapfrog_join_with_ids(database, patterns, variable_order)
+            }
             PhysicalOperator::InMemoryBuffer { content, origin: _ } => {
                 content.clone() // TODO: make sure we dont have to clone here
             }
@@ -145,7 +271,25 @@ impl ExecutionEngine {
                 let mut input_results = Self::execute_with_ids(input, database);
                 let output_var = output_variable.strip_prefix('?').unwrap_or(output_variable);
 
-                if function_name == "CONCAT" {
+                if function_name == ARITHMETIC_BIND {
+                    let Some(Ok((_, expression))) = arguments.first().map(|text| parse_arithmetic_expression(text)) else {
+                        return input_results;
+                    };
+                    let mut dict = database.dictionary.write().unwrap();
+                    for row in &mut input_results {
+                        let resolve = |var: &str| {
+                            let id = row.get(var.strip_prefix('?').unwrap_or(var))?;
+                            dict.decode(*id).and_then(numeric_value)
+                        };
+                        if let Ok(value) = expression.evaluate(&resolve) {
+                            let result_id = dict.encode(&format_number(value));
+                            row.insert(output_var.to_string(), result_id);
+                        }
+                    }
+                    drop(dict);
+
+                    input_results
+                } else if function_name == "CONCAT" {
                     // Decode all needed values first
                     let dict = database.dictionary.read().unwrap();
                     let decoded_values: Vec<Vec<String>> = input_results
@@ -667,6 +811,7 @@ impl ExecutionEngine {
 
         // Adaptive strategy: use sequential for large result sets
         let use_sequential = results.len() > 10_000 || first_card > 50_000;
+        let budget = OperatorBudget::current(patterns.len() * 2);
 
         // Process remaining patterns
         for (pattern_idx, _) in &pattern_estimates[1..] {
@@ -677,6 +822,9 @@ impl ExecutionEngine {
                 let mut new_results = Vec::new();
 
                 for binding in results.iter().take(100_000) {  // Hard limit on input size
+                    if !budget.scan(1) {
+                        return Vec::new();
+                    }
                     if let Some(&join_value) = binding.get(join_var_stripped) {
                         let mut bound_bindings = HashMap::new();
                         bound_bindings.insert(join_var_stripped.to_string(), join_value);
@@ -690,6 +838,9 @@ impl ExecutionEngine {
                                 merged.entry(var).or_insert(val);
                             }
                             new_results.push(merged);
+                            if !budget.produce(1) {
+                                return Vec::new();
+                            }
 
                             // Hard stop if we exceed 500K results
                             if new_results.len() >= 500_000 {
@@ -706,12 +857,16 @@ impl ExecutionEngine {
                 results = results
                 .into_par_iter()
                 .flat_map(|binding| {
+                    if !budget.scan(1) {
+                        return Vec::new();
+                    }
                     if let Some(&join_value) = binding.get(join_var_stripped) {
                         let mut bound_bindings = HashMap::new();
                         bound_bindings.insert(join_var_stripped.to_string(), join_value);
 
                         let bound_pattern = Self::bind_pattern(pattern, &bound_bindings);
                         let matches = Self::execute_index_scan_with_ids(database, &bound_pattern);
+                        budget.produce(matches.len());
 
                         matches
                         .into_iter()
@@ -730,7 +885,7 @@ impl ExecutionEngine {
                 .collect();
             }
 
-            if results.is_empty() {
+            if results.is_empty() || budget.is_aborted() {
                 return Vec::new();
             }
         }
@@ -781,6 +936,7 @@ impl ExecutionEngine {
         };
 
         let mut hash_table: HashMap<Vec<u32>, Vec<HashMap<String, u32>>> = HashMap::with_capacity(build_side.len());
+        let budget = OperatorBudget::current(build_side[0].len() + probe_side[0].len());
 
         // Build phase
         for tuple in build_side {
@@ -792,9 +948,13 @@ impl ExecutionEngine {
         probe_side
         .par_iter()
         .flat_map(|probe_tuple| {
+            if !budget.scan(1) {
+                return Vec::new();
+            }
             let key: Vec<u32> = common_vars.iter().map(|var| probe_tuple[var]).collect();
 
             if let Some(matching_tuples) = hash_table.get(&key) {
+                budget.produce(matching_tuples.len());
                 matching_tuples
                 .iter()
                 .map(|build_tuple| {
@@ -831,6 +991,7 @@ impl ExecutionEngine {
         // Simple hash join implementation
         let mut results = Vec::new();
         let mut hash_table: HashMap<Vec<u32>, Vec<HashMap<String, u32>>> = HashMap::new();
+        let budget = OperatorBudget::current(left_results[0].len() + right_results[0].len());
 
         // Build hash table from left results
         for left_tuple in left_results {
@@ -840,6 +1001,9 @@ impl ExecutionEngine {
 
         // Probe with right results
         for right_tuple in right_results {
+            if !budget.scan(1) {
+                return Vec::new();
+            }
             let key: Vec<u32> = common_vars.iter().map(|var| right_tuple[var]).collect();
 
             if let Some(matching_left_tuples) = hash_table.get(&key) {
@@ -852,6 +1016,9 @@ impl ExecutionEngine {
                     }
                     results.push(joined_tuple);
                 }
+                if !budget.produce(matching_left_tuples.len()) {
+                    return Vec::new();
+                }
             }
         }
 
@@ -863,13 +1030,16 @@ impl ExecutionEngine {
         left_results: Vec<HashMap<String, u32>>,
         right_results: Vec<HashMap<String, u32>>,
     ) -> Vec<HashMap<String, u32>> {
+        let width = left_results.first().map_or(0, HashMap::len) + right_results.first().map_or(0, HashMap::len);
+        let budget = OperatorBudget::current(width);
         left_results
         .into_iter()
+        .take_while(|_| budget.scan(right_results.len()))
         .flat_map(|left_tuple| {
             right_results
             .iter()
             .filter_map(|right_tuple| {
-                Self::can_join_with_ids(&left_tuple, right_tuple).then(|| {
+                (Self::can_join_with_ids(&left_tuple, right_tuple) && budget.produce(1)).then(|| {
                     let mut joined_tuple = left_tuple.clone();
                     for (var, value) in right_tuple {
                         if !joined_tuple.contains_key(var) {
@@ -895,18 +1065,20 @@ impl ExecutionEngine {
         let max_total = 1_000_000;
 
         let chunk_size = (left_results.len() / rayon::current_num_threads()).max(1).max(100);
+        let budget = OperatorBudget::current(left_results.first().map_or(0, HashMap::len) + 2);
 
         left_results
         .par_chunks(chunk_size)
         .flat_map(|chunk| {
             chunk.iter().flat_map(|left_tuple| {
                 // Check global limit
-                if total_results.load(std::sync::atomic::Ordering::Relaxed) >= max_total {
+                if total_results.load(std::sync::atomic::Ordering::Relaxed) >= max_total || !budget.scan(1) {
                     return Vec::new();
                 }
 
                 let bound_pattern = Self::bind_pattern(right_pattern, left_tuple);
                 let matches = Self::execute_index_scan_with_ids(database, &bound_pattern);
+                budget.produce(matches.len());
 
                 // Limit matches per binding
                 let match_limit = matches.len().min(10_000);
@@ -1061,12 +1233,17 @@ impl ExecutionEngine {
         }
 
         // Parallel merge using index
+        let budget = OperatorBudget::current(left_results[0].len() + right_results[0].len());
         left_results
         .par_iter()
         .flat_map(|left_tuple| {
+            if !budget.scan(1) {
+                return Vec::new();
+            }
             let key: Vec<u32> = common_vars.iter().filter_map(|v| left_tuple.get(v).copied()).collect();
 
             if let Some(right_indices) = right_index.get(&key) {
+                budget.produce(right_indices.len());
                 right_indices
                 .iter()
                 .map(|&idx| {
@@ -1103,9 +1280,14 @@ impl ExecutionEngine {
         left_results: Vec<HashMap<String, u32>>,
         right_results: Vec<HashMap<String, u32>>,
     ) -> Vec<HashMap<String, u32>> {
+        let width = left_results.first().map_or(0, HashMap::len) + right_results.first().map_or(0, HashMap::len);
+        let budget = OperatorBudget::current(width);
         left_results
         .into_par_iter()
         .flat_map(|left_tuple| {
+            if !budget.scan(right_results.len()) || !budget.produce(right_results.len()) {
+                return Vec::new();
+            }
             right_results
             .iter()
             .map(|right_tuple| {
@@ -0,0 +1,289 @@
+/*
+ * Copyright © 2024 Volodymyr Kadzhaia
+ * Copyright © 2024 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use crate::query_context::OperatorBudget;
+use crate::sparql_database::SparqlDatabase;
+use shared::terms::{Term, TriplePattern};
+use shared::index_manager::UnifiedIndex;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+/// One triple pattern materialized as a trie: its matches projected onto the
+/// pattern's variables (in global variable order), sorted and deduplicated.
+/// A trie level is a column; the children of a prefix are a contiguous range.
+struct SortedTrie {
+    /// Depth (position in the global variable order) of every column
+    depths: Vec<usize>,
+    tuples: Vec<Vec<u32>>,
+}
+
+impl SortedTrie {
+    /// Reads the matches from the index permutation that lists the pattern's
+    /// constants first and then its variables in variable order, so the tuples
+    /// come out in trie order. Permutations are hashed, so the keys of each
+    /// level are sorted on the way down.
+    fn build(database: &SparqlDatabase, pattern: &TriplePattern, variable_order: &[String]) -> Self {
+        let terms = [&pattern.0, &pattern.1, &pattern.2];
+        let mut constants: Vec<(usize, u32)> = Vec::new();
+        let mut variables: Vec<(usize, usize)> = Vec::new();
+        // Quoted triple patterns are not matched here and constrain nothing
+        let mut unconstrained: Vec<usize> = Vec::new();
+        for (position, term) in terms.iter().enumerate() {
+            match term {
+                Term::Variable(name) => {
+                    let name = name.strip_prefix('?').unwrap_or(name);
+                    let depth = variable_order
+                        .iter()
+                        .position(|var| var == name)
+                        .expect("leapfrog variable order covers every pattern variable");
+                    variables.push((depth, position));
+                }
+                Term::Constant(value) => constants.push((position, *value)),
+                Term::QuotedTriple(_) => unconstrained.push(position),
+            }
+        }
+        variables.sort_unstable();
+
+        let order: Vec<usize> = constants
+            .iter()
+            .map(|&(position, _)| position)
+            .chain(variables.iter().map(|&(_, position)| position))
+            .chain(unconstrained)
+            .collect();
+        let keys: Vec<u32> = constants.iter().map(|&(_, value)| value).collect();
+        let mut depths: Vec<usize> = variables.iter().map(|&(depth, _)| depth).collect();
+        depths.dedup();
+
+        let mut tuples = Vec::new();
+        walk_sorted(permutation(&database.index_manager, &order), &keys, |entry| {
+            let mut values = [0; 3];
+            for (&position, value) in order.iter().zip(entry) {
+                values[position] = value;
+            }
+            // A variable repeated within the pattern must bind one value
+            let mut tuple: Vec<u32> = Vec::with_capacity(depths.len());
+            for (i, &(depth, position)) in variables.iter().enumerate() {
+                if i > 0 && variables[i - 1].0 == depth {
+                    if *tuple.last().unwrap() != values[position] {
+                        return;
+                    }
+                } else {
+                    tuple.push(values[position]);
+                }
+            }
+            tuples.push(tuple);
+        });
+        // Only entries that differ in an unconstrained position repeat a tuple
+        tuples.dedup();
+
+        SortedTrie { depths, tuples }
+    }
+
+    fn column(&self, depth: usize) -> Option<usize> {
+        self.depths.iter().position(|&d| d == depth)
+    }
+
+    /// First index in `range` whose value in `column` is at least `key`
+    fn seek(&self, range: &Range<usize>, column: usize, key: u32) -> usize {
+        range.start + self.tuples[range.clone()].partition_point(|tuple| tuple[column] < key)
+    }
+
+    /// Sub-range of `range` whose value in `column` equals `key`
+    fn narrow(&self, range: &Range<usize>, column: usize, key: u32) -> Range<usize> {
+        let start = self.seek(range, column, key);
+        let end = start + self.tuples[start..range.end].partition_point(|tuple| tuple[column] == key);
+        start..end
+    }
+}
+
+/// Permutation of `index` whose levels are the triple positions in `order`
+/// (0 for the subject, 1 for the predicate, 2 for the object)
+fn permutation<'a>(index: &'a UnifiedIndex, order: &[usize]) -> &'a HashMap<u32, HashMap<u32, HashSet<u32>>> {
+    match order {
+        [0, 1, ..] => &index.spo,
+        [0, 2, ..] => &index.sop,
+        [1, 0, ..] => &index.pso,
+        [1, 2, ..] => &index.pos,
+        [2, 0, ..] => &index.osp,
+        _ => &index.ops,
+    }
+}
+
+/// Calls `visit` with every entry of `index` that starts with `keys`, in
+/// ascending order
+fn walk_sorted(index: &HashMap<u32, HashMap<u32, HashSet<u32>>>, keys: &[u32], mut visit: impl FnMut([u32; 3])) {
+    fn sorted(mut keys: Vec<u32>) -> Vec<u32> {
+        keys.sort_unstable();
+        keys
+    }
+    let firsts = match keys.first() {
+        Some(&a) => vec![a],
+        None => sorted(index.keys().copied().collect()),
+    };
+    for a in firsts {
+        let Some(postings) = index.get(&a) else { continue };
+        let seconds = match keys.get(1) {
+            Some(&b) => vec![b],
+            None => sorted(postings.keys().copied().collect()),
+        };
+        for b in seconds {
+            let Some(leaves) = postings.get(&b) else { continue };
+            match keys.get(2) {
+                Some(&c) => {
+                    if leaves.contains(&c) {
+                        visit([a, b, c]);
+                    }
+                }
+                None => sorted(leaves.iter().copied().collect()).into_iter().for_each(|c| visit([a, b, c])),
+            }
+        }
+    }
+}
+
+/// Leapfrog Triejoin (Veldhuizen, 2014): a worst-case optimal multi-way join.
+/// Variables are bound one at a time in `variable_order`; each value is found by
+/// leapfrogging over the sorted tries of every pattern that contains the variable,
+/// so no binary intermediate result is ever materialized.
+pub(super) fn execute_leapfrog_join_with_ids(
+    database: &SparqlDatabase,
+    patterns: &[TriplePattern],
+    variable_order: &[String],
+) -> Vec<HashMap<String, u32>> {
+    let tries: Vec<SortedTrie> = patterns
+        .iter()
+        .map(|pattern| SortedTrie::build(database, pattern, variable_order))
+        .collect();
+    // A pattern without variables only checks that its triple exists
+    if tries.iter().any(|trie| trie.tuples.is_empty()) {
+        return Vec::new();
+    }
+
+    let mut ranges: Vec<Range<usize>> = tries.iter().map(|trie| 0..trie.tuples.len()).collect();
+    let mut binding = Vec::with_capacity(variable_order.len());
+    let mut results = Vec::new();
+    let budget = OperatorBudget::current(variable_order.len());
+    leapfrog_level(&tries, &mut ranges, 0, variable_order, &mut binding, &mut results, &budget);
+    if budget.is_aborted() {
+        return Vec::new();
+    }
+    results
+}
+
+fn leapfrog_level(
+    tries: &[SortedTrie],
+    ranges: &mut [Range<usize>],
+    depth: usize,
+    variable_order: &[String],
+    binding: &mut Vec<u32>,
+    results: &mut Vec<HashMap<String, u32>>,
+    budget: &OperatorBudget,
+) {
+    if depth == variable_order.len() {
+        results.push(variable_order.iter().cloned().zip(binding.iter().copied()).collect());
+        budget.produce(1);
+        return;
+    }
+
+    let participants: Vec<(usize, usize)> = tries
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, trie)| trie.column(depth).map(|column| (idx, column)))
+        .collect();
+    if participants.is_empty() {
+        return;
+    }
+
+    // Positions of every participant's iterator within its current range
+    let mut positions: Vec<usize> = participants.iter().map(|&(idx, _)| ranges[idx].start).collect();
+    let value_at = |slot: usize, position: usize| {
+        let (idx, column) = participants[slot];
+        tries[idx].tuples[position][column]
+    };
+
+    while budget.scan(1) {
+        // Leapfrog: every iterator seeks to the largest current key until all agree
+        let mut key = 0;
+        for (slot, &(idx, _)) in participants.iter().enumerate() {
+            if positions[slot] >= ranges[idx].end {
+                return;
+            }
+            key = key.max(value_at(slot, positions[slot]));
+        }
+        let mut agreed = true;
+        for (slot, &(idx, column)) in participants.iter().enumerate() {
+            let range = positions[slot]..ranges[idx].end;
+            positions[slot] = tries[idx].seek(&range, column, key);
+            if positions[slot] >= ranges[idx].end {
+                return;
+            }
+            if value_at(slot, positions[slot]) != key {
+                agreed = false;
+            }
+        }
+        if !agreed {
+            continue;
+        }
+
+        // Descend into the children of `key` in every participating trie
+        let saved: Vec<Range<usize>> = participants.iter().map(|&(idx, _)| ranges[idx].clone()).collect();
+        for (slot, &(idx, column)) in participants.iter().enumerate() {
+            ranges[idx] = tries[idx].narrow(&(positions[slot]..saved[slot].end), column, key);
+        }
+        binding.push(key);
+        leapfrog_level(tries, ranges, depth + 1, variable_order, binding, results, budget);
+        binding.pop();
+        for (slot, &(idx, _)) in participants.iter().enumerate() {
+            positions[slot] = ranges[idx].end;
+            ranges[idx] = saved[slot].clone();
+        }
+    }
+}
+
+/// Variable order for a leapfrog join: the most shared variable first, then
+/// repeatedly the variable sharing the most patterns with those already chosen,
+/// so that every level intersects as many tries as possible.
+pub fn leapfrog_variable_order(patterns: &[TriplePattern]) -> Vec<String> {
+    let pattern_vars: Vec<Vec<String>> = patterns
+        .iter()
+        .map(|(s, p, o)| {
+            let mut vars: Vec<String> = [s, p, o]
+                .iter()
+                .filter_map(|term| match term {
+                    Term::Variable(name) => Some(name.strip_prefix('?').unwrap_or(name).to_string()),
+                    _ => None,
+                })
+                .collect();
+            vars.dedup();
+            vars
+        })
+        .collect();
+
+    let mut remaining: Vec<String> = pattern_vars.iter().flatten().cloned().collect();
+    remaining.sort();
+    remaining.dedup();
+
+    let mut order: Vec<String> = Vec::with_capacity(remaining.len());
+    while !remaining.is_empty() {
+        let score = |var: &String| {
+            let occurrences = pattern_vars.iter().filter(|vars| vars.contains(var)).count();
+            let connections = pattern_vars
+                .iter()
+                .filter(|vars| vars.contains(var) && vars.iter().any(|other| order.contains(other)))
+                .count();
+            (connections, occurrences)
+        };
+        // Ties keep the alphabetical order, so plans are deterministic
+        let best = (0..remaining.len())
+            .max_by(|&a, &b| score(&remaining[a]).cmp(&score(&remaining[b])).then(b.cmp(&a)))
+            .unwrap();
+        order.push(remaining.remove(best));
+    }
+    order
+}
@@ -8,6 +8,11 @@
  * you can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+pub mod columnar;
+pub mod cursor;
 pub mod engine;
+pub mod leapfrog;
 
-pub use engine::ExecutionEngine;
+pub use columnar::ColumnBatch;
+pub use cursor::ResultCursor;
+pub use engine::{ExecutionEngine, ExecutionProfile};
@@ -0,0 +1,268 @@
+/*
+ * Copyright © 2024 Volodymyr Kadzhaia
+ * Copyright © 2024 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use super::cost::CostEstimator;
+use super::execution::ExecutionProfile;
+use super::operators::PhysicalOperator;
+use super::optimizer::serialize_arith_expr;
+use super::stats::DatabaseStats;
+use serde::Serialize;
+use shared::dictionary::Dictionary;
+use shared::query::FilterExpression;
+use shared::terms::{Term, TriplePattern};
+use std::fmt;
+use std::time::Duration;
+
+/// One operator of an explained plan, with the optimizer's estimates and,
+/// after EXPLAIN ANALYZE, what actually happened
+#[derive(Debug, Clone, Serialize)]
+pub struct ExplainNode {
+    pub operator: String,
+    pub detail: String,
+    pub estimated_rows: u64,
+    /// Estimated cost of the operator including its inputs
+    pub estimated_cost: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual_rows: Option<usize>,
+    /// Time spent in the operator including its inputs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual_time_ms: Option<f64>,
+    pub children: Vec<ExplainNode>,
+}
+
+impl ExplainNode {
+    pub fn new(
+        plan: &PhysicalOperator,
+        stats: &DatabaseStats,
+        dictionary: &Dictionary,
+        profile: Option<&ExecutionProfile>,
+    ) -> Self {
+        let estimator = CostEstimator::new(stats);
+        Self::build(plan, &estimator, dictionary, profile)
+    }
+
+    /// Node for a solution modifier the physical plan does not contain, such
+    /// as GROUP BY, ORDER BY or LIMIT, applied to the rows of `input`.
+    /// `actual` holds the rows it produced and the time since execution
+    /// started, after EXPLAIN ANALYZE.
+    pub fn modifier(
+        operator: &str,
+        detail: String,
+        estimated_rows: u64,
+        estimated_cost: u64,
+        actual: Option<(usize, Duration)>,
+        input: ExplainNode,
+    ) -> Self {
+        ExplainNode {
+            operator: operator.to_string(),
+            detail,
+            estimated_rows,
+            estimated_cost,
+            actual_rows: actual.map(|(rows, _)| rows),
+            actual_time_ms: actual.map(|(_, elapsed)| elapsed.as_secs_f64() * 1000.0),
+            children: vec![input],
+        }
+    }
+
+    fn build(
+        plan: &PhysicalOperator,
+        estimator: &CostEstimator,
+        dictionary: &Dictionary,
+        profile: Option<&ExecutionProfile>,
+    ) -> Self {
+        let pattern = |pattern: &TriplePattern| format_pattern(pattern, dictionary);
+        let (operator, detail, inputs): (&str, String, Vec<&PhysicalOperator>) = match plan {
+            PhysicalOperator::TableScan { pattern: p } => ("TableScan", pattern(p), vec![]),
+            PhysicalOperator::IndexScan { pattern: p } => ("IndexScan", pattern(p), vec![]),
+            PhysicalOperator::Filter { input, condition } => {
+                ("Filter", format_filter(&condition.expression), vec![input])
+            }
+            PhysicalOperator::HashJoin { left, right } => ("HashJoin", String::new(), vec![left, right]),
+            PhysicalOperator::NestedLoopJoin { left, right } => {
+                ("NestedLoopJoin", String::new(), vec![left, right])
+            }
+            PhysicalOperator::ParallelJoin { left, right } => ("ParallelJoin", String::new(), vec![left, right]),
+            PhysicalOperator::OptimizedHashJoin { left, right } => {
+                ("OptimizedHashJoin", String::new(), vec![left, right])
+            }
+            PhysicalOperator::StarJoin { join_var, patterns } => (
+                "StarJoin",
+                format!(
+                    "on {}: {}",
+                    format_variable(join_var),
+                    patterns.iter().map(pattern).collect::<Vec<_>>().join(", ")
+                ),
+                vec![],
+            ),
+            PhysicalOperator::LeapfrogJoin { patterns, variable_order } => (
+                "LeapfrogJoin",
+                format!(
+                    "order [{}]: {}",
+                    variable_order.iter().map(|var| format_variable(var)).collect::<Vec<_>>().join(", "),
+                    patterns.iter().map(pattern).collect::<Vec<_>>().join(", ")
+                ),
+                vec![],
+            ),
+            PhysicalOperator::Projection { input, variables } => (
+                "Projection",
+                variables.iter().map(|var| format_variable(var)).collect::<Vec<_>>().join(", "),
+                vec![input],
+            ),
+            PhysicalOperator::InMemoryBuffer { content, origin } => {
+                ("InMemoryBuffer", format!("{} ({} rows)", origin, content.len()), vec![])
+            }
+            PhysicalOperator::Subquery { inner, projected_vars } => (
+                "Subquery",
+                projected_vars.iter().map(|var| format_variable(var)).collect::<Vec<_>>().join(", "),
+                vec![inner],
+            ),
+            PhysicalOperator::Bind { input, function_name, arguments, output_variable } => (
+                "Bind",
+                format!("{} := {}({})", format_variable(output_variable), function_name, arguments.join(", ")),
+                vec![input],
+            ),
+            PhysicalOperator::Values { variables, values } => (
+                "Values",
+                format!(
+                    "{} ({} rows)",
+                    variables.iter().map(|var| format_variable(var)).collect::<Vec<_>>().join(", "),
+                    values.len()
+                ),
+                vec![],
+            ),
+            PhysicalOperator::MLPredict { input, model_name, input_variables, output_variable, .. } => (
+                "MLPredict",
+                format!(
+                    "{} := {}({})",
+                    format_variable(output_variable),
+                    model_name,
+                    input_variables.iter().map(|var| format_variable(var)).collect::<Vec<_>>().join(", ")
+                ),
+                vec![input],
+            ),
+        };
+
+        ExplainNode {
+            operator: operator.to_string(),
+            detail,
+            estimated_rows: estimator.estimate_output_cardinality(plan),
+            estimated_cost: estimator.estimate_cost(plan),
+            actual_rows: profile.and_then(|profile| profile.rows(plan)),
+            actual_time_ms: profile
+                .and_then(|profile| profile.elapsed(plan))
+                .map(|elapsed| elapsed.as_secs_f64() * 1000.0),
+            children: inputs
+                .into_iter()
+                .map(|input| Self::build(input, estimator, dictionary, profile))
+                .collect(),
+        }
+    }
+
+    fn render(&self, depth: usize, out: &mut String) {
+        let indent = "  ".repeat(depth);
+        let arrow = if depth == 0 { "" } else { "-> " };
+        out.push_str(&format!("{}{}{}", indent, arrow, self.operator));
+        if !self.detail.is_empty() {
+            out.push_str(&format!(" {}", self.detail));
+        }
+        out.push_str(&format!("  (estimated rows={} cost={})", self.estimated_rows, self.estimated_cost));
+        match (self.actual_rows, self.actual_time_ms) {
+            (Some(rows), Some(time)) => out.push_str(&format!(" (actual rows={} time={:.3} ms)", rows, time)),
+            (Some(rows), None) => out.push_str(&format!(" (actual rows={})", rows)),
+            // Not evaluated on its own, e.g. the probe side of a bind join
+            _ => {}
+        }
+        out.push('\n');
+        for child in &self.children {
+            child.render(depth + 1, out);
+        }
+    }
+}
+
+/// Plan picked for a query by `Streamertail::find_best_plan`
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryExplanation {
+    /// Whether the plan was executed (EXPLAIN ANALYZE)
+    pub analyzed: bool,
+    pub planning_time_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execution_time_ms: Option<f64>,
+    /// Rows produced by the plan, before solution modifiers such as LIMIT
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_rows: Option<usize>,
+    pub plan: ExplainNode,
+}
+
+impl QueryExplanation {
+    /// Indented plan tree, one operator per line
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        self.plan.render(0, &mut out);
+        out.push_str(&format!("Planning time: {:.3} ms\n", self.planning_time_ms));
+        if let Some(execution_time) = self.execution_time_ms {
+            out.push_str(&format!("Execution time: {:.3} ms\n", execution_time));
+        }
+        if let Some(rows) = self.total_rows {
+            out.push_str(&format!("Rows: {}\n", rows));
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_else(|_| "{}".to_string())
+    }
+}
+
+impl fmt::Display for QueryExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_text())
+    }
+}
+
+pub(crate) fn format_variable(var: &str) -> String {
+    format!("?{}", var.strip_prefix('?').unwrap_or(var))
+}
+
+fn format_term(term: &Term, dictionary: &Dictionary) -> String {
+    match term {
+        Term::Variable(var) => format_variable(var),
+        Term::Constant(id) => match dictionary.decode(*id) {
+            Some(value) if value.starts_with('"') || value.starts_with("_:") => value.to_string(),
+            Some(value) => format!("<{}>", value),
+            None => format!("#{}", id),
+        },
+        Term::QuotedTriple(inner) => format!(
+            "<< {} {} {} >>",
+            format_term(&inner.0, dictionary),
+            format_term(&inner.1, dictionary),
+            format_term(&inner.2, dictionary)
+        ),
+    }
+}
+
+fn format_pattern(pattern: &TriplePattern, dictionary: &Dictionary) -> String {
+    format!(
+        "({} {} {})",
+        format_term(&pattern.0, dictionary),
+        format_term(&pattern.1, dictionary),
+        format_term(&pattern.2, dictionary)
+    )
+}
+
+fn format_filter(expression: &FilterExpression) -> String {
+    match expression {
+        FilterExpression::Comparison(var, op, value) => format!("{} {} {}", var, op, value),
+        FilterExpression::And(left, right) => format!("({} && {})", format_filter(left), format_filter(right)),
+        FilterExpression::Or(left, right) => format!("({} || {})", format_filter(left), format_filter(right)),
+        FilterExpression::Not(inner) => format!("!({})", format_filter(inner)),
+        FilterExpression::ArithmeticExpr(expr) => serialize_arith_expr(expr),
+        FilterExpression::FunctionCall(name, args) => format!("{}({})", name, args.join(", ")),
+    }
+}
@@ -10,6 +10,7 @@
 
 pub mod cost;
 pub mod execution;
+pub mod explain;
 pub mod operators;
 pub mod optimizer;
 pub mod stats;
@@ -18,7 +19,8 @@ pub mod utils;
 
 // Re-export main components for convenience
 pub use cost::{CostConstants, CostEstimator};
-pub use execution::ExecutionEngine;
+pub use execution::{ColumnBatch, ExecutionEngine, ExecutionProfile, ResultCursor};
+pub use explain::{ExplainNode, QueryExplanation};
 pub use operators::{LogicalOperator, PhysicalOperator};
 pub use optimizer::Streamertail;
 pub use stats::DatabaseStats;
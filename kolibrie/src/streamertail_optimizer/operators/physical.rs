@@ -12,7 +12,7 @@ use super::super::Condition;
 use shared::terms::{Bindings, TriplePattern};
 
 /// Physical operators represent the actual execution plan after optimization
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum PhysicalOperator {
     TableScan {
         pattern: TriplePattern,
@@ -44,6 +44,11 @@ pub enum PhysicalOperator {
         join_var: String,
         patterns: Vec<TriplePattern>,
     },
+    /// Worst-case optimal multi-way join (Leapfrog Triejoin) for cyclic patterns
+    LeapfrogJoin {
+        patterns: Vec<TriplePattern>,
+        variable_order: Vec<String>,
+    },
     Projection {
         input: Box<PhysicalOperator>,
         variables: Vec<String>,
@@ -126,6 +131,14 @@ impl PhysicalOperator {
         }
     }
 
+    /// Creates a new leapfrog triejoin physical operator
+    pub fn leapfrog_join(patterns: Vec<TriplePattern>, variable_order: Vec<String>) -> Self {
+        Self::LeapfrogJoin {
+            patterns,
+            variable_order,
+        }
+    }
+
     /// Creates a new projection physical operator
     pub fn projection(input: PhysicalOperator, variables: Vec<String>) -> Self {
         Self::Projection {
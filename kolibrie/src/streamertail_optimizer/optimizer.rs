@@ -9,7 +9,8 @@
  */
 
 use super::cost::CostEstimator;
-use super::execution::ExecutionEngine;
+use super::execution::{ExecutionEngine, ResultCursor};
+use super::execution::leapfrog::leapfrog_variable_order;
 use super::operators::{LogicalOperator, PhysicalOperator};
 use super::stats::DatabaseStats;
 
@@ -26,7 +27,7 @@ pub struct Streamertail {
     pub stats: Arc<DatabaseStats>,
 }
 
-fn serialize_arith_expr(expr: &shared::query::ArithmeticExpression) -> String {
+pub(super) fn serialize_arith_expr(expr: &shared::query::ArithmeticExpression) -> String {
     use shared::query::ArithmeticExpression as AE;
     match expr {
         AE::Operand(s) => s.to_string(),
@@ -70,6 +71,16 @@ impl Streamertail {
         ExecutionEngine::execute(plan, database)
     }
 
+    /// Executes a physical plan through a pull-based cursor, so consumers that
+    /// stop early only pay for the rows they read
+    pub fn execute_plan_iter<'a>(
+        &self,
+        plan: &PhysicalOperator,
+        database: &'a mut SparqlDatabase,
+    ) -> ResultCursor<'a> {
+        ExecutionEngine::execute_iter(plan, database)
+    }
+
     /// Optimizes and executes a logical plan in one step
     pub fn optimize_and_execute(
         &mut self,
@@ -80,8 +91,84 @@ impl Streamertail {
         self.execute_plan(&physical_plan, database)
     }
 
+    /// Detects if a join tree is a cyclic query pattern (triangle, clique, ...) and
+    /// returns its patterns. Only join trees of plain scans qualify.
+    fn is_cyclic_query(&self, plan: &LogicalOperator) -> Option<Vec<TriplePattern>> {
+        fn collect_scans(plan: &LogicalOperator, patterns: &mut Vec<TriplePattern>) -> bool {
+            match plan {
+                LogicalOperator::Scan { pattern } => {
+                    patterns.push(pattern.clone());
+                    true
+                }
+                LogicalOperator::Join { left, right } => {
+                    collect_scans(left, patterns) && collect_scans(right, patterns)
+                }
+                _ => false,
+            }
+        }
+
+        let mut patterns = Vec::new();
+        if !collect_scans(plan, &mut patterns) || patterns.len() < 3 {
+            return None;
+        }
+        if patterns.iter().any(|(s, p, o)| {
+            [s, p, o].iter().any(|term| matches!(term, Term::QuotedTriple(_)))
+        }) {
+            return None;
+        }
+
+        // GYO reduction: drop variables that occur in a single pattern and patterns
+        // whose variables are covered by another one; a cyclic query cannot be reduced
+        let mut edges: Vec<HashSet<&String>> = patterns
+            .iter()
+            .map(|(s, p, o)| {
+                [s, p, o]
+                    .into_iter()
+                    .filter_map(|term| match term {
+                        Term::Variable(var) => Some(var),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect();
+
+        loop {
+            let before: usize = edges.len() + edges.iter().map(|edge| edge.len()).sum::<usize>();
+
+            let mut occurrences: HashMap<&String, usize> = HashMap::new();
+            for var in edges.iter().flatten() {
+                *occurrences.entry(*var).or_insert(0) += 1;
+            }
+            for edge in edges.iter_mut() {
+                edge.retain(|var| occurrences[var] > 1);
+            }
+
+            if let Some(covered) = (0..edges.len()).find(|&i| {
+                (0..edges.len()).any(|j| i != j && edges[i].is_subset(&edges[j]))
+            }) {
+                edges.remove(covered);
+            }
+
+            let after: usize = edges.len() + edges.iter().map(|edge| edge.len()).sum::<usize>();
+            if after == before {
+                break;
+            }
+        }
+
+        if edges.len() > 1 {
+            Some(patterns)
+        } else {
+            None
+        }
+    }
+
     /// Detects if a join tree is a star query pattern
     fn is_star_query(&self, plan: &LogicalOperator) -> Option<Vec<(String, Vec<TriplePattern>)>> {
+        // Cyclic queries are joined by a leapfrog join instead (see find_best_plan_recursive)
+        if self.is_cyclic_query(plan).is_some() {
+            return None;
+        }
+
         let mut patterns = Vec::new();
         self.collect_patterns(plan, &mut patterns);
 
@@ -190,6 +277,15 @@ impl Streamertail {
             return plan.clone();
         }
 
+        // Cyclic join trees: a worst-case optimal join avoids the large intermediates
+        // that any tree of binary joins produces for triangles and cliques
+        if let Some(patterns) = self.is_cyclic_query(logical_plan) {
+            let variable_order = leapfrog_variable_order(&patterns);
+            let leapfrog_plan = PhysicalOperator::leapfrog_join(patterns, variable_order);
+            self.memo.insert(key, leapfrog_plan.clone());
+            return leapfrog_plan;
+        }
+
         if let LogicalOperator::Projection { predicate: proj_pred, variables } = logical_plan {
             if let LogicalOperator::Selection { predicate: sel_pred, condition } = proj_pred.as_ref() {
                 if let Some(stars) = self.is_star_query(sel_pred) {
@@ -339,7 +435,7 @@ impl Streamertail {
                 let best_input_plan = self.find_best_plan_recursive(input);
 
                 // Discover model path
-                let model_path = self.discover_model_path();
+                let model_path = Self::discover_model_path();
 
                 // Create the physical ML.PREDICT operator
                 let ml_predict_plan = PhysicalOperator::ml_predict(
@@ -370,7 +466,7 @@ impl Streamertail {
     }
 
     /// Discovers the model path from the model name
-    fn discover_model_path(&self) -> String {
+    pub(crate) fn discover_model_path() -> String {
         let mut path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         
         loop {
@@ -825,4 +921,76 @@ mod tests {
         let pattern = (Term::Constant(1), Term::Constant(2), Term::Constant(3));
         assert_eq!(optimizer.count_bound_variables(&pattern), 3);
     }
+
+    fn edge(from: &str, to: &str) -> LogicalOperator {
+        LogicalOperator::scan((
+            Term::Variable(from.to_string()),
+            Term::Constant(1),
+            Term::Variable(to.to_string()),
+        ))
+    }
+
+    fn triangle() -> LogicalOperator {
+        LogicalOperator::join(LogicalOperator::join(edge("a", "b"), edge("b", "c")), edge("c", "a"))
+    }
+
+    #[test]
+    fn test_cyclic_queries_use_leapfrog_join() {
+        let mut optimizer = create_test_optimizer();
+        assert!(optimizer.is_cyclic_query(&triangle()).is_some());
+
+        let chain = LogicalOperator::join(LogicalOperator::join(edge("a", "b"), edge("b", "c")), edge("c", "d"));
+        assert!(optimizer.is_cyclic_query(&chain).is_none());
+        let star = LogicalOperator::join(LogicalOperator::join(edge("a", "b"), edge("a", "c")), edge("a", "d"));
+        assert!(optimizer.is_cyclic_query(&star).is_none());
+
+        let plan = optimizer.find_best_plan(&LogicalOperator::projection(triangle(), vec!["a".to_string()]));
+        let PhysicalOperator::Projection { input, .. } = plan else { panic!("expected a projection") };
+        assert!(matches!(*input, PhysicalOperator::LeapfrogJoin { .. }));
+    }
+
+    #[test]
+    fn test_leapfrog_join_matches_binary_joins() {
+        let mut database = SparqlDatabase::new();
+        // Two triangles (1-2-3 and 3-4-5) plus edges that only close paths
+        for (from, to) in [(1, 2), (2, 3), (3, 1), (3, 4), (4, 5), (5, 3), (2, 4), (5, 6)] {
+            database.add_triple(shared::triple::Triple { subject: from, predicate: 1, object: to });
+        }
+
+        let mut optimizer = Streamertail::new(&database);
+        let leapfrog = optimizer.find_best_plan(&triangle());
+        let binary = PhysicalOperator::hash_join(
+            PhysicalOperator::hash_join(
+                PhysicalOperator::index_scan((Term::Variable("a".into()), Term::Constant(1), Term::Variable("b".into()))),
+                PhysicalOperator::index_scan((Term::Variable("b".into()), Term::Constant(1), Term::Variable("c".into()))),
+            ),
+            PhysicalOperator::index_scan((Term::Variable("c".into()), Term::Constant(1), Term::Variable("a".into()))),
+        );
+
+        let sorted = |plan: &PhysicalOperator, database: &mut SparqlDatabase| {
+            let mut rows: Vec<Vec<(String, u32)>> = plan
+                .execute_with_ids(database)
+                .into_iter()
+                .map(|row| {
+                    let mut row: Vec<(String, u32)> = row.into_iter().collect();
+                    row.sort();
+                    row
+                })
+                .collect();
+            rows.sort();
+            rows
+        };
+        let expected = sorted(&binary, &mut database);
+        assert_eq!(expected.len(), 6); // each triangle once per rotation
+        assert_eq!(sorted(&leapfrog, &mut database), expected);
+
+        // Variable predicates and variables repeated within a pattern
+        database.add_triple(shared::triple::Triple { subject: 4, predicate: 2, object: 2 });
+        database.add_triple(shared::triple::Triple { subject: 6, predicate: 1, object: 6 });
+        let var = |name: &str| Term::Variable(name.into());
+        let patterns = vec![(var("x"), var("p"), var("y")), (var("y"), var("q"), var("x")), (var("y"), Term::Constant(1), var("y"))];
+        let leapfrog = PhysicalOperator::leapfrog_join(patterns.clone(), leapfrog_variable_order(&patterns));
+        let expected = vec![vec![("p".to_string(), 1), ("q".to_string(), 1), ("x".to_string(), 6), ("y".to_string(), 6)]];
+        assert_eq!(sorted(&leapfrog, &mut database), expected);
+    }
 }
@@ -0,0 +1,187 @@
+/*
+ * Copyright © 2024 Volodymyr Kadzhaia
+ * Copyright © 2024 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::{BTreeMap, HashMap};
+
+/// Subjects sharing exactly the same set of predicates
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CharacteristicSet {
+    pub distinct_subjects: u64,
+    /// Number of triples per predicate over all subjects of the set
+    pub occurrences: HashMap<u32, u64>,
+}
+
+/// Characteristic sets of the database (Neumann & Moerkotte, ICDE 2011),
+/// used to estimate the result size of star joins on a shared subject
+#[derive(Debug, Clone, Default)]
+pub struct CharacteristicSets {
+    subject_predicates: HashMap<u32, BTreeMap<u32, u64>>,
+    sets: HashMap<Vec<u32>, CharacteristicSet>,
+}
+
+impl CharacteristicSets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of distinct characteristic sets
+    pub fn len(&self) -> usize {
+        self.sets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sets.is_empty()
+    }
+
+    pub fn distinct_subjects(&self) -> usize {
+        self.subject_predicates.len()
+    }
+
+    /// Triples per predicate of a subject
+    pub fn predicates_of(&self, subject: u32) -> Option<&BTreeMap<u32, u64>> {
+        self.subject_predicates.get(&subject)
+    }
+
+    pub fn get(&self, predicates: &[u32]) -> Option<&CharacteristicSet> {
+        self.sets.get(predicates)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Vec<u32>, &CharacteristicSet)> {
+        self.sets.iter()
+    }
+
+    /// Records a triple `subject predicate _`, moving the subject to its new set
+    pub fn insert(&mut self, subject: u32, predicate: u32) {
+        self.detach(subject);
+        let predicates = self.subject_predicates.entry(subject).or_default();
+        *predicates.entry(predicate).or_insert(0) += 1;
+        self.attach(subject);
+    }
+
+    /// Forgets a triple `subject predicate _`
+    pub fn remove(&mut self, subject: u32, predicate: u32) {
+        let known = self
+            .subject_predicates
+            .get(&subject)
+            .is_some_and(|predicates| predicates.contains_key(&predicate));
+        if !known {
+            return;
+        }
+        self.detach(subject);
+        let predicates = self.subject_predicates.get_mut(&subject).unwrap();
+        if let Some(count) = predicates.get_mut(&predicate) {
+            *count -= 1;
+            if *count == 0 {
+                predicates.remove(&predicate);
+            }
+        }
+        if predicates.is_empty() {
+            self.subject_predicates.remove(&subject);
+        } else {
+            self.attach(subject);
+        }
+    }
+
+    fn attach(&mut self, subject: u32) {
+        let Some(predicates) = self.subject_predicates.get(&subject) else {
+            return;
+        };
+        let set = self.sets.entry(predicates.keys().copied().collect()).or_default();
+        set.distinct_subjects += 1;
+        for (&predicate, &count) in predicates {
+            *set.occurrences.entry(predicate).or_insert(0) += count;
+        }
+    }
+
+    fn detach(&mut self, subject: u32) {
+        let Some(predicates) = self.subject_predicates.get(&subject) else {
+            return;
+        };
+        let key: Vec<u32> = predicates.keys().copied().collect();
+        let Some(set) = self.sets.get_mut(&key) else {
+            return;
+        };
+        set.distinct_subjects -= 1;
+        for (predicate, count) in predicates {
+            if let Some(occurrences) = set.occurrences.get_mut(predicate) {
+                *occurrences -= count;
+            }
+        }
+        if set.distinct_subjects == 0 {
+            self.sets.remove(&key);
+        }
+    }
+
+    /// Estimated number of results of a star join `?s p1 ?o1 . ?s p2 ?o2 ...`
+    /// with the given predicates (repeated predicates allowed).
+    ///
+    /// Sums, over every set containing all the predicates, the number of its
+    /// subjects times the average number of objects per subject for each pattern.
+    pub fn estimate_star(&self, predicates: &[u32]) -> f64 {
+        self.sets
+            .iter()
+            .filter(|(key, _)| predicates.iter().all(|p| key.binary_search(p).is_ok()))
+            .map(|(_, set)| {
+                let subjects = set.distinct_subjects as f64;
+                predicates.iter().fold(subjects, |estimate, predicate| {
+                    let occurrences = set.occurrences.get(predicate).copied().unwrap_or(0) as f64;
+                    estimate * occurrences / subjects
+                })
+            })
+            .sum()
+    }
+
+    /// Estimated number of distinct subjects having all the predicates
+    pub fn subjects_with(&self, predicates: &[u32]) -> u64 {
+        self.sets
+            .iter()
+            .filter(|(key, _)| predicates.iter().all(|p| key.binary_search(p).is_ok()))
+            .map(|(_, set)| set.distinct_subjects)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn star_estimates_are_exact_for_uniform_sets() {
+        let mut sets = CharacteristicSets::new();
+        // Ten people with a name and two emails, five with only a name
+        for person in 0..15 {
+            sets.insert(person, 1);
+            if person < 10 {
+                sets.insert(person, 2);
+                sets.insert(person, 2);
+            }
+        }
+        assert_eq!(sets.len(), 2);
+        assert_eq!(sets.estimate_star(&[1]), 15.0);
+        assert_eq!(sets.estimate_star(&[1, 2]), 20.0);
+        assert_eq!(sets.subjects_with(&[2]), 10);
+        assert_eq!(sets.estimate_star(&[3]), 0.0);
+    }
+
+    #[test]
+    fn removal_moves_subjects_back() {
+        let mut sets = CharacteristicSets::new();
+        sets.insert(1, 10);
+        sets.insert(1, 20);
+        sets.insert(2, 10);
+        sets.remove(1, 20);
+        assert_eq!(sets.len(), 1);
+        assert_eq!(sets.get(&[10]).unwrap().distinct_subjects, 2);
+        sets.remove(1, 10);
+        sets.remove(2, 10);
+        assert!(sets.is_empty());
+        assert_eq!(sets.distinct_subjects(), 0);
+    }
+}
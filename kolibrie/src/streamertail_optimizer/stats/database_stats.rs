@@ -8,8 +8,11 @@
  * you can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+use super::characteristic_sets::CharacteristicSets;
+use super::histogram::{parse_numeric_literal, NumericHistogram};
 use crate::sparql_database::SparqlDatabase;
-use rayon::prelude::*;
+use shared::dictionary::Dictionary;
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::sync::RwLock;
 
@@ -23,6 +26,18 @@ pub struct DatabaseStats {
     pub object_cardinalities: HashMap<u32, u64>,
     pub join_selectivity_cache: RwLock<HashMap<u32, f64>>,
     pub predicate_histogram: HashMap<u32, Vec<(u32, u64)>>, // For better selectivity estimation
+    /// Subjects grouped by their predicate sets, for star join estimates
+    pub characteristic_sets: CharacteristicSets,
+    /// Triples per predicate pointing at each object
+    pub object_predicates: HashMap<u32, HashMap<u32, u64>>,
+    pub predicate_distinct_subjects: HashMap<u32, u64>,
+    pub predicate_distinct_objects: HashMap<u32, u64>,
+    /// Matches of `?x p1 ?y . ?y p2 ?z` per predicate pair `(p1, p2)`
+    pub predicate_chain_counts: HashMap<(u32, u32), u64>,
+    /// Distribution of the numeric objects of each predicate
+    pub numeric_histograms: HashMap<u32, NumericHistogram>,
+    /// Numeric value of the literals seen so far, by dictionary id
+    pub numeric_values: HashMap<u32, f64>,
 }
 
 impl DatabaseStats {
@@ -36,71 +51,134 @@ impl DatabaseStats {
             object_cardinalities: HashMap::new(),
             join_selectivity_cache: RwLock::new(HashMap::new()),
             predicate_histogram: HashMap::new(),
+            characteristic_sets: CharacteristicSets::new(),
+            object_predicates: HashMap::new(),
+            predicate_distinct_subjects: HashMap::new(),
+            predicate_distinct_objects: HashMap::new(),
+            predicate_chain_counts: HashMap::new(),
+            numeric_histograms: HashMap::new(),
+            numeric_values: HashMap::new(),
         }
     }
 
-    /// Gathers statistics from the database using sampling for performance
+    /// Gathers statistics from a full scan of the database. Characteristic
+    /// sets and chain counts need every triple, so the cardinalities and
+    /// histograms are counted in the same pass rather than sampled; the
+    /// result matches what `update_stats` maintains incrementally.
     pub fn gather_stats_fast(database: &SparqlDatabase) -> Self {
-        let total_triples = database.triples.len() as u64;
-
-        // Convert BTreeSet to Vec for sampling
-        let triples_vec: Vec<_> = database.triples.iter().collect();
-
-        // Use sampling for large datasets instead of full scan
-        let sample_size = (total_triples as usize).min(100_000);
-        let step = if total_triples > sample_size as u64 {
-            total_triples as usize / sample_size
-        } else {
-            1
+        let quoted_triple_count = database.quoted_triple_store.read().unwrap().len() as u64;
+        let mut stats = Self {
+            quoted_triple_count,
+            ..Self::new()
         };
 
-        // Sample the data by stepping through the vector
-        let sampled_triples: Vec<_> = triples_vec.iter().step_by(step).take(sample_size).collect();
-
-        // Use parallel processing for stats gathering
-        let stats_data: Vec<_> = sampled_triples
-            .par_iter()
-            .map(|triple| {
-                let subject = triple.subject;
-                let predicate = triple.predicate;
-                let object = triple.object;
-                (subject, predicate, object)
-            })
+        let dictionary = database.dictionary.read().unwrap();
+        let mut decoded: HashMap<u32, Option<f64>> = HashMap::new();
+        let mut numeric_objects: HashMap<u32, Vec<f64>> = HashMap::new();
+        for triple in &database.triples {
+            stats.count_triple(triple.subject, triple.predicate, triple.object);
+            let value = *decoded
+                .entry(triple.object)
+                .or_insert_with(|| dictionary.decode(triple.object).and_then(parse_numeric_literal));
+            if let Some(value) = value {
+                numeric_objects.entry(triple.predicate).or_default().push(value);
+            }
+        }
+        stats.numeric_values = decoded
+            .into_iter()
+            .filter_map(|(id, value)| value.map(|value| (id, value)))
+            .collect();
+        stats.numeric_histograms = numeric_objects
+            .into_iter()
+            .map(|(predicate, values)| (predicate, NumericHistogram::from_values(values)))
             .collect();
+        stats
+    }
 
-        // Build cardinality maps
-        let mut predicate_cardinalities: HashMap<u32, u64> = HashMap::new();
-        let mut subject_cardinalities: HashMap<u32, u64> = HashMap::new();
-        let mut object_cardinalities: HashMap<u32, u64> = HashMap::new();
-
-        for (subject, predicate, object) in stats_data {
-            *predicate_cardinalities.entry(predicate).or_insert(0) += 1;
-            *subject_cardinalities.entry(subject).or_insert(0) += 1;
-            *object_cardinalities.entry(object).or_insert(0) += 1;
-        }
-
-        // Scale up sampled statistics
-        let scale_factor = if step > 1 { step as u64 } else { 1 };
-        predicate_cardinalities
-            .values_mut()
-            .for_each(|v| *v *= scale_factor);
-        subject_cardinalities
-            .values_mut()
-            .for_each(|v| *v *= scale_factor);
-        object_cardinalities
-            .values_mut()
-            .for_each(|v| *v *= scale_factor);
+    /// Adds a triple to the cardinalities and to the structural statistics
+    fn count_triple(&mut self, subject: u32, predicate: u32, object: u32) {
+        self.total_triples += 1;
+        *self.predicate_cardinalities.entry(predicate).or_insert(0) += 1;
+        *self.subject_cardinalities.entry(subject).or_insert(0) += 1;
+        *self.object_cardinalities.entry(object).or_insert(0) += 1;
+        self.record_triple(subject, predicate, object);
+    }
 
-        let quoted_triple_count = database.quoted_triple_store.read().unwrap().len() as u64;
+    /// Adds a triple to the characteristic sets, distinct counts and chain counts
+    fn record_triple(&mut self, subject: u32, predicate: u32, object: u32) {
+        // Chains the new triple forms with the ones already recorded
+        if let Some(next) = self.characteristic_sets.predicates_of(object) {
+            for (&next_predicate, &count) in next {
+                *self.predicate_chain_counts.entry((predicate, next_predicate)).or_insert(0) += count;
+            }
+        }
+        if let Some(previous) = self.object_predicates.get(&subject) {
+            for (&previous_predicate, &count) in previous {
+                *self.predicate_chain_counts.entry((previous_predicate, predicate)).or_insert(0) += count;
+            }
+        }
+        if subject == object {
+            *self.predicate_chain_counts.entry((predicate, predicate)).or_insert(0) += 1;
+        }
 
-        Self {
-            total_triples,
-            quoted_triple_count,
-            predicate_cardinalities,
-            subject_cardinalities,
-            object_cardinalities,
-            join_selectivity_cache: RwLock::new(HashMap::new()),
-            predicate_histogram: HashMap::new(),
+        let new_subject = self
+            .characteristic_sets
+            .predicates_of(subject)
+            .is_none_or(|predicates| !predicates.contains_key(&predicate));
+        if new_subject {
+            *self.predicate_distinct_subjects.entry(predicate).or_insert(0) += 1;
+        }
+        self.characteristic_sets.insert(subject, predicate);
+
+        let count = self.object_predicates.entry(object).or_default().entry(predicate).or_insert(0);
+        if *count == 0 {
+            *self.predicate_distinct_objects.entry(predicate).or_insert(0) += 1;
+        }
+        *count += 1;
+    }
+
+    /// Inverse of `record_triple`
+    fn forget_triple(&mut self, subject: u32, predicate: u32, object: u32) {
+        let known = self
+            .object_predicates
+            .get(&object)
+            .is_some_and(|predicates| predicates.contains_key(&predicate));
+        if !known {
+            return;
+        }
+
+        self.characteristic_sets.remove(subject, predicate);
+        let gone_subject = self
+            .characteristic_sets
+            .predicates_of(subject)
+            .is_none_or(|predicates| !predicates.contains_key(&predicate));
+        if gone_subject {
+            decrement(&mut self.predicate_distinct_subjects, &predicate, 1);
+        }
+
+        let predicates = self.object_predicates.get_mut(&object).unwrap();
+        let count = predicates.get_mut(&predicate).unwrap();
+        *count -= 1;
+        if *count == 0 {
+            predicates.remove(&predicate);
+            decrement(&mut self.predicate_distinct_objects, &predicate, 1);
+        }
+        if predicates.is_empty() {
+            self.object_predicates.remove(&object);
+        }
+
+        if let Some(next) = self.characteristic_sets.predicates_of(object) {
+            for (&next_predicate, &count) in next {
+                decrement(&mut self.predicate_chain_counts, &(predicate, next_predicate), count);
+            }
+        }
+        if let Some(previous) = self.object_predicates.get(&subject) {
+            for (&previous_predicate, &count) in previous {
+                decrement(&mut self.predicate_chain_counts, &(previous_predicate, predicate), count);
+            }
+        }
+        if subject == object {
+            decrement(&mut self.predicate_chain_counts, &(predicate, predicate), 1);
         }
     }
 
@@ -152,12 +230,67 @@ impl DatabaseStats {
         selectivity
     }
 
+    /// Distinct subjects, predicates and objects in the database
+    pub fn distinct_counts(&self) -> (u64, u64, u64) {
+        (
+            self.characteristic_sets.distinct_subjects() as u64,
+            self.predicate_distinct_subjects.len() as u64,
+            self.object_predicates.len() as u64,
+        )
+    }
+
+    /// Distinct subjects having the predicate
+    pub fn get_predicate_distinct_subjects(&self, predicate: u32) -> u64 {
+        self.predicate_distinct_subjects.get(&predicate).copied().unwrap_or(0)
+    }
+
+    /// Distinct objects of the predicate
+    pub fn get_predicate_distinct_objects(&self, predicate: u32) -> u64 {
+        self.predicate_distinct_objects.get(&predicate).copied().unwrap_or(0)
+    }
+
+    /// Triples with the predicate and the object
+    pub fn get_predicate_object_count(&self, predicate: u32, object: u32) -> u64 {
+        self.object_predicates
+            .get(&object)
+            .and_then(|predicates| predicates.get(&predicate))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Matches of the chain `?x first ?y . ?y second ?z`
+    pub fn get_chain_count(&self, first: u32, second: u32) -> u64 {
+        self.predicate_chain_counts.get(&(first, second)).copied().unwrap_or(0)
+    }
+
+    /// Estimated result size of a star join on a shared subject
+    pub fn estimate_star(&self, predicates: &[u32]) -> f64 {
+        self.characteristic_sets.estimate_star(predicates)
+    }
+
+    /// Fraction of the objects of a predicate satisfying `? op value`, when
+    /// the predicate has numeric objects
+    pub fn numeric_selectivity(&self, predicate: u32, op: &str, value: f64) -> Option<f64> {
+        self.numeric_histograms.get(&predicate)?.selectivity(op, value)
+    }
+
+    /// Like `update_stats`, decoding the object to keep the numeric histograms
+    /// current when it is a literal not seen before
+    pub fn update_stats_with_dictionary(&mut self, subject: u32, predicate: u32, object: u32, dictionary: &Dictionary) {
+        if let Entry::Vacant(entry) = self.numeric_values.entry(object) {
+            if let Some(value) = dictionary.decode(object).and_then(parse_numeric_literal) {
+                entry.insert(value);
+            }
+        }
+        self.update_stats(subject, predicate, object);
+    }
+
     /// Updates statistics with new data
     pub fn update_stats(&mut self, subject: u32, predicate: u32, object: u32) {
-        self.total_triples += 1;
-        *self.predicate_cardinalities.entry(predicate).or_insert(0) += 1;
-        *self.subject_cardinalities.entry(subject).or_insert(0) += 1;
-        *self.object_cardinalities.entry(object).or_insert(0) += 1;
+        self.count_triple(subject, predicate, object);
+        if let Some(&value) = self.numeric_values.get(&object) {
+            self.numeric_histograms.entry(predicate).or_default().insert(value);
+        }
 
         // Clear cache as statistics have changed
         self.join_selectivity_cache.write().unwrap().clear();
@@ -187,13 +320,104 @@ impl DatabaseStats {
             }
         }
 
+        if let Some(&value) = self.numeric_values.get(&object) {
+            if let Some(histogram) = self.numeric_histograms.get_mut(&predicate) {
+                histogram.remove(value);
+            }
+        }
+        self.forget_triple(subject, predicate, object);
+
         // Clear cache as statistics have changed
         self.join_selectivity_cache.write().unwrap().clear();
     }
 }
 
+fn decrement<K: std::hash::Hash + Eq>(counts: &mut HashMap<K, u64>, key: &K, by: u64) {
+    if let Some(count) = counts.get_mut(key) {
+        *count = count.saturating_sub(by);
+        if *count == 0 {
+            counts.remove(key);
+        }
+    }
+}
+
 impl Default for DatabaseStats {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::triple::Triple;
+
+    fn people_database() -> SparqlDatabase {
+        let mut database = SparqlDatabase::new();
+        for person in 0..10 {
+            let subject = format!("http://example.org/person{}", person);
+            database.add_triple_parts(&subject, "http://example.org/age", &(20 + person).to_string());
+            if person < 4 {
+                let friend = format!("http://example.org/person{}", person + 1);
+                database.add_triple_parts(&subject, "http://example.org/knows", &friend);
+            }
+        }
+        database
+    }
+
+    fn id(database: &SparqlDatabase, term: &str) -> u32 {
+        database.dictionary.write().unwrap().encode(term)
+    }
+
+    #[test]
+    fn gathers_structural_statistics() {
+        let database = people_database();
+        let stats = DatabaseStats::gather_stats_fast(&database);
+        let age = id(&database, "http://example.org/age");
+        let knows = id(&database, "http://example.org/knows");
+
+        assert_eq!(stats.characteristic_sets.len(), 2);
+        assert_eq!(stats.estimate_star(&[age, knows]), 4.0);
+        assert_eq!(stats.get_predicate_distinct_subjects(knows), 4);
+        assert_eq!(stats.get_predicate_distinct_objects(age), 10);
+        assert_eq!(stats.distinct_counts(), (10, 2, 14));
+        // person0 knows person1 ... person3 knows person4, all of them have an age
+        assert_eq!(stats.get_chain_count(knows, age), 4);
+        assert_eq!(stats.get_chain_count(knows, knows), 3);
+
+        let above = stats.numeric_selectivity(age, ">", 24.0).unwrap();
+        assert!((above - 0.5).abs() < 0.1, "{}", above);
+        assert_eq!(stats.numeric_selectivity(knows, ">", 24.0), None);
+    }
+
+    #[test]
+    fn incremental_maintenance_matches_a_fresh_gather() {
+        let mut database = people_database();
+        database.get_or_build_stats();
+        database.add_triple_parts("http://example.org/person9", "http://example.org/knows", "http://example.org/person0");
+        database.add_triple_parts("http://example.org/person10", "http://example.org/age", "55");
+        let age = id(&database, "http://example.org/age");
+        let knows = id(&database, "http://example.org/knows");
+        let removed = Triple {
+            subject: id(&database, "http://example.org/person0"),
+            predicate: knows,
+            object: id(&database, "http://example.org/person1"),
+        };
+        database.delete_triple(&removed);
+
+        let maintained = database.get_or_build_stats();
+        let fresh = DatabaseStats::gather_stats_fast(&database);
+        assert_eq!(maintained.total_triples, fresh.total_triples);
+        assert_eq!(maintained.predicate_cardinalities, fresh.predicate_cardinalities);
+        assert_eq!(maintained.distinct_counts(), fresh.distinct_counts());
+        assert_eq!(maintained.predicate_chain_counts, fresh.predicate_chain_counts);
+        assert_eq!(maintained.predicate_distinct_subjects, fresh.predicate_distinct_subjects);
+        assert_eq!(maintained.predicate_distinct_objects, fresh.predicate_distinct_objects);
+        for (predicates, set) in fresh.characteristic_sets.iter() {
+            assert_eq!(maintained.characteristic_sets.get(predicates), Some(set));
+        }
+        assert_eq!(maintained.estimate_star(&[age, knows]), 4.0);
+        assert_eq!(maintained.numeric_histograms[&age].total(), 11);
+        assert!(maintained.numeric_selectivity(age, ">", 50.0).unwrap() > 0.0);
+    }
+}
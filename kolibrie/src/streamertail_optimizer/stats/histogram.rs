@@ -0,0 +1,204 @@
+/*
+ * Copyright © 2024 Volodymyr Kadzhaia
+ * Copyright © 2024 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/// Number of buckets of a freshly built histogram
+pub const HISTOGRAM_BUCKETS: usize = 32;
+
+/// Equi-depth histogram over the numeric objects of one predicate.
+///
+/// Bucket `i` covers `(upper[i - 1], upper[i]]`, the first one starting at `min`.
+/// Buckets are balanced when the histogram is built; later insertions and
+/// removals adjust the counts of the bucket a value falls in, widening the
+/// outer buckets for values outside the known range.
+#[derive(Debug, Clone, Default)]
+pub struct NumericHistogram {
+    min: f64,
+    upper: Vec<f64>,
+    counts: Vec<u64>,
+    /// Distinct values per bucket when the histogram was built
+    distinct: Vec<u64>,
+    total: u64,
+}
+
+impl NumericHistogram {
+    pub fn from_values(mut values: Vec<f64>) -> Self {
+        values.retain(|value| value.is_finite());
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_by(|a, b| a.total_cmp(b));
+
+        let depth = values.len().div_ceil(HISTOGRAM_BUCKETS);
+        let mut histogram = NumericHistogram {
+            min: values[0],
+            total: values.len() as u64,
+            ..Self::default()
+        };
+        let mut start = 0;
+        while start < values.len() {
+            // Equal values never straddle two buckets
+            let mut end = (start + depth).min(values.len());
+            while end < values.len() && values[end] == values[end - 1] {
+                end += 1;
+            }
+            let bucket = &values[start..end];
+            histogram.upper.push(bucket[bucket.len() - 1]);
+            histogram.counts.push(bucket.len() as u64);
+            histogram.distinct.push(1 + bucket.windows(2).filter(|pair| pair[0] != pair[1]).count() as u64);
+            start = end;
+        }
+        histogram
+    }
+
+    /// Number of values in the histogram
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn insert(&mut self, value: f64) {
+        if !value.is_finite() {
+            return;
+        }
+        if self.upper.is_empty() {
+            *self = Self::from_values(vec![value]);
+            return;
+        }
+        if value < self.min {
+            self.min = value;
+        }
+        let bucket = self.bucket_of(value).unwrap_or_else(|| {
+            let last = self.upper.len() - 1;
+            self.upper[last] = value;
+            last
+        });
+        self.counts[bucket] += 1;
+        self.total += 1;
+    }
+
+    pub fn remove(&mut self, value: f64) {
+        if let Some(bucket) = self.bucket_of(value) {
+            if self.counts[bucket] > 0 {
+                self.counts[bucket] -= 1;
+                self.total -= 1;
+            }
+        }
+    }
+
+    fn bucket_of(&self, value: f64) -> Option<usize> {
+        let bucket = self.upper.partition_point(|&upper| upper < value);
+        (bucket < self.upper.len()).then_some(bucket)
+    }
+
+    /// Estimated fraction of the values below `value`, or at most `value` when
+    /// `inclusive`, interpolating linearly within a bucket
+    fn fraction_below(&self, value: f64, inclusive: bool) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        let mut below = 0.0;
+        let mut lower = self.min;
+        for (idx, &upper) in self.upper.iter().enumerate() {
+            let count = self.counts[idx] as f64;
+            if value > upper || (inclusive && value == upper) {
+                below += count;
+            } else if value > lower {
+                below += count * (value - lower) / (upper - lower);
+                break;
+            } else {
+                break;
+            }
+            lower = upper;
+        }
+        (below / self.total as f64).clamp(0.0, 1.0)
+    }
+
+    /// Estimated fraction of the values satisfying `? op value`
+    pub fn selectivity(&self, op: &str, value: f64) -> Option<f64> {
+        if self.total == 0 {
+            return None;
+        }
+        let equal = || match self.bucket_of(value) {
+            Some(bucket) if value >= self.min => {
+                self.counts[bucket] as f64 / self.distinct[bucket].max(1) as f64 / self.total as f64
+            }
+            _ => 0.0,
+        };
+        let selectivity = match op {
+            "<" => self.fraction_below(value, false),
+            "<=" => self.fraction_below(value, true),
+            ">" => 1.0 - self.fraction_below(value, true),
+            ">=" => 1.0 - self.fraction_below(value, false),
+            "=" => equal(),
+            "!=" => 1.0 - equal(),
+            _ => return None,
+        };
+        Some(selectivity.clamp(0.0, 1.0))
+    }
+}
+
+/// Numeric value of a literal as stored in the dictionary: `30`, `"30"` or
+/// `"30"^^<http://www.w3.org/2001/XMLSchema#integer>`
+pub fn parse_numeric_literal(literal: &str) -> Option<f64> {
+    let lexical = match literal.strip_prefix('"') {
+        Some(rest) => &rest[..rest.find('"')?],
+        None => literal,
+    };
+    lexical.trim().parse::<f64>().ok().filter(|value| value.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range_selectivity_follows_the_distribution() {
+        let histogram = NumericHistogram::from_values((0..1000).map(|v| v as f64).collect());
+        let below = histogram.selectivity("<", 250.0).unwrap();
+        assert!((below - 0.25).abs() < 0.01, "{}", below);
+        let above = histogram.selectivity(">", 899.0).unwrap();
+        assert!((above - 0.1).abs() < 0.01, "{}", above);
+        assert_eq!(histogram.selectivity(">", 5000.0), Some(0.0));
+        assert_eq!(histogram.selectivity(">=", -1.0), Some(1.0));
+    }
+
+    #[test]
+    fn skewed_values_get_their_own_buckets() {
+        let mut values = vec![7.0; 900];
+        values.extend((0..100).map(|v| 100.0 + v as f64));
+        let histogram = NumericHistogram::from_values(values);
+        let equal = histogram.selectivity("=", 7.0).unwrap();
+        assert!((equal - 0.9).abs() < 0.01, "{}", equal);
+        let above = histogram.selectivity(">", 7.0).unwrap();
+        assert!((above - 0.1).abs() < 0.01, "{}", above);
+    }
+
+    #[test]
+    fn insertions_and_removals_adjust_counts() {
+        let mut histogram = NumericHistogram::from_values(vec![1.0, 2.0, 3.0, 4.0]);
+        histogram.insert(10.0);
+        histogram.insert(-5.0);
+        assert_eq!(histogram.total(), 6);
+        assert_eq!(histogram.selectivity("<=", 10.0), Some(1.0));
+        histogram.remove(10.0);
+        histogram.remove(-5.0);
+        assert_eq!(histogram.total(), 4);
+    }
+
+    #[test]
+    fn literals_are_parsed_with_or_without_datatypes() {
+        assert_eq!(parse_numeric_literal("30"), Some(30.0));
+        assert_eq!(parse_numeric_literal("\"2.5\""), Some(2.5));
+        assert_eq!(
+            parse_numeric_literal("\"42\"^^<http://www.w3.org/2001/XMLSchema#integer>"),
+            Some(42.0)
+        );
+        assert_eq!(parse_numeric_literal("http://example.org/a"), None);
+    }
+}
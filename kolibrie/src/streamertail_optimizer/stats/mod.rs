@@ -8,6 +8,10 @@
  * you can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+pub mod characteristic_sets;
 pub mod database_stats;
+pub mod histogram;
 
+pub use characteristic_sets::{CharacteristicSet, CharacteristicSets};
 pub use database_stats::DatabaseStats;
+pub use histogram::NumericHistogram;
@@ -12,7 +12,7 @@ use shared::{dictionary::Dictionary, query::FilterExpression};
 use std::collections::HashMap;
 
 /// Represents a condition for filtering operations
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Condition {
     pub expression: FilterExpression<'static>,
 }
@@ -51,6 +51,11 @@ impl Condition {
         match expr {
             FilterExpression::Comparison(var, op, value) => {
                 let var_name = var.strip_prefix('?').unwrap_or(var);
+                // A variable on the right is compared by its value
+                let value = match value.strip_prefix('?').and_then(|name| result.get(name)) {
+                    Some(bound) => bound.as_str(),
+                    None => *value,
+                };
                 if let Some(result_value) = result.get(var_name) {
                     match *op {
                         "=" => result_value == value,
@@ -125,11 +130,16 @@ impl Condition {
         match expr {
             FilterExpression::Comparison(var, op, value) => {
                 let var_name = var.strip_prefix('?').unwrap_or(var);
+                // A variable on the right is compared by its value
+                let value = match value.strip_prefix('?').and_then(|name| result.get(name)) {
+                    Some(&bound) => dictionary.decode(bound).unwrap_or(value),
+                    None => *value,
+                };
                 if let Some(&id) = result.get(var_name) {
                     let decoded_value = dictionary.decode(id).unwrap();
                     match *op {
-                        "=" => decoded_value == *value,
-                        "!=" => decoded_value != *value,
+                        "=" => decoded_value == value,
+                        "!=" => decoded_value != value,
                         ">" => decoded_value.parse::<f64>().unwrap_or(0.0) 
                             > value.parse::<f64>().unwrap_or(0.0),
                         ">=" => decoded_value.parse::<f64>().unwrap_or(0.0) 
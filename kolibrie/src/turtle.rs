@@ -0,0 +1,602 @@
+/*
+ * Copyright © 2025 Volodymyr Kadzhaia
+ * Copyright © 2025 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+
+/// Labels of the blank nodes made for `[ ]` and `( )`, unique per process
+static FRESH_BLANK_NODES: AtomicUsize = AtomicUsize::new(0);
+
+/// A parsed term: IRIs without brackets, blank nodes as `_:label`, plain
+/// literals as their lexical form, followed by `@lang` when tagged, and typed
+/// literals (numbers and booleans included) as `"lexical"^^<datatype>`.
+/// Unlike the parsers of `SparqlDatabase`, datatypes are kept, as SHACL
+/// validation needs them.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TurtleTerm {
+    Term(String),
+    Quoted(Box<TurtleTriple>),
+}
+
+pub(crate) type TurtleTriple = [TurtleTerm; 3];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Iri(String),
+    PrefixedName(String, String),
+    BlankNode(String),
+    String(String),
+    LanguageTag(String),
+    Datatype,
+    Number(String),
+    /// `a`, `true`, `false` and the directives
+    Word(String),
+    Punct(&'static str),
+    Invalid(String),
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Iri(iri) => format!("<{}>", iri),
+            Token::PrefixedName(prefix, local) => format!("{}:{}", prefix, local),
+            Token::BlankNode(label) => format!("_:{}", label),
+            Token::String(lexical) => format!("\"{}\"", lexical),
+            Token::LanguageTag(tag) => format!("@{}", tag),
+            Token::Datatype => "^^".to_string(),
+            Token::Number(number) | Token::Word(number) => number.clone(),
+            Token::Punct(punct) => punct.to_string(),
+            Token::Invalid(message) => message.clone(),
+        }
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':' | '%')
+}
+
+/// Reads a name starting at `start`; a name never ends with a dot, which
+/// closes the statement instead
+fn read_name(chars: &[char], start: usize) -> (String, usize) {
+    let mut name = String::new();
+    let mut i = start;
+    while i < chars.len() {
+        if chars[i] == '\\' && i + 1 < chars.len() {
+            name.push(chars[i + 1]);
+            i += 2;
+        } else if is_name_char(chars[i]) {
+            name.push(chars[i]);
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    while name.ends_with('.') {
+        name.pop();
+        i -= 1;
+    }
+    (name, i)
+}
+
+/// Reads a short or long (`"""`) string starting at `start`, resolving its
+/// escapes; returns the lexical form and the position after the string
+fn read_string(chars: &[char], start: usize) -> Result<(String, usize), String> {
+    let quote = chars[start];
+    let long = chars.get(start + 1) == Some(&quote) && chars.get(start + 2) == Some(&quote);
+    let mut i = start + if long { 3 } else { 1 };
+    let mut lexical = String::new();
+    loop {
+        let Some(&c) = chars.get(i) else {
+            return Err("unterminated string".to_string());
+        };
+        if c == '\\' {
+            let escape = chars.get(i + 1).copied().unwrap_or_default();
+            i += 2;
+            match escape {
+                't' => lexical.push('\t'),
+                'n' => lexical.push('\n'),
+                'r' => lexical.push('\r'),
+                'b' => lexical.push('\u{8}'),
+                'f' => lexical.push('\u{c}'),
+                'u' | 'U' => {
+                    let digits = if escape == 'u' { 4 } else { 8 };
+                    let code: String = chars.iter().skip(i).take(digits).collect();
+                    let decoded = u32::from_str_radix(&code, 16).ok().and_then(char::from_u32);
+                    lexical.push(decoded.ok_or_else(|| format!("invalid escape \\{}{}", escape, code))?);
+                    i += digits;
+                }
+                other => lexical.push(other),
+            }
+        } else if long && c == quote && chars.get(i + 1) == Some(&quote) && chars.get(i + 2) == Some(&quote) {
+            return Ok((lexical, i + 3));
+        } else if !long && c == quote {
+            return Ok((lexical, i + 1));
+        } else if !long && c == '\n' {
+            return Err("unterminated string".to_string());
+        } else {
+            lexical.push(c);
+            i += 1;
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '#' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+        let pair: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+        if let Some(punct) = ["<<", ">>", "{|", "|}"].into_iter().find(|punct| *punct == pair) {
+            tokens.push(Token::Punct(punct));
+            i += 2;
+            continue;
+        }
+        if pair == "^^" {
+            tokens.push(Token::Datatype);
+            i += 2;
+            continue;
+        }
+        match c {
+            '<' => match chars[i + 1..].iter().position(|&c| c == '>' || c.is_whitespace()) {
+                Some(end) if chars[i + 1 + end] == '>' => {
+                    tokens.push(Token::Iri(chars[i + 1..i + 1 + end].iter().collect()));
+                    i += end + 2;
+                }
+                _ => {
+                    tokens.push(Token::Invalid("unterminated IRI".to_string()));
+                    i += 1;
+                }
+            },
+            '"' | '\'' => match read_string(&chars, i) {
+                Ok((lexical, end)) => {
+                    tokens.push(Token::String(lexical));
+                    i = end;
+                }
+                Err(message) => {
+                    tokens.push(Token::Invalid(message));
+                    i = chars.len();
+                }
+            },
+            '@' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&c| !(c.is_ascii_alphanumeric() || c == '-'))
+                    .map_or(chars.len(), |end| i + 1 + end);
+                let word: String = chars[i + 1..end].iter().collect();
+                tokens.push(match word.as_str() {
+                    "prefix" | "base" => Token::Word(format!("@{}", word)),
+                    _ => Token::LanguageTag(word),
+                });
+                i = end;
+            }
+            '_' if next == Some(':') => {
+                let (label, end) = read_name(&chars, i + 2);
+                tokens.push(Token::BlankNode(label));
+                i = end;
+            }
+            '.' | ';' | ',' | '[' | ']' | '(' | ')' => {
+                let punct = [".", ";", ",", "[", "]", "(", ")"].into_iter().find(|p| p.starts_with(c)).unwrap();
+                tokens.push(Token::Punct(punct));
+                i += 1;
+            }
+            c if c.is_ascii_digit()
+                || (matches!(c, '+' | '-' | '.') && next.is_some_and(|n| n.is_ascii_digit())) =>
+            {
+                let mut end = i + 1;
+                while end < chars.len()
+                    && (chars[end].is_ascii_digit()
+                        || matches!(chars[end], '.' | 'e' | 'E')
+                        || (matches!(chars[end], '+' | '-') && matches!(chars[end - 1], 'e' | 'E')))
+                {
+                    end += 1;
+                }
+                while chars[end - 1] == '.' {
+                    end -= 1;
+                }
+                tokens.push(Token::Number(chars[i..end].iter().collect()));
+                i = end;
+            }
+            c if is_name_char(c) => {
+                let (name, end) = read_name(&chars, i);
+                tokens.push(match name.split_once(':') {
+                    Some((prefix, local)) => Token::PrefixedName(prefix.to_string(), local.to_string()),
+                    None => Token::Word(name),
+                });
+                i = end.max(i + 1);
+            }
+            other => {
+                tokens.push(Token::Invalid(format!("unexpected character '{}'", other)));
+                i += 1;
+            }
+        }
+    }
+    tokens
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    prefixes: &'a mut HashMap<String, String>,
+    base: Option<String>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn advance(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Punct(p)) if *p == punct);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), String> {
+        if self.eat(punct) {
+            return Ok(());
+        }
+        Err(match self.peek() {
+            Some(token) => format!("expected '{}', found {}", punct, token.describe()),
+            None => format!("expected '{}' at the end of the input", punct),
+        })
+    }
+
+    fn unexpected(token: Option<&Token>) -> String {
+        match token {
+            Some(Token::Invalid(message)) => message.clone(),
+            Some(token) => format!("unexpected {}", token.describe()),
+            None => "unexpected end of the input".to_string(),
+        }
+    }
+
+    /// Skips the rest of a statement starting at `start` that failed to parse
+    fn skip_statement(&mut self, start: usize) {
+        self.position = self.position.max(start + 1).min(self.tokens.len());
+        if self.tokens[self.position - 1] == Token::Punct(".") {
+            return;
+        }
+        while let Some(token) = self.advance() {
+            if *token == Token::Punct(".") {
+                break;
+            }
+        }
+    }
+
+    fn statement(&mut self, triples: &mut Vec<TurtleTriple>) -> Result<(), String> {
+        match self.peek() {
+            Some(Token::Word(word)) if word == "@prefix" || word.eq_ignore_ascii_case("prefix") => {
+                self.advance();
+                let (prefix, namespace) = match (self.advance(), self.advance()) {
+                    (Some(Token::PrefixedName(prefix, local)), Some(iri @ Token::Iri(_))) if local.is_empty() => {
+                        (prefix.clone(), self.iri(iri)?)
+                    }
+                    _ => return Err("invalid prefix declaration".to_string()),
+                };
+                self.prefixes.insert(prefix, namespace);
+                if word.starts_with('@') {
+                    self.expect(".")?;
+                }
+            }
+            Some(Token::Word(word)) if word == "@base" || word.eq_ignore_ascii_case("base") => {
+                self.advance();
+                match self.advance() {
+                    Some(iri @ Token::Iri(_)) => self.base = Some(self.iri(iri)?),
+                    _ => return Err("invalid base declaration".to_string()),
+                }
+                if word.starts_with('@') {
+                    self.expect(".")?;
+                }
+            }
+            Some(Token::Punct("[")) => {
+                let subject = self.blank_node_property_list(triples)?;
+                if !matches!(self.peek(), Some(Token::Punct("."))) {
+                    self.predicate_object_list(&subject, triples)?;
+                }
+                self.expect(".")?;
+            }
+            _ => {
+                let subject = self.node(triples, false)?;
+                self.predicate_object_list(&subject, triples)?;
+                self.expect(".")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn iri(&self, token: &Token) -> Result<String, String> {
+        match token {
+            Token::Iri(iri) => Ok(match &self.base {
+                Some(base) if !iri.contains(':') => format!("{}{}", base, iri),
+                _ => iri.clone(),
+            }),
+            Token::PrefixedName(prefix, local) => self
+                .prefixes
+                .get(prefix)
+                .map(|namespace| format!("{}{}", namespace, local))
+                .ok_or_else(|| format!("undeclared prefix '{}:'", prefix)),
+            other => Err(Self::unexpected(Some(other))),
+        }
+    }
+
+    fn verb(&mut self) -> Result<TurtleTerm, String> {
+        match self.advance() {
+            Some(Token::Word(word)) if word == "a" => Ok(TurtleTerm::Term(format!("{}type", RDF))),
+            Some(token @ (Token::Iri(_) | Token::PrefixedName(..))) => self.iri(token).map(TurtleTerm::Term),
+            other => Err(format!("expected a predicate: {}", Self::unexpected(other))),
+        }
+    }
+
+    fn predicate_object_list(&mut self, subject: &TurtleTerm, triples: &mut Vec<TurtleTriple>) -> Result<(), String> {
+        loop {
+            let predicate = self.verb()?;
+            loop {
+                let object = self.node(triples, true)?;
+                let triple = [subject.clone(), predicate.clone(), object];
+                triples.push(triple.clone());
+                if self.eat("{|") {
+                    self.predicate_object_list(&TurtleTerm::Quoted(Box::new(triple)), triples)?;
+                    self.expect("|}")?;
+                }
+                if !self.eat(",") {
+                    break;
+                }
+            }
+            if !self.eat(";") {
+                return Ok(());
+            }
+            while self.eat(";") {}
+            if matches!(self.peek(), None | Some(Token::Punct("." | "]" | "|}"))) {
+                return Ok(());
+            }
+        }
+    }
+
+    fn fresh_blank_node() -> TurtleTerm {
+        TurtleTerm::Term(format!("_:genid{}", FRESH_BLANK_NODES.fetch_add(1, Ordering::Relaxed)))
+    }
+
+    fn blank_node_property_list(&mut self, triples: &mut Vec<TurtleTriple>) -> Result<TurtleTerm, String> {
+        self.expect("[")?;
+        let node = Self::fresh_blank_node();
+        if !self.eat("]") {
+            self.predicate_object_list(&node, triples)?;
+            self.expect("]")?;
+        }
+        Ok(node)
+    }
+
+    /// A subject or object; `literals` tells whether literals are allowed
+    fn node(&mut self, triples: &mut Vec<TurtleTriple>, literals: bool) -> Result<TurtleTerm, String> {
+        let token = self.peek();
+        match token {
+            Some(Token::Punct("[")) => return self.blank_node_property_list(triples),
+            Some(Token::String(_) | Token::Number(_)) if !literals => {
+                return Err(format!("a literal cannot be a subject: {}", Self::unexpected(token)))
+            }
+            _ => {}
+        }
+        self.advance();
+        match token {
+            Some(token @ (Token::Iri(_) | Token::PrefixedName(..))) => self.iri(token).map(TurtleTerm::Term),
+            Some(Token::BlankNode(label)) => Ok(TurtleTerm::Term(format!("_:{}", label))),
+            Some(Token::Punct("(")) => {
+                let mut items = Vec::new();
+                while !self.eat(")") {
+                    items.push(self.node(triples, true)?);
+                }
+                let mut list = TurtleTerm::Term(format!("{}nil", RDF));
+                for item in items.into_iter().rev() {
+                    let cell = Self::fresh_blank_node();
+                    triples.push([cell.clone(), TurtleTerm::Term(format!("{}first", RDF)), item]);
+                    triples.push([cell.clone(), TurtleTerm::Term(format!("{}rest", RDF)), list]);
+                    list = cell;
+                }
+                Ok(list)
+            }
+            Some(Token::Punct("<<")) => {
+                let subject = self.node(triples, false)?;
+                let predicate = self.verb()?;
+                let object = self.node(triples, true)?;
+                self.expect(">>")?;
+                Ok(TurtleTerm::Quoted(Box::new([subject, predicate, object])))
+            }
+            Some(Token::String(lexical)) => match self.peek() {
+                Some(Token::LanguageTag(tag)) => {
+                    self.advance();
+                    Ok(TurtleTerm::Term(format!("{}@{}", lexical, tag)))
+                }
+                Some(Token::Datatype) => {
+                    self.advance();
+                    let datatype = match self.advance() {
+                        Some(datatype @ (Token::Iri(_) | Token::PrefixedName(..))) => self.iri(datatype)?,
+                        other => return Err(format!("expected a datatype: {}", Self::unexpected(other))),
+                    };
+                    Ok(typed_literal(lexical, &datatype))
+                }
+                _ => Ok(TurtleTerm::Term(lexical.clone())),
+            },
+            Some(Token::Number(number)) => {
+                let datatype = if number.contains(['e', 'E']) {
+                    "double"
+                } else if number.contains('.') {
+                    "decimal"
+                } else {
+                    "integer"
+                };
+                Ok(typed_literal(number, &format!("{}{}", XSD, datatype)))
+            }
+            Some(Token::Word(word)) if literals && (word == "true" || word == "false") => {
+                Ok(typed_literal(word, &format!("{}boolean", XSD)))
+            }
+            other => Err(Self::unexpected(other)),
+        }
+    }
+}
+
+fn typed_literal(lexical: &str, datatype: &str) -> TurtleTerm {
+    TurtleTerm::Term(format!("\"{}\"^^<{}>", lexical, datatype))
+}
+
+/// Parses a Turtle-star document into triples, resolving prefixed names with
+/// `prefixes` and adding the prefixes the document declares to it. A
+/// statement that does not parse is skipped and gives one error.
+pub(crate) fn parse_turtle(input: &str, prefixes: &mut HashMap<String, String>) -> (Vec<TurtleTriple>, Vec<String>) {
+    let tokens = tokenize(input);
+    let mut parser = Parser { tokens: &tokens, position: 0, prefixes, base: None };
+    let mut triples = Vec::new();
+    let mut errors = Vec::new();
+    while parser.peek().is_some() {
+        let start = parser.position;
+        let mut statement = Vec::new();
+        match parser.statement(&mut statement) {
+            Ok(()) => triples.extend(statement),
+            Err(error) => {
+                errors.push(error);
+                parser.skip_statement(start);
+            }
+        }
+    }
+    (triples, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ex(name: &str) -> String {
+        format!("http://example.org/{}", name)
+    }
+
+    fn term(text: &str) -> TurtleTerm {
+        TurtleTerm::Term(text.to_string())
+    }
+
+    /// Parsed triples of `input`, which must have no invalid statement
+    fn parse(input: &str) -> Vec<TurtleTriple> {
+        let (triples, errors) = parse_turtle(input, &mut HashMap::new());
+        assert!(errors.is_empty(), "{:?}", errors);
+        triples
+    }
+
+    fn object_of(triples: &[TurtleTriple], subject: &TurtleTerm, predicate: &str) -> TurtleTerm {
+        triples
+            .iter()
+            .find(|[s, p, _]| s == subject && *p == term(predicate))
+            .map(|[_, _, o]| o.clone())
+            .unwrap()
+    }
+
+    #[test]
+    fn abbreviations_expand_to_triples_keeping_datatypes() {
+        let mut prefixes = HashMap::new();
+        let (triples, errors) = parse_turtle(
+            r#"
+@prefix ex: <http://example.org/> .
+PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>
+
+# Predicate and object lists
+ex:alice a ex:Person ;
+    ex:name "Alice"@en, "Alicia" ;
+    ex:age "42"^^xsd:integer ;
+    ex:height 1.68 ;
+    ex:active true .
+"#,
+            &mut prefixes,
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        let alice = term(&ex("alice"));
+        let xsd = |name: &str| format!("http://www.w3.org/2001/XMLSchema#{}", name);
+        assert_eq!(
+            triples,
+            vec![
+                [alice.clone(), term(&format!("{}type", RDF)), term(&ex("Person"))],
+                [alice.clone(), term(&ex("name")), term("Alice@en")],
+                [alice.clone(), term(&ex("name")), term("Alicia")],
+                [alice.clone(), term(&ex("age")), term(&format!("\"42\"^^<{}>", xsd("integer")))],
+                [alice.clone(), term(&ex("height")), term(&format!("\"1.68\"^^<{}>", xsd("decimal")))],
+                [alice, term(&ex("active")), term(&format!("\"true\"^^<{}>", xsd("boolean")))],
+            ]
+        );
+        assert_eq!(prefixes.get("ex"), Some(&ex("")));
+    }
+
+    #[test]
+    fn blank_nodes_and_collections_become_fresh_nodes() {
+        let triples = parse(
+            r#"@prefix ex: <http://example.org/> .
+ex:team ex:lead [ ex:name "Bob" ] ;
+    ex:members ( ex:alice ex:bob ) ."#,
+        );
+        assert_eq!(triples.len(), 7);
+
+        let lead = object_of(&triples, &term(&ex("team")), &ex("lead"));
+        assert!(matches!(&lead, TurtleTerm::Term(label) if label.starts_with("_:")));
+        assert_eq!(object_of(&triples, &lead, &ex("name")), term("Bob"));
+
+        let head = object_of(&triples, &term(&ex("team")), &ex("members"));
+        assert_eq!(object_of(&triples, &head, &format!("{}first", RDF)), term(&ex("alice")));
+        let tail = object_of(&triples, &head, &format!("{}rest", RDF));
+        assert_eq!(object_of(&triples, &tail, &format!("{}first", RDF)), term(&ex("bob")));
+        assert_eq!(object_of(&triples, &tail, &format!("{}rest", RDF)), term(&format!("{}nil", RDF)));
+    }
+
+    #[test]
+    fn annotations_describe_quoted_triples() {
+        let triples = parse(
+            r#"@prefix ex: <http://example.org/> .
+ex:alice ex:knows ex:bob {| ex:since "2020" |} ."#,
+        );
+        let knows = [term(&ex("alice")), term(&ex("knows")), term(&ex("bob"))];
+        assert_eq!(
+            triples,
+            vec![knows.clone(), [TurtleTerm::Quoted(Box::new(knows)), term(&ex("since")), term("2020")]]
+        );
+    }
+
+    #[test]
+    fn invalid_statements_are_skipped_and_reported() {
+        let (triples, errors) = parse_turtle(
+            r#"@prefix ex: <http://example.org/> .
+ex:a ex:p ex:b .
+ex:c ex:p .
+undeclared:x ex:p ex:y .
+ex:d ex:p "unterminated
+"#,
+            &mut HashMap::new(),
+        );
+        assert_eq!(triples, vec![[term(&ex("a")), term(&ex("p")), term(&ex("b"))]]);
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].contains("unexpected ."), "{}", errors[0]);
+        assert!(errors[1].contains("undeclared"), "{}", errors[1]);
+    }
+}
@@ -0,0 +1,30 @@
+/*
+ * Copyright © 2026 Volodymyr Kadzhaia
+ * Copyright © 2026 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use kolibrie::sparql_database::SparqlDatabase;
+
+/// `people` people, each knowing the next one in a ring, aged 20 to 49
+pub fn people_db(people: usize) -> SparqlDatabase {
+    let mut db = SparqlDatabase::new();
+    let mut ntriples = String::new();
+    for person in 0..people {
+        ntriples.push_str(&format!(
+            "<http://example.org/p{person}> <http://example.org/knows> <http://example.org/p{}> .\n",
+            (person + 1) % people
+        ));
+        ntriples.push_str(&format!(
+            "<http://example.org/p{person}> <http://example.org/age> \"{}\" .\n",
+            20 + person % 30
+        ));
+    }
+    db.parse_ntriples_and_add(&ntriples);
+    db.get_or_build_stats();
+    db
+}
@@ -0,0 +1,98 @@
+/*
+ * Copyright © 2026 Volodymyr Kadzhaia
+ * Copyright © 2026 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use datalog::reasoning::entailment::EntailmentRegime;
+use kolibrie::execute_query::execute_query_rayon_parallel2_volcano;
+use kolibrie::rsp_engine::{
+    OperationMode, QueryExecutionMode, RSPBuilder, RSPEngine, ResultConsumer, SimpleR2R,
+};
+use kolibrie::sparql_database::SparqlDatabase;
+use shared::triple::Triple;
+use std::sync::{Arc, Mutex};
+
+const ONTOLOGY: &str = r#"
+<http://example.org/TemperatureSensor> <http://www.w3.org/2000/01/rdf-schema#subClassOf> <http://example.org/Sensor> .
+<http://example.org/Sensor> <http://www.w3.org/2000/01/rdf-schema#subClassOf> <http://example.org/Device> .
+"#;
+
+#[test]
+fn databases_materialize_entailed_triples() {
+    let mut db = SparqlDatabase::new();
+    db.parse_ntriples_and_add(ONTOLOGY);
+    db.parse_ntriples_and_add(
+        "<http://example.org/t1> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://example.org/TemperatureSensor> .\n",
+    );
+    let sparql = r#"PREFIX ex: <http://example.org/>
+        SELECT ?device WHERE { ?device a ex:Device . }"#;
+    let rows = execute_query_rayon_parallel2_volcano(sparql, &mut db);
+    assert!(!rows.iter().flatten().any(|value| value.contains("t1")));
+
+    let entailed = db.materialize_entailment(EntailmentRegime::Rdfs);
+    assert!(!entailed.is_empty());
+    let rows = execute_query_rayon_parallel2_volcano(sparql, &mut db);
+    assert_eq!(rows.len(), 1);
+    assert!(rows[0][0].contains("t1"));
+
+    // Already entailed triples are not reported again
+    assert!(db.materialize_entailment(EntailmentRegime::Rdfs).is_empty());
+}
+
+#[test]
+fn rsp_windows_see_types_entailed_by_the_static_ontology() {
+    let result_container = Arc::new(Mutex::new(Vec::new()));
+    let result_container_clone = Arc::clone(&result_container);
+    let result_consumer = ResultConsumer {
+        function: Arc::new(move |r: Vec<(String, String)>| {
+            result_container_clone.lock().unwrap().push(r);
+        }),
+    };
+    let r2r = Box::new(SimpleR2R::with_execution_mode(QueryExecutionMode::Volcano));
+
+    let rsp_ql_query = r#"
+        REGISTER RSTREAM <http://out/stream> AS
+        SELECT *
+        FROM NAMED WINDOW :wind ON :stream1 [RANGE 10 STEP 2]
+        WHERE {
+            WINDOW :wind {
+                ?device a <http://example.org/Device> .
+            }
+        }
+    "#;
+
+    let mut engine: RSPEngine<Triple, Vec<(String, String)>> = RSPBuilder::new()
+        .add_rsp_ql_query(rsp_ql_query)
+        .add_consumer(result_consumer)
+        .add_r2r(r2r)
+        .set_operation_mode(OperationMode::SingleThread)
+        .set_entailment_regime(EntailmentRegime::Rdfs)
+        .build()
+        .expect("Failed to build RSP engine");
+    engine.add_static_ntriples(ONTOLOGY);
+
+    for i in 0..5 {
+        let data = format!(
+            "<http://example.org/t{}> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://example.org/TemperatureSensor> .",
+            i
+        );
+        for triple in engine.parse_data(&data) {
+            engine.add_to_stream("stream1", triple, i);
+        }
+    }
+    engine.stop();
+
+    let results = result_container.lock().unwrap();
+    assert!(
+        results
+            .iter()
+            .any(|binding| binding.iter().any(|(k, v)| k == "device" && v.contains("t0"))),
+        "Expected sensors typed as devices, got: {:?}",
+        *results
+    );
+}
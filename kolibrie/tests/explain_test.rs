@@ -0,0 +1,123 @@
+/*
+ * Copyright © 2026 Volodymyr Kadzhaia
+ * Copyright © 2026 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+mod common;
+
+use common::people_db;
+use kolibrie::execute_query::{strip_explain_prefix, ExplainMode};
+
+const QUERY: &str = r#"PREFIX ex: <http://example.org/>
+    SELECT ?a ?age WHERE {
+        ?a ex:knows ?b .
+        ?b ex:age ?age .
+        FILTER(?age > 30)
+    }"#;
+
+#[test]
+fn explain_prefixes_are_recognized() {
+    assert_eq!(strip_explain_prefix("EXPLAIN SELECT ?s").map(|(mode, _)| mode), Some(ExplainMode::Plan));
+    assert_eq!(
+        strip_explain_prefix("  explain analyze SELECT ?s"),
+        Some((ExplainMode::Analyze, " SELECT ?s"))
+    );
+    assert!(strip_explain_prefix("SELECT ?explained").is_none());
+    assert!(strip_explain_prefix("EXPLAINED").is_none());
+}
+
+#[test]
+fn explain_reports_estimates_without_executing() {
+    let mut db = people_db(20);
+    let explanation = db.explain(&format!("EXPLAIN {}", QUERY)).unwrap();
+
+    assert!(!explanation.analyzed);
+    assert!(explanation.execution_time_ms.is_none());
+    assert!(explanation.plan.actual_rows.is_none());
+    assert!(explanation.plan.estimated_cost > 0);
+
+    let text = explanation.to_text();
+    assert!(text.contains("<http://example.org/knows>"), "{}", text);
+    assert!(text.contains("estimated rows="), "{}", text);
+    assert!(!text.contains("actual rows="), "{}", text);
+}
+
+#[test]
+fn explain_analyze_reports_actual_rows_and_timings() {
+    let mut db = people_db(20);
+    let explanation = db.explain(&format!("EXPLAIN ANALYZE {}", QUERY)).unwrap();
+
+    assert!(explanation.analyzed);
+    assert_eq!(explanation.total_rows, Some(9));
+    assert_eq!(explanation.plan.actual_rows, Some(9));
+    assert!(explanation.plan.actual_time_ms.is_some());
+    assert!(explanation.to_text().contains("actual rows=9"));
+
+    let json: serde_json::Value = serde_json::from_str(&explanation.to_json()).unwrap();
+    assert_eq!(json["analyzed"], true);
+    assert_eq!(json["plan"]["actual_rows"], 9);
+    assert!(json["plan"]["children"].is_array());
+}
+
+#[test]
+fn updates_cannot_be_explained() {
+    let mut db = people_db(20);
+    let update = r#"EXPLAIN PREFIX ex: <http://example.org/>
+        INSERT { ex:p1 ex:knows ex:p5 . } WHERE { ex:p1 ex:knows ?x . }"#;
+    assert!(db.explain(update).is_err());
+}
+
+#[test]
+fn explain_analyze_covers_solution_modifiers() {
+    let mut db = people_db(20);
+    let query = r#"EXPLAIN ANALYZE PREFIX ex: <http://example.org/>
+        SELECT ?a ?age WHERE {
+            ?a ex:knows ?b .
+            ?b ex:age ?age .
+            FILTER(?age > 30)
+        }
+        ORDER BY DESC(?age)
+        LIMIT 3"#;
+    let explanation = db.explain(query).unwrap();
+
+    let limit = &explanation.plan;
+    assert_eq!(limit.operator, "Limit");
+    assert_eq!(limit.detail, "3");
+    assert_eq!(limit.actual_rows, Some(3));
+    assert!(limit.estimated_rows <= 3);
+
+    let sort = &limit.children[0];
+    assert_eq!(sort.operator, "Sort");
+    assert_eq!(sort.detail, "?age DESC");
+    assert_eq!(sort.actual_rows, Some(9));
+    assert!(sort.actual_time_ms.unwrap() <= limit.actual_time_ms.unwrap());
+    // The plan below the modifiers produces every row
+    assert_eq!(sort.children[0].actual_rows, Some(9));
+    assert_eq!(explanation.total_rows, Some(9));
+
+    let text = explanation.to_text();
+    assert!(text.starts_with("Limit 3"), "{}", text);
+    assert!(text.contains("-> Sort ?age DESC"), "{}", text);
+}
+
+#[test]
+fn explain_shows_grouping_and_aggregates() {
+    let mut db = people_db(20);
+    let query = r#"EXPLAIN PREFIX ex: <http://example.org/>
+        SELECT ?a SUM(?age) AS ?total WHERE {
+            ?a ex:knows ?b .
+            ?b ex:age ?age .
+        }
+        GROUPBY ?a"#;
+    let explanation = db.explain(query).unwrap();
+
+    assert_eq!(explanation.plan.operator, "Aggregate");
+    assert_eq!(explanation.plan.detail, "by ?a, SUM(?age) AS ?total");
+    assert!(explanation.plan.actual_rows.is_none());
+    assert!(explanation.plan.estimated_cost >= explanation.plan.children[0].estimated_cost);
+}
@@ -165,6 +165,17 @@ mod tests {
         assert_eq!(new_var, "?fullName");
     }
     
+    #[test]
+    fn test_arithmetic_bind_parsing() {
+        let input = "BIND(?net + ?tax * 2 AS ?gross)";
+        let (rest, (func_name, args, new_var)) = parse_bind(input).unwrap();
+
+        assert!(rest.is_empty());
+        assert_eq!(func_name, ARITHMETIC_BIND);
+        assert_eq!(args, vec!["?net + ?tax * 2"]);
+        assert_eq!(new_var, "?gross");
+    }
+    
     #[test]
     fn test_rule_parsing() {
         let input = r#"RULE :OverheatingAlert :-
@@ -0,0 +1,220 @@
+/*
+ * Copyright © 2026 Volodymyr Kadzhaia
+ * Copyright © 2026 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+mod common;
+
+use common::people_db;
+use kolibrie::execute_query::{execute_query, execute_query_iter, execute_query_rayon_parallel2_volcano};
+use kolibrie::plan_cache::{LiteralRole, PlanCache};
+
+#[test]
+fn placeholders_are_bound_on_every_execution() {
+    let mut db = people_db(20);
+    let query = db
+        .prepare(
+            r#"PREFIX ex: <http://example.org/>
+            SELECT ?friend ?age WHERE {
+                $person ex:knows ?friend .
+                ?friend ex:age ?age .
+            }"#,
+        )
+        .unwrap();
+    assert_eq!(query.parameters(), ["person"]);
+
+    let rows = query.execute(&mut db, &[("person", "<http://example.org/p3>")]).unwrap();
+    assert_eq!(rows, vec![vec!["http://example.org/p4".to_string(), "24".to_string()]]);
+    let rows = query.execute(&mut db, &[("$person", "ex:p19")]).unwrap();
+    assert_eq!(rows, vec![vec!["http://example.org/p0".to_string(), "20".to_string()]]);
+    assert!(query.execute(&mut db, &[]).unwrap_err().contains("$person"));
+    assert!(query.execute(&mut db, &[("person", "ex:p1"), ("nobody", "1")]).is_err());
+}
+
+#[test]
+fn filter_parameters_and_bound_variables() {
+    let mut db = people_db(20);
+    let query = db
+        .prepare(
+            r#"PREFIX ex: <http://example.org/>
+            SELECT ?person ?age WHERE {
+                ?person ex:age ?age .
+                FILTER(?age >= $min)
+            }"#,
+        )
+        .unwrap();
+    assert_eq!(query.execute(&mut db, &[("min", "35")]).unwrap().len(), 5);
+    assert_eq!(query.execute(&mut db, &[("min", "30")]).unwrap().len(), 10);
+
+    // Ordinary variables can be bound too, and show up with their value
+    let rows = query
+        .execute(&mut db, &[("min", "0"), ("?person", "<http://example.org/p7>")])
+        .unwrap();
+    assert_eq!(rows, vec![vec!["http://example.org/p7".to_string(), "27".to_string()]]);
+}
+
+#[test]
+fn solution_modifiers_apply_to_prepared_queries() {
+    let mut db = people_db(20);
+    let query = db
+        .prepare(
+            r#"PREFIX ex: <http://example.org/>
+            SELECT ?person ?age WHERE {
+                ?person ex:age ?age .
+                FILTER(?age < $max)
+            } ORDER BY DESC(?age) LIMIT 2"#,
+        )
+        .unwrap();
+    let ages: Vec<String> = query
+        .execute(&mut db, &[("max", "25")])
+        .unwrap()
+        .into_iter()
+        .map(|row| row[1].clone())
+        .collect();
+    assert_eq!(ages, vec!["24", "23"]);
+}
+
+#[test]
+fn plans_are_reused_until_the_data_changes() {
+    let mut db = people_db(20);
+    let sparql = r#"PREFIX ex: <http://example.org/>
+        SELECT ?a ?b WHERE { ?a ex:knows ?b . }"#;
+    assert_eq!(execute_query_iter(sparql, &mut db).count(), 20);
+    assert_eq!(db.plan_cache.misses(), 1);
+    // Whitespace does not change the shape of a query
+    let reformatted = sparql.replace("    ", " ");
+    assert_eq!(execute_query_iter(&reformatted, &mut db).count(), 20);
+    assert_eq!(db.plan_cache.hits(), 1);
+    assert_eq!(db.plan_cache.len(), 1);
+
+    // A few more triples keep the plans ...
+    db.add_triple_parts("http://example.org/p0", "http://example.org/knows", "http://example.org/p5");
+    db.invalidate_stats_cache();
+    assert_eq!(db.plan_cache.len(), 1);
+    assert_eq!(execute_query_iter(sparql, &mut db).count(), 21);
+
+    // ... many more drop them
+    for person in 0..20 {
+        db.add_triple_parts(
+            &format!("http://example.org/p{person}"),
+            "http://example.org/name",
+            &format!("Person {person}"),
+        );
+    }
+    db.invalidate_stats_cache();
+    assert!(db.plan_cache.is_empty());
+}
+
+#[test]
+fn parameters_reach_arithmetic_filters() {
+    let mut db = people_db(20);
+    let query = db
+        .prepare(
+            r#"PREFIX ex: <http://example.org/>
+            SELECT ?person WHERE {
+                ?person ex:age ?age .
+                FILTER(?age - $skip)
+            }"#,
+        )
+        .unwrap();
+    let rows = query.execute(&mut db, &[("skip", "27")]).unwrap();
+    assert_eq!(rows.len(), 19);
+    assert!(!rows.contains(&vec!["http://example.org/p7".to_string()]));
+
+    // Comparisons against an expression over a parameter cannot be bound
+    let compound = db.prepare(
+        r#"PREFIX ex: <http://example.org/>
+        SELECT ?person WHERE { ?person ex:age ?age . FILTER(?age > ($min + 1)) }"#,
+    );
+    assert!(compound.unwrap_err().contains("?min"));
+}
+
+#[test]
+fn prepared_queries_are_not_planned_again() {
+    let mut db = people_db(20);
+    let query = db
+        .prepare(
+            r#"PREFIX ex: <http://example.org/>
+            SELECT ?person WHERE { ?person ex:age ?age . FILTER(?age >= $min) }"#,
+        )
+        .unwrap();
+    let (hits, misses) = (db.plan_cache.hits(), db.plan_cache.misses());
+    for min in ["0", "30", "35"] {
+        query.execute(&mut db, &[("min", min)]).unwrap();
+    }
+    assert_eq!((db.plan_cache.hits(), db.plan_cache.misses()), (hits, misses));
+
+    // The plan outlives the cache it came from
+    db.plan_cache.clear();
+    assert_eq!(query.execute(&mut db, &[("min", "35")]).unwrap().len(), 5);
+    assert_eq!(db.plan_cache.misses(), misses);
+}
+
+#[test]
+fn eager_query_functions_use_the_plan_cache() {
+    let mut db = people_db(20);
+    let query = |person: usize| {
+        format!(
+            r#"PREFIX ex: <http://example.org/>
+            SELECT ?friend ?age WHERE {{ ex:p{person} ex:knows ?friend . ?friend ex:age ?age . }}"#
+        )
+    };
+    assert_eq!(execute_query(&query(3), &mut db), vec![vec!["http://example.org/p4".to_string(), "24".to_string()]]);
+    assert_eq!(db.plan_cache.misses(), 1);
+    assert_eq!(execute_query(&query(3), &mut db).len(), 1);
+    assert_eq!(execute_query_rayon_parallel2_volcano(&query(3), &mut db).len(), 1);
+    assert_eq!(db.plan_cache.hits(), 2);
+    assert_eq!(db.plan_cache.len(), 1);
+
+    // Grouped queries are planned and cached too
+    let grouped = r#"PREFIX ex: <http://example.org/>
+        SELECT ?person SUM(?age) AS ?total WHERE { ?person ex:age ?age . } GROUPBY ?person"#;
+    assert_eq!(execute_query_rayon_parallel2_volcano(grouped, &mut db).len(), 20);
+    assert_eq!(execute_query(grouped, &mut db).len(), 20);
+    assert_eq!(db.plan_cache.len(), 2);
+    assert_eq!(db.plan_cache.hits(), 3);
+}
+
+#[test]
+fn queries_differing_in_literals_share_a_plan() {
+    let mut db = people_db(20);
+    let query = |age: &str, min: &str| {
+        format!(
+            r#"PREFIX ex: <http://example.org/>
+            SELECT ?person ?friend WHERE {{
+                ?person ex:age "{age}" .
+                ?friend ex:knows ?person .
+                ?friend ex:age ?friendAge .
+                FILTER(?friendAge >= {min})
+            }}"#
+        )
+    };
+    let rows: Vec<Vec<String>> = execute_query_iter(&query("25", "0"), &mut db).collect();
+    assert_eq!(rows, vec![vec!["http://example.org/p5".to_string(), "http://example.org/p4".to_string()]]);
+    let rows: Vec<Vec<String>> = execute_query_iter(&query("31", "1"), &mut db).collect();
+    assert_eq!(rows, vec![vec!["http://example.org/p11".to_string(), "http://example.org/p10".to_string()]]);
+    assert_eq!(db.plan_cache.len(), 1);
+    assert_eq!(db.plan_cache.hits(), 1);
+
+    // A filter keeping far fewer friends is costed on its own
+    assert!(execute_query_iter(&query("31", "37"), &mut db).next().is_none());
+    assert_eq!(db.plan_cache.len(), 2);
+    assert_eq!(db.plan_cache.hits(), 1);
+
+    let shape = PlanCache::abstract_literals(&query("25", "0"));
+    assert_eq!(shape.literals, ["\"25\"", "0"]);
+    assert_eq!(
+        shape.roles,
+        [
+            LiteralRole::Object { predicate: "ex:age".to_string() },
+            LiteralRole::Comparison { operator: ">=".to_string(), predicate: Some("ex:age".to_string()) },
+        ]
+    );
+    assert!(shape.sparql.contains("?person ex:age ?__literal0 ."));
+    assert!(shape.sparql.contains("FILTER(?friendAge >= ?__literal1)"));
+}
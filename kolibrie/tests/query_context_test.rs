@@ -0,0 +1,101 @@
+/*
+ * Copyright © 2026 Volodymyr Kadzhaia
+ * Copyright © 2026 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use kolibrie::execute_query::{
+    execute_query_rayon_parallel2_volcano, execute_query_volcano_with_context, execute_query_with_context,
+};
+use kolibrie::query_context::{CancellationToken, QueryContext, QueryError};
+use kolibrie::sparql_database::SparqlDatabase;
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn linked_db(size: usize) -> SparqlDatabase {
+    let mut db = SparqlDatabase::new();
+    let mut ntriples = String::new();
+    for node in 0..size {
+        ntriples.push_str(&format!(
+            "<http://example.org/n{node}> <http://example.org/next> <http://example.org/n{}> .\n",
+            (node + 1) % size
+        ));
+    }
+    db.parse_ntriples_and_add(&ntriples);
+    db.get_or_build_stats();
+    db
+}
+
+// Every pair of triples, then every triple of triples: a runaway result
+const CROSS_PRODUCT: &str = r#"PREFIX ex: <http://example.org/>
+    SELECT ?a ?b ?c WHERE {
+        ?a ex:next ?x .
+        ?b ex:next ?y .
+        ?c ex:next ?z .
+    }"#;
+
+#[test]
+fn unlimited_contexts_do_not_change_results() {
+    let mut db = linked_db(50);
+    let sparql = r#"PREFIX ex: <http://example.org/>
+        SELECT ?a ?c WHERE { ?a ex:next ?b . ?b ex:next ?c . }"#;
+    let expected = execute_query_rayon_parallel2_volcano(sparql, &mut db);
+    let context = QueryContext::new()
+        .with_timeout(Duration::from_secs(60))
+        .with_memory_limit(1 << 30);
+    let rows = execute_query_volcano_with_context(sparql, &mut db, &context).unwrap();
+    assert_eq!(rows.len(), 50);
+    assert_eq!(rows, expected);
+    assert!(context.memory_used() > 0);
+}
+
+#[test]
+fn runaway_joins_hit_the_memory_limit() {
+    let mut db = linked_db(400);
+    let context = QueryContext::new().with_memory_limit(16 * 1024 * 1024);
+    let outcome = execute_query_volcano_with_context(CROSS_PRODUCT, &mut db, &context);
+    assert_eq!(outcome, Err(QueryError::MemoryLimit { limit: 16 * 1024 * 1024 }));
+
+    // The string-based join of execute_query is limited as well
+    let context = QueryContext::new().with_memory_limit(16 * 1024 * 1024);
+    let outcome = execute_query_with_context(CROSS_PRODUCT, &mut db, &context);
+    assert_eq!(outcome, Err(QueryError::MemoryLimit { limit: 16 * 1024 * 1024 }));
+}
+
+#[test]
+fn deadlines_stop_running_queries() {
+    let mut db = linked_db(400);
+    let started = Instant::now();
+    let context = QueryContext::new()
+        .with_timeout(Duration::from_millis(50))
+        .with_memory_limit(1 << 30);
+    let outcome = execute_query_volcano_with_context(CROSS_PRODUCT, &mut db, &context);
+    assert_eq!(outcome, Err(QueryError::Timeout));
+    assert!(started.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn tokens_cancel_queries_from_another_thread() {
+    let mut db = linked_db(400);
+    let token = CancellationToken::new();
+    let context = QueryContext::new()
+        .with_cancellation(token.clone())
+        .with_memory_limit(1 << 30);
+    let canceller = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        token.cancel();
+    });
+    let outcome = execute_query_volcano_with_context(CROSS_PRODUCT, &mut db, &context);
+    canceller.join().unwrap();
+    assert_eq!(outcome, Err(QueryError::Cancelled));
+
+    // A cancelled context stops later queries before they start
+    let started = Instant::now();
+    let outcome = execute_query_with_context(CROSS_PRODUCT, &mut db, &context);
+    assert_eq!(outcome, Err(QueryError::Cancelled));
+    assert!(started.elapsed() < Duration::from_secs(1));
+}
@@ -0,0 +1,74 @@
+/*
+ * Copyright © 2026 Volodymyr Kadzhaia
+ * Copyright © 2026 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+mod common;
+
+use common::people_db;
+use kolibrie::execute_query::{execute_ask, execute_query_iter, execute_query_rayon_parallel2_volcano};
+
+#[test]
+fn streamed_rows_match_eager_execution() {
+    let mut db = people_db(50);
+    let query = r#"PREFIX ex: <http://example.org/>
+        SELECT ?a ?b ?age WHERE {
+            ?a ex:knows ?b .
+            ?b ex:age ?age .
+            FILTER(?age > 30)
+        }"#;
+
+    let mut eager = execute_query_rayon_parallel2_volcano(query, &mut db);
+    let iter = execute_query_iter(query, &mut db);
+    assert_eq!(iter.variables(), ["?a", "?b", "?age"]);
+    let mut streamed: Vec<Vec<String>> = iter.collect();
+
+    eager.sort();
+    streamed.sort();
+    assert_eq!(streamed.len(), 28);
+    assert_eq!(streamed, eager);
+}
+
+#[test]
+fn limit_stops_the_stream() {
+    let mut db = people_db(50);
+    let query = r#"PREFIX ex: <http://example.org/>
+        SELECT ?a ?b WHERE { ?a ex:knows ?b . } LIMIT 7"#;
+
+    let mut iter = execute_query_iter(query, &mut db);
+    assert_eq!(iter.next_batch(5).len(), 5);
+    assert_eq!(iter.next_batch(5).len(), 2);
+    assert!(iter.next_batch(5).is_empty());
+}
+
+#[test]
+fn ordered_queries_fall_back_to_eager_execution() {
+    let mut db = people_db(50);
+    let query = r#"PREFIX ex: <http://example.org/>
+        SELECT ?a ?age WHERE { ?a ex:age ?age . } ORDER BY DESC(?age) LIMIT 3"#;
+
+    let eager = execute_query_rayon_parallel2_volcano(query, &mut db);
+    let streamed: Vec<Vec<String>> = execute_query_iter(query, &mut db).collect();
+    assert_eq!(streamed.len(), 3);
+    assert_eq!(streamed, eager);
+}
+
+#[test]
+fn ask_queries_answer_from_the_first_solution() {
+    let mut db = people_db(50);
+    assert!(execute_ask(
+        r#"PREFIX ex: <http://example.org/>
+        ASK { ex:p1 ex:knows ?someone . }"#,
+        &mut db
+    ));
+    assert!(!execute_ask(
+        r#"PREFIX ex: <http://example.org/>
+        ASK WHERE { ?someone ex:knows ex:nobody . }"#,
+        &mut db
+    ));
+}
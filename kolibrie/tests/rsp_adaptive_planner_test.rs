@@ -0,0 +1,140 @@
+/*
+* Copyright © 2025 Volodymyr Kadzhaia
+* Copyright © 2025 Pieter Bonte
+* KU Leuven — Stream Intelligence Lab, Belgium
+* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this file,
+* you can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+
+use kolibrie::adaptive_planner::{AdaptivePlannerConfig, ReplanReason, ReplanTrigger};
+use kolibrie::rsp_engine::{
+    OperationMode, QueryExecutionMode, RSPBuilder, RSPEngine, ResultConsumer, SimpleR2R,
+};
+use shared::triple::Triple;
+use std::sync::{Arc, Mutex};
+
+type Results = Arc<Mutex<Vec<Vec<(String, String)>>>>;
+type Engine = RSPEngine<Triple, Vec<(String, String)>>;
+
+fn query(replan: &str) -> String {
+    format!(
+        r#"
+        REGISTER RSTREAM <http://out/stream> AS
+        SELECT *
+        FROM NAMED WINDOW :w ON :readings [RANGE 4 STEP 2] {}
+        WHERE {{ WINDOW :w {{
+            ?r <http://test/sensor> ?s .
+            ?s <http://test/zone> ?z .
+            ?r <http://test/value> ?v .
+        }} }}
+    "#,
+        replan
+    )
+}
+
+fn build_engine(query: &str, planner: Option<AdaptivePlannerConfig>) -> (Engine, Results) {
+    let results: Results = Arc::new(Mutex::new(Vec::new()));
+    let rc = Arc::clone(&results);
+    let mut builder = RSPBuilder::new()
+        .add_rsp_ql_query(query)
+        .add_consumer(ResultConsumer {
+            function: Arc::new(move |r: Vec<(String, String)>| {
+                rc.lock().unwrap().push(r);
+            }),
+        })
+        .add_r2r(Box::new(SimpleR2R::with_execution_mode(QueryExecutionMode::Volcano)))
+        .set_operation_mode(OperationMode::SingleThread);
+    if let Some(config) = planner {
+        builder = builder.set_adaptive_planner(config);
+    }
+    (builder.build().expect("Failed to build engine"), results)
+}
+
+/// One reading per timestamp; the number of readings per zone grows over time.
+fn feed(engine: &mut Engine, readings: usize) {
+    for ts in 1..=readings {
+        let data = format!(
+            "<http://test/r{0}> <http://test/sensor> <http://test/s{1}> .\n\
+             <http://test/s{1}> <http://test/zone> <http://test/z{2}> .\n\
+             <http://test/r{0}> <http://test/value> \"{0}\" .",
+            ts,
+            ts % 3,
+            ts % 2
+        );
+        for triple in engine.parse_data(&data) {
+            engine.add_to_stream(":readings", triple, ts);
+        }
+    }
+}
+
+fn sorted(results: &Results) -> Vec<Vec<(String, String)>> {
+    let mut all = results.lock().unwrap().clone();
+    all.sort();
+    all
+}
+
+#[test]
+fn with_replan_clause_installs_a_planner() {
+    let (mut engine, _) = build_engine(&query("WITH REPLAN always"), None);
+    feed(&mut engine, 12);
+
+    let metrics = engine.adaptive_planner().expect("WITH REPLAN must install a planner").total_metrics();
+    assert!(metrics.firings > 1);
+    assert_eq!(metrics.replans, metrics.firings);
+    assert_eq!(metrics.reasons.get(&ReplanReason::Initial), Some(&1));
+    assert_eq!(metrics.reasons.get(&ReplanReason::Always), Some(&(metrics.firings - 1)));
+}
+
+#[test]
+fn static_trigger_only_computes_the_initial_plan() {
+    let (mut engine, _) = build_engine(&query(""), Some(AdaptivePlannerConfig::default()));
+    feed(&mut engine, 12);
+
+    let planner = engine.adaptive_planner().unwrap();
+    let metrics = planner.metrics()[":w"].clone();
+    assert!(metrics.firings > 1);
+    assert_eq!(metrics.replans, 1);
+    assert_eq!(planner.stats_history(":w").len() as u64, metrics.firings);
+}
+
+#[test]
+fn window_clause_overrides_the_builder_trigger() {
+    let config = AdaptivePlannerConfig {
+        trigger: ReplanTrigger::Always,
+        ..AdaptivePlannerConfig::default()
+    };
+    let (mut engine, _) = build_engine(&query("WITH REPLAN size(1000)"), Some(config));
+    feed(&mut engine, 12);
+
+    let metrics = engine.adaptive_planner().unwrap().total_metrics();
+    assert_eq!(metrics.replans, 1);
+    assert!(metrics.reasons.get(&ReplanReason::Always).is_none());
+}
+
+#[test]
+fn replanning_does_not_change_results() {
+    let (mut reference, reference_results) = build_engine(&query(""), None);
+    let (mut adaptive, adaptive_results) = build_engine(&query("WITH REPLAN always"), None);
+    feed(&mut reference, 12);
+    feed(&mut adaptive, 12);
+
+    assert!(reference.adaptive_planner().is_none());
+    assert!(!reference_results.lock().unwrap().is_empty());
+    assert_eq!(sorted(&adaptive_results), sorted(&reference_results));
+}
+
+#[test]
+fn estimate_error_trigger_uses_execution_feedback() {
+    let (mut engine, _) = build_engine(&query("WITH REPLAN qerror(1000000)"), None);
+    feed(&mut engine, 12);
+    let metrics = engine.adaptive_planner().unwrap().total_metrics();
+    assert_eq!(metrics.replans, 1);
+    assert!(metrics.last_q_error.is_some_and(|q| q >= 1.0));
+
+    // Join estimates are rarely exact, so a threshold of 1 eventually fires.
+    let (mut engine, _) = build_engine(&query("WITH REPLAN qerror(1, 1)"), None);
+    feed(&mut engine, 12);
+    let metrics = engine.adaptive_planner().unwrap().total_metrics();
+    assert!(metrics.reasons.get(&ReplanReason::EstimateError).is_some());
+}
@@ -0,0 +1,126 @@
+/*
+* Copyright © 2025 Volodymyr Kadzhaia
+* Copyright © 2025 Pieter Bonte
+* KU Leuven — Stream Intelligence Lab, Belgium
+* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this file,
+* you can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+
+use kolibrie::rsp::buffer::OverflowPolicy;
+use kolibrie::rsp_engine::{
+    OperationMode, QueryExecutionMode, RSPBuilder, RSPEngine, ResultConsumer, SimpleR2R,
+};
+use shared::triple::Triple;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const QUERY: &str = r#"
+    REGISTER RSTREAM <http://out/stream> AS
+    SELECT *
+    FROM NAMED WINDOW :w ON :sensors [RANGE 2 STEP 1]
+    WHERE { WINDOW :w { ?s a <http://test/Sensor> . } }
+"#;
+
+const FIRINGS: usize = 20;
+
+/// Build a MultiThread engine whose worker takes 20ms per window firing, feed it
+/// `FIRINGS` timestamps (one firing each) as fast as possible and return it with
+/// the number of firings the worker evaluated.
+fn run_slow_engine(
+    overflow: OverflowPolicy,
+) -> (RSPEngine<Triple, Vec<(String, String)>>, Arc<AtomicUsize>, Duration) {
+    let evaluated = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&evaluated);
+    let mut engine: RSPEngine<Triple, Vec<(String, String)>> = RSPBuilder::new()
+        .add_rsp_ql_query(QUERY)
+        .add_consumer(ResultConsumer {
+            function: Arc::new(|_| {}),
+        })
+        .add_r2r(Box::new(SimpleR2R::with_execution_mode(QueryExecutionMode::Volcano)))
+        .set_operation_mode(OperationMode::MultiThread)
+        .set_window_buffer(2, overflow)
+        .build()
+        .unwrap();
+    engine.set_window_plan_adaptor(Arc::new(move |_, _, _, _| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(20));
+        None
+    }));
+
+    let start = Instant::now();
+    for ts in 1..=FIRINGS {
+        let data = format!("<http://test/sensor{}> a <http://test/Sensor> .", ts);
+        for triple in engine.parse_data(&data) {
+            engine.add_to_stream(":sensors", triple, ts);
+        }
+    }
+    (engine, evaluated, start.elapsed())
+}
+
+fn wait_for_idle(engine: &RSPEngine<Triple, Vec<(String, String)>>) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while engine.queue_metrics()[0].1.depth > 0 && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(10));
+    }
+    // Let the worker finish the item it took last.
+    thread::sleep(Duration::from_millis(50));
+}
+
+#[test]
+fn block_policy_applies_backpressure() {
+    let (engine, evaluated, elapsed) = run_slow_engine(OverflowPolicy::Block);
+    wait_for_idle(&engine);
+
+    let (window, metrics) = &engine.queue_metrics()[0];
+    assert!(window.contains('w'));
+    assert_eq!(metrics.capacity, 2);
+    assert_eq!(metrics.enqueued as usize, FIRINGS);
+    assert_eq!(metrics.dropped + metrics.coalesced, 0);
+    assert!(metrics.max_depth <= 2);
+    assert!(metrics.blocked > Duration::ZERO);
+    // The producer was held back to the worker's pace.
+    assert!(elapsed >= Duration::from_millis(20 * (FIRINGS as u64 - 3)));
+    assert_eq!(evaluated.load(Ordering::SeqCst), FIRINGS);
+}
+
+#[test]
+fn drop_oldest_policy_sheds_load() {
+    let (engine, evaluated, _) = run_slow_engine(OverflowPolicy::DropOldest);
+    wait_for_idle(&engine);
+
+    let metrics = &engine.queue_metrics()[0].1;
+    assert_eq!(metrics.enqueued as usize, FIRINGS);
+    assert!(metrics.dropped > 0);
+    assert_eq!(metrics.blocked, Duration::ZERO);
+    assert_eq!(
+        evaluated.load(Ordering::SeqCst),
+        FIRINGS - metrics.dropped as usize
+    );
+}
+
+#[test]
+fn coalesce_policy_keeps_latest_content() {
+    let (engine, evaluated, _) = run_slow_engine(OverflowPolicy::Coalesce);
+    wait_for_idle(&engine);
+
+    let metrics = &engine.queue_metrics()[0].1;
+    assert!(metrics.coalesced > 0);
+    assert_eq!(
+        evaluated.load(Ordering::SeqCst),
+        FIRINGS - metrics.coalesced as usize
+    );
+}
+
+#[test]
+fn single_thread_mode_has_no_queues() {
+    let engine: RSPEngine<Triple, Vec<(String, String)>> = RSPBuilder::new()
+        .add_rsp_ql_query(QUERY)
+        .add_r2r(Box::new(SimpleR2R::with_execution_mode(QueryExecutionMode::Volcano)))
+        .set_operation_mode(OperationMode::SingleThread)
+        .build()
+        .unwrap();
+    assert!(engine.queue_metrics().is_empty());
+}
@@ -0,0 +1,207 @@
+/*
+* Copyright © 2025 Volodymyr Kadzhaia
+* Copyright © 2025 Pieter Bonte
+* KU Leuven — Stream Intelligence Lab, Belgium
+* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this file,
+* you can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+
+use kolibrie::rsp::checkpoint::EngineCheckpoint;
+use kolibrie::rsp::source::{EventFormat, FileSource};
+use kolibrie::rsp_engine::{
+    OperationMode, QueryExecutionMode, RSPBuilder, RSPEngine, ResultConsumer, SimpleR2R,
+};
+use shared::triple::Triple;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+type Results = Arc<Mutex<Vec<Vec<(String, String)>>>>;
+type Engine = RSPEngine<Triple, Vec<(String, String)>>;
+
+fn query(stream_type: &str) -> String {
+    query_with(stream_type, "")
+}
+
+fn query_with(stream_type: &str, partition_by: &str) -> String {
+    format!(
+        r#"
+        REGISTER {} <http://out/stream> AS
+        SELECT *
+        FROM NAMED WINDOW :w ON :sensors [RANGE 3 STEP 1] {}
+        WHERE {{ WINDOW :w {{ ?s a <http://test/Sensor> . }} }}
+    "#,
+        stream_type, partition_by
+    )
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("kolibrie_{}_{}", name, std::process::id()))
+}
+
+fn build_engine(query: &str, restore: Option<&Path>) -> (Engine, Results) {
+    let results: Results = Arc::new(Mutex::new(Vec::new()));
+    let rc = Arc::clone(&results);
+    let consumer = ResultConsumer {
+        function: Arc::new(move |r: Vec<(String, String)>| {
+            rc.lock().unwrap().push(r);
+        }),
+    };
+    let mut builder = RSPBuilder::new()
+        .add_rsp_ql_query(query)
+        .add_consumer(consumer)
+        .add_r2r(Box::new(SimpleR2R::with_execution_mode(QueryExecutionMode::Volcano)))
+        .set_operation_mode(OperationMode::SingleThread);
+    if let Some(path) = restore {
+        builder = builder.restore_from_checkpoint(path);
+    }
+    (builder.build().expect("Failed to build engine"), results)
+}
+
+fn feed(engine: &mut Engine, timestamps: std::ops::RangeInclusive<usize>) {
+    for ts in timestamps {
+        // Sensor 1 is present at every timestamp, sensor `ts` only once.
+        let data = format!(
+            "<http://test/sensor1> a <http://test/Sensor> .\n<http://test/sensor{}> a <http://test/Sensor> .",
+            ts
+        );
+        for triple in engine.parse_data(&data) {
+            engine.add_to_stream(":sensors", triple, ts);
+        }
+    }
+}
+
+fn sorted(results: &Results) -> Vec<Vec<(String, String)>> {
+    let mut all = results.lock().unwrap().clone();
+    all.sort();
+    all
+}
+
+/// Results of an uninterrupted run must equal those of a run that is checkpointed
+/// after `ts = 4` and resumed in a new engine.
+fn assert_resume_matches_uninterrupted(query: &str, name: &str) {
+    let path = temp_path(&format!("checkpoint_{}.json", name));
+
+    let (mut reference, reference_results) = build_engine(query, None);
+    feed(&mut reference, 1..=8);
+
+    let (mut before, before_results) = build_engine(query, None);
+    feed(&mut before, 1..=4);
+    before.save_checkpoint(&path).unwrap();
+    drop(before);
+
+    let (mut after, after_results) = build_engine(query, Some(&path));
+    feed(&mut after, 5..=8);
+    std::fs::remove_file(&path).ok();
+
+    let mut resumed = sorted(&before_results);
+    resumed.extend(sorted(&after_results));
+    resumed.sort();
+    assert!(!resumed.is_empty());
+    assert_eq!(resumed, sorted(&reference_results));
+}
+
+#[test]
+fn rstream_resumes_from_checkpoint() {
+    assert_resume_matches_uninterrupted(&query("RSTREAM"), "RSTREAM");
+}
+
+#[test]
+fn istream_keeps_last_result_across_restart() {
+    // Without the restored R2S state, sensor1 would be emitted again after the restart.
+    assert_resume_matches_uninterrupted(&query("ISTREAM"), "ISTREAM");
+}
+
+#[test]
+fn partitioned_windows_resume_from_checkpoint() {
+    // Every sensor has its own window; sensor1's spans the restart.
+    assert_resume_matches_uninterrupted(&query_with("RSTREAM", "PARTITION BY ?s"), "partitioned");
+
+    let (mut engine, _) = build_engine(&query_with("RSTREAM", "PARTITION BY ?s"), None);
+    feed(&mut engine, 1..=3);
+    let checkpoint = engine.checkpoint().unwrap();
+    assert_eq!(checkpoint.windows[0].app_time, 3);
+    assert!(checkpoint.windows[0].partitions.len() > 1);
+    assert!(checkpoint.windows[0]
+        .partitions
+        .iter()
+        .flatten()
+        .flat_map(|w| &w.items)
+        .any(|item| item.contains("<http://test/sensor3>")));
+}
+
+#[test]
+fn checkpoint_file_round_trips() {
+    let path = temp_path("checkpoint_round_trip.json");
+    let (mut engine, _) = build_engine(&query("RSTREAM"), None);
+    feed(&mut engine, 1..=3);
+    let checkpoint = engine.checkpoint().unwrap();
+    checkpoint.save(&path).unwrap();
+    let loaded = EngineCheckpoint::load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(loaded, checkpoint);
+    assert_eq!(loaded.windows.len(), 1);
+    assert_eq!(loaded.windows[0].app_time, 3);
+    assert!(loaded.windows[0]
+        .active
+        .iter()
+        .flat_map(|w| &w.items)
+        .any(|item| item.contains("<http://test/sensor3>")));
+}
+
+#[test]
+fn file_source_resumes_from_checkpointed_position() {
+    let data_path = temp_path("checkpoint_source.nt");
+    let checkpoint_path = temp_path("checkpoint_source.json");
+    let line = |i: usize| format!("<http://test/sensor{}> a <http://test/Sensor> .\n", i);
+    std::fs::write(&data_path, (1..=3).map(line).collect::<String>()).unwrap();
+
+    let (mut first, _) = build_engine(&query("RSTREAM"), None);
+    first
+        .attach_source(":sensors", Box::new(FileSource::open(&data_path, EventFormat::NTriples).unwrap()))
+        .unwrap();
+    assert_eq!(first.run_sources(), 3);
+    first.save_checkpoint(&checkpoint_path).unwrap();
+    drop(first);
+
+    // More data arrives while the engine is down.
+    let mut contents = std::fs::read_to_string(&data_path).unwrap();
+    contents.push_str(&(4..=5).map(line).collect::<String>());
+    std::fs::write(&data_path, contents).unwrap();
+
+    let (mut second, results) = build_engine(&query("RSTREAM"), Some(&checkpoint_path));
+    second
+        .attach_source(":sensors", Box::new(FileSource::open(&data_path, EventFormat::NTriples).unwrap()))
+        .unwrap();
+    let processed = second.run_sources();
+    std::fs::remove_file(&data_path).ok();
+    std::fs::remove_file(&checkpoint_path).ok();
+
+    assert_eq!(processed, 2, "Only lines after the checkpointed position are replayed");
+    // Sequence timestamps continue at 4, so the restored window closes and fires.
+    assert!(results
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|r| r.iter().any(|(k, v)| k == "s" && v.contains("sensor3"))));
+}
+
+#[test]
+fn periodic_checkpoint_is_written() {
+    let path = temp_path("checkpoint_periodic.json");
+    std::fs::remove_file(&path).ok();
+    let query = query("RSTREAM");
+    let mut engine: Engine = RSPBuilder::new()
+        .add_rsp_ql_query(&query)
+        .add_r2r(Box::new(SimpleR2R::with_execution_mode(QueryExecutionMode::Volcano)))
+        .set_operation_mode(OperationMode::SingleThread)
+        .set_checkpointing(&path, Duration::ZERO)
+        .build()
+        .unwrap();
+    feed(&mut engine, 1..=2);
+    let loaded = EngineCheckpoint::load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(loaded.windows[0].app_time, 2);
+}
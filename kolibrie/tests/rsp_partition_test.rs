@@ -0,0 +1,138 @@
+/*
+* Copyright © 2025 Volodymyr Kadzhaia
+* Copyright © 2025 Pieter Bonte
+* KU Leuven — Stream Intelligence Lab, Belgium
+* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this file,
+* you can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+
+use kolibrie::rsp_engine::{
+    OperationMode, QueryExecutionMode, RSPBuilder, RSPEngine, ResultConsumer, SimpleR2R,
+};
+use shared::triple::Triple;
+use std::sync::{Arc, Mutex};
+
+type Results = Arc<Mutex<Vec<Vec<(String, String)>>>>;
+type Engine = RSPEngine<Triple, Vec<(String, String)>>;
+
+const SENSORS: usize = 50;
+
+fn query(stream_type: &str, partition: &str) -> String {
+    format!(
+        r#"
+        REGISTER {} <http://out/stream> AS
+        SELECT *
+        FROM NAMED WINDOW :w ON :sensors [RANGE 3 STEP 1] {}
+        WHERE {{ WINDOW :w {{ ?s a <http://test/Sensor> . ?s <http://test/value> ?v . }} }}
+    "#,
+        stream_type, partition
+    )
+}
+
+fn build_engine(query: &str, mode: OperationMode) -> (Engine, Results) {
+    let results: Results = Arc::new(Mutex::new(Vec::new()));
+    let rc = Arc::clone(&results);
+    let engine = RSPBuilder::new()
+        .add_rsp_ql_query(query)
+        .add_consumer(ResultConsumer {
+            function: Arc::new(move |r: Vec<(String, String)>| {
+                rc.lock().unwrap().push(r);
+            }),
+        })
+        .add_r2r(Box::new(SimpleR2R::with_execution_mode(QueryExecutionMode::Volcano)))
+        .set_operation_mode(mode)
+        .set_partition_workers(4)
+        .build()
+        .expect("Failed to build engine");
+    (engine, results)
+}
+
+/// Every sensor reports at every timestamp; odd sensors only every other one.
+fn feed(engine: &mut Engine, timestamps: std::ops::RangeInclusive<usize>) {
+    for ts in timestamps {
+        for sensor in 1..=SENSORS {
+            if sensor % 2 == 1 && ts % 2 == 1 {
+                continue;
+            }
+            let data = format!(
+                "<http://test/sensor{0}> a <http://test/Sensor> .\n<http://test/sensor{0}> <http://test/value> \"{1}\" .",
+                sensor, ts
+            );
+            for triple in engine.parse_data(&data) {
+                engine.add_to_stream(":sensors", triple, ts);
+            }
+        }
+    }
+}
+
+fn sorted(results: &Results) -> Vec<Vec<(String, String)>> {
+    let mut all = results.lock().unwrap().clone();
+    all.sort();
+    all
+}
+
+#[test]
+fn partitioned_window_matches_unpartitioned_results() {
+    let (mut reference, reference_results) =
+        build_engine(&query("RSTREAM", ""), OperationMode::SingleThread);
+    feed(&mut reference, 1..=8);
+    reference.stop();
+
+    let (mut partitioned, partitioned_results) =
+        build_engine(&query("RSTREAM", "PARTITION BY ?s"), OperationMode::MultiThread);
+    feed(&mut partitioned, 1..=8);
+    assert_eq!(partitioned.partition_keys()[0].1, SENSORS);
+    assert_eq!(partitioned.queue_metrics().len(), 4);
+    partitioned.stop();
+
+    assert!(!reference_results.lock().unwrap().is_empty());
+    assert_eq!(sorted(&partitioned_results), sorted(&reference_results));
+}
+
+#[test]
+fn single_thread_partitions_are_evaluated_inline() {
+    let (mut reference, reference_results) =
+        build_engine(&query("RSTREAM", ""), OperationMode::SingleThread);
+    let (mut partitioned, partitioned_results) =
+        build_engine(&query("RSTREAM", "PARTITION BY ?s"), OperationMode::SingleThread);
+    feed(&mut reference, 1..=6);
+    feed(&mut partitioned, 1..=6);
+
+    // Results are available without stopping the engine.
+    assert!(!partitioned_results.lock().unwrap().is_empty());
+    assert!(partitioned.queue_metrics().is_empty());
+    assert_eq!(sorted(&partitioned_results), sorted(&reference_results));
+}
+
+#[test]
+fn istream_is_applied_to_merged_partition_results() {
+    // Per-partition R2S would forget the other partitions' previous results and
+    // re-emit them; merging first gives the same stream as a single window.
+    let (mut reference, reference_results) =
+        build_engine(&query("ISTREAM", ""), OperationMode::SingleThread);
+    let (mut partitioned, partitioned_results) =
+        build_engine(&query("ISTREAM", "PARTITION BY ?s"), OperationMode::SingleThread);
+    feed(&mut reference, 1..=8);
+    feed(&mut partitioned, 1..=8);
+
+    assert!(!reference_results.lock().unwrap().is_empty());
+    assert_eq!(sorted(&partitioned_results), sorted(&reference_results));
+}
+
+#[test]
+fn partition_variable_must_be_the_shared_subject() {
+    let query = r#"
+        REGISTER RSTREAM <http://out/stream> AS
+        SELECT *
+        FROM NAMED WINDOW :w ON :sensors [RANGE 3 STEP 1] PARTITION BY ?v
+        WHERE { WINDOW :w { ?s <http://test/value> ?v . } }
+    "#;
+    let result = RSPBuilder::<Triple, Vec<(String, String)>>::new()
+        .add_rsp_ql_query(query)
+        .add_r2r(Box::new(SimpleR2R::with_execution_mode(QueryExecutionMode::Volcano)))
+        .set_operation_mode(OperationMode::SingleThread)
+        .build();
+    let error = result.err().expect("PARTITION BY on an object variable must be rejected");
+    assert!(error.contains("PARTITION BY ?v"));
+}
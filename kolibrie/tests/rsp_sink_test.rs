@@ -0,0 +1,281 @@
+/*
+* Copyright © 2025 Volodymyr Kadzhaia
+* Copyright © 2025 Pieter Bonte
+* KU Leuven — Stream Intelligence Lab, Belgium
+* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this file,
+* you can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+
+use kolibrie::rsp::sink::{FileSink, MqttSink, StreamSink, WebhookSink};
+use kolibrie::rsp::source::EventFormat;
+use kolibrie::rsp_engine::{
+    OperationMode, QueryExecutionMode, RSPBuilder, RSPEngine, ResultConsumer, SimpleR2R,
+};
+use shared::triple::Triple;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+type Results = Arc<Mutex<Vec<Vec<(String, String)>>>>;
+
+const QUERY: &str = r#"
+    REGISTER RSTREAM <http://out/sensors> AS
+    SELECT *
+    FROM NAMED WINDOW :w ON :sensors [RANGE 3 STEP 1]
+    WHERE { WINDOW :w { ?s a <http://test/Sensor> . } }
+"#;
+
+fn build_engine(
+    query: &str,
+    sinks: Vec<Box<dyn StreamSink>>,
+) -> (RSPEngine<Triple, Vec<(String, String)>>, Results) {
+    let results: Results = Arc::new(Mutex::new(Vec::new()));
+    let rc = Arc::clone(&results);
+    let consumer = ResultConsumer {
+        function: Arc::new(move |r: Vec<(String, String)>| {
+            rc.lock().unwrap().push(r);
+        }),
+    };
+    let mut builder = RSPBuilder::new()
+        .add_rsp_ql_query(query)
+        .add_consumer(consumer)
+        .add_r2r(Box::new(SimpleR2R::with_execution_mode(QueryExecutionMode::Volcano)))
+        .set_operation_mode(OperationMode::SingleThread);
+    for sink in sinks {
+        builder = builder.add_sink("<http://out/sensors>", sink);
+    }
+    let engine = builder.build().expect("Failed to build engine");
+    (engine, results)
+}
+
+fn feed_sensors(engine: &mut RSPEngine<Triple, Vec<(String, String)>>, n: usize) {
+    for i in 1..=n {
+        let data = format!("<http://test/sensor{}> a <http://test/Sensor> .", i);
+        for triple in engine.parse_data(&data) {
+            engine.add_to_stream(":sensors", triple, i);
+        }
+    }
+}
+
+#[test]
+fn file_sink_writes_output_stream() {
+    let path = std::env::temp_dir().join(format!("kolibrie_sink_{}.jsonl", std::process::id()));
+    let sink = FileSink::create(&path, EventFormat::JsonLines).unwrap();
+    let (mut engine, results) = build_engine(QUERY, vec![Box::new(sink)]);
+    feed_sensors(&mut engine, 5);
+    engine.stop();
+
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    let emitted = results.lock().unwrap().len();
+    assert!(emitted > 0);
+    // One line per variable binding; SELECT * over `?s` yields one line per result.
+    assert_eq!(written.lines().count(), emitted);
+    assert!(written.contains("http://out/sensors#s"));
+    assert!(written.contains("http://test/sensor3"));
+}
+
+#[test]
+fn webhook_sink_posts_results() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::channel::<String>();
+    thread::spawn(move || {
+        for conn in listener.incoming() {
+            let mut conn = conn.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            // Read headers, then the announced body.
+            let header_end = loop {
+                let n = conn.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+                if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break pos + 4;
+                }
+            };
+            let head = String::from_utf8_lossy(&request[..header_end]).to_string();
+            let length: usize = head
+                .lines()
+                .find_map(|l| l.strip_prefix("Content-Length: "))
+                .unwrap()
+                .trim()
+                .parse()
+                .unwrap();
+            while request.len() < header_end + length {
+                let n = conn.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            conn.write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            tx.send(String::from_utf8_lossy(&request).to_string()).unwrap();
+        }
+    });
+
+    let sink = WebhookSink::new(&format!("http://{}/hook", addr), EventFormat::NTriples).unwrap();
+    let (mut engine, results) = build_engine(QUERY, vec![Box::new(sink)]);
+    feed_sensors(&mut engine, 5);
+    engine.stop();
+    assert!(!results.lock().unwrap().is_empty());
+
+    let request = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(request.starts_with("POST /hook HTTP/1.1"));
+    assert!(request.contains("Content-Type: application/n-triples"));
+    assert!(request.contains("<http://out/sensors#s> <http://test/sensor"));
+}
+
+/// Read one MQTT control packet, returning its first header byte.
+fn read_mqtt_packet(conn: &mut std::net::TcpStream) -> Option<(u8, Vec<u8>)> {
+    let mut header = [0u8; 1];
+    conn.read_exact(&mut header).ok()?;
+    let mut len = 0usize;
+    let mut shift = 0;
+    loop {
+        let mut b = [0u8; 1];
+        conn.read_exact(&mut b).ok()?;
+        len |= ((b[0] & 0x7F) as usize) << shift;
+        if b[0] & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    let mut body = vec![0u8; len];
+    conn.read_exact(&mut body).ok()?;
+    Some((header[0], body))
+}
+
+#[test]
+fn mqtt_sink_publishes_results() {
+    // Minimal in-process MQTT 3.1.1 broker recording QoS 1 publishes.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (tx, rx) = mpsc::channel::<(String, String)>();
+    thread::spawn(move || {
+        let (mut conn, _) = listener.accept().unwrap();
+        while let Some((header, body)) = read_mqtt_packet(&mut conn) {
+            match header >> 4 {
+                1 => conn.write_all(&[0x20, 0x02, 0x00, 0x00]).unwrap(),
+                3 => {
+                    let topic_len = u16::from_be_bytes([body[0], body[1]]) as usize;
+                    let topic = String::from_utf8_lossy(&body[2..2 + topic_len]).to_string();
+                    let mut offset = 2 + topic_len;
+                    if (header >> 1) & 0x03 > 0 {
+                        conn.write_all(&[0x40, 0x02, body[offset], body[offset + 1]]).unwrap();
+                        offset += 2;
+                    }
+                    let payload = String::from_utf8_lossy(&body[offset..]).to_string();
+                    tx.send((topic, payload)).unwrap();
+                }
+                12 => conn.write_all(&[0xD0, 0x00]).unwrap(),
+                14 => break,
+                _ => {}
+            }
+        }
+    });
+
+    let sink = MqttSink::connect("kolibrie-sink-test", "127.0.0.1", port, "results/sensors", EventFormat::JsonLines);
+    let (mut engine, results) = build_engine(QUERY, vec![Box::new(sink)]);
+    feed_sensors(&mut engine, 5);
+    engine.stop();
+    assert!(!results.lock().unwrap().is_empty());
+
+    let (topic, payload) = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(topic, "results/sensors");
+    assert!(payload.contains("\"p\":\"http://out/sensors#s\""));
+}
+
+#[test]
+fn output_stream_feeds_downstream_query() {
+    let (mut upstream, upstream_results) = build_engine(QUERY, Vec::new());
+
+    let downstream_query = r#"
+        REGISTER RSTREAM <http://out/alerts> AS
+        SELECT *
+        FROM NAMED WINDOW :d ON <http://out/sensors> [RANGE 10 STEP 1]
+        WHERE { WINDOW :d { ?result <http://out/sensors#s> ?sensor . } }
+    "#;
+    let (mut downstream, downstream_results) = build_engine(downstream_query, Vec::new());
+    let source = upstream.subscribe_output();
+    downstream
+        .attach_source(upstream.output_stream(), Box::new(source))
+        .unwrap();
+
+    feed_sensors(&mut upstream, 5);
+    upstream.stop();
+    downstream.run_sources();
+    downstream.stop();
+
+    assert!(!upstream_results.lock().unwrap().is_empty());
+    let results = downstream_results.lock().unwrap();
+    assert!(!results.is_empty(), "Upstream output must reach the downstream window");
+    assert!(results
+        .iter()
+        .any(|r| r.iter().any(|(k, v)| k == "sensor" && v.contains("sensor"))));
+}
+
+#[test]
+fn chained_queries_run_in_one_engine() {
+    let (mut engine, sensor_results) = build_engine(QUERY, Vec::new());
+    let alerts_query = r#"
+        REGISTER RSTREAM <http://out/alerts> AS
+        SELECT ?sensor
+        FROM NAMED WINDOW :d ON <http://out/sensors> [RANGE 10 STEP 1]
+        WHERE { WINDOW :d { ?result <http://out/sensors#s> ?sensor . } }
+    "#;
+    let (alerts, alert_results) = build_engine(alerts_query, Vec::new());
+    engine.chain(alerts).unwrap();
+
+    let path = std::env::temp_dir().join(format!("kolibrie_chain_{}.nt", std::process::id()));
+    let sink = FileSink::create(&path, EventFormat::NTriples).unwrap();
+    engine.attach_sink("<http://out/alerts>", Box::new(sink)).unwrap();
+
+    feed_sensors(&mut engine, 5);
+    engine.stop();
+
+    assert!(!sensor_results.lock().unwrap().is_empty());
+    let results = alert_results.lock().unwrap();
+    assert!(!results.is_empty(), "The chained query must read the output of the first one");
+    assert!(results
+        .iter()
+        .all(|r| r.iter().any(|(k, v)| k == "sensor" && v.starts_with("http://test/sensor"))));
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    assert!(written.contains("<http://out/alerts#sensor>"));
+}
+
+#[test]
+fn chains_reading_their_own_output_are_rejected() {
+    let (mut engine, _) = build_engine(QUERY, Vec::new());
+
+    // A query reading nothing the engine produces is no chain
+    let (unrelated, _) = build_engine(QUERY, Vec::new());
+    assert!(engine.chain(unrelated).unwrap_err().contains("reads none"));
+
+    let looping_query = r#"
+        REGISTER RSTREAM <http://out/loop> AS
+        SELECT *
+        FROM NAMED WINDOW :a ON <http://out/sensors> [RANGE 10 STEP 1]
+        FROM NAMED WINDOW :b ON <http://out/loop> [RANGE 10 STEP 1]
+        WHERE { WINDOW :a { ?result <http://out/sensors#s> ?sensor . }
+                WINDOW :b { ?loop <http://out/loop#sensor> ?sensor . } }
+    "#;
+    let (looping, _) = build_engine(looping_query, Vec::new());
+    assert!(engine.chain(looping).unwrap_err().contains("cycle"));
+}
+
+#[test]
+fn sink_on_other_stream_is_rejected() {
+    let path = std::env::temp_dir().join(format!("kolibrie_sink_reject_{}.nt", std::process::id()));
+    let sink = FileSink::create(&path, EventFormat::NTriples).unwrap();
+    let result = RSPBuilder::<Triple, Vec<(String, String)>>::new()
+        .add_rsp_ql_query(QUERY)
+        .add_r2r(Box::new(SimpleR2R::with_execution_mode(QueryExecutionMode::Volcano)))
+        .set_operation_mode(OperationMode::SingleThread)
+        .add_sink("<http://out/elsewhere>", Box::new(sink))
+        .build();
+    std::fs::remove_file(&path).ok();
+    assert!(result.is_err());
+}
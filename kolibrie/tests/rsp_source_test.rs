@@ -0,0 +1,299 @@
+/*
+* Copyright © 2025 Volodymyr Kadzhaia
+* Copyright © 2025 Pieter Bonte
+* KU Leuven — Stream Intelligence Lab, Belgium
+* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this file,
+* you can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+
+use kolibrie::rsp::source::{
+    EventFormat, FileSource, MqttSource, StreamSource, TcpLineSource, Timestamping, WebSocketSource,
+};
+use kolibrie::rsp_engine::{
+    OperationMode, QueryExecutionMode, RSPBuilder, RSPEngine, ResultConsumer, SimpleR2R,
+};
+use shared::triple::Triple;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+type Results = Arc<Mutex<Vec<Vec<(String, String)>>>>;
+
+const QUERY: &str = r#"
+    REGISTER RSTREAM <http://out/stream> AS
+    SELECT *
+    FROM NAMED WINDOW :w ON :sensors [RANGE 3 STEP 1]
+    WHERE { WINDOW :w { ?s a <http://test/Sensor> . } }
+"#;
+
+fn build_engine(sources: Vec<Box<dyn StreamSource>>) -> (RSPEngine<Triple, Vec<(String, String)>>, Results) {
+    let results: Results = Arc::new(Mutex::new(Vec::new()));
+    let rc = Arc::clone(&results);
+    let consumer = ResultConsumer {
+        function: Arc::new(move |r: Vec<(String, String)>| {
+            rc.lock().unwrap().push(r);
+        }),
+    };
+    let mut builder = RSPBuilder::new()
+        .add_rsp_ql_query(QUERY)
+        .add_consumer(consumer)
+        .add_r2r(Box::new(SimpleR2R::with_execution_mode(QueryExecutionMode::Volcano)))
+        .set_operation_mode(OperationMode::SingleThread);
+    for source in sources {
+        builder = builder.add_source(":sensors", source);
+    }
+    let engine = builder.build().expect("Failed to build engine");
+    (engine, results)
+}
+
+fn sensor_lines(n: usize) -> String {
+    (1..=n)
+        .map(|i| format!("<http://test/sensor{}> a <http://test/Sensor> .\n", i))
+        .collect()
+}
+
+/// Pump sources until `results` holds at least `expected` firings or the deadline passes.
+fn pump_until(engine: &mut RSPEngine<Triple, Vec<(String, String)>>, results: &Results, expected: usize) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while results.lock().unwrap().len() < expected && Instant::now() < deadline {
+        engine.poll_sources();
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn file_source_feeds_window() {
+    let path = std::env::temp_dir().join(format!("kolibrie_source_{}.jsonl", std::process::id()));
+    let mut lines = String::new();
+    for i in 1..=5 {
+        lines.push_str(&format!(
+            "{{\"s\": \"http://test/sensor{}\", \"p\": \"http://www.w3.org/1999/02/22-rdf-syntax-ns#type\", \"o\": \"http://test/Sensor\", \"ts\": {}}}\n",
+            i, i
+        ));
+    }
+    std::fs::write(&path, lines).unwrap();
+
+    let source = FileSource::open(&path, EventFormat::JsonLines).unwrap();
+    let (mut engine, results) = build_engine(vec![Box::new(source)]);
+    let processed = engine.run_sources();
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(processed, 5);
+    let results = results.lock().unwrap();
+    // ts=2..5 each close a window: [-1,1], [0,2], [1,3], [2,4].
+    assert!(!results.is_empty(), "File events must trigger window evaluation");
+    assert!(results
+        .iter()
+        .any(|r| r.iter().any(|(k, v)| k == "s" && v.contains("sensor3"))));
+}
+
+#[test]
+fn file_source_replay_speed_paces_events() {
+    let path = std::env::temp_dir().join(format!("kolibrie_replay_{}.nt", std::process::id()));
+    std::fs::write(&path, sensor_lines(3)).unwrap();
+
+    // Sequence timestamps 1..3 at 10ms per unit → at least 20ms of pacing.
+    let mut source = FileSource::open(&path, EventFormat::NTriples)
+        .unwrap()
+        .replay_speed(1.0)
+        .time_unit(Duration::from_millis(10));
+    let start = Instant::now();
+    let mut events = Vec::new();
+    while let Some(batch) = source.poll_events().unwrap() {
+        events.extend(batch);
+    }
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(events.len(), 3);
+    assert!(start.elapsed() >= Duration::from_millis(20));
+}
+
+#[test]
+fn tcp_source_feeds_window() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (mut conn, _) = listener.accept().unwrap();
+        conn.write_all(sensor_lines(5).as_bytes()).unwrap();
+    });
+
+    let source = TcpLineSource::connect(addr, EventFormat::NTriples)
+        .unwrap()
+        .timestamping(Timestamping::Sequence);
+    let (mut engine, results) = build_engine(vec![Box::new(source)]);
+    server.join().unwrap();
+    assert_eq!(engine.run_sources(), 5);
+    assert!(!results.lock().unwrap().is_empty());
+}
+
+#[test]
+fn websocket_source_feeds_window() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let (mut conn, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut byte = [0u8; 1];
+        while !request.ends_with(b"\r\n\r\n") {
+            conn.read_exact(&mut byte).unwrap();
+            request.push(byte[0]);
+        }
+        conn.write_all(
+            b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\r\n",
+        )
+        .unwrap();
+        let payload = sensor_lines(5);
+        let mut frame = vec![0x81, 126];
+        frame.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        frame.extend_from_slice(payload.as_bytes());
+        conn.write_all(&frame).unwrap();
+        conn.write_all(&[0x88, 0x00]).unwrap();
+        // Wait for the client's close frame before dropping the connection.
+        let _ = conn.read(&mut [0u8; 16]);
+    });
+
+    let url = format!("ws://{}/events", addr);
+    let source = WebSocketSource::connect(&url, EventFormat::NTriples)
+        .unwrap()
+        .timestamping(Timestamping::Sequence);
+    let (mut engine, results) = build_engine(vec![Box::new(source)]);
+    assert_eq!(engine.run_sources(), 5);
+    assert!(!results.lock().unwrap().is_empty());
+}
+
+/// Read one MQTT control packet, returning its first header byte.
+fn read_mqtt_packet(conn: &mut std::net::TcpStream) -> Option<(u8, Vec<u8>)> {
+    let mut header = [0u8; 1];
+    conn.read_exact(&mut header).ok()?;
+    let mut len = 0usize;
+    let mut shift = 0;
+    loop {
+        let mut b = [0u8; 1];
+        conn.read_exact(&mut b).ok()?;
+        len |= ((b[0] & 0x7F) as usize) << shift;
+        if b[0] & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    let mut body = vec![0u8; len];
+    conn.read_exact(&mut body).ok()?;
+    Some((header[0], body))
+}
+
+#[test]
+fn mqtt_source_feeds_window() {
+    // Minimal in-process MQTT 3.1.1 broker: CONNACK, SUBACK, then one QoS 0 publish.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let (mut conn, _) = listener.accept().unwrap();
+        while let Some((header, body)) = read_mqtt_packet(&mut conn) {
+            match header >> 4 {
+                1 => conn.write_all(&[0x20, 0x02, 0x00, 0x00]).unwrap(),
+                8 => {
+                    conn.write_all(&[0x90, 0x03, body[0], body[1], 0x00]).unwrap();
+                    let topic = b"sensors/events";
+                    let payload = sensor_lines(5);
+                    let mut publish = vec![0x30];
+                    let mut remaining = 2 + topic.len() + payload.len();
+                    loop {
+                        let byte = (remaining % 128) as u8;
+                        remaining /= 128;
+                        if remaining == 0 {
+                            publish.push(byte);
+                            break;
+                        }
+                        publish.push(byte | 0x80);
+                    }
+                    publish.extend_from_slice(&(topic.len() as u16).to_be_bytes());
+                    publish.extend_from_slice(topic);
+                    publish.extend_from_slice(payload.as_bytes());
+                    conn.write_all(&publish).unwrap();
+                }
+                12 => conn.write_all(&[0xD0, 0x00]).unwrap(),
+                _ => {}
+            }
+        }
+    });
+
+    let source = MqttSource::connect("kolibrie-test", "127.0.0.1", port, "sensors/#", EventFormat::NTriples)
+        .unwrap()
+        .timestamping(Timestamping::Sequence);
+    let (mut engine, results) = build_engine(vec![Box::new(source)]);
+    pump_until(&mut engine, &results, 1);
+    engine.stop();
+    assert!(!results.lock().unwrap().is_empty());
+}
+
+#[test]
+fn source_on_undeclared_stream_is_rejected() {
+    let path = std::env::temp_dir().join(format!("kolibrie_undeclared_{}.nt", std::process::id()));
+    std::fs::write(&path, sensor_lines(1)).unwrap();
+    let source = FileSource::open(&path, EventFormat::NTriples).unwrap();
+    let result = RSPBuilder::<Triple, Vec<(String, String)>>::new()
+        .add_rsp_ql_query(QUERY)
+        .add_r2r(Box::new(SimpleR2R::with_execution_mode(QueryExecutionMode::Volcano)))
+        .set_operation_mode(OperationMode::SingleThread)
+        .add_source("<http://unknown/stream>", Box::new(source))
+        .build();
+    std::fs::remove_file(&path).ok();
+    assert!(result.is_err());
+}
+
+#[test]
+fn source_terms_keep_their_kind_into_the_window() {
+    let lines = [
+        r#"<http://test/sensor1> <http://test/reading> "12:30" ."#,
+        r#"<http://test/sensor1> <http://test/reading> "21.5"^^<http://www.w3.org/2001/XMLSchema#decimal> ."#,
+        r#"<http://test/sensor1> <http://test/reading> "warm"@en ."#,
+        r#"<http://test/sensor1> <http://test/reading> <http://test/sensor2> ."#,
+    ];
+    let path = std::env::temp_dir().join(format!("kolibrie_terms_{}.nt", std::process::id()));
+    std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+
+    // Decoded events serialize back to the statements they were read from.
+    let mut source = FileSource::open(&path, EventFormat::NTriples).unwrap();
+    let mut events = Vec::new();
+    while let Some(batch) = source.poll_events().unwrap() {
+        events.extend(batch);
+    }
+    let written: Vec<String> = events.iter().map(|e| e.triple.to_ntriples()).collect();
+    assert_eq!(written, lines);
+
+    let results: Results = Arc::new(Mutex::new(Vec::new()));
+    let rc = Arc::clone(&results);
+    let mut engine: RSPEngine<Triple, Vec<(String, String)>> = RSPBuilder::new()
+        .add_rsp_ql_query(
+            r#"
+            REGISTER RSTREAM <http://out/stream> AS
+            SELECT ?o
+            FROM NAMED WINDOW :w ON :readings [RANGE 10 STEP 1]
+            WHERE { WINDOW :w { <http://test/sensor1> <http://test/reading> ?o . } }
+        "#,
+        )
+        .add_consumer(ResultConsumer {
+            function: Arc::new(move |r: Vec<(String, String)>| rc.lock().unwrap().push(r)),
+        })
+        .add_r2r(Box::new(SimpleR2R::with_execution_mode(QueryExecutionMode::Volcano)))
+        .set_operation_mode(OperationMode::SingleThread)
+        .add_source(":readings", Box::new(FileSource::open(&path, EventFormat::NTriples).unwrap()))
+        .build()
+        .expect("Failed to build engine");
+    assert_eq!(engine.run_sources(), 4);
+    engine.stop();
+    std::fs::remove_file(&path).ok();
+
+    let mut values: Vec<String> = results
+        .lock()
+        .unwrap()
+        .iter()
+        .flat_map(|row| row.iter().filter(|(k, _)| k == "o").map(|(_, v)| v.clone()))
+        .collect();
+    values.sort();
+    values.dedup();
+    assert_eq!(values, vec!["12:30", "21.5", "http://test/sensor2", "warm@en"]);
+}
@@ -0,0 +1,127 @@
+/*
+ * Copyright © 2026 Volodymyr Kadzhaia
+ * Copyright © 2026 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use kolibrie::execute_query::execute_query_rayon_parallel2_volcano;
+use kolibrie::parser::process_rule_definition;
+use kolibrie::sparql_database::SparqlDatabase;
+use shared::rule::AggregateFunction;
+use shared::triple::Triple;
+
+fn decoded(db: &SparqlDatabase, triples: &[Triple]) -> Vec<(String, String, String)> {
+    let dict = db.dictionary.read().unwrap();
+    let decode = |id: u32| dict.decode(id).unwrap_or_default().to_string();
+    let mut decoded: Vec<_> = triples
+        .iter()
+        .map(|t| (decode(t.subject), decode(t.predicate), decode(t.object)))
+        .collect();
+    decoded.sort();
+    decoded
+}
+
+#[test]
+fn sensors_with_more_than_three_alarms_are_faulty() {
+    let mut db = SparqlDatabase::new();
+    let mut data = String::new();
+    for (alarm, sensor) in [(1, "s1"), (2, "s1"), (3, "s1"), (4, "s1"), (5, "s2"), (6, "s2")] {
+        data.push_str(&format!(
+            "<http://example.org/alarm{}> <http://example.org/alarmOf> <http://example.org/{}> .\n",
+            alarm, sensor
+        ));
+    }
+    db.parse_ntriples_and_add(&data);
+
+    let rule = r#"PREFIX ex: <http://example.org/>
+RULE :FaultySensor :-
+CONSTRUCT {
+    ?sensor ex:status ex:faulty ;
+            ex:alarmCount ?n .
+}
+WHERE {
+    ?alarm ex:alarmOf ?sensor .
+    BIND(COUNT(?alarm) AS ?n)
+    FILTER(?n > 3)
+}"#;
+    let (rule, inferred) = process_rule_definition(rule, &mut db).expect("rule processing failed");
+    assert_eq!(rule.aggregates.len(), 1);
+    assert_eq!(rule.aggregates[0].function, AggregateFunction::Count);
+    assert_eq!(
+        decoded(&db, &inferred),
+        vec![
+            ("http://example.org/s1".to_string(), "http://example.org/alarmCount".to_string(), "4".to_string()),
+            ("http://example.org/s1".to_string(), "http://example.org/status".to_string(), "http://example.org/faulty".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn rules_compute_head_terms() {
+    let mut db = SparqlDatabase::new();
+    db.parse_ntriples_and_add(
+        r#"<http://example.org/o1> <http://example.org/net> "40" .
+<http://example.org/o1> <http://example.org/tax> "2" .
+"#,
+    );
+
+    let rule = r#"PREFIX ex: <http://example.org/>
+RULE :GrossPrice :-
+CONSTRUCT {
+    ?order ex:gross ?total .
+}
+WHERE {
+    ?order ex:net ?net .
+    ?order ex:tax ?tax .
+    BIND(?net + ?tax AS ?total)
+}"#;
+    let (rule, inferred) = process_rule_definition(rule, &mut db).expect("rule processing failed");
+    assert_eq!(rule.bindings.len(), 1);
+    assert_eq!(
+        decoded(&db, &inferred),
+        vec![("http://example.org/o1".to_string(), "http://example.org/gross".to_string(), "42".to_string())]
+    );
+}
+
+#[test]
+fn rules_with_unknown_functions_or_wrong_arities_are_rejected() {
+    for bind in ["BIND(COUNT(?net, ?tax) AS ?total)", "BIND(SUBSTR(?net) AS ?total)"] {
+        let mut db = SparqlDatabase::new();
+        db.parse_ntriples_and_add("<http://example.org/o1> <http://example.org/net> \"40\" .\n");
+        let rule = format!(
+            r#"PREFIX ex: <http://example.org/>
+RULE :Broken :-
+CONSTRUCT {{
+    ?order ex:gross ?total .
+}}
+WHERE {{
+    ?order ex:net ?net .
+    ?order ex:tax ?tax .
+    {}
+}}"#,
+            bind
+        );
+        let err = process_rule_definition(&rule, &mut db).expect_err("rule should be rejected");
+        assert!(err.contains("cannot compute 'total'"), "{}", err);
+    }
+}
+
+#[test]
+fn queries_bind_arithmetic_expressions() {
+    let mut db = SparqlDatabase::new();
+    db.parse_ntriples_and_add(
+        r#"<http://example.org/o1> <http://example.org/net> "40" .
+<http://example.org/o1> <http://example.org/tax> "2" .
+"#,
+    );
+    let rows = execute_query_rayon_parallel2_volcano(
+        r#"PREFIX ex: <http://example.org/>
+        SELECT ?total WHERE { ?order ex:net ?net . ?order ex:tax ?tax . BIND(?net + ?tax * 2 AS ?total) }"#,
+        &mut db,
+    );
+    assert_eq!(rows, vec![vec!["44".to_string()]]);
+}
@@ -0,0 +1,254 @@
+/*
+ * Copyright © 2026 Volodymyr Kadzhaia
+ * Copyright © 2026 Pieter Bonte
+ * KU Leuven — Stream Intelligence Lab, Belgium
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * you can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use kolibrie::execute_query::execute_query;
+use kolibrie::shacl::{Path, ShapesGraph, ValidationReport};
+use kolibrie::sparql_database::SparqlDatabase;
+
+const SHAPES: &str = r#"
+@prefix sh: <http://www.w3.org/ns/shacl#> .
+@prefix xsd: <http://www.w3.org/2001/XMLSchema#> .
+@prefix ex: <http://example.org/> .
+
+ex:PersonShape a sh:NodeShape ;
+    sh:targetClass ex:Person ;
+    sh:property [
+        sh:path ex:name ;
+        sh:minCount 1 ;
+        sh:maxCount 1 ;
+        sh:datatype xsd:string ;
+    ] ;
+    sh:property [
+        sh:path ex:age ;
+        sh:datatype xsd:integer ;
+        sh:minInclusive 0 ;
+        sh:maxExclusive 150 ;
+    ] ;
+    sh:property [
+        sh:path ex:email ;
+        sh:pattern "^[a-z.]+@example\\.org$" ;
+        sh:flags "i" ;
+    ] ;
+    sh:property [
+        sh:path ex:worksFor ;
+        sh:class ex:Company ;
+        sh:nodeKind sh:IRI ;
+    ] ;
+    sh:property [
+        sh:path ex:status ;
+        sh:in ( "active" "retired" ) ;
+        sh:message "unknown status" ;
+    ] .
+"#;
+
+fn people(ntriples: &str) -> SparqlDatabase {
+    let mut db = SparqlDatabase::new();
+    db.parse_ntriples_and_add(
+        r#"<http://example.org/Startup> <http://www.w3.org/2000/01/rdf-schema#subClassOf> <http://example.org/Company> .
+<http://example.org/acme> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://example.org/Startup> .
+"#,
+    );
+    db.parse_ntriples_and_add(ntriples);
+    db
+}
+
+/// (focus node, component) of every result, sorted
+fn violations(report: &ValidationReport) -> Vec<(String, String)> {
+    let mut violations: Vec<(String, String)> = report
+        .results
+        .iter()
+        .map(|r| (r.focus_node.replace("http://example.org/", ""), r.source_constraint_component.clone()))
+        .collect();
+    violations.sort();
+    violations
+}
+
+#[test]
+fn conforming_data_gives_an_empty_report() {
+    let db = people(
+        r#"<http://example.org/alice> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://example.org/Person> .
+<http://example.org/alice> <http://example.org/name> "Alice" .
+<http://example.org/alice> <http://example.org/age> "42"^^<http://www.w3.org/2001/XMLSchema#integer> .
+<http://example.org/alice> <http://example.org/email> "Alice@Example.org" .
+<http://example.org/alice> <http://example.org/worksFor> <http://example.org/acme> .
+<http://example.org/alice> <http://example.org/status> "active" .
+"#,
+    );
+    let shapes = ShapesGraph::parse(SHAPES).unwrap();
+    let report = db.validate(&shapes).unwrap();
+    assert!(report.conforms, "{:?}", report.results);
+    assert!(report.results.is_empty());
+}
+
+#[test]
+fn property_shapes_report_each_violated_constraint() {
+    let db = people(
+        r#"<http://example.org/bob> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://example.org/Person> .
+<http://example.org/bob> <http://example.org/age> "two hundred" .
+<http://example.org/bob> <http://example.org/email> "bob at example" .
+<http://example.org/bob> <http://example.org/worksFor> <http://example.org/carol> .
+<http://example.org/bob> <http://example.org/status> "sleeping" .
+<http://example.org/carol> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://example.org/Person> .
+<http://example.org/carol> <http://example.org/name> "Carol" .
+<http://example.org/carol> <http://example.org/name> "Caroline" .
+<http://example.org/carol> <http://example.org/age> "200" .
+"#,
+    );
+    let report = db.validate(&ShapesGraph::parse(SHAPES).unwrap()).unwrap();
+    assert!(!report.conforms);
+    let expected: Vec<(String, String)> = [
+        ("bob", "Class"),
+        ("bob", "Datatype"),
+        ("bob", "In"),
+        ("bob", "MaxExclusive"),
+        ("bob", "MinCount"),
+        ("bob", "MinInclusive"),
+        ("bob", "Pattern"),
+        ("carol", "MaxCount"),
+        ("carol", "MaxExclusive"),
+    ]
+    .iter()
+    .map(|(focus, component)| (focus.to_string(), format!("{}ConstraintComponent", component)))
+    .collect();
+    assert_eq!(violations(&report), expected);
+
+    let status = report.results.iter().find(|r| r.source_constraint_component == "InConstraintComponent").unwrap();
+    assert_eq!(status.message, "unknown status");
+    assert_eq!(status.value.as_deref(), Some("sleeping"));
+    assert_eq!(status.result_path, Some(Path::Predicate("http://example.org/status".to_string())));
+    assert_eq!(status.severity, "http://www.w3.org/ns/shacl#Violation");
+}
+
+#[test]
+fn logical_constraints_combine_shapes() {
+    let shapes = ShapesGraph::parse(
+        r#"
+@prefix sh: <http://www.w3.org/ns/shacl#> .
+@prefix ex: <http://example.org/> .
+
+ex:HasName sh:path ex:name ; sh:minCount 1 .
+ex:HasLabel sh:path ex:label ; sh:minCount 1 .
+
+ex:NamedShape a sh:NodeShape ;
+    sh:targetSubjectsOf ex:kind ;
+    sh:or ( ex:HasName ex:HasLabel ) .
+
+ex:ExclusiveShape a sh:NodeShape ;
+    sh:targetSubjectsOf ex:kind ;
+    sh:xone ( ex:HasName ex:HasLabel ) ;
+    sh:severity sh:Warning .
+
+ex:NotDraftShape a sh:NodeShape ;
+    sh:targetSubjectsOf ex:kind ;
+    sh:and ( ex:HasName [ sh:not [ sh:path ex:kind ; sh:hasValue "draft" ] ] ) .
+"#,
+    )
+    .unwrap();
+    let mut db = SparqlDatabase::new();
+    db.parse_ntriples_and_add(
+        r#"<http://example.org/a> <http://example.org/kind> "final" .
+<http://example.org/a> <http://example.org/name> "A" .
+<http://example.org/b> <http://example.org/kind> "final" .
+<http://example.org/b> <http://example.org/name> "B" .
+<http://example.org/b> <http://example.org/label> "Bee" .
+<http://example.org/c> <http://example.org/kind> "final" .
+<http://example.org/d> <http://example.org/kind> "draft" .
+<http://example.org/d> <http://example.org/name> "D" .
+"#,
+    );
+    let report = db.validate(&shapes).unwrap();
+    let expected: Vec<(String, String)> = [
+        ("b", "Xone"),
+        ("c", "And"),
+        ("c", "Or"),
+        ("c", "Xone"),
+        ("d", "And"),
+    ]
+    .iter()
+    .map(|(focus, component)| (focus.to_string(), format!("{}ConstraintComponent", component)))
+    .collect();
+    assert_eq!(violations(&report), expected);
+    let warnings = report.results.iter().filter(|r| r.severity.ends_with("#Warning")).count();
+    assert_eq!(warnings, 2);
+}
+
+#[test]
+fn reports_are_rdf_graphs() {
+    let shapes = ShapesGraph::parse(
+        r#"
+@prefix sh: <http://www.w3.org/ns/shacl#> .
+@prefix ex: <http://example.org/> .
+
+ex:ParentShape sh:targetNode ex:alice ;
+    sh:property [ sh:path [ sh:inversePath ex:parent ] ; sh:minCount 1 ] .
+"#,
+    )
+    .unwrap();
+    let mut db = SparqlDatabase::new();
+    db.parse_ntriples_and_add("<http://example.org/alice> <http://example.org/name> \"Alice\" .\n");
+    let report = shapes.validate(&db).unwrap();
+    assert_eq!(report.results.len(), 1);
+
+    let triples = report.to_triples();
+    let sh = |name: &str| format!("http://www.w3.org/ns/shacl#{}", name);
+    assert!(triples.contains(&("_:report".to_string(), sh("conforms"), "false".to_string())));
+    assert!(triples.contains(&("_:result0".to_string(), sh("focusNode"), "http://example.org/alice".to_string())));
+    assert!(triples.contains(&("_:result0_path".to_string(), sh("inversePath"), "http://example.org/parent".to_string())));
+
+    let turtle = report.to_turtle();
+    assert!(turtle.contains("_:report a sh:ValidationReport ."));
+    assert!(turtle.contains("_:report sh:conforms false ."));
+    assert!(turtle.contains("_:result0 sh:sourceConstraintComponent sh:MinCountConstraintComponent ."));
+
+    report.add_to(&mut db);
+    let results = execute_query(
+        "PREFIX sh: <http://www.w3.org/ns/shacl#>
+         SELECT ?focus WHERE { ?result sh:focusNode ?focus . ?result sh:sourceConstraintComponent sh:MinCountConstraintComponent }",
+        &mut db,
+    );
+    assert_eq!(results, vec![vec!["http://example.org/alice".to_string()]]);
+}
+
+#[test]
+fn turtle_data_is_validated_with_its_datatypes() {
+    let shapes = ShapesGraph::parse(SHAPES).unwrap();
+    let data = |age: &str| {
+        format!(
+            r#"@prefix ex: <http://example.org/> .
+@prefix xsd: <http://www.w3.org/2001/XMLSchema#> .
+ex:alice a ex:Person ; ex:name "Alice" ; ex:age {} ."#,
+            age
+        )
+    };
+    assert!(shapes.validate_turtle(&data("42")).unwrap().conforms);
+    assert!(shapes.validate_turtle(&data("\"42\"^^xsd:integer")).unwrap().conforms);
+
+    let report = shapes.validate_turtle(&data("\"42\"^^xsd:string")).unwrap();
+    assert_eq!(violations(&report), vec![("alice".to_string(), "DatatypeConstraintComponent".to_string())]);
+    assert!(report.to_turtle().contains("sh:value \"42\"^^<http://www.w3.org/2001/XMLSchema#string> ."));
+
+    // Numbers are integers, not strings
+    let report = shapes.validate_turtle(&data("42 ; ex:name 7")).unwrap();
+    assert_eq!(violations(&report).len(), 2);
+    assert!(shapes.validate_turtle("ex:alice ex:age 42 .").is_err());
+}
+
+#[test]
+fn malformed_shapes_are_rejected() {
+    assert!(ShapesGraph::parse("ex:Shape sh:targetClass ex:Person .").is_err());
+    assert!(ShapesGraph::parse("{ ?x <http://example.org/p> ?y } => { ?y <http://example.org/p> ?x } .").is_err());
+
+    let shapes = ShapesGraph::parse(
+        r#"<http://example.org/S> <http://www.w3.org/ns/shacl#targetNode> <http://example.org/x> ;
+    <http://www.w3.org/ns/shacl#pattern> "(unclosed" ."#,
+    )
+    .unwrap();
+    assert!(SparqlDatabase::new().validate(&shapes).is_err());
+}
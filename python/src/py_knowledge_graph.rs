@@ -139,6 +139,8 @@ impl PyKnowledgeGraph {
                 .map(|p| (convert_term(p.subject), convert_term(p.predicate), convert_term(p.object)))
                 .collect(),
             negative_premise: vec![],
+            bindings: vec![],
+            aggregates: vec![],
             filters: rule.filters
                 .into_iter()
                 .map(|f| shared::rule::FilterCondition {
@@ -231,6 +233,8 @@ impl PyKnowledgeGraph {
                 .collect(),
 
             negative_premise: vec![],
+            bindings: vec![],
+            aggregates: vec![],
 
             filters: rule.filters
                 .into_iter()
@@ -275,6 +279,65 @@ impl PyKnowledgeGraph {
             .collect()
     }
 
+    /// Answers a conjunctive query under "ar", "iar" or "brave" semantics
+    fn query_with_semantics(
+        &mut self,
+        query: Vec<PyTriplePattern>,
+        answer_variables: Vec<String>,
+        semantics: &str,
+    ) -> PyResult<Vec<HashMap<String, PyTerm>>> {
+        let semantics = match semantics.to_ascii_lowercase().as_str() {
+            "ar" => datalog::reasoning::repairs::RepairSemantics::AR,
+            "iar" => datalog::reasoning::repairs::RepairSemantics::IAR,
+            "brave" => datalog::reasoning::repairs::RepairSemantics::Brave,
+            other => {
+                return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                    "unknown repair semantics '{}', expected ar, iar or brave",
+                    other
+                )))
+            }
+        };
+        let rust_query: Vec<_> = query
+            .into_iter()
+            .map(|p| (convert_term(p.subject), convert_term(p.predicate), convert_term(p.object)))
+            .collect();
+
+        let answer_variables: Vec<&str> = answer_variables.iter().map(String::as_str).collect();
+        let results = self.inner.query_with_semantics(&rust_query, &answer_variables, semantics);
+
+        Ok(results
+            .into_iter()
+            .map(|bindings| {
+                bindings
+                    .into_iter()
+                    .map(|(key, value)| (key, convert_term_back(shared::terms::Term::Constant(value))))
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Minimal sets of facts that violate a constraint, decoded
+    fn conflicts(&self) -> Vec<Vec<(String, String, String)>> {
+        let conflicts = self.inner.conflicts();
+
+        let dict = self.inner.dictionary.read().unwrap();
+        conflicts
+            .into_iter()
+            .map(|conflict| {
+                conflict
+                    .facts
+                    .iter()
+                    .map(|triple| {
+                        let s = dict.decode(triple.subject).unwrap_or_default().to_string();
+                        let p = dict.decode(triple.predicate).unwrap_or_default().to_string();
+                        let o = dict.decode(triple.object).unwrap_or_default().to_string();
+                        (s, p, o)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
     fn infer_new_facts_semi_naive_with_repairs(&mut self) -> Vec<(String, String, String)> {
         let inferred = self.inner.infer_new_facts_semi_naive_with_repairs();
         
@@ -9,9 +9,13 @@
  */
 
 use pyo3::prelude::*;
+use kolibrie::execute_query::{execute_ask, execute_query_iter};
+use kolibrie::prepared_query::PreparedQuery;
 use kolibrie::sparql_database::SparqlDatabase;
+use std::sync::mpsc::{self, Receiver};
 use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::thread;
 
 #[pyclass]
 pub struct PySparqlDatabase {
@@ -64,6 +68,139 @@ impl PySparqlDatabase {
             periodic_periods: Vec::new(), // <- This was missing!
         }
     }
+
+    /// Run a SPARQL query and iterate over its rows lazily.
+    ///
+    /// Rows are pulled from the query engine `batch_size` at a time, so
+    /// breaking out of the loop stops the evaluation. The database stays
+    /// locked until the iterator is exhausted or dropped.
+    #[pyo3(signature = (sparql, batch_size = 1024))]
+    fn query_iter(&self, py: Python<'_>, sparql: &str, batch_size: usize) -> PyQueryResultIterator {
+        let (variables_tx, variables_rx) = mpsc::channel();
+        // One batch in flight: the producer only runs ahead of the consumer by a batch
+        let (batches_tx, batches_rx) = mpsc::sync_channel(1);
+        let db = Arc::clone(&self.db);
+        let sparql = sparql.to_string();
+        let batch_size = batch_size.max(1);
+
+        thread::spawn(move || {
+            let mut db = match db.lock() {
+                Ok(db) => db,
+                Err(_) => return,
+            };
+            let mut rows = execute_query_iter(&sparql, &mut db);
+            let _ = variables_tx.send(rows.variables().to_vec());
+            loop {
+                let batch = rows.next_batch(batch_size);
+                if batch.is_empty() || batches_tx.send(batch).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let variables = py.allow_threads(move || variables_rx.recv().unwrap_or_default());
+        PyQueryResultIterator {
+            variables,
+            buffered: Mutex::new(VecDeque::new()),
+            batches: Mutex::new(Some(batches_rx)),
+        }
+    }
+
+    /// Answer a SPARQL ASK query, stopping at the first solution.
+    fn ask(&self, py: Python<'_>, sparql: &str) -> bool {
+        py.allow_threads(|| match self.db.lock() {
+            Ok(mut db) => execute_ask(sparql, &mut db),
+            Err(_) => false,
+        })
+    }
+
+    /// Plan a SELECT query with `$name` placeholders once, to execute it
+    /// many times with different values.
+    fn prepare(&self, sparql: &str) -> PyResult<PyPreparedQuery> {
+        let mut db = self
+            .db
+            .lock()
+            .map_err(|_| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>("Failed to acquire database lock"))?;
+        let query = db
+            .prepare(sparql)
+            .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+        Ok(PyPreparedQuery {
+            db: Arc::clone(&self.db),
+            query,
+        })
+    }
+}
+
+/// A query planned once, see `PySparqlDatabase.prepare`.
+#[pyclass]
+pub struct PyPreparedQuery {
+    db: Arc<Mutex<SparqlDatabase>>,
+    query: PreparedQuery,
+}
+
+#[pymethods]
+impl PyPreparedQuery {
+    /// Names of the `$` placeholders of the query.
+    #[getter]
+    fn parameters(&self) -> Vec<String> {
+        self.query.parameters().to_vec()
+    }
+
+    /// Run the query with the given values for its parameters, e.g.
+    /// `query.execute({"person": "<http://example.org/alice>"})`.
+    #[pyo3(signature = (params = None))]
+    fn execute(&self, py: Python<'_>, params: Option<HashMap<String, String>>) -> PyResult<Vec<Vec<String>>> {
+        let params = params.unwrap_or_default();
+        let db = Arc::clone(&self.db);
+        let query = self.query.clone();
+        py.allow_threads(move || {
+            let bindings: Vec<(&str, &str)> = params.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect();
+            let mut db = db
+                .lock()
+                .map_err(|_| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>("Failed to acquire database lock"))?;
+            query
+                .execute(&mut db, &bindings)
+                .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)
+        })
+    }
+}
+
+/// Python iterator over the rows of a SPARQL query, see `PySparqlDatabase.query_iter`.
+#[pyclass]
+pub struct PyQueryResultIterator {
+    variables: Vec<String>,
+    buffered: Mutex<VecDeque<Vec<String>>>,
+    /// Batches produced by the query thread; `None` once it has finished
+    batches: Mutex<Option<Receiver<Vec<Vec<String>>>>>,
+}
+
+#[pymethods]
+impl PyQueryResultIterator {
+    /// The selected variables, in the order of the values in every row.
+    #[getter]
+    fn variables(&self) -> Vec<String> {
+        self.variables.clone()
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&self, py: Python<'_>) -> Option<Vec<String>> {
+        let mut buffered = self.buffered.lock().unwrap();
+        if buffered.is_empty() {
+            let mut batches = self.batches.lock().unwrap();
+            if let Some(receiver) = batches.take() {
+                let (batch, receiver) = py.allow_threads(move || (receiver.recv().ok(), receiver));
+                // Dropping the receiver at the end releases the query thread and its database lock
+                if let Some(batch) = batch {
+                    buffered.extend(batch);
+                    *batches = Some(receiver);
+                }
+            }
+        }
+        buffered.pop_front()
+    }
 }
 
 #[pyclass]
@@ -716,6 +853,8 @@ impl PyQueryBuilder {
 pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
     // Register classes
     m.add_class::<PySparqlDatabase>()?;
+    m.add_class::<PyQueryResultIterator>()?;
+    m.add_class::<PyPreparedQuery>()?;
     m.add_class::<PyQueryBuilder>()?;
     m.add_class::<PyStreamingQuery>()?;
     m.add_class::<PyPeriodicReportStrategy>()?;
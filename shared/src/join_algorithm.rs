@@ -524,12 +524,21 @@ pub fn perform_hash_join_for_rules(
         .as_ref()
         .and_then(|s| dict.string_to_id.get(s).copied());
 
+    // Constant subject and object of the premise
+    let constant = |term: &Term| match term {
+        Term::Constant(c) => Some(*c),
+        _ => None,
+    };
+    let (subject_id, object_id) = (constant(&premise.0), constant(&premise.2));
+
     // Pre-filter triples (this is very fast)
     let filtered_triples: Vec<&Triple> = triples
         .iter()
         .filter(|triple| {
             triple.predicate == predicate_id
                 && literal_filter_id.map_or(true, |filter_id| triple.object == filter_id)
+                && subject_id.is_none_or(|id| triple.subject == id)
+                && object_id.is_none_or(|id| triple.object == id)
         })
         .collect();
 
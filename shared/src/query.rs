@@ -11,7 +11,7 @@
 use std::collections::HashMap;
 use std::time::Duration;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum FilterExpression<'a> {
     Comparison(&'a str, &'a str, &'a str),
     And(Box<FilterExpression<'a>>, Box<FilterExpression<'a>>),
@@ -21,7 +21,7 @@ pub enum FilterExpression<'a> {
     FunctionCall(&'a str, Vec<&'a str>),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ArithmeticExpression<'a> {
     Operand(&'a str), // Variable, literal, or number
     Add(Box<ArithmeticExpression<'a>>, Box<ArithmeticExpression<'a>>),
@@ -174,8 +174,12 @@ pub struct WindowClause<'a> {
     pub window_iri: &'a str,
     pub stream_iri: &'a str,
     pub window_spec: WindowSpec<'a>,
+    /// `PARTITION BY ?var`: keep a separate window per value of `?var`.
+    pub partition_by: Option<&'a str>,
     /// Per-window sync policy; `None` means use the engine-level default.
     pub policy: Option<SyncPolicy>,
+    /// Per-window `WITH REPLAN` trigger; `None` means use the engine-level default.
+    pub replan: Option<ReplanTrigger>,
 }
 
 #[derive(Clone, Debug)]
@@ -216,6 +220,47 @@ impl Default for SyncPolicy {
     }
 }
 
+/// When the adaptive planner recomputes a window's join order, judged by how the
+/// statistics of the window content moved since the plan was last computed, or by
+/// how far the running plan's cardinality estimates were off.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub enum ReplanTrigger {
+    /// Keep the plan computed for the first firing.
+    #[default]
+    Static,
+    /// Replan on every firing.
+    Always,
+    /// Relative change in the number of triples exceeds `threshold`.
+    OnSizeChange { threshold: f64 },
+    /// Total variation distance between object distributions exceeds `threshold`.
+    OnDistributionChange { threshold: f64 },
+    /// Fraction of object pairs whose frequency order flipped exceeds `threshold`.
+    OnRankingChange { threshold: f64 },
+    /// Any of the size, distribution or ranking conditions holds.
+    Hybrid { size_threshold: f64, distribution_threshold: f64, ranking_threshold: f64 },
+    /// The largest q-error (max of estimate/actual and actual/estimate) of the running
+    /// plan's operators exceeds `threshold` in `patience` consecutive firings.
+    OnEstimateError { threshold: f64, patience: usize },
+}
+
+impl std::fmt::Display for ReplanTrigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplanTrigger::Static => write!(f, "Static"),
+            ReplanTrigger::Always => write!(f, "Always"),
+            ReplanTrigger::OnSizeChange { threshold } => write!(f, "OnSizeChange({})", threshold),
+            ReplanTrigger::OnDistributionChange { threshold } => write!(f, "OnDistributionChange({})", threshold),
+            ReplanTrigger::OnRankingChange { threshold } => write!(f, "OnRankingChange({})", threshold),
+            ReplanTrigger::Hybrid { size_threshold, distribution_threshold, ranking_threshold } => {
+                write!(f, "Hybrid({},{},{})", size_threshold, distribution_threshold, ranking_threshold)
+            }
+            ReplanTrigger::OnEstimateError { threshold, patience } => {
+                write!(f, "OnEstimateError({},{})", threshold, patience)
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum StreamType<'a> {
     RStream,
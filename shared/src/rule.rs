@@ -9,7 +9,8 @@
  */
 
 use crate::terms::{Term, TriplePattern};
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::str::FromStr;
 
 #[derive(Debug, Clone)]
 pub struct FilterCondition {
@@ -21,13 +22,233 @@ pub struct FilterCondition {
 #[derive(Debug, Clone)]
 pub struct Rule {
     pub premise: Vec<TriplePattern>,
-    /// Negated body atoms (NOT X) for single-stratum negation-as-failure.
+    /// Negated body atoms (NOT X) for stratified negation-as-failure: they are
+    /// evaluated once the strata deriving matching facts are complete.
     /// Every variable appearing here must also appear in `premise` (rule safety).
     pub negative_premise: Vec<TriplePattern>,
     pub filters: Vec<FilterCondition>,
+    /// Variables computed from each body solution, in order (`?total = ?a + ?b`).
+    /// Filters and conclusions can use them; a solution for which an
+    /// expression cannot be evaluated is dropped.
+    pub bindings: Vec<RuleBinding>,
+    /// Aggregates over the body solutions, grouped by the conclusion variables
+    /// the body binds. Filters on their results act as `HAVING` conditions.
+    /// Such rules are evaluated once the facts their premises match are
+    /// complete (stratified aggregation).
+    pub aggregates: Vec<RuleAggregate>,
     pub conclusion: Vec<TriplePattern>,
 }
 
+impl Rule {
+    /// Variables computed after aggregation: the aggregate results and the
+    /// bindings using them
+    pub fn post_aggregation_variables(&self) -> HashSet<&str> {
+        let mut computed: HashSet<&str> = self.aggregates.iter().map(|a| a.result.as_str()).collect();
+        for binding in &self.bindings {
+            if binding.expression.variables().iter().any(|v| computed.contains(v)) {
+                computed.insert(binding.variable.as_str());
+            }
+        }
+        computed
+    }
+
+    /// Conclusion variables that nothing in the body binds; each solution of
+    /// the body stands for some value of them, which the chase invents
+    pub fn existential_variables(&self) -> BTreeSet<&str> {
+        let bound: HashSet<&str> = self.premise.iter()
+            .flat_map(pattern_variables)
+            .chain(self.bindings.iter().map(|b| b.variable.as_str()))
+            .chain(self.aggregates.iter().map(|a| a.result.as_str()))
+            .collect();
+        self.conclusion.iter()
+            .flat_map(pattern_variables)
+            .filter(|var| !bound.contains(var))
+            .collect()
+    }
+}
+
+/// `?variable = expression`, computed from the bound variables of a rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleBinding {
+    pub variable: String,
+    pub expression: RuleExpression,
+}
+
+/// Arithmetic or string expression over the bound variables of a rule
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleExpression {
+    Variable(String),
+    /// Number or string, without quotes
+    Literal(String),
+    Add(Box<RuleExpression>, Box<RuleExpression>),
+    Subtract(Box<RuleExpression>, Box<RuleExpression>),
+    Multiply(Box<RuleExpression>, Box<RuleExpression>),
+    Divide(Box<RuleExpression>, Box<RuleExpression>),
+    /// Built-in function: `CONCAT`, `STR`, `UCASE`, `LCASE`, `STRLEN`, `ABS`,
+    /// `ROUND`, `FLOOR` or `CEIL`
+    Function(String, Vec<RuleExpression>),
+}
+
+impl RuleExpression {
+    pub fn variables(&self) -> Vec<&str> {
+        match self {
+            Self::Variable(v) => vec![v.as_str()],
+            Self::Literal(_) => vec![],
+            Self::Add(l, r) | Self::Subtract(l, r) | Self::Multiply(l, r) | Self::Divide(l, r) => {
+                let mut vars = l.variables();
+                vars.extend(r.variables());
+                vars
+            }
+            Self::Function(_, args) => args.iter().flat_map(|arg| arg.variables()).collect(),
+        }
+    }
+
+    /// Value of the expression, given the value of each variable (as stored in
+    /// the dictionary). `None` when a variable is unbound, an arithmetic
+    /// operand is not a number, a division is by zero or the function is
+    /// unknown.
+    pub fn evaluate<F: Fn(&str) -> Option<String>>(&self, resolve: &F) -> Option<String> {
+        let number = |expr: &RuleExpression| expr.evaluate(resolve).as_deref().and_then(numeric_value);
+        let arithmetic = |l: &RuleExpression, r: &RuleExpression, op: fn(f64, f64) -> Option<f64>| {
+            op(number(l)?, number(r)?).map(format_number)
+        };
+        match self {
+            Self::Variable(v) => resolve(v),
+            Self::Literal(value) => Some(value.clone()),
+            Self::Add(l, r) => arithmetic(l, r, |a, b| Some(a + b)),
+            Self::Subtract(l, r) => arithmetic(l, r, |a, b| Some(a - b)),
+            Self::Multiply(l, r) => arithmetic(l, r, |a, b| Some(a * b)),
+            Self::Divide(l, r) => arithmetic(l, r, |a, b| (b != 0.0).then(|| a / b)),
+            Self::Function(name, args) => {
+                let values = args
+                    .iter()
+                    .map(|arg| arg.evaluate(resolve).map(|value| lexical_form(&value).to_string()))
+                    .collect::<Option<Vec<String>>>()?;
+                let single = || if values.len() == 1 { Some(values[0].as_str()) } else { None };
+                let unary = |op: fn(f64) -> f64| single().and_then(numeric_value).map(|n| format_number(op(n)));
+                match name.to_uppercase().as_str() {
+                    "CONCAT" => Some(values.concat()),
+                    "STR" => single().map(str::to_string),
+                    "UCASE" => single().map(str::to_uppercase),
+                    "LCASE" => single().map(str::to_lowercase),
+                    "STRLEN" => single().map(|value| value.chars().count().to_string()),
+                    "ABS" => unary(f64::abs),
+                    "ROUND" => unary(f64::round),
+                    "FLOOR" => unary(f64::floor),
+                    "CEIL" => unary(f64::ceil),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Checks that every function of the expression is a built-in given as
+    /// many arguments as it takes
+    pub fn check_functions(&self) -> Result<(), String> {
+        match self {
+            Self::Variable(_) | Self::Literal(_) => Ok(()),
+            Self::Add(l, r) | Self::Subtract(l, r) | Self::Multiply(l, r) | Self::Divide(l, r) => {
+                l.check_functions()?;
+                r.check_functions()
+            }
+            Self::Function(name, args) => {
+                let arity = match name.to_uppercase().as_str() {
+                    "CONCAT" => None,
+                    "STR" | "UCASE" | "LCASE" | "STRLEN" | "ABS" | "ROUND" | "FLOOR" | "CEIL" => Some(1),
+                    _ if name.parse::<AggregateFunction>().is_ok() => {
+                        return Err(format!("aggregate {} takes a single variable, got {} arguments", name, args.len()));
+                    }
+                    _ => return Err(format!("unknown function '{}'", name)),
+                };
+                if args.is_empty() || arity.is_some_and(|arity| args.len() != arity) {
+                    let expected = arity.map_or("one or more".to_string(), |arity| arity.to_string());
+                    return Err(format!("function {} takes {} argument(s), got {}", name, expected, args.len()));
+                }
+                args.iter().try_for_each(Self::check_functions)
+            }
+        }
+    }
+}
+
+/// Aggregate function of a rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Min,
+    Max,
+    Avg,
+}
+
+impl FromStr for AggregateFunction {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name.to_uppercase().as_str() {
+            "COUNT" => Ok(Self::Count),
+            "SUM" => Ok(Self::Sum),
+            "MIN" => Ok(Self::Min),
+            "MAX" => Ok(Self::Max),
+            "AVG" => Ok(Self::Avg),
+            _ => Err(format!("unknown aggregate function '{}'", name)),
+        }
+    }
+}
+
+/// `?result = FUNCTION(?argument)` over the body solutions of a group. `COUNT`
+/// counts the solutions binding the argument; the other functions skip
+/// solutions whose argument is not a number, and groups without any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleAggregate {
+    pub function: AggregateFunction,
+    pub argument: String,
+    pub result: String,
+}
+
+impl RuleAggregate {
+    /// Aggregate of the argument values of a group, `None` when it has none
+    pub fn compute<'a>(&self, values: impl IntoIterator<Item = &'a str>) -> Option<String> {
+        if self.function == AggregateFunction::Count {
+            return Some(values.into_iter().count().to_string());
+        }
+        let numbers: Vec<f64> = values.into_iter().filter_map(numeric_value).collect();
+        if numbers.is_empty() {
+            return None;
+        }
+        let result = match self.function {
+            AggregateFunction::Sum => numbers.iter().sum(),
+            AggregateFunction::Min => numbers.iter().copied().fold(f64::INFINITY, f64::min),
+            AggregateFunction::Max => numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            AggregateFunction::Avg => numbers.iter().sum::<f64>() / numbers.len() as f64,
+            AggregateFunction::Count => unreachable!(),
+        };
+        Some(format_number(result))
+    }
+}
+
+/// Lexical form of a stored value: literals lose their quotes, language tag
+/// and datatype
+pub fn lexical_form(value: &str) -> &str {
+    match value.strip_prefix('"').and_then(|rest| rest.rfind('"').map(|end| &rest[..end])) {
+        Some(lexical) => lexical,
+        None => value,
+    }
+}
+
+/// Number a stored value denotes, if any
+pub fn numeric_value(value: &str) -> Option<f64> {
+    lexical_form(value).trim().parse().ok()
+}
+
+/// Integral values are written without a fractional part, so `2 + 3` is `5`
+pub fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        value.to_string()
+    }
+}
+
 /// Returns an iterator over the variable names bound by a triple pattern.
 pub fn pattern_variables(pat: &TriplePattern) -> impl Iterator<Item = &str> {
     let (s, p, o) = pat;
@@ -36,11 +257,14 @@ pub fn pattern_variables(pat: &TriplePattern) -> impl Iterator<Item = &str> {
     })
 }
 
-/// Check that every variable in `negative_premise` is bound by at least one positive premise.
+/// Check that every variable in `negative_premise` is bound by at least one positive premise,
+/// that computed variables only use bound variables and are not bound already, that
+/// aggregates range over variables of the body, and that existential variables (see
+/// [`Rule::existential_variables`]) are neither filtered nor combined with aggregates.
 ///
 /// Returns `Err` describing the first unsafe variable found.
 pub fn check_rule_safety(rule: &Rule) -> Result<(), String> {
-    let bound: HashSet<&str> = rule.premise.iter()
+    let mut bound: HashSet<&str> = rule.premise.iter()
         .flat_map(pattern_variables)
         .collect();
     for pat in &rule.negative_premise {
@@ -53,5 +277,164 @@ pub fn check_rule_safety(rule: &Rule) -> Result<(), String> {
             }
         }
     }
+
+    let post_aggregation = rule.post_aggregation_variables();
+    for binding in rule.bindings.iter().filter(|b| !post_aggregation.contains(b.variable.as_str())) {
+        bind_variable(&mut bound, binding)?;
+    }
+    for aggregate in &rule.aggregates {
+        if !bound.contains(aggregate.argument.as_str()) {
+            return Err(format!(
+                "unsafe aggregate: variable '{}' is not bound by the rule body",
+                aggregate.argument
+            ));
+        }
+    }
+    for aggregate in &rule.aggregates {
+        if !bound.insert(aggregate.result.as_str()) {
+            return Err(format!("aggregate result '{}' is already bound", aggregate.result));
+        }
+    }
+    for binding in rule.bindings.iter().filter(|b| post_aggregation.contains(b.variable.as_str())) {
+        bind_variable(&mut bound, binding)?;
+    }
+
+    let existential = rule.existential_variables();
+    if let (Some(var), false) = (existential.iter().next(), rule.aggregates.is_empty()) {
+        return Err(format!("existential variable '{}' cannot be combined with aggregates", var));
+    }
+    if let Some(filter) = rule.filters.iter().find(|f| existential.contains(f.variable.as_str())) {
+        return Err(format!(
+            "unsafe filter: existential variable '{}' has no value to compare",
+            filter.variable
+        ));
+    }
+    Ok(())
+}
+
+/// Checks every rule of a program with [`check_rule_safety`] and, unless the
+/// chase is bounded by a depth, that its rules with existential variables are
+/// weakly acyclic (see [`check_weak_acyclicity`]).
+pub fn check_program_safety(rules: &[Rule], depth_bounded: bool) -> Result<(), String> {
+    for rule in rules {
+        check_rule_safety(rule)?;
+    }
+    if !depth_bounded && rules.iter().any(|rule| !rule.existential_variables().is_empty()) {
+        check_weak_acyclicity(rules)?;
+    }
+    Ok(())
+}
+
+/// Place a value can occupy: the constants of a pattern (`None` for its
+/// variables) and the position within it
+type Position = ([Option<u32>; 3], usize);
+
+fn positions_of<'a>(var: &'a str, patterns: &'a [TriplePattern]) -> impl Iterator<Item = Position> + 'a {
+    patterns.iter().flat_map(move |(s, p, o)| {
+        let terms = [s, p, o];
+        let constants = terms.map(|term| match term {
+            Term::Constant(c) => Some(*c),
+            _ => None,
+        });
+        terms
+            .into_iter()
+            .enumerate()
+            .filter(move |(_, term)| matches!(term, Term::Variable(v) if v == var))
+            .map(move |(index, _)| (constants, index))
+    })
+}
+
+/// Whether a value derived in the conclusion position `from` can match the
+/// body position `to`
+fn flows_into(from: &Position, to: &Position) -> bool {
+    from.1 == to.1 && from.0.iter().zip(&to.0).all(|(a, b)| a.is_none() || b.is_none() || a == b)
+}
+
+/// Checks that the rules are weakly acyclic, which guarantees that the chase
+/// terminates.
+///
+/// The dependency graph has an edge from every position of a body variable
+/// to its positions in the conclusion, a special edge from there to the
+/// positions of the existential variables, and an edge from every conclusion
+/// position to the body positions its facts can match. Rules are weakly
+/// acyclic when no cycle goes through a special edge, i.e. no invented value
+/// can lead to inventing another one in the same place.
+pub fn check_weak_acyclicity(rules: &[Rule]) -> Result<(), String> {
+    let mut edges: HashMap<Position, HashSet<Position>> = HashMap::new();
+    let mut special = Vec::new();
+    let mut heads = HashSet::new();
+    let mut bodies = HashSet::new();
+    for rule in rules {
+        let existential = rule.existential_variables();
+        let body_variables: HashSet<&str> = rule.premise.iter().flat_map(pattern_variables).collect();
+        for var in rule.conclusion.iter().flat_map(pattern_variables) {
+            heads.extend(positions_of(var, &rule.conclusion));
+        }
+        for var in &body_variables {
+            bodies.extend(positions_of(var, &rule.premise));
+        }
+        let frontier: HashSet<&str> = rule.conclusion
+            .iter()
+            .flat_map(pattern_variables)
+            .filter(|var| body_variables.contains(var))
+            .collect();
+        for var in frontier {
+            for from in positions_of(var, &rule.premise) {
+                for to in positions_of(var, &rule.conclusion) {
+                    edges.entry(from).or_default().insert(to);
+                }
+                for &invented in &existential {
+                    for to in positions_of(invented, &rule.conclusion) {
+                        special.push((from, to));
+                        edges.entry(from).or_default().insert(to);
+                    }
+                }
+            }
+        }
+    }
+    for head in &heads {
+        for body in bodies.iter().filter(|body| flows_into(head, body)) {
+            edges.entry(*head).or_default().insert(*body);
+        }
+    }
+
+    let reaches = |start: Position, goal: Position| {
+        let mut seen = HashSet::from([start]);
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            if node == goal {
+                return true;
+            }
+            for &next in edges.get(&node).into_iter().flatten() {
+                if seen.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        false
+    };
+    match special.into_iter().find(|&(from, to)| reaches(to, from)) {
+        Some(_) => Err(
+            "rules are not weakly acyclic: an invented value can lead to inventing another, so the chase may not terminate without a depth bound"
+                .to_string(),
+        ),
+        None => Ok(()),
+    }
+}
+
+fn bind_variable<'a>(bound: &mut HashSet<&'a str>, binding: &'a RuleBinding) -> Result<(), String> {
+    binding
+        .expression
+        .check_functions()
+        .map_err(|err| format!("cannot compute '{}': {}", binding.variable, err))?;
+    if let Some(var) = binding.expression.variables().into_iter().find(|v| !bound.contains(v)) {
+        return Err(format!(
+            "unsafe binding: variable '{}' used to compute '{}' is not bound",
+            var, binding.variable
+        ));
+    }
+    if !bound.insert(binding.variable.as_str()) {
+        return Err(format!("computed variable '{}' is already bound", binding.variable));
+    }
     Ok(())
 }
\ No newline at end of file
@@ -22,10 +22,24 @@ pub enum Term {
 pub type TriplePattern = (Term, Term, Term);
 pub type Bindings = Vec<HashMap<String, u32>>;
 
-#[derive(Debug)]
+/// Term of an N3 document before prefixes are expanded and values encoded
+#[derive(Debug, Clone, PartialEq)]
 pub enum UnresolvedTerm {
     Var(String),
     Prefixed(String),
+    /// IRI without the angle brackets, possibly relative to the base IRI
+    Iri(String),
+    /// Literal as stored in the dictionary: its lexical form, followed by
+    /// `@lang` when it has a language tag
+    Literal(String),
+    /// Labelled blank node `_:label`
+    BlankNode(String),
+    /// Anonymous blank node `[ predicate object ; ... ]`
+    PropertyList(Vec<(UnresolvedTerm, UnresolvedTerm)>),
+    /// Collection `( term ... )`
+    List(Vec<UnresolvedTerm>),
+    /// Quoted formula `{ triples }`
+    Formula(Vec<UnresolvedTriple>),
 }
 
 pub type UnresolvedTriple = (UnresolvedTerm, UnresolvedTerm, UnresolvedTerm);